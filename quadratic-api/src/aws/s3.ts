@@ -21,7 +21,7 @@ export const s3Client = new S3Client({
   forcePathStyle: true,
 });
 
-export const uploadStringAsFileS3 = async (fileKey: string, contents: string) => {
+export const uploadStringAsFileS3 = async (fileKey: string, contents: string | Buffer) => {
   const command = new PutObjectCommand({
     Bucket: AWS_S3_BUCKET_NAME,
     Key: fileKey,
@@ -44,8 +44,8 @@ async function handler(req: RequestWithUser, res: Response<ApiTypes['/v0/example
       file: { name, lastCheckpointDataUrl, lastCheckpointVersion },
     } = (await fetch(apiUrl).then((res) => res.json())) as ApiTypes['/v0/files/:uuid.GET.response'];
 
-    // Fetch the contents of the file
-    const fileContents = await fetch(lastCheckpointDataUrl).then((res) => res.text());
+    // Fetch the contents of the file, which can be binary
+    const fileContents = Buffer.from(await fetch(lastCheckpointDataUrl).then((res) => res.arrayBuffer()));
 
     // Create a private file for the user in the requested team
     const dbFile = await createFile({
@@ -73,6 +73,11 @@ describe('POST /v0/files', () => {
       await createFile({ contents, version }).expect(400).expect(expectError);
       await createFile({ name, contents, version }).expect(400).expect(expectError);
     });
+    it('rejects request with an unknown contents encoding', async () => {
+      await createFile({ ...validPayload, contentsEncoding: 'hex' })
+        .expect(400)
+        .expect(expectError);
+    });
     it('rejects request with a team that doesn’t exist', async () => {
       await createFile({ ...validPayload, teamUuid: 'invalid_uuid' })
         .expect(404)
@@ -111,6 +116,16 @@ describe('POST /v0/files', () => {
         });
     });
 
+    it('creates a file with binary contents', async () => {
+      await createFile({
+        ...validPayload,
+        contents: Buffer.from('\0QUADRATIC').toString('base64'),
+        contentsEncoding: 'base64',
+      })
+        .expect(201)
+        .expect(expectValidResponse);
+    });
+
     it('creates a private file', async () => {
       const createResponse = await createFile({ ...validPayload, isPrivate: true })
         .expect(201)
@@ -9,7 +9,7 @@ export async function createFile({
   teamId,
   isPrivate,
 }: {
-  contents: string;
+  contents: string | Buffer;
   name: string;
   userId: number;
   version: string;
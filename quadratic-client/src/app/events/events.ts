@@ -19,6 +19,7 @@ import { CellEdit, MultiplayerUser } from '@/app/web-workers/multiplayerWebWorke
 import {
   CoreClientImage,
   CoreClientImportProgress,
+  CoreClientImportWarnings,
   CoreClientTransactionProgress,
   CoreClientTransactionStart,
 } from '@/app/web-workers/quadraticCore/coreClientMessages';
@@ -34,6 +35,7 @@ interface EventTypes {
   panMode: (pan: PanMode) => void;
 
   undoRedo: (undo: boolean, redo: boolean) => void;
+  protectionError: (error: string) => void;
 
   addSheet: (sheetInfo: SheetInfo, user: boolean) => void;
   deleteSheet: (sheetId: string, user: boolean) => void;
@@ -73,6 +75,7 @@ interface EventTypes {
   updateImage: (message: CoreClientImage) => void;
 
   importProgress: (message: CoreClientImportProgress) => void;
+  importWarnings: (message: CoreClientImportWarnings) => void;
   transactionStart: (message: CoreClientTransactionStart) => void;
   transactionProgress: (message: CoreClientTransactionProgress) => void;
 
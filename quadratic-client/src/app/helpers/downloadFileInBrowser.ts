@@ -1,8 +1,9 @@
-export function downloadQuadraticFile(filename: string, data: string) {
-  downloadFile(filename, data, 'application/json', 'grid');
+export function downloadQuadraticFile(filename: string, data: string | ArrayBuffer) {
+  const mimeType = typeof data === 'string' ? 'application/json' : 'application/octet-stream';
+  downloadFile(filename, data, mimeType, 'grid');
 }
 
-export function downloadFile(filename: string, data: string, mime_type: string, extension: string) {
+export function downloadFile(filename: string, data: string | ArrayBuffer, mime_type: string, extension: string) {
   const blob = new Blob([data], { type: mime_type });
   //@ts-expect-error
   if (window.navigator.msSaveOrOpenBlob) {
@@ -39,10 +39,19 @@ export function isCsv(file: File): boolean {
   return file.type === 'text/csv' || file.type === 'text/tab-separated-values' || hasExtension(file.name, 'csv');
 }
 
+export const EXCEL_EXTENSIONS = ['xlsx', 'xlsm', 'xlsb', 'xls', 'ods'];
+
+const EXCEL_MIME_TYPES = [
+  'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet',
+  'application/vnd.ms-excel.sheet.macroEnabled.12',
+  'application/vnd.ms-excel.sheet.binary.macroEnabled.12',
+  'application/vnd.ms-excel',
+  'application/vnd.oasis.opendocument.spreadsheet',
+];
+
+// spreadsheets that are imported by `importExcel` (Excel and OpenDocument)
 export function isExcel(file: File): boolean {
-  return (
-    file.type === 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet' || hasExtension(file.name, 'xlsx')
-  );
+  return EXCEL_MIME_TYPES.includes(file.type) || hasExtensions(file.name, EXCEL_EXTENSIONS);
 }
 
 export function isGrid(file: File): boolean {
@@ -16,7 +16,7 @@ export type GridBounds = { "type": "empty" } | { "type": "nonEmpty" } & Rect;
 export type CellAlign = "center" | "left" | "right";
 export type CellWrap = "overflow" | "wrap" | "clip";
 export interface NumericFormat { type: NumericFormatKind, symbol: string | null, }
-export type NumericFormatKind = "NUMBER" | "CURRENCY" | "PERCENTAGE" | "EXPONENTIAL";
+export type NumericFormatKind = "NUMBER" | "CURRENCY" | "PERCENTAGE" | "EXPONENTIAL" | "CUSTOM" | "DATE" | "TIME" | "DATETIME";
 export interface SheetId { id: string, }
 export interface JsRenderCell { x: bigint, y: bigint, value: string, language?: CodeCellLanguage, align?: CellAlign, wrap?: CellWrap, bold?: boolean, italic?: boolean, textColor?: string, special: JsRenderCellSpecial | null, }
 export interface JsRenderFill { x: bigint, y: bigint, w: number, h: number, color: string, }
@@ -37,7 +37,8 @@ export interface SheetRect { min: Pos, max: Pos, sheet_id: SheetId, }
 export interface Selection { sheet_id: SheetId, x: bigint, y: bigint, rects: Array<Rect> | null, rows: Array<bigint> | null, columns: Array<bigint> | null, all: boolean, }
 export interface Placement { index: number, position: number, size: number, }
 export interface ColumnRow { column: number, row: number, }
-export interface SheetInfo { sheet_id: string, name: string, order: string, color: string | null, offsets: string, bounds: GridBounds, bounds_without_formatting: GridBounds, }
+export interface SheetInfo { sheet_id: string, name: string, order: string, color: string | null, offsets: string, bounds: GridBounds, bounds_without_formatting: GridBounds, protections: Array<JsProtection>, }
+export interface JsProtection { id: string, rect: Rect | null, owner: string, editors: Array<string>, can_edit: boolean, }
 export type PasteSpecial = "None" | "Values" | "Formats";
 export interface Rgba { red: number, green: number, blue: number, alpha: number, }
 export type CellBorderLine = "line1" | "line2" | "line3" | "dotted" | "dashed" | "double";
@@ -49,9 +50,16 @@ export interface JsCodeResult { transaction_id: string, success: boolean, std_ou
 export interface MinMax { min: number, max: number, }
 export interface TransientResize { row: bigint | null, column: bigint | null, old_size: number, new_size: number, }
 export interface SheetBounds { sheet_id: string, bounds: GridBounds, bounds_without_formatting: GridBounds, }
-export type TransactionName = "Unknown" | "ResizeColumn" | "ResizeRow" | "Autocomplete" | "SetBorders" | "SetCells" | "SetFormats" | "CutClipboard" | "PasteClipboard" | "SetCode" | "RunCode" | "Import" | "SetSheetMetadata" | "SheetAdd" | "SheetDelete" | "DuplicateSheet" | "MoveCells";
+export type TransactionName = "Unknown" | "ResizeColumn" | "ResizeRow" | "Autocomplete" | "SetBorders" | "SetCells" | "SetFormats" | "CutClipboard" | "PasteClipboard" | "SetCode" | "RunCode" | "Import" | "SetSheetMetadata" | "SheetAdd" | "SheetDelete" | "DuplicateSheet" | "MoveCells" | "Merge" | "SetProtection";
 export interface JsGetCellResponse { x: bigint, y: bigint, value: string, type_name: string, }
 export interface SummarizeSelectionResult { count: bigint, sum: number | null, average: number | null, }
 export interface Format { align: CellAlign | null, wrap: CellWrap | null, numeric_format: NumericFormat | null, numeric_decimals: number | null, numeric_commas: boolean | null, bold: boolean | null, italic: boolean | null, text_color: string | null, fill_color: string | null, render_size: RenderSize | null, }
 export interface JsSheetFill { columns: Array<[bigint, [string, bigint]]>, rows: Array<[bigint, [string, bigint]]>, all: string | null, }
 export interface ColumnRow { column: number, row: number, }
+export type ExportCompression = "None" | "Snappy" | "Gzip" | "Lz4";
+export interface ColumnarExportOptions { header_row: boolean, compression: ExportCompression, }
+export type CsvEncoding = "Auto" | "Utf8" | "Utf16" | "Latin1" | "Windows1252";
+export interface CsvImportOptions { delimiter: string | null, quote: string, header_row: boolean, skip_rows: number, encoding: CsvEncoding, infer_types: boolean, text_columns: Array<number>, }
+export interface ImportLimits { max_bytes: bigint, max_rows: bigint, }
+export interface ImportWarning { sheet_name: string | null, kind: ImportWarningKind, detail: string | null, count: number, }
+export type ImportWarningKind = "MergedCells" | "ConditionalFormatting" | "DataValidation" | "Drawings" | "HiddenRowsOrColumns" | "UnsupportedNumberFormat" | "UnsupportedBorderStyle" | "UnsupportedFill" | "UnsupportedColor" | "MetadataUnreadable" | "UnsupportedFunction" | "UnknownFunction" | "UnsupportedFormula" | "FormulaParseError";
@@ -1,28 +1,28 @@
 import { events } from '@/app/events/events';
-import {
-  CoreClientImportProgress,
-  CoreClientTransactionProgress,
-  CoreClientTransactionStart,
-} from '@/app/web-workers/quadraticCore/coreClientMessages';
+import { CoreClientImportProgress } from '@/app/web-workers/quadraticCore/coreClientMessages';
+import { quadraticCore } from '@/app/web-workers/quadraticCore/quadraticCore';
+import { Button } from '@/shared/shadcn/ui/button';
 import { Progress } from '@/shared/shadcn/ui/progress';
 import { useEffect, useState } from 'react';
 
-// The last message.total + 1 is used to track the execute operation progress.
+// Imports are applied in batches as they are read, so the import progress
+// also tracks the execute operation progress. Progress is the number of bytes
+// of the file that have been read. A cancelled import is rolled back.
 
 export const ImportProgress = () => {
   const [filename, setFilename] = useState<string | undefined>(undefined);
   const [percentage, setPercentage] = useState<number | undefined>(undefined);
-  const [total, setTotal] = useState<number | undefined>(undefined);
   const [show, setShow] = useState(false);
 
-  // Used to track the import operation-creation progress.
   useEffect(() => {
     const handleProgress = (message: CoreClientImportProgress) => {
       setFilename(message.filename);
-      const progress = (message.current / (message.total * 2)) * 100;
-      setTotal(message.total * 2);
-      setPercentage(progress);
+      setPercentage((message.current / message.total) * 100);
       setShow(true);
+      if (message.current >= message.total) {
+        // allow the bar to complete before removing it
+        setTimeout(() => setShow(false), 250);
+      }
     };
     events.on('importProgress', handleProgress);
     return () => {
@@ -30,39 +30,6 @@ export const ImportProgress = () => {
     };
   }, []);
 
-  // Used to track the execute operation for the import
-  useEffect(() => {
-    if (!total) return;
-    let transactionId: string | undefined;
-    let maxOperations = 0;
-    const transactionStart = (message: CoreClientTransactionStart) => {
-      if (message.transactionType === 'Import') {
-        transactionId = message.transactionId;
-      }
-    };
-    const transactionProgress = (message: CoreClientTransactionProgress) => {
-      maxOperations = Math.max(maxOperations, message.remainingOperations);
-      if (message.transactionId === transactionId) {
-        setPercentage((percentage) => {
-          if (!percentage) return percentage;
-          const operationPercentage = (1 - message.remainingOperations / maxOperations) * 50;
-          const final = 50 + operationPercentage;
-          if (final >= 100) {
-            // allow the bar to complete before removing it
-            setTimeout(() => setShow(false), 250);
-          }
-          return final;
-        });
-      }
-    };
-    events.on('transactionStart', transactionStart);
-    events.on('transactionProgress', transactionProgress);
-    return () => {
-      events.off('transactionStart', transactionStart);
-      events.off('transactionProgress', transactionProgress);
-    };
-  }, [total]);
-
   if (!show) return;
 
   return (
@@ -76,11 +43,15 @@ export const ImportProgress = () => {
         border: '1px solid black',
         padding: '1rem',
         minWidth: '300px',
-        pointerEvents: 'none',
       }}
     >
       <div style={{ marginBottom: '1rem' }}>Importing {filename}...</div>
       <Progress value={percentage} />
+      <div style={{ marginTop: '1rem', display: 'flex', justifyContent: 'flex-end' }}>
+        <Button variant="outline" size="sm" onClick={() => quadraticCore.cancelImport()}>
+          Cancel
+        </Button>
+      </div>
     </div>
   );
 };
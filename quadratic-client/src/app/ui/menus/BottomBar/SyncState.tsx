@@ -101,9 +101,15 @@ export default function SyncState() {
     };
     events.on('offlineTransactionsApplied', offlineTransactionsApplied);
 
+    const protectionError = (error: string) => {
+      addGlobalSnackbar(error, { severity: 'warning' });
+    };
+    events.on('protectionError', protectionError);
+
     return () => {
       events.off('offlineTransactions', updateUnsavedTransactions);
       events.off('offlineTransactionsApplied', offlineTransactionsApplied);
+      events.off('protectionError', protectionError);
     };
   }, [addGlobalSnackbar]);
 
@@ -13,6 +13,15 @@ export interface CoreMultiplayerTransaction {
 
 export interface MultiplayerCoreReceiveTransactions {
   type: 'multiplayerCoreReceiveTransactions';
+  transactions: string | Uint8Array;
+}
+
+// a checkpoint of the file and the transactions after it, for clients that
+// are too far behind to catch up with transactions alone
+export interface MultiplayerCoreLoadCheckpoint {
+  type: 'multiplayerCoreLoadCheckpoint';
+  file: Uint8Array;
+  sequenceNum: number;
   transactions: string;
 }
 
@@ -26,15 +35,30 @@ export interface MultiplayerCoreReceiveCurrentTransaction {
   sequenceNum: number;
 }
 
+export interface MultiplayerCoreRejectTransaction {
+  type: 'multiplayerCoreRejectTransaction';
+  transactionId: string;
+}
+
 export interface CoreMultiplayerRequestTransactions {
   type: 'coreMultiplayerRequestTransactions';
   sequenceNum: number;
 }
 
+// core was unable to load a checkpoint, so the file is reloaded
+export interface CoreMultiplayerReload {
+  type: 'coreMultiplayerReload';
+}
+
 export type MultiplayerCoreMessage =
   | MultiplayerCoreSequenceNum
   | MultiplayerCoreReceiveTransactions
+  | MultiplayerCoreLoadCheckpoint
   | MultiplayerCoreReceiveTransaction
-  | MultiplayerCoreReceiveCurrentTransaction;
+  | MultiplayerCoreReceiveCurrentTransaction
+  | MultiplayerCoreRejectTransaction;
 
-export type CoreMultiplayerMessage = CoreMultiplayerTransaction | CoreMultiplayerRequestTransactions;
+export type CoreMultiplayerMessage =
+  | CoreMultiplayerTransaction
+  | CoreMultiplayerRequestTransactions
+  | CoreMultiplayerReload;
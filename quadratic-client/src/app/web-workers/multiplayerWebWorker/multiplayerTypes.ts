@@ -42,6 +42,7 @@ export interface MultiplayerUser extends MultiplayerUserServer {
 export interface Version {
   recommendedVersion: number;
   requiredVersion: number;
+  protocolVersion?: number;
 }
 
 export interface ReceiveRoom {
@@ -71,12 +72,14 @@ export interface MessageUserUpdate {
 
 export interface SendEnterRoom extends MultiplayerUserServer {
   type: 'EnterRoom';
+  protocol_version?: number;
 }
 
 export interface ReceiveEnterRoom {
   type: 'EnterRoom';
   file_id: string;
   sequence_num: number;
+  protocol_version?: number;
 }
 
 export interface Transaction {
@@ -86,11 +89,12 @@ export interface Transaction {
   operations: string[];
 }
 
+// operations are compressed bytes when using the binary protocol
 export interface ReceiveTransaction {
   type: 'Transaction';
   id: string;
   file_id: string;
-  operations: string;
+  operations: string | Uint8Array;
   sequence_num: number;
 }
 
@@ -107,11 +111,34 @@ export interface SendGetTransactions {
   session_id: string;
   file_id: string;
   min_sequence_num: number;
+
+  // the server may respond with CatchUp instead of Transactions
+  catch_up?: boolean;
 }
 
+// transactions are compressed bytes when using the binary protocol
 export interface ReceiveTransactions {
   type: 'Transactions';
+  transactions: string | Uint8Array;
+}
+
+export interface CatchUpCheckpoint {
+  sequence_num: number;
+  version: string;
+  key: string;
+
+  // signed url to download the checkpoint; if not set, the file is reloaded
+  url?: string;
+}
+
+// a page of transactions for a client that fell far behind; if checkpoint is
+// set, the transactions before it are no longer available
+export interface ReceiveCatchUp {
+  type: 'CatchUp';
+  file_id: string;
+  checkpoint?: CatchUpCheckpoint;
   transactions: string;
+  next_sequence_num?: number;
 }
 
 export interface Heartbeat {
@@ -120,6 +147,73 @@ export interface Heartbeat {
   file_id: string;
 }
 
+export type EditHistoryQuery =
+  | { type: 'Cell'; sheet_pos: { x: number; y: number; sheet_id: { id: string } }; limit: number }
+  | { type: 'User'; user_id: string; from: string; to: string };
+
+export interface SendGetEditHistory {
+  type: 'GetEditHistory';
+  session_id: string;
+  file_id: string;
+  query: EditHistoryQuery;
+}
+
+// the areas are [sheet_id, rect], where rect is null for changes to the sheet itself
+export interface EditRecord {
+  transaction_id: string;
+  sequence_num: number;
+  user_id: string;
+  session_id: string;
+  timestamp: string;
+  areas: [{ id: string }, { min: Coordinate; max: Coordinate } | null][];
+}
+
+export interface ReceiveEditHistory {
+  type: 'EditHistory';
+  file_id: string;
+  records: EditRecord[];
+}
+
+export interface SendChatMessage {
+  type: 'ChatMessage';
+  id: string;
+  session_id: string;
+  file_id: string;
+  text: string;
+  mentions: string[];
+  anchor: { x: number; y: number; sheet_id: { id: string } } | null;
+}
+
+export interface SendGetChatMessages {
+  type: 'GetChatMessages';
+  session_id: string;
+  file_id: string;
+}
+
+export interface ChatMessage {
+  id: string;
+  user_id: string;
+  session_id: string;
+  first_name: string;
+  last_name: string;
+  text: string;
+  mentions: string[];
+  anchor: { x: number; y: number; sheet_id: { id: string } } | null;
+  timestamp: string;
+}
+
+export interface ReceiveChatMessage {
+  type: 'ChatMessage';
+  file_id: string;
+  message: ChatMessage;
+}
+
+export interface ReceiveChatMessages {
+  type: 'ChatMessages';
+  file_id: string;
+  messages: ChatMessage[];
+}
+
 export interface ReceiveEmpty {
   type: 'Empty';
 }
@@ -141,8 +235,18 @@ export type ReceiveMessages =
   | ReceiveTransaction
   | ReceiveEmpty
   | ReceiveTransactions
+  | ReceiveCatchUp
   | ReceiveEnterRoom
   | ReceiveError
-  | ReceiveCurrentTransaction;
-
-export type MultiplayerServerMessage = SendTransaction | SendEnterRoom | SendGetTransactions;
+  | ReceiveCurrentTransaction
+  | ReceiveEditHistory
+  | ReceiveChatMessage
+  | ReceiveChatMessages;
+
+export type MultiplayerServerMessage =
+  | SendTransaction
+  | SendEnterRoom
+  | SendGetTransactions
+  | SendGetEditHistory
+  | SendChatMessage
+  | SendGetChatMessages;
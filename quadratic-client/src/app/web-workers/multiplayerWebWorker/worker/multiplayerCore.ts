@@ -5,6 +5,7 @@
 import { debugWebWorkersMessages } from '@/app/debugFlags';
 import { CoreMultiplayerMessage, MultiplayerCoreMessage } from '../multiplayerCoreMessages';
 import { ReceiveTransaction } from '../multiplayerTypes';
+import { multiplayerClient } from './multiplayerClient';
 import { multiplayerServer } from './multiplayerServer';
 
 class MultiplayerCore {
@@ -32,6 +33,10 @@ class MultiplayerCore {
         multiplayerServer.requestTransactions(e.data.sequenceNum);
         break;
 
+      case 'coreMultiplayerReload':
+        multiplayerClient.reload();
+        break;
+
       default:
         console.warn('[multiplayerCore] Unhandled message type', e.data);
     }
@@ -51,7 +56,23 @@ class MultiplayerCore {
     });
   }
 
-  receiveTransactions(transactions: string) {
+  rejectTransaction(transactionId: string) {
+    this.send({
+      type: 'multiplayerCoreRejectTransaction',
+      transactionId,
+    });
+  }
+
+  loadCheckpoint(file: Uint8Array, sequenceNum: number, transactions: string) {
+    this.send({
+      type: 'multiplayerCoreLoadCheckpoint',
+      file,
+      sequenceNum,
+      transactions,
+    });
+  }
+
+  receiveTransactions(transactions: string | Uint8Array) {
     this.send({
       type: 'multiplayerCoreReceiveTransactions',
       transactions,
@@ -9,6 +9,7 @@ import { debugShow, debugShowMultiplayer } from '../../../debugFlags';
 import { ClientMultiplayerInit, MultiplayerState } from '../multiplayerClientMessages';
 import { CoreMultiplayerTransaction } from '../multiplayerCoreMessages';
 import {
+  CatchUpCheckpoint,
   CellEdit,
   Heartbeat,
   MessageUserUpdate,
@@ -122,6 +123,7 @@ export class MultiplayerServer {
     }
 
     this.websocket = new WebSocket(import.meta.env.VITE_QUADRATIC_MULTIPLAYER_URL);
+    this.websocket.binaryType = 'arraybuffer';
     this.websocket.addEventListener('message', this.handleMessage);
 
     this.websocket.addEventListener('close', () => {
@@ -175,6 +177,7 @@ export class MultiplayerServer {
       viewport: this.userData.viewport,
       code_running: this.userData.codeRunning,
       follow: this.userData.follow,
+      protocol_version: this.updateAlertVersion.protocolVersion,
     };
     this.send(enterRoom);
     if (debugShowMultiplayer) console.log(`[Multiplayer] Joined room ${this.fileId}.`);
@@ -228,7 +231,32 @@ export class MultiplayerServer {
    * Receive Messages from Multiplayer Server *
    ********************************************/
 
-  private handleMessage = (e: MessageEvent<string>) => {
+  // Binary messages are the length of a JSON header, the header, and the
+  // compressed operations or transactions, which are decoded by core.
+  private handleBinaryMessage(buffer: ArrayBuffer) {
+    const headerLength = new DataView(buffer).getUint32(0);
+    const header = JSON.parse(new TextDecoder().decode(new Uint8Array(buffer, 4, headerLength)));
+    const body = new Uint8Array(buffer, 4 + headerLength);
+    switch (header.type) {
+      case 'Transaction':
+        multiplayerCore.receiveTransaction({ ...header, operations: body });
+        break;
+
+      case 'Transactions':
+        multiplayerCore.receiveTransactions(body);
+        break;
+
+      default:
+        console.warn(`Unknown binary message type: ${header.type}`);
+    }
+  }
+
+  private handleMessage = (e: MessageEvent<string | ArrayBuffer>) => {
+    if (e.data instanceof ArrayBuffer) {
+      this.handleBinaryMessage(e.data);
+      return;
+    }
+
     const data: ReceiveMessages = JSON.parse(e.data);
     switch (data.type) {
       case 'UsersInRoom':
@@ -247,6 +275,22 @@ export class MultiplayerServer {
         multiplayerCore.receiveTransactions(data.transactions);
         break;
 
+      case 'CatchUp':
+        if (data.file_id !== this.fileId) throw new Error('Expected file_id to match in CatchUp');
+
+        // the transactions that we need were truncated, so load the latest
+        // checkpoint and catch up from there
+        if (data.checkpoint) {
+          console.warn(`[Multiplayer] Warn: Catching up from checkpoint ${data.checkpoint.sequence_num}`);
+          this.catchUpFromCheckpoint(data.checkpoint, data.transactions, data.next_sequence_num);
+          break;
+        }
+        multiplayerCore.receiveTransactions(data.transactions);
+        if (data.next_sequence_num != null) {
+          this.requestTransactions(data.next_sequence_num);
+        }
+        break;
+
       case 'EnterRoom':
         if (data.file_id !== this.fileId) throw new Error('Expected file_id to match in EnterRoom');
         multiplayerCore.receiveCurrentTransaction(data.sequence_num);
@@ -257,6 +301,14 @@ export class MultiplayerServer {
         break;
 
       case 'Error':
+        // The server rejected one of our transactions, so it needs to be rolled back
+        if (typeof data.error != 'string' && 'InvalidTransaction' in data.error) {
+          const [transactionId, reason] = data.error.InvalidTransaction;
+          console.warn(`[Multiplayer] Transaction ${transactionId} was rejected: ${reason}`);
+          multiplayerCore.rejectTransaction(transactionId);
+          break;
+        }
+
         if (data.error_level === 'Error') {
           // If the server is missing transactions, reload the page
           if (typeof data.error != 'string' && 'MissingTransactions' in data.error) {
@@ -309,6 +361,33 @@ export class MultiplayerServer {
     this.send(message);
   }
 
+  // Downloads the checkpoint and sends it to core with the transactions after
+  // it. The file is reloaded if the checkpoint cannot be downloaded.
+  private async catchUpFromCheckpoint(
+    checkpoint: CatchUpCheckpoint,
+    transactions: string,
+    nextSequenceNum?: number
+  ) {
+    if (!checkpoint.url) {
+      multiplayerClient.reload();
+      return;
+    }
+    try {
+      const res = await fetch(checkpoint.url);
+      if (!res.ok) throw new Error(`Unable to download checkpoint ${checkpoint.key}: ${res.status}`);
+      const file = new Uint8Array(await res.arrayBuffer());
+      multiplayerCore.loadCheckpoint(file, checkpoint.sequence_num, transactions);
+    } catch (e) {
+      console.error('[Multiplayer] Error downloading checkpoint', e);
+      Sentry.captureException(e);
+      multiplayerClient.reload();
+      return;
+    }
+    if (nextSequenceNum != null) {
+      this.requestTransactions(nextSequenceNum);
+    }
+  }
+
   requestTransactions(sequenceNum: number) {
     if (!this.sessionId) throw new Error('Expected sessionId to be defined in requestTransactions');
     if (!this.fileId) throw new Error('Expected fileId to be defined in requestTransactions');
@@ -318,6 +397,7 @@ export class MultiplayerServer {
       session_id: this.sessionId,
       file_id: this.fileId,
       min_sequence_num: sequenceNum,
+      catch_up: true,
     };
     this.send(message);
   }
@@ -3,6 +3,8 @@ import {
   CellFormatSummary,
   CodeCellLanguage,
   Format,
+  ImportLimits,
+  ImportWarning,
   JsCodeCell,
   JsHtmlOutput,
   JsRenderBorders,
@@ -32,6 +34,12 @@ export interface ClientCoreLoad {
   sequenceNumber: number;
   id: number;
   fileId: string;
+
+  // the signed in user, for checking protected ranges and sheets
+  userId?: string;
+
+  // the name of the sheet in the url, which is loaded before the others
+  sheetName?: string;
 }
 
 export interface CoreClientLoad {
@@ -760,6 +768,12 @@ export interface CoreClientImportProgress {
   height: number;
 }
 
+export interface CoreClientImportWarnings {
+  type: 'coreClientImportWarnings';
+  filename: string;
+  warnings: ImportWarning[];
+}
+
 export interface CoreClientTransactionStart {
   type: 'coreClientTransactionStart';
   transactionId: string;
@@ -795,6 +809,15 @@ export interface CoreClientImportExcel {
   error?: string;
 }
 
+export interface ClientCoreCancelImport {
+  type: 'clientCoreCancelImport';
+}
+
+export interface ClientCoreSetImportLimits {
+  type: 'clientCoreSetImportLimits';
+  limits: ImportLimits;
+}
+
 export interface ClientCoreCancelExecution {
   type: 'clientCoreCancelExecution';
   language: CodeCellLanguage;
@@ -817,6 +840,11 @@ export interface CoreClientUndoRedo {
   redo: boolean;
 }
 
+export interface CoreClientProtectionError {
+  type: 'coreClientProtectionError';
+  error: string;
+}
+
 export interface ClientCoreMoveCells {
   type: 'clientCoreMoveCells';
   source: SheetRect;
@@ -900,6 +928,8 @@ export type ClientCoreMessage =
   | ClientCoreInitJavascript
   | ClientCoreImportExcel
   | ClientCoreCancelExecution
+  | ClientCoreCancelImport
+  | ClientCoreSetImportLimits
   | ClientCoreGetJwt
   | ClientCoreMoveCells
   | ClientCoreMoveCells
@@ -944,6 +974,7 @@ export type CoreClientMessage =
   | CoreClientSheetCodeCellRender
   | CoreClientSheetBoundsUpdate
   | CoreClientImportProgress
+  | CoreClientImportWarnings
   | CoreClientTransactionStart
   | CoreClientTransactionProgress
   | CoreClientUpdateCodeCell
@@ -952,6 +983,7 @@ export type CoreClientMessage =
   | CoreClientConnectionState
   | CoreClientOfflineTransactions
   | CoreClientUndoRedo
+  | CoreClientProtectionError
   | CoreClientGetJwt
   | CoreClientImage
   | CoreClientGetFormatAll
@@ -15,6 +15,7 @@ import {
   CellFormatSummary,
   CodeCellLanguage,
   Format,
+  ImportLimits,
   JsCodeCell,
   JsRenderCell,
   MinMax,
@@ -120,6 +121,9 @@ class QuadraticCore {
     } else if (e.data.type === 'coreClientImportProgress') {
       events.emit('importProgress', e.data);
       return;
+    } else if (e.data.type === 'coreClientImportWarnings') {
+      events.emit('importWarnings', e.data);
+      return;
     } else if (e.data.type === 'coreClientTransactionStart') {
       events.emit('transactionStart', e.data);
       return;
@@ -150,6 +154,9 @@ class QuadraticCore {
     } else if (e.data.type === 'coreClientUndoRedo') {
       events.emit('undoRedo', e.data.undo, e.data.redo);
       return;
+    } else if (e.data.type === 'coreClientProtectionError') {
+      events.emit('protectionError', e.data.error);
+      return;
     } else if (e.data.type === 'coreClientGetJwt') {
       const jwt = await authClient.getTokenOrRedirect();
       const data = e.data as CoreClientGetJwt;
@@ -197,7 +204,12 @@ class QuadraticCore {
   }
 
   // Loads a Grid file and initializes renderWebWorker upon response
-  async load(url: string, version: string, sequenceNumber: number): Promise<{ version?: string; error?: string }> {
+  async load(
+    url: string,
+    version: string,
+    sequenceNumber: number,
+    userId?: string
+  ): Promise<{ version?: string; error?: string }> {
     // this is the channel between the core worker and the render worker
     const port = new MessageChannel();
     renderWebWorker.init(port.port2);
@@ -215,6 +227,7 @@ class QuadraticCore {
           throw new Error('Expected CoreClientLoad to include either version or error');
         }
       };
+      const sheetName = new URLSearchParams(window.location.search).get('sheet');
       // load the file and send the render message port to
       const message: ClientCoreLoad = {
         type: 'clientCoreLoad',
@@ -223,6 +236,8 @@ class QuadraticCore {
         sequenceNumber,
         id,
         fileId: window.location.pathname.split('/')[2],
+        userId,
+        sheetName: sheetName ? decodeURI(sheetName) : undefined,
       };
       if (debugShowFileIO) console.log(`[quadraticCore] loading file ${url}`);
       this.send(message, port.port1);
@@ -488,6 +503,15 @@ class QuadraticCore {
     });
   }
 
+  // Cancels the CSV or Parquet import in progress, which is rolled back
+  cancelImport() {
+    this.send({ type: 'clientCoreCancelImport' });
+  }
+
+  setImportLimits(limits: ImportLimits) {
+    this.send({ type: 'clientCoreSetImportLimits', limits });
+  }
+
   initMultiplayer(port: MessagePort) {
     this.send({ type: 'clientCoreInitMultiplayer' }, port);
   }
@@ -943,7 +967,7 @@ class QuadraticCore {
     this.send({ type: 'clientCoreCancelExecution', language });
   }
 
-  // create a new grid file and import an xlsx file
+  // create a new grid file and import a spreadsheet (xlsx, xlsb, xls, or ods)
   importExcel = async (
     file: File
   ): Promise<{
@@ -12,6 +12,7 @@ import {
   CellFormatSummary,
   CodeCellLanguage,
   Format,
+  ImportLimits,
   JsCodeCell,
   JsCodeResult,
   JsRenderCell,
@@ -21,7 +22,7 @@ import {
   SheetPos,
   SummarizeSelectionResult,
 } from '@/app/quadratic-core-types';
-import initCore, { GridController } from '@/app/quadratic-core/quadratic_core';
+import initCore, { GridController, ImportJob } from '@/app/quadratic-core/quadratic_core';
 import { MultiplayerCoreReceiveTransaction } from '@/app/web-workers/multiplayerWebWorker/multiplayerCoreMessages';
 import * as Sentry from '@sentry/react';
 import {
@@ -33,6 +34,7 @@ import {
   ClientCoreSummarizeSelection,
 } from '../coreClientMessages';
 import { coreClient } from './coreClient';
+import { coreMultiplayer } from './coreMultiplayer';
 import { coreRender } from './coreRender';
 import { offline } from './offline';
 import { numbersToRect, pointsToRect, posToPos, posToRect } from './rustConversions';
@@ -50,9 +52,12 @@ class Core {
   private clientQueue: Function[] = [];
   private renderQueue: Function[] = [];
 
-  private async loadGridFile(file: string): Promise<string> {
+  // client requests wait while an import's batches are applied
+  private importing = false;
+
+  private async loadGridFile(file: string): Promise<Uint8Array> {
     const res = await fetch(file);
-    return await res.text();
+    return new Uint8Array(await res.arrayBuffer());
   }
 
   constructor() {
@@ -64,7 +69,7 @@ class Core {
   }
 
   private next = async () => {
-    if (this.clientQueue.length) {
+    if (this.clientQueue.length && !this.importing) {
       this.clientQueue.shift()?.();
     } else if (this.renderQueue.length) {
       this.renderQueue.shift()?.();
@@ -78,16 +83,44 @@ class Core {
     coreRender.init(renderPort);
     const results = await Promise.all([this.loadGridFile(message.url), initCore()]);
     try {
-      this.gridController = GridController.newFromFile(results[0], message.sequenceNumber, true);
+      this.gridController = GridController.newFromFile(
+        results[0],
+        message.sequenceNumber,
+        true,
+        message.sheetName
+      );
+      this.gridController.setUserId(message.userId);
     } catch (e) {
       console.error('Error loading grid file:', e);
       Sentry.captureException(e);
       return { error: 'Unable to load file' };
     }
     if (debugWebWorkers) console.log('[core] GridController loaded');
+    this.loadSheetsInBackground();
     return { version: this.gridController.getVersion() };
   }
 
+  // Sheets after the first are decoded one at a time between other requests.
+  // A sheet is also loaded immediately when it is accessed.
+  private loadSheetsInBackground() {
+    this.renderQueue.push(() => {
+      if (!this.gridController) throw new Error('Expected gridController to be defined in Core.loadSheetsInBackground');
+      try {
+        if (this.gridController.loadNextSheet()) {
+          this.loadSheetsInBackground();
+        } else if (debugWebWorkers) {
+          console.log('[core] all sheets loaded');
+        }
+      } catch (e) {
+        console.error('Error loading sheet:', e);
+        Sentry.captureException(e);
+
+        // sheets that cannot be loaded are skipped
+        this.loadSheetsInBackground();
+      }
+    });
+  }
+
   getSheetName(sheetId: string): Promise<string> {
     return new Promise((resolve) => {
       this.clientQueue.push(() => {
@@ -285,7 +318,11 @@ class Core {
       this.clientQueue.push(async () => {
         if (!this.gridController) throw new Error('Expected gridController to be defined');
         const data = message.transaction;
-        this.gridController.multiplayerTransaction(data.id, data.sequence_num, data.operations);
+        if (typeof data.operations === 'string') {
+          this.gridController.multiplayerTransaction(data.id, data.sequence_num, data.operations);
+        } else {
+          this.gridController.multiplayerTransactionBinary(data.id, data.sequence_num, data.operations);
+        }
         offline.markTransactionSent(data.id);
         if (await offline.unsentTransactionsCount()) {
           coreClient.sendMultiplayerState('syncing');
@@ -297,10 +334,59 @@ class Core {
     });
   }
 
-  receiveTransactions(transactions: string) {
+  rejectTransaction(transactionId: string) {
+    return new Promise((resolve) => {
+      this.clientQueue.push(async () => {
+        if (!this.gridController) throw new Error('Expected gridController to be defined');
+        this.gridController.rejectedTransaction(transactionId);
+
+        // the transaction should not be sent again when reconnecting
+        offline.markTransactionSent(transactionId);
+        if (await offline.unsentTransactionsCount()) {
+          coreClient.sendMultiplayerState('syncing');
+        } else {
+          coreClient.sendMultiplayerState('connected');
+        }
+        resolve(undefined);
+      });
+    });
+  }
+
+  receiveTransactions(transactions: string | Uint8Array) {
     return new Promise((resolve) => {
       this.clientQueue.push(async () => {
         if (!this.gridController) throw new Error('Expected gridController to be defined');
+        if (typeof transactions === 'string') {
+          this.gridController.receiveMultiplayerTransactions(transactions);
+        } else {
+          this.gridController.receiveMultiplayerTransactionsBinary(transactions);
+        }
+        if (await offline.unsentTransactionsCount()) {
+          coreClient.sendMultiplayerState('syncing');
+        } else {
+          coreClient.sendMultiplayerState('connected');
+        }
+        resolve(undefined);
+      });
+    });
+  }
+
+  // Replaces the grid with a checkpoint and applies the transactions after it.
+  // The file is reloaded if the checkpoint cannot be loaded.
+  loadCheckpoint(file: Uint8Array, sequenceNum: number, transactions: string) {
+    return new Promise((resolve) => {
+      this.clientQueue.push(async () => {
+        if (!this.gridController) throw new Error('Expected gridController to be defined');
+        try {
+          this.gridController.loadCheckpoint(file, sequenceNum);
+        } catch (e) {
+          console.error('Error loading checkpoint:', e);
+          Sentry.captureException(e);
+          coreMultiplayer.reload();
+          resolve(undefined);
+          return;
+        }
+        this.loadSheetsInBackground();
         this.gridController.receiveMultiplayerTransactions(transactions);
         if (await offline.unsentTransactionsCount()) {
           coreClient.sendMultiplayerState('syncing');
@@ -408,10 +494,18 @@ class Core {
     cursor?: string
   ): Promise<string | undefined> {
     return new Promise((resolve) => {
-      this.clientQueue.push(() => {
+      this.clientQueue.push(async () => {
         if (!this.gridController) throw new Error('Expected gridController to be defined');
         try {
-          this.gridController.importCsv(sheetId, new Uint8Array(file), fileName, posToPos(x, y), cursor);
+          const job = this.gridController.importCsv(
+            sheetId,
+            new Uint8Array(file),
+            fileName,
+            posToPos(x, y),
+            undefined,
+            cursor
+          );
+          await this.runImport(job);
           resolve(undefined);
         } catch (error) {
           // TODO(ddimaria): standardize on how WASM formats errors for a consistent error
@@ -434,10 +528,17 @@ class Core {
     cursor?: string
   ): Promise<string | undefined> {
     return new Promise((resolve) => {
-      this.clientQueue.push(() => {
+      this.clientQueue.push(async () => {
         if (!this.gridController) throw new Error('Expected gridController to be defined');
         try {
-          this.gridController.importParquet(sheetId, new Uint8Array(file), fileName, posToPos(x, y), cursor);
+          const job = this.gridController.importParquet(
+            sheetId,
+            new Uint8Array(file),
+            fileName,
+            posToPos(x, y),
+            cursor
+          );
+          await this.runImport(job);
           resolve(undefined);
         } catch (error) {
           // TODO(ddimaria): standardize on how WASM formats errors for a consistent error
@@ -450,6 +551,35 @@ class Core {
     });
   }
 
+  // Applies an import's batches one at a time, yielding between them so that
+  // the import can be cancelled while it runs.
+  private async runImport(job: ImportJob) {
+    if (!this.gridController) throw new Error('Expected gridController to be defined in Core.runImport');
+    this.importing = true;
+    try {
+      while (this.gridController.continueImport(job)) {
+        await this.allowEventLoop();
+      }
+    } finally {
+      this.importing = false;
+      job.free();
+    }
+  }
+
+  // Cancels the import in progress before its next batch. This is not queued,
+  // since client requests wait for the import.
+  cancelImport() {
+    if (!this.gridController) throw new Error('Expected gridController to be defined in Core.cancelImport');
+    this.gridController.cancelImport();
+  }
+
+  setImportLimits(limits: ImportLimits) {
+    this.clientQueue.push(() => {
+      if (!this.gridController) throw new Error('Expected gridController to be defined');
+      this.gridController.setImportLimits(JSON.stringify(limits, bigIntReplacer));
+    });
+  }
+
   deleteCellValues(selection: Selection, cursor?: string) {
     return new Promise((resolve) => {
       this.clientQueue.push(() => {
@@ -559,7 +689,7 @@ class Core {
 
   async upgradeGridFile(file: string, sequenceNum: number): Promise<{ grid: string; version: string }> {
     await initCore();
-    const gc = GridController.newFromFile(file, sequenceNum, false);
+    const gc = GridController.newFromFile(new TextEncoder().encode(file), sequenceNum, false, undefined);
     const grid = gc.exportToFile();
     const version = gc.getVersion();
     return { grid, version };
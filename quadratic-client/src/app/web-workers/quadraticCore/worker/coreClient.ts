@@ -8,6 +8,7 @@
 import { debugWebWorkers, debugWebWorkersMessages } from '@/app/debugFlags';
 import { getLanguage } from '@/app/helpers/codeCellLanguage';
 import {
+  ImportWarning,
   JsCodeCell,
   JsHtmlOutput,
   JsRenderBorders,
@@ -39,6 +40,7 @@ declare var self: WorkerGlobalScope &
       width: number,
       height: number
     ) => void;
+    sendImportWarnings: (filename: string, warnings: ImportWarning[]) => void;
     sendAddSheetClient: (sheetInfo: SheetInfo, user: boolean) => void;
     sendDeleteSheetClient: (sheetId: string, user: boolean) => void;
     sheetInfoUpdate: (sheetInfo: SheetInfo) => void;
@@ -78,6 +80,7 @@ declare var self: WorkerGlobalScope &
       renderCodeCell?: JsRenderCodeCell
     ) => void;
     sendUndoRedo: (undo: boolean, redo: boolean) => void;
+    sendProtectionError: (error: string) => void;
     sendImage: (sheetId: string, x: number, y: number, image?: string, w?: string, h?: string) => void;
   };
 
@@ -89,6 +92,7 @@ class CoreClient {
   start() {
     self.onmessage = this.handleMessage;
     self.sendImportProgress = coreClient.sendImportProgress;
+    self.sendImportWarnings = coreClient.sendImportWarnings;
     self.sendAddSheetClient = coreClient.sendAddSheet;
     self.sendDeleteSheetClient = coreClient.sendDeleteSheet;
     self.sendSheetInfoClient = coreClient.sendSheetInfoClient;
@@ -108,6 +112,7 @@ class CoreClient {
     self.sendTransactionProgress = coreClient.sendTransactionProgress;
     self.sendUpdateCodeCell = coreClient.sendUpdateCodeCell;
     self.sendUndoRedo = coreClient.sendUndoRedo;
+    self.sendProtectionError = coreClient.sendProtectionError;
     self.sendImage = coreClient.sendImage;
     if (debugWebWorkers) console.log('[coreClient] initialized.');
   }
@@ -277,6 +282,14 @@ class CoreClient {
         }
         return;
 
+      case 'clientCoreCancelImport':
+        core.cancelImport();
+        return;
+
+      case 'clientCoreSetImportLimits':
+        core.setImportLimits(e.data.limits);
+        return;
+
       case 'clientCoreDeleteCellValues':
         await core.deleteCellValues(e.data.selection, e.data.cursor);
         return;
@@ -528,6 +541,10 @@ class CoreClient {
     this.send({ type: 'coreClientImportProgress', filename, current, total, x, y, width, height });
   };
 
+  sendImportWarnings = (filename: string, warnings: ImportWarning[]) => {
+    this.send({ type: 'coreClientImportWarnings', filename, warnings });
+  };
+
   sendAddSheet = (sheetInfo: SheetInfo, user: boolean) => {
     this.send({ type: 'coreClientAddSheet', sheetInfo, user });
   };
@@ -645,6 +662,10 @@ class CoreClient {
     this.send({ type: 'coreClientUndoRedo', undo, redo });
   };
 
+  sendProtectionError = (error: string) => {
+    this.send({ type: 'coreClientProtectionError', error });
+  };
+
   getJwt() {
     return new Promise((resolve) => {
       const id = this.id++;
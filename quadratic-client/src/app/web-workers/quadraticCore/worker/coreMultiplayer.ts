@@ -39,6 +39,14 @@ class CoreMultiplayer {
         core.receiveTransactions(e.data.transactions);
         break;
 
+      case 'multiplayerCoreLoadCheckpoint':
+        core.loadCheckpoint(e.data.file, e.data.sequenceNum, e.data.transactions);
+        break;
+
+      case 'multiplayerCoreRejectTransaction':
+        core.rejectTransaction(e.data.transactionId);
+        break;
+
       default:
         console.warn('[coreMultiplayer] Unhandled message type', e.data);
     }
@@ -58,6 +66,10 @@ class CoreMultiplayer {
       sequenceNum,
     });
   };
+
+  reload() {
+    this.send({ type: 'coreMultiplayerReload' });
+  }
 }
 
 export const coreMultiplayer = new CoreMultiplayer();
@@ -2,6 +2,7 @@
 
 import {
   ConnectionKind,
+  ImportWarning,
   JsCodeCell,
   JsHtmlOutput,
   JsRenderBorders,
@@ -27,6 +28,7 @@ declare var self: WorkerGlobalScope &
       width: number,
       height: number
     ) => void;
+    sendImportWarnings: (filename: string, warnings: ImportWarning[]) => void;
     sendCompleteRenderCells: (sheetId: string, hashX: number, hashY: number, cells: string) => void;
     sendAddSheetClient: (sheetInfo: SheetInfo, user: boolean) => void;
     sendDeleteSheetClient: (sheetId: string, user: boolean) => void;
@@ -36,6 +38,7 @@ declare var self: WorkerGlobalScope &
     sendSheetMetaFills: (sheetId: string, fills: JsSheetFill) => void;
     sendSheetBorders: (sheetId: string, borders: JsRenderBorders) => void;
     sheetInfoUpdate: (sheetInfo: SheetInfo) => void;
+    sendProtectionError: (error: string) => void;
     sendSheetInfoUpdateRender: (sheetInfo: SheetInfo) => void;
     sendAddSheetRender: (sheetInfo: SheetInfo) => void;
     sendDeleteSheetRender: (sheetId: string) => void;
@@ -107,6 +110,10 @@ export const jsImportProgress = (
   return self.sendImportProgress(filename, current, total, x, y, width, height);
 };
 
+export const jsImportWarnings = (filename: string, warnings: string /*ImportWarning[]*/) => {
+  return self.sendImportWarnings(filename, JSON.parse(warnings));
+};
+
 export const jsRenderCellSheets = (sheetId: string, hashX: bigint, hashY: bigint, cells: string /*JsRenderCell[]*/) => {
   self.sendCompleteRenderCells(sheetId, Number(hashX), Number(hashY), cells);
 };
@@ -249,3 +256,7 @@ export const jsSheetMetaFills = (sheetId: string, sheetMetaFillsStringified: str
   const sheetMetaFills = JSON.parse(sheetMetaFillsStringified) as JsSheetFill;
   self.sendSheetMetaFills(sheetId, sheetMetaFills);
 };
+
+export const jsProtectionError = (error: string) => {
+  self.sendProtectionError(error);
+};
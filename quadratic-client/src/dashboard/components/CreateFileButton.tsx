@@ -1,4 +1,12 @@
-import { getExtension, isCsv, isExcel, isGrid, isParquet, stripExtension } from '@/app/helpers/files';
+import {
+  EXCEL_EXTENSIONS,
+  getExtension,
+  isCsv,
+  isExcel,
+  isGrid,
+  isParquet,
+  stripExtension,
+} from '@/app/helpers/files';
 import { validateAndUpgradeGridFile } from '@/app/schemas/validateAndUpgradeGridFile';
 import { quadraticCore } from '@/app/web-workers/quadraticCore/quadraticCore';
 import { useGlobalSnackbar } from '@/shared/components/GlobalSnackbarProvider';
@@ -100,8 +108,8 @@ export default function CreateFileButton({ isPrivate }: { isPrivate?: boolean })
     e.target.value = '';
   };
 
-  const DropDownButton = (props: { extension: string; name: string }): JSX.Element => {
-    const { name, extension } = props;
+  const DropDownButton = (props: { extensions: string[]; name: string }): JSX.Element => {
+    const { name, extensions } = props;
 
     return (
       <DropdownMenuItem
@@ -113,11 +121,11 @@ export default function CreateFileButton({ isPrivate }: { isPrivate?: boolean })
         }}
       >
         <label className="flex cursor-pointer justify-between gap-4">
-          {name} <span className="mx-1 font-mono text-xs text-muted-foreground">.{extension}</span>
+          {name} <span className="mx-1 font-mono text-xs text-muted-foreground">.{extensions[0]}</span>
           <input
             type="file"
             name="content"
-            accept={`.${extension}`}
+            accept={extensions.map((extension) => `.${extension}`).join(',')}
             onChange={(e) => {
               onOpenChange(false);
               handleImport(e);
@@ -138,8 +146,9 @@ export default function CreateFileButton({ isPrivate }: { isPrivate?: boolean })
           </Button>
         </DropdownMenuTrigger>
         <DropdownMenuContent align="end">
-          <DropDownButton name="Quadratic" extension="grid" />
-          <DropDownButton name="Excel" extension="xlsx" />
+          <DropDownButton name="Quadratic" extensions={['grid']} />
+          <DropDownButton name="Excel" extensions={EXCEL_EXTENSIONS.filter((extension) => extension !== 'ods')} />
+          <DropDownButton name="OpenDocument" extensions={['ods']} />
         </DropdownMenuContent>
       </DropdownMenu>
       <Button asChild>
@@ -56,10 +56,12 @@ export const loader = async ({ request, params }: LoaderFunctionArgs): Promise<F
   await Promise.all([initRustClient(), loadAssets()]);
 
   // initialize Core web worker
+  const user = await authClient.user();
   const result = await quadraticCore.load(
     data.file.lastCheckpointDataUrl,
     data.file.lastCheckpointVersion,
-    data.file.lastCheckpointSequenceNumber
+    data.file.lastCheckpointSequenceNumber,
+    user?.sub
   );
   if (result.error) {
     Sentry.captureEvent({
@@ -25,6 +25,16 @@ const DEFAULT_FILE: any = {
   version: '1.4',
 };
 
+// binary file contents are sent to the API as base64
+const arrayBufferToBase64 = (buffer: ArrayBuffer): string => {
+  const bytes = new Uint8Array(buffer);
+  let binary = '';
+  for (let i = 0; i < bytes.length; i += 0x8000) {
+    binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+  }
+  return btoa(binary);
+};
+
 export const apiClient = {
   teams: {
     async list() {
@@ -116,7 +126,7 @@ export const apiClient = {
       teamUuid,
       isPrivate,
     }: {
-      file?: Pick<ApiTypes['/v0/files.POST.request'], 'name' | 'contents' | 'version'>;
+      file?: Pick<ApiTypes['/v0/files.POST.request'], 'name' | 'contents' | 'contentsEncoding' | 'version'>;
       teamUuid: ApiTypes['/v0/files.POST.request']['teamUuid'];
       isPrivate: ApiTypes['/v0/files.POST.request']['isPrivate'];
     }) {
@@ -141,7 +151,8 @@ export const apiClient = {
       mixpanel.track('[Files].downloadFile', { id: uuid });
       const { file } = await this.get(uuid);
       const checkpointUrl = file.lastCheckpointDataUrl;
-      const checkpointData = await fetch(checkpointUrl).then((res) => res.text());
+      // checkpoints are binary files
+      const checkpointData = await fetch(checkpointUrl).then((res) => res.arrayBuffer());
       downloadQuadraticFile(file.name, checkpointData);
     },
     async duplicate(uuid: string, isPrivate?: boolean) {
@@ -152,8 +163,8 @@ export const apiClient = {
         team,
       } = await apiClient.files.get(uuid);
 
-      // Get the most recent checkpoint for the file
-      const lastCheckpointContents = await fetch(lastCheckpointDataUrl).then((res) => res.text());
+      // Get the most recent checkpoint for the file, which is binary
+      const lastCheckpointContents = await fetch(lastCheckpointDataUrl).then((res) => res.arrayBuffer());
 
       // Create it on the server
       const {
@@ -162,7 +173,8 @@ export const apiClient = {
         file: {
           name: name + ' (Copy)',
           version: lastCheckpointVersion,
-          contents: lastCheckpointContents,
+          contents: arrayBufferToBase64(lastCheckpointContents),
+          contentsEncoding: 'base64',
         },
         teamUuid: team.uuid,
         isPrivate,
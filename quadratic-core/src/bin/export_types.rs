@@ -10,7 +10,7 @@ use quadratic_core::{
     },
     grid::{
         js_types::{
-            JsCodeCell, JsHtmlOutput, JsRenderBorder, JsRenderBorders, JsRenderCell,
+            JsCodeCell, JsHtmlOutput, JsProtection, JsRenderBorder, JsRenderBorders, JsRenderCell,
             JsRenderCellSpecial, JsRenderCodeCell, JsRenderCodeCellState,
         },
         sheet::search::SearchOptions,
@@ -82,6 +82,7 @@ fn main() {
         Placement,
         ColumnRow,
         SheetInfo,
+        JsProtection,
         PasteSpecial,
         Rgba,
         CellBorderLine,
@@ -99,6 +100,13 @@ fn main() {
         Format,
         JsSheetFill,
         ColumnRow,
+        arrow::ExportCompression,
+        controller::export::ColumnarExportOptions,
+        controller::operations::import::CsvEncoding,
+        controller::operations::import::CsvImportOptions,
+        controller::operations::import::ImportLimits,
+        controller::operations::import::ImportWarning,
+        controller::operations::import::ImportWarningKind,
     );
 
     if create_dir_all("../quadratic-client/src/app/quadratic-core-types").is_ok() {
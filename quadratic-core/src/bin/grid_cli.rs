@@ -0,0 +1,244 @@
+//! Opens a `.grid` file or a CSV, Excel, Parquet, or JSON file, recomputes
+//! its formulas, and writes the result or prints the values of formulas.
+//!
+//! ```sh
+//! cargo run --bin grid_cli -- data.xlsx data.grid
+//! cargo run --bin grid_cli -- data.grid --query "'Sheet 1'!A0:D10"
+//! cargo run --bin grid_cli -- data.grid --check
+//! ```
+//!
+//! The format of each file is chosen by its extension: `.csv`, `.tsv`,
+//! `.xlsx` (or `.xls`, `.xlsb`, `.ods` for input), `.parquet`, `.json`, and
+//! `.ndjson`. Any other input is read as a `.grid` file, and any other
+//! output is written as a binary `.grid` file. Only one sheet is written to
+//! CSV, Parquet, and JSON: the one named by `--sheet`, or the first.
+//!
+//! Options:
+//!
+//! - `--query <formula>` prints the value of a formula, evaluated in the
+//!   first cell of the sheet, as CSV. It can be repeated.
+//! - `--sheet <name>` selects the sheet for queries and for single-sheet
+//!   outputs.
+//! - `--check` lists the code cells with errors, and fails if there are any.
+//! - `--no-recompute` keeps the results of formulas from the input file.
+//!
+//! Python and Javascript cells cannot run without a browser, so they keep the
+//! results saved in the file.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, bail, Context, Result};
+use quadratic_core::{
+    controller::{
+        export::ColumnarExportOptions, operations::import::CsvImportOptions, GridController,
+    },
+    grid::{file, CodeRunResult, Grid, SheetId},
+    selection::Selection,
+    Pos, SheetPos, Value,
+};
+
+struct Args {
+    input: String,
+    output: Option<String>,
+    queries: Vec<String>,
+    sheet: Option<String>,
+    check: bool,
+    recompute: bool,
+}
+
+const USAGE: &str = "Usage: grid_cli <input> [<output>] [--query <formula>]... [--sheet <name>] [--check] [--no-recompute]";
+
+fn parse_args() -> Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let mut paths = vec![];
+    let mut queries = vec![];
+    let mut sheet = None;
+    let mut check = false;
+    let mut recompute = true;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--query" => queries.push(args.next().context("--query needs a formula")?),
+            "--sheet" => sheet = Some(args.next().context("--sheet needs a name")?),
+            "--check" => check = true,
+            "--no-recompute" => recompute = false,
+            arg if arg.starts_with("--") => bail!("Unknown option {arg}"),
+            _ => paths.push(arg),
+        }
+    }
+    let mut paths = paths.into_iter();
+    let (Some(input), output, None) = (paths.next(), paths.next(), paths.next()) else {
+        bail!(USAGE);
+    };
+    Ok(Args {
+        input,
+        output,
+        queries,
+        sheet,
+        check,
+        recompute,
+    })
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn open(path: &str) -> Result<GridController> {
+    let contents = std::fs::read(path).with_context(|| format!("Unable to read {path}"))?;
+    let file_name = Path::new(path)
+        .file_name()
+        .map_or(path.to_string(), |name| name.to_string_lossy().to_string());
+    let grid = match extension(path).as_str() {
+        "xlsx" | "xlsm" | "xlsb" | "xls" | "ods" => {
+            let mut gc = GridController::from_grid(Grid::new_blank(), 0);
+            for warning in gc.import_excel(contents, &file_name)? {
+                eprintln!("Warning: {}", serde_json::to_string(&warning)?);
+            }
+            return Ok(gc);
+        }
+        "csv" | "tsv" | "txt" | "parquet" | "json" | "ndjson" => Grid::new(),
+        _ => file::import_bytes(&contents)?,
+    };
+
+    let mut gc = GridController::from_grid(grid, 0);
+    let sheet_id = gc.sheet_ids()[0];
+    match extension(path).as_str() {
+        "csv" | "tsv" | "txt" => gc.import_csv(
+            sheet_id,
+            &contents,
+            &file_name,
+            Pos::ORIGIN,
+            &CsvImportOptions::default(),
+            None,
+        )?,
+        "parquet" => gc.import_parquet(sheet_id, contents, &file_name, Pos::ORIGIN, None)?,
+        "json" | "ndjson" => gc.import_json(sheet_id, &contents, &file_name, Pos::ORIGIN, None)?,
+        _ => (),
+    }
+    Ok(gc)
+}
+
+fn find_sheet(gc: &mut GridController, name: Option<&String>) -> Result<SheetId> {
+    match name {
+        Some(name) => gc
+            .try_sheet_from_name(name.clone())
+            .map(|sheet| sheet.id)
+            .ok_or_else(|| anyhow!("Sheet {name} not found")),
+        None => Ok(gc.sheet_ids()[0]),
+    }
+}
+
+/// Prints the code cells whose last run failed. Returns the number of errors.
+fn check(gc: &GridController) -> usize {
+    let mut errors = 0;
+    for sheet in gc.grid().sheets() {
+        for (pos, code_run) in &sheet.code_runs {
+            let error = match &code_run.result {
+                CodeRunResult::Err(error) => error.to_string(),
+                CodeRunResult::Ok(_) if code_run.spill_error => "Spill error".to_string(),
+                CodeRunResult::Ok(_) => continue,
+            };
+            println!("{}!{}: {error}", sheet.name, pos.a1_string());
+            errors += 1;
+        }
+    }
+    errors
+}
+
+fn print_value(value: &Value) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    match value {
+        Value::Single(value) => writer.write_record([value.to_string()])?,
+        Value::Array(array) => {
+            for row in array.rows() {
+                writer.write_record(row.iter().map(|value| value.to_string()))?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write(gc: &mut GridController, path: &str, sheet: Option<&String>) -> Result<()> {
+    let extension = extension(path);
+    let selection = Selection::all(find_sheet(gc, sheet)?);
+    let options = ColumnarExportOptions {
+        header_row: true,
+        ..Default::default()
+    };
+    let contents = match extension.as_str() {
+        "csv" => gc.export_csv_selection(selection)?.into_bytes(),
+        "tsv" => {
+            let csv = gc.export_csv_selection(selection)?;
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(csv.as_bytes());
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(b'\t')
+                .from_writer(vec![]);
+            for record in reader.records() {
+                writer.write_record(&record?)?;
+            }
+            writer.into_inner()?
+        }
+        "xlsx" => gc.export_excel()?,
+        "parquet" => gc.export_parquet_selection(selection, options)?,
+        "json" | "ndjson" => gc
+            .export_json_selection(selection, extension == "ndjson")?
+            .into_bytes(),
+        _ => file::export_binary(gc.grid_mut())?,
+    };
+    std::fs::write(path, contents).with_context(|| format!("Unable to write {path}"))
+}
+
+fn run(args: Args) -> Result<ExitCode> {
+    let mut gc = open(&args.input)?;
+    if args.recompute {
+        let ops = gc.rerun_all_formula_cells_operations();
+        gc.server_apply_transaction(ops);
+    }
+
+    let mut failed = false;
+    if !args.queries.is_empty() {
+        let sheet_id = find_sheet(&mut gc, args.sheet.as_ref())?;
+        for query in &args.queries {
+            match gc.evaluate_formula(SheetPos::new(sheet_id, 0, 0), query) {
+                Ok(value) => print_value(&value)?,
+                Err(e) => {
+                    eprintln!("Unable to evaluate {query}: {e}");
+                    failed = true;
+                }
+            }
+        }
+    }
+    if args.check {
+        let errors = check(&gc);
+        if errors > 0 {
+            eprintln!("{errors} code cells have errors.");
+            failed = true;
+        }
+    }
+    if let Some(output) = &args.output {
+        write(&mut gc, output, args.sheet.as_ref())?;
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn main() -> ExitCode {
+    match parse_args().and_then(run) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
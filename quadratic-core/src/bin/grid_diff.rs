@@ -0,0 +1,92 @@
+//! Lists the differences between two `.grid` files, or merges two copies of
+//! a file with the file they started from.
+//!
+//! ```sh
+//! cargo run --bin grid_diff -- old.grid new.grid
+//! cargo run --bin grid_diff -- base.grid ours.grid theirs.grid [merged.grid]
+//! ```
+//!
+//! A merge keeps our changes where both copies changed the same thing, and
+//! lists those conflicts. With an output file, the merged grid is written in
+//! the binary format.
+
+use std::process::ExitCode;
+
+use quadratic_core::{
+    controller::{operations::diff::diff_grids, GridController},
+    grid::{file, Grid, SheetId},
+};
+
+fn read(path: &str) -> Result<Grid, ExitCode> {
+    let contents = std::fs::read(path).map_err(|e| {
+        eprintln!("Unable to read {path}: {e}");
+        ExitCode::FAILURE
+    })?;
+    file::import_bytes(&contents).map_err(|e| {
+        eprintln!("Unable to read {path}: {e}");
+        ExitCode::FAILURE
+    })
+}
+
+/// Returns the name of a sheet in either grid.
+fn sheet_name(sheet_id: SheetId, grids: &[&Grid]) -> String {
+    grids
+        .iter()
+        .find_map(|grid| grid.try_sheet(sheet_id))
+        .map_or(sheet_id.to_string(), |sheet| sheet.name.clone())
+}
+
+fn run(paths: &[String]) -> Result<ExitCode, ExitCode> {
+    match paths {
+        [from, to] => {
+            let (from, to) = (read(from)?, read(to)?);
+            let changes = diff_grids(&from, &to);
+            for change in &changes {
+                let sheet = sheet_name(change.sheet_id(), &[&from, &to]);
+                println!("{sheet}: {change}");
+            }
+            Ok(if changes.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        [base, ours, theirs, output @ ..] if output.len() <= 1 => {
+            let (base, theirs) = (read(base)?, read(theirs)?);
+            let mut gc = GridController::from_grid(read(ours)?, 0);
+            let conflicts = gc.merge(&base, &theirs, None);
+            for conflict in &conflicts {
+                let sheet = sheet_name(conflict.ours.sheet_id(), &[gc.grid(), &theirs]);
+                println!("conflict in {sheet}:");
+                println!("  ours:   {}", conflict.ours);
+                println!("  theirs: {}", conflict.theirs);
+            }
+            if let Some(output) = output.first() {
+                let merged = file::export_binary(gc.grid_mut()).map_err(|e| {
+                    eprintln!("Unable to export the merged grid: {e}");
+                    ExitCode::FAILURE
+                })?;
+                std::fs::write(output, merged).map_err(|e| {
+                    eprintln!("Unable to write {output}: {e}");
+                    ExitCode::FAILURE
+                })?;
+                println!("Merged file was written to {output}.");
+            }
+            Ok(if conflicts.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        _ => {
+            eprintln!("Usage: grid_diff <from> <to>");
+            eprintln!("       grid_diff <base> <ours> <theirs> [<output>]");
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    run(&args).unwrap_or_else(|code| code)
+}
@@ -0,0 +1,88 @@
+//! Validates a `.grid` file and writes a repaired copy.
+//!
+//! ```sh
+//! cargo run --bin repair_grid -- damaged.grid [repaired.grid] [--json]
+//! ```
+//!
+//! Without an output file, the problems are only reported. With one, the
+//! repaired grid is written in the binary format (or JSON with `--json`), and
+//! the values that were removed are written next to it in `.report.json`.
+
+use std::process::ExitCode;
+
+use quadratic_core::grid::file::{self, repair::repair};
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let json = args.iter().any(|arg| arg == "--json");
+    let paths = args
+        .iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    let (input, output) = match paths.as_slice() {
+        [input] => (input, None),
+        [input, output] => (input, Some(output)),
+        _ => {
+            eprintln!("Usage: repair_grid <input> [<output>] [--json]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let contents = match std::fs::read(input) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Unable to read {input}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (mut grid, report) = match repair(&contents) {
+        Ok(repaired) => repaired,
+        Err(e) => {
+            eprintln!("Unable to repair {input}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    print!("{report}");
+
+    if let Some(output) = output {
+        let repaired = if json {
+            file::export_vec(&mut grid)
+        } else {
+            file::export_binary(&mut grid)
+        };
+        let repaired = match repaired {
+            Ok(repaired) => repaired,
+            Err(e) => {
+                eprintln!("Unable to export the repaired grid: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Err(e) = std::fs::write(output, repaired) {
+            eprintln!("Unable to write {output}: {e}");
+            return ExitCode::FAILURE;
+        }
+        println!("Repaired file was written to {output}.");
+
+        if !report.is_valid() {
+            let report_path = format!("{output}.report.json");
+            let report = match serde_json::to_string_pretty(&report) {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("Unable to serialize the report: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            if let Err(e) = std::fs::write(&report_path, report) {
+                eprintln!("Unable to write {report_path}: {e}");
+                return ExitCode::FAILURE;
+            }
+            println!("Removed values were written to {report_path}.");
+        }
+    }
+
+    if report.is_valid() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,130 @@
+//! Edit History
+//!
+//! Records of who changed which cells, built from the authors of the
+//! transactions that the server sequences.  Multiplayer stores them and
+//! answers queries like "who last changed Sheet1!B7?" and "what did this user
+//! change yesterday?".
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::transaction::TransactionServer;
+use crate::{grid::SheetId, Rect, SheetPos};
+
+/// The areas of a file that a transaction changed, and who changed them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EditRecord {
+    pub transaction_id: Uuid,
+    pub sequence_num: u64,
+    pub user_id: String,
+    pub session_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+
+    // the changed cells, or None for changes to the sheet itself
+    pub areas: Vec<(SheetId, Option<Rect>)>,
+}
+
+impl EditRecord {
+    /// Returns the record of a transaction, or None if its author is unknown.
+    pub fn from_transaction(transaction: &TransactionServer) -> Option<Self> {
+        let author = transaction.author.as_ref()?;
+        let mut areas = vec![];
+        for area in transaction
+            .operations
+            .iter()
+            .flat_map(|operation| operation.changed_areas())
+        {
+            if !areas.contains(&area) {
+                areas.push(area);
+            }
+        }
+
+        Some(EditRecord {
+            transaction_id: transaction.id,
+            sequence_num: transaction.sequence_num,
+            user_id: author.user_id.to_owned(),
+            session_id: author.session_id,
+            timestamp: author.timestamp,
+            areas,
+        })
+    }
+
+    /// Returns whether the transaction changed a cell.
+    pub fn changed(&self, sheet_pos: SheetPos) -> bool {
+        let pos = Rect::single_pos(sheet_pos.into());
+        self.areas.iter().any(|(sheet_id, rect)| {
+            *sheet_id == sheet_pos.sheet_id && rect.is_some_and(|rect| rect.intersects(pos))
+        })
+    }
+}
+
+/// A question to ask the edit history.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum EditHistoryQuery {
+    /// The last `limit` changes to a cell, newest first.
+    Cell { sheet_pos: SheetPos, limit: usize },
+
+    /// The changes by a user between two times (inclusive), oldest first.
+    User {
+        user_id: String,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cell_values::CellValues, controller::operations::operation::Operation,
+        controller::transaction::TransactionAuthor, CellValue,
+    };
+
+    fn transaction(
+        sequence_num: u64,
+        user_id: &str,
+        timestamp: DateTime<Utc>,
+        operations: Vec<Operation>,
+    ) -> TransactionServer {
+        TransactionServer {
+            id: Uuid::new_v4(),
+            file_id: Uuid::new_v4(),
+            operations,
+            sequence_num,
+            author: Some(TransactionAuthor {
+                user_id: user_id.into(),
+                session_id: Uuid::new_v4(),
+                timestamp,
+            }),
+        }
+    }
+
+    fn set_value(sheet_pos: SheetPos) -> Operation {
+        Operation::SetCellValues {
+            sheet_pos,
+            values: CellValues::from(CellValue::Text("a".into())),
+        }
+    }
+
+    #[test]
+    fn records_transactions() {
+        let sheet_id = SheetId::test();
+        let sheet_pos = SheetPos::new(sheet_id, 1, 6);
+        let mut transaction = transaction(
+            1,
+            "user",
+            Utc::now(),
+            vec![set_value(sheet_pos), set_value(sheet_pos)],
+        );
+
+        let record = EditRecord::from_transaction(&transaction).unwrap();
+        assert_eq!(record.areas, vec![(sheet_id, Some(Rect::new(1, 6, 1, 6)))]);
+        assert!(record.changed(sheet_pos));
+        assert!(!record.changed(SheetPos::new(sheet_id, 1, 7)));
+
+        transaction.author = None;
+        assert_eq!(EditRecord::from_transaction(&transaction), None);
+    }
+}
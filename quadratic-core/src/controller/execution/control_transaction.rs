@@ -7,7 +7,10 @@ use crate::{
         active_transactions::{
             pending_transaction::PendingTransaction, transaction_name::TransactionName,
         },
-        operations::operation::Operation,
+        operations::{
+            import::{ImportBatches, ImportJob},
+            operation::Operation,
+        },
         transaction::Transaction,
         transaction_summary::{CELL_SHEET_HEIGHT, CELL_SHEET_WIDTH},
         transaction_types::JsCodeResult,
@@ -21,6 +24,11 @@ use crate::{
 impl GridController {
     // loop compute cycle until complete or an async call is made
     pub(super) fn start_transaction(&mut self, transaction: &mut PendingTransaction) {
+        self.send_transaction_start(transaction);
+        self.run_transaction(transaction);
+    }
+
+    fn send_transaction_start(&self, transaction: &PendingTransaction) {
         if cfg!(target_family = "wasm") && !transaction.is_server() {
             let transaction_name = serde_json::to_string(&transaction.transaction_name)
                 .unwrap_or("Unknown".to_string());
@@ -29,6 +37,9 @@ impl GridController {
                 transaction_name,
             );
         }
+    }
+
+    fn run_transaction(&mut self, transaction: &mut PendingTransaction) {
         loop {
             if transaction.operations.is_empty() {
                 transaction.complete = true;
@@ -95,6 +106,11 @@ impl GridController {
         cursor: Option<String>,
         transaction_name: TransactionName,
     ) {
+        if let Err(error) = self.check_protections(&operations) {
+            self.send_protection_error(&error);
+            return;
+        }
+
         let mut transaction = PendingTransaction {
             transaction_type: TransactionType::User,
             operations: operations.into(),
@@ -106,6 +122,114 @@ impl GridController {
         self.finalize_transaction(&mut transaction);
     }
 
+    /// Starts applying the batches of an import as a single user
+    /// transaction. Each batch is applied by `continue_import` before the
+    /// next one is read, and code cells that depend on the imported cells are
+    /// computed once, after the last batch.
+    pub(crate) fn begin_import(
+        &mut self,
+        batches: impl ImportBatches + 'static,
+        cursor: Option<String>,
+    ) -> ImportJob {
+        // a cancellation that arrived after the last import finished doesn't
+        // apply to this one
+        self.import_cancellation.reset();
+
+        let transaction = PendingTransaction {
+            transaction_type: TransactionType::User,
+            cursor,
+            transaction_name: TransactionName::Import,
+            ..Default::default()
+        };
+        self.send_transaction_start(&transaction);
+
+        ImportJob {
+            transaction: Some(transaction),
+            batches: Box::new(batches),
+            compute_operations: vec![],
+        }
+    }
+
+    /// Applies the remaining batches of an import.
+    pub(crate) fn finish_import(&mut self, mut import: ImportJob) -> anyhow::Result<()> {
+        while self.continue_import(&mut import)? {}
+        Ok(())
+    }
+
+    /// Applies the next batch of an import. Returns whether batches remain;
+    /// the transaction is finalized after the last one.
+    ///
+    /// If the batch fails or the import was cancelled, the applied batches
+    /// are rolled back and the error is returned.
+    pub fn continue_import(&mut self, import: &mut ImportJob) -> anyhow::Result<bool> {
+        let Some(mut transaction) = import.transaction.take() else {
+            return Ok(false);
+        };
+
+        match self.apply_import_batch(&mut transaction, import) {
+            Ok(true) => {
+                import.transaction = Some(transaction);
+                Ok(true)
+            }
+            Ok(false) => {
+                self.import_cancellation.reset();
+                transaction
+                    .operations
+                    .extend(import.compute_operations.drain(..));
+                self.run_transaction(&mut transaction);
+                self.finalize_transaction(&mut transaction);
+                Ok(false)
+            }
+            Err(e) => {
+                self.import_cancellation.reset();
+                self.rollback_transaction(transaction);
+                Err(e)
+            }
+        }
+    }
+
+    // applies the next batch of an import; returns false if there are none
+    fn apply_import_batch(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        import: &mut ImportJob,
+    ) -> anyhow::Result<bool> {
+        if self.import_cancellation.is_cancelled() {
+            anyhow::bail!("Import cancelled");
+        }
+        let Some(batch) = import.batches.next_batch(self)? else {
+            return Ok(false);
+        };
+        self.check_protections(&batch)?;
+        transaction.operations.extend(batch);
+        while let Some(op) = transaction.operations.pop_front() {
+            match op {
+                Operation::ComputeCode { .. } => {
+                    if !import.compute_operations.contains(&op) {
+                        import.compute_operations.push(op);
+                    }
+                }
+                op => {
+                    transaction.operations.push_front(op);
+                    self.execute_operation(transaction);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reverts the operations applied by a transaction that was not
+    /// finalized. The rollback is not added to the undo stack or sent to the
+    /// server.
+    fn rollback_transaction(&mut self, transaction: PendingTransaction) {
+        let mut rollback = PendingTransaction {
+            transaction_type: TransactionType::Multiplayer,
+            operations: transaction.reverse_operations.into(),
+            ..Default::default()
+        };
+        self.run_transaction(&mut rollback);
+    }
+
     pub fn start_undo_transaction(
         &mut self,
         transaction: Transaction,
@@ -15,6 +15,10 @@ impl GridController {
         output: &SheetRect,
         skip_compute: Option<SheetPos>,
     ) {
+        // sheets that were not loaded with the file may have dependent code
+        for sheet_id in self.grid.unloaded_sheets_accessing(output) {
+            self.load_sheet_on_access(sheet_id);
+        }
         self.get_dependent_code_cells(output)
             .iter()
             .for_each(|sheet_positions| {
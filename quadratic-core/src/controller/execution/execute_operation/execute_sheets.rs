@@ -205,6 +205,43 @@ impl GridController {
         }
     }
 
+    pub(crate) fn execute_set_protection(
+        &mut self,
+        transaction: &mut PendingTransaction,
+        op: Operation,
+    ) {
+        if let Operation::SetProtection {
+            sheet_id,
+            protection_id,
+            protection,
+        } = op
+        {
+            let Some(sheet) = self.try_sheet_mut(sheet_id) else {
+                // sheet may have been deleted
+                return;
+            };
+            let old_protection = sheet.set_protection(protection_id, protection.clone());
+
+            transaction
+                .forward_operations
+                .push(Operation::SetProtection {
+                    sheet_id,
+                    protection_id,
+                    protection,
+                });
+            transaction.reverse_operations.insert(
+                0,
+                Operation::SetProtection {
+                    sheet_id,
+                    protection_id,
+                    protection: old_protection,
+                },
+            );
+
+            self.send_sheet_info(sheet_id);
+        }
+    }
+
     pub(crate) fn execute_duplicate_sheet(
         &mut self,
         transaction: &mut PendingTransaction,
@@ -16,6 +16,10 @@ impl GridController {
     ///
     pub fn execute_operation(&mut self, transaction: &mut PendingTransaction) {
         if let Some(op) = transaction.operations.pop_front() {
+            for sheet_id in op.sheet_ids() {
+                self.load_sheet_on_access(sheet_id);
+            }
+
             #[cfg(feature = "show-operations")]
             dbgjs!(&format!("[Operation] {:?}", &op));
 
@@ -38,6 +42,7 @@ impl GridController {
                 Operation::SetSheetName { .. } => self.execute_set_sheet_name(transaction, op),
                 Operation::SetSheetColor { .. } => self.execute_set_sheet_color(transaction, op),
                 Operation::DuplicateSheet { .. } => self.execute_duplicate_sheet(transaction, op),
+                Operation::SetProtection { .. } => self.execute_set_protection(transaction, op),
 
                 Operation::ResizeColumn { .. } => self.execute_resize_column(transaction, op),
                 Operation::ResizeRow { .. } => self.execute_resize_row(transaction, op),
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 
 use super::TransactionType;
 use crate::controller::{
@@ -7,8 +7,12 @@ use crate::controller::{
     },
     operations::operation::Operation,
     transaction::TransactionServer,
+    transaction_summary::{CELL_SHEET_HEIGHT, CELL_SHEET_WIDTH},
     GridController,
 };
+use crate::grid::{Grid, GridBounds};
+use crate::wasm_bindings::controller::sheet_info::SheetInfo;
+use crate::Pos;
 use chrono::{Duration, TimeDelta, Utc};
 use uuid::Uuid;
 
@@ -236,6 +240,82 @@ impl GridController {
         self.finalize_transaction(&mut results);
     }
 
+    /// Replaces the grid with a checkpoint of the file at `sequence_num`,
+    /// which the server sends when the transactions that we are missing are
+    /// no longer available.  The client's sheets are replaced with the
+    /// checkpoint's, and unsaved transactions are reapplied on top of it.
+    pub fn load_checkpoint(&mut self, grid: Grid, sequence_num: u64) {
+        let old_sheets = self
+            .grid
+            .sheets()
+            .iter()
+            .map(|sheet| (sheet.id, sheet.bounds(false)))
+            .collect::<Vec<_>>();
+        self.grid = grid;
+
+        if cfg!(target_family = "wasm") || cfg!(test) {
+            for (sheet_id, bounds) in old_sheets.iter() {
+                let Some(sheet) = self.try_sheet(*sheet_id) else {
+                    crate::wasm_bindings::js::jsDeleteSheet(sheet_id.to_string(), false);
+                    continue;
+                };
+
+                // clears the cells that are no longer in the sheet
+                if let GridBounds::NonEmpty(bounds) = bounds {
+                    let mut positions = HashSet::new();
+                    for y in (bounds.min.y..=bounds.max.y + CELL_SHEET_HEIGHT as i64)
+                        .step_by(CELL_SHEET_HEIGHT as usize)
+                    {
+                        for x in (bounds.min.x..=bounds.max.x + CELL_SHEET_WIDTH as i64)
+                            .step_by(CELL_SHEET_WIDTH as usize)
+                        {
+                            positions.insert(Pos { x, y });
+                        }
+                    }
+                    sheet.send_render_cells(&positions);
+                }
+            }
+
+            for sheet_id in self.sheet_ids() {
+                if !old_sheets.iter().any(|(id, _)| *id == sheet_id) {
+                    if let Some(sheet) = self.try_sheet(sheet_id) {
+                        let sheet_info = SheetInfo::new(sheet, self.user_id());
+                        if let Ok(sheet_info) = serde_json::to_string(&sheet_info) {
+                            crate::wasm_bindings::js::jsAddSheet(sheet_info, false);
+                        }
+                    }
+                }
+                self.send_loaded_sheet(sheet_id);
+            }
+        }
+
+        // transactions that were received early are applied if they follow
+        // the checkpoint
+        self.transactions
+            .out_of_order_transactions
+            .retain(|transaction| transaction.sequence_num > Some(sequence_num));
+        self.apply_out_of_order_transactions(sequence_num);
+        self.reapply_unsaved_transactions();
+    }
+
+    /// Called when the server rejects one of our transactions. The
+    /// transaction is rolled back and removed from the undo stack; any later
+    /// unsaved transactions are kept.
+    pub fn rejected_transaction(&mut self, transaction_id: Uuid) {
+        let Some(index) = self
+            .transactions
+            .unsaved_transactions
+            .find_index(transaction_id)
+        else {
+            return;
+        };
+        self.rollback_unsaved_transactions();
+        self.transactions.unsaved_transactions.remove(index);
+        self.undo_stack
+            .retain(|transaction| transaction.id != transaction_id);
+        self.reapply_unsaved_transactions();
+    }
+
     /// Called by TS for each offline transaction it has in its offline queue.
     pub fn apply_offline_unsaved_transaction(
         &mut self,
@@ -275,8 +355,11 @@ impl GridController {
 mod tests {
     use super::*;
     use crate::{
-        controller::{transaction_types::JsCodeResult, GridController},
-        grid::{CodeCellLanguage, Sheet},
+        controller::{transaction::Transaction, transaction_types::JsCodeResult, GridController},
+        grid::{
+            file::{export_binary, import_lazy},
+            CodeCellLanguage, Sheet,
+        },
         CellValue, CodeCellValue, Pos, SheetPos,
     };
     use bigdecimal::BigDecimal;
@@ -652,12 +735,14 @@ mod tests {
                 id: Uuid::new_v4(),
                 sequence_num: 1,
                 operations: other_1_operations,
+                author: None,
             },
             TransactionServer {
                 file_id: Uuid::new_v4(),
                 id: Uuid::new_v4(),
                 sequence_num: 2,
                 operations: other_2_operations,
+                author: None,
             },
         ]);
         assert_eq!(client.transactions.last_sequence_num, 2);
@@ -723,6 +808,7 @@ mod tests {
             id: Uuid::new_v4(),
             sequence_num: 1,
             operations: other_operations,
+            author: None,
         }]);
 
         assert_eq!(
@@ -817,6 +903,7 @@ mod tests {
             id: Uuid::new_v4(),
             sequence_num: 1,
             operations: other_operations,
+            author: None,
         }]);
 
         // expect this to be None since the async client.set_code_cell overwrites the other's multiplayer transaction
@@ -1147,4 +1234,70 @@ mod tests {
             Some(CellValue::Number(BigDecimal::from(3)))
         );
     }
+
+    #[test]
+    fn rejected_transaction() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "rejected".to_string(), None);
+        let rejected_id = gc.last_transaction().unwrap().id;
+        gc.set_cell_value(SheetPos::new(sheet_id, 1, 0), "kept".to_string(), None);
+
+        gc.rejected_transaction(rejected_id);
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(sheet.display_value(Pos { x: 0, y: 0 }), None);
+        assert_eq!(
+            sheet.display_value(Pos { x: 1, y: 0 }),
+            Some(CellValue::Text("kept".to_string()))
+        );
+        assert_eq!(gc.transactions.unsaved_transactions.len(), 1);
+
+        // only the kept transaction can be undone
+        gc.undo(None);
+        assert_eq!(gc.sheet(sheet_id).display_value(Pos { x: 1, y: 0 }), None);
+        assert!(!gc.has_undo());
+
+        // unknown transactions are ignored
+        gc.rejected_transaction(Uuid::new_v4());
+    }
+
+    #[test]
+    fn loads_a_checkpoint() {
+        let mut checkpoint = GridController::test();
+        let sheet_id = checkpoint.sheet_ids()[0];
+        checkpoint.set_cell_value(SheetPos::new(sheet_id, 0, 0), "checkpoint".into(), None);
+        checkpoint.add_sheet(None);
+        let file = export_binary(checkpoint.grid_mut()).unwrap();
+
+        let mut client = GridController::test();
+        client.grid.try_sheet_mut(client.sheet_ids()[0]).unwrap().id = sheet_id;
+        client.set_cell_value(SheetPos::new(sheet_id, 1, 0), "unsaved".into(), None);
+        client.transactions.out_of_order_transactions = [12, 5]
+            .map(|sequence_num| Transaction {
+                sequence_num: Some(sequence_num),
+                ..Default::default()
+            })
+            .to_vec();
+
+        client.load_checkpoint(import_lazy(&file, None).unwrap(), 10);
+        assert_eq!(client.transactions.last_sequence_num, 10);
+
+        // transactions that are in the checkpoint are dropped
+        assert_eq!(client.transactions.out_of_order_transactions.len(), 1);
+        assert_eq!(
+            client.transactions.out_of_order_transactions[0].sequence_num,
+            Some(12)
+        );
+        assert_eq!(client.sheet_ids().len(), 2);
+
+        let sheet = client.sheet(sheet_id);
+        assert_eq!(
+            sheet.display_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("checkpoint".to_string()))
+        );
+        assert_eq!(
+            sheet.display_value(Pos { x: 1, y: 0 }),
+            Some(CellValue::Text("unsaved".to_string()))
+        );
+    }
 }
@@ -2,9 +2,9 @@ use chrono::Utc;
 
 use crate::{
     controller::{active_transactions::pending_transaction::PendingTransaction, GridController},
-    formulas::{parse_formula, Ctx},
+    formulas::{find_cell_references, parse_formula, Ctx},
     grid::{CodeRun, CodeRunResult},
-    SheetPos,
+    CodeResult, SheetPos, Value,
 };
 
 impl GridController {
@@ -14,6 +14,7 @@ impl GridController {
         sheet_pos: SheetPos,
         code: String,
     ) {
+        self.load_referenced_sheets(&code, sheet_pos);
         let mut ctx = Ctx::new(self.grid(), sheet_pos);
         transaction.current_sheet_pos = Some(sheet_pos);
         match parse_formula(&code, sheet_pos.into()) {
@@ -43,6 +44,23 @@ impl GridController {
             }
         }
     }
+
+    /// Evaluates a formula as if it were in `sheet_pos`, without changing
+    /// the grid.
+    pub fn evaluate_formula(&mut self, sheet_pos: SheetPos, formula: &str) -> CodeResult<Value> {
+        self.load_referenced_sheets(formula, sheet_pos);
+        let mut ctx = Ctx::new(self.grid(), sheet_pos);
+        parse_formula(formula, sheet_pos.into())?.eval(&mut ctx)
+    }
+
+    /// Loads the other sheets that a formula references.
+    fn load_referenced_sheets(&mut self, formula: &str, sheet_pos: SheetPos) {
+        for cell_ref in find_cell_references(formula, sheet_pos.into()) {
+            if let Some(sheet_name) = cell_ref.inner.sheet_name() {
+                self.try_sheet_from_name(sheet_name.clone());
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -427,4 +445,29 @@ mod test {
         let result = sheet.code_run(pos).unwrap();
         assert!(!result.spill_error);
     }
+
+    #[test]
+    fn test_evaluate_formula() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "1".into(), None);
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 1), "2".into(), None);
+        let sheet_pos = SheetPos::new(sheet_id, 0, 0);
+
+        assert_eq!(
+            gc.evaluate_formula(sheet_pos, "SUM(A0:A1)").unwrap(),
+            Value::Single(CellValue::Number(3.into()))
+        );
+        assert_eq!(
+            gc.evaluate_formula(sheet_pos, "'Sheet 1'!A0:A1").unwrap(),
+            Value::Array(Array::from(vec![
+                vec![CellValue::Number(1.into())],
+                vec![CellValue::Number(2.into())]
+            ]))
+        );
+        assert!(gc.evaluate_formula(sheet_pos, "SUM(").is_err());
+
+        // the grid is unchanged
+        assert_eq!(gc.sheet(sheet_id).code_runs.len(), 0);
+    }
 }
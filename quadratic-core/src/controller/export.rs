@@ -1,15 +1,36 @@
+use std::collections::{HashMap, HashSet};
+
 use anyhow::{Context, Result};
 use csv::Writer;
 use itertools::PeekingNext;
+use serde::{Deserialize, Serialize};
 
 use super::GridController;
-use crate::{selection::Selection, Pos};
+use crate::{
+    arrow::{cell_values_to_record_batch, record_batch_to_arrow_ipc, ExportCompression},
+    json::records_to_json,
+    parquet::record_batch_to_parquet,
+    selection::Selection,
+    util::column_name,
+    xlsx, CellValue, Pos,
+};
+
+/// Options for exporting a selection to Parquet or Arrow IPC.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+pub struct ColumnarExportOptions {
+    /// Use the first row of the selection as column names. Otherwise columns
+    /// are named by their letter.
+    pub header_row: bool,
+    pub compression: ExportCompression,
+}
 
 impl GridController {
     /// exports a CSV string from a selection on the grid.
     ///
     /// Returns a [`String`].
-    pub fn export_csv_selection(&self, selection: Selection) -> Result<String> {
+    pub fn export_csv_selection(&mut self, selection: Selection) -> Result<String> {
+        self.load_sheet_on_access(selection.sheet_id);
         let sheet = self
             .try_sheet(selection.sheet_id)
             .context("Sheet not found")?;
@@ -22,9 +43,10 @@ impl GridController {
             for x in bounds.min.x..=bounds.max.x {
                 // we need to ignore unselected columns or rows
                 if selection.rects.is_some() || selection.pos_in_selection(Pos { x, y }) {
-                    if let Some((_, value)) = iter.peeking_next(|(pos, _)| pos.x == x && pos.y == y)
+                    if let Some((pos, value)) =
+                        iter.peeking_next(|(pos, _)| pos.x == x && pos.y == y)
                     {
-                        line.push(value.to_string());
+                        line.push(sheet.display_string(*pos, value));
                     } else {
                         line.push("".to_string());
                     }
@@ -39,13 +61,139 @@ impl GridController {
 
         Ok(output)
     }
+
+    /// exports a selection on the grid as a Parquet file. The type of each
+    /// column is inferred from its values.
+    ///
+    /// Returns the bytes of the file.
+    pub fn export_parquet_selection(
+        &mut self,
+        selection: Selection,
+        options: ColumnarExportOptions,
+    ) -> Result<Vec<u8>> {
+        let (headers, columns) = self.selection_columns(&selection, options.header_row)?;
+        let batch = cell_values_to_record_batch(headers, columns)?;
+        record_batch_to_parquet(&batch, options.compression)
+    }
+
+    /// exports a selection on the grid as an Arrow IPC file. The type of each
+    /// column is inferred from its values.
+    ///
+    /// Returns the bytes of the file.
+    pub fn export_arrow_selection(
+        &mut self,
+        selection: Selection,
+        options: ColumnarExportOptions,
+    ) -> Result<Vec<u8>> {
+        let (headers, columns) = self.selection_columns(&selection, options.header_row)?;
+        let batch = cell_values_to_record_batch(headers, columns)?;
+        record_batch_to_arrow_ipc(&batch, options.compression)
+    }
+
+    /// exports a selection on the grid as JSON records keyed by the first
+    /// row of the selection. Dotted column names (`a.b`) become nested
+    /// objects. If `ndjson` is true, each record is written on its own line.
+    pub fn export_json_selection(&mut self, selection: Selection, ndjson: bool) -> Result<String> {
+        let (headers, columns) = self.selection_columns(&selection, true)?;
+        records_to_json(&headers, &columns, ndjson)
+    }
+
+    /// Returns the raw values of a selection by column, along with the name
+    /// of each column.
+    fn selection_columns(
+        &mut self,
+        selection: &Selection,
+        header_row: bool,
+    ) -> Result<(Vec<String>, Vec<Vec<CellValue>>)> {
+        self.load_sheet_on_access(selection.sheet_id);
+        let sheet = self
+            .try_sheet(selection.sheet_id)
+            .context("Sheet not found")?;
+        let bounds = sheet.selection_bounds(selection).context("No values")?;
+        let values = sheet
+            .selection_sorted_vec(selection, false)
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        // only selected columns (or rows) are exported, unless the selection
+        // spans them all
+        let spans_all = selection.all || selection.rects.is_some();
+        let columns = bounds
+            .x_range()
+            .filter(|x| {
+                spans_all
+                    || selection.rows.is_some()
+                    || selection.columns.as_ref().is_some_and(|c| c.contains(x))
+            })
+            .collect::<Vec<_>>();
+        let rows = bounds
+            .y_range()
+            .filter(|y| {
+                spans_all
+                    || selection.columns.is_some()
+                    || selection.rows.as_ref().is_some_and(|r| r.contains(y))
+            })
+            .collect::<Vec<_>>();
+
+        let value = |x, y| {
+            values
+                .get(&Pos { x, y })
+                .map_or(CellValue::Blank, |&value| value.clone())
+        };
+
+        let (header_y, rows) = match rows.split_first() {
+            Some((first, rest)) if header_row => (Some(*first), rest),
+            _ => (None, rows.as_slice()),
+        };
+
+        let mut names = HashSet::new();
+        let headers = columns
+            .iter()
+            .map(|&x| {
+                let name = match header_y.map(|y| value(x, y)) {
+                    Some(CellValue::Blank) | None => column_name(x),
+                    Some(header) => header.to_string(),
+                };
+                let mut unique = name.clone();
+                let mut i = 2;
+                while !names.insert(unique.clone()) {
+                    unique = format!("{name}_{i}");
+                    i += 1;
+                }
+                unique
+            })
+            .collect();
+
+        let columns = columns
+            .iter()
+            .map(|&x| rows.iter().map(|&y| value(x, y)).collect())
+            .collect();
+
+        Ok((headers, columns))
+    }
+
+    /// exports the whole grid as an XLSX file.
+    ///
+    /// Returns the bytes of the file.
+    pub fn export_excel(&self) -> Result<Vec<u8>> {
+        xlsx::write_grid(self.grid())
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::io::{Cursor, Read};
+
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use arrow_schema::DataType;
+
     use super::*;
-    use crate::Rect;
+    use crate::{
+        grid::{Bold, CodeCellLanguage, NumericFormat, NumericFormatKind, SheetId},
+        Rect, SheetPos,
+    };
 
     #[test]
     fn exports_a_csv() {
@@ -75,4 +223,281 @@ mod tests {
 
         assert_eq!(&result, expected);
     }
+
+    #[test]
+    fn exports_a_csv_with_custom_formats() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.test_set_value_number(0, 0, "1234.5");
+        sheet.test_set_value_number(1, 0, "-2");
+        sheet.set_formatting_value::<NumericFormat>(
+            Pos { x: 0, y: 0 },
+            Some(NumericFormat::custom("#,##0.00")),
+        );
+        sheet.set_formatting_value::<NumericFormat>(
+            Pos { x: 1, y: 0 },
+            Some(NumericFormat::custom("0.0;(0.0)")),
+        );
+        assert_eq!(
+            sheet
+                .format_cell(0, 0, false)
+                .numeric_format
+                .map(|format| format.kind),
+            Some(NumericFormatKind::Custom)
+        );
+
+        let selected = Selection {
+            sheet_id,
+            rects: Some(vec![Rect::from_numbers(0, 0, 2, 1)]),
+            ..Default::default()
+        };
+        let result = gc.export_csv_selection(selected).unwrap();
+        assert_eq!(&result, "\"1,234.50\",(2.0)\n");
+    }
+
+    #[test]
+    fn exports_sheets_that_are_not_loaded() {
+        let mut gc = GridController::test();
+        gc.add_sheet(None);
+        let sheet_id = gc.sheet_ids()[1];
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "1".to_string(), None);
+        let file = crate::grid::file::export_binary(gc.grid_mut()).unwrap();
+
+        let mut gc =
+            GridController::from_grid(crate::grid::file::import_lazy(&file, None).unwrap(), 0);
+        assert_eq!(gc.grid().unloaded_sheet_ids(), vec![sheet_id]);
+
+        let selected = Selection {
+            sheet_id,
+            rects: Some(vec![Rect::from_numbers(0, 0, 1, 1)]),
+            ..Default::default()
+        };
+        assert_eq!(gc.export_csv_selection(selected.clone()).unwrap(), "1\n");
+        assert!(gc.grid().unloaded_sheet_ids().is_empty());
+
+        let (_, plain_text, _) = gc.cut_to_clipboard_operations(&selected).unwrap();
+        assert_eq!(plain_text, "1");
+    }
+
+    #[test]
+    fn exports_json_records() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let file = r#"[{"name":"a","meta":{"id":1}},{"name":"b","meta":{"id":2.5}}]"#;
+        gc.import_json(
+            sheet_id,
+            file.as_bytes(),
+            "records.json",
+            Pos { x: 0, y: 0 },
+            None,
+        )
+        .unwrap();
+
+        let selected = Selection {
+            sheet_id,
+            rects: Some(vec![Rect::from_numbers(0, 0, 2, 3)]),
+            ..Default::default()
+        };
+        let result = gc.export_json_selection(selected.clone(), true).unwrap();
+        assert_eq!(
+            result,
+            "{\"name\":\"a\",\"meta\":{\"id\":1}}\n{\"name\":\"b\",\"meta\":{\"id\":2.5}}\n"
+        );
+
+        let result = gc.export_json_selection(selected, false).unwrap();
+        let records: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            records,
+            serde_json::from_str::<serde_json::Value>(file).unwrap()
+        );
+    }
+
+    #[test]
+    fn exports_an_excel_file() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_sheet_name(sheet_id, "Sales".to_string(), None);
+        gc.set_sheet_color(sheet_id, Some("#ff0000".to_string()), None);
+        gc.add_sheet_with_name("Other data".to_string(), None);
+        let other_id = gc.sheet_ids()[1];
+
+        let sheet_pos = |x, y, sheet_id| SheetPos { x, y, sheet_id };
+        gc.set_cell_value(sheet_pos(0, 0, sheet_id), "1".to_string(), None);
+        gc.set_cell_value(sheet_pos(1, 0, sheet_id), "a & b".to_string(), None);
+        gc.set_cell_value(sheet_pos(0, 0, other_id), "5".to_string(), None);
+        gc.set_code_cell(
+            sheet_pos(0, 1, sheet_id),
+            CodeCellLanguage::Formula,
+            "A0 + 'Other data'!A0".to_string(),
+            None,
+        );
+
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.set_formatting_value::<Bold>(Pos { x: 1, y: 0 }, Some(true));
+        sheet.offsets.set_column_width(0, 200.0);
+
+        let file = gc.export_excel().unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(&file)).unwrap();
+        let mut read_part = |path: &str| {
+            let mut xml = String::new();
+            archive
+                .by_name(path)
+                .unwrap()
+                .read_to_string(&mut xml)
+                .unwrap();
+            xml
+        };
+        let workbook = read_part("xl/workbook.xml");
+        assert!(workbook.find("Sales").unwrap() < workbook.find("Other data").unwrap());
+
+        let worksheet = read_part("xl/worksheets/sheet1.xml");
+        assert!(worksheet.contains(r#"<tabColor rgb="FFFF0000"/>"#));
+        assert!(worksheet.contains(r#"<col min="1" max="1" width="27.86" customWidth="1"/>"#));
+        assert!(worksheet.contains(r#"<c r="A2"><f>A1 + 'Other data'!A1</f><v>6</v></c>"#));
+        assert!(worksheet.contains(
+            r#"<c r="B1" s="1" t="inlineStr"><is><t xml:space="preserve">a &amp; b</t></is></c>"#
+        ));
+        assert!(read_part("xl/styles.xml").contains("<font><b/>"));
+
+        // round trip through the Excel importer
+        let mut imported = GridController::test_blank();
+        imported.import_excel(file, "export.xlsx").unwrap();
+        let sheet = imported.grid().sheets()[0].clone();
+        assert_eq!(sheet.name, "Sales");
+        assert_eq!(
+            sheet.display_value(Pos { x: 0, y: 1 }),
+            Some(CellValue::Number(1.into()))
+        );
+        assert_eq!(
+            sheet.display_value(Pos { x: 0, y: 2 }),
+            Some(CellValue::Number(6.into()))
+        );
+        assert_eq!(
+            sheet.display_value(Pos { x: 1, y: 1 }),
+            Some(CellValue::Text("a & b".to_string()))
+        );
+    }
+
+    fn set_columnar_test_values(gc: &mut GridController) -> SheetId {
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_values(
+            SheetPos {
+                x: 0,
+                y: 0,
+                sheet_id,
+            },
+            vec![
+                vec!["name", "count", "when", "ok"],
+                vec!["a", "1", "2024-01-15", "true"],
+                vec!["b", "2.5", "2024-01-16", ""],
+            ],
+            None,
+        );
+        sheet_id
+    }
+
+    #[test]
+    fn exports_a_parquet_file() {
+        let mut gc = GridController::test();
+        let sheet_id = set_columnar_test_values(&mut gc);
+
+        let selection = Selection {
+            sheet_id,
+            rects: Some(vec![Rect::from_numbers(0, 0, 3, 3)]),
+            ..Default::default()
+        };
+        let options = ColumnarExportOptions {
+            header_row: true,
+            compression: ExportCompression::Snappy,
+        };
+        let file = gc.export_parquet_selection(selection, options).unwrap();
+
+        let values = crate::parquet::parquet_to_vec(file).unwrap();
+        let values = values
+            .iter()
+            .map(|row| row.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                vec!["name", "count", "when"],
+                vec!["a", "1", "2024-01-15"],
+                vec!["b", "2.5", "2024-01-16"],
+            ]
+        );
+    }
+
+    #[test]
+    fn exports_an_arrow_file() {
+        let mut gc = GridController::test();
+        let sheet_id = set_columnar_test_values(&mut gc);
+
+        let selection = Selection {
+            sheet_id,
+            columns: Some(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        let file = gc
+            .export_arrow_selection(selection, ColumnarExportOptions::default())
+            .unwrap();
+
+        let mut reader = FileReader::try_new(Cursor::new(file), None).unwrap();
+        let schema = reader.schema();
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("B", DataType::Utf8),
+                ("C", DataType::Utf8),
+                ("D", DataType::Utf8),
+            ]
+        );
+
+        let options = ColumnarExportOptions {
+            header_row: true,
+            compression: ExportCompression::Lz4,
+        };
+        let selection = Selection {
+            sheet_id,
+            columns: Some(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        let file = gc.export_arrow_selection(selection, options).unwrap();
+        let mut reader = FileReader::try_new(Cursor::new(file), None).unwrap();
+        let schema = reader.schema();
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("count", DataType::Float64),
+                ("when", DataType::Date32),
+                ("ok", DataType::Boolean),
+            ]
+        );
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert!(batch.column(2).is_null(1));
+
+        let options = ColumnarExportOptions {
+            header_row: true,
+            compression: ExportCompression::Gzip,
+        };
+        let selection = Selection {
+            sheet_id,
+            all: true,
+            ..Default::default()
+        };
+        assert!(gc.export_arrow_selection(selection, options).is_err());
+    }
 }
@@ -1,8 +1,13 @@
-use self::{active_transactions::ActiveTransactions, transaction::Transaction};
+use self::{
+    active_transactions::ActiveTransactions,
+    operations::import::{ImportCancellation, ImportLimits},
+    transaction::Transaction,
+};
 use crate::grid::Grid;
 use wasm_bindgen::prelude::*;
 pub mod active_transactions;
 pub mod dependencies;
+pub mod edit_history;
 pub mod execution;
 pub mod export;
 pub mod formula;
@@ -25,6 +30,13 @@ pub struct GridController {
 
     // holds information about transactions in progress
     transactions: ActiveTransactions,
+
+    // limits and cancellation of imports
+    import_limits: ImportLimits,
+    import_cancellation: ImportCancellation,
+
+    // the user making changes, for checking protections (None if unknown)
+    user_id: Option<String>,
 }
 
 impl GridController {
@@ -52,6 +64,26 @@ impl GridController {
         &mut self.grid
     }
 
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    /// Sets the user making changes. Protected sheets are sent to the client
+    /// again since which protections are locked depends on the user.
+    pub fn set_user_id(&mut self, user_id: Option<String>) {
+        self.user_id = user_id;
+        let protected = self
+            .grid
+            .sheets()
+            .iter()
+            .filter(|sheet| !sheet.protections.is_empty())
+            .map(|sheet| sheet.id)
+            .collect::<Vec<_>>();
+        for sheet_id in protected {
+            self.send_sheet_info(sheet_id);
+        }
+    }
+
     // create a new gc for testing purposes in both Rust and TS
     pub fn test() -> Self {
         Self::from_grid(Grid::new(), 0)
@@ -4,7 +4,7 @@ use crate::{
     controller::GridController,
     grid::{formatting::CellFmtArray, NumericDecimals, NumericFormat, NumericFormatKind},
     selection::Selection,
-    CellValue, RunLengthEncoding, SheetPos, SheetRect,
+    CellValue, DateLocale, RunLengthEncoding, SheetPos, SheetRect,
 };
 use bigdecimal::BigDecimal;
 use std::str::FromStr;
@@ -83,12 +83,31 @@ impl GridController {
                 )),
             });
             CellValue::Number(percent)
+        } else if let Some(date_time) =
+            CellValue::unpack_date_time(value, self.date_locale(sheet_pos))
+        {
+            date_time
         } else {
             CellValue::Text(value.into())
         };
         (ops, cell_value)
     }
 
+    /// Returns the locale used to read dates entered at a position. This is
+    /// the locale of the cell's date format, if it has one.
+    fn date_locale(&self, sheet_pos: SheetPos) -> DateLocale {
+        self.try_sheet(sheet_pos.sheet_id)
+            .and_then(|sheet| sheet.get_formatting_value::<NumericFormat>(sheet_pos.into()))
+            .filter(|format| {
+                matches!(
+                    format.kind,
+                    NumericFormatKind::Date | NumericFormatKind::Time | NumericFormatKind::DateTime
+                )
+            })
+            .and_then(|format| format.symbol.as_deref().and_then(DateLocale::from_tag))
+            .unwrap_or_default()
+    }
+
     /// Generate operations for a user-initiated change to a cell value
     pub fn set_cell_value_operations(
         &mut self,
@@ -148,9 +167,9 @@ mod test {
     use crate::{
         cell_values::CellValues,
         controller::{operations::operation::Operation, GridController},
-        grid::{CodeCellLanguage, SheetId},
+        grid::{CodeCellLanguage, NumericFormatKind, SheetId},
         selection::Selection,
-        CellValue, Rect, SheetPos,
+        CellValue, Duration, Instant, Rect, SheetPos,
     };
 
     #[test]
@@ -350,4 +369,36 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn date_time_to_cell_value() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let sheet_pos = SheetPos {
+            x: 1,
+            y: 2,
+            sheet_id,
+        };
+
+        let (ops, value) = gc.string_to_cell_value(sheet_pos, "2024-01-15");
+        assert_eq!(ops.len(), 0);
+        assert_eq!(value, CellValue::Instant(Instant::from_serial(45306.0)));
+
+        let (_, value) = gc.string_to_cell_value(sheet_pos, "1/15/2024 1:30 PM");
+        assert_eq!(value, CellValue::Instant(Instant::from_serial(45306.5625)));
+
+        let (_, value) = gc.string_to_cell_value(sheet_pos, "1:30:15");
+        assert_eq!(value, CellValue::Duration(Duration::from_seconds(5415.0)));
+
+        // dates are read using the locale of the cell's date format
+        gc.set_numeric_format_selection(
+            Selection::pos(1, 2, sheet_id),
+            NumericFormatKind::Date,
+            Some("en-GB".to_string()),
+            None,
+        )
+        .unwrap();
+        let (_, value) = gc.string_to_cell_value(sheet_pos, "2/1/2024");
+        assert_eq!(value, CellValue::Instant(Instant::from_serial(45323.0)));
+    }
 }
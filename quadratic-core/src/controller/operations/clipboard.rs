@@ -65,6 +65,7 @@ impl GridController {
         &mut self,
         selection: &Selection,
     ) -> Result<(Vec<Operation>, String, String), String> {
+        self.load_sheet_on_access(selection.sheet_id);
         let sheet = self
             .try_sheet(selection.sheet_id)
             .ok_or("Unable to find Sheet")?;
@@ -128,6 +128,29 @@ impl GridController {
             .collect()
     }
 
+    /// Reruns the formula cells in all Sheets. Python and Javascript cells
+    /// keep their last results.
+    pub fn rerun_all_formula_cells_operations(&self) -> Vec<Operation> {
+        self.rerun_all_code_cells_operations()
+            .into_iter()
+            .filter(|op| match op {
+                Operation::ComputeCode { sheet_pos } => self
+                    .try_sheet(sheet_pos.sheet_id)
+                    .and_then(|sheet| sheet.cell_value((*sheet_pos).into()))
+                    .is_some_and(|value| {
+                        matches!(
+                            value,
+                            CellValue::Code(CodeCellValue {
+                                language: CodeCellLanguage::Formula,
+                                ..
+                            })
+                        )
+                    }),
+                _ => false,
+            })
+            .collect()
+    }
+
     /// Reruns a code cell
     pub fn rerun_code_cell_operations(&self, sheet_pos: SheetPos) -> Vec<Operation> {
         vec![Operation::ComputeCode { sheet_pos }]
@@ -376,4 +399,36 @@ mod test {
         gc.rerun_code_cell(sheet_pos, None);
         gc.rerun_sheet_code_cells(sheet_id, None);
     }
+
+    #[test]
+    fn rerun_all_formula_cells_operations() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let formula = SheetPos::new(sheet_id, 0, 0);
+        gc.set_code_cell(
+            formula,
+            CodeCellLanguage::Formula,
+            "1 + 1".to_string(),
+            None,
+        );
+
+        // a Python cell with a previous result
+        let sheet = gc.sheet_mut(sheet_id);
+        let python = Pos { x: 1, y: 0 };
+        sheet.set_cell_value(
+            python,
+            CellValue::Code(CodeCellValue {
+                language: CodeCellLanguage::Python,
+                code: "2".to_string(),
+            }),
+        );
+        let code_run = sheet.code_run(formula.into()).unwrap().clone();
+        sheet.set_code_run(python, Some(code_run));
+
+        assert_eq!(gc.rerun_all_code_cells_operations().len(), 2);
+        assert_eq!(
+            gc.rerun_all_formula_cells_operations(),
+            vec![Operation::ComputeCode { sheet_pos: formula }]
+        );
+    }
 }
@@ -0,0 +1,839 @@
+//! Compares two grids, listing what changed between them and producing the
+//! operations that turn one into the other. Two copies of a grid can also be
+//! merged with the base they both started from; changes that both copies made
+//! differently are reported as conflicts.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use crate::{
+    cell_values::CellValues,
+    grid::{
+        file::sheet_schema::export_sheet,
+        formats::{format::Format, Formats},
+        CodeRun, Grid, Sheet, SheetBorders, SheetId,
+    },
+    selection::Selection,
+    CellValue, IsBlank, Pos, Rect, SheetPos, SheetRect,
+};
+
+use super::operation::Operation;
+
+/// What a format change applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FormatTarget {
+    All,
+    Column(i64),
+    Row(i64),
+    Cell(Pos),
+}
+
+/// A single difference between two grids.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridChange {
+    AddSheet {
+        sheet: Box<Sheet>,
+    },
+    DeleteSheet {
+        sheet_id: SheetId,
+        name: String,
+    },
+    RenameSheet {
+        sheet_id: SheetId,
+        from: String,
+        to: String,
+    },
+    SetSheetColor {
+        sheet_id: SheetId,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    ReorderSheet {
+        sheet_id: SheetId,
+        from: String,
+        to: String,
+    },
+
+    /// A cell's value or code changed, or its code has a different result.
+    SetCell {
+        sheet_pos: SheetPos,
+        from: Option<CellValue>,
+        to: Option<CellValue>,
+        code_run: Option<Box<CodeRun>>,
+        index: usize,
+    },
+    SetFormat {
+        sheet_id: SheetId,
+        target: FormatTarget,
+        from: Format,
+        to: Format,
+    },
+
+    /// Borders within `sheet_rect` changed. `borders` are the new borders.
+    SetBorders {
+        sheet_rect: SheetRect,
+        borders: SheetBorders,
+    },
+    ResizeColumn {
+        sheet_id: SheetId,
+        column: i64,
+        from: f64,
+        to: f64,
+    },
+    ResizeRow {
+        sheet_id: SheetId,
+        row: i64,
+        from: f64,
+        to: f64,
+    },
+}
+
+/// Identifies what a change modifies, to find changes that conflict.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum ChangeKey {
+    Sheet(SheetId),
+    SheetName(SheetId),
+    SheetColor(SheetId),
+    SheetOrder(SheetId),
+    Cell(SheetPos),
+    Format(SheetId, FormatTarget),
+    Borders(SheetId),
+    Column(SheetId, i64),
+    Row(SheetId, i64),
+}
+
+impl GridChange {
+    /// Returns the sheet that the change is in.
+    pub fn sheet_id(&self) -> SheetId {
+        match self {
+            GridChange::AddSheet { sheet } => sheet.id,
+            GridChange::SetCell { sheet_pos, .. } => sheet_pos.sheet_id,
+            GridChange::SetBorders { sheet_rect, .. } => sheet_rect.sheet_id,
+            GridChange::DeleteSheet { sheet_id, .. }
+            | GridChange::RenameSheet { sheet_id, .. }
+            | GridChange::SetSheetColor { sheet_id, .. }
+            | GridChange::ReorderSheet { sheet_id, .. }
+            | GridChange::SetFormat { sheet_id, .. }
+            | GridChange::ResizeColumn { sheet_id, .. }
+            | GridChange::ResizeRow { sheet_id, .. } => *sheet_id,
+        }
+    }
+
+    fn key(&self) -> ChangeKey {
+        let sheet_id = self.sheet_id();
+        match self {
+            GridChange::AddSheet { .. } | GridChange::DeleteSheet { .. } => {
+                ChangeKey::Sheet(sheet_id)
+            }
+            GridChange::RenameSheet { .. } => ChangeKey::SheetName(sheet_id),
+            GridChange::SetSheetColor { .. } => ChangeKey::SheetColor(sheet_id),
+            GridChange::ReorderSheet { .. } => ChangeKey::SheetOrder(sheet_id),
+            GridChange::SetCell { sheet_pos, .. } => ChangeKey::Cell(*sheet_pos),
+            GridChange::SetFormat { target, .. } => ChangeKey::Format(sheet_id, *target),
+            GridChange::SetBorders { .. } => ChangeKey::Borders(sheet_id),
+            GridChange::ResizeColumn { column, .. } => ChangeKey::Column(sheet_id, *column),
+            GridChange::ResizeRow { row, .. } => ChangeKey::Row(sheet_id, *row),
+        }
+    }
+
+    /// Returns whether two changes to the same thing have the same result.
+    fn same_result(&self, other: &GridChange) -> bool {
+        match (self, other) {
+            (GridChange::AddSheet { sheet: a }, GridChange::AddSheet { sheet: b }) => a == b,
+            (GridChange::DeleteSheet { .. }, GridChange::DeleteSheet { .. }) => true,
+            (GridChange::RenameSheet { to: a, .. }, GridChange::RenameSheet { to: b, .. })
+            | (GridChange::ReorderSheet { to: a, .. }, GridChange::ReorderSheet { to: b, .. }) => {
+                a == b
+            }
+            (GridChange::SetSheetColor { to: a, .. }, GridChange::SetSheetColor { to: b, .. }) => {
+                a == b
+            }
+            (
+                GridChange::SetCell {
+                    to: a,
+                    code_run: a_run,
+                    ..
+                },
+                GridChange::SetCell {
+                    to: b,
+                    code_run: b_run,
+                    ..
+                },
+            ) => a == b && same_code_run(a_run.as_deref(), b_run.as_deref()),
+            (GridChange::SetFormat { to: a, .. }, GridChange::SetFormat { to: b, .. }) => a == b,
+            (GridChange::SetBorders { .. }, GridChange::SetBorders { .. }) => self == other,
+            (GridChange::ResizeColumn { to: a, .. }, GridChange::ResizeColumn { to: b, .. })
+            | (GridChange::ResizeRow { to: a, .. }, GridChange::ResizeRow { to: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for GridChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |value: &Option<CellValue>| {
+            value
+                .as_ref()
+                .map_or("(empty)".to_string(), |value| match value {
+                    CellValue::Code(code) => format!("={}", code.code),
+                    value => value.to_string(),
+                })
+        };
+        let format = |format: &Format| match format.to_string().trim_end_matches(", ") {
+            "" => "(none)".to_string(),
+            format => format.to_string(),
+        };
+        match self {
+            GridChange::AddSheet { sheet } => write!(f, "added sheet {}", sheet.name),
+            GridChange::DeleteSheet { name, .. } => write!(f, "deleted sheet {name}"),
+            GridChange::RenameSheet { from, to, .. } => {
+                write!(f, "renamed sheet {from} to {to}")
+            }
+            GridChange::SetSheetColor { from, to, .. } => write!(
+                f,
+                "changed sheet color from {} to {}",
+                from.as_deref().unwrap_or("none"),
+                to.as_deref().unwrap_or("none")
+            ),
+            GridChange::ReorderSheet { .. } => write!(f, "moved sheet"),
+            GridChange::SetCell {
+                sheet_pos,
+                from,
+                to,
+                ..
+            } => {
+                let pos = Pos::from(*sheet_pos).a1_string();
+                if from == to {
+                    write!(f, "{pos}: changed the result of {}", value(to))
+                } else {
+                    write!(f, "{pos}: {} -> {}", value(from), value(to))
+                }
+            }
+            GridChange::SetFormat {
+                target, from, to, ..
+            } => {
+                match target {
+                    FormatTarget::All => write!(f, "sheet format")?,
+                    FormatTarget::Column(x) => {
+                        write!(f, "column {} format", crate::util::column_name(*x))?
+                    }
+                    FormatTarget::Row(y) => write!(f, "row {y} format")?,
+                    FormatTarget::Cell(pos) => write!(f, "{} format", pos.a1_string())?,
+                }
+                write!(f, ": {} -> {}", format(from), format(to))
+            }
+            GridChange::SetBorders { sheet_rect, .. } => write!(
+                f,
+                "borders changed in {}:{}",
+                sheet_rect.min.a1_string(),
+                sheet_rect.max.a1_string()
+            ),
+            GridChange::ResizeColumn {
+                column, from, to, ..
+            } => write!(
+                f,
+                "column {} width: {from} -> {to}",
+                crate::util::column_name(*column)
+            ),
+            GridChange::ResizeRow { row, from, to, .. } => {
+                write!(f, "row {row} height: {from} -> {to}")
+            }
+        }
+    }
+}
+
+/// Compares code runs, ignoring when they were run.
+fn same_code_run(a: Option<&CodeRun>, b: Option<&CodeRun>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a == &CodeRun {
+                last_modified: a.last_modified,
+                ..b.clone()
+            }
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Returns the changes that turn `from` into `to`.
+pub fn diff_grids(from: &Grid, to: &Grid) -> Vec<GridChange> {
+    let mut changes = vec![];
+
+    // new sheets are added first so there is always at least one sheet
+    for sheet in to.sheets() {
+        if from.try_sheet(sheet.id).is_none() {
+            changes.push(GridChange::AddSheet {
+                sheet: Box::new(sheet.clone()),
+            });
+        }
+    }
+    for from_sheet in from.sheets() {
+        if let Some(to_sheet) = to.try_sheet(from_sheet.id) {
+            diff_sheets(from_sheet, to_sheet, &mut changes);
+        }
+    }
+    for sheet in from.sheets() {
+        if to.try_sheet(sheet.id).is_none() {
+            changes.push(GridChange::DeleteSheet {
+                sheet_id: sheet.id,
+                name: sheet.name.clone(),
+            });
+        }
+    }
+    changes
+}
+
+fn diff_sheets(from: &Sheet, to: &Sheet, changes: &mut Vec<GridChange>) {
+    let sheet_id = from.id;
+    if from.name != to.name {
+        changes.push(GridChange::RenameSheet {
+            sheet_id,
+            from: from.name.clone(),
+            to: to.name.clone(),
+        });
+    }
+    if from.color != to.color {
+        changes.push(GridChange::SetSheetColor {
+            sheet_id,
+            from: from.color.clone(),
+            to: to.color.clone(),
+        });
+    }
+    if from.order != to.order {
+        changes.push(GridChange::ReorderSheet {
+            sheet_id,
+            from: from.order.clone(),
+            to: to.order.clone(),
+        });
+    }
+
+    // cell values and code
+    let cells = [from, to]
+        .into_iter()
+        .flat_map(|sheet| {
+            sheet
+                .columns
+                .iter()
+                .flat_map(|(x, column)| column.values.keys().map(move |y| Pos { x: *x, y: *y }))
+                .chain(sheet.code_runs.keys().copied())
+        })
+        .collect::<BTreeSet<_>>();
+    for pos in cells {
+        let from_value = from.cell_value(pos).filter(|value| !value.is_blank());
+        let to_value = to.cell_value(pos).filter(|value| !value.is_blank());
+        let to_code_run = to.code_run(pos);
+        if from_value != to_value || !same_code_run(from.code_run(pos), to_code_run) {
+            changes.push(GridChange::SetCell {
+                sheet_pos: pos.to_sheet_pos(sheet_id),
+                from: from_value,
+                to: to_value,
+                code_run: to_code_run.cloned().map(Box::new),
+                index: to.code_runs.get_index_of(&pos).unwrap_or_default(),
+            });
+        }
+    }
+
+    // formats, from the widest to the narrowest
+    let mut format_change = |target, from_format: Format, to_format: Format| {
+        if from_format != to_format {
+            changes.push(GridChange::SetFormat {
+                sheet_id,
+                target,
+                from: from_format,
+                to: to_format,
+            });
+        }
+    };
+    format_change(FormatTarget::All, from.format_all(), to.format_all());
+    let columns = from
+        .formats_columns
+        .keys()
+        .chain(to.formats_columns.keys())
+        .collect::<BTreeSet<_>>();
+    for x in columns {
+        format_change(
+            FormatTarget::Column(*x),
+            from.format_column(*x),
+            to.format_column(*x),
+        );
+    }
+    let rows = from
+        .formats_rows
+        .keys()
+        .chain(to.formats_rows.keys())
+        .collect::<BTreeSet<_>>();
+    for y in rows {
+        format_change(
+            FormatTarget::Row(*y),
+            from.format_row(*y),
+            to.format_row(*y),
+        );
+    }
+    let cells = formatted_cells(from)
+        .into_iter()
+        .chain(formatted_cells(to))
+        .collect::<BTreeSet<_>>();
+    for pos in cells {
+        format_change(
+            FormatTarget::Cell(pos),
+            from.format_cell(pos.x, pos.y, false),
+            to.format_cell(pos.x, pos.y, false),
+        );
+    }
+
+    // borders
+    let borders_rect = match (borders_rect(from), borders_rect(to)) {
+        (Some(a), Some(b)) => Some(a.union(&b)),
+        (a, b) => a.or(b),
+    };
+    if let Some(rect) = borders_rect {
+        let borders = to.get_rect_borders(rect);
+        if from.get_rect_borders(rect) != borders {
+            changes.push(GridChange::SetBorders {
+                sheet_rect: rect.to_sheet_rect(sheet_id),
+                borders,
+            });
+        }
+    }
+
+    // column widths and row heights
+    let (from_columns, from_rows) = from.offsets.export();
+    let (to_columns, to_rows) = to.offsets.export();
+    let columns = from_columns
+        .iter()
+        .chain(&to_columns)
+        .map(|(x, _)| *x)
+        .collect::<BTreeSet<_>>();
+    for column in columns {
+        let (from_width, to_width) = (
+            from.offsets.column_width(column),
+            to.offsets.column_width(column),
+        );
+        if from_width != to_width {
+            changes.push(GridChange::ResizeColumn {
+                sheet_id,
+                column,
+                from: from_width,
+                to: to_width,
+            });
+        }
+    }
+    let rows = from_rows
+        .iter()
+        .chain(&to_rows)
+        .map(|(y, _)| *y)
+        .collect::<BTreeSet<_>>();
+    for row in rows {
+        let (from_height, to_height) = (from.offsets.row_height(row), to.offsets.row_height(row));
+        if from_height != to_height {
+            changes.push(GridChange::ResizeRow {
+                sheet_id,
+                row,
+                from: from_height,
+                to: to_height,
+            });
+        }
+    }
+}
+
+/// Returns the cells that have their own format.
+fn formatted_cells(sheet: &Sheet) -> Vec<Pos> {
+    sheet
+        .format_selection(&Selection::all(sheet.id))
+        .into_iter()
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+/// Returns the rect that contains all of a sheet's borders.
+fn borders_rect(sheet: &Sheet) -> Option<Rect> {
+    sheet
+        .borders()
+        .per_cell
+        .borders
+        .iter()
+        .filter_map(|(x, column)| {
+            let range = column.range()?;
+            Some(Rect::new_span(
+                Pos {
+                    x: *x,
+                    y: range.start,
+                },
+                Pos {
+                    x: *x,
+                    y: range.end - 1,
+                },
+            ))
+        })
+        .reduce(|a, b| a.union(&b))
+}
+
+/// Returns the operations that turn `from` into `to`.
+pub fn diff_operations(from: &Grid, to: &Grid) -> Vec<Operation> {
+    change_operations(&diff_grids(from, to), to, &HashSet::new())
+}
+
+/// Converts changes into operations. `to` is the grid the changes came from.
+///
+/// Changing the format of a sheet, column, or row clears the formats of the
+/// cells it covers, so the formats of `to`'s cells in those sheets are set
+/// again afterwards, except for the cells in `keep_cell_formats`.
+///
+/// Added sheets get a suffix if their name is still in use by a sheet that is
+/// renamed or deleted later, so their names are set again at the end.
+fn change_operations(
+    changes: &[GridChange],
+    to: &Grid,
+    keep_cell_formats: &HashSet<SheetPos>,
+) -> Vec<Operation> {
+    let mut ops = vec![];
+    let mut cell_formats: HashMap<SheetId, Vec<(Pos, Format)>> = HashMap::new();
+    let mut reset_cell_formats = HashSet::new();
+    let mut sheet_names = vec![];
+    for change in changes {
+        match change {
+            GridChange::AddSheet { sheet } => {
+                ops.push(Operation::AddSheetSchema {
+                    schema: export_sheet(sheet),
+                });
+                sheet_names.push(Operation::SetSheetName {
+                    sheet_id: sheet.id,
+                    name: sheet.name.clone(),
+                });
+            }
+            GridChange::DeleteSheet { sheet_id, .. } => {
+                ops.push(Operation::DeleteSheet {
+                    sheet_id: *sheet_id,
+                });
+            }
+            GridChange::RenameSheet { sheet_id, to, .. } => ops.push(Operation::SetSheetName {
+                sheet_id: *sheet_id,
+                name: to.clone(),
+            }),
+            GridChange::SetSheetColor { sheet_id, to, .. } => {
+                ops.push(Operation::SetSheetColor {
+                    sheet_id: *sheet_id,
+                    color: to.clone(),
+                });
+            }
+            GridChange::ReorderSheet { sheet_id, to, .. } => ops.push(Operation::ReorderSheet {
+                target: *sheet_id,
+                order: to.clone(),
+            }),
+            GridChange::SetCell {
+                sheet_pos,
+                from,
+                to,
+                code_run,
+                index,
+            } => {
+                if from != to {
+                    ops.push(Operation::SetCellValues {
+                        sheet_pos: *sheet_pos,
+                        values: CellValues::from(to.clone().unwrap_or(CellValue::Blank)),
+                    });
+                }
+                if matches!(to, Some(CellValue::Code(_))) {
+                    ops.push(Operation::SetCodeRun {
+                        sheet_pos: *sheet_pos,
+                        code_run: code_run.as_deref().cloned(),
+                        index: *index,
+                    });
+                }
+            }
+            GridChange::SetFormat {
+                sheet_id,
+                target,
+                to,
+                ..
+            } => {
+                let selection = match target {
+                    FormatTarget::All => Selection::all(*sheet_id),
+                    FormatTarget::Column(x) => Selection::columns(&[*x], *sheet_id),
+                    FormatTarget::Row(y) => Selection::rows(&[*y], *sheet_id),
+                    FormatTarget::Cell(pos) => {
+                        cell_formats
+                            .entry(*sheet_id)
+                            .or_default()
+                            .push((*pos, to.clone()));
+                        continue;
+                    }
+                };
+                reset_cell_formats.insert(*sheet_id);
+                ops.push(Operation::SetCellFormatsSelection {
+                    selection,
+                    formats: Formats::repeat(to.to_replace(), 1),
+                });
+            }
+            GridChange::SetBorders {
+                sheet_rect,
+                borders,
+            } => ops.push(Operation::SetBorders {
+                sheet_rect: *sheet_rect,
+                borders: borders.clone(),
+            }),
+            GridChange::ResizeColumn {
+                sheet_id,
+                column,
+                to,
+                ..
+            } => ops.push(Operation::ResizeColumn {
+                sheet_id: *sheet_id,
+                column: *column,
+                new_size: *to,
+                client_resized: false,
+            }),
+            GridChange::ResizeRow {
+                sheet_id, row, to, ..
+            } => ops.push(Operation::ResizeRow {
+                sheet_id: *sheet_id,
+                row: *row,
+                new_size: *to,
+                client_resized: false,
+            }),
+        }
+    }
+
+    for sheet_id in reset_cell_formats {
+        let Some(sheet) = to.try_sheet(sheet_id) else {
+            continue;
+        };
+        let formats = cell_formats.entry(sheet_id).or_default();
+        for pos in formatted_cells(sheet) {
+            if !keep_cell_formats.contains(&pos.to_sheet_pos(sheet_id))
+                && !formats.iter().any(|(p, _)| *p == pos)
+            {
+                formats.push((pos, sheet.format_cell(pos.x, pos.y, false)));
+            }
+        }
+    }
+    for (sheet_id, cells) in cell_formats {
+        if cells.is_empty() {
+            continue;
+        }
+        let mut formats = Formats::default();
+        for (_, format) in &cells {
+            formats.push(format.to_replace());
+        }
+        ops.push(Operation::SetCellFormatsSelection {
+            selection: Selection {
+                sheet_id,
+                rects: Some(
+                    cells
+                        .iter()
+                        .map(|(pos, _)| Rect::single_pos(*pos))
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            formats,
+        });
+    }
+    ops.extend(sheet_names);
+    ops
+}
+
+/// A change that both copies of a grid made differently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub ours: GridChange,
+    pub theirs: GridChange,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridMerge {
+    /// Operations that add their changes to our grid.
+    pub operations: Vec<Operation>,
+
+    /// Changes that were not merged. Our changes are kept.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Merges the changes that `theirs` made since `base` into `ours`.
+pub fn merge_grids(base: &Grid, ours: &Grid, theirs: &Grid) -> GridMerge {
+    let our_changes = diff_grids(base, ours);
+    let their_changes = diff_grids(base, theirs);
+
+    let ours_by_key = our_changes
+        .iter()
+        .map(|change| (change.key(), change))
+        .collect::<HashMap<_, _>>();
+    let mut ours_by_sheet: HashMap<SheetId, &GridChange> = HashMap::new();
+    for change in &our_changes {
+        ours_by_sheet.entry(change.sheet_id()).or_insert(change);
+    }
+    let ours_deleted = our_changes
+        .iter()
+        .filter(|change| matches!(change, GridChange::DeleteSheet { .. }))
+        .map(|change| (change.sheet_id(), change))
+        .collect::<HashMap<_, _>>();
+
+    let mut merged = vec![];
+    let mut conflicts = vec![];
+    for change in their_changes {
+        let conflict = if let Some(ours) = ours_by_key.get(&change.key()) {
+            if ours.same_result(&change) {
+                continue;
+            }
+            Some(*ours)
+        } else if let Some(ours) = ours_deleted.get(&change.sheet_id()) {
+            // we deleted the sheet that they changed
+            Some(*ours)
+        } else if matches!(change, GridChange::DeleteSheet { .. }) {
+            // they deleted a sheet that we changed
+            ours_by_sheet.get(&change.sheet_id()).copied()
+        } else {
+            None
+        };
+        match conflict {
+            Some(ours) => conflicts.push(MergeConflict {
+                ours: ours.clone(),
+                theirs: change,
+            }),
+            None => merged.push(change),
+        }
+    }
+
+    // keep our cell formats where they differ from the base
+    let keep_cell_formats = our_changes
+        .iter()
+        .filter_map(|change| match change {
+            GridChange::SetFormat {
+                sheet_id,
+                target: FormatTarget::Cell(pos),
+                ..
+            } => Some(pos.to_sheet_pos(*sheet_id)),
+            _ => None,
+        })
+        .collect();
+    GridMerge {
+        operations: change_operations(&merged, theirs, &keep_cell_formats),
+        conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        controller::{active_transactions::transaction_name::TransactionName, GridController},
+        grid::CodeCellLanguage,
+    };
+
+    fn apply(grid: &Grid, operations: Vec<Operation>) -> GridController {
+        let mut gc = GridController::from_grid(grid.clone(), 0);
+        gc.start_user_transaction(operations, None, TransactionName::Merge);
+        gc
+    }
+
+    #[test]
+    fn diffs_grids() {
+        let mut from = GridController::test();
+        let sheet_id = from.sheet_ids()[0];
+        from.set_cell_value(SheetPos::new(sheet_id, 0, 0), "1".to_string(), None);
+        from.set_cell_value(SheetPos::new(sheet_id, 0, 1), "removed".to_string(), None);
+        from.add_sheet(None);
+        let deleted_sheet_id = from.sheet_ids()[1];
+
+        let mut to = from.clone();
+        to.set_cell_value(SheetPos::new(sheet_id, 0, 0), "2".to_string(), None);
+        to.delete_cells(&Selection::pos(0, 1, sheet_id), None);
+        to.set_code_cell(
+            SheetPos::new(sheet_id, 1, 0),
+            CodeCellLanguage::Formula,
+            "A0 * 10".to_string(),
+            None,
+        );
+        to.set_cell_bold(SheetPos::new(sheet_id, 0, 0).into(), Some(true), None);
+        to.commit_single_resize(sheet_id, Some(2), None, 200.0, None);
+        to.set_sheet_name(sheet_id, "Renamed".to_string(), None);
+        to.delete_sheet(deleted_sheet_id, None);
+        to.add_sheet(None);
+
+        let changes = diff_grids(from.grid(), to.grid());
+        let descriptions = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            descriptions,
+            vec![
+                "added sheet Sheet 1",
+                "renamed sheet Sheet 1 to Renamed",
+                "A0: 1 -> 2",
+                "A1: removed -> (empty)",
+                "B0: (empty) -> =A0 * 10",
+                "A0 format: (none) -> bold: true",
+                "column C width: 100 -> 200",
+                "deleted sheet Sheet 2",
+            ]
+        );
+
+        // applying the operations leaves nothing to change
+        let gc = apply(from.grid(), diff_operations(from.grid(), to.grid()));
+        assert_eq!(diff_grids(gc.grid(), to.grid()), vec![]);
+        assert_eq!(
+            gc.sheet(sheet_id).display_value(Pos { x: 1, y: 0 }),
+            Some(CellValue::Number(20.into()))
+        );
+    }
+
+    #[test]
+    fn merges_grids() {
+        let mut base = GridController::test();
+        let sheet_id = base.sheet_ids()[0];
+        base.set_cell_value(SheetPos::new(sheet_id, 0, 0), "base".to_string(), None);
+
+        let mut ours = base.clone();
+        ours.set_cell_value(SheetPos::new(sheet_id, 0, 0), "ours".to_string(), None);
+        ours.set_cell_value(SheetPos::new(sheet_id, 0, 1), "same".to_string(), None);
+        ours.set_sheet_color(sheet_id, Some("red".to_string()), None);
+
+        let mut theirs = base.clone();
+        theirs.set_cell_value(SheetPos::new(sheet_id, 0, 0), "theirs".to_string(), None);
+        theirs.set_cell_value(SheetPos::new(sheet_id, 0, 1), "same".to_string(), None);
+        theirs.set_cell_value(SheetPos::new(sheet_id, 0, 2), "added".to_string(), None);
+        theirs.set_cell_italic(SheetPos::new(sheet_id, 0, 0).into(), Some(true), None);
+
+        let conflicts = ours.merge(base.grid(), theirs.grid(), None);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].ours.to_string(), "A0: base -> ours");
+        assert_eq!(conflicts[0].theirs.to_string(), "A0: base -> theirs");
+
+        let sheet = ours.sheet(sheet_id);
+        assert_eq!(
+            sheet.cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("ours".into()))
+        );
+        assert_eq!(
+            sheet.cell_value(Pos { x: 0, y: 2 }),
+            Some(CellValue::Text("added".into()))
+        );
+        assert_eq!(sheet.format_cell(0, 0, false).italic, Some(true));
+        assert_eq!(sheet.color, Some("red".to_string()));
+    }
+
+    #[test]
+    fn reports_changes_to_deleted_sheets() {
+        let mut base = GridController::test();
+        base.add_sheet(None);
+        let sheet_id = base.sheet_ids()[1];
+
+        let mut ours = base.clone();
+        ours.delete_sheet(sheet_id, None);
+
+        let mut theirs = base.clone();
+        theirs.set_cell_value(SheetPos::new(sheet_id, 0, 0), "1".to_string(), None);
+
+        let merge = merge_grids(base.grid(), ours.grid(), theirs.grid());
+        assert!(merge.operations.is_empty());
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].ours.to_string(), "deleted sheet Sheet 2");
+
+        // and the other way around
+        let merge = merge_grids(base.grid(), theirs.grid(), ours.grid());
+        assert!(merge.operations.is_empty());
+        assert_eq!(
+            merge.conflicts[0].theirs.to_string(),
+            "deleted sheet Sheet 2"
+        );
+    }
+}
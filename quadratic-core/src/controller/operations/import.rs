@@ -1,139 +1,793 @@
-use std::io::Cursor;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    io::Cursor,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use anyhow::{anyhow, bail, Result};
 use lexicon_fractional_index::key_between;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     cell_values::CellValues,
-    controller::GridController,
-    grid::{file::sheet_schema::export_sheet, CodeCellLanguage, Sheet, SheetId},
-    CellValue, CodeCellValue, Pos, SheetPos,
+    color::Rgba,
+    controller::{active_transactions::pending_transaction::PendingTransaction, GridController},
+    grid::{
+        file::sheet_schema::export_sheet, formatting::CellFmtArray, generate_borders,
+        set_rect_borders, Bold, BorderSelection, BorderStyle, CellAlign, CellBorderLine, CellWrap,
+        CodeCellLanguage, FillColor, Italic, NumericFormat, Sheet, SheetId, TextColor,
+    },
+    json::json_to_records,
+    limits::{IMPORT_MAX_BYTES, IMPORT_MAX_ROWS},
+    xlsx::{
+        color::{css_color, XlsxColor},
+        formula::{openformula_to_excel, translate_excel_formula, FormulaContext, FormulaIssue},
+        styles::XlsxBorderSide,
+        XlsxSheet, XlsxWorkbook,
+    },
+    CellValue, CodeCellValue, DateLocale, Duration, Instant, Pos, Rect, RunLengthEncoding,
+    SheetPos, SheetRect,
 };
 use bytes::Bytes;
-use calamine::{Data as ExcelData, Reader as ExcelReader, Xlsx, XlsxError};
-use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use calamine::{Data as ExcelData, Ods, Reader as ExcelReader, Sheets, Xls, Xlsb, Xlsx};
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
 
 use super::operation::Operation;
 
 const IMPORT_LINES_PER_OPERATION: u32 = 10000;
 
-impl GridController {
-    /// Imports a CSV file into the grid.
-    pub fn import_csv_operations(
-        &mut self,
+/// Delimiters that are detected when a CSV file's delimiter is not given,
+/// with ties going to the last one.
+const CSV_DELIMITERS: [u8; 4] = [b'|', b';', b'\t', b','];
+
+/// Number of lines used to detect a CSV file's delimiter.
+const CSV_SNIFF_LINES: usize = 20;
+
+/// Magic bytes of a zip archive (xlsx, xlsb, and ods files).
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Magic bytes of an OLE compound file (xls files).
+const OLE_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Media type of an OpenDocument spreadsheet, stored uncompressed in its
+/// `mimetype` part.
+const ODS_MIME_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+/// Spreadsheet formats that are imported by `import_excel`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkbookFormat {
+    /// Excel 2007+ (`.xlsx`, `.xlsm`).
+    Xlsx,
+
+    /// Excel 2007+ binary (`.xlsb`).
+    Xlsb,
+
+    /// Excel 97-2003 (`.xls`).
+    Xls,
+
+    /// OpenDocument (`.ods`).
+    Ods,
+}
+
+impl WorkbookFormat {
+    /// Detects the format of a spreadsheet from its magic bytes, falling back
+    /// to the file extension.
+    pub fn detect(file: &[u8], file_name: &str) -> Option<Self> {
+        if file.starts_with(OLE_MAGIC) {
+            return Some(Self::Xls);
+        }
+        if file.starts_with(ZIP_MAGIC) {
+            if let Ok(archive) = zip::ZipArchive::new(Cursor::new(file)) {
+                let has_part = |name: &str| archive.file_names().any(|part| part == name);
+
+                // the `mimetype` part is the first in the archive, so its
+                // contents are near the start of the file
+                let is_ods = file
+                    .windows(ODS_MIME_TYPE.len())
+                    .take(100)
+                    .any(|bytes| bytes == ODS_MIME_TYPE.as_bytes());
+                if has_part("mimetype") && is_ods {
+                    return Some(Self::Ods);
+                }
+                if has_part("xl/workbook.bin") {
+                    return Some(Self::Xlsb);
+                }
+                if has_part("xl/workbook.xml") {
+                    return Some(Self::Xlsx);
+                }
+            }
+        }
+
+        let extension = file_name.rsplit_once('.')?.1.to_ascii_lowercase();
+        match extension.as_str() {
+            "xlsx" | "xlsm" => Some(Self::Xlsx),
+            "xlsb" => Some(Self::Xlsb),
+            "xls" => Some(Self::Xls),
+            "ods" => Some(Self::Ods),
+            _ => None,
+        }
+    }
+}
+
+/// A feature of an imported file that was dropped or approximated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+pub enum ImportWarningKind {
+    MergedCells,
+    ConditionalFormatting,
+    DataValidation,
+    Drawings,
+    HiddenRowsOrColumns,
+    UnsupportedNumberFormat,
+    UnsupportedBorderStyle,
+    UnsupportedFill,
+    UnsupportedColor,
+
+    /// Styles and layout could not be read, so only values were imported.
+    MetadataUnreadable,
+
+    /// Formulas use an Excel function that Quadratic does not implement.
+    UnsupportedFunction,
+
+    /// Formulas use a function that is not part of Excel, such as a VBA or
+    /// add-in function.
+    UnknownFunction,
+
+    /// Formulas use syntax that could not be translated, so they were
+    /// imported unchanged.
+    UnsupportedFormula,
+
+    /// Formulas could not be parsed, so they were imported unchanged.
+    FormulaParseError,
+}
+
+/// The number of times a kind of unsupported feature was found in a sheet
+/// (or in the whole file if `sheet_name` is `None`). `detail` names the
+/// function or syntax for formula warnings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+pub struct ImportWarning {
+    pub sheet_name: Option<String>,
+    pub kind: ImportWarningKind,
+    pub detail: Option<String>,
+    pub count: u32,
+}
+
+impl ImportWarning {
+    fn new(sheet_name: Option<&str>, kind: ImportWarningKind, count: u32) -> Self {
+        Self {
+            sheet_name: sheet_name.map(str::to_string),
+            kind,
+            detail: None,
+            count,
+        }
+    }
+
+    fn with_detail(self, detail: String) -> Self {
+        Self {
+            detail: Some(detail),
+            ..self
+        }
+    }
+}
+
+/// Text encoding of an imported CSV file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+pub enum CsvEncoding {
+    /// UTF-8 or UTF-16 if the file is valid as either, otherwise
+    /// Windows-1252.
+    #[default]
+    Auto,
+    Utf8,
+    Utf16,
+    Latin1,
+    Windows1252,
+}
+
+impl CsvEncoding {
+    /// Decodes a file to text. Invalid characters are replaced or dropped.
+    fn decode(self, file: &[u8]) -> Cow<'_, str> {
+        let without_bom = file.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(file);
+        match self {
+            CsvEncoding::Auto => match std::str::from_utf8(without_bom) {
+                Ok(text) => Cow::Borrowed(text),
+                Err(_) if looks_like_utf16(file) => {
+                    Cow::Owned(read_utf16(file).unwrap_or_default())
+                }
+                Err(_) => CsvEncoding::Windows1252.decode(file),
+            },
+            CsvEncoding::Utf8 => String::from_utf8_lossy(without_bom),
+            CsvEncoding::Utf16 => Cow::Owned(read_utf16(file).unwrap_or_default()),
+            CsvEncoding::Latin1 => Cow::Owned(file.iter().map(|&b| b as char).collect()),
+            CsvEncoding::Windows1252 => Cow::Owned(
+                file.iter()
+                    .map(|&b| match b {
+                        0x80..=0x9F => WINDOWS_1252_CONTROL_RANGE[(b - 0x80) as usize],
+                        _ => b as char,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Characters of Windows-1252 bytes 0x80 to 0x9F, which differ from
+/// Latin-1. Unassigned bytes map to the matching control character.
+const WINDOWS_1252_CONTROL_RANGE: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Options for importing a CSV file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+#[serde(default)]
+pub struct CsvImportOptions {
+    /// Field delimiter. If `None`, it is detected from the file (comma, tab,
+    /// semicolon, or pipe).
+    pub delimiter: Option<char>,
+    pub quote: char,
+
+    /// Whether the first row (after skipped rows) is a header. Headers are
+    /// imported as bold text.
+    pub header_row: bool,
+
+    /// Number of rows to skip at the start of the file.
+    pub skip_rows: u32,
+    pub encoding: CsvEncoding,
+
+    /// Whether to convert values to numbers, dates, etc.
+    pub infer_types: bool,
+
+    /// Columns (relative to the file) whose values are always imported as
+    /// text.
+    pub text_columns: Vec<u32>,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: None,
+            quote: '"',
+            header_row: false,
+            skip_rows: 0,
+            encoding: CsvEncoding::Auto,
+            infer_types: true,
+            text_columns: vec![],
+        }
+    }
+}
+
+impl CsvImportOptions {
+    /// Returns whether values of a column are converted from text.
+    fn infer_types(&self, column: u32) -> bool {
+        self.infer_types && !self.text_columns.contains(&column)
+    }
+}
+
+/// Hard limits on the size of an imported file, so that a file too large to
+/// import fails with a clear error instead of exhausting memory.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+#[serde(default)]
+pub struct ImportLimits {
+    /// Maximum size of the file, in bytes.
+    pub max_bytes: u64,
+
+    /// Maximum number of rows in the file, including any header row.
+    pub max_rows: u64,
+}
+
+impl Default for ImportLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: IMPORT_MAX_BYTES,
+            max_rows: IMPORT_MAX_ROWS,
+        }
+    }
+}
+
+impl ImportLimits {
+    fn check_bytes(&self, file_name: &str, bytes: usize) -> Result<()> {
+        if bytes as u64 > self.max_bytes {
+            bail!(
+                "{file_name} is {bytes} bytes, which is more than the import limit of {} bytes",
+                self.max_bytes
+            );
+        }
+        Ok(())
+    }
+
+    fn check_rows(&self, file_name: &str, rows: u64) -> Result<()> {
+        if rows > self.max_rows {
+            bail!(
+                "{file_name} has {rows} rows, which is more than the import limit of {} rows",
+                self.max_rows
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A handle that cancels the import in progress. Imports check it between
+/// batches, and a cancelled import is rolled back.
+#[derive(Debug, Default, Clone)]
+pub struct ImportCancellation(Arc<AtomicBool>);
+
+impl ImportCancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+impl PartialEq for ImportCancellation {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_cancelled() == other.is_cancelled()
+    }
+}
+
+/// A source of import operations that are read in batches, so that each
+/// batch is applied and released before the next one is read.
+pub(crate) trait ImportBatches {
+    /// Returns the next batch of operations, or `None` once the file has been
+    /// read.
+    fn next_batch(&mut self, gc: &mut GridController) -> Result<Option<Vec<Operation>>>;
+}
+
+/// An import whose batches are applied one at a time by
+/// `GridController::continue_import`, so that the caller can handle other
+/// events, like cancelling the import, between batches.
+#[cfg_attr(feature = "js", wasm_bindgen::prelude::wasm_bindgen)]
+pub struct ImportJob {
+    // None once the import is finalized or rolled back
+    pub(crate) transaction: Option<PendingTransaction>,
+    pub(crate) batches: Box<dyn ImportBatches>,
+    pub(crate) compute_operations: Vec<Operation>,
+}
+
+/// Reads a CSV file in batches of `IMPORT_LINES_PER_OPERATION` rows. The
+/// file is parsed as it is read, so the row limit is checked and progress is
+/// reported by the bytes read so far.
+pub(crate) struct CsvBatches {
+    records: csv::StringRecordsIntoIter<Cursor<Vec<u8>>>,
+    sheet_id: SheetId,
+    file_name: String,
+    insert_at: Pos,
+    options: CsvImportOptions,
+    max_rows: u64,
+    total_bytes: u64,
+    width: u32,
+    current_y: u32,
+}
+
+impl CsvBatches {
+    pub(crate) fn new(
         sheet_id: SheetId,
         file: &[u8],
         file_name: &str,
         insert_at: Pos,
-    ) -> Result<Vec<Operation>> {
+        options: &CsvImportOptions,
+        limits: &ImportLimits,
+    ) -> Result<Self> {
         let error = |message: String| anyhow!("Error parsing CSV file {}: {}", file_name, message);
-        let file = match String::from_utf8_lossy(file) {
-            std::borrow::Cow::Borrowed(_) => file,
-            std::borrow::Cow::Owned(_) => {
-                if let Some(utf) = read_utf16(file) {
-                    return self.import_csv_operations(
-                        sheet_id,
-                        utf.as_bytes(),
-                        file_name,
-                        insert_at,
-                    );
-                }
-                file
+        limits.check_bytes(file_name, file.len())?;
+
+        let text = options.encoding.decode(file);
+        let quote = ascii_byte(options.quote).ok_or_else(|| error("invalid quote".into()))?;
+        let delimiter = match options.delimiter {
+            Some(delimiter) => {
+                ascii_byte(delimiter).ok_or_else(|| error("invalid delimiter".into()))?
             }
+            None => sniff_delimiter(&text, options.quote),
         };
-
-        // first get the total number of lines so we can provide progress
-        let mut reader = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(file);
-        let height = reader.records().count() as u32;
-
-        let mut reader = csv::ReaderBuilder::new()
+        let file = text.into_owned().into_bytes();
+        let total_bytes = file.len() as u64;
+        let mut records = csv::ReaderBuilder::new()
             .has_headers(false)
             .flexible(true)
-            .from_reader(file);
+            .delimiter(delimiter)
+            .quote(quote)
+            .from_reader(Cursor::new(file))
+            .into_records();
+        for _ in 0..options.skip_rows {
+            if records.next().is_none() {
+                break;
+            }
+        }
 
-        let width = reader.headers()?.len() as u32;
-        if width == 0 {
-            bail!("empty files cannot be processed");
+        Ok(Self {
+            records,
+            sheet_id,
+            file_name: file_name.to_string(),
+            insert_at,
+            options: options.clone(),
+            max_rows: limits.max_rows,
+            total_bytes,
+            width: 0,
+            current_y: 0,
+        })
+    }
+}
+
+impl ImportBatches for CsvBatches {
+    fn next_batch(&mut self, gc: &mut GridController) -> Result<Option<Vec<Operation>>> {
+        let mut records = vec![];
+        for entry in self
+            .records
+            .by_ref()
+            .take(IMPORT_LINES_PER_OPERATION as usize)
+        {
+            let line = self.options.skip_rows as usize + self.current_y as usize + records.len();
+            let record = entry.map_err(|e| {
+                anyhow!(
+                    "Error parsing CSV file {}: line {}: {}",
+                    self.file_name,
+                    line + 1,
+                    e
+                )
+            })?;
+            records.push(record);
+        }
+
+        if records.is_empty() {
+            if self.width == 0 {
+                bail!("empty files cannot be processed");
+            }
+            return Ok(None);
+        }
+        if self.current_y as u64 + records.len() as u64 > self.max_rows {
+            bail!(
+                "{} has more than {} rows, which is the import limit",
+                self.file_name,
+                self.max_rows
+            );
         }
 
-        // then create operations using MAXIMUM_IMPORT_LINES to break up the SetCellValues operations
+        let (sheet_id, insert_at) = (self.sheet_id, self.insert_at);
+        let width = records.iter().map(|record| record.len()).max().unwrap_or(0) as u32;
+        let height = records.len() as u32;
         let mut ops = vec![] as Vec<Operation>;
         let mut cell_values = CellValues::new(width, height);
-        let mut current_y = 0;
-        let mut y: u32 = 0;
-        for entry in reader.records() {
-            match entry {
-                Err(e) => return Err(error(format!("line {}: {}", current_y + y + 1, e))),
-                Ok(record) => {
-                    for (x, value) in record.iter().enumerate() {
-                        let (operations, cell_value) = self.string_to_cell_value(
-                            SheetPos {
-                                x: insert_at.x + x as i64,
-                                y: insert_at.y + current_y as i64 + y as i64,
-                                sheet_id,
-                            },
-                            value,
-                        );
-                        ops.extend(operations);
-                        cell_values.set(x as u32, y, cell_value);
+        for (y, record) in records.iter().enumerate() {
+            let y = y as u32;
+            let is_header = self.options.header_row && self.current_y + y == 0;
+            for (x, value) in record.iter().enumerate() {
+                let cell_value = if is_header || !self.options.infer_types(x as u32) {
+                    match value {
+                        "" => CellValue::Blank,
+                        _ => CellValue::Text(value.to_string()),
                     }
-                }
-            }
-            y += 1;
-            if y >= IMPORT_LINES_PER_OPERATION {
-                ops.push(Operation::SetCellValues {
-                    sheet_pos: SheetPos {
-                        x: insert_at.x,
-                        y: insert_at.y + current_y as i64,
-                        sheet_id,
-                    },
-                    values: cell_values,
-                });
-                current_y += y;
-                y = 0;
-                let h = (height - current_y).min(IMPORT_LINES_PER_OPERATION);
-                cell_values = CellValues::new(width, h);
-
-                // update the progress bar every time there's a new operation
-                if cfg!(target_family = "wasm") {
-                    crate::wasm_bindings::js::jsImportProgress(
-                        file_name,
-                        current_y,
-                        height,
-                        insert_at.x,
-                        insert_at.y,
-                        width,
-                        height,
+                } else {
+                    let (operations, cell_value) = gc.string_to_cell_value(
+                        SheetPos {
+                            x: insert_at.x + x as i64,
+                            y: insert_at.y + (self.current_y + y) as i64,
+                            sheet_id,
+                        },
+                        value,
                     );
-                }
+                    ops.extend(operations);
+                    cell_value
+                };
+                cell_values.set(x as u32, y, cell_value);
             }
         }
 
-        // finally add the final operation
         ops.push(Operation::SetCellValues {
             sheet_pos: SheetPos {
                 x: insert_at.x,
-                y: insert_at.y + current_y as i64,
+                y: insert_at.y + self.current_y as i64,
                 sheet_id,
             },
             values: cell_values,
         });
-        Ok(ops)
+
+        if self.options.header_row && self.current_y == 0 {
+            ops.push(Operation::SetCellFormats {
+                sheet_rect: SheetRect::from_numbers(
+                    insert_at.x,
+                    insert_at.y,
+                    width as i64,
+                    1,
+                    sheet_id,
+                ),
+                attr: CellFmtArray::Bold(RunLengthEncoding::repeat(Some(true), width as usize)),
+            });
+        }
+        self.current_y += height;
+        self.width = self.width.max(width);
+
+        // update the progress bar every time there's a new batch
+        if cfg!(target_family = "wasm") {
+            let bytes_read = self.records.reader().position().byte();
+            crate::wasm_bindings::js::jsImportProgress(
+                &self.file_name,
+                bytes_read as u32,
+                self.total_bytes as u32,
+                insert_at.x,
+                insert_at.y,
+                self.width,
+                self.current_y,
+            );
+        }
+
+        Ok(Some(ops))
     }
+}
 
-    /// Imports an Excel file into the grid.
+/// Reads a Parquet file one record batch at a time, with one operation per
+/// column of each record batch.
+pub(crate) struct ParquetBatches {
+    reader: ParquetRecordBatchReader,
+    headers: Option<Vec<CellValue>>,
+    sheet_id: SheetId,
+    file_name: String,
+    insert_at: Pos,
+    width: u32,
+    total_size: u32,
+    current_size: u32,
+}
+
+impl ParquetBatches {
+    pub(crate) fn new(
+        sheet_id: SheetId,
+        file: Vec<u8>,
+        file_name: &str,
+        insert_at: Pos,
+        limits: &ImportLimits,
+    ) -> Result<Self> {
+        limits.check_bytes(file_name, file.len())?;
+
+        // this is not expensive
+        let bytes = Bytes::from(file);
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes)?;
+
+        // headers
+        let metadata = builder.metadata();
+        let total_size = metadata.file_metadata().num_rows().max(0) as u64;
+        limits.check_rows(file_name, total_size + 1)?;
+        let fields = metadata.file_metadata().schema().get_fields();
+        let headers: Vec<CellValue> = fields.iter().map(|f| f.name().into()).collect();
+
+        Ok(Self {
+            reader: builder.build()?,
+            width: headers.len() as u32,
+            headers: Some(headers),
+            sheet_id,
+            file_name: file_name.to_string(),
+            insert_at,
+            total_size: total_size as u32,
+            current_size: 0,
+        })
+    }
+}
+
+impl ImportBatches for ParquetBatches {
+    fn next_batch(&mut self, _gc: &mut GridController) -> Result<Option<Vec<Operation>>> {
+        let (sheet_id, insert_at) = (self.sheet_id, self.insert_at);
+        let mut ops = vec![] as Vec<Operation>;
+
+        if let Some(headers) = self.headers.take() {
+            ops.push(Operation::SetCellValues {
+                sheet_pos: (insert_at.x, insert_at.y, sheet_id).into(),
+                values: CellValues::from_flat_array(headers.len() as u32, 1, headers),
+            });
+        }
+
+        let Some(batch) = self.reader.next() else {
+            return Ok((!ops.is_empty()).then_some(ops));
+        };
+        let batch = batch?;
+        let num_cols = batch.num_columns();
+        self.width = self.width.max(num_cols as u32);
+
+        for col_index in 0..num_cols {
+            let col = batch.column(col_index);
+            let values: CellValues = col.try_into()?;
+
+            ops.push(Operation::SetCellValues {
+                sheet_pos: (
+                    insert_at.x + col_index as i64,
+                    insert_at.y + self.current_size as i64 + 1,
+                    sheet_id,
+                )
+                    .into(),
+                values,
+            });
+        }
+        self.current_size += batch.num_rows() as u32;
+
+        // update the progress bar every time there's a new batch
+        if cfg!(target_family = "wasm") {
+            crate::wasm_bindings::js::jsImportProgress(
+                &self.file_name,
+                self.current_size,
+                self.total_size,
+                insert_at.x,
+                insert_at.y,
+                self.width,
+                self.total_size + 1,
+            );
+        }
+
+        Ok(Some(ops))
+    }
+}
+
+/// Reads the records of a JSON file in batches of
+/// `IMPORT_LINES_PER_OPERATION` rows, after a bold header row of column
+/// names.
+pub(crate) struct JsonBatches {
+    headers: Option<Vec<String>>,
+    rows: std::vec::IntoIter<Vec<CellValue>>,
+    sheet_id: SheetId,
+    file_name: String,
+    insert_at: Pos,
+    width: u32,
+    height: u32,
+    current_y: u32,
+}
+
+impl JsonBatches {
+    pub(crate) fn new(
+        sheet_id: SheetId,
+        file: &[u8],
+        file_name: &str,
+        insert_at: Pos,
+        limits: &ImportLimits,
+    ) -> Result<Self> {
+        limits.check_bytes(file_name, file.len())?;
+        let (headers, rows) = json_to_records(file)
+            .map_err(|e| anyhow!("Error parsing JSON file {file_name}: {e}"))?;
+        if headers.is_empty() {
+            bail!("empty files cannot be processed");
+        }
+        limits.check_rows(file_name, rows.len() as u64 + 1)?;
+
+        Ok(Self {
+            width: headers.len() as u32,
+            height: rows.len() as u32 + 1,
+            headers: Some(headers),
+            rows: rows.into_iter(),
+            sheet_id,
+            file_name: file_name.to_string(),
+            insert_at,
+            current_y: 1,
+        })
+    }
+}
+
+impl ImportBatches for JsonBatches {
+    fn next_batch(&mut self, _gc: &mut GridController) -> Result<Option<Vec<Operation>>> {
+        let (sheet_id, insert_at, width) = (self.sheet_id, self.insert_at, self.width);
+        let mut ops = vec![] as Vec<Operation>;
+
+        if let Some(headers) = self.headers.take() {
+            ops.push(Operation::SetCellValues {
+                sheet_pos: (insert_at.x, insert_at.y, sheet_id).into(),
+                values: CellValues::from_flat_array(
+                    width,
+                    1,
+                    headers.into_iter().map(CellValue::Text).collect(),
+                ),
+            });
+            ops.push(Operation::SetCellFormats {
+                sheet_rect: SheetRect::from_numbers(
+                    insert_at.x,
+                    insert_at.y,
+                    width as i64,
+                    1,
+                    sheet_id,
+                ),
+                attr: CellFmtArray::Bold(RunLengthEncoding::repeat(Some(true), width as usize)),
+            });
+        }
+
+        let chunk = self
+            .rows
+            .by_ref()
+            .take(IMPORT_LINES_PER_OPERATION as usize)
+            .flatten()
+            .collect::<Vec<_>>();
+        if !chunk.is_empty() {
+            let chunk_height = chunk.len() as u32 / width;
+            ops.push(Operation::SetCellValues {
+                sheet_pos: (insert_at.x, insert_at.y + self.current_y as i64, sheet_id).into(),
+                values: CellValues::from_flat_array(width, chunk_height, chunk),
+            });
+            self.current_y += chunk_height;
+
+            // update the progress bar every time there's a new batch
+            if cfg!(target_family = "wasm") {
+                crate::wasm_bindings::js::jsImportProgress(
+                    &self.file_name,
+                    self.current_y,
+                    self.height,
+                    insert_at.x,
+                    insert_at.y,
+                    width,
+                    self.height,
+                );
+            }
+        }
+
+        Ok((!ops.is_empty()).then_some(ops))
+    }
+}
+
+impl GridController {
+    /// Imports a CSV file into the grid.
+    pub fn import_csv_operations(
+        &mut self,
+        sheet_id: SheetId,
+        file: &[u8],
+        file_name: &str,
+        insert_at: Pos,
+        options: &CsvImportOptions,
+    ) -> Result<Vec<Operation>> {
+        let mut batches = CsvBatches::new(
+            sheet_id,
+            file,
+            file_name,
+            insert_at,
+            options,
+            &self.import_limits,
+        )?;
+        self.collect_import_batches(&mut batches)
+    }
+
+    /// Imports a spreadsheet file (Excel or OpenDocument) into the grid. The
+    /// format is detected from the file's contents and name.
+    ///
+    /// Returns the operations and the features that could not be imported.
     pub fn import_excel_operations(
         &mut self,
         file: Vec<u8>,
         file_name: &str,
-    ) -> Result<Vec<Operation>> {
+    ) -> Result<(Vec<Operation>, Vec<ImportWarning>)> {
         let mut ops = vec![] as Vec<Operation>;
-        let error = |e: XlsxError| anyhow!("Error parsing Excel file {file_name}: {e}");
+        let mut warnings = vec![];
+        let error = |e: calamine::Error| anyhow!("Error parsing spreadsheet {file_name}: {e}");
+        self.import_limits.check_bytes(file_name, file.len())?;
+
+        let Some(format) = WorkbookFormat::detect(&file, file_name) else {
+            bail!("Unsupported spreadsheet format: {file_name}");
+        };
+
+        // styles and layout are not exposed by calamine, so they are read
+        // separately; values are still imported if the metadata cannot be read
+        let metadata = match format {
+            WorkbookFormat::Xlsx => XlsxWorkbook::read(&file).unwrap_or_else(|_| {
+                warnings.push(ImportWarning::new(
+                    None,
+                    ImportWarningKind::MetadataUnreadable,
+                    1,
+                ));
+                XlsxWorkbook::default()
+            }),
+            _ => XlsxWorkbook::default(),
+        };
 
         let cursor = Cursor::new(file);
-        let mut workbook: Xlsx<_> = ExcelReader::new(cursor).map_err(error)?;
+        let mut workbook = match format {
+            WorkbookFormat::Xlsx => Sheets::Xlsx(Xlsx::new(cursor).map_err(|e| error(e.into()))?),
+            WorkbookFormat::Xlsb => Sheets::Xlsb(Xlsb::new(cursor).map_err(|e| error(e.into()))?),
+            WorkbookFormat::Xls => Sheets::Xls(Xls::new(cursor).map_err(|e| error(e.into()))?),
+            WorkbookFormat::Ods => Sheets::Ods(Ods::new(cursor).map_err(|e| error(e.into()))?),
+        };
         let sheets = workbook.sheet_names().to_owned();
 
         // first cell in excel is A1, but first cell in quadratic is A0
@@ -158,26 +812,16 @@ impl GridController {
                     let cell_value = match cell {
                         ExcelData::Empty => continue,
                         ExcelData::String(value) => CellValue::Text(value.to_string()),
-                        ExcelData::DateTimeIso(ref value) => CellValue::Text(value.to_string()),
-                        ExcelData::DurationIso(ref value) => CellValue::Text(value.to_string()),
+                        ExcelData::DateTimeIso(ref value) => Instant::parse(value, DateLocale::Iso)
+                            .map_or_else(|| CellValue::Text(value.to_string()), Into::into),
+                        ExcelData::DurationIso(ref value) => Duration::parse(value)
+                            .map_or_else(|| CellValue::Text(value.to_string()), Into::into),
                         ExcelData::Float(ref value) => {
                             CellValue::unpack_str_float(&value.to_string(), CellValue::Blank)
                         }
-                        // TODO(ddimaria): implement when implementing Instant
-                        // ExcelData::DateTime(ref value) => match value.is_datetime() {
-                        //     true => value.as_datetime().map_or_else(
-                        //         || CellValue::Blank,
-                        //         |v| CellValue::Instant(v.into()),
-                        //     ),
-                        //     false => CellValue::Text(value.to_string()),
-                        // },
-                        // TODO(ddimaria): remove when implementing Instant
                         ExcelData::DateTime(ref value) => match value.is_datetime() {
-                            true => value.as_datetime().map_or_else(
-                                || CellValue::Blank,
-                                |v| CellValue::Text(v.to_string()),
-                            ),
-                            false => CellValue::Text(value.to_string()),
+                            true => CellValue::Instant(Instant::from_serial(value.as_f64())),
+                            false => CellValue::Duration(Duration::from_days(value.as_f64())),
                         },
                         ExcelData::Int(ref value) => {
                             CellValue::unpack_str_float(&value.to_string(), CellValue::Blank)
@@ -200,6 +844,7 @@ impl GridController {
             let formula = workbook.worksheet_formula(&sheet_name).map_err(error)?;
             let insert_at = formula.start().map_or_else(Pos::default, xlsx_range_to_pos);
             let mut formula_compute_ops = vec![];
+            let mut formula_issues: BTreeMap<(ImportWarningKind, String), u32> = BTreeMap::new();
             for (y, row) in formula.rows().enumerate() {
                 for (x, cell) in row.iter().enumerate() {
                     if !cell.is_empty() {
@@ -207,9 +852,50 @@ impl GridController {
                             x: insert_at.x + x as i64,
                             y: insert_at.y + y as i64,
                         };
+                        let context = FormulaContext {
+                            sheet_name: &sheet_name,
+                            pos,
+                            tables: &metadata.tables,
+                        };
+
+                        let cell = match format {
+                            WorkbookFormat::Ods => Cow::Owned(openformula_to_excel(cell)),
+                            _ => Cow::Borrowed(cell.as_str()),
+                        };
+
+                        // formulas that cannot be translated are kept as they
+                        // are, so that they can be fixed by hand
+                        let (code, issues) = match translate_excel_formula(&cell, &context) {
+                            Ok(translated) => (translated.code, translated.issues),
+                            Err(issue) => (cell.to_string(), vec![issue]),
+                        };
+                        for issue in issues {
+                            let kind = match issue {
+                                FormulaIssue::UnsupportedFunction(_) => {
+                                    ImportWarningKind::UnsupportedFunction
+                                }
+                                FormulaIssue::UnknownFunction(_) => {
+                                    ImportWarningKind::UnknownFunction
+                                }
+                                FormulaIssue::UnsupportedSyntax(_) => {
+                                    ImportWarningKind::UnsupportedFormula
+                                }
+                                FormulaIssue::InvalidFormula => {
+                                    ImportWarningKind::FormulaParseError
+                                }
+                            };
+                            let detail = match issue {
+                                FormulaIssue::UnsupportedFunction(name)
+                                | FormulaIssue::UnknownFunction(name) => name,
+                                FormulaIssue::UnsupportedSyntax(syntax) => syntax.to_string(),
+                                FormulaIssue::InvalidFormula => code.clone(),
+                            };
+                            *formula_issues.entry((kind, detail)).or_default() += 1;
+                        }
+
                         let cell_value = CellValue::Code(CodeCellValue {
                             language: CodeCellLanguage::Formula,
-                            code: cell.to_string(),
+                            code,
                         });
                         sheet.set_cell_value(pos, cell_value);
                         // add code compute operation, to generate code runs
@@ -219,13 +905,28 @@ impl GridController {
                     }
                 }
             }
+            warnings.extend(formula_issues.into_iter().map(|((kind, detail), count)| {
+                ImportWarning::new(Some(&sheet_name), kind, count).with_detail(detail)
+            }));
+
+            // formats and layout
+            if let Some(sheet_metadata) = metadata.sheet(&sheet_name) {
+                let last_column = range.end().map_or(0, |(_, col)| col as i64);
+                warnings.extend(import_excel_formats(
+                    &mut sheet,
+                    &metadata,
+                    sheet_metadata,
+                    last_column,
+                ));
+            }
+
             // add new sheets
             ops.push(Operation::AddSheetSchema {
                 schema: export_sheet(&sheet),
             });
             ops.extend(formula_compute_ops);
         }
-        Ok(ops)
+        Ok((ops, warnings))
     }
 
     /// Imports a Parquet file into the grid.
@@ -236,69 +937,272 @@ impl GridController {
         file_name: &str,
         insert_at: Pos,
     ) -> Result<Vec<Operation>> {
-        let mut ops = vec![] as Vec<Operation>;
+        let mut batches =
+            ParquetBatches::new(sheet_id, file, file_name, insert_at, &self.import_limits)?;
+        self.collect_import_batches(&mut batches)
+    }
 
-        // this is not expensive
-        let bytes = Bytes::from(file);
-        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes)?;
+    /// Imports a JSON file (an array of records, a single record, or
+    /// newline-delimited records) into the grid. The header row holds the
+    /// column names, with nested objects flattened into dotted names.
+    pub fn import_json_operations(
+        &mut self,
+        sheet_id: SheetId,
+        file: &[u8],
+        file_name: &str,
+        insert_at: Pos,
+    ) -> Result<Vec<Operation>> {
+        let mut batches =
+            JsonBatches::new(sheet_id, file, file_name, insert_at, &self.import_limits)?;
+        self.collect_import_batches(&mut batches)
+    }
 
-        // headers
-        let metadata = builder.metadata();
-        let total_size = metadata.file_metadata().num_rows() as u32;
-        let fields = metadata.file_metadata().schema().get_fields();
-        let headers: Vec<CellValue> = fields.iter().map(|f| f.name().into()).collect();
-        let mut width = headers.len() as u32;
+    /// Reads all batches of an import into a single list of operations.
+    fn collect_import_batches(
+        &mut self,
+        batches: &mut impl ImportBatches,
+    ) -> Result<Vec<Operation>> {
+        let mut ops = vec![];
+        while let Some(batch) = batches.next_batch(self)? {
+            ops.extend(batch);
+        }
+        Ok(ops)
+    }
+}
 
-        ops.push(Operation::SetCellValues {
-            sheet_pos: (insert_at.x, insert_at.y, sheet_id).into(),
-            values: CellValues::from_flat_array(headers.len() as u32, 1, headers),
-        });
+/// Applies an Excel sheet's cell formats, borders, column widths, row
+/// heights, and tab color to an imported sheet. Returns the features that
+/// could not be imported.
+///
+/// `last_column` is the last column with data; column widths that Excel
+/// applies to the rest of the sheet are not imported past it.
+fn import_excel_formats(
+    sheet: &mut Sheet,
+    metadata: &XlsxWorkbook,
+    sheet_metadata: &XlsxSheet,
+    last_column: i64,
+) -> Vec<ImportWarning> {
+    let mut counts: BTreeMap<ImportWarningKind, u32> = BTreeMap::new();
+    let mut warn = |kind: ImportWarningKind, count: u32| {
+        if count > 0 {
+            *counts.entry(kind).or_default() += count;
+        }
+    };
+    let styles = &metadata.styles;
+    let resolve = |color: &XlsxColor| color.resolve(&metadata.theme_colors);
+
+    for (pos, &style) in sheet_metadata.cell_styles.iter() {
+        // first row in excel is 1, but first row in quadratic is 0 (see
+        // import_excel_operations)
+        let pos = Pos {
+            x: pos.x,
+            y: pos.y + 1,
+        };
+
+        if let Some(numeric_format) = styles.numeric_format(style) {
+            sheet.set_formatting_value::<NumericFormat>(pos, Some(numeric_format));
+        } else if styles.has_unsupported_format(style) {
+            warn(ImportWarningKind::UnsupportedNumberFormat, 1);
+        }
+
+        let Some(xf) = styles.cell_xf(style) else {
+            continue;
+        };
 
-        let reader = builder.build()?;
-
-        let mut height = 0;
-        let mut current_size = 0;
-        for (row_index, batch) in reader.enumerate() {
-            let batch = batch?;
-            let num_rows = batch.num_rows();
-            let num_cols = batch.num_columns();
-
-            current_size += num_rows;
-            width = width.max(num_cols as u32);
-            height = height.max(num_rows as u32);
-
-            for col_index in 0..num_cols {
-                let col = batch.column(col_index);
-                let values: CellValues = col.try_into()?;
-
-                let operations = Operation::SetCellValues {
-                    sheet_pos: (
-                        insert_at.x + col_index as i64,
-                        insert_at.y + (row_index * num_rows) as i64 + 1,
-                        sheet_id,
-                    )
-                        .into(),
-                    values,
+        if let Some(font) = styles.fonts.get(xf.font_id as usize) {
+            if font.bold {
+                sheet.set_formatting_value::<Bold>(pos, Some(true));
+            }
+            if font.italic {
+                sheet.set_formatting_value::<Italic>(pos, Some(true));
+            }
+            if let Some(color) = font.color.filter(|color| !color.is_default()) {
+                match resolve(&color) {
+                    // black is the default text color
+                    Some(rgba) if rgba == Rgba::default() => {}
+                    Some(rgba) => {
+                        sheet.set_formatting_value::<TextColor>(pos, Some(css_color(rgba)));
+                    }
+                    None => warn(ImportWarningKind::UnsupportedColor, 1),
+                }
+            }
+        }
+
+        if let Some(fill) = styles.fills.get(xf.fill_id as usize) {
+            match fill.pattern.as_deref() {
+                _ if fill.gradient => warn(ImportWarningKind::UnsupportedFill, 1),
+                None | Some("none") => {}
+                Some("solid") => match fill.color.as_ref().and_then(resolve) {
+                    Some(rgba) => {
+                        sheet.set_formatting_value::<FillColor>(pos, Some(css_color(rgba)));
+                    }
+                    None => warn(ImportWarningKind::UnsupportedColor, 1),
+                },
+                Some(_) => warn(ImportWarningKind::UnsupportedFill, 1),
+            }
+        }
+
+        let align = match xf.horizontal.as_deref() {
+            Some("left") => Some(CellAlign::Left),
+            Some("center" | "centerContinuous") => Some(CellAlign::Center),
+            Some("right") => Some(CellAlign::Right),
+            _ => None,
+        };
+        if align.is_some() {
+            sheet.set_formatting_value::<CellAlign>(pos, align);
+        }
+        if xf.wrap_text {
+            sheet.set_formatting_value::<CellWrap>(pos, Some(CellWrap::Wrap));
+        }
+
+        if let Some(border) = styles.borders.get(xf.border_id as usize) {
+            let rect = Rect::single_pos(pos);
+            for (side, selection) in [
+                (&border.left, BorderSelection::Left),
+                (&border.top, BorderSelection::Top),
+                (&border.right, BorderSelection::Right),
+                (&border.bottom, BorderSelection::Bottom),
+            ] {
+                let Some(side) = side else {
+                    continue;
                 };
-                ops.push(operations);
-
-                // update the progress bar every time there's a new operation
-                if cfg!(target_family = "wasm") {
-                    crate::wasm_bindings::js::jsImportProgress(
-                        file_name,
-                        current_size as u32,
-                        total_size,
-                        insert_at.x,
-                        insert_at.y,
-                        width,
-                        height,
-                    );
+                let (line, supported) = excel_border_line(side);
+                if !supported {
+                    warn(ImportWarningKind::UnsupportedBorderStyle, 1);
                 }
+                let color = side
+                    .color
+                    .filter(|color| !color.is_default())
+                    .and_then(|color| resolve(&color))
+                    .unwrap_or_default();
+                let borders = generate_borders(
+                    sheet,
+                    &rect,
+                    vec![selection],
+                    Some(BorderStyle { color, line }),
+                );
+                set_rect_borders(sheet, &rect, borders);
+            }
+            if border.diagonal {
+                warn(ImportWarningKind::UnsupportedBorderStyle, 1);
             }
         }
+    }
 
-        Ok(ops)
+    // widths are in characters of the default font and heights are in points
+    for columns in sheet_metadata.columns.iter() {
+        if columns.hidden {
+            warn(
+                ImportWarningKind::HiddenRowsOrColumns,
+                (columns.last - columns.first + 1) as u32,
+            );
+        }
+        if let Some(width) = columns.width {
+            for x in columns.first..=columns.last.min(last_column.max(columns.first)) {
+                sheet
+                    .offsets
+                    .set_column_width(x, (width * 7.0 + 5.0).round());
+            }
+        }
+    }
+    for (&row, &height) in sheet_metadata.row_heights.iter() {
+        sheet
+            .offsets
+            .set_row_height(row + 1, (height / 0.75).round());
+    }
+
+    if let Some(tab_color) = sheet_metadata.tab_color.filter(|color| !color.is_default()) {
+        match resolve(&tab_color) {
+            Some(rgba) => sheet.color = Some(css_color(rgba)),
+            None => warn(ImportWarningKind::UnsupportedColor, 1),
+        }
     }
+
+    warn(ImportWarningKind::MergedCells, sheet_metadata.merged_cells);
+    warn(
+        ImportWarningKind::ConditionalFormatting,
+        sheet_metadata.conditional_formats,
+    );
+    warn(
+        ImportWarningKind::DataValidation,
+        sheet_metadata.data_validations,
+    );
+    warn(ImportWarningKind::Drawings, sheet_metadata.drawings);
+    warn(
+        ImportWarningKind::HiddenRowsOrColumns,
+        sheet_metadata.hidden_rows,
+    );
+
+    counts
+        .into_iter()
+        .map(|(kind, count)| ImportWarning::new(Some(&sheet_metadata.name), kind, count))
+        .collect()
+}
+
+/// Returns the closest border line to an Excel border style, and whether it
+/// is an exact match.
+fn excel_border_line(side: &XlsxBorderSide) -> (CellBorderLine, bool) {
+    match side.style.as_str() {
+        "thin" | "hair" => (CellBorderLine::Line1, true),
+        "medium" => (CellBorderLine::Line2, true),
+        "thick" => (CellBorderLine::Line3, true),
+        "dotted" => (CellBorderLine::Dotted, true),
+        "dashed" => (CellBorderLine::Dashed, true),
+        "double" => (CellBorderLine::Double, true),
+        "mediumDashed" | "mediumDashDot" | "mediumDashDotDot" | "slantDashDot" | "dashDot"
+        | "dashDotDot" => (CellBorderLine::Dashed, false),
+        _ => (CellBorderLine::Line1, false),
+    }
+}
+
+fn ascii_byte(c: char) -> Option<u8> {
+    c.is_ascii().then_some(c as u8)
+}
+
+/// Returns the delimiter that splits the first lines of a file most
+/// consistently, preferring delimiters that appear the same number of times
+/// on every line. Defaults to a comma.
+fn sniff_delimiter(text: &str, quote: char) -> u8 {
+    let lines = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(CSV_SNIFF_LINES)
+        .collect::<Vec<_>>();
+
+    let count_outside_quotes = |line: &str, delimiter: u8| {
+        let mut in_quotes = false;
+        line.chars()
+            .filter(|&c| {
+                if c == quote {
+                    in_quotes = !in_quotes;
+                }
+                !in_quotes && c == delimiter as char
+            })
+            .count()
+    };
+
+    CSV_DELIMITERS
+        .iter()
+        .map(|&delimiter| {
+            let counts = lines
+                .iter()
+                .map(|line| count_outside_quotes(line, delimiter))
+                .collect::<Vec<_>>();
+            let total = counts.iter().sum::<usize>();
+            let consistent = total > 0 && counts.windows(2).all(|w| w[0] == w[1]);
+            (delimiter, consistent, total)
+        })
+        .filter(|(_, _, total)| *total > 0)
+        .max_by_key(|(_, consistent, total)| (*consistent, *total))
+        .map_or(b',', |(delimiter, _, _)| delimiter)
+}
+
+/// UTF-16 files either start with a byte order mark or, for mostly ASCII
+/// text, have many zero bytes.
+fn looks_like_utf16(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0xFF, 0xFE])
+        || bytes.starts_with(&[0xFE, 0xFF])
+        || bytes.iter().filter(|&&b| b == 0).count() * 4 >= bytes.len()
 }
 
 fn read_utf16(bytes: &[u8]) -> Option<String> {
@@ -330,7 +1234,11 @@ fn read_utf16(bytes: &[u8]) -> Option<String> {
 mod test {
     use super::read_utf16;
     use super::*;
-    use crate::CellValue;
+    use crate::{
+        grid::{get_cell_borders_in_rect, CellSide, NumericFormatKind},
+        xlsx::reader::XlsxColumns,
+        CellValue,
+    };
 
     const INVALID_ENCODING_FILE: &[u8] =
         include_bytes!("../../../../quadratic-rust-shared/data/csv/encoding_issue.csv");
@@ -350,7 +1258,13 @@ mod test {
         const SIMPLE_CSV: &str =
             "city,region,country,population\nSouthborough,MA,United States,a lot of people";
 
-        let ops = gc.import_csv_operations(sheet_id, SIMPLE_CSV.as_bytes(), "smallpop.csv", pos);
+        let ops = gc.import_csv_operations(
+            sheet_id,
+            SIMPLE_CSV.as_bytes(),
+            "smallpop.csv",
+            pos,
+            &CsvImportOptions::default(),
+        );
         assert_eq!(ops.as_ref().unwrap().len(), 1);
         assert_eq!(
             ops.unwrap()[0],
@@ -370,6 +1284,90 @@ mod test {
         );
     }
 
+    #[test]
+    fn sniffs_csv_delimiters() {
+        assert_eq!(sniff_delimiter("a,b,c\n1,2,3", '"'), b',');
+        assert_eq!(sniff_delimiter("a\tb\tc\n1\t2,5\t3", '"'), b'\t');
+        assert_eq!(sniff_delimiter("a;b;c\n1,5;2,5;3", '"'), b';');
+        assert_eq!(sniff_delimiter("a|b\n\"x|y\"|z", '"'), b'|');
+        assert_eq!(sniff_delimiter("\"a;b\",c\n\"d;e\",f", '"'), b',');
+        assert_eq!(sniff_delimiter("no delimiters", '"'), b',');
+    }
+
+    #[test]
+    fn decodes_csv_encodings() {
+        let file = [0x93, b'h', b'i', 0x94, b' ', 0xE9];
+        assert_eq!(CsvEncoding::Windows1252.decode(&file), "“hi” é");
+        assert_eq!(CsvEncoding::Latin1.decode(&file), "\u{93}hi\u{94} é");
+        assert_eq!(CsvEncoding::Auto.decode(&file), "“hi” é");
+        assert_eq!(
+            CsvEncoding::Auto.decode("\u{FEFF}héllo".as_bytes()),
+            "héllo"
+        );
+        assert_eq!(
+            CsvEncoding::Auto.decode(INVALID_ENCODING_FILE),
+            read_utf16(INVALID_ENCODING_FILE).unwrap()
+        );
+    }
+
+    #[test]
+    fn imports_a_csv_with_options() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+        let pos = Pos { x: 0, y: 0 };
+
+        let csv = "Exported 2024\nname;zip;amount\nBob;02134;10\n";
+        let options = CsvImportOptions {
+            header_row: true,
+            skip_rows: 1,
+            text_columns: vec![1],
+            ..Default::default()
+        };
+        let ops = gc
+            .import_csv_operations(sheet_id, csv.as_bytes(), "options.csv", pos, &options)
+            .unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                Operation::SetCellValues {
+                    sheet_pos: SheetPos {
+                        x: 0,
+                        y: 0,
+                        sheet_id
+                    },
+                    values: CellValues::from(vec![
+                        vec!["name".into(), "zip".into(), "amount".into()],
+                        vec!["Bob".into(), "02134".into(), CellValue::Number(10.into())],
+                    ]),
+                },
+                Operation::SetCellFormats {
+                    sheet_rect: SheetRect::from_numbers(0, 0, 3, 1, sheet_id),
+                    attr: CellFmtArray::Bold(RunLengthEncoding::repeat(Some(true), 3)),
+                },
+            ]
+        );
+
+        let options = CsvImportOptions {
+            delimiter: Some(','),
+            infer_types: false,
+            ..Default::default()
+        };
+        let ops = gc
+            .import_csv_operations(sheet_id, "1,a;b\n".as_bytes(), "options.csv", pos, &options)
+            .unwrap();
+        assert_eq!(
+            ops,
+            vec![Operation::SetCellValues {
+                sheet_pos: SheetPos {
+                    x: 0,
+                    y: 0,
+                    sheet_id
+                },
+                values: CellValues::from(vec![vec!["1"], vec!["a;b"]]),
+            }]
+        );
+    }
+
     #[test]
     fn imports_a_long_csv() {
         let mut gc = GridController::test();
@@ -381,7 +1379,13 @@ mod test {
             csv.push_str(&format!("city{},MA,United States,{}\n", i, i * 1000));
         }
 
-        let ops = gc.import_csv_operations(sheet_id, csv.as_bytes(), "long.csv", pos);
+        let ops = gc.import_csv_operations(
+            sheet_id,
+            csv.as_bytes(),
+            "long.csv",
+            pos,
+            &CsvImportOptions::default(),
+        );
         assert_eq!(ops.as_ref().unwrap().len(), 3);
         let first_pos = match ops.as_ref().unwrap()[0] {
             Operation::SetCellValues { sheet_pos, .. } => sheet_pos,
@@ -412,6 +1416,44 @@ mod test {
         );
     }
 
+    #[test]
+    fn imports_json() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+        let file = b"{\"city\": \"Southborough\", \"geo\": {\"state\": \"MA\"}}\n\
+                     {\"city\": \"Boston\", \"population\": 650706}\n";
+
+        let ops = gc
+            .import_json_operations(sheet_id, file, "cities.ndjson", Pos { x: 1, y: 2 })
+            .unwrap();
+        assert_eq!(ops.len(), 3);
+        assert_eq!(
+            ops[0],
+            Operation::SetCellValues {
+                sheet_pos: (1, 2, sheet_id).into(),
+                values: CellValues::from(vec![vec!["city"], vec!["geo.state"], vec!["population"]]),
+            }
+        );
+
+        gc.import_json(sheet_id, file, "cities.ndjson", Pos { x: 1, y: 2 }, None)
+            .unwrap();
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(
+            sheet.cell_value((2, 3).into()),
+            Some(CellValue::Text("MA".into()))
+        );
+        assert_eq!(sheet.cell_value((2, 4).into()), None);
+        assert_eq!(
+            sheet.cell_value((3, 4).into()),
+            Some(CellValue::Number(650706.into()))
+        );
+        assert_eq!(sheet.format_cell(1, 2, false).bold, Some(true));
+
+        assert!(gc
+            .import_json_operations(sheet_id, b"[{]", "invalid.json", Pos::default())
+            .is_err());
+    }
+
     #[test]
     fn import_excel() {
         let mut gc = GridController::test_blank();
@@ -440,6 +1482,37 @@ mod test {
         assert_eq!(sheet.cell_value((3, 1).into()), None);
     }
 
+    #[test]
+    fn import_excel_number_formats() {
+        let mut gc = GridController::test_blank();
+        let file =
+            include_bytes!("../../../../quadratic-rust-shared/data/excel/financial_sample.xlsx");
+        gc.import_excel(file.to_vec(), "financial_sample.xlsx")
+            .unwrap();
+
+        let sheet = gc.sheet(gc.grid.sheets()[0].id);
+
+        // F2 is formatted as accounting
+        let pos = Pos { x: 5, y: 2 };
+        assert_eq!(sheet.cell_value(pos), Some(CellValue::Number(3.into())));
+        assert_eq!(
+            sheet
+                .format_cell(pos.x, pos.y, false)
+                .numeric_format
+                .map(|format| format.kind),
+            Some(NumericFormatKind::Custom)
+        );
+        assert_eq!(
+            sheet
+                .display_string(pos, &CellValue::Number(3.into()))
+                .trim(),
+            "$3.00"
+        );
+
+        // E2 has no number format
+        assert_eq!(sheet.format_cell(4, 2, false).numeric_format, None);
+    }
+
     #[test]
     fn import_excel_invalid() {
         let mut gc = GridController::test_blank();
@@ -447,4 +1520,194 @@ mod test {
         let result = gc.import_excel(file.to_vec(), "invalid.xlsx");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn import_ods() {
+        let mut gc = GridController::test_blank();
+        let file = include_bytes!("../../../test-files/simple.ods");
+        gc.import_excel(file.to_vec(), "simple.ods").unwrap();
+
+        let sheet = gc.sheet(gc.grid.sheets()[0].id);
+        assert_eq!(sheet.name, "Sheet1");
+        assert_eq!(
+            sheet.cell_value((0, 1).into()),
+            Some(CellValue::Number(1.into()))
+        );
+        assert_eq!(
+            sheet.cell_value((1, 2).into()),
+            Some(CellValue::Text("two".into()))
+        );
+        assert_eq!(
+            sheet.cell_value((0, 3).into()),
+            Some(CellValue::Code(CodeCellValue {
+                language: CodeCellLanguage::Formula,
+                code: "SUM(A1:A2)".into()
+            }))
+        );
+        assert_eq!(
+            sheet.cell_value((1, 3).into()),
+            Some(CellValue::Code(CodeCellValue {
+                language: CodeCellLanguage::Formula,
+                code: "IF(A1 > 0, \"yes\", \"no\")".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn detects_workbook_formats() {
+        let xlsx = include_bytes!("../../../test-files/simple.xlsx");
+        let ods = include_bytes!("../../../test-files/simple.ods");
+        let mut xls = OLE_MAGIC.to_vec();
+        xls.extend([0; 504]);
+
+        // the contents take precedence over the extension
+        let detect = WorkbookFormat::detect;
+        assert_eq!(detect(xlsx, "simple.xlsx"), Some(WorkbookFormat::Xlsx));
+        assert_eq!(detect(xlsx, "simple.ods"), Some(WorkbookFormat::Xlsx));
+        assert_eq!(detect(ods, "simple"), Some(WorkbookFormat::Ods));
+        assert_eq!(detect(&xls, "simple.xlsx"), Some(WorkbookFormat::Xls));
+        assert_eq!(detect(b"", "simple.XLSB"), Some(WorkbookFormat::Xlsb));
+        assert_eq!(detect(b"a,b", "simple.csv"), None);
+    }
+
+    #[test]
+    fn import_excel_styles_and_layout() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_values(
+            SheetPos {
+                x: 0,
+                y: 1,
+                sheet_id,
+            },
+            vec![vec!["a", "b", "c"]],
+            None,
+        );
+        let sheet = gc.sheet_mut(sheet_id);
+        sheet.color = Some("rgb(0, 176, 80)".to_string());
+        sheet.set_formatting_value::<Bold>(Pos { x: 0, y: 1 }, Some(true));
+        sheet.set_formatting_value::<Italic>(Pos { x: 0, y: 1 }, Some(true));
+        sheet.set_formatting_value::<TextColor>(
+            Pos { x: 1, y: 1 },
+            Some("rgb(255, 0, 0)".to_string()),
+        );
+        sheet.set_formatting_value::<FillColor>(
+            Pos { x: 1, y: 1 },
+            Some("rgb(255, 255, 0)".to_string()),
+        );
+        sheet.set_formatting_value::<CellAlign>(Pos { x: 2, y: 1 }, Some(CellAlign::Center));
+        sheet.set_formatting_value::<CellWrap>(Pos { x: 2, y: 1 }, Some(CellWrap::Wrap));
+        let rect = Rect::single_pos(Pos { x: 2, y: 1 });
+        let borders = generate_borders(
+            sheet,
+            &rect,
+            vec![BorderSelection::Bottom],
+            Some(BorderStyle {
+                color: Rgba::default(),
+                line: CellBorderLine::Double,
+            }),
+        );
+        set_rect_borders(sheet, &rect, borders);
+        sheet.offsets.set_column_width(1, 180.0);
+        sheet.offsets.set_row_height(1, 40.0);
+        let file = gc.export_excel().unwrap();
+
+        let mut imported = GridController::test_blank();
+        let (_, warnings) = imported
+            .import_excel_operations(file.clone(), "formats.xlsx")
+            .unwrap();
+        assert_eq!(warnings, vec![]);
+
+        imported.import_excel(file, "formats.xlsx").unwrap();
+        let sheet = imported.grid().sheets()[0].clone();
+        assert_eq!(sheet.color, Some("rgb(0, 176, 80)".to_string()));
+
+        let format = sheet.format_cell(0, 1, false);
+        assert_eq!((format.bold, format.italic), (Some(true), Some(true)));
+
+        let format = sheet.format_cell(1, 1, false);
+        assert_eq!(format.text_color, Some("rgb(255, 0, 0)".to_string()));
+        assert_eq!(format.fill_color, Some("rgb(255, 255, 0)".to_string()));
+
+        let format = sheet.format_cell(2, 1, false);
+        assert_eq!(format.align, Some(CellAlign::Center));
+        assert_eq!(format.wrap, Some(CellWrap::Wrap));
+
+        let borders = get_cell_borders_in_rect(&sheet, rect, None);
+        let bottom = borders[0].2.as_ref().unwrap().borders[CellSide::Bottom as usize];
+        assert_eq!(bottom.map(|style| style.line), Some(CellBorderLine::Double));
+
+        assert_eq!(sheet.offsets.column_width(1), 180.0);
+        assert_eq!(sheet.offsets.row_height(1), 40.0);
+    }
+
+    #[test]
+    fn import_excel_formulas() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        let sheet = gc.sheet_mut(sheet_id);
+        let formula = |code: &str| {
+            CellValue::Code(CodeCellValue {
+                language: CodeCellLanguage::Formula,
+                code: code.to_string(),
+            })
+        };
+        sheet.set_cell_value(Pos { x: 0, y: 1 }, CellValue::Number(1.into()));
+        sheet.set_cell_value(Pos { x: 1, y: 1 }, formula("SUM(A1, 2) * 3"));
+        sheet.set_cell_value(Pos { x: 1, y: 2 }, formula("IFERROR(A1, 0)"));
+        sheet.set_cell_value(Pos { x: 1, y: 3 }, formula("IFERROR(A2, 0)"));
+        let file = gc.export_excel().unwrap();
+
+        let mut imported = GridController::test_blank();
+        let (_, warnings) = imported
+            .import_excel_operations(file.clone(), "formulas.xlsx")
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                ImportWarning::new(Some("Sheet 1"), ImportWarningKind::UnsupportedFunction, 2)
+                    .with_detail("IFERROR".to_string())
+            ]
+        );
+
+        imported.import_excel(file, "formulas.xlsx").unwrap();
+        let sheet = imported.grid().sheets()[0].clone();
+        assert_eq!(
+            sheet.cell_value(Pos { x: 1, y: 1 }),
+            Some(formula("SUM(A1, 2) * 3"))
+        );
+        assert_eq!(
+            sheet.cell_value(Pos { x: 1, y: 3 }),
+            Some(formula("IFERROR(A2, 0)"))
+        );
+    }
+
+    #[test]
+    fn import_excel_warnings() {
+        let sheet = XlsxSheet {
+            name: "Sheet1".to_string(),
+            columns: vec![XlsxColumns {
+                first: 2,
+                last: 3,
+                width: None,
+                hidden: true,
+            }],
+            merged_cells: 2,
+            hidden_rows: 1,
+            ..Default::default()
+        };
+        let metadata = XlsxWorkbook {
+            sheets: vec![sheet.clone()],
+            ..Default::default()
+        };
+        let mut grid_sheet = Sheet::test();
+        let warnings = import_excel_formats(&mut grid_sheet, &metadata, &sheet, 0);
+        assert_eq!(
+            warnings,
+            vec![
+                ImportWarning::new(Some("Sheet1"), ImportWarningKind::MergedCells, 2),
+                ImportWarning::new(Some("Sheet1"), ImportWarningKind::HiddenRowsOrColumns, 3),
+            ]
+        );
+    }
 }
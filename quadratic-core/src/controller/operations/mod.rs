@@ -7,8 +7,10 @@ pub mod borders;
 pub mod cell_value;
 pub mod clipboard;
 pub mod code_cell;
+pub mod diff;
 pub mod formats;
 pub mod formatting;
 pub mod import;
 pub mod operation;
+pub mod protection;
 pub mod sheets;
@@ -1,14 +1,15 @@
 use core::fmt;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
     cell_values::CellValues,
     grid::{
         file::sheet_schema::SheetSchema, formats::Formats, formatting::CellFmtArray, CodeRun,
-        Sheet, SheetBorders, SheetId,
+        Protection, Sheet, SheetBorders, SheetId,
     },
     selection::Selection,
-    SheetPos, SheetRect,
+    Rect, SheetPos, SheetRect,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -78,6 +79,13 @@ pub enum Operation {
         order: String,
     },
 
+    // Adds, replaces, or removes (if None) a protected range or sheet.
+    SetProtection {
+        sheet_id: SheetId,
+        protection_id: Uuid,
+        protection: Option<Protection>,
+    },
+
     // Sheet offsets operations
     ResizeColumn {
         sheet_id: SheetId,
@@ -118,6 +126,127 @@ pub enum Operation {
     },
 }
 
+impl Operation {
+    /// Returns the sheets whose contents the operation reads or changes, which
+    /// must be loaded before it is executed.
+    pub fn sheet_ids(&self) -> Vec<SheetId> {
+        match self {
+            Operation::SetCellValues { sheet_pos, .. }
+            | Operation::SetCodeRun { sheet_pos, .. }
+            | Operation::ComputeCode { sheet_pos } => vec![sheet_pos.sheet_id],
+            Operation::SetCellFormats { sheet_rect, .. }
+            | Operation::SetBorders { sheet_rect, .. } => vec![sheet_rect.sheet_id],
+            Operation::SetCellFormatsSelection { selection, .. } => vec![selection.sheet_id],
+            Operation::DuplicateSheet { sheet_id, .. }
+            | Operation::DeleteSheet { sheet_id }
+            | Operation::SetProtection { sheet_id, .. }
+            | Operation::ResizeColumn { sheet_id, .. }
+            | Operation::ResizeRow { sheet_id, .. } => vec![*sheet_id],
+            Operation::MoveCells { source, dest } => vec![source.sheet_id, dest.sheet_id],
+            Operation::AddSheet { .. }
+            | Operation::AddSheetSchema { .. }
+            | Operation::SetSheetName { .. }
+            | Operation::SetSheetColor { .. }
+            | Operation::ReorderSheet { .. }
+            | Operation::SetCursor { .. }
+            | Operation::SetCursorSelection { .. } => vec![],
+        }
+    }
+
+    /// Returns the areas that the operation changes, for checking protections
+    /// and indexing edit history: the cells it changes, or None for changes
+    /// to the sheet itself.  A code run changes its code cell and its whole
+    /// output, even where a spill blocks it.  The output of the code run that
+    /// it replaces is not known from the operation (see `CodeOutputs`).
+    /// Protections are changed by SetProtection, which is checked separately.
+    pub fn changed_areas(&self) -> Vec<(SheetId, Option<Rect>)> {
+        let all = || Some(Rect::new(i64::MIN, i64::MIN, i64::MAX, i64::MAX));
+        let column = |x: i64| Some(Rect::new(x, i64::MIN, x, i64::MAX));
+        let row = |y: i64| Some(Rect::new(i64::MIN, y, i64::MAX, y));
+
+        match self {
+            Operation::SetCellValues { sheet_pos, values } => vec![(
+                sheet_pos.sheet_id,
+                Some(Rect::from_numbers(
+                    sheet_pos.x,
+                    sheet_pos.y,
+                    values.w as i64,
+                    values.h as i64,
+                )),
+            )],
+            Operation::SetCellFormats { sheet_rect, .. }
+            | Operation::SetBorders { sheet_rect, .. } => {
+                vec![(sheet_rect.sheet_id, Some((*sheet_rect).into()))]
+            }
+            Operation::SetCellFormatsSelection { selection, .. } => {
+                let sheet_id = selection.sheet_id;
+                if selection.all {
+                    return vec![(sheet_id, all())];
+                }
+                let rects = selection.rects.iter().flatten().map(|rect| Some(*rect));
+                let columns = selection.columns.iter().flatten().map(|x| column(*x));
+                let rows = selection.rows.iter().flatten().map(|y| row(*y));
+                rects
+                    .chain(columns)
+                    .chain(rows)
+                    .map(|rect| (sheet_id, rect))
+                    .collect()
+            }
+            Operation::MoveCells { source, dest } => vec![
+                (source.sheet_id, Some((*source).into())),
+                (
+                    dest.sheet_id,
+                    Some(Rect::from_numbers(
+                        dest.x,
+                        dest.y,
+                        source.width() as i64,
+                        source.height() as i64,
+                    )),
+                ),
+            ],
+            Operation::DeleteSheet { sheet_id } => vec![(*sheet_id, all())],
+            Operation::SetSheetName { sheet_id, .. }
+            | Operation::SetSheetColor { sheet_id, .. }
+            | Operation::ReorderSheet {
+                target: sheet_id, ..
+            } => vec![(*sheet_id, None)],
+            Operation::ResizeColumn {
+                sheet_id,
+                column: x,
+                ..
+            } => vec![(*sheet_id, column(*x))],
+            Operation::ResizeRow {
+                sheet_id, row: y, ..
+            } => vec![(*sheet_id, row(*y))],
+            Operation::SetCodeRun {
+                sheet_pos,
+                code_run,
+                ..
+            } => {
+                let pos = (*sheet_pos).into();
+                let output = code_run.as_ref().map(|run| run.output_rect(pos, true));
+                [Some(Rect::single_pos(pos)), output]
+                    .into_iter()
+                    .flatten()
+                    .map(|rect| (sheet_pos.sheet_id, Some(rect)))
+                    .collect()
+            }
+            Operation::ComputeCode { sheet_pos } => {
+                vec![(
+                    sheet_pos.sheet_id,
+                    Some(Rect::single_pos((*sheet_pos).into())),
+                )]
+            }
+            Operation::AddSheet { .. }
+            | Operation::AddSheetSchema { .. }
+            | Operation::DuplicateSheet { .. }
+            | Operation::SetProtection { .. }
+            | Operation::SetCursor { .. }
+            | Operation::SetCursorSelection { .. } => vec![],
+        }
+    }
+}
+
 impl fmt::Display for Operation {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -208,6 +337,15 @@ impl fmt::Display for Operation {
             Operation::AddSheetSchema { schema } => {
                 write!(fmt, "AddSheetSchema {{ schema: {:?} }}", schema)
             }
+            Operation::SetProtection {
+                sheet_id,
+                protection_id,
+                protection,
+            } => write!(
+                fmt,
+                "SetProtection {{ sheet_id: {}, protection_id: {}, protection: {:?} }}",
+                sheet_id, protection_id, protection
+            ),
         }
     }
 }
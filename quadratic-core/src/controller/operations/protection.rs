@@ -0,0 +1,616 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use super::operation::Operation;
+use crate::{
+    controller::GridController,
+    error_core::{CoreError, Result},
+    grid::{Protection, Sheet, SheetId},
+    Pos, Rect,
+};
+
+/// The protections of a grid, by sheet.
+pub type SheetProtections = HashMap<SheetId, Vec<Protection>>;
+
+/// The output areas of a grid's code runs, by sheet and code cell.  Replacing
+/// or removing a code run changes the cells of its old output, which the
+/// operation doesn't say.
+pub type CodeOutputs = HashMap<SheetId, HashMap<Pos, Rect>>;
+
+/// Returns the output areas of a sheet's code runs, including outputs that a
+/// spill blocks.
+pub fn sheet_code_outputs(sheet: &Sheet) -> HashMap<Pos, Rect> {
+    sheet
+        .code_runs
+        .iter()
+        .map(|(pos, run)| (*pos, run.output_rect(*pos, true)))
+        .collect()
+}
+
+// updates `outputs` with the code runs that an operation sets, moves, or
+// removes
+fn apply_code_output(operation: &Operation, outputs: &mut CodeOutputs) -> Result<()> {
+    match operation {
+        Operation::SetCodeRun {
+            sheet_pos,
+            code_run,
+            ..
+        } => {
+            let sheet_outputs = outputs.entry(sheet_pos.sheet_id).or_default();
+            let pos = (*sheet_pos).into();
+            match code_run {
+                Some(run) => sheet_outputs.insert(pos, run.output_rect(pos, true)),
+                None => sheet_outputs.remove(&pos),
+            };
+        }
+        Operation::MoveCells { source, dest } => {
+            let source_rect: Rect = (*source).into();
+            let moved: Vec<(Pos, Rect)> = match outputs.get_mut(&source.sheet_id) {
+                Some(sheet_outputs) => {
+                    let positions: Vec<Pos> = sheet_outputs
+                        .keys()
+                        .filter(|pos| source_rect.contains(**pos))
+                        .copied()
+                        .collect();
+                    positions
+                        .into_iter()
+                        .filter_map(|pos| sheet_outputs.remove(&pos).map(|rect| (pos, rect)))
+                        .collect()
+                }
+                None => vec![],
+            };
+            let (dx, dy) = (dest.x - source.min.x, dest.y - source.min.y);
+            let dest_outputs = outputs.entry(dest.sheet_id).or_default();
+            for (pos, mut rect) in moved {
+                rect.translate(dx, dy);
+                dest_outputs.insert(
+                    Pos {
+                        x: pos.x + dx,
+                        y: pos.y + dy,
+                    },
+                    rect,
+                );
+            }
+        }
+        Operation::AddSheet { sheet } => {
+            outputs.insert(sheet.id, sheet_code_outputs(sheet));
+        }
+        Operation::AddSheetSchema { schema } => {
+            outputs.insert(
+                schema.sheet_id(),
+                sheet_code_outputs(&schema.into_latest()?),
+            );
+        }
+        Operation::DuplicateSheet {
+            sheet_id,
+            new_sheet_id,
+        } => {
+            let copied = outputs.get(sheet_id).cloned().unwrap_or_default();
+            outputs.insert(*new_sheet_id, copied);
+        }
+        Operation::DeleteSheet { sheet_id } => {
+            outputs.remove(sheet_id);
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Updates `outputs` with the code runs that operations set, move, or
+/// remove.  Used to catch up with operations that were already checked.
+pub fn apply_code_outputs(operations: &[Operation], outputs: &mut CodeOutputs) -> Result<()> {
+    operations
+        .iter()
+        .try_for_each(|operation| apply_code_output(operation, outputs))
+}
+
+fn find_protection(
+    protections: &SheetProtections,
+    sheet_id: SheetId,
+    protection_id: Uuid,
+) -> Option<&Protection> {
+    protections
+        .get(&sheet_id)?
+        .iter()
+        .find(|protection| protection.id == protection_id)
+}
+
+// checks that a user can add a sheet with protections, which they must own
+fn check_added_sheet(
+    protections: &SheetProtections,
+    sheet_id: SheetId,
+    added: &[Protection],
+    user_id: Option<&str>,
+) -> Result<()> {
+    if protections.contains_key(&sheet_id) {
+        return Err(CoreError::Protected(format!(
+            "sheet {sheet_id} already exists"
+        )));
+    }
+    if added
+        .iter()
+        .any(|protection| Some(protection.owner.as_str()) != user_id)
+    {
+        return Err(CoreError::Protected(
+            "protections must be owned by the user that adds them".into(),
+        ));
+    }
+    Ok(())
+}
+
+// updates `protections` with the sheets and protections that an operation
+// adds, changes, or removes
+fn apply_operation(operation: &Operation, protections: &mut SheetProtections) -> Result<()> {
+    match operation {
+        Operation::SetProtection {
+            sheet_id,
+            protection_id,
+            protection,
+        } => {
+            let sheet_protections = protections.entry(*sheet_id).or_default();
+            sheet_protections.retain(|p| p.id != *protection_id);
+            sheet_protections.extend(protection.clone());
+        }
+        Operation::AddSheet { sheet } => {
+            protections.insert(sheet.id, sheet.protections.clone());
+        }
+        Operation::AddSheetSchema { schema } => {
+            protections.insert(schema.sheet_id(), schema.protections()?);
+        }
+        Operation::DuplicateSheet {
+            sheet_id,
+            new_sheet_id,
+        } => {
+            let copied = protections.get(sheet_id).cloned().unwrap_or_default();
+            protections.insert(*new_sheet_id, copied);
+        }
+        Operation::DeleteSheet { sheet_id } => {
+            protections.remove(sheet_id);
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Updates `protections` with the sheets and protections that operations
+/// add, change, or remove, without checking them.  Used to catch up with
+/// operations that were already checked.
+pub fn apply_protections(
+    operations: &[Operation],
+    protections: &mut SheetProtections,
+) -> Result<()> {
+    operations
+        .iter()
+        .try_for_each(|operation| apply_operation(operation, protections))
+}
+
+/// Checks that a user (None if unknown) can apply operations to sheets with
+/// the given protections.  `protections` has an entry for every known sheet,
+/// and is updated with the sheets and protections that the operations add,
+/// change, or remove.  `outputs` are the outputs of the sheets' code runs,
+/// which are checked when a code run replaces them, and are updated too.
+///
+/// Returns the reason that the operations cannot be applied.
+pub fn check_protections(
+    operations: &[Operation],
+    user_id: Option<&str>,
+    protections: &mut SheetProtections,
+    outputs: &mut CodeOutputs,
+) -> Result<()> {
+    for operation in operations {
+        let mut areas = operation.changed_areas();
+        if let Operation::SetCodeRun { sheet_pos, .. } = operation {
+            let old_output = outputs
+                .get(&sheet_pos.sheet_id)
+                .and_then(|sheet_outputs| sheet_outputs.get(&(*sheet_pos).into()));
+            if let Some(old_output) = old_output {
+                areas.push((sheet_pos.sheet_id, Some(*old_output)));
+            }
+        }
+
+        for (sheet_id, rect) in areas {
+            let blocked = protections.get(&sheet_id).and_then(|protections| {
+                protections
+                    .iter()
+                    .find(|protection| protection.covers(rect) && !protection.can_edit(user_id))
+            });
+            if let Some(protection) = blocked {
+                return Err(CoreError::Protected(format!(
+                    "sheet {sheet_id}: {} can only be changed by the protection's owner and editors",
+                    protection.description()
+                )));
+            }
+        }
+
+        match operation {
+            Operation::SetProtection {
+                sheet_id,
+                protection_id,
+                protection,
+            } => {
+                let existing = find_protection(protections, *sheet_id, *protection_id);
+                let owner = existing.or(protection.as_ref()).map(|p| p.owner.as_str());
+                if owner.is_some() && owner != user_id {
+                    return Err(CoreError::Protected(match existing {
+                        Some(_) => {
+                            format!("only the owner of protection {protection_id} can change it")
+                        }
+                        None => "protections must be owned by the user that adds them".into(),
+                    }));
+                }
+            }
+            Operation::AddSheet { sheet } => {
+                check_added_sheet(protections, sheet.id, &sheet.protections, user_id)?;
+            }
+            Operation::AddSheetSchema { schema } => {
+                let added = schema.protections()?;
+                check_added_sheet(protections, schema.sheet_id(), &added, user_id)?;
+            }
+            Operation::DuplicateSheet {
+                sheet_id,
+                new_sheet_id,
+            } => {
+                let copied = protections.get(sheet_id).cloned().unwrap_or_default();
+
+                // the owner of the sheet can copy everyone's protections
+                let owns_sheet = copied.iter().any(|protection| {
+                    protection.rect.is_none() && Some(protection.owner.as_str()) == user_id
+                });
+                if !owns_sheet {
+                    check_added_sheet(protections, *new_sheet_id, &copied, user_id)?;
+                } else if protections.contains_key(new_sheet_id) {
+                    return Err(CoreError::Protected(format!(
+                        "sheet {new_sheet_id} already exists"
+                    )));
+                }
+            }
+            _ => (),
+        }
+
+        apply_operation(operation, protections)?;
+        apply_code_output(operation, outputs)?;
+    }
+
+    Ok(())
+}
+
+impl GridController {
+    /// Returns the protections of the grid's sheets.
+    pub fn sheet_protections(&self) -> SheetProtections {
+        self.grid
+            .sheets()
+            .iter()
+            .filter(|sheet| !sheet.protections.is_empty())
+            .map(|sheet| (sheet.id, sheet.protections.clone()))
+            .collect()
+    }
+
+    /// Returns the outputs of the code runs of the grid's loaded sheets.
+    pub fn code_outputs(&self) -> CodeOutputs {
+        self.grid
+            .sheets()
+            .iter()
+            .map(|sheet| (sheet.id, sheet_code_outputs(sheet)))
+            .collect()
+    }
+
+    /// Checks that the current user can apply operations to the grid.
+    pub fn check_protections(&self, operations: &[Operation]) -> Result<()> {
+        let mut protections = self
+            .grid
+            .sheets()
+            .iter()
+            .map(|sheet| (sheet.id, sheet.protections.clone()))
+            .collect();
+
+        check_protections(
+            operations,
+            self.user_id.as_deref(),
+            &mut protections,
+            &mut self.code_outputs(),
+        )
+    }
+
+    fn owned_protection(&self, sheet_id: SheetId, protection_id: Uuid) -> Result<&Protection> {
+        let protection = self
+            .try_sheet(sheet_id)
+            .and_then(|sheet| sheet.protection(protection_id))
+            .ok_or_else(|| {
+                CoreError::Protected(format!("protection {protection_id} was not found"))
+            })?;
+        if Some(protection.owner.as_str()) != self.user_id.as_deref() {
+            return Err(CoreError::Protected(format!(
+                "only the owner of protection {protection_id} can change it"
+            )));
+        }
+        Ok(protection)
+    }
+
+    /// Protects a range of cells, or the whole sheet if `rect` is None.  The
+    /// current user owns the protection.
+    pub fn add_protection_operations(
+        &self,
+        sheet_id: SheetId,
+        rect: Option<Rect>,
+        editors: Vec<String>,
+    ) -> Result<Vec<Operation>> {
+        let owner = self.user_id.clone().ok_or_else(|| {
+            CoreError::Protected("only signed in users can add protections".into())
+        })?;
+        let protection = Protection::new(rect, owner, editors);
+        Ok(vec![Operation::SetProtection {
+            sheet_id,
+            protection_id: protection.id,
+            protection: Some(protection),
+        }])
+    }
+
+    /// Replaces the users that can edit a protection.  Only its owner can
+    /// change it.
+    pub fn set_protection_editors_operations(
+        &self,
+        sheet_id: SheetId,
+        protection_id: Uuid,
+        editors: Vec<String>,
+    ) -> Result<Vec<Operation>> {
+        let protection = self.owned_protection(sheet_id, protection_id)?;
+        Ok(vec![Operation::SetProtection {
+            sheet_id,
+            protection_id,
+            protection: Some(Protection {
+                editors,
+                ..protection.clone()
+            }),
+        }])
+    }
+
+    /// Removes a protection.  Only its owner can remove it.
+    pub fn remove_protection_operations(
+        &self,
+        sheet_id: SheetId,
+        protection_id: Uuid,
+    ) -> Result<Vec<Operation>> {
+        self.owned_protection(sheet_id, protection_id)?;
+        Ok(vec![Operation::SetProtection {
+            sheet_id,
+            protection_id,
+            protection: None,
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::grid::{CodeRun, CodeRunResult};
+    use crate::{cell_values::CellValues, Array, CellValue, SheetPos, Value};
+
+    // checks operations on sheets without code runs
+    fn check(
+        operations: &[Operation],
+        user_id: Option<&str>,
+        protections: &mut SheetProtections,
+    ) -> Result<()> {
+        check_protections(operations, user_id, protections, &mut CodeOutputs::new())
+    }
+
+    fn set_value(sheet_id: SheetId, x: i64, y: i64) -> Operation {
+        Operation::SetCellValues {
+            sheet_pos: SheetPos::new(sheet_id, x, y),
+            values: CellValues::from(CellValue::Text("a".into())),
+        }
+    }
+
+    #[test]
+    fn checks_protected_cells() {
+        let sheet_id = SheetId::new();
+        let range = Protection::new(Some(Rect::new(0, 0, 2, 0)), "owner".into(), vec![]);
+        let mut protections = SheetProtections::from([(sheet_id, vec![range])]);
+
+        let operations = vec![set_value(sheet_id, 1, 0)];
+        assert_eq!(
+            check(&operations, Some("other"), &mut protections),
+            Err(CoreError::Protected(format!(
+                "sheet {sheet_id}: cells A0:C0 can only be changed by the protection's owner and editors"
+            )))
+        );
+        assert!(check(&operations, Some("owner"), &mut protections).is_ok());
+
+        // cells outside of the range and other sheets can be changed
+        let operations = vec![set_value(sheet_id, 1, 1), set_value(SheetId::new(), 1, 0)];
+        assert!(check(&operations, None, &mut protections).is_ok());
+
+        // range protections don't cover the sheet's name
+        let operations = vec![Operation::SetSheetName {
+            sheet_id,
+            name: "name".into(),
+        }];
+        assert!(check(&operations, None, &mut protections).is_ok());
+
+        // but they do cover deleting the sheet
+        let operations = vec![Operation::DeleteSheet { sheet_id }];
+        assert!(check(&operations, None, &mut protections).is_err());
+    }
+
+    #[test]
+    fn checks_code_outputs() {
+        let sheet_id = SheetId::new();
+        let range = Protection::new(Some(Rect::new(2, 0, 3, 3)), "owner".into(), vec![]);
+        let mut protections = SheetProtections::from([(sheet_id, vec![range])]);
+        let mut outputs = CodeOutputs::new();
+        let set_code_run = |x: i64, y: i64, output: Option<Vec<Vec<&str>>>| {
+            let code_run = output.map(|output| CodeRun {
+                formatted_code_string: None,
+                std_out: None,
+                std_err: None,
+                cells_accessed: HashSet::new(),
+                result: CodeRunResult::Ok(Value::Array(Array::from(output))),
+                return_type: None,
+                spill_error: false,
+                line_number: None,
+                output_type: None,
+                last_modified: Utc::now(),
+            });
+            vec![Operation::SetCodeRun {
+                sheet_pos: SheetPos::new(sheet_id, x, y),
+                code_run,
+                index: 0,
+            }]
+        };
+
+        // the output spills into the protected range
+        let spill = set_code_run(0, 0, Some(vec![vec!["1", "2", "3"]]));
+        assert!(check_protections(&spill, Some("other"), &mut protections, &mut outputs).is_err());
+        assert!(outputs.is_empty());
+        check_protections(&spill, Some("owner"), &mut protections, &mut outputs).unwrap();
+        assert_eq!(
+            outputs[&sheet_id][&Pos { x: 0, y: 0 }],
+            Rect::new(0, 0, 2, 0)
+        );
+
+        // removing or replacing the run changes its old output
+        let clear = set_code_run(0, 0, None);
+        assert!(check_protections(&clear, Some("other"), &mut protections, &mut outputs).is_err());
+        let replace = set_code_run(0, 0, Some(vec![vec!["1"]]));
+        assert!(check_protections(&replace, None, &mut protections, &mut outputs).is_err());
+
+        // code in the protected range can't be run either
+        let compute = vec![Operation::ComputeCode {
+            sheet_pos: SheetPos::new(sheet_id, 2, 2),
+        }];
+        assert!(check_protections(&compute, None, &mut protections, &mut outputs).is_err());
+
+        // runs outside of the range can be changed
+        let outside = set_code_run(0, 5, Some(vec![vec!["1", "2"]]));
+        assert!(check_protections(&outside, None, &mut protections, &mut outputs).is_ok());
+        check_protections(&clear, Some("owner"), &mut protections, &mut outputs).unwrap();
+        assert!(!outputs[&sheet_id].contains_key(&Pos { x: 0, y: 0 }));
+    }
+
+    #[test]
+    fn checks_changes_to_protections() {
+        let sheet_id = SheetId::new();
+        let protection = Protection::new(None, "owner".into(), vec!["editor".into()]);
+        let protection_id = protection.id;
+        let mut protections = SheetProtections::new();
+        let set = |protection: Option<Protection>| {
+            vec![Operation::SetProtection {
+                sheet_id,
+                protection_id,
+                protection,
+            }]
+        };
+
+        assert_eq!(
+            check(
+                &set(Some(protection.clone())),
+                Some("editor"),
+                &mut protections
+            ),
+            Err(CoreError::Protected(
+                "protections must be owned by the user that adds them".into()
+            ))
+        );
+        check(
+            &set(Some(protection.clone())),
+            Some("owner"),
+            &mut protections,
+        )
+        .unwrap();
+        assert_eq!(protections[&sheet_id], vec![protection]);
+
+        // editors can change the sheet, but not its protection
+        let operations = vec![set_value(sheet_id, 5, 5)];
+        assert!(check(&operations, Some("editor"), &mut protections).is_ok());
+        assert_eq!(
+            check(&set(None), Some("editor"), &mut protections),
+            Err(CoreError::Protected(format!(
+                "only the owner of protection {protection_id} can change it"
+            )))
+        );
+
+        check(&set(None), Some("owner"), &mut protections).unwrap();
+        assert!(protections[&sheet_id].is_empty());
+    }
+
+    #[test]
+    fn checks_protections_of_added_sheets() {
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let mut sheet = grid.sheet(sheet_id).clone();
+        let protection = Protection::new(None, "owner".into(), vec![]);
+        sheet.protections = vec![protection.clone()];
+        let mut protections = SheetProtections::from([(sheet_id, vec![protection])]);
+
+        // existing sheets cannot be replaced
+        let operations = vec![Operation::AddSheet {
+            sheet: sheet.clone(),
+        }];
+        assert_eq!(
+            check(&operations, Some("owner"), &mut protections),
+            Err(CoreError::Protected(format!(
+                "sheet {sheet_id} already exists"
+            )))
+        );
+
+        // added sheets cannot have protections owned by other users
+        sheet.id = SheetId::new();
+        let operations = vec![Operation::AddSheet {
+            sheet: sheet.clone(),
+        }];
+        assert_eq!(
+            check(&operations, Some("other"), &mut protections),
+            Err(CoreError::Protected(
+                "protections must be owned by the user that adds them".into()
+            ))
+        );
+        assert!(check(&operations, Some("owner"), &mut protections).is_ok());
+
+        // only the sheet's owner can copy its protections
+        let duplicate = |new_sheet_id| {
+            vec![Operation::DuplicateSheet {
+                sheet_id,
+                new_sheet_id,
+            }]
+        };
+        assert!(check(&duplicate(SheetId::new()), Some("other"), &mut protections).is_err());
+        assert!(check(&duplicate(SheetId::new()), Some("owner"), &mut protections).is_ok());
+        assert!(check(&duplicate(sheet.id), Some("owner"), &mut protections).is_err());
+
+        grid.set_user_id(Some("other".into()));
+        assert!(grid.check_protections(&duplicate(SheetId::new())).is_ok());
+    }
+
+    #[test]
+    fn applies_protections_without_checking_them() {
+        let sheet_id = SheetId::new();
+        let new_sheet_id = SheetId::new();
+        let protection = Protection::new(None, "owner".into(), vec![]);
+        let mut protections = SheetProtections::from([(sheet_id, vec![])]);
+
+        let operations = vec![
+            Operation::SetProtection {
+                sheet_id,
+                protection_id: protection.id,
+                protection: Some(protection.clone()),
+            },
+            Operation::DuplicateSheet {
+                sheet_id,
+                new_sheet_id,
+            },
+            Operation::DeleteSheet { sheet_id },
+        ];
+        apply_protections(&operations, &mut protections).unwrap();
+        assert_eq!(
+            protections,
+            SheetProtections::from([(new_sheet_id, vec![protection])])
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use crate::{
+    error_core::CoreError,
     grid::{js_types::JsRenderFill, RenderSize, SheetId},
     selection::Selection,
     wasm_bindings::controller::sheet_info::{SheetBounds, SheetInfo},
@@ -152,7 +153,7 @@ impl GridController {
     pub fn send_add_sheet(&self, sheet_id: SheetId, transaction: &PendingTransaction) {
         if (cfg!(target_family = "wasm") || cfg!(test)) && !transaction.is_server() {
             if let Some(sheet) = self.try_sheet(sheet_id) {
-                let sheet_info = SheetInfo::from(sheet);
+                let sheet_info = SheetInfo::new(sheet, self.user_id());
                 if let Ok(sheet_info) = serde_json::to_string(&sheet_info) {
                     crate::wasm_bindings::js::jsAddSheet(
                         sheet_info,
@@ -173,11 +174,19 @@ impl GridController {
         }
     }
 
+    /// Sends the reason that the user's changes were not applied to the
+    /// client
+    pub fn send_protection_error(&self, error: &CoreError) {
+        if cfg!(target_family = "wasm") || cfg!(test) {
+            crate::wasm_bindings::js::jsProtectionError(error.to_string());
+        }
+    }
+
     /// Sends sheet info to the client
     pub fn send_sheet_info(&self, sheet_id: SheetId) {
         if cfg!(target_family = "wasm") || cfg!(test) {
             if let Some(sheet) = self.try_sheet(sheet_id) {
-                let sheet_info = SheetInfo::from(sheet);
+                let sheet_info = SheetInfo::new(sheet, self.user_id());
                 if let Ok(sheet_info) = serde_json::to_string(&sheet_info) {
                     crate::wasm_bindings::js::jsSheetInfoUpdate(sheet_info);
                 }
@@ -185,6 +194,39 @@ impl GridController {
         }
     }
 
+    /// Sends the contents of a sheet that was loaded after the file was
+    /// opened.
+    pub fn send_loaded_sheet(&self, sheet_id: SheetId) {
+        if !cfg!(target_family = "wasm") && !cfg!(test) {
+            return;
+        }
+        let Some(sheet) = self.try_sheet(sheet_id) else {
+            return;
+        };
+        self.send_sheet_info(sheet_id);
+        sheet.send_all_render_cells();
+        if let Ok(fills) = serde_json::to_string(&self.sheet_fills(sheet_id)) {
+            crate::wasm_bindings::js::jsSheetFills(sheet_id.to_string(), fills);
+        }
+        sheet.send_sheet_fills();
+        if let Ok(borders) = serde_json::to_string(&sheet.render_borders()) {
+            crate::wasm_bindings::js::jsSheetBorders(sheet_id.to_string(), borders);
+        }
+        let code = sheet.get_all_render_code_cells();
+        if !code.is_empty() {
+            if let Ok(code) = serde_json::to_string(&code) {
+                crate::wasm_bindings::js::jsSheetCodeCell(sheet_id.to_string(), code);
+            }
+        }
+        let html = sheet.get_html_output();
+        if !html.is_empty() {
+            if let Ok(html) = serde_json::to_string(&html) {
+                crate::wasm_bindings::js::jsHtmlOutput(html);
+            }
+        }
+        sheet.send_all_images();
+    }
+
     pub fn send_image(&self, sheet_pos: SheetPos) {
         if cfg!(target_family = "wasm") || cfg!(test) {
             if let Some(sheet) = self.try_sheet(sheet_pos.sheet_id) {
@@ -1,6 +1,7 @@
 use super::GridController;
 use crate::grid::Sheet;
 use crate::grid::SheetId;
+use anyhow::Result;
 
 impl GridController {
     pub fn sheet_ids(&self) -> Vec<SheetId> {
@@ -16,7 +17,9 @@ impl GridController {
     }
 
     pub fn try_sheet_from_name(&mut self, name: String) -> Option<&Sheet> {
-        self.grid.try_sheet_from_name(name)
+        let sheet_id = self.grid.try_sheet_from_name(name)?.id;
+        self.load_sheet_on_access(sheet_id);
+        self.grid.try_sheet(sheet_id)
     }
 
     pub fn try_sheet_mut_from_name(&mut self, name: String) -> Option<&mut Sheet> {
@@ -31,6 +34,41 @@ impl GridController {
         self.grid.try_sheet_mut_from_string_id(id)
     }
 
+    /// Loads a sheet that was not decoded when the file was opened, and sends
+    /// its contents to the client. Returns whether the sheet was loaded by
+    /// this call.
+    pub fn load_sheet(&mut self, sheet_id: SheetId) -> Result<bool> {
+        let loaded = self.grid.load_sheet(sheet_id)?;
+        if loaded {
+            self.send_loaded_sheet(sheet_id);
+        }
+        Ok(loaded)
+    }
+
+    /// Loads the next sheet that was not decoded when the file was opened,
+    /// skipping sheets that could not be decoded. Returns whether any sheets
+    /// remain to be loaded.
+    pub fn load_next_sheet(&mut self) -> Result<bool> {
+        let pending = self
+            .grid
+            .unloaded_sheet_ids()
+            .into_iter()
+            .filter(|sheet_id| self.grid.sheet_load_error(*sheet_id).is_none())
+            .collect::<Vec<_>>();
+        if let Some(sheet_id) = pending.first() {
+            self.load_sheet(*sheet_id)?;
+        }
+        Ok(pending.len() > 1)
+    }
+
+    /// Loads a sheet before it is accessed. A sheet that cannot be decoded
+    /// stays empty, and the error is logged. Exports fail until it loads.
+    pub(crate) fn load_sheet_on_access(&mut self, sheet_id: SheetId) {
+        if let Err(e) = self.load_sheet(sheet_id) {
+            dbgjs!(format!("Unable to load sheet {sheet_id}: {e}"));
+        }
+    }
+
     #[cfg(test)]
     pub fn sheet(&self, sheet_id: SheetId) -> &Sheet {
         self.try_sheet(sheet_id).unwrap()
@@ -49,7 +87,11 @@ impl GridController {
 
 #[cfg(test)]
 mod test {
-    use crate::{controller::GridController, grid::SheetId};
+    use crate::{
+        controller::GridController,
+        grid::{file, CodeCellLanguage, SheetId},
+        CellValue, Pos, SheetPos,
+    };
 
     #[test]
     fn test_sheet_ids() {
@@ -144,4 +186,55 @@ mod test {
         );
         assert_eq!(gc.try_sheet_from_string_id("not found".to_string()), None);
     }
+
+    #[test]
+    fn loads_sheets_on_access() {
+        let mut gc = GridController::test();
+        gc.add_sheet(None);
+        gc.add_sheet(None);
+        let sheet_ids = gc.sheet_ids();
+        let (first, second, third) = (sheet_ids[0], sheet_ids[1], sheet_ids[2]);
+        gc.set_cell_value(SheetPos::new(first, 0, 0), "1".to_string(), None);
+        gc.set_cell_value(SheetPos::new(second, 0, 0), "10".to_string(), None);
+        gc.set_code_cell(
+            SheetPos::new(third, 0, 0),
+            CodeCellLanguage::Formula,
+            "'Sheet 1'!A0 * 2".to_string(),
+            None,
+        );
+        let file = file::export_binary(gc.grid_mut()).unwrap();
+
+        let mut gc = GridController::from_grid(file::import_lazy(&file, None).unwrap(), 0);
+        assert_eq!(gc.grid().unloaded_sheet_ids(), vec![second, third]);
+
+        // a formula that references another sheet loads it
+        gc.set_code_cell(
+            SheetPos::new(first, 1, 0),
+            CodeCellLanguage::Formula,
+            "'Sheet 2'!A0 + 1".to_string(),
+            None,
+        );
+        assert_eq!(gc.grid().unloaded_sheet_ids(), vec![third]);
+        assert_eq!(
+            gc.sheet(first).display_value(Pos { x: 1, y: 0 }),
+            Some(CellValue::Number(11.into()))
+        );
+
+        // changing a cell loads and recomputes code on unloaded sheets
+        gc.set_cell_value(SheetPos::new(first, 0, 0), "5".to_string(), None);
+        assert!(gc.grid().unloaded_sheet_ids().is_empty());
+        assert_eq!(
+            gc.sheet(third).display_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Number(10.into()))
+        );
+
+        // the remaining sheets can be loaded in the background
+        let mut gc = GridController::from_grid(file::import_lazy(&file, None).unwrap(), 0);
+        assert!(gc.load_next_sheet().unwrap());
+        assert!(!gc.load_next_sheet().unwrap());
+        assert_eq!(
+            gc.sheet(second).cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Number(10.into()))
+        );
+    }
 }
@@ -1,4 +1,9 @@
-use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::{
@@ -39,6 +44,19 @@ pub struct TransactionServer {
     pub file_id: Uuid,
     pub operations: Vec<Operation>,
     pub sequence_num: u64,
+
+    // who sent the transaction (None for transactions stored before authors
+    // were recorded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<TransactionAuthor>,
+}
+
+/// The user that sent a transaction, and when the server sequenced it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TransactionAuthor {
+    pub user_id: String,
+    pub session_id: Uuid,
+    pub timestamp: DateTime<Utc>,
 }
 
 // From doesn't work since we don't have file_id
@@ -54,9 +72,86 @@ impl Into<Transaction> for TransactionServer {
     }
 }
 
+/// Serializes and compresses operations or transactions for the binary
+/// multiplayer protocol.
+pub fn serialize_and_compress<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(vec![], Compression::fast());
+    ciborium::into_writer(value, &mut encoder)
+        .map_err(|e| anyhow!("Could not serialize operations: {e}"))?;
+    Ok(encoder.finish()?)
+}
+
+/// Reads operations or transactions from the binary multiplayer protocol.
+pub fn decompress_and_deserialize<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    decompress_and_deserialize_with_limit(bytes, usize::MAX)
+}
+
+/// Reads operations or transactions from the binary multiplayer protocol,
+/// failing if they decompress to more than `limit` bytes.
+pub fn decompress_and_deserialize_with_limit<T: DeserializeOwned>(
+    bytes: &[u8],
+    limit: usize,
+) -> Result<T> {
+    let mut decoded = vec![];
+    DeflateDecoder::new(bytes)
+        .take(limit.saturating_add(1) as u64)
+        .read_to_end(&mut decoded)?;
+    if decoded.len() > limit {
+        bail!("Operations are larger than {limit} bytes");
+    }
+    ciborium::from_reader(decoded.as_slice())
+        .map_err(|e| anyhow!("Could not deserialize operations: {e}"))
+}
+
 impl GridController {
     /// Marks a transaction as sent by the multiplayer.ts server
     pub fn mark_transaction_sent(&mut self, transaction_id: Uuid) {
         self.transactions.mark_transaction_sent(transaction_id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cell_values::CellValues, grid::SheetId, CellValue, SheetPos};
+
+    #[test]
+    fn serialize_and_compress_operations() {
+        let sheet_id = SheetId::new();
+        let operations = vec![
+            Operation::SetCellValues {
+                sheet_pos: SheetPos::new(sheet_id, 1, 2),
+                values: CellValues::from(CellValue::Text("hello".into())),
+            },
+            Operation::ResizeColumn {
+                sheet_id,
+                column: 3,
+                new_size: 120.0,
+                client_resized: true,
+            },
+        ];
+        let bytes = serialize_and_compress(&operations).unwrap();
+        let decoded: Vec<Operation> = decompress_and_deserialize(&bytes).unwrap();
+        assert_eq!(decoded, operations);
+
+        let transactions = vec![TransactionServer {
+            id: Uuid::new_v4(),
+            file_id: Uuid::new_v4(),
+            operations,
+            sequence_num: 1,
+            author: Some(TransactionAuthor {
+                user_id: "user".into(),
+                session_id: Uuid::new_v4(),
+                timestamp: Utc::now(),
+            }),
+        }];
+        let bytes = serialize_and_compress(&transactions).unwrap();
+        let decoded: Vec<TransactionServer> = decompress_and_deserialize(&bytes).unwrap();
+        assert_eq!(decoded, transactions);
+
+        assert!(decompress_and_deserialize::<Vec<Operation>>(b"not operations").is_err());
+        assert!(
+            decompress_and_deserialize_with_limit::<Vec<TransactionServer>>(&bytes, 10).is_err()
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use crate::controller::active_transactions::transaction_name::TransactionName;
+use crate::controller::operations::import::{
+    CsvBatches, CsvImportOptions, ImportCancellation, ImportJob, ImportLimits, ImportWarning,
+    JsonBatches, ParquetBatches,
+};
 use crate::controller::GridController;
 use crate::{grid::SheetId, Pos};
 use anyhow::Result;
@@ -11,25 +14,75 @@ impl GridController {
         file: &[u8],
         file_name: &str,
         insert_at: Pos,
+        options: &CsvImportOptions,
         cursor: Option<String>,
     ) -> Result<()> {
-        let ops = self.import_csv_operations(sheet_id, file, file_name, insert_at)?;
-        self.start_user_transaction(ops, cursor, TransactionName::Import);
-        Ok(())
+        let import =
+            self.begin_import_csv(sheet_id, file, file_name, insert_at, options, cursor)?;
+        self.finish_import(import)
     }
 
-    /// Imports an Excel file into the grid.
+    /// Starts importing a CSV file into the grid. Its batches are applied by
+    /// calling `continue_import`.
+    pub fn begin_import_csv(
+        &mut self,
+        sheet_id: SheetId,
+        file: &[u8],
+        file_name: &str,
+        insert_at: Pos,
+        options: &CsvImportOptions,
+        cursor: Option<String>,
+    ) -> Result<ImportJob> {
+        let batches = CsvBatches::new(
+            sheet_id,
+            file,
+            file_name,
+            insert_at,
+            options,
+            &self.import_limits,
+        )?;
+        Ok(self.begin_import(batches, cursor))
+    }
+
+    /// Imports a spreadsheet file (xlsx, xlsb, xls, or ods) into the grid.
     ///
-    /// Returns a [`TransactionSummary`].
-    pub fn import_excel(&mut self, file: Vec<u8>, file_name: &str) -> Result<()> {
-        let import_ops = self.import_excel_operations(file, file_name)?;
+    /// Returns the features that could not be imported.
+    pub fn import_excel(&mut self, file: Vec<u8>, file_name: &str) -> Result<Vec<ImportWarning>> {
+        let (import_ops, warnings) = self.import_excel_operations(file, file_name)?;
         self.server_apply_transaction(import_ops);
 
         // Rerun all code cells after importing Excel file
         // This is required to run compute cells in order
         let code_rerun_ops = self.rerun_all_code_cells_operations();
         self.server_apply_transaction(code_rerun_ops);
-        Ok(())
+        Ok(warnings)
+    }
+
+    /// Imports a JSON or NDJSON file into the grid.
+    pub fn import_json(
+        &mut self,
+        sheet_id: SheetId,
+        file: &[u8],
+        file_name: &str,
+        insert_at: Pos,
+        cursor: Option<String>,
+    ) -> Result<()> {
+        let import = self.begin_import_json(sheet_id, file, file_name, insert_at, cursor)?;
+        self.finish_import(import)
+    }
+
+    /// Starts importing a JSON or NDJSON file into the grid. Its batches are
+    /// applied by calling `continue_import`.
+    pub fn begin_import_json(
+        &mut self,
+        sheet_id: SheetId,
+        file: &[u8],
+        file_name: &str,
+        insert_at: Pos,
+        cursor: Option<String>,
+    ) -> Result<ImportJob> {
+        let batches = JsonBatches::new(sheet_id, file, file_name, insert_at, &self.import_limits)?;
+        Ok(self.begin_import(batches, cursor))
     }
 
     /// Imports a Parquet file into the grid.
@@ -41,15 +94,41 @@ impl GridController {
         insert_at: Pos,
         cursor: Option<String>,
     ) -> Result<()> {
-        let ops = self.import_parquet_operations(sheet_id, file, file_name, insert_at)?;
-        self.start_user_transaction(ops, cursor, TransactionName::Import);
-        Ok(())
+        let import = self.begin_import_parquet(sheet_id, file, file_name, insert_at, cursor)?;
+        self.finish_import(import)
+    }
+
+    /// Starts importing a Parquet file into the grid. Its batches are applied
+    /// by calling `continue_import`.
+    pub fn begin_import_parquet(
+        &mut self,
+        sheet_id: SheetId,
+        file: Vec<u8>,
+        file_name: &str,
+        insert_at: Pos,
+        cursor: Option<String>,
+    ) -> Result<ImportJob> {
+        let batches =
+            ParquetBatches::new(sheet_id, file, file_name, insert_at, &self.import_limits)?;
+        Ok(self.begin_import(batches, cursor))
+    }
+
+    /// Sets the limits on the size of imported files.
+    pub fn set_import_limits(&mut self, limits: ImportLimits) {
+        self.import_limits = limits;
+    }
+
+    /// Returns a handle that cancels the CSV, JSON, or Parquet import in
+    /// progress.
+    pub fn import_cancellation(&self) -> ImportCancellation {
+        self.import_cancellation.clone()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
+        controller::operations::{import::ImportBatches, operation::Operation},
         grid::{CodeCellLanguage, CodeRunResult},
         test_util::{assert_cell_value_row, print_table},
         wasm_bindings::js::clear_js_calls,
@@ -80,8 +159,14 @@ mod tests {
         let sheet_id = grid_controller.grid.sheets()[0].id;
         let pos = Pos { x: 0, y: 0 };
 
-        let _ =
-            grid_controller.import_csv(sheet_id, scv_file.as_slice(), "smallpop.csv", pos, None);
+        let _ = grid_controller.import_csv(
+            sheet_id,
+            scv_file.as_slice(),
+            "smallpop.csv",
+            pos,
+            &CsvImportOptions::default(),
+            None,
+        );
 
         print_table(
             &grid_controller,
@@ -114,7 +199,14 @@ mod tests {
         let sheet_id = grid_controller.grid.sheets()[0].id;
         let pos = Pos { x: 0, y: 0 };
 
-        let result = grid_controller.import_csv(sheet_id, "".as_bytes(), "smallpop.csv", pos, None);
+        let result = grid_controller.import_csv(
+            sheet_id,
+            "".as_bytes(),
+            "smallpop.csv",
+            pos,
+            &CsvImportOptions::default(),
+            None,
+        );
         assert!(result.is_err());
     }
 
@@ -133,12 +225,169 @@ mod tests {
             csv.as_bytes(),
             "large.csv",
             Pos { x: 0, y: 0 },
+            &CsvImportOptions::default(),
             None,
         );
         assert!(result.is_ok());
         clear_js_calls();
     }
 
+    #[test]
+    fn import_large_csv_is_undone_at_once() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+        let csv = (0..25000).map(|y| format!("{y},a\n")).collect::<String>();
+        gc.import_csv(
+            sheet_id,
+            csv.as_bytes(),
+            "large.csv",
+            Pos { x: 0, y: 0 },
+            &CsvImportOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_cell_value_row(&gc, sheet_id, 0, 1, 24999, vec!["24999", "a"]);
+        assert_eq!(gc.undo_stack.len(), 1);
+
+        gc.undo(None);
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(sheet.cell_value(Pos { x: 0, y: 0 }), None);
+        assert_eq!(sheet.cell_value(Pos { x: 1, y: 24999 }), None);
+        clear_js_calls();
+    }
+
+    #[test]
+    fn errors_on_import_limits() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+        let csv = "a\nb\nc\n";
+        let import = |gc: &mut GridController| {
+            gc.import_csv(
+                sheet_id,
+                csv.as_bytes(),
+                "limited.csv",
+                Pos { x: 0, y: 0 },
+                &CsvImportOptions::default(),
+                None,
+            )
+        };
+
+        gc.set_import_limits(ImportLimits {
+            max_rows: 2,
+            ..Default::default()
+        });
+        let error = import(&mut gc).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "limited.csv has more than 2 rows, which is the import limit"
+        );
+
+        gc.set_import_limits(ImportLimits {
+            max_bytes: 4,
+            ..Default::default()
+        });
+        let error = import(&mut gc).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "limited.csv is 6 bytes, which is more than the import limit of 4 bytes"
+        );
+        assert_eq!(gc.sheet(sheet_id).cell_value(Pos { x: 0, y: 0 }), None);
+        assert!(gc.undo_stack.is_empty());
+    }
+
+    /// Batches that cancel the import after the first batch is read.
+    struct CancelledBatches {
+        cancellation: ImportCancellation,
+        batches: Vec<Vec<Operation>>,
+    }
+
+    impl ImportBatches for CancelledBatches {
+        fn next_batch(&mut self, _gc: &mut GridController) -> Result<Option<Vec<Operation>>> {
+            self.cancellation.cancel();
+            Ok(self.batches.pop())
+        }
+    }
+
+    #[test]
+    fn cancelled_import_is_rolled_back() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+        gc.set_cell_value((0, 0, sheet_id).into(), "original".into(), None);
+
+        let batches = CancelledBatches {
+            cancellation: gc.import_cancellation(),
+            batches: vec![
+                vec![Operation::SetCellValues {
+                    sheet_pos: (0, 1, sheet_id).into(),
+                    values: CellValue::Text("second".into()).into(),
+                }],
+                vec![Operation::SetCellValues {
+                    sheet_pos: (0, 0, sheet_id).into(),
+                    values: CellValue::Text("first".into()).into(),
+                }],
+            ],
+        };
+        let import = gc.begin_import(batches, None);
+        let error = gc.finish_import(import).unwrap_err();
+        assert_eq!(error.to_string(), "Import cancelled");
+
+        let sheet = gc.sheet(sheet_id);
+        assert_eq!(
+            sheet.cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("original".into()))
+        );
+        assert_eq!(sheet.cell_value(Pos { x: 0, y: 1 }), None);
+        assert_eq!(gc.undo_stack.len(), 1);
+
+        // the handle is reset for the next import
+        assert!(!gc.import_cancellation().is_cancelled());
+        clear_js_calls();
+    }
+
+    /// Batches that are read in order.
+    struct TestBatches(Vec<Vec<Operation>>);
+
+    impl ImportBatches for TestBatches {
+        fn next_batch(&mut self, _gc: &mut GridController) -> Result<Option<Vec<Operation>>> {
+            Ok(self.0.pop())
+        }
+    }
+
+    #[test]
+    fn import_continues_one_batch_at_a_time() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.grid.sheets()[0].id;
+        let batch = |y: i64| {
+            vec![Operation::SetCellValues {
+                sheet_pos: (0, y, sheet_id).into(),
+                values: CellValue::Text(y.to_string()).into(),
+            }]
+        };
+
+        let mut import = gc.begin_import(TestBatches(vec![batch(1), batch(0)]), None);
+        assert!(gc.continue_import(&mut import).unwrap());
+        assert_eq!(
+            gc.sheet(sheet_id).cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("0".into()))
+        );
+        assert_eq!(gc.sheet(sheet_id).cell_value(Pos { x: 0, y: 1 }), None);
+
+        assert!(gc.continue_import(&mut import).unwrap());
+        assert!(gc.undo_stack.is_empty());
+
+        // the transaction is finalized once there are no more batches
+        assert!(!gc.continue_import(&mut import).unwrap());
+        assert_eq!(
+            gc.sheet(sheet_id).cell_value(Pos { x: 0, y: 1 }),
+            Some(CellValue::Text("1".into()))
+        );
+        assert_eq!(gc.undo_stack.len(), 1);
+
+        assert!(!gc.continue_import(&mut import).unwrap());
+        assert_eq!(gc.undo_stack.len(), 1);
+        clear_js_calls();
+    }
+
     #[test]
     fn import_problematic_line() {
         let mut gc = GridController::test();
@@ -149,6 +398,7 @@ mod tests {
                 csv.as_bytes(),
                 "bad line",
                 Pos { x: 0, y: 0 },
+                &CsvImportOptions::default(),
             )
             .unwrap();
         let op = &ops[0];
@@ -199,7 +449,7 @@ mod tests {
             vec![
                 "",
                 "Hello",
-                "2016-10-20 00:00:00",
+                "2016-10-20",
                 "",
                 "1.1",
                 "2024-01-01 13:00:00",
@@ -316,8 +566,8 @@ mod tests {
                 "2024-05-08 19:49:07",                  // timestamp
                 "2024-05-08 19:49:07",                  // timestamptz
                 "2024-05-08",                           // date
-                "00:01:11",                             // time
-                "00:01:11",                             // timetz
+                "0:01:11",                              // time
+                "0:01:11",                              // timetz
                 "",                                     // interval
                 "4599689c-7048-47dc-abf7-f7e9ee636578", // uuid
                 "{\"a\":\"b\"}",                        // json
@@ -357,8 +607,15 @@ mod tests {
         let sheet_id = gc.grid.sheets()[0].id;
         let pos = Pos { x: 0, y: 0 };
 
-        gc.import_csv(sheet_id, scv_file.as_slice(), "test.csv", pos, None)
-            .expect("import_csv");
+        gc.import_csv(
+            sheet_id,
+            scv_file.as_slice(),
+            "test.csv",
+            pos,
+            &CsvImportOptions::default(),
+            None,
+        )
+        .expect("import_csv");
 
         print_table(&gc, sheet_id, Rect::new_span(pos, Pos { x: 3, y: 4 }));
 
@@ -374,8 +631,15 @@ mod tests {
         let sheet_id = gc.grid.sheets()[0].id;
         let pos = Pos { x: 0, y: 0 };
 
-        gc.import_csv(sheet_id, scv_file.as_slice(), "test.csv", pos, None)
-            .expect("import_csv");
+        gc.import_csv(
+            sheet_id,
+            scv_file.as_slice(),
+            "test.csv",
+            pos,
+            &CsvImportOptions::default(),
+            None,
+        )
+        .expect("import_csv");
 
         print_table(&gc, sheet_id, Rect::new_span(pos, Pos { x: 3, y: 4 }));
 
@@ -392,8 +656,15 @@ mod tests {
         let sheet_id = gc.grid.sheets()[0].id;
         let pos = Pos { x: 0, y: 0 };
 
-        gc.import_csv(sheet_id, scv_file.as_slice(), "test.csv", pos, None)
-            .expect("import_csv");
+        gc.import_csv(
+            sheet_id,
+            scv_file.as_slice(),
+            "test.csv",
+            pos,
+            &CsvImportOptions::default(),
+            None,
+        )
+        .expect("import_csv");
 
         print_table(&gc, sheet_id, Rect::new_span(pos, Pos { x: 2, y: 3 }));
 
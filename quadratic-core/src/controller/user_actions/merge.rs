@@ -0,0 +1,23 @@
+use crate::controller::active_transactions::transaction_name::TransactionName;
+use crate::controller::operations::diff::{merge_grids, MergeConflict};
+use crate::controller::GridController;
+use crate::grid::Grid;
+
+impl GridController {
+    /// Merges the changes that `theirs` made since `base` into the grid.
+    ///
+    /// Returns the changes that could not be merged because the grid changed
+    /// the same thing differently.
+    pub fn merge(
+        &mut self,
+        base: &Grid,
+        theirs: &Grid,
+        cursor: Option<String>,
+    ) -> Vec<MergeConflict> {
+        let merge = merge_grids(base, self.grid(), theirs);
+        if !merge.operations.is_empty() {
+            self.start_user_transaction(merge.operations, cursor, TransactionName::Merge);
+        }
+        merge.conflicts
+    }
+}
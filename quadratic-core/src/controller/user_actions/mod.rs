@@ -8,5 +8,7 @@ pub mod code;
 pub mod formats;
 pub mod formatting;
 pub mod import;
+pub mod merge;
+pub mod protections;
 pub mod sheets;
 pub mod undo;
@@ -0,0 +1,160 @@
+use uuid::Uuid;
+
+use crate::{
+    controller::{
+        active_transactions::transaction_name::TransactionName, operations::operation::Operation,
+        GridController,
+    },
+    error_core::Result,
+    grid::SheetId,
+    Rect,
+};
+
+impl GridController {
+    /// Protects a range of cells, or the whole sheet if `rect` is None, so
+    /// that only the current user and `editors` can change it.  Returns the
+    /// id of the protection.
+    pub fn add_protection(
+        &mut self,
+        sheet_id: SheetId,
+        rect: Option<Rect>,
+        editors: Vec<String>,
+        cursor: Option<String>,
+    ) -> Result<Uuid> {
+        let ops = self.add_protection_operations(sheet_id, rect, editors)?;
+        let protection_id = match ops.first() {
+            Some(Operation::SetProtection { protection_id, .. }) => *protection_id,
+            _ => unreachable!("expected a SetProtection operation"),
+        };
+        self.start_user_transaction(ops, cursor, TransactionName::SetProtection);
+        Ok(protection_id)
+    }
+
+    pub fn set_protection_editors(
+        &mut self,
+        sheet_id: SheetId,
+        protection_id: Uuid,
+        editors: Vec<String>,
+        cursor: Option<String>,
+    ) -> Result<()> {
+        let ops = self.set_protection_editors_operations(sheet_id, protection_id, editors)?;
+        self.start_user_transaction(ops, cursor, TransactionName::SetProtection);
+        Ok(())
+    }
+
+    pub fn remove_protection(
+        &mut self,
+        sheet_id: SheetId,
+        protection_id: Uuid,
+        cursor: Option<String>,
+    ) -> Result<()> {
+        let ops = self.remove_protection_operations(sheet_id, protection_id)?;
+        self.start_user_transaction(ops, cursor, TransactionName::SetProtection);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use crate::{
+        error_core::CoreError,
+        wasm_bindings::{controller::sheet_info::SheetInfo, js::expect_js_call},
+        CellValue, Pos, SheetPos,
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn protects_a_range() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_user_id(Some("owner".into()));
+        let protection_id = gc
+            .add_protection(sheet_id, Some(Rect::new(0, 0, 1, 0)), vec![], None)
+            .unwrap();
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "owner".into(), None);
+
+        // the lock is shown to other users
+        gc.set_user_id(Some("other".into()));
+        let sheet_info = SheetInfo::new(gc.sheet(sheet_id), gc.user_id());
+        assert!(!sheet_info.protections[0].can_edit);
+        expect_js_call(
+            "jsSheetInfoUpdate",
+            serde_json::to_string(&sheet_info).unwrap(),
+            true,
+        );
+
+        // who cannot change the range or the protection
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "other".into(), None);
+        expect_js_call(
+            "jsProtectionError",
+            format!(
+                "Protected: sheet {sheet_id}: cells A0:B0 can only be changed by the protection's owner and editors"
+            ),
+            true,
+        );
+        assert_eq!(
+            gc.sheet(sheet_id).cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("owner".into()))
+        );
+        assert_eq!(
+            gc.remove_protection(sheet_id, protection_id, None),
+            Err(CoreError::Protected(format!(
+                "only the owner of protection {protection_id} can change it"
+            )))
+        );
+
+        // or undo the owner's changes
+        gc.undo(None);
+        expect_js_call(
+            "jsProtectionError",
+            format!(
+                "Protected: sheet {sheet_id}: cells A0:B0 can only be changed by the protection's owner and editors"
+            ),
+            true,
+        );
+
+        // but can change cells outside of it
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 1), "other".into(), None);
+        assert_eq!(
+            gc.sheet(sheet_id).cell_value(Pos { x: 0, y: 1 }),
+            Some(CellValue::Text("other".into()))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn changes_protection_editors() {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_user_id(Some("owner".into()));
+        let protection_id = gc.add_protection(sheet_id, None, vec![], None).unwrap();
+        gc.set_protection_editors(sheet_id, protection_id, vec!["editor".into()], None)
+            .unwrap();
+
+        gc.set_user_id(Some("editor".into()));
+        gc.set_sheet_name(sheet_id, "Editor".into(), None);
+        assert_eq!(gc.sheet(sheet_id).name, "Editor");
+
+        gc.set_user_id(Some("owner".into()));
+        gc.remove_protection(sheet_id, protection_id, None).unwrap();
+        assert!(gc.sheet(sheet_id).protections.is_empty());
+
+        // undoing the removal restores the protection
+        gc.undo(None);
+        assert_eq!(
+            gc.sheet(sheet_id)
+                .protection(protection_id)
+                .unwrap()
+                .editors,
+            vec!["editor".to_string()]
+        );
+
+        // signed out users cannot add protections
+        gc.set_user_id(None);
+        assert!(gc.add_protection(sheet_id, None, vec![], None).is_err());
+    }
+}
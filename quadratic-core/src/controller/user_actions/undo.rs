@@ -1,8 +1,22 @@
 use uuid::Uuid;
 
-use crate::controller::{execution::TransactionType, GridController};
+use crate::controller::{execution::TransactionType, transaction::Transaction, GridController};
 
 impl GridController {
+    // transactions that change protected cells stay on their stack
+    fn can_undo_redo(&self, transaction: Option<&Transaction>) -> bool {
+        let Some(transaction) = transaction else {
+            return true;
+        };
+        match self.check_protections(&transaction.operations) {
+            Ok(()) => true,
+            Err(error) => {
+                self.send_protection_error(&error);
+                false
+            }
+        }
+    }
+
     pub fn has_undo(&self) -> bool {
         !self.undo_stack.is_empty()
     }
@@ -10,6 +24,9 @@ impl GridController {
         !self.redo_stack.is_empty()
     }
     pub fn undo(&mut self, cursor: Option<String>) {
+        if !self.can_undo_redo(self.undo_stack.last()) {
+            return;
+        }
         if let Some(mut transaction) = self.undo_stack.pop() {
             // we need to assign the transaction a new id to avoid conflicts with the original transaction.
             transaction.id = Uuid::new_v4();
@@ -17,6 +34,9 @@ impl GridController {
         }
     }
     pub fn redo(&mut self, cursor: Option<String>) {
+        if !self.can_undo_redo(self.redo_stack.last()) {
+            return;
+        }
         if let Some(mut transaction) = self.redo_stack.pop() {
             // we need to assign the transaction a new id to avoid conflicts with the original transaction.
             transaction.id = Uuid::new_v4();
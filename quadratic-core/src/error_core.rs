@@ -30,6 +30,9 @@ pub enum CoreError {
 
     #[error("CodeCellSheetError: {0}")]
     CodeCellSheetError(String),
+
+    #[error("Protected: {0}")]
+    Protected(String),
 }
 
 impl From<serde_json::Error> for CoreError {
@@ -48,6 +48,16 @@ impl From<CellRef> for RangeRef {
     }
 }
 impl RangeRef {
+    /// Returns the name of the sheet the range is on, if it is on another
+    /// sheet.
+    pub fn sheet_name(&self) -> Option<&String> {
+        match self {
+            RangeRef::RowRange { sheet, .. } | RangeRef::ColRange { sheet, .. } => sheet.as_ref(),
+            RangeRef::CellRange { start, .. } => start.sheet.as_ref(),
+            RangeRef::Cell { pos } => pos.sheet.as_ref(),
+        }
+    }
+
     /// Returns the human-friendly string representing this range reference in
     /// A1-style notation.
     pub fn a1_string(self, base: Pos) -> String {
@@ -11,7 +11,8 @@ use regex::Regex;
 
 use super::wildcard_pattern_to_regex;
 use crate::{
-    Array, CellValue, CodeResult, CoerceInto, RunError, RunErrorMsg, SpannableIterExt, Spanned,
+    Array, CellValue, CodeResult, CoerceInto, DateLocale, Instant, RunError, RunErrorMsg,
+    SpannableIterExt, Spanned,
 };
 
 #[derive(Debug, Clone)]
@@ -48,6 +49,8 @@ impl TryFrom<Spanned<&CellValue>> for Criterion {
                     CellValue::Logical(false)
                 } else if let Ok(n) = BigDecimal::from_str(rhs_string) {
                     CellValue::Number(n)
+                } else if let Some(instant) = Instant::parse(rhs_string, DateLocale::default()) {
+                    CellValue::Instant(instant)
                 } else if compare_fn == CompareFn::Eql && rhs_string.contains(['?', '*']) {
                     // If the string doesn't contain any `?` or `*`, then Excel
                     // treats all `~` as literal.
@@ -89,6 +92,9 @@ impl Criterion {
             CellValue::Text(rhs) => compare_fn.compare(&lhs.to_string().to_ascii_lowercase(), rhs),
             CellValue::Number(rhs) => match lhs {
                 CellValue::Number(lhs) => compare_fn.compare(lhs, rhs),
+                // dates compare as serial numbers
+                CellValue::Instant(lhs) => BigDecimal::try_from(lhs.to_serial())
+                    .is_ok_and(|lhs| compare_fn.compare(&lhs, rhs)),
                 _ => false,
             },
             CellValue::Logical(rhs) => match lhs {
@@ -97,6 +103,10 @@ impl Criterion {
             },
             CellValue::Instant(rhs) => match lhs {
                 CellValue::Instant(lhs) => compare_fn.compare(lhs, rhs),
+                CellValue::Number(lhs) => BigDecimal::try_from(rhs.to_serial())
+                    .is_ok_and(|rhs| compare_fn.compare(lhs, &rhs)),
+                CellValue::Text(lhs) => Instant::parse(lhs, DateLocale::default())
+                    .is_some_and(|lhs| compare_fn.compare(&lhs, rhs)),
                 _ => false,
             },
             CellValue::Duration(rhs) => match lhs {
@@ -417,4 +427,27 @@ mod tests {
             &["hello qq"],
         );
     }
+
+    #[test]
+    fn test_formula_date_criteria() {
+        let date = |s| CellValue::Instant(Instant::parse(s, DateLocale::default()).unwrap());
+
+        let c = make_criterion(">=2024-01-15");
+        assert!(matches(&c, date("2024-01-15")));
+        assert!(matches(&c, date("2024-02-01 08:00")));
+        assert!(!matches(&c, date("2023-12-31")));
+        assert!(matches(&c, "2024-03-01"));
+        assert!(!matches(&c, "not a date"));
+
+        // dates compare with their serial number
+        assert!(matches(&c, 45306.0));
+        assert!(!matches(&c, 45305.0));
+        let c = make_criterion("<45306");
+        assert!(matches(&c, date("2024-01-14")));
+        assert!(!matches(&c, date("2024-01-15")));
+
+        let c = make_criterion(date("2024-01-15"));
+        assert!(matches(&c, date("2024-01-15")));
+        assert!(!matches(&c, date("2024-01-16")));
+    }
 }
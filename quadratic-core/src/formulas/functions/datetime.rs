@@ -0,0 +1,291 @@
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime, TimeDelta, Timelike, Utc};
+
+use super::*;
+use crate::{DateLocale, Duration, Instant};
+
+pub const CATEGORY: FormulaFunctionCategory = FormulaFunctionCategory {
+    include_in_docs: true,
+    include_in_completions: true,
+    name: "Date & time functions",
+    docs: "Dates are stored as the number of days since December 30, 1899 \
+           (the same serial numbers used by Excel), so they can be compared \
+           with and added to numbers. Times are fractions of a day.\
+           \n\n",
+    get_functions,
+};
+
+fn get_functions() -> Vec<FormulaFunction> {
+    vec![
+        formula_fn!(
+            /// Returns a date from a year, month, and day.
+            ///
+            /// Months and days outside the usual range roll over into the
+            /// next or previous month or year.
+            #[examples("DATE(2024, 1, 15)", "DATE(A1, B1 + 1, 1)")]
+            #[zip_map]
+            fn DATE([year]: i64, [month]: i64, [day]: i64) {
+                date(year, month, day).map(Instant::from)
+            }
+        ),
+        formula_fn!(
+            /// Returns a time of day from an hour, minute, and second.
+            ///
+            /// Times wrap around at 24 hours.
+            #[examples("TIME(13, 30, 0)")]
+            #[zip_map]
+            fn TIME([hour]: f64, [minute]: f64, [second]: f64) {
+                let seconds = hour * 3600.0 + minute * 60.0 + second;
+                if seconds < 0.0 {
+                    return Err(RunErrorMsg::InvalidArgument.without_span());
+                }
+                Duration::from_seconds(seconds.rem_euclid(86_400.0))
+            }
+        ),
+        formula_fn!(
+            /// Returns the current date and time (in UTC).
+            #[include_args_in_completion(false)]
+            #[examples("NOW()")]
+            fn NOW() {
+                Instant::from(Utc::now().naive_utc())
+            }
+        ),
+        formula_fn!(
+            /// Returns the current date (in UTC).
+            #[include_args_in_completion(false)]
+            #[examples("TODAY()")]
+            fn TODAY() {
+                Instant::from(Utc::now().date_naive())
+            }
+        ),
+        formula_fn!(
+            /// Returns the year of a date.
+            #[examples("YEAR(A1)", "YEAR(\"2024-01-15\")")]
+            #[zip_map]
+            fn YEAR([date]: Instant) {
+                naive_datetime(date).map(|date| date.year() as i64)
+            }
+        ),
+        formula_fn!(
+            /// Returns the month of a date, from 1 (January) to 12
+            /// (December).
+            #[examples("MONTH(A1)")]
+            #[zip_map]
+            fn MONTH([date]: Instant) {
+                naive_datetime(date).map(|date| date.month())
+            }
+        ),
+        formula_fn!(
+            /// Returns the day of the month of a date, from 1 to 31.
+            #[examples("DAY(A1)")]
+            #[zip_map]
+            fn DAY([date]: Instant) {
+                naive_datetime(date).map(|date| date.day())
+            }
+        ),
+        formula_fn!(
+            /// Returns the hour of a date or time, from 0 to 23.
+            #[examples("HOUR(A1)", "HOUR(\"13:45\")")]
+            #[zip_map]
+            fn HOUR([time]: Instant) {
+                naive_datetime(time).map(|time| time.hour())
+            }
+        ),
+        formula_fn!(
+            /// Returns the minute of a date or time, from 0 to 59.
+            #[examples("MINUTE(A1)")]
+            #[zip_map]
+            fn MINUTE([time]: Instant) {
+                naive_datetime(time).map(|time| time.minute())
+            }
+        ),
+        formula_fn!(
+            /// Returns the second of a date or time, from 0 to 59.
+            #[examples("SECOND(A1)")]
+            #[zip_map]
+            fn SECOND([time]: Instant) {
+                naive_datetime(time).map(|time| time.second())
+            }
+        ),
+        formula_fn!(
+            /// Returns the day of the week of a date.
+            ///
+            /// - If `return_type` is `1` or omitted, Sunday is `1` and Saturday
+            ///   is `7`.
+            /// - If `return_type` is `2`, Monday is `1` and Sunday is `7`.
+            /// - If `return_type` is `3`, Monday is `0` and Sunday is `6`.
+            #[examples("WEEKDAY(A1)", "WEEKDAY(A1, 2)")]
+            #[zip_map]
+            fn WEEKDAY([date]: Instant, [return_type]: (Option<i64>)) {
+                let weekday = naive_datetime(date)?.weekday();
+                match return_type.unwrap_or(1) {
+                    1 => Ok(weekday.number_from_sunday()),
+                    2 => Ok(weekday.number_from_monday()),
+                    3 => Ok(weekday.num_days_from_monday()),
+                    _ => Err(RunErrorMsg::InvalidArgument.without_span()),
+                }
+            }
+        ),
+        formula_fn!(
+            /// Returns the date that is `months` months before or after
+            /// `start_date`. If the day does not exist in the resulting month,
+            /// the last day of that month is used.
+            #[examples("EDATE(A1, 1)", "EDATE(\"2024-01-31\", -2)")]
+            #[zip_map]
+            fn EDATE([start_date]: Instant, [months]: i64) {
+                let date = naive_datetime(start_date)?.date();
+                let offset = Months::new(months.unsigned_abs().try_into().unwrap_or(u32::MAX));
+                let date = if months >= 0 {
+                    date.checked_add_months(offset)
+                } else {
+                    date.checked_sub_months(offset)
+                };
+                date.map(Instant::from)
+                    .ok_or_else(|| RunErrorMsg::Overflow.without_span())
+            }
+        ),
+        formula_fn!(
+            /// Converts text to a date.
+            #[examples("DATEVALUE(\"2024-01-15\")", "DATEVALUE(\"Jan 15, 2024\")")]
+            #[zip_map]
+            fn DATEVALUE(span: Span, [date_text]: String) {
+                Instant::parse(&date_text, DateLocale::default())
+                    .and_then(|instant| instant.to_naive_datetime())
+                    .map(|datetime| Instant::from(datetime.date()))
+                    .ok_or_else(|| {
+                        RunErrorMsg::Expected {
+                            expected: "date".into(),
+                            got: Some(date_text.clone().into()),
+                        }
+                        .with_span(span)
+                    })
+            }
+        ),
+    ]
+}
+
+/// Returns a date from a year, month, and day, rolling over months and days
+/// that are out of range. Years from 0 to 1899 are relative to 1900, as in
+/// Excel.
+fn date(year: i64, month: i64, day: i64) -> CodeResult<NaiveDate> {
+    let year = if (0..1900).contains(&year) {
+        year + 1900
+    } else {
+        year
+    };
+    let months = year
+        .checked_mul(12)
+        .and_then(|months| months.checked_add(month.checked_sub(1)?))
+        .ok_or_else(|| RunErrorMsg::Overflow.without_span())?;
+
+    i32::try_from(months.div_euclid(12))
+        .ok()
+        .and_then(|year| NaiveDate::from_ymd_opt(year, months.rem_euclid(12) as u32 + 1, 1))
+        .and_then(|first| first.checked_add_signed(TimeDelta::try_days(day.checked_sub(1)?)?))
+        .ok_or_else(|| RunErrorMsg::Overflow.without_span())
+}
+
+fn naive_datetime(instant: Instant) -> CodeResult<NaiveDateTime> {
+    instant
+        .to_naive_datetime()
+        .ok_or_else(|| RunErrorMsg::Overflow.without_span())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_formula_date() {
+        let g = Grid::new();
+        assert_eq!("2024-01-15", eval_to_string(&g, "DATE(2024, 1, 15)"));
+        assert_eq!("2025-02-01", eval_to_string(&g, "DATE(2024, 13, 32)"));
+        assert_eq!("2023-12-31", eval_to_string(&g, "DATE(2024, 1, 0)"));
+        assert_eq!("1999-03-01", eval_to_string(&g, "DATE(99, 3, 1)"));
+        assert_eq!("45306", eval_to_string(&g, "DATE(2024, 1, 15) + 0"));
+        assert_eq!("TRUE", eval_to_string(&g, "DATE(2024, 1, 15) > 45305"));
+    }
+
+    #[test]
+    fn test_formula_time() {
+        let g = Grid::new();
+        assert_eq!("13:30:00", eval_to_string(&g, "TIME(13, 30, 0)"));
+        assert_eq!("1:00:00", eval_to_string(&g, "TIME(25, 0, 0)"));
+        assert_eq!("0.5", eval_to_string(&g, "TIME(12, 0, 0) + 0"));
+        assert_eq!(
+            RunErrorMsg::InvalidArgument,
+            eval_to_err(&g, "TIME(-1, 0, 0)").msg,
+        );
+    }
+
+    #[test]
+    fn test_formula_date_parts() {
+        let g = Grid::new();
+        assert_eq!("2024", eval_to_string(&g, "YEAR(\"2024-01-15 13:45:30\")"));
+        assert_eq!("1", eval_to_string(&g, "MONTH(\"2024-01-15 13:45:30\")"));
+        assert_eq!("15", eval_to_string(&g, "DAY(\"2024-01-15 13:45:30\")"));
+        assert_eq!("13", eval_to_string(&g, "HOUR(\"2024-01-15 13:45:30\")"));
+        assert_eq!("45", eval_to_string(&g, "MINUTE(\"2024-01-15 13:45:30\")"));
+        assert_eq!("30", eval_to_string(&g, "SECOND(\"2024-01-15 13:45:30\")"));
+        assert_eq!("13", eval_to_string(&g, "HOUR(\"13:45\")"));
+        assert_eq!("2024", eval_to_string(&g, "YEAR(45306)"));
+        assert_eq!(
+            RunErrorMsg::Expected {
+                expected: "date".into(),
+                got: Some("text".into()),
+            },
+            eval_to_err(&g, "YEAR(\"hello\")").msg,
+        );
+    }
+
+    #[test]
+    fn test_formula_weekday() {
+        let g = Grid::new();
+        // 2024-01-15 is a Monday
+        assert_eq!("2", eval_to_string(&g, "WEEKDAY(\"2024-01-15\")"));
+        assert_eq!("1", eval_to_string(&g, "WEEKDAY(\"2024-01-15\", 2)"));
+        assert_eq!("0", eval_to_string(&g, "WEEKDAY(\"2024-01-15\", 3)"));
+        assert_eq!(
+            RunErrorMsg::InvalidArgument,
+            eval_to_err(&g, "WEEKDAY(\"2024-01-15\", 4)").msg,
+        );
+    }
+
+    #[test]
+    fn test_formula_edate() {
+        let g = Grid::new();
+        assert_eq!("2024-02-15", eval_to_string(&g, "EDATE(\"2024-01-15\", 1)"));
+        assert_eq!("2024-02-29", eval_to_string(&g, "EDATE(\"2024-01-31\", 1)"));
+        assert_eq!(
+            "2023-11-30",
+            eval_to_string(&g, "EDATE(\"2024-01-31\", -2)")
+        );
+    }
+
+    #[test]
+    fn test_formula_datevalue() {
+        let g = Grid::new();
+        assert_eq!(
+            "2024-01-15",
+            eval_to_string(&g, "DATEVALUE(\"Jan 15, 2024\")")
+        );
+        assert_eq!(
+            "2024-01-15",
+            eval_to_string(&g, "DATEVALUE(\"2024-01-15 13:45\")")
+        );
+        assert_eq!(
+            RunErrorMsg::Expected {
+                expected: "date".into(),
+                got: Some("hello".into()),
+            },
+            eval_to_err(&g, "DATEVALUE(\"hello\")").msg,
+        );
+    }
+
+    #[test]
+    fn test_formula_now_and_today() {
+        let g = Grid::new();
+        let today = eval_to_string(&g, "TODAY()");
+        assert_eq!(today.len(), "2024-01-15".len());
+        assert!(eval_to_string(&g, "NOW() >= TODAY()") == "TRUE");
+    }
+}
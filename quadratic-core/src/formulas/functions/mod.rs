@@ -6,6 +6,7 @@ use lazy_static::lazy_static;
 
 #[macro_use]
 mod macros;
+mod datetime;
 pub mod excel;
 mod logic;
 mod lookup;
@@ -38,6 +39,7 @@ pub const CATEGORIES: &[FormulaFunctionCategory] = &[
     logic::CATEGORY,
     string::CATEGORY,
     lookup::CATEGORY,
+    datetime::CATEGORY,
 ];
 
 lazy_static! {
@@ -43,7 +43,8 @@ fn get_functions() -> Vec<FormulaFunction> {
             }
         ),
         formula_fn!(
-            /// Returns the number of numeric values.
+            /// Returns the number of numeric values. Dates and times are
+            /// counted as numbers.
             ///
             /// - Blank cells are not counted.
             /// - Cells containing an error are not counted.
@@ -51,7 +52,14 @@ fn get_functions() -> Vec<FormulaFunction> {
             fn COUNT(numbers: (Iter<CellValue>)) {
                 // Ignore error values.
                 numbers
-                    .filter(|x| matches!(x, Ok(CellValue::Number(_))))
+                    .filter(|x| {
+                        matches!(
+                            x,
+                            Ok(CellValue::Number(_)
+                                | CellValue::Instant(_)
+                                | CellValue::Duration(_))
+                        )
+                    })
                     .count() as f64
             }
         ),
@@ -239,6 +247,10 @@ mod tests {
         );
         assert_eq!("1", eval_to_string(&g, "COUNT(2)"));
         assert_eq!("10", eval_to_string(&g, "COUNT(1..10)"));
+        assert_eq!(
+            "2",
+            eval_to_string(&g, "COUNT(DATE(2024, 1, 15), TIME(1, 0, 0), \"x\")")
+        );
         assert_eq!("11", eval_to_string(&g, "COUNT(0..10)"));
         assert_eq!("1", eval_to_string(&g, "COUNT({\"\",1,,,})"));
     }
@@ -93,6 +93,40 @@ pub fn replace_a1_notation(source: &str, pos: Pos) -> String {
     replace_cell_references(source, pos, &replace_fn)
 }
 
+/// Converts a formula to Excel syntax. Cell references are rewritten using
+/// `replace_fn`, and string literals are rewritten with double quotes (Excel
+/// does not support single-quoted strings or backslash escapes).
+pub fn to_excel_formula(source: &str, pos: Pos, replace_fn: &dyn Fn(RangeRef) -> String) -> String {
+    let references = find_cell_references(source, pos);
+    let in_reference = |span: Span| {
+        references
+            .iter()
+            .any(|r| r.span.start <= span.start && span.end <= r.span.end)
+    };
+
+    let mut replacements = lexer::tokenize(source)
+        .filter(|t| t.inner == Token::StringLiteral && !in_reference(t.span))
+        .filter_map(|t| {
+            let literal = &source[t.span.start as usize..t.span.end as usize];
+            let contents = parse_string_literal(literal)?;
+            Some((t.span, format!("\"{}\"", contents.replace('"', "\"\""))))
+        })
+        .chain(
+            references
+                .into_iter()
+                .map(|Spanned { span, inner }| (span, replace_fn(inner))),
+        )
+        .collect_vec();
+    replacements.sort_by_key(|(span, _)| span.start);
+
+    // replace in reverse order to preserve previous span references
+    let mut replaced = source.to_string();
+    for (span, replacement) in replacements.into_iter().rev() {
+        replaced.replace_range::<Range<usize>>(span.into(), &replacement);
+    }
+    replaced
+}
+
 fn replace_cell_references(
     source: &str,
     pos: Pos,
@@ -310,6 +344,19 @@ mod tests {
         assert_eq!(replaced, expected);
     }
 
+    #[test]
+    fn test_to_excel_formula() {
+        let replace_fn = |cell_ref: RangeRef| cell_ref.a1_string(Pos { x: 0, y: 1 });
+        assert_eq!(
+            to_excel_formula("IF(A0 = 'a\\'b', \"x\", B1:C2)", (0, 0).into(), &replace_fn),
+            "IF(A1 = \"a'b\", \"x\", B2:C3)"
+        );
+        assert_eq!(
+            to_excel_formula("'Sheet 2'!A0 & 'say \"hi\"'", (0, 0).into(), &replace_fn),
+            "\"Sheet 2\"!A1 & \"say \"\"hi\"\"\""
+        );
+    }
+
     #[test]
     fn check_formula() {
         assert!(parse_and_check_formula("SUM(10)", 0, 0));
@@ -6,7 +6,7 @@ use crate::grid::{
     formatting::RenderSize,
     generate_borders, set_rect_borders, BorderSelection, BorderStyle, CellAlign, CellBorderLine,
     CellWrap, CodeCellLanguage, CodeRun, CodeRunResult, Column, ColumnData, ConnectionKind, Grid,
-    GridBounds, NumericFormat, NumericFormatKind, Sheet, SheetBorders, SheetId,
+    GridBounds, NumericFormat, NumericFormatKind, Protection, Sheet, SheetBorders, SheetId,
 };
 use crate::sheet_offsets::SheetOffsets;
 use crate::{CellValue, CodeCellValue, Pos, Rect, Value};
@@ -19,6 +19,7 @@ use std::{
     collections::{BTreeMap, HashMap},
     str::FromStr,
 };
+use uuid::Uuid;
 
 use super::CURRENT_VERSION;
 
@@ -78,6 +79,10 @@ fn set_column_format_numeric_format(
                         current::NumericFormatKind::Currency => NumericFormatKind::Currency,
                         current::NumericFormatKind::Percentage => NumericFormatKind::Percentage,
                         current::NumericFormatKind::Exponential => NumericFormatKind::Exponential,
+                        current::NumericFormatKind::Custom => NumericFormatKind::Custom,
+                        current::NumericFormatKind::Date => NumericFormatKind::Date,
+                        current::NumericFormatKind::Time => NumericFormatKind::Time,
+                        current::NumericFormatKind::DateTime => NumericFormatKind::DateTime,
                     },
                     symbol: format.value.symbol.to_owned(),
                 }),
@@ -144,6 +149,39 @@ fn set_column_format_render_size(
     }
 }
 
+pub(crate) fn import_cell_value(value: &current::CellValue) -> Result<CellValue> {
+    Ok(match value {
+        current::CellValue::Blank => CellValue::Blank,
+        current::CellValue::Text(text) => CellValue::Text(text.to_owned()),
+        current::CellValue::Number(number) => CellValue::Number(BigDecimal::from_str(number)?),
+        current::CellValue::Html(html) => CellValue::Html(html.to_owned()),
+        current::CellValue::Code(code_cell) => CellValue::Code(CodeCellValue {
+            code: code_cell.code.to_owned(),
+            language: match code_cell.language {
+                current::CodeCellLanguage::Python => CodeCellLanguage::Python,
+                current::CodeCellLanguage::Formula => CodeCellLanguage::Formula,
+                current::CodeCellLanguage::Connection { ref kind, ref id } => {
+                    CodeCellLanguage::Connection {
+                        kind: match kind {
+                            current::ConnectionKind::Postgres => ConnectionKind::Postgres,
+                            current::ConnectionKind::Mysql => ConnectionKind::Mysql,
+                        },
+                        id: id.clone(),
+                    }
+                }
+                current::CodeCellLanguage::Javascript => CodeCellLanguage::Javascript,
+            },
+        }),
+        current::CellValue::Logical(logical) => CellValue::Logical(*logical),
+        current::CellValue::Instant(instant) => CellValue::Instant(serde_json::from_str(instant)?),
+        current::CellValue::Duration(duration) => {
+            CellValue::Duration(serde_json::from_str(duration)?)
+        }
+        current::CellValue::Error(error) => CellValue::Error(Box::new((*error).clone().into())),
+        current::CellValue::Image(image) => CellValue::Image(image.to_owned()),
+    })
+}
+
 fn import_column_builder(columns: &[(i64, current::Column)]) -> Result<BTreeMap<i64, Column>> {
     columns
         .iter()
@@ -161,44 +199,7 @@ fn import_column_builder(columns: &[(i64, current::Column)]) -> Result<BTreeMap<
             set_column_format_render_size(&mut col.render_size, &column.render_size);
 
             for (y, value) in column.values.iter() {
-                let cell_value = match value {
-                    current::CellValue::Blank => CellValue::Blank,
-                    current::CellValue::Text(text) => CellValue::Text(text.to_owned()),
-                    current::CellValue::Number(number) => {
-                        CellValue::Number(BigDecimal::from_str(number)?)
-                    }
-                    current::CellValue::Html(html) => CellValue::Html(html.to_owned()),
-                    current::CellValue::Code(code_cell) => CellValue::Code(CodeCellValue {
-                        code: code_cell.code.to_owned(),
-                        language: match code_cell.language {
-                            current::CodeCellLanguage::Python => CodeCellLanguage::Python,
-                            current::CodeCellLanguage::Formula => CodeCellLanguage::Formula,
-                            current::CodeCellLanguage::Connection { ref kind, ref id } => {
-                                CodeCellLanguage::Connection {
-                                    kind: match kind {
-                                        current::ConnectionKind::Postgres => {
-                                            ConnectionKind::Postgres
-                                        }
-                                        current::ConnectionKind::Mysql => ConnectionKind::Mysql,
-                                    },
-                                    id: id.clone(),
-                                }
-                            }
-                            current::CodeCellLanguage::Javascript => CodeCellLanguage::Javascript,
-                        },
-                    }),
-                    current::CellValue::Logical(logical) => CellValue::Logical(*logical),
-                    current::CellValue::Instant(instant) => {
-                        CellValue::Instant(serde_json::from_str(instant)?)
-                    }
-                    current::CellValue::Duration(duration) => {
-                        CellValue::Duration(serde_json::from_str(duration)?)
-                    }
-                    current::CellValue::Error(error) => {
-                        CellValue::Error(Box::new((*error).clone().into()))
-                    }
-                    current::CellValue::Image(image) => CellValue::Image(image.to_owned()),
-                };
+                let cell_value = import_cell_value(value)?;
                 if let Ok(y) = y.parse::<i64>() {
                     col.values.insert(y, cell_value);
                 }
@@ -255,10 +256,23 @@ fn import_code_cell_output(type_field: &str, value: &str) -> CellValue {
             "FALSE" => CellValue::Logical(false),
             _ => CellValue::Logical(false),
         },
+        "time instant" => serde_json::from_str(value).map_or(CellValue::Blank, CellValue::Instant),
+        "time duration" => {
+            serde_json::from_str(value).map_or(CellValue::Blank, CellValue::Duration)
+        }
         _ => CellValue::Blank,
     }
 }
 
+/// Instants and durations are stored as JSON so they round-trip exactly.
+fn export_code_cell_output(value: &CellValue) -> String {
+    match value {
+        CellValue::Instant(instant) => serde_json::to_string(instant).unwrap_or_default(),
+        CellValue::Duration(duration) => serde_json::to_string(duration).unwrap_or_default(),
+        _ => value.to_string(),
+    }
+}
+
 fn import_code_cell_builder(sheet: &current::Sheet) -> Result<IndexMap<Pos, CodeRun>> {
     // davidfig: probably the more idiomatic way is to return the code_runs below. It's above my skill level, though.
     let mut code_runs = IndexMap::new();
@@ -332,6 +346,10 @@ fn import_format(format: &current::Format) -> Format {
                     current::NumericFormatKind::Currency => NumericFormatKind::Currency,
                     current::NumericFormatKind::Percentage => NumericFormatKind::Percentage,
                     current::NumericFormatKind::Exponential => NumericFormatKind::Exponential,
+                    current::NumericFormatKind::Custom => NumericFormatKind::Custom,
+                    current::NumericFormatKind::Date => NumericFormatKind::Date,
+                    current::NumericFormatKind::Time => NumericFormatKind::Time,
+                    current::NumericFormatKind::DateTime => NumericFormatKind::DateTime,
                 },
                 symbol: numeric_format.symbol.to_owned(),
             }),
@@ -355,6 +373,20 @@ fn import_formats(format: &[(i64, (current::Format, i64))]) -> BTreeMap<i64, (Fo
         .collect()
 }
 
+pub(crate) fn import_protections(protections: &[current::Protection]) -> Result<Vec<Protection>> {
+    protections
+        .iter()
+        .map(|protection| {
+            Ok(Protection {
+                id: Uuid::from_str(&protection.id)?,
+                rect: protection.rect.clone().map(Rect::from),
+                owner: protection.owner.to_owned(),
+                editors: protection.editors.to_owned(),
+            })
+        })
+        .collect()
+}
+
 pub fn import_sheet(sheet: &current::Sheet) -> Result<Sheet> {
     let mut new_sheet = Sheet {
         id: SheetId::from_str(&sheet.id.id)?,
@@ -375,6 +407,8 @@ pub fn import_sheet(sheet: &current::Sheet) -> Result<Sheet> {
         format_all: sheet.formats_all.as_ref().map(import_format),
         formats_columns: import_formats(&sheet.formats_columns),
         formats_rows: import_formats(&sheet.formats_rows),
+
+        protections: import_protections(&sheet.protections)?,
     };
     new_sheet.recalculate_bounds();
     import_borders_builder(&mut new_sheet, sheet);
@@ -388,6 +422,7 @@ pub fn import(file: current::GridSchema) -> Result<Grid> {
             .into_iter()
             .map(|sheet| import_sheet(&sheet))
             .collect::<Result<_>>()?,
+        unloaded_sheets: HashMap::new(),
     })
 }
 
@@ -459,6 +494,10 @@ fn export_column_data_numeric_format(
                             NumericFormatKind::Exponential => {
                                 current::NumericFormatKind::Exponential
                             }
+                            NumericFormatKind::Custom => current::NumericFormatKind::Custom,
+                            NumericFormatKind::Date => current::NumericFormatKind::Date,
+                            NumericFormatKind::Time => current::NumericFormatKind::Time,
+                            NumericFormatKind::DateTime => current::NumericFormatKind::DateTime,
                         },
                         symbol: block.content.value.symbol.clone(),
                     },
@@ -595,12 +634,12 @@ fn export_column_builder(sheet: &Sheet) -> Vec<(i64, current::Column)> {
                                     CellValue::Logical(logical) => {
                                         current::CellValue::Logical(*logical)
                                     }
-                                    CellValue::Instant(instant) => {
-                                        current::CellValue::Instant(instant.to_string())
-                                    }
-                                    CellValue::Duration(duration) => {
-                                        current::CellValue::Duration(duration.to_string())
-                                    }
+                                    CellValue::Instant(instant) => current::CellValue::Instant(
+                                        serde_json::to_string(instant).unwrap_or_default(),
+                                    ),
+                                    CellValue::Duration(duration) => current::CellValue::Duration(
+                                        serde_json::to_string(duration).unwrap_or_default(),
+                                    ),
                                     CellValue::Error(error) => current::CellValue::Error(
                                         current::RunError::from_grid_run_error(error),
                                     ),
@@ -674,6 +713,10 @@ fn export_format(format: &Format) -> Option<current::Format> {
                         NumericFormatKind::Currency => current::NumericFormatKind::Currency,
                         NumericFormatKind::Percentage => current::NumericFormatKind::Percentage,
                         NumericFormatKind::Exponential => current::NumericFormatKind::Exponential,
+                        NumericFormatKind::Custom => current::NumericFormatKind::Custom,
+                        NumericFormatKind::Date => current::NumericFormatKind::Date,
+                        NumericFormatKind::Time => current::NumericFormatKind::Time,
+                        NumericFormatKind::DateTime => current::NumericFormatKind::DateTime,
                     },
                     symbol: numeric_format.symbol.to_owned(),
                 }
@@ -705,6 +748,18 @@ fn export_formats(formats: &BTreeMap<i64, (Format, i64)>) -> Vec<(i64, (current:
         .collect()
 }
 
+fn export_protections(protections: &[Protection]) -> Vec<current::Protection> {
+    protections
+        .iter()
+        .map(|protection| current::Protection {
+            id: protection.id.to_string(),
+            rect: protection.rect.map(current::Rect::from),
+            owner: protection.owner.to_owned(),
+            editors: protection.editors.to_owned(),
+        })
+        .collect()
+}
+
 pub(crate) fn export_sheet(sheet: &Sheet) -> current::Sheet {
     current::Sheet {
         id: current::Id {
@@ -719,6 +774,7 @@ pub(crate) fn export_sheet(sheet: &Sheet) -> current::Sheet {
         formats_all: sheet.format_all.as_ref().and_then(export_format),
         formats_columns: export_formats(&sheet.formats_columns),
         formats_rows: export_formats(&sheet.formats_rows),
+        protections: export_protections(&sheet.protections),
         code_runs: sheet
             .code_runs
             .iter()
@@ -728,7 +784,7 @@ pub(crate) fn export_sheet(sheet: &Sheet) -> current::Sheet {
                         Value::Single(cell_value) => {
                             current::OutputValue::Single(current::OutputValueValue {
                                 type_field: cell_value.type_name().into(),
-                                value: cell_value.to_string(),
+                                value: export_code_cell_output(cell_value),
                             })
                         }
                         Value::Array(array) => current::OutputValue::Array(current::OutputArray {
@@ -741,7 +797,7 @@ pub(crate) fn export_sheet(sheet: &Sheet) -> current::Sheet {
                                 .flat_map(|row| {
                                     row.iter().map(|cell| current::OutputValueValue {
                                         type_field: cell.type_name().into(),
-                                        value: cell.to_string(),
+                                        value: export_code_cell_output(cell),
                                     })
                                 })
                                 .collect(),
@@ -777,6 +833,7 @@ pub(crate) fn export_sheet(sheet: &Sheet) -> current::Sheet {
 }
 
 pub fn export(grid: &mut Grid) -> Result<current::GridSchema> {
+    grid.load_all_sheets()?;
     Ok(current::GridSchema {
         version: Some(CURRENT_VERSION.into()),
         sheets: grid.sheets().iter().map(export_sheet).collect(),
@@ -1,19 +1,41 @@
-use super::Grid;
+use super::{Grid, Sheet, SheetId, UnloadedSheet};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::str::FromStr;
 
 pub mod current;
+pub mod repair;
 pub mod sheet_schema;
 mod v1_3;
 mod v1_4;
 pub mod v1_5;
+pub mod v1_6;
+pub mod v1_7;
 
 pub static CURRENT_VERSION: &str = "1.5";
 
+/// Version of files exported with `export_binary`.
+pub static CURRENT_BINARY_VERSION: &str = "1.7";
+
+/// Marks a file in the binary format. JSON files never start with a zero
+/// byte, so older files are still read as JSON. The magic is followed by the
+/// length of the version, the version, and the compressed body.
+const BINARY_MAGIC: &[u8] = b"\0QUADRATIC";
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "version")]
 enum GridFile {
+    #[serde(rename = "1.7")]
+    V1_7 {
+        #[serde(flatten)]
+        grid: v1_6::schema::GridSchema,
+    },
+    #[serde(rename = "1.6")]
+    V1_6 {
+        #[serde(flatten)]
+        grid: v1_6::schema::GridSchema,
+    },
     #[serde(rename = "1.5")]
     V1_5 {
         #[serde(flatten)]
@@ -32,8 +54,22 @@ enum GridFile {
 }
 
 impl GridFile {
+    /// Reads the body of a binary file of the given version.
+    fn from_binary(version: &str, body: &[u8]) -> Result<Self> {
+        match version {
+            "1.7" => Ok(GridFile::V1_7 {
+                grid: v1_7::file::decode(body)?,
+            }),
+            "1.6" => Ok(GridFile::V1_6 {
+                grid: v1_6::file::decode(body)?,
+            }),
+            _ => Err(anyhow!("Unsupported binary file version {version}")),
+        }
+    }
+
     fn into_latest(self) -> Result<v1_5::schema::GridSchema> {
         match self {
+            GridFile::V1_7 { grid } | GridFile::V1_6 { grid } => Ok(v1_6::file::to_v1_5(grid)),
             GridFile::V1_5 { grid } => Ok(grid),
             GridFile::V1_4 { grid } => v1_4::file::upgrade(grid),
             GridFile::V1_3 { grid } => {
@@ -51,15 +87,107 @@ impl GridFile {
 
 pub fn import(file_contents: &str) -> Result<Grid> {
     let file = serde_json::from_str::<GridFile>(file_contents)
-        .map_err(|e| {
-            dbg!(&e);
-            anyhow!(e)
-        })?
+        .map_err(|e| repair::schema_error(file_contents).unwrap_or_else(|| anyhow!(e)))?
         .into_latest()?;
 
     current::import(file)
 }
 
+/// Splits a binary file into its version and body. Returns `None` for JSON
+/// files.
+fn read_binary_header(file_contents: &[u8]) -> Result<Option<(&str, &[u8])>> {
+    let Some(binary) = file_contents.strip_prefix(BINARY_MAGIC) else {
+        return Ok(None);
+    };
+    let (&version_len, rest) = binary
+        .split_first()
+        .ok_or_else(|| anyhow!("Missing binary file version"))?;
+    let version_len = version_len as usize;
+    if rest.len() < version_len {
+        return Err(anyhow!("Missing binary file version"));
+    }
+    let (version, body) = rest.split_at(version_len);
+    Ok(Some((std::str::from_utf8(version)?, body)))
+}
+
+/// Imports a file in either the binary or the JSON format.
+pub fn import_bytes(file_contents: &[u8]) -> Result<Grid> {
+    let mut grid = import_lazy(file_contents, None)?;
+    grid.load_all_sheets()?;
+    Ok(grid)
+}
+
+/// Imports a file, decoding only the metadata of its sheets and the contents
+/// of `active_sheet` (or the first sheet). The other sheets are decoded on
+/// access with `Grid::load_sheet`. Files without an index of their sheets
+/// are decoded in full.
+pub fn import_lazy(file_contents: &[u8], active_sheet: Option<SheetId>) -> Result<Grid> {
+    let mut grid = import_sheet_index(file_contents)?;
+    load_active_sheet(&mut grid, active_sheet)?;
+    Ok(grid)
+}
+
+/// Imports a file like `import_lazy`, with the active sheet given by its
+/// name, as it is in the client's url.
+pub fn import_lazy_by_name(file_contents: &[u8], active_sheet: Option<&str>) -> Result<Grid> {
+    let mut grid = import_sheet_index(file_contents)?;
+    let active_sheet = active_sheet
+        .and_then(|name| grid.try_sheet_from_name(name.to_string()))
+        .map(|sheet| sheet.id);
+    load_active_sheet(&mut grid, active_sheet)?;
+    Ok(grid)
+}
+
+// loads the active sheet, or the first sheet if there is no such sheet
+fn load_active_sheet(grid: &mut Grid, active_sheet: Option<SheetId>) -> Result<()> {
+    if !grid.sheets().is_empty() {
+        let active_sheet = active_sheet
+            .filter(|sheet_id| grid.try_sheet(*sheet_id).is_some())
+            .unwrap_or_else(|| grid.first_sheet_id());
+        grid.load_sheet(active_sheet)?;
+    }
+    Ok(())
+}
+
+// decodes the metadata of the file's sheets, without their contents
+fn import_sheet_index(file_contents: &[u8]) -> Result<Grid> {
+    let body = match read_binary_header(file_contents)? {
+        None => return import(std::str::from_utf8(file_contents)?),
+        Some(("1.7", body)) => body,
+        Some((version, body)) => {
+            let file = GridFile::from_binary(version, body)?.into_latest()?;
+            return current::import(file);
+        }
+    };
+
+    let (index, payloads) = v1_7::file::decode_index(body)?;
+    let mut grid = Grid::new_blank();
+    for sheet_index in &index.sheets {
+        let mut sheet = Sheet::new(
+            SheetId::from_str(&sheet_index.id.id)?,
+            sheet_index.name.clone(),
+            sheet_index.order.clone(),
+        );
+        sheet.color = sheet_index.color.clone();
+        sheet.protections = current::import_protections(&sheet_index.protections)?;
+        let payload = v1_7::file::sheet_payload(sheet_index, payloads)?;
+        let cells_accessed = sheet_index
+            .cells_accessed
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .collect();
+        grid.add_unloaded_sheet(sheet, UnloadedSheet::new(cells_accessed, payload.to_vec()));
+    }
+    Ok(grid)
+}
+
+/// Decodes the payload of a sheet that was not loaded with its file.
+pub(crate) fn decode_sheet(payload: &[u8]) -> Result<Sheet> {
+    let sheet = v1_6::file::sheet_to_v1_5(v1_7::file::decode_sheet(payload)?);
+    current::import_sheet(&sheet)
+}
+
 pub fn export(grid: &mut Grid) -> Result<String> {
     let converted = current::export(grid)?;
     let serialized = serde_json::to_string(&converted).map_err(|e| anyhow!(e))?;
@@ -73,13 +201,27 @@ pub fn export_vec(grid: &mut Grid) -> Result<Vec<u8>> {
     Ok(serialized)
 }
 
+/// Exports a grid in the compact binary format.
+pub fn export_binary(grid: &mut Grid) -> Result<Vec<u8>> {
+    let converted = v1_6::file::from_v1_5(current::export(grid)?);
+    let mut file = BINARY_MAGIC.to_vec();
+    file.push(CURRENT_BINARY_VERSION.len() as u8);
+    file.extend_from_slice(CURRENT_BINARY_VERSION.as_bytes());
+    v1_7::file::encode(converted.sheets, &mut file)?;
+
+    Ok(file)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         color::Rgba,
-        grid::{generate_borders, set_rect_borders, BorderSelection, BorderStyle, CellBorderLine},
-        Pos, Rect,
+        grid::{
+            generate_borders, set_rect_borders, BorderSelection, BorderStyle, CellBorderLine,
+            Protection,
+        },
+        CellValue, Duration, Instant, Pos, Rect,
     };
 
     const V1_3_FILE: &str = include_str!("../../../../quadratic-rust-shared/data/grid/v1_3.grid");
@@ -214,9 +356,158 @@ mod tests {
         assert_eq!(V1_5_FILE, exported);
     }
 
+    #[test]
+    fn imports_and_exports_a_binary_grid() {
+        let mut imported = import(V1_5_FILE).unwrap();
+        let binary = export_binary(&mut imported).unwrap();
+        assert!(binary.starts_with(BINARY_MAGIC));
+        assert!(binary.len() < V1_5_FILE.len());
+
+        let mut reimported = import_bytes(&binary).unwrap();
+        assert_eq!(export(&mut reimported).unwrap(), V1_5_FILE);
+
+        // JSON files are still imported
+        let mut json = import_bytes(V1_4_FILE.as_bytes()).unwrap();
+        export(&mut json).unwrap();
+    }
+
+    #[test]
+    fn imports_a_v1_6_binary_grid() {
+        let mut grid = import(V1_5_FILE).unwrap();
+        let converted = v1_6::file::from_v1_5(current::export(&mut grid).unwrap());
+        let mut file = BINARY_MAGIC.to_vec();
+        file.push(3);
+        file.extend_from_slice(b"1.6");
+        v1_6::file::encode(&converted, &mut file).unwrap();
+
+        let mut imported = import_bytes(&file).unwrap();
+        assert_eq!(export(&mut imported).unwrap(), V1_5_FILE);
+    }
+
+    #[test]
+    fn loads_sheets_lazily() {
+        let mut grid = Grid::new();
+        let first = grid.first_sheet_id();
+        let second = grid.add_sheet(None);
+        grid.try_sheet_mut(first)
+            .unwrap()
+            .set_cell_value(Pos { x: 0, y: 0 }, CellValue::Text("first".into()));
+        grid.try_sheet_mut(second)
+            .unwrap()
+            .set_cell_value(Pos { x: 1, y: 1 }, CellValue::Text("second".into()));
+        let file = export_binary(&mut grid).unwrap();
+
+        let mut imported = import_lazy(&file, None).unwrap();
+        assert_eq!(imported.sheets().len(), 2);
+        assert!(imported.is_sheet_loaded(first));
+        assert_eq!(imported.unloaded_sheet_ids(), vec![second]);
+        assert_eq!(imported.sheets()[1].name, "Sheet 2");
+        assert_eq!(imported.sheets()[1].cell_value(Pos { x: 1, y: 1 }), None);
+
+        // a sheet renamed or protected before it is loaded keeps its changes
+        let protection = Protection::new(None, "owner".into(), vec![]);
+        let unloaded = imported.try_sheet_mut(second).unwrap();
+        unloaded.name = "Renamed".into();
+        unloaded.protections = vec![protection.clone()];
+        assert!(imported.load_sheet(second).unwrap());
+        assert!(!imported.load_sheet(second).unwrap());
+        let sheet = imported.try_sheet(second).unwrap();
+        assert_eq!(sheet.name, "Renamed");
+        assert_eq!(sheet.protections, vec![protection]);
+        assert_eq!(
+            sheet.cell_value(Pos { x: 1, y: 1 }),
+            Some(CellValue::Text("second".into()))
+        );
+
+        // the active sheet is loaded first, and exports load every sheet
+        let mut imported = import_lazy(&file, Some(second)).unwrap();
+        assert_eq!(imported.unloaded_sheet_ids(), vec![first]);
+        let imported = import_lazy_by_name(&file, Some("Sheet 2")).unwrap();
+        assert_eq!(imported.unloaded_sheet_ids(), vec![first]);
+        let imported = import_lazy_by_name(&file, Some("Missing")).unwrap();
+        assert_eq!(imported.unloaded_sheet_ids(), vec![second]);
+        let exported = export_binary(&mut imported).unwrap();
+        assert!(imported.unloaded_sheet_ids().is_empty());
+        assert_eq!(
+            import_bytes(&exported).unwrap().sheets()[0].cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Text("first".into()))
+        );
+    }
+
+    #[test]
+    fn keeps_sheets_that_cannot_be_loaded() {
+        let mut grid = Grid::new();
+        let sheet = Sheet::new(SheetId::new(), "Bad".into(), "a9".into());
+        let sheet_id =
+            grid.add_unloaded_sheet(sheet, UnloadedSheet::new(vec![], b"not a sheet".to_vec()));
+
+        assert!(grid.load_sheet(sheet_id).is_err());
+        assert!(grid.sheet_load_error(sheet_id).is_some());
+        assert_eq!(grid.unloaded_sheet_ids(), vec![sheet_id]);
+
+        // the sheet is not decoded again, and is never exported without its
+        // contents
+        assert!(grid.load_sheet(sheet_id).is_err());
+        assert!(export_binary(&mut grid).is_err());
+        assert!(export(&mut grid).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_binary_versions() {
+        let mut file = BINARY_MAGIC.to_vec();
+        file.push(3);
+        file.extend_from_slice(b"9.9");
+        assert_eq!(
+            import_bytes(&file).unwrap_err().to_string(),
+            "Unsupported binary file version 9.9"
+        );
+
+        let mut truncated = BINARY_MAGIC.to_vec();
+        truncated.push(3);
+        assert!(import_bytes(&truncated).is_err());
+    }
+
     #[test]
     fn imports_and_exports_v1_4_default() {
         let mut imported = import(V1_4_FILE).unwrap();
         export(&mut imported).unwrap();
     }
+
+    #[test]
+    fn imports_and_exports_dates_and_durations() {
+        let mut grid = Grid::new();
+        let sheet = &mut grid.sheets_mut()[0];
+        let instant = Instant::from_serial(45292.5);
+        let duration = Duration::from_seconds(5400.0);
+        sheet.set_cell_value(Pos { x: 0, y: 0 }, CellValue::Instant(instant));
+        sheet.set_cell_value(Pos { x: 0, y: 1 }, CellValue::Duration(duration));
+
+        let exported = export(&mut grid).unwrap();
+        let imported = import(&exported).unwrap();
+        let sheet = &imported.sheets()[0];
+        assert_eq!(
+            sheet.cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Instant(instant))
+        );
+        assert_eq!(
+            sheet.cell_value(Pos { x: 0, y: 1 }),
+            Some(CellValue::Duration(duration))
+        );
+    }
+
+    #[test]
+    fn imports_and_exports_protections() {
+        let mut grid = Grid::new();
+        let protections = vec![
+            Protection::new(None, "owner".into(), vec![]),
+            Protection::new(Some(Rect::new(0, 0, 5, 1)), "owner".into(), vec!["editor".into()]),
+        ];
+        grid.sheets_mut()[0].protections.clone_from(&protections);
+
+        let json = import(&export(&mut grid).unwrap()).unwrap();
+        assert_eq!(json.sheets()[0].protections, protections);
+
+        let binary = import_bytes(&export_binary(&mut grid).unwrap()).unwrap();
+        assert_eq!(binary.sheets()[0].protections, protections);
+    }
 }
@@ -0,0 +1,609 @@
+//! Validates `.grid` files and salvages what can be read from damaged ones.
+//!
+//! Schema errors are reported with the JSON path of the value that could not
+//! be read. Cells, formats, and code runs that cannot be read are removed and
+//! kept in the report so they can be restored by hand. Sheets are only dropped
+//! when their own fields cannot be read.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value as JsonValue};
+use serde_path_to_error::Segment;
+use strum_macros::Display;
+
+use super::{current, read_binary_header, v1_3, v1_4, v1_5::schema as v1_5, v1_6, v1_7, GridFile};
+use crate::grid::{Grid, SheetId};
+
+/// Most values removed from a file before giving up on it.
+const MAX_REPAIRS: usize = 100_000;
+
+#[derive(Serialize, Debug, Display, Copy, Clone, PartialEq, Eq)]
+pub enum RepairAction {
+    DroppedSheet,
+    DroppedColumn,
+    DroppedCell,
+    DroppedFormat,
+    QuarantinedCodeRun,
+    ResetOffsets,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RepairIssue {
+    /// JSON path of the value that could not be read, eg
+    /// `$.sheets[0].code_runs[2][1].result`.
+    pub path: String,
+    pub error: String,
+    pub action: RepairAction,
+
+    /// The value that was removed from the file.
+    pub quarantined: Option<JsonValue>,
+}
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct RepairReport {
+    pub version: Option<String>,
+    pub issues: Vec<RepairIssue>,
+}
+
+impl RepairReport {
+    /// Returns whether the file was read without any repairs.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn add(
+        &mut self,
+        path: String,
+        error: impl ToString,
+        action: RepairAction,
+        quarantined: Option<JsonValue>,
+    ) {
+        self.issues.push(RepairIssue {
+            path,
+            error: error.to_string(),
+            action,
+            quarantined,
+        });
+    }
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "File version: {}",
+            self.version.as_deref().unwrap_or("unknown")
+        )?;
+        if self.issues.is_empty() {
+            return writeln!(f, "No problems found");
+        }
+        for issue in &self.issues {
+            writeln!(f, "{}: {} ({})", issue.path, issue.error, issue.action)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a file in either the binary or the JSON format, removing what cannot
+/// be read. Returns an error only if nothing can be salvaged, eg when the file
+/// is not JSON or the index of a binary file is corrupt.
+pub fn repair(file_contents: &[u8]) -> Result<(Grid, RepairReport)> {
+    let mut report = RepairReport::default();
+    let schema = match read_binary_header(file_contents)? {
+        Some((version, body)) => {
+            report.version = Some(version.to_string());
+            read_binary(version, body, &mut report)?
+        }
+        None => read_json(file_contents, &mut report)?,
+    };
+    let grid = import_sheets(schema, &mut report);
+    Ok((grid, report))
+}
+
+/// Returns the first schema error in a JSON file, including the path of the
+/// value that could not be read.
+pub(crate) fn schema_error(file_contents: &str) -> Option<anyhow::Error> {
+    let json = serde_json::from_str::<JsonValue>(file_contents).ok()?;
+    match json.get("version")?.as_str()? {
+        "1.7" | "1.6" => first_error::<v1_6::schema::GridSchema>(&json),
+        "1.5" => first_error::<v1_5::GridSchema>(&json),
+        "1.4" => first_error::<v1_4::schema::GridSchema>(&json),
+        "1.3" => first_error::<v1_3::schema::GridSchema>(&json),
+        _ => None,
+    }
+}
+
+fn first_error<T: DeserializeOwned>(json: &JsonValue) -> Option<anyhow::Error> {
+    serde_path_to_error::deserialize::<_, T>(json)
+        .err()
+        .map(|e| anyhow!("{}: {}", json_path(&path_parts(e.path())), e.inner()))
+}
+
+fn read_json(file_contents: &[u8], report: &mut RepairReport) -> Result<v1_5::GridSchema> {
+    let mut json = serde_json::from_slice::<JsonValue>(file_contents)
+        .map_err(|e| anyhow!("File is not valid JSON: {e}"))?;
+    let version = json
+        .get("version")
+        .and_then(JsonValue::as_str)
+        .map(String::from);
+    report.version = version.clone();
+    match version.as_deref() {
+        Some("1.7" | "1.6") => Ok(v1_6::file::to_v1_5(deserialize_repairing(
+            &mut json,
+            report,
+            repair_sheet,
+        )?)),
+        Some("1.5") => deserialize_repairing(&mut json, report, repair_v1_5),
+        Some("1.4") => {
+            let grid = deserialize_repairing(&mut json, report, repair_sheet)?;
+            GridFile::V1_4 { grid }.into_latest()
+        }
+        Some("1.3") => {
+            // version 1.3 has no sheets, so it can only be validated
+            let grid = deserialize_repairing(&mut json, report, |_| None)?;
+            GridFile::V1_3 { grid }.into_latest()
+        }
+        Some(version) => bail!("Unsupported file version {version}"),
+        None => bail!("File is missing its version"),
+    }
+}
+
+/// Reads a binary file, dropping the sheets that cannot be decoded.
+fn read_binary(version: &str, body: &[u8], report: &mut RepairReport) -> Result<v1_5::GridSchema> {
+    if version != "1.7" {
+        return GridFile::from_binary(version, body)?.into_latest();
+    }
+    let (index, payloads) = v1_7::file::decode_index(body)?;
+    let mut sheets = vec![];
+    for (i, sheet_index) in index.sheets.iter().enumerate() {
+        match v1_7::file::sheet_payload(sheet_index, payloads).and_then(v1_7::file::decode_sheet) {
+            Ok(sheet) => sheets.push(v1_6::file::sheet_to_v1_5(sheet)),
+            Err(e) => report.add(
+                format!("$.sheets[{i}]"),
+                e,
+                RepairAction::DroppedSheet,
+                serde_json::to_value(sheet_index).ok(),
+            ),
+        }
+    }
+    Ok(v1_5::GridSchema {
+        sheets,
+        version: Some(super::CURRENT_VERSION.into()),
+    })
+}
+
+/// Imports each sheet separately, dropping the ones that still fail.
+fn import_sheets(mut schema: v1_5::GridSchema, report: &mut RepairReport) -> Grid {
+    repair_contents(&mut schema, report);
+
+    let mut grid = Grid::new_blank();
+    for (i, sheet) in schema.sheets.iter().enumerate() {
+        let error = match current::import_sheet(sheet) {
+            Ok(imported) if grid.try_sheet(imported.id).is_none() => {
+                grid.sheets.push(imported);
+                continue;
+            }
+            Ok(imported) => anyhow!("Duplicate sheet id {}", imported.id),
+            Err(e) => e,
+        };
+        report.add(
+            format!("$.sheets[{i}]"),
+            error,
+            RepairAction::DroppedSheet,
+            serde_json::to_value(sheet).ok(),
+        );
+    }
+    if grid.sheets.is_empty() {
+        grid.add_sheet(None);
+    }
+    grid
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathPart {
+    Index(usize),
+    Key(String),
+}
+
+fn path_parts(path: &serde_path_to_error::Path) -> Vec<PathPart> {
+    path.iter()
+        .map_while(|segment| match segment {
+            Segment::Seq { index } => Some(PathPart::Index(*index)),
+            Segment::Map { key } => Some(PathPart::Key(key.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn json_path(path: &[PathPart]) -> String {
+    path.iter().fold("$".to_string(), |path, part| match part {
+        PathPart::Index(index) => format!("{path}[{index}]"),
+        PathPart::Key(key) => format!("{path}.{key}"),
+    })
+}
+
+/// A value to remove from (or replace in) a file that cannot be read.
+struct Repair {
+    target: Vec<PathPart>,
+    action: RepairAction,
+    replacement: Option<JsonValue>,
+}
+
+/// Chooses how to repair a file given the path of a schema error.
+type Repairer = fn(&[PathPart]) -> Option<Repair>;
+
+/// Deserializes a file, removing the values that cannot be read until the
+/// rest of it can be.
+fn deserialize_repairing<T: DeserializeOwned>(
+    json: &mut JsonValue,
+    report: &mut RepairReport,
+    repairer: Repairer,
+) -> Result<T> {
+    for _ in 0..MAX_REPAIRS {
+        let error = match serde_path_to_error::deserialize::<_, T>(&*json) {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        let path = path_parts(error.path());
+        let unrepairable = || anyhow!("{}: {}", json_path(&path), error.inner());
+        let repair = repairer(&path).ok_or_else(unrepairable)?;
+        let removed = match apply_repair(json, &repair) {
+            Some(removed) => removed,
+
+            // fall back to dropping the sheet if the value was not found
+            None => {
+                let repair = repair_sheet(&path).ok_or_else(unrepairable)?;
+                let removed = apply_repair(json, &repair).ok_or_else(unrepairable)?;
+                report.add(
+                    json_path(&path),
+                    error.inner(),
+                    repair.action,
+                    Some(removed),
+                );
+                continue;
+            }
+        };
+        report.add(
+            json_path(&path),
+            error.inner(),
+            repair.action,
+            Some(removed),
+        );
+    }
+    bail!("File has more than {MAX_REPAIRS} problems")
+}
+
+/// Removes or replaces the target of a repair, returning the old value.
+fn apply_repair(json: &mut JsonValue, repair: &Repair) -> Option<JsonValue> {
+    let (last, parents) = repair.target.split_last()?;
+    let mut parent = json;
+    for part in parents {
+        parent = match part {
+            PathPart::Index(index) => parent.get_mut(*index)?,
+            PathPart::Key(key) => parent.get_mut(key.as_str())?,
+        };
+    }
+    match (last, &repair.replacement) {
+        (PathPart::Index(index), Some(replacement)) => Some(std::mem::replace(
+            parent.get_mut(*index)?,
+            replacement.clone(),
+        )),
+        (PathPart::Key(key), Some(replacement)) => Some(std::mem::replace(
+            parent.get_mut(key.as_str())?,
+            replacement.clone(),
+        )),
+        (PathPart::Index(index), None) => {
+            let array = parent.as_array_mut()?;
+            (*index < array.len()).then(|| array.remove(*index))
+        }
+        (PathPart::Key(key), None) => parent.as_object_mut()?.remove(key),
+    }
+}
+
+/// Drops the sheet that contains an error.
+fn repair_sheet(path: &[PathPart]) -> Option<Repair> {
+    match path {
+        [PathPart::Key(sheets), PathPart::Index(_), ..] if sheets == "sheets" => Some(Repair {
+            target: path[..2].to_vec(),
+            action: RepairAction::DroppedSheet,
+            replacement: None,
+        }),
+        _ => None,
+    }
+}
+
+/// Removes the smallest part of a version 1.5 sheet that contains an error.
+fn repair_v1_5(path: &[PathPart]) -> Option<Repair> {
+    use PathPart::{Index, Key};
+    use RepairAction::*;
+
+    let [Key(sheets), Index(_), rest @ ..] = path else {
+        return None;
+    };
+    if sheets != "sheets" {
+        return None;
+    }
+    let remove = |len: usize, action| {
+        Some(Repair {
+            target: path[..2 + len].to_vec(),
+            action,
+            replacement: None,
+        })
+    };
+    let replace = |replacement, action| {
+        Some(Repair {
+            target: path[..3].to_vec(),
+            action,
+            replacement: Some(replacement),
+        })
+    };
+    match rest {
+        [Key(field), Index(_), Index(1), Key(values), Key(_), ..]
+            if field == "columns" && values == "values" =>
+        {
+            remove(5, DroppedCell)
+        }
+        [Key(field), Index(_), Index(1), Key(_), Key(_), ..] if field == "columns" => {
+            remove(5, DroppedFormat)
+        }
+        [Key(field), Index(_), ..] if field == "columns" => remove(2, DroppedColumn),
+        [Key(field)] if field == "columns" => replace(json!([]), DroppedColumn),
+        [Key(field), Index(_), ..] if field == "code_runs" => remove(2, QuarantinedCodeRun),
+        [Key(field)] if field == "code_runs" => replace(json!([]), QuarantinedCodeRun),
+        [Key(field), Key(_), ..] if field == "borders" => remove(2, DroppedFormat),
+        [Key(field)] if field == "borders" => replace(json!({}), DroppedFormat),
+        [Key(field), Index(_), ..] if field == "formats_columns" || field == "formats_rows" => {
+            remove(2, DroppedFormat)
+        }
+        [Key(field), ..]
+            if field == "formats_all" || field == "formats_columns" || field == "formats_rows" =>
+        {
+            remove(1, DroppedFormat)
+        }
+        [Key(field), ..] if field == "offsets" => replace(json!([[], []]), ResetOffsets),
+        _ => remove(0, DroppedSheet),
+    }
+}
+
+/// Removes values that match the schema but cannot be imported.
+fn repair_contents(schema: &mut v1_5::GridSchema, report: &mut RepairReport) {
+    for (i, sheet) in schema.sheets.iter_mut().enumerate() {
+        let sheet_path = format!("$.sheets[{i}]");
+        for (j, (_, column)) in sheet.columns.iter_mut().enumerate() {
+            let path = format!("{sheet_path}.columns[{j}][1]");
+            column.values.retain(|y, value| {
+                let error = match (y.parse::<i64>(), current::import_cell_value(value)) {
+                    (Ok(_), Ok(_)) => return true,
+                    (Err(e), _) => anyhow!(e),
+                    (_, Err(e)) => e,
+                };
+                report.add(
+                    format!("{path}.values.{y}"),
+                    error,
+                    RepairAction::DroppedCell,
+                    serde_json::to_value(&*value).ok(),
+                );
+                false
+            });
+            repair_column_format(&mut column.align, &format!("{path}.align"), report);
+            repair_column_format(&mut column.wrap, &format!("{path}.wrap"), report);
+            repair_column_format(
+                &mut column.numeric_format,
+                &format!("{path}.numeric_format"),
+                report,
+            );
+            repair_column_format(
+                &mut column.numeric_decimals,
+                &format!("{path}.numeric_decimals"),
+                report,
+            );
+            repair_column_format(
+                &mut column.numeric_commas,
+                &format!("{path}.numeric_commas"),
+                report,
+            );
+            repair_column_format(&mut column.bold, &format!("{path}.bold"), report);
+            repair_column_format(&mut column.italic, &format!("{path}.italic"), report);
+            repair_column_format(
+                &mut column.text_color,
+                &format!("{path}.text_color"),
+                report,
+            );
+            repair_column_format(
+                &mut column.fill_color,
+                &format!("{path}.fill_color"),
+                report,
+            );
+            repair_column_format(
+                &mut column.render_size,
+                &format!("{path}.render_size"),
+                report,
+            );
+        }
+
+        let mut j = 0;
+        sheet.code_runs.retain(|(_, code_run)| {
+            let path = format!("{sheet_path}.code_runs[{j}]");
+            j += 1;
+            let Err(error) = check_code_run(code_run) else {
+                return true;
+            };
+            report.add(
+                path,
+                error,
+                RepairAction::QuarantinedCodeRun,
+                serde_json::to_value(code_run).ok(),
+            );
+            false
+        });
+
+        sheet.borders.retain(|x, borders| {
+            let Err(error) = x.parse::<i64>() else {
+                return true;
+            };
+            report.add(
+                format!("{sheet_path}.borders.{x}"),
+                error,
+                RepairAction::DroppedFormat,
+                serde_json::to_value(&*borders).ok(),
+            );
+            false
+        });
+    }
+}
+
+fn repair_column_format<T: Serialize>(
+    formats: &mut HashMap<String, v1_5::ColumnRepeat<T>>,
+    path: &str,
+    report: &mut RepairReport,
+) {
+    formats.retain(|y, format| {
+        let error = match y.parse::<i64>() {
+            Ok(y) if y.checked_add(format.len as i64).is_some() => return true,
+            Ok(_) => anyhow!("Format of length {} overflows the column", format.len),
+            Err(e) => anyhow!(e),
+        };
+        report.add(
+            format!("{path}.{y}"),
+            error,
+            RepairAction::DroppedFormat,
+            serde_json::to_value(&*format).ok(),
+        );
+        false
+    });
+}
+
+fn check_code_run(code_run: &v1_5::CodeRun) -> Result<()> {
+    for sheet_rect in &code_run.cells_accessed {
+        SheetId::from_str(&sheet_rect.sheet_id.id)?;
+    }
+    if let v1_5::CodeRunResult::Ok(v1_5::OutputValue::Array(array)) = &code_run.result {
+        let (w, h) = (array.size.w, array.size.h);
+        if w <= 0 || h <= 0 || w.checked_mul(h) != Some(array.values.len() as i64) {
+            bail!("Array of size {w}x{h} has {} values", array.values.len());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        controller::GridController,
+        grid::{file, CodeCellLanguage},
+        CellValue, Pos, SheetPos,
+    };
+
+    fn test_file() -> (GridController, JsonValue) {
+        let mut gc = GridController::test();
+        let sheet_id = gc.sheet_ids()[0];
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 0), "1".to_string(), None);
+        gc.set_cell_value(SheetPos::new(sheet_id, 0, 1), "2".to_string(), None);
+        gc.set_code_cell(
+            SheetPos::new(sheet_id, 1, 0),
+            CodeCellLanguage::Formula,
+            "A0 + 1".to_string(),
+            None,
+        );
+        gc.add_sheet(None);
+        let json = serde_json::from_str(&file::export(gc.grid_mut()).unwrap()).unwrap();
+        (gc, json)
+    }
+
+    #[test]
+    fn valid_files_are_not_changed() {
+        let (_, json) = test_file();
+        let (grid, report) = repair(json.to_string().as_bytes()).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.version.as_deref(), Some("1.5"));
+        assert_eq!(grid, file::import(&json.to_string()).unwrap());
+    }
+
+    #[test]
+    fn repairs_corrupt_json() {
+        let (gc, mut json) = test_file();
+        let sheet_id = gc.sheet_ids()[0];
+        let column = &mut json["sheets"][0]["columns"][0][1];
+        column["values"]["0"] = json!({ "Number": 5 });
+        column["values"]["1"] = json!({ "Number": "not a number" });
+        json["sheets"][0]["code_runs"][0][1]["result"] = json!("bad");
+        json["sheets"][1]["name"] = json!(5);
+
+        let (grid, report) = repair(json.to_string().as_bytes()).unwrap();
+        let mut issues = report
+            .issues
+            .iter()
+            .map(|issue| (issue.path.as_str(), issue.action))
+            .collect::<Vec<_>>();
+        issues.sort_by_key(|(path, _)| *path);
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    "$.sheets[0].code_runs[0][1].result",
+                    RepairAction::QuarantinedCodeRun
+                ),
+                (
+                    "$.sheets[0].columns[0][1].values.0",
+                    RepairAction::DroppedCell
+                ),
+                (
+                    "$.sheets[0].columns[0][1].values.1",
+                    RepairAction::DroppedCell
+                ),
+                ("$.sheets[1].name", RepairAction::DroppedSheet),
+            ]
+        );
+        let code_run = report
+            .issues
+            .iter()
+            .find(|issue| issue.action == RepairAction::QuarantinedCodeRun)
+            .and_then(|issue| issue.quarantined.as_ref())
+            .unwrap();
+        assert_eq!(code_run[1]["result"], json!("bad"));
+
+        // the formula itself is kept, without its output
+        assert_eq!(grid.sheets().len(), 1);
+        let sheet = grid.try_sheet(sheet_id).unwrap();
+        assert_eq!(sheet.cell_value(Pos { x: 0, y: 0 }), None);
+        assert_eq!(sheet.cell_value(Pos { x: 0, y: 1 }), None);
+        assert!(sheet.cell_value(Pos { x: 1, y: 0 }).is_some());
+        assert!(sheet.code_run(Pos { x: 1, y: 0 }).is_none());
+    }
+
+    #[test]
+    fn drops_undecodable_binary_sheets() {
+        let (mut gc, _) = test_file();
+        let mut file = file::export_binary(gc.grid_mut()).unwrap();
+
+        // the last sheet's payload is at the end of the file
+        file.pop();
+
+        let (grid, report) = repair(&file).unwrap();
+        assert_eq!(report.version.as_deref(), Some("1.7"));
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].path, "$.sheets[1]");
+        assert_eq!(report.issues[0].action, RepairAction::DroppedSheet);
+        assert_eq!(grid.sheets().len(), 1);
+        assert_eq!(
+            grid.sheets()[0].cell_value(Pos { x: 0, y: 0 }),
+            Some(CellValue::Number(1.into()))
+        );
+    }
+
+    #[test]
+    fn reports_unrepairable_files() {
+        assert!(repair(b"not json").is_err());
+        assert_eq!(
+            repair(br#"{"version":"1.5"}"#).unwrap_err().to_string(),
+            "$: missing field `sheets`"
+        );
+        assert!(file::import(r#"{"version":"1.5","sheets":[{"id":5}]}"#)
+            .unwrap_err()
+            .to_string()
+            .starts_with("$.sheets[0].id: invalid type"));
+    }
+}
@@ -1,6 +1,6 @@
 use super::current;
 use super::v1_5;
-use crate::grid::Sheet;
+use crate::grid::{Protection, Sheet, SheetId};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +17,20 @@ impl SheetSchema {
             SheetSchema::V1_5(sheet) => current::import_sheet(sheet),
         }
     }
+
+    /// Returns the id of the sheet without importing it.
+    pub fn sheet_id(&self) -> SheetId {
+        match self {
+            SheetSchema::V1_5(sheet) => SheetId::from(sheet.id.clone()),
+        }
+    }
+
+    /// Returns the protections of the sheet without importing it.
+    pub fn protections(&self) -> Result<Vec<Protection>> {
+        match self {
+            SheetSchema::V1_5(sheet) => current::import_protections(&sheet.protections),
+        }
+    }
 }
 
 /// Exports a Sheet to the latest schema version.
@@ -35,6 +49,7 @@ mod test {
         sheet.set_cell_value((0, 0).into(), "Hello, world!".to_string());
         sheet.calculate_bounds();
         let schema = export_sheet(&sheet);
+        assert_eq!(schema.sheet_id(), sheet.id);
         let imported = schema.into_latest().unwrap();
         assert_eq!(sheet, imported);
     }
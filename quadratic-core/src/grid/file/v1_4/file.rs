@@ -321,6 +321,7 @@ fn upgrade_sheet(sheet: &v1_4::Sheet) -> v1_5::Sheet {
         formats_all: None,
         formats_columns: vec![],
         formats_rows: vec![],
+        protections: vec![],
     }
 }
 
@@ -127,7 +127,7 @@ pub struct OutputSize {
     pub w: i64,
     pub h: i64,
 }
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RenderSize {
     pub w: String,
@@ -90,6 +90,26 @@ impl From<SheetRect> for crate::SheetRect {
     }
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub min: Pos,
+    pub max: Pos,
+}
+impl From<crate::Rect> for Rect {
+    fn from(rect: crate::Rect) -> Self {
+        Self {
+            min: rect.min.into(),
+            max: rect.max.into(),
+        }
+    }
+}
+
+impl From<Rect> for crate::Rect {
+    fn from(rect: Rect) -> Self {
+        crate::Rect::new(rect.min.x, rect.min.y, rect.max.x, rect.max.y)
+    }
+}
+
 pub type Offsets = v1_4::Offsets;
 
 pub type Borders = HashMap<String, Vec<(i64, Vec<Option<CellBorder>>)>>;
@@ -129,6 +149,17 @@ pub struct Sheet {
 
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub formats_rows: Vec<(i64, (Format, i64))>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub protections: Vec<Protection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Protection {
+    pub id: String,
+    pub rect: Option<Rect>,
+    pub owner: String,
+    pub editors: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -197,16 +228,20 @@ pub struct ColumnRepeat<T> {
     pub len: u32,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NumericFormatKind {
     #[default]
     Number,
     Currency,
     Percentage,
     Exponential,
+    Custom,
+    Date,
+    Time,
+    DateTime,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NumericFormat {
     #[serde(rename = "type")]
     pub kind: NumericFormatKind,
@@ -235,14 +270,14 @@ pub struct CodeCell {
     pub code: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CellAlign {
     Left,
     Center,
     Right,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CellWrap {
     Overflow,
     Wrap,
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    grid::file::{
+        v1_5::schema::{self as v1_5, ColumnRepeat},
+        v1_6::schema::{self as current, ColumnFormat, ColumnValues},
+    },
+    RunLengthEncoding,
+};
+
+/// Writes the compressed binary encoding of a grid or sheet.
+pub fn encode<T: Serialize>(value: &T, writer: impl Write) -> Result<()> {
+    let mut encoder = DeflateEncoder::new(writer, Compression::default());
+    ciborium::into_writer(value, &mut encoder)
+        .map_err(|e| anyhow!("Could not encode file: {e}"))?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Reads a grid or sheet from its compressed binary encoding.
+pub fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    ciborium::from_reader(DeflateDecoder::new(body))
+        .map_err(|e| anyhow!("Could not decode file: {e}"))
+}
+
+fn values_to_blocks(values: HashMap<String, v1_5::CellValue>) -> Vec<ColumnValues> {
+    let mut values = values
+        .into_iter()
+        .filter_map(|(y, value)| Some((y.parse::<i64>().ok()?, value)))
+        .collect::<Vec<_>>();
+    values.sort_by_key(|(y, _)| *y);
+
+    let mut blocks: Vec<ColumnValues> = vec![];
+    for (y, value) in values {
+        match blocks.last_mut() {
+            Some(block) if block.y + block.values.len() as i64 == y => block.values.push(value),
+            _ => blocks.push(ColumnValues {
+                y,
+                values: vec![value],
+            }),
+        }
+    }
+    blocks
+}
+
+fn blocks_to_values(blocks: Vec<ColumnValues>) -> HashMap<String, v1_5::CellValue> {
+    blocks
+        .into_iter()
+        .flat_map(|block| {
+            let y = block.y;
+            block
+                .values
+                .into_iter()
+                .enumerate()
+                .map(move |(i, value)| ((y + i as i64).to_string(), value))
+        })
+        .collect()
+}
+
+fn repeats_to_format<T: Clone + Eq>(repeats: HashMap<String, ColumnRepeat<T>>) -> ColumnFormat<T> {
+    let mut repeats = repeats
+        .into_iter()
+        .filter_map(|(y, repeat)| Some((y.parse::<i64>().ok()?, repeat)))
+        .collect::<Vec<_>>();
+    repeats.sort_by_key(|(y, _)| *y);
+
+    let mut blocks: ColumnFormat<T> = vec![];
+    for (y, repeat) in repeats {
+        let len = repeat.len as usize;
+        match blocks.last_mut() {
+            Some((start, rle)) if *start + rle.size() as i64 == y => rle.push_n(repeat.value, len),
+            _ => blocks.push((y, RunLengthEncoding::repeat(repeat.value, len))),
+        }
+    }
+    blocks
+}
+
+fn format_to_repeats<T: Clone + Eq>(format: ColumnFormat<T>) -> HashMap<String, ColumnRepeat<T>> {
+    let mut repeats = HashMap::new();
+    for (mut y, rle) in format {
+        for (value, len) in rle.iter_runs() {
+            repeats.insert(
+                y.to_string(),
+                ColumnRepeat {
+                    value: value.clone(),
+                    len: len as u32,
+                },
+            );
+            y += len as i64;
+        }
+    }
+    repeats
+}
+
+fn column_from_v1_5(column: v1_5::Column) -> current::Column {
+    current::Column {
+        values: values_to_blocks(column.values),
+        align: repeats_to_format(column.align),
+        wrap: repeats_to_format(column.wrap),
+        numeric_format: repeats_to_format(column.numeric_format),
+        numeric_decimals: repeats_to_format(column.numeric_decimals),
+        numeric_commas: repeats_to_format(column.numeric_commas),
+        bold: repeats_to_format(column.bold),
+        italic: repeats_to_format(column.italic),
+        text_color: repeats_to_format(column.text_color),
+        fill_color: repeats_to_format(column.fill_color),
+        render_size: repeats_to_format(column.render_size),
+    }
+}
+
+fn column_to_v1_5(column: current::Column) -> v1_5::Column {
+    v1_5::Column {
+        values: blocks_to_values(column.values),
+        align: format_to_repeats(column.align),
+        wrap: format_to_repeats(column.wrap),
+        numeric_format: format_to_repeats(column.numeric_format),
+        numeric_decimals: format_to_repeats(column.numeric_decimals),
+        numeric_commas: format_to_repeats(column.numeric_commas),
+        bold: format_to_repeats(column.bold),
+        italic: format_to_repeats(column.italic),
+        text_color: format_to_repeats(column.text_color),
+        fill_color: format_to_repeats(column.fill_color),
+        render_size: format_to_repeats(column.render_size),
+    }
+}
+
+pub fn sheet_from_v1_5(sheet: v1_5::Sheet) -> current::Sheet {
+    current::Sheet {
+        id: sheet.id,
+        name: sheet.name,
+        color: sheet.color,
+        order: sheet.order,
+        offsets: sheet.offsets,
+        columns: sheet
+            .columns
+            .into_iter()
+            .map(|(x, column)| (x, column_from_v1_5(column)))
+            .collect(),
+        borders: sheet.borders,
+        code_runs: sheet.code_runs,
+        formats_all: sheet.formats_all,
+        formats_columns: sheet.formats_columns,
+        formats_rows: sheet.formats_rows,
+        protections: sheet.protections,
+    }
+}
+
+pub fn sheet_to_v1_5(sheet: current::Sheet) -> v1_5::Sheet {
+    v1_5::Sheet {
+        id: sheet.id,
+        name: sheet.name,
+        color: sheet.color,
+        order: sheet.order,
+        offsets: sheet.offsets,
+        columns: sheet
+            .columns
+            .into_iter()
+            .map(|(x, column)| (x, column_to_v1_5(column)))
+            .collect(),
+        borders: sheet.borders,
+        code_runs: sheet.code_runs,
+        formats_all: sheet.formats_all,
+        formats_columns: sheet.formats_columns,
+        formats_rows: sheet.formats_rows,
+        protections: sheet.protections,
+    }
+}
+
+/// Converts a 1.5 grid to its binary layout.
+pub fn from_v1_5(schema: v1_5::GridSchema) -> current::GridSchema {
+    current::GridSchema {
+        sheets: schema.sheets.into_iter().map(sheet_from_v1_5).collect(),
+        version: Some("1.6".into()),
+    }
+}
+
+/// Converts a grid in the binary layout to the 1.5 schema, which is how it is
+/// loaded.
+pub fn to_v1_5(schema: current::GridSchema) -> v1_5::GridSchema {
+    v1_5::GridSchema {
+        sheets: schema.sheets.into_iter().map(sheet_to_v1_5).collect(),
+        version: Some("1.5".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_columns_to_blocks() {
+        let column = v1_5::Column {
+            values: HashMap::from([
+                ("0".into(), v1_5::CellValue::Text("a".into())),
+                ("1".into(), v1_5::CellValue::Number("1".into())),
+                ("5".into(), v1_5::CellValue::Logical(true)),
+            ]),
+            bold: HashMap::from([
+                (
+                    "0".into(),
+                    ColumnRepeat {
+                        value: true,
+                        len: 2,
+                    },
+                ),
+                (
+                    "2".into(),
+                    ColumnRepeat {
+                        value: true,
+                        len: 3,
+                    },
+                ),
+                (
+                    "5".into(),
+                    ColumnRepeat {
+                        value: false,
+                        len: 1,
+                    },
+                ),
+                (
+                    "10".into(),
+                    ColumnRepeat {
+                        value: true,
+                        len: 1,
+                    },
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let converted = column_from_v1_5(column.clone());
+        assert_eq!(converted.values.len(), 2);
+        assert_eq!(converted.values[1].y, 5);
+        assert_eq!(converted.bold.len(), 2);
+        assert_eq!(converted.bold[0].1.size(), 6);
+        assert_eq!(converted.bold[0].1.iter_runs().count(), 2);
+
+        // adjacent runs of the same value are merged
+        let round_trip = column_to_v1_5(converted);
+        assert_eq!(round_trip.values, column.values);
+        assert_eq!(round_trip.bold.len(), 3);
+        assert_eq!(
+            round_trip.bold["0"],
+            ColumnRepeat {
+                value: true,
+                len: 5
+            }
+        );
+        assert_eq!(round_trip.bold["10"], column.bold["10"]);
+    }
+
+    #[test]
+    fn encodes_and_decodes() {
+        let schema = current::GridSchema {
+            sheets: vec![current::Sheet {
+                name: "Sheet 1".into(),
+                columns: vec![(
+                    0,
+                    column_from_v1_5(v1_5::Column {
+                        values: HashMap::from([("3".into(), v1_5::CellValue::Text("a".into()))]),
+                        ..Default::default()
+                    }),
+                )],
+                ..Default::default()
+            }],
+            version: Some("1.6".into()),
+        };
+        let mut body = vec![];
+        encode(&schema, &mut body).unwrap();
+        assert_eq!(decode::<current::GridSchema>(&body).unwrap(), schema);
+        assert!(decode::<current::GridSchema>(b"not a grid").is_err());
+    }
+}
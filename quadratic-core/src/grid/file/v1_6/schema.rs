@@ -0,0 +1,70 @@
+//! Version 1.6 stores the 1.5 schema in a compact binary encoding. Cell
+//! values are stored as blocks of consecutive cells, and formats as
+//! run-length encoded blocks, instead of maps keyed by row.
+
+use crate::{grid::file::v1_5::schema as v1_5, RunLengthEncoding};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridSchema {
+    pub sheets: Vec<Sheet>,
+    pub version: Option<String>,
+}
+
+pub type Id = v1_5::Id;
+pub type Offsets = v1_5::Offsets;
+pub type Borders = v1_5::Borders;
+pub type Format = v1_5::Format;
+pub type Pos = v1_5::Pos;
+pub type Protection = v1_5::Protection;
+pub type CodeRun = v1_5::CodeRun;
+pub type CellValue = v1_5::CellValue;
+pub type CellAlign = v1_5::CellAlign;
+pub type CellWrap = v1_5::CellWrap;
+pub type NumericFormat = v1_5::NumericFormat;
+pub type RenderSize = v1_5::RenderSize;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sheet {
+    pub id: Id,
+    pub name: String,
+    pub color: Option<String>,
+    pub order: String,
+    pub offsets: Offsets,
+    pub columns: Vec<(i64, Column)>,
+    pub borders: Borders,
+    pub code_runs: Vec<(Pos, CodeRun)>,
+    pub formats_all: Option<Format>,
+    pub formats_columns: Vec<(i64, (Format, i64))>,
+    pub formats_rows: Vec<(i64, (Format, i64))>,
+
+    // added after 1.6 was released; older files don't have it
+    #[serde(default)]
+    pub protections: Vec<Protection>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    pub values: Vec<ColumnValues>,
+    pub align: ColumnFormat<CellAlign>,
+    pub wrap: ColumnFormat<CellWrap>,
+    pub numeric_format: ColumnFormat<NumericFormat>,
+    pub numeric_decimals: ColumnFormat<i16>,
+    pub numeric_commas: ColumnFormat<bool>,
+    pub bold: ColumnFormat<bool>,
+    pub italic: ColumnFormat<bool>,
+    pub text_color: ColumnFormat<String>,
+    pub fill_color: ColumnFormat<String>,
+    pub render_size: ColumnFormat<RenderSize>,
+}
+
+/// Values of consecutive cells in a column, starting at row `y`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnValues {
+    pub y: i64,
+    pub values: Vec<CellValue>,
+}
+
+/// Blocks of consecutive formatted cells in a column, each starting at the
+/// given row.
+pub type ColumnFormat<T> = Vec<(i64, RunLengthEncoding<T>)>;
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+
+use crate::grid::file::{
+    v1_6::{file as v1_6_file, schema as v1_6},
+    v1_7::schema::{self as current, SheetIndex},
+};
+
+/// Writes the length of the index, the index, and each sheet's compressed
+/// payload.
+pub fn encode(sheets: Vec<current::Sheet>, writer: &mut Vec<u8>) -> Result<()> {
+    let mut index = vec![];
+    let mut payloads = vec![];
+    for sheet in sheets {
+        let mut cells_accessed: Vec<current::SheetRect> = vec![];
+        for (_, code_run) in &sheet.code_runs {
+            for rect in &code_run.cells_accessed {
+                if !cells_accessed.contains(rect) {
+                    cells_accessed.push(rect.clone());
+                }
+            }
+        }
+
+        let protections = sheet.protections.clone();
+        let offset = payloads.len();
+        v1_6_file::encode(&sheet, &mut payloads)?;
+        index.push(SheetIndex {
+            id: sheet.id,
+            name: sheet.name,
+            color: sheet.color,
+            order: sheet.order,
+            cells_accessed,
+            protections,
+            offset: offset as u64,
+            len: (payloads.len() - offset) as u64,
+        });
+    }
+
+    let mut encoded_index = vec![];
+    let schema = current::GridSchema {
+        sheets: index,
+        version: Some("1.7".into()),
+    };
+    v1_6_file::encode(&schema, &mut encoded_index)?;
+    writer.extend_from_slice(&(encoded_index.len() as u32).to_le_bytes());
+    writer.extend_from_slice(&encoded_index);
+    writer.extend_from_slice(&payloads);
+    Ok(())
+}
+
+/// Reads the index of a file, returning it with the sheets' payloads.
+pub fn decode_index(body: &[u8]) -> Result<(current::GridSchema, &[u8])> {
+    let error = || anyhow!("Could not decode file: missing index");
+    if body.len() < 4 {
+        return Err(error());
+    }
+    let (len, rest) = body.split_at(4);
+    let len = u32::from_le_bytes(len.try_into()?) as usize;
+    if rest.len() < len {
+        return Err(error());
+    }
+    let (index, payloads) = rest.split_at(len);
+    Ok((v1_6_file::decode(index)?, payloads))
+}
+
+/// Returns a sheet's payload from the payloads that follow the index.
+pub fn sheet_payload<'a>(sheet: &SheetIndex, payloads: &'a [u8]) -> Result<&'a [u8]> {
+    let error = || anyhow!("Could not decode file: missing sheet {}", sheet.name);
+    let start = usize::try_from(sheet.offset)?;
+    let end = start
+        .checked_add(usize::try_from(sheet.len)?)
+        .ok_or_else(error)?;
+    payloads.get(start..end).ok_or_else(error)
+}
+
+pub fn decode_sheet(payload: &[u8]) -> Result<current::Sheet> {
+    v1_6_file::decode(payload)
+}
+
+/// Reads all sheets of a file.
+pub fn decode(body: &[u8]) -> Result<v1_6::GridSchema> {
+    let (index, payloads) = decode_index(body)?;
+    let sheets = index
+        .sheets
+        .iter()
+        .map(|sheet| decode_sheet(sheet_payload(sheet, payloads)?))
+        .collect::<Result<_>>()?;
+    Ok(v1_6::GridSchema {
+        sheets,
+        version: Some("1.6".into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_sheets_separately() {
+        let sheet = |name: &str| current::Sheet {
+            name: name.into(),
+            order: name.into(),
+            ..Default::default()
+        };
+        let mut body = vec![];
+        encode(vec![sheet("a"), sheet("b")], &mut body).unwrap();
+
+        let (index, payloads) = decode_index(&body).unwrap();
+        assert_eq!(index.sheets.len(), 2);
+        assert_eq!(index.sheets[1].name, "b");
+        let payload = sheet_payload(&index.sheets[1], payloads).unwrap();
+        assert_eq!(decode_sheet(payload).unwrap(), sheet("b"));
+        assert_eq!(decode(&body).unwrap().sheets, vec![sheet("a"), sheet("b")]);
+
+        assert!(decode_index(&body[..3]).is_err());
+        let truncated = &payloads[..payloads.len() - 1];
+        assert!(sheet_payload(&index.sheets[1], truncated).is_err());
+    }
+}
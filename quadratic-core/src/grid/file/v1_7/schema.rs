@@ -0,0 +1,35 @@
+//! Version 1.7 stores each sheet of the 1.6 binary layout separately, after
+//! an index of the sheets' metadata, so that sheets can be decoded on demand.
+
+use crate::grid::file::{v1_5::schema as v1_5, v1_6::schema as v1_6};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridSchema {
+    pub sheets: Vec<SheetIndex>,
+    pub version: Option<String>,
+}
+
+pub type Id = v1_6::Id;
+pub type SheetRect = v1_5::SheetRect;
+pub type Sheet = v1_6::Sheet;
+pub type Protection = v1_6::Protection;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SheetIndex {
+    pub id: Id,
+    pub name: String,
+    pub color: Option<String>,
+    pub order: String,
+
+    /// Cells read by the sheet's code cells.
+    pub cells_accessed: Vec<SheetRect>,
+
+    /// Protections of the sheet, so they are shown before it is loaded.
+    #[serde(default)]
+    pub protections: Vec<Protection>,
+
+    /// Range of the sheet's payload, relative to the end of the index.
+    pub offset: u64,
+    pub len: u64,
+}
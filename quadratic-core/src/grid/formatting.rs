@@ -1,5 +1,5 @@
 use super::{block::SameValue, Column, ColumnData};
-use crate::RunLengthEncoding;
+use crate::{DateLocale, FormatCode, RunLengthEncoding};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use strum_macros::{Display, EnumString};
@@ -162,9 +162,66 @@ impl CellWrap {
 pub struct NumericFormat {
     #[serde(rename = "type")]
     pub kind: NumericFormatKind,
+
+    /// Currency symbol for [`NumericFormatKind::Currency`], the format code
+    /// for [`NumericFormatKind::Custom`], or the locale tag (eg, `en-GB`) for
+    /// date and time formats.
     pub symbol: Option<String>,
 }
 
+impl NumericFormat {
+    /// Creates a custom format from an Excel-style format code.
+    pub fn custom(code: impl Into<String>) -> Self {
+        Self {
+            kind: NumericFormatKind::Custom,
+            symbol: Some(code.into()),
+        }
+    }
+
+    /// Creates a date, time, or date and time format for a locale tag.
+    pub fn date_time(kind: NumericFormatKind, locale: Option<&str>) -> Self {
+        Self {
+            kind,
+            symbol: locale.map(String::from),
+        }
+    }
+
+    /// Returns true if the format is displayed using a format code.
+    pub fn uses_format_code(&self) -> bool {
+        matches!(
+            self.kind,
+            NumericFormatKind::Custom
+                | NumericFormatKind::Date
+                | NumericFormatKind::Time
+                | NumericFormatKind::DateTime
+        )
+    }
+
+    /// Returns the format code of a custom, date, or time format. Date and
+    /// time formats use the pattern of their locale (see [`DateLocale`]).
+    pub fn format_code_str(&self) -> Option<String> {
+        let locale = || {
+            self.symbol
+                .as_deref()
+                .and_then(DateLocale::from_tag)
+                .unwrap_or_default()
+        };
+        match self.kind {
+            NumericFormatKind::Custom => self.symbol.clone(),
+            NumericFormatKind::Date => Some(locale().date_pattern().to_string()),
+            NumericFormatKind::Time => Some(locale().time_pattern().to_string()),
+            NumericFormatKind::DateTime => Some(locale().date_time_pattern()),
+            _ => None,
+        }
+    }
+
+    /// Returns the parsed format code (see [`NumericFormat::format_code_str`]).
+    /// Invalid codes return None so the value displays unformatted.
+    pub fn format_code(&self) -> Option<FormatCode> {
+        FormatCode::parse(&self.format_code_str()?).ok()
+    }
+}
+
 #[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "js", derive(ts_rs::TS))]
 /// Measures DOM element size in pixels.
@@ -185,4 +242,9 @@ pub enum NumericFormatKind {
     Currency, // { symbol: String }, // TODO: would be nice if this were just a single char (and it could be)
     Percentage,
     Exponential,
+    /// Excel-style format code (eg, `#,##0.00;[Red](#,##0.00)`)
+    Custom,
+    Date,
+    Time,
+    DateTime,
 }
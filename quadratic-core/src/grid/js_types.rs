@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use super::formatting::{CellAlign, CellWrap};
 use super::CodeCellLanguage;
 use crate::grid::BorderStyle;
-use crate::{Pos, SheetRect};
+use crate::{Pos, Rect, SheetRect};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "js", derive(ts_rs::TS))]
@@ -201,3 +201,18 @@ pub struct JsClipboard {
     pub plain_text: String,
     pub html: String,
 }
+
+/// A protected range or sheet.  The client shows a lock on protections that
+/// the user cannot edit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+pub struct JsProtection {
+    pub id: String,
+
+    // None if the whole sheet is protected
+    pub rect: Option<Rect>,
+
+    pub owner: String,
+    pub editors: Vec<String>,
+    pub can_edit: bool,
+}
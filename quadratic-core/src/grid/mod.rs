@@ -17,8 +17,11 @@ pub use formatting::{
     NumericFormat, NumericFormatKind, RenderSize, TextColor,
 };
 pub use ids::*;
+pub use protection::Protection;
 use serde::{Deserialize, Serialize};
 pub use sheet::Sheet;
+pub use sheets::UnloadedSheet;
+use std::collections::HashMap;
 #[cfg(feature = "js")]
 use wasm_bindgen::prelude::*;
 
@@ -32,6 +35,7 @@ pub mod formats;
 pub mod formatting;
 mod ids;
 pub mod js_types;
+pub mod protection;
 pub mod search;
 pub mod series;
 pub mod sheet;
@@ -41,6 +45,10 @@ pub mod sheets;
 #[cfg_attr(feature = "js", wasm_bindgen)]
 pub struct Grid {
     sheets: Vec<Sheet>,
+
+    // contents of sheets that are decoded on first access (see `Grid::load_sheet`)
+    #[serde(skip)]
+    unloaded_sheets: HashMap<SheetId, UnloadedSheet>,
 }
 impl Default for Grid {
     fn default() -> Self {
@@ -54,7 +62,10 @@ impl Grid {
         ret
     }
     pub fn new_blank() -> Self {
-        Grid { sheets: vec![] }
+        Grid {
+            sheets: vec![],
+            unloaded_sheets: HashMap::new(),
+        }
     }
 
     #[cfg(test)]
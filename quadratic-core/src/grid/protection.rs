@@ -0,0 +1,85 @@
+//! Protected ranges and sheets
+//!
+//! A protection locks a range of cells, or a whole sheet, so that only its
+//! owner and the editors they allow can change it.  Users are identified by
+//! the user ids of their accounts.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Rect;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Protection {
+    pub id: Uuid,
+
+    // the protected cells, or None if the whole sheet is protected
+    pub rect: Option<Rect>,
+
+    pub owner: String,
+    pub editors: Vec<String>,
+}
+
+impl Protection {
+    pub fn new(rect: Option<Rect>, owner: String, editors: Vec<String>) -> Self {
+        Protection {
+            id: Uuid::new_v4(),
+            rect,
+            owner,
+            editors,
+        }
+    }
+
+    /// Returns whether the user can change what the protection covers.
+    pub fn can_edit(&self, user_id: Option<&str>) -> bool {
+        user_id.is_some_and(|user_id| {
+            self.owner == user_id || self.editors.iter().any(|editor| editor == user_id)
+        })
+    }
+
+    /// Returns whether a change covers what is protected.  `rect` is None for
+    /// changes to the sheet itself (eg, its name), which are only covered by
+    /// sheet protections.
+    pub fn covers(&self, rect: Option<Rect>) -> bool {
+        match (self.rect, rect) {
+            (None, _) => true,
+            (Some(protected), Some(rect)) => protected.intersects(rect),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Describes what is protected, for errors.
+    pub fn description(&self) -> String {
+        match self.rect {
+            None => "the sheet".into(),
+            Some(rect) => format!("cells {}:{}", rect.min.a1_string(), rect.max.a1_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_edit() {
+        let protection = Protection::new(None, "owner".into(), vec!["editor".into()]);
+        assert!(protection.can_edit(Some("owner")));
+        assert!(protection.can_edit(Some("editor")));
+        assert!(!protection.can_edit(Some("viewer")));
+        assert!(!protection.can_edit(None));
+    }
+
+    #[test]
+    fn covers() {
+        let sheet = Protection::new(None, "owner".into(), vec![]);
+        assert!(sheet.covers(None));
+        assert!(sheet.covers(Some(Rect::new(100, 100, 100, 100))));
+
+        let range = Protection::new(Some(Rect::new(0, 0, 2, 2)), "owner".into(), vec![]);
+        assert!(range.covers(Some(Rect::new(2, 2, 4, 4))));
+        assert!(!range.covers(Some(Rect::new(3, 0, 4, 4))));
+        assert!(!range.covers(None));
+        assert_eq!(range.description(), "cells A0:C2");
+    }
+}
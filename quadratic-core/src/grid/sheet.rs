@@ -12,6 +12,7 @@ use super::formats::format::Format;
 use super::formatting::CellFmtAttr;
 use super::ids::SheetId;
 use super::js_types::CellFormatSummary;
+use super::protection::Protection;
 use super::{CodeRun, NumericFormatKind};
 use crate::grid::{borders, SheetBorders};
 use crate::sheet_offsets::SheetOffsets;
@@ -24,6 +25,7 @@ pub mod clipboard;
 pub mod code;
 pub mod formats;
 pub mod formatting;
+pub mod protection;
 pub mod rendering;
 pub mod search;
 pub mod selection;
@@ -72,6 +74,10 @@ pub struct Sheet {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub format_all: Option<Format>,
 
+    // ranges and sheet protections (see `Protection`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protections: Vec<Protection>,
+
     // bounds for the grid with only data
     pub(super) data_bounds: GridBounds,
 
@@ -95,6 +101,8 @@ impl Sheet {
             formats_rows: BTreeMap::new(),
             format_all: None,
 
+            protections: vec![],
+
             data_bounds: GridBounds::Empty,
             format_bounds: GridBounds::Empty,
 
@@ -323,6 +331,29 @@ impl Sheet {
         self.id.to_string()
     }
 
+    /// Returns the text displayed for a value at a position, using the
+    /// position's numeric formatting (including custom format codes).
+    pub fn display_string(&self, pos: Pos, value: &CellValue) -> String {
+        let format = self.format_cell(pos.x, pos.y, true);
+        let numeric_decimals = match value {
+            CellValue::Number(_) => {
+                let is_percentage = format
+                    .numeric_format
+                    .as_ref()
+                    .is_some_and(|numeric_format| {
+                        numeric_format.kind == NumericFormatKind::Percentage
+                    });
+                self.calculate_decimal_places(pos, is_percentage)
+            }
+            _ => None,
+        };
+        value.to_display(
+            format.numeric_format,
+            numeric_decimals,
+            format.numeric_commas,
+        )
+    }
+
     /// get or calculate decimal places for a cell
     pub fn calculate_decimal_places(&self, pos: Pos, is_percentage: bool) -> Option<i16> {
         // first check if numeric_decimals already exists for this cell
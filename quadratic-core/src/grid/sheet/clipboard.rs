@@ -164,8 +164,9 @@ impl Sheet {
                     html.push_str(format!("<td {}>", style).as_str());
 
                     if let Some(value) = &simple_value {
-                        plain_text.push_str(&value.to_string());
-                        html.push_str(&value.to_string());
+                        let display = self.display_string(pos, value);
+                        plain_text.push_str(&display);
+                        html.push_str(&display);
                     }
                 }
             }
@@ -0,0 +1,66 @@
+use uuid::Uuid;
+
+use super::Sheet;
+use crate::grid::Protection;
+
+impl Sheet {
+    /// Returns the protection with the given id.
+    pub fn protection(&self, protection_id: Uuid) -> Option<&Protection> {
+        self.protections
+            .iter()
+            .find(|protection| protection.id == protection_id)
+    }
+
+    /// Adds, replaces, or removes (if None) a protection.  Returns the
+    /// protection that was replaced or removed.
+    pub fn set_protection(
+        &mut self,
+        protection_id: Uuid,
+        protection: Option<Protection>,
+    ) -> Option<Protection> {
+        let index = self
+            .protections
+            .iter()
+            .position(|protection| protection.id == protection_id);
+        match (index, protection) {
+            (Some(index), Some(protection)) => {
+                Some(std::mem::replace(&mut self.protections[index], protection))
+            }
+            (Some(index), None) => Some(self.protections.remove(index)),
+            (None, Some(protection)) => {
+                self.protections.push(protection);
+                None
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rect;
+
+    #[test]
+    fn set_protection() {
+        let mut sheet = Sheet::test();
+        let protection = Protection::new(None, "owner".into(), vec![]);
+        let id = protection.id;
+
+        assert_eq!(sheet.set_protection(id, Some(protection.clone())), None);
+        assert_eq!(sheet.protection(id), Some(&protection));
+
+        let range = Protection {
+            rect: Some(Rect::new(0, 0, 1, 1)),
+            ..protection.clone()
+        };
+        assert_eq!(
+            sheet.set_protection(id, Some(range.clone())),
+            Some(protection)
+        );
+        assert_eq!(sheet.protections, vec![range.clone()]);
+
+        assert_eq!(sheet.set_protection(id, None), Some(range));
+        assert!(sheet.protections.is_empty());
+    }
+}
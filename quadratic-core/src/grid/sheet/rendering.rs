@@ -9,13 +9,21 @@ use crate::{
             JsHtmlOutput, JsRenderBorders, JsRenderCell, JsRenderCellSpecial, JsRenderCodeCell,
             JsRenderCodeCellState, JsRenderFill, JsSheetFill,
         },
-        CellAlign, CodeCellLanguage, CodeRun, Column, NumericFormatKind,
+        CellAlign, CodeCellLanguage, CodeRun, Column, NumericFormat, NumericFormatKind,
     },
     CellValue, Pos, Rect, RunError, RunErrorMsg,
 };
 
 use super::Sheet;
 
+/// Returns the color of the custom format section used to display a value.
+fn custom_format_color(
+    numeric_format: Option<&NumericFormat>,
+    value: &CellValue,
+) -> Option<String> {
+    numeric_format?.format_code()?.format(value)?.color
+}
+
 impl Sheet {
     /// checks columns for any column that has data that might render
     pub fn has_render_cells(&self, rect: Rect) -> bool {
@@ -101,7 +109,10 @@ impl Sheet {
 
         match column {
             None => {
-                let align = if matches!(value, CellValue::Number(_)) {
+                let align = if matches!(
+                    value,
+                    CellValue::Number(_) | CellValue::Instant(_) | CellValue::Duration(_)
+                ) {
                     Some(CellAlign::Right)
                 } else {
                     None
@@ -113,16 +124,23 @@ impl Sheet {
                     self.format_all.as_ref(),
                 );
                 let align = format.align.or(align);
+
+                // only format codes (custom, date, and time) apply to cells without a column
+                let numeric_format = format
+                    .numeric_format
+                    .filter(|numeric_format| numeric_format.uses_format_code());
+                let text_color =
+                    custom_format_color(numeric_format.as_ref(), &value).or(format.text_color);
                 JsRenderCell {
                     x,
                     y,
-                    value: value.to_display(None, None, None),
+                    value: value.to_display(numeric_format, None, None),
                     language,
                     align,
                     wrap: format.wrap,
                     bold: format.bold,
                     italic: format.italic,
-                    text_color: format.text_color,
+                    text_color,
                     special: None,
                 }
             }
@@ -137,11 +155,15 @@ impl Sheet {
                 let wrap = column.wrap.get(y).or(format.wrap);
                 let bold = column.bold.get(y).or(format.bold);
                 let italic = column.italic.get(y).or(format.italic);
-                let text_color = column.text_color.get(y).or(format.text_color);
+                let numeric_format = column.numeric_format.get(y).or(format.numeric_format);
+
+                // a custom format's section color (eg, [Red]) overrides the text color
+                let text_color = custom_format_color(numeric_format.as_ref(), &value)
+                    .or(column.text_color.get(y))
+                    .or(format.text_color);
                 let value = match &value {
                     CellValue::Number(_) => {
                         // get numeric_format and numeric_decimal to turn number into a string
-                        let numeric_format = column.numeric_format.get(y).or(format.numeric_format);
                         let is_percentage = numeric_format.as_ref().is_some_and(|numeric_format| {
                             numeric_format.kind == NumericFormatKind::Percentage
                         });
@@ -154,7 +176,12 @@ impl Sheet {
 
                         value.to_display(numeric_format, numeric_decimals, numeric_commas)
                     }
-                    _ => value.to_display(None, None, None),
+                    CellValue::Instant(_) | CellValue::Duration(_) => {
+                        // dates and times are aligned like numbers
+                        align = align.or(Some(CellAlign::Right));
+                        value.to_display(numeric_format, None, None)
+                    }
+                    _ => value.to_display(numeric_format, None, None),
                 };
                 JsRenderCell {
                     x,
@@ -1,7 +1,4 @@
-use crate::{
-    grid::{CodeRunResult, Column},
-    CellValue, Pos, SheetPos, Value,
-};
+use crate::{grid::CodeRunResult, CellValue, Pos, SheetPos, Value};
 
 use super::Sheet;
 use serde::{Deserialize, Serialize};
@@ -21,7 +18,7 @@ pub struct SearchOptions {
 
 impl Sheet {
     /// Compares a CellValue to a query.
-    /// Note: Column and y are necessary to compare display value for CellValue::Number (regrettably).
+    /// Note: pos is necessary to compare display value for CellValue::Number (regrettably).
     ///
     /// Returns true if the cell value matches the query.
     #[allow(clippy::too_many_arguments)]
@@ -29,7 +26,6 @@ impl Sheet {
         &self,
         cell_value: &CellValue,
         query: &String,
-        column: Option<&Column>,
         pos: Pos,
         case_sensitive: bool,
         whole_cell: bool,
@@ -53,18 +49,13 @@ impl Sheet {
                 if n.to_string() == *query || (!whole_cell && n.to_string().contains(query)) {
                     true
                 } else {
-                    // test against any formatting applied to the number
-                    if let Some(column) = column.map_or(self.get_column(pos.x), Some) {
-                        // compare the number using its display value (eg, $ or % or commas)
-                        let numeric_format = column.numeric_format.get(pos.y);
-                        let numeric_decimals = column.numeric_decimals.get(pos.y);
-                        let numeric_commas = column.numeric_commas.get(pos.y);
-                        let display =
-                            cell_value.to_display(numeric_format, numeric_decimals, numeric_commas);
-                        display == *query || (!whole_cell && display.contains(query))
-                    } else {
-                        false
+                    // compare the number using its display value (eg, $ or % or
+                    // commas, or a custom format code)
+                    let mut display = self.display_string(pos, cell_value);
+                    if !case_sensitive {
+                        display = display.to_lowercase();
                     }
+                    display == *query || (!whole_cell && display.contains(query))
                 }
             }
             CellValue::Logical(b) => {
@@ -99,7 +90,6 @@ impl Sheet {
                     if self.compare_cell_value(
                         cell_value,
                         query,
-                        Some(column),
                         Pos { x: *x, y: *y },
                         case_sensitive,
                         whole_cell,
@@ -133,7 +123,6 @@ impl Sheet {
                         if self.compare_cell_value(
                             v,
                             query,
-                            None,
                             *pos,
                             case_sensitive,
                             whole_cell,
@@ -149,7 +138,6 @@ impl Sheet {
                                 if self.compare_cell_value(
                                     cell_value,
                                     query,
-                                    None,
                                     Pos {
                                         x: pos.x + x as i64,
                                         y: pos.y + y as i64,
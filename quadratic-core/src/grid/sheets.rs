@@ -1,7 +1,31 @@
-use super::{Grid, Sheet, SheetId};
+use super::{file, Grid, Sheet, SheetId};
+use crate::SheetRect;
+use anyhow::{anyhow, Result};
 use lexicon_fractional_index::key_between;
 use std::str::FromStr;
 
+/// The encoded contents of a sheet that is decoded on first access.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnloadedSheet {
+    /// Cells read by the sheet's code cells, so that changes to them load the
+    /// sheet and rerun its code.
+    pub cells_accessed: Vec<SheetRect>,
+    payload: Vec<u8>,
+
+    /// Why the payload could not be decoded, once it was tried.
+    error: Option<String>,
+}
+
+impl UnloadedSheet {
+    pub fn new(cells_accessed: Vec<SheetRect>, payload: Vec<u8>) -> Self {
+        Self {
+            cells_accessed,
+            payload,
+            error: None,
+        }
+    }
+}
+
 impl Grid {
     pub fn sheets(&self) -> &[Sheet] {
         &self.sheets
@@ -118,6 +142,7 @@ impl Grid {
     }
 
     pub fn remove_sheet(&mut self, sheet_id: SheetId) -> Option<Sheet> {
+        self.unloaded_sheets.remove(&sheet_id);
         let i = self.sheet_id_to_index(sheet_id);
         match i {
             Some(i) => Some(self.sheets.remove(i)),
@@ -156,6 +181,90 @@ impl Grid {
         self.sheets.iter_mut().find(|s| s.id == sheet_id)
     }
 
+    /// Adds a sheet whose contents are decoded on first access. `sheet` holds
+    /// only the sheet's metadata until then.
+    pub fn add_unloaded_sheet(&mut self, sheet: Sheet, unloaded: UnloadedSheet) -> SheetId {
+        let sheet_id = sheet.id;
+        self.sheets.push(sheet);
+        self.sort_sheets();
+        self.unloaded_sheets.insert(sheet_id, unloaded);
+        sheet_id
+    }
+
+    pub fn is_sheet_loaded(&self, sheet_id: SheetId) -> bool {
+        !self.unloaded_sheets.contains_key(&sheet_id)
+    }
+
+    /// Returns why a sheet's contents could not be decoded, if they were
+    /// tried.
+    pub fn sheet_load_error(&self, sheet_id: SheetId) -> Option<&str> {
+        self.unloaded_sheets.get(&sheet_id)?.error.as_deref()
+    }
+
+    /// Returns the sheets whose contents have not been decoded, in order.
+    pub fn unloaded_sheet_ids(&self) -> Vec<SheetId> {
+        self.sheets
+            .iter()
+            .filter(|sheet| !self.is_sheet_loaded(sheet.id))
+            .map(|sheet| sheet.id)
+            .collect()
+    }
+
+    /// Returns the unloaded sheets with code cells that read `sheet_rect`.
+    pub fn unloaded_sheets_accessing(&self, sheet_rect: &SheetRect) -> Vec<SheetId> {
+        self.unloaded_sheet_ids()
+            .into_iter()
+            .filter(|sheet_id| {
+                self.unloaded_sheets[sheet_id]
+                    .cells_accessed
+                    .iter()
+                    .any(|cells_accessed| sheet_rect.intersects(*cells_accessed))
+            })
+            .collect()
+    }
+
+    /// Decodes the contents of a sheet that has not been loaded. The sheet's
+    /// metadata (its name, color, order, and protections) is kept, since it
+    /// is read and changed without loading the sheet.
+    ///
+    /// A sheet that cannot be decoded keeps its payload, so that it is never
+    /// exported without its contents, and is not decoded again: loading it
+    /// returns the same error.
+    ///
+    /// Returns whether the sheet was loaded by this call.
+    pub fn load_sheet(&mut self, sheet_id: SheetId) -> Result<bool> {
+        let Some(unloaded) = self.unloaded_sheets.get_mut(&sheet_id) else {
+            return Ok(false);
+        };
+        if let Some(error) = &unloaded.error {
+            return Err(anyhow!("Unable to load sheet {sheet_id}: {error}"));
+        }
+        let mut sheet = match file::decode_sheet(&unloaded.payload) {
+            Ok(sheet) => sheet,
+            Err(e) => {
+                unloaded.error = Some(e.to_string());
+                return Err(anyhow!("Unable to load sheet {sheet_id}: {e}"));
+            }
+        };
+        self.unloaded_sheets.remove(&sheet_id);
+
+        if let Some(existing) = self.try_sheet_mut(sheet_id) {
+            sheet.name = std::mem::take(&mut existing.name);
+            sheet.color = existing.color.take();
+            sheet.order = std::mem::take(&mut existing.order);
+            sheet.protections = std::mem::take(&mut existing.protections);
+            *existing = sheet;
+        }
+        Ok(true)
+    }
+
+    pub fn load_all_sheets(&mut self) -> Result<()> {
+        for sheet_id in self.unloaded_sheet_ids() {
+            self.load_sheet(sheet_id)?;
+        }
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn sheets_mut(&mut self) -> &mut [Sheet] {
         &mut self.sheets
@@ -18,6 +18,7 @@ pub mod sheet_offsets;
 mod span;
 pub mod test_util;
 mod values;
+pub mod xlsx;
 
 #[cfg(feature = "js")]
 pub mod wasm_bindings;
@@ -38,6 +39,12 @@ pub mod limits {
 
     /// Maximum cell range size allowed. Must be strictly less than `u32::MAX`.
     pub const CELL_RANGE_LIMIT: u32 = 1_000_000;
+
+    /// Default maximum size of an imported file, in bytes.
+    pub const IMPORT_MAX_BYTES: u64 = 1 << 30;
+
+    /// Default maximum number of rows in an imported file.
+    pub const IMPORT_MAX_ROWS: u64 = 10_000_000;
 }
 
 pub const DEFAULT_COLUMN_WIDTH: f64 = 100.0;
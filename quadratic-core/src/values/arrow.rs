@@ -1,20 +1,37 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use arrow_array::{
     cast::AsArray,
     types::{Date32Type, Date64Type},
-    Array, ArrayRef,
+    Array, ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray,
 };
 use arrow_buffer::ArrowNativeType;
 use arrow_data::ArrayData;
-use arrow_schema::{DataType, TimeUnit};
-use bigdecimal::BigDecimal;
-use chrono::{NaiveDate, TimeZone, Utc};
-
-use crate::{cell_values::CellValues, CellValue};
-
-use super::time::map_local_result;
+use arrow_ipc::{
+    writer::{FileWriter, IpcWriteOptions},
+    CompressionType,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::NaiveDate;
+
+use serde::{Deserialize, Serialize};
+
+use super::time::SECONDS_PER_DAY;
+use crate::{cell_values::CellValues, CellValue, Duration, Instant, IsBlank};
+
+/// Compression of an exported Parquet or Arrow IPC file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "js", derive(ts_rs::TS))]
+pub enum ExportCompression {
+    #[default]
+    None,
+    Snappy,
+    Gzip,
+    Lz4,
+}
 
 fn i32_naive_date(value: i32) -> NaiveDate {
     Date32Type::to_naive_date(value)
@@ -24,6 +41,14 @@ fn i64_naive_date(value: i64) -> NaiveDate {
     Date64Type::to_naive_date(value)
 }
 
+fn time_of_day(seconds: f64) -> CellValue {
+    CellValue::Duration(Duration::from_seconds(seconds))
+}
+
+fn timestamp(seconds: f64) -> CellValue {
+    CellValue::Instant(Instant::new(seconds))
+}
+
 pub fn arrow_col_to_cell_value_vec(array: &ArrayRef) -> Result<Vec<CellValue>> {
     let data_type = array.data_type();
     let array_data = array.to_data();
@@ -52,13 +77,13 @@ pub fn arrow_col_to_cell_value_vec(array: &ArrayRef) -> Result<Vec<CellValue>> {
             &i64_naive_date,
         )),
         DataType::Time32(unit) => {
-            arrow_time_unit_to_cell_values::<i32>(array_data, unit, "%H:%M:%S", &None)
+            arrow_time_unit_to_cell_values::<i32>(array_data, unit, &time_of_day, &None)
         }
         DataType::Time64(unit) => {
-            arrow_time_unit_to_cell_values::<i64>(array_data, unit, "%H:%M:%S", &None)
+            arrow_time_unit_to_cell_values::<i64>(array_data, unit, &time_of_day, &None)
         }
         DataType::Timestamp(unit, extra) => {
-            arrow_time_unit_to_cell_values::<i64>(array_data, unit, "%Y-%m-%d %H:%M:%S", extra)
+            arrow_time_unit_to_cell_values::<i64>(array_data, unit, &timestamp, extra)
         }
         // unsupported data type
         _ => {
@@ -172,10 +197,7 @@ where
         let data = buffer.typed_data::<T>();
         values.extend(
             data.iter()
-                .map(|v| {
-                    let timestamp = conversion_fn(*v).format("%Y-%m-%d").to_string();
-                    CellValue::Text(timestamp)
-                })
+                .map(|v| CellValue::Instant(conversion_fn(*v).into()))
                 .collect::<Vec<CellValue>>(),
         );
     }
@@ -186,13 +208,20 @@ where
 fn arrow_time_unit_to_cell_values<T>(
     array_data: ArrayData,
     time_unit: &TimeUnit,
-    format: &str,
+    conversion_fn: &dyn Fn(f64) -> CellValue,
     _extra: &Option<Arc<str>>,
 ) -> Result<Vec<CellValue>>
 where
     T: ArrowNativeType,
     T: Into<i64>,
 {
+    let units_per_second = match time_unit {
+        TimeUnit::Nanosecond => 1_000_000_000.0,
+        TimeUnit::Microsecond => 1_000_000.0,
+        TimeUnit::Millisecond => 1_000.0,
+        TimeUnit::Second => 1.0,
+    };
+
     let mut values = vec![];
 
     for buffer in array_data.buffers() {
@@ -200,25 +229,131 @@ where
         values.extend(
             data.iter()
                 .map(|v| {
-                    let timestamp = match time_unit {
-                        TimeUnit::Nanosecond => Utc.timestamp_nanos((*v).into()).format(format),
-                        TimeUnit::Microsecond => {
-                            map_local_result(Utc.timestamp_micros((*v).into()))?.format(format)
-                        }
-                        TimeUnit::Millisecond => {
-                            map_local_result(Utc.timestamp_millis_opt((*v).into()))?.format(format)
-                        }
-                        TimeUnit::Second => {
-                            map_local_result(Utc.timestamp_millis_opt((*v).into()))?.format(format)
-                        }
-                    };
-
-                    // TODO(ddimaria): convert to Instant when they're implement
-                    Ok(CellValue::Text(timestamp.to_string()))
+                    let value: i64 = (*v).into();
+                    conversion_fn(value as f64 / units_per_second)
                 })
-                .collect::<Result<Vec<CellValue>>>()?,
+                .collect::<Vec<CellValue>>(),
         );
     }
 
     Ok(values)
 }
+
+/// Infers the Arrow type of a column of cell values. Blanks are ignored (and
+/// become nulls). Columns with mixed or unsupported types become text.
+pub fn infer_arrow_type(values: &[CellValue]) -> DataType {
+    let mut values = values.iter().filter(|v| !v.is_blank()).peekable();
+    let Some(&first) = values.peek() else {
+        return DataType::Utf8;
+    };
+
+    match first {
+        CellValue::Number(_) => {
+            let mut is_integer = true;
+            for value in values {
+                match value {
+                    CellValue::Number(n) => is_integer &= n.is_integer() && n.to_i64().is_some(),
+                    _ => return DataType::Utf8,
+                }
+            }
+            if is_integer {
+                DataType::Int64
+            } else {
+                DataType::Float64
+            }
+        }
+        CellValue::Logical(_) => {
+            if values.all(|v| matches!(v, CellValue::Logical(_))) {
+                DataType::Boolean
+            } else {
+                DataType::Utf8
+            }
+        }
+        CellValue::Instant(_) => {
+            let mut is_date = true;
+            for value in values {
+                match value {
+                    CellValue::Instant(i) => is_date &= i.is_date(),
+                    _ => return DataType::Utf8,
+                }
+            }
+            if is_date {
+                DataType::Date32
+            } else {
+                DataType::Timestamp(TimeUnit::Microsecond, None)
+            }
+        }
+        _ => DataType::Utf8,
+    }
+}
+
+/// Converts a column of cell values to an Arrow array of `data_type` (as
+/// returned by [`infer_arrow_type`]). Blank cells become nulls.
+pub fn cell_values_to_arrow_col(values: &[CellValue], data_type: &DataType) -> ArrayRef {
+    match data_type {
+        DataType::Int64 => Arc::new(Int64Array::from_iter(values.iter().map(|v| match v {
+            CellValue::Number(n) => n.to_i64(),
+            _ => None,
+        }))),
+        DataType::Float64 => Arc::new(Float64Array::from_iter(values.iter().map(|v| match v {
+            CellValue::Number(n) => n.to_f64(),
+            _ => None,
+        }))),
+        DataType::Boolean => Arc::new(BooleanArray::from_iter(values.iter().map(|v| match v {
+            CellValue::Logical(b) => Some(*b),
+            _ => None,
+        }))),
+        DataType::Date32 => Arc::new(Date32Array::from_iter(values.iter().map(|v| match v {
+            CellValue::Instant(i) => Some((i.seconds / SECONDS_PER_DAY).floor() as i32),
+            _ => None,
+        }))),
+        DataType::Timestamp(TimeUnit::Microsecond, None) => Arc::new(
+            TimestampMicrosecondArray::from_iter(values.iter().map(|v| match v {
+                CellValue::Instant(i) => Some((i.seconds * 1_000_000.0).round() as i64),
+                _ => None,
+            })),
+        ),
+        _ => Arc::new(StringArray::from_iter(values.iter().map(|v| {
+            (!v.is_blank()).then(|| match v {
+                CellValue::Text(s) => s.to_owned(),
+                _ => v.to_string(),
+            })
+        }))),
+    }
+}
+
+/// Converts columns of cell values to a record batch, inferring the type of
+/// each column.
+pub fn cell_values_to_record_batch(
+    headers: Vec<String>,
+    columns: Vec<Vec<CellValue>>,
+) -> Result<RecordBatch> {
+    let mut fields = vec![];
+    let mut arrays = vec![];
+    for (name, values) in headers.into_iter().zip(columns.iter()) {
+        let data_type = infer_arrow_type(values);
+        arrays.push(cell_values_to_arrow_col(values, &data_type));
+        fields.push(Field::new(name, data_type, true));
+    }
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+/// Writes a record batch to the bytes of an Arrow IPC file. Only LZ4
+/// compression is supported by the IPC format.
+pub fn record_batch_to_arrow_ipc(
+    batch: &RecordBatch,
+    compression: ExportCompression,
+) -> Result<Vec<u8>> {
+    let compression = match compression {
+        ExportCompression::None => None,
+        ExportCompression::Lz4 => Some(CompressionType::LZ4_FRAME),
+        other => bail!("{other:?} compression is not supported for Arrow IPC files"),
+    };
+    let options = IpcWriteOptions::default().try_with_compression(compression)?;
+
+    let mut writer = FileWriter::try_new_with_options(vec![], &batch.schema(), options)?;
+    writer.write(batch)?;
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
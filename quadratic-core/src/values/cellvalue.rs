@@ -5,7 +5,7 @@ use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
 use chrono::{TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::{Duration, Instant, IsBlank};
+use super::{DateLocale, Duration, Instant, IsBlank};
 use crate::{
     controller::operations::operation::Operation,
     grid::{
@@ -104,8 +104,8 @@ impl CellValue {
             CellValue::Number(n) => n.to_string(),
             CellValue::Logical(true) => "TRUE".to_string(),
             CellValue::Logical(false) => "FALSE".to_string(),
-            CellValue::Instant(_) => todo!("repr of Instant"),
-            CellValue::Duration(_) => todo!("repr of Duration"),
+            CellValue::Instant(i) => format!("{:?}", i.to_string()),
+            CellValue::Duration(d) => format!("{:?}", d.to_string()),
             CellValue::Error(_) => "[error]".to_string(),
             CellValue::Html(s) => s.clone(),
             CellValue::Code(_) => todo!("repr of code"),
@@ -149,6 +149,14 @@ impl CellValue {
         numeric_decimals: Option<i16>,
         numeric_commas: Option<bool>,
     ) -> String {
+        if let Some(formatted) = numeric_format
+            .as_ref()
+            .and_then(NumericFormat::format_code)
+            .and_then(|format_code| format_code.format(self))
+        {
+            return formatted.text;
+        }
+
         match self {
             CellValue::Blank => String::new(),
             CellValue::Text(s) => s.to_string(),
@@ -210,12 +218,16 @@ impl CellValue {
                     }
                     NumericFormatKind::Number => number,
                     NumericFormatKind::Exponential => number,
+                    NumericFormatKind::Custom
+                    | NumericFormatKind::Date
+                    | NumericFormatKind::Time
+                    | NumericFormatKind::DateTime => number,
                 }
             }
             CellValue::Logical(true) => "true".to_string(),
             CellValue::Logical(false) => "false".to_string(),
-            CellValue::Instant(_) => todo!("repr of Instant"),
-            CellValue::Duration(_) => todo!("repr of Duration"),
+            CellValue::Instant(i) => i.to_string(),
+            CellValue::Duration(d) => d.to_string(),
             CellValue::Error(_) => "[error]".to_string(),
 
             // these should not render
@@ -232,8 +244,8 @@ impl CellValue {
             CellValue::Number(n) => n.to_string(),
             CellValue::Logical(true) => "true".to_string(),
             CellValue::Logical(false) => "false".to_string(),
-            CellValue::Instant(_) => todo!("repr of Instant"),
-            CellValue::Duration(_) => todo!("repr of Duration"),
+            CellValue::Instant(i) => i.to_string(),
+            CellValue::Duration(d) => d.to_string(),
             CellValue::Error(_) => "[error]".to_string(),
 
             // this should not be editable
@@ -302,10 +314,28 @@ impl CellValue {
             chrono::LocalResult::Single(timestamp) => timestamp,
             _ => bail!("Could not parse timestamp: {}", value),
         };
-        // TODO(ddimaria): convert to Instant when they're implement
-        Ok(CellValue::Text(
-            timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
-        ))
+        Ok(CellValue::Instant(timestamp.naive_utc().into()))
+    }
+
+    /// Parses a date, date and time, or time of day (eg, `2024-01-15`,
+    /// `1/15/2024 1:45 PM`, or `13:45`).
+    pub fn unpack_date_time(value: &str, locale: DateLocale) -> Option<CellValue> {
+        if let Some(instant) = Instant::parse(value, locale) {
+            return Some(CellValue::Instant(instant));
+        }
+        Duration::parse(value).map(CellValue::Duration)
+    }
+
+    /// Returns the number a date, duration, or number is treated as when
+    /// compared or used in a formula: dates are serial numbers and durations
+    /// are a number of days.
+    pub fn as_serial(&self) -> Option<f64> {
+        match self {
+            CellValue::Number(n) => n.to_f64(),
+            CellValue::Instant(i) => Some(i.to_serial()),
+            CellValue::Duration(d) => d.to_days(),
+            _ => None,
+        }
     }
 
     pub fn unpack_str_float(value: &str, default: CellValue) -> CellValue {
@@ -352,6 +382,15 @@ impl CellValue {
             (CellValue::Duration(a), CellValue::Duration(b)) => a.cmp(b),
             (CellValue::Blank, CellValue::Blank) => std::cmp::Ordering::Equal,
 
+            // dates and durations compare with numbers as serial numbers
+            (
+                CellValue::Number(_) | CellValue::Instant(_) | CellValue::Duration(_),
+                CellValue::Number(_) | CellValue::Instant(_) | CellValue::Duration(_),
+            ) => match (self.as_serial(), other.as_serial()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => return Ok(None),
+            },
+
             (CellValue::Number(_), _)
             | (CellValue::Text(_), _)
             | (CellValue::Logical(_), _)
@@ -371,18 +410,17 @@ impl CellValue {
         fn type_id(v: &CellValue) -> u8 {
             // Sort order, based on the results of Excel's `SORT()` function.
             // The comparison operators are the same, except that blank coerces
-            // to zero before comparison.
+            // to zero before comparison. Excel stores dates and durations as
+            // numbers, so they sort with numbers.
             match v {
-                CellValue::Number(_) => 0,
+                CellValue::Number(_) | CellValue::Instant(_) | CellValue::Duration(_) => 0,
                 CellValue::Text(_) => 1,
                 CellValue::Logical(_) => 2,
                 CellValue::Error(_) => 3,
-                CellValue::Instant(_) => 4,
-                CellValue::Duration(_) => 5,
-                CellValue::Blank => 6,
-                CellValue::Html(_) => 7,
-                CellValue::Code(_) => 8,
-                CellValue::Image(_) => 9,
+                CellValue::Blank => 4,
+                CellValue::Html(_) => 5,
+                CellValue::Code(_) => 6,
+                CellValue::Image(_) => 7,
             }
         }
 
@@ -529,7 +567,10 @@ impl CellValue {
                 CellValue::Logical(is_true)
             }
             "instant" => CellValue::unpack_str_unix_timestamp(value)?,
-            "duration" => CellValue::Text("not implemented".into()),
+            "duration" => match Duration::parse(value) {
+                Some(duration) => CellValue::Duration(duration),
+                None => bail!("Could not parse duration: {}", value),
+            },
             "image" => CellValue::Image(value.into()),
             _ => CellValue::Text(value.into()),
         };
@@ -554,7 +595,7 @@ mod test {
 
     use crate::{
         grid::{NumericFormat, NumericFormatKind, Sheet},
-        CellValue,
+        CellValue, DateLocale, Duration, Instant,
     };
 
     #[test]
@@ -693,6 +734,31 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_cell_value_to_display_custom() {
+        let cv = CellValue::Number(BigDecimal::from_str("-1234.5").unwrap());
+        let numeric_format = Some(NumericFormat::custom("#,##0.00;(#,##0.00)"));
+        assert_eq!(
+            cv.to_display(numeric_format.clone(), None, None),
+            String::from("(1,234.50)")
+        );
+
+        let cv = CellValue::Text(String::from("hello"));
+        let numeric_format = Some(NumericFormat::custom("\"Name: \"@"));
+        assert_eq!(
+            cv.to_display(numeric_format, None, None),
+            String::from("Name: hello")
+        );
+
+        // invalid format codes fall back to the unformatted number
+        let cv = CellValue::Number(BigDecimal::from_str("12.5").unwrap());
+        let numeric_format = Some(NumericFormat::custom("\"unterminated"));
+        assert_eq!(
+            cv.to_display(numeric_format, None, None),
+            String::from("12.5")
+        );
+    }
+
     #[test]
     fn test_unpack_percentage() {
         let value = String::from("1238.12232%");
@@ -780,4 +846,49 @@ mod test {
         let value = CellValue::Text("test".into());
         assert!(!value.is_image());
     }
+
+    #[test]
+    fn test_unpack_date_time() {
+        assert_eq!(
+            CellValue::unpack_date_time("2024-01-15", DateLocale::EnUs),
+            Some(CellValue::Instant(Instant::from_serial(45306.0)))
+        );
+        assert_eq!(
+            CellValue::unpack_date_time("15/01/2024", DateLocale::EnGb),
+            Some(CellValue::Instant(Instant::from_serial(45306.0)))
+        );
+        assert_eq!(
+            CellValue::unpack_date_time("13:30", DateLocale::EnUs),
+            Some(CellValue::Duration(Duration::from_seconds(48_600.0)))
+        );
+        assert_eq!(CellValue::unpack_date_time("hello", DateLocale::EnUs), None);
+        assert_eq!(CellValue::unpack_date_time("123", DateLocale::EnUs), None);
+    }
+
+    #[test]
+    fn test_date_time_display_and_compare() {
+        let date = CellValue::Instant(Instant::from_serial(45306.5));
+        assert_eq!(date.to_display(None, None, None), "2024-01-15 12:00:00");
+        assert_eq!(date.to_edit(), "2024-01-15 12:00:00");
+        assert_eq!(date.repr(), "\"2024-01-15 12:00:00\"");
+
+        let time = CellValue::Duration(Duration::from_seconds(90.0));
+        assert_eq!(time.to_display(None, None, None), "0:01:30");
+
+        // dates and durations compare with numbers as serial numbers
+        let number = CellValue::Number(BigDecimal::from(45306));
+        assert_eq!(
+            date.partial_cmp(&number).unwrap(),
+            Some(std::cmp::Ordering::Greater)
+        );
+        assert_eq!(
+            time.partial_cmp(&CellValue::Number(BigDecimal::from(1)))
+                .unwrap(),
+            Some(std::cmp::Ordering::Less)
+        );
+        assert_eq!(
+            date.cmp(&CellValue::Text("a".into())).unwrap(),
+            std::cmp::Ordering::Less
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use bigdecimal::{BigDecimal, ToPrimitive, Zero};
 
-use super::{CellValue, IsBlank, Value};
+use super::{CellValue, DateLocale, Duration, Instant, IsBlank, Value};
 use crate::{CodeResult, CodeResultExt, RunErrorMsg, Span, Spanned, Unspan};
 
 const CURRENCY_PREFIXES: &[char] = &['$', '¥', '£', '€'];
@@ -57,6 +57,16 @@ impl From<u32> for CellValue {
         CellValue::Number(BigDecimal::from(value))
     }
 }
+impl From<Instant> for CellValue {
+    fn from(value: Instant) -> Self {
+        CellValue::Instant(value)
+    }
+}
+impl From<Duration> for CellValue {
+    fn from(value: Duration) -> Self {
+        CellValue::Duration(value)
+    }
+}
 impl From<bool> for CellValue {
     fn from(value: bool) -> Self {
         CellValue::Logical(value)
@@ -122,7 +132,9 @@ impl<'a> TryFrom<&'a CellValue> for f64 {
             CellValue::Number(n) => Ok(n.to_f64().unwrap()),
             CellValue::Logical(true) => Ok(1.0),
             CellValue::Logical(false) => Ok(0.0),
-            CellValue::Instant(_) | CellValue::Duration(_) => Err(RunErrorMsg::Expected {
+            // dates are serial numbers, and durations are a number of days
+            CellValue::Instant(i) => Ok(i.to_serial()),
+            CellValue::Duration(d) => d.to_days().ok_or_else(|| RunErrorMsg::Expected {
                 expected: "number".into(),
                 got: Some(value.type_name().into()),
             }),
@@ -166,6 +178,28 @@ impl<'a> TryFrom<&'a CellValue> for bool {
     }
 }
 
+impl<'a> TryFrom<&'a CellValue> for Instant {
+    type Error = RunErrorMsg;
+
+    fn try_from(value: &'a CellValue) -> Result<Self, Self::Error> {
+        let error = || RunErrorMsg::Expected {
+            expected: "date".into(),
+            got: Some(value.type_name().into()),
+        };
+        match value {
+            CellValue::Instant(i) => Ok(*i),
+            CellValue::Duration(d) => d.to_days().map(Instant::from_serial).ok_or_else(error),
+            CellValue::Text(s) => Instant::parse(s, DateLocale::default())
+                .or_else(|| Duration::parse(s)?.to_days().map(Instant::from_serial))
+                .ok_or_else(error),
+            CellValue::Blank | CellValue::Number(_) => {
+                Ok(Instant::from_serial(f64::try_from(value)?))
+            }
+            _ => Err(error()),
+        }
+    }
+}
+
 impl TryFrom<CellValue> for String {
     type Error = RunErrorMsg;
 
@@ -191,6 +225,7 @@ macro_rules! impl_try_from_cell_value_for {
 impl_try_from_cell_value_for!(f64);
 impl_try_from_cell_value_for!(i64);
 impl_try_from_cell_value_for!(bool);
+impl_try_from_cell_value_for!(Instant);
 
 impl<'a> TryFrom<&'a Value> for &'a CellValue {
     type Error = RunErrorMsg;
@@ -229,6 +264,7 @@ impl_try_from_value_for!(String);
 impl_try_from_value_for!(f64);
 impl_try_from_value_for!(i64);
 impl_try_from_value_for!(bool);
+impl_try_from_value_for!(Instant);
 
 /// Coercion from `Value` or `CellValue` into a particular Rust type.
 pub trait CoerceInto: Sized + Unspan
@@ -0,0 +1,1085 @@
+//! Excel-style number format codes (eg, `#,##0.00;[Red](#,##0.00)`).
+//!
+//! A format code has up to four sections separated by `;`. Without conditions
+//! the sections apply to positive numbers, negative numbers, zero, and text.
+//! A section may start with a color (`[Red]`) and/or a condition (`[>=100]`),
+//! in which case the first two sections are chosen by their conditions and the
+//! third applies to everything else.
+
+use anyhow::{bail, Result};
+use bigdecimal::{
+    num_bigint::BigInt, BigDecimal, FromPrimitive, RoundingMode, Signed, ToPrimitive, Zero,
+};
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate, NaiveDateTime, Timelike};
+
+use super::time::SECONDS_PER_DAY;
+use crate::CellValue;
+
+/// Maximum number of sections in a format code.
+const MAX_SECTIONS: usize = 4;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const DAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// A value rendered with a format code.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FormattedValue {
+    pub text: String,
+
+    /// CSS color requested by the section (eg, `[Red]`).
+    pub color: Option<String>,
+}
+
+/// A parsed Excel-style format code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatCode {
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Section {
+    color: Option<String>,
+    condition: Option<Condition>,
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Condition {
+    comparison: Comparison,
+    value: f64,
+}
+
+impl Condition {
+    fn matches(&self, n: f64) -> bool {
+        match self.comparison {
+            Comparison::Eq => n == self.value,
+            Comparison::Ne => n != self.value,
+            Comparison::Lt => n < self.value,
+            Comparison::Le => n <= self.value,
+            Comparison::Gt => n > self.value,
+            Comparison::Ge => n >= self.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Digit {
+    /// `0`: always shows a digit.
+    Zero,
+    /// `#`: shows only significant digits.
+    Hash,
+    /// `?`: shows a space in place of insignificant zeros.
+    Question,
+}
+
+impl Digit {
+    fn pad(&self) -> &'static str {
+        match self {
+            Digit::Zero => "0",
+            Digit::Hash => "",
+            Digit::Question => " ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DatePart {
+    Year2,
+    Year4,
+    Month,
+    Month2,
+    MonthAbbr,
+    MonthName,
+    MonthLetter,
+    Day,
+    Day2,
+    DayAbbr,
+    DayName,
+    Hour,
+    Hour2,
+    Minute,
+    Minute2,
+    Second,
+    Second2,
+    SubSecond(usize),
+    ElapsedHours,
+    ElapsedMinutes,
+    ElapsedSeconds,
+    AmPm,
+    AP,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    General,
+    Literal(String),
+    Digit(Digit),
+    DecimalPoint,
+    Comma,
+    Percent,
+    Exponent { plus: bool },
+    Text,
+    Date(DatePart),
+}
+
+fn excel_color(name: &str) -> Option<&'static str> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "black" | "color1" => "rgb(0, 0, 0)",
+        "white" | "color2" => "rgb(255, 255, 255)",
+        "red" | "color3" => "rgb(255, 0, 0)",
+        "green" | "color4" => "rgb(0, 255, 0)",
+        "blue" | "color5" => "rgb(0, 0, 255)",
+        "yellow" | "color6" => "rgb(255, 255, 0)",
+        "magenta" | "color7" => "rgb(255, 0, 255)",
+        "cyan" | "color8" => "rgb(0, 255, 255)",
+        _ => return None,
+    })
+}
+
+fn parse_condition(s: &str) -> Option<Condition> {
+    let (comparison, rest) = if let Some(rest) = s.strip_prefix("<=") {
+        (Comparison::Le, rest)
+    } else if let Some(rest) = s.strip_prefix(">=") {
+        (Comparison::Ge, rest)
+    } else if let Some(rest) = s.strip_prefix("<>") {
+        (Comparison::Ne, rest)
+    } else if let Some(rest) = s.strip_prefix('<') {
+        (Comparison::Lt, rest)
+    } else if let Some(rest) = s.strip_prefix('>') {
+        (Comparison::Gt, rest)
+    } else if let Some(rest) = s.strip_prefix('=') {
+        (Comparison::Eq, rest)
+    } else {
+        return None;
+    };
+    let value = rest.trim().parse::<f64>().ok()?;
+    Some(Condition { comparison, value })
+}
+
+/// Counts how many times `c` repeats (case-insensitive) starting at `i`.
+fn run_length(chars: &[char], i: usize, c: char) -> usize {
+    chars[i..]
+        .iter()
+        .take_while(|ch| ch.eq_ignore_ascii_case(&c))
+        .count()
+}
+
+fn starts_with_ignore_case(chars: &[char], i: usize, s: &str) -> bool {
+    let len = s.chars().count();
+    i + len <= chars.len()
+        && chars[i..i + len]
+            .iter()
+            .zip(s.chars())
+            .all(|(a, b)| a.eq_ignore_ascii_case(&b))
+}
+
+impl FormatCode {
+    /// Parses a format code.
+    pub fn parse(code: &str) -> Result<Self> {
+        if code.trim().is_empty() {
+            bail!("Format code is empty");
+        }
+
+        let chars: Vec<char> = code.chars().collect();
+        let mut sections = vec![];
+        let mut section = Section::default();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                ';' => {
+                    sections.push(std::mem::take(&mut section));
+                    i += 1;
+                }
+                '"' => {
+                    let Some(end) = chars[i + 1..].iter().position(|&c| c == '"') else {
+                        bail!("Unterminated string in format code: {code}");
+                    };
+                    let literal: String = chars[i + 1..i + 1 + end].iter().collect();
+                    section.tokens.push(Token::Literal(literal));
+                    i += end + 2;
+                }
+                '\\' => {
+                    if let Some(next) = chars.get(i + 1) {
+                        section.tokens.push(Token::Literal(next.to_string()));
+                    }
+                    i += 2;
+                }
+                '_' => {
+                    // leaves space the width of the next character
+                    section.tokens.push(Token::Literal(" ".into()));
+                    i += 2;
+                }
+                '*' => {
+                    // repeat-to-fill has no meaning outside of Excel's layout
+                    i += 2;
+                }
+                '[' => {
+                    let Some(end) = chars[i + 1..].iter().position(|&c| c == ']') else {
+                        bail!("Unterminated bracket in format code: {code}");
+                    };
+                    let inner: String = chars[i + 1..i + 1 + end].iter().collect();
+                    i += end + 2;
+                    Self::parse_bracket(&inner, &mut section)?;
+                }
+                '0' => {
+                    section.tokens.push(Token::Digit(Digit::Zero));
+                    i += 1;
+                }
+                '#' => {
+                    section.tokens.push(Token::Digit(Digit::Hash));
+                    i += 1;
+                }
+                '?' => {
+                    section.tokens.push(Token::Digit(Digit::Question));
+                    i += 1;
+                }
+                '.' => {
+                    let after_seconds = matches!(
+                        section.tokens.last(),
+                        Some(Token::Date(
+                            DatePart::Second | DatePart::Second2 | DatePart::ElapsedSeconds
+                        ))
+                    );
+                    if after_seconds && chars.get(i + 1) == Some(&'0') {
+                        let zeros = run_length(&chars, i + 1, '0');
+                        section
+                            .tokens
+                            .push(Token::Date(DatePart::SubSecond(zeros.min(3))));
+                        i += zeros + 1;
+                    } else {
+                        section.tokens.push(Token::DecimalPoint);
+                        i += 1;
+                    }
+                }
+                ',' => {
+                    section.tokens.push(Token::Comma);
+                    i += 1;
+                }
+                '%' => {
+                    section.tokens.push(Token::Percent);
+                    i += 1;
+                }
+                '@' => {
+                    section.tokens.push(Token::Text);
+                    i += 1;
+                }
+                'E' | 'e' if matches!(chars.get(i + 1), Some('+') | Some('-')) => {
+                    section.tokens.push(Token::Exponent {
+                        plus: chars[i + 1] == '+',
+                    });
+                    i += 2;
+                }
+                'G' | 'g' if starts_with_ignore_case(&chars, i, "general") => {
+                    section.tokens.push(Token::General);
+                    i += "general".len();
+                }
+                'A' | 'a' if starts_with_ignore_case(&chars, i, "am/pm") => {
+                    section.tokens.push(Token::Date(DatePart::AmPm));
+                    i += "am/pm".len();
+                }
+                'A' | 'a' if starts_with_ignore_case(&chars, i, "a/p") => {
+                    section.tokens.push(Token::Date(DatePart::AP));
+                    i += "a/p".len();
+                }
+                'y' | 'Y' => {
+                    let len = run_length(&chars, i, 'y');
+                    let part = if len <= 2 {
+                        DatePart::Year2
+                    } else {
+                        DatePart::Year4
+                    };
+                    section.tokens.push(Token::Date(part));
+                    i += len;
+                }
+                'm' | 'M' => {
+                    let len = run_length(&chars, i, 'm');
+                    let part = match len {
+                        1 => DatePart::Month,
+                        2 => DatePart::Month2,
+                        3 => DatePart::MonthAbbr,
+                        4 => DatePart::MonthName,
+                        _ => DatePart::MonthLetter,
+                    };
+                    section.tokens.push(Token::Date(part));
+                    i += len;
+                }
+                'd' | 'D' => {
+                    let len = run_length(&chars, i, 'd');
+                    let part = match len {
+                        1 => DatePart::Day,
+                        2 => DatePart::Day2,
+                        3 => DatePart::DayAbbr,
+                        _ => DatePart::DayName,
+                    };
+                    section.tokens.push(Token::Date(part));
+                    i += len;
+                }
+                'h' | 'H' => {
+                    let len = run_length(&chars, i, 'h');
+                    let part = if len == 1 {
+                        DatePart::Hour
+                    } else {
+                        DatePart::Hour2
+                    };
+                    section.tokens.push(Token::Date(part));
+                    i += len;
+                }
+                's' | 'S' => {
+                    let len = run_length(&chars, i, 's');
+                    let part = if len == 1 {
+                        DatePart::Second
+                    } else {
+                        DatePart::Second2
+                    };
+                    section.tokens.push(Token::Date(part));
+                    i += len;
+                }
+                _ => {
+                    section.tokens.push(Token::Literal(c.to_string()));
+                    i += 1;
+                }
+            }
+        }
+        sections.push(section);
+
+        if sections.len() > MAX_SECTIONS {
+            bail!("Format code has more than {MAX_SECTIONS} sections: {code}");
+        }
+        sections.iter_mut().for_each(Self::resolve_minutes);
+
+        Ok(Self { sections })
+    }
+
+    fn parse_bracket(inner: &str, section: &mut Section) -> Result<()> {
+        let lower = inner.to_ascii_lowercase();
+        if let Some(color) = excel_color(inner) {
+            section.color = Some(color.to_string());
+        } else if lower.starts_with("color") {
+            // palette colors beyond the first eight are ignored
+        } else if inner.starts_with(['<', '>', '=']) {
+            let Some(condition) = parse_condition(inner) else {
+                bail!("Invalid condition in format code: [{inner}]");
+            };
+            section.condition = Some(condition);
+        } else if let Some(currency) = inner.strip_prefix('$') {
+            // locale and currency, eg: [$€-407] or [$-409]
+            let symbol = currency.split('-').next().unwrap_or_default();
+            if !symbol.is_empty() {
+                section.tokens.push(Token::Literal(symbol.to_string()));
+            }
+        } else if !lower.is_empty() && lower.chars().all(|c| c == 'h') {
+            section.tokens.push(Token::Date(DatePart::ElapsedHours));
+        } else if !lower.is_empty() && lower.chars().all(|c| c == 'm') {
+            section.tokens.push(Token::Date(DatePart::ElapsedMinutes));
+        } else if !lower.is_empty() && lower.chars().all(|c| c == 's') {
+            section.tokens.push(Token::Date(DatePart::ElapsedSeconds));
+        }
+        Ok(())
+    }
+
+    /// `m` and `mm` mean minutes when they follow an hour or precede a second.
+    fn resolve_minutes(section: &mut Section) {
+        let date_indices: Vec<usize> = section
+            .tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| matches!(t, Token::Date(_)))
+            .map(|(i, _)| i)
+            .collect();
+        for (n, &index) in date_indices.iter().enumerate() {
+            let is_month = matches!(
+                section.tokens[index],
+                Token::Date(DatePart::Month | DatePart::Month2)
+            );
+            if !is_month {
+                continue;
+            }
+            let after_hour = n > 0
+                && matches!(
+                    section.tokens[date_indices[n - 1]],
+                    Token::Date(DatePart::Hour | DatePart::Hour2 | DatePart::ElapsedHours)
+                );
+            let before_second = date_indices.get(n + 1).is_some_and(|&next| {
+                matches!(
+                    section.tokens[next],
+                    Token::Date(DatePart::Second | DatePart::Second2)
+                )
+            });
+            if after_hour || before_second {
+                section.tokens[index] = match section.tokens[index] {
+                    Token::Date(DatePart::Month) => Token::Date(DatePart::Minute),
+                    _ => Token::Date(DatePart::Minute2),
+                };
+            }
+        }
+    }
+
+    /// Returns true if the format code displays numbers as dates or times.
+    pub fn is_date_time(&self) -> bool {
+        self.sections.first().is_some_and(Section::is_date_time)
+    }
+
+    /// Returns true if the format code only formats text (eg, `@`).
+    pub fn is_text(&self) -> bool {
+        self.sections.len() == 1
+            && self.sections[0].tokens.contains(&Token::Text)
+            && !self.sections[0]
+                .tokens
+                .iter()
+                .any(|t| matches!(t, Token::Digit(_) | Token::General | Token::Date(_)))
+    }
+
+    /// Formats a value. Returns None if the format code does not apply to the
+    /// value's type.
+    pub fn format(&self, value: &CellValue) -> Option<FormattedValue> {
+        match value {
+            CellValue::Number(n) => Some(self.format_number(n)),
+            CellValue::Text(s) => self.format_text(s),
+            CellValue::Instant(instant) => {
+                BigDecimal::from_f64(instant.to_serial()).map(|n| self.format_number(&n))
+            }
+            CellValue::Duration(duration) => duration
+                .to_days()
+                .and_then(BigDecimal::from_f64)
+                .map(|n| self.format_number(&n)),
+            _ => None,
+        }
+    }
+
+    /// Formats a number using the section that applies to it.
+    pub fn format_number(&self, n: &BigDecimal) -> FormattedValue {
+        let Some((section, absolute)) = self.number_section(n) else {
+            // Excel shows #### when no section applies
+            return FormattedValue {
+                text: "#".repeat(4),
+                color: None,
+            };
+        };
+        let value = if absolute { n.abs() } else { n.clone() };
+        FormattedValue {
+            text: section.format_number(&value),
+            color: section.color.clone(),
+        }
+    }
+
+    /// Formats text using the text section, if there is one.
+    pub fn format_text(&self, text: &str) -> Option<FormattedValue> {
+        let section = if self.sections.len() == MAX_SECTIONS {
+            self.sections.last()
+        } else {
+            self.sections
+                .iter()
+                .find(|section| section.tokens.contains(&Token::Text))
+        }?;
+        Some(FormattedValue {
+            text: section.format_text(text),
+            color: section.color.clone(),
+        })
+    }
+
+    /// Finds the section for a number. The bool is true if the section shows
+    /// the absolute value (ie, the section supplies its own negative styling).
+    fn number_section(&self, n: &BigDecimal) -> Option<(&Section, bool)> {
+        let number_sections = &self.sections[..self.sections.len().min(3)];
+        let value = n.to_f64().unwrap_or_default();
+        if number_sections.iter().any(|s| s.condition.is_some()) {
+            if let Some(section) = number_sections
+                .iter()
+                .take(2)
+                .find(|s| s.condition.is_some_and(|c| c.matches(value)))
+            {
+                return Some((section, false));
+            }
+            return number_sections
+                .iter()
+                .find(|s| s.condition.is_none())
+                .map(|section| (section, false));
+        }
+        Some(match number_sections.len() {
+            1 => (&number_sections[0], false),
+            2 if n.is_negative() => (&number_sections[1], true),
+            2 => (&number_sections[0], false),
+            _ if n.is_negative() => (&number_sections[1], true),
+            _ if n.is_zero() => (&number_sections[2], false),
+            _ => (&number_sections[0], false),
+        })
+    }
+}
+
+impl Section {
+    fn is_date_time(&self) -> bool {
+        self.tokens.iter().any(|t| matches!(t, Token::Date(_)))
+    }
+
+    fn format_text(&self, text: &str) -> String {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Text => Some(text),
+                Token::Literal(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn format_number(&self, n: &BigDecimal) -> String {
+        if self.is_date_time() {
+            return self.format_date_time(n.to_f64().unwrap_or_default());
+        }
+
+        let has_digits = self
+            .tokens
+            .iter()
+            .any(|t| matches!(t, Token::Digit(_) | Token::DecimalPoint));
+        if !has_digits {
+            // eg, `General`, `"Total: "General` or a section of literals
+            return self
+                .tokens
+                .iter()
+                .map(|token| match token {
+                    Token::General => general(n),
+                    Token::Literal(s) => s.clone(),
+                    Token::Percent => "%".into(),
+                    _ => String::new(),
+                })
+                .collect();
+        }
+
+        let layout = NumberLayout::new(&self.tokens);
+        let mut value = n.clone();
+        for _ in 0..layout.percents {
+            value *= BigDecimal::from(100);
+        }
+        for _ in 0..layout.scale {
+            value = value / BigDecimal::from(1000);
+        }
+        let negative = value.is_negative();
+        let value = value.abs();
+
+        let (mantissa, exponent) = match layout.exponent {
+            Some(_) => layout.split_exponent(&value),
+            None => (value, 0),
+        };
+        let rounded = mantissa.with_scale_round(layout.fraction.len() as i64, RoundingMode::HalfUp);
+        let (int_str, frac_str) = split_digits(&rounded);
+        let int_digits = int_str.trim_start_matches('0');
+
+        let int_output = layout.render_integer(int_digits);
+        let frac_output = layout.render_fraction(&frac_str);
+        let exp_output = layout.render_exponent(exponent);
+
+        let mut out = String::new();
+        if negative && !rounded.is_zero() {
+            out.push('-');
+        }
+        let mut int_index = 0;
+        let mut frac_index = 0;
+        let mut phase = Phase::Integer;
+        for token in layout.tokens.iter() {
+            match token {
+                Token::Digit(_) => match phase {
+                    Phase::Integer => {
+                        out.push_str(&int_output[int_index]);
+                        int_index += 1;
+                    }
+                    Phase::Fraction => {
+                        out.push_str(&frac_output[frac_index]);
+                        frac_index += 1;
+                    }
+                    Phase::Exponent => {}
+                },
+                Token::DecimalPoint => {
+                    if phase == Phase::Integer {
+                        out.push('.');
+                        phase = Phase::Fraction;
+                    }
+                }
+                Token::Exponent { .. } => {
+                    if phase != Phase::Exponent {
+                        out.push_str(&exp_output);
+                        phase = Phase::Exponent;
+                    }
+                }
+                Token::Percent => out.push('%'),
+                Token::Literal(s) => out.push_str(s),
+                Token::General => out.push_str(&general(n)),
+                Token::Comma | Token::Text | Token::Date(_) => {}
+            }
+        }
+        out
+    }
+
+    fn format_date_time(&self, serial: f64) -> String {
+        let has_sub_second = self
+            .tokens
+            .iter()
+            .any(|t| matches!(t, Token::Date(DatePart::SubSecond(_))));
+        let twelve_hour = self
+            .tokens
+            .iter()
+            .any(|t| matches!(t, Token::Date(DatePart::AmPm | DatePart::AP)));
+
+        let Some(datetime) = serial_to_datetime(serial, has_sub_second) else {
+            return general(&BigDecimal::from_f64(serial).unwrap_or_default());
+        };
+        let hour12 = match datetime.hour() % 12 {
+            0 => 12,
+            h => h,
+        };
+        let hour = if twelve_hour { hour12 } else { datetime.hour() };
+        let is_pm = datetime.hour() >= 12;
+
+        let mut out = String::new();
+        for token in self.tokens.iter() {
+            match token {
+                Token::Literal(s) => out.push_str(s),
+                Token::Date(part) => match part {
+                    DatePart::Year2 => out.push_str(&format!("{:02}", datetime.year() % 100)),
+                    DatePart::Year4 => out.push_str(&format!("{:04}", datetime.year())),
+                    DatePart::Month => out.push_str(&datetime.month().to_string()),
+                    DatePart::Month2 => out.push_str(&format!("{:02}", datetime.month())),
+                    DatePart::MonthAbbr => {
+                        out.push_str(&MONTH_NAMES[datetime.month0() as usize][..3]);
+                    }
+                    DatePart::MonthName => {
+                        out.push_str(MONTH_NAMES[datetime.month0() as usize]);
+                    }
+                    DatePart::MonthLetter => {
+                        out.push_str(&MONTH_NAMES[datetime.month0() as usize][..1]);
+                    }
+                    DatePart::Day => out.push_str(&datetime.day().to_string()),
+                    DatePart::Day2 => out.push_str(&format!("{:02}", datetime.day())),
+                    DatePart::DayAbbr => {
+                        let weekday = datetime.weekday().num_days_from_sunday() as usize;
+                        out.push_str(&DAY_NAMES[weekday][..3]);
+                    }
+                    DatePart::DayName => {
+                        let weekday = datetime.weekday().num_days_from_sunday() as usize;
+                        out.push_str(DAY_NAMES[weekday]);
+                    }
+                    DatePart::Hour => out.push_str(&hour.to_string()),
+                    DatePart::Hour2 => out.push_str(&format!("{:02}", hour)),
+                    DatePart::Minute => out.push_str(&datetime.minute().to_string()),
+                    DatePart::Minute2 => out.push_str(&format!("{:02}", datetime.minute())),
+                    DatePart::Second => out.push_str(&datetime.second().to_string()),
+                    DatePart::Second2 => out.push_str(&format!("{:02}", datetime.second())),
+                    DatePart::SubSecond(digits) => {
+                        let millis = datetime.nanosecond() / 1_000_000;
+                        let fraction = format!("{:03}", millis.min(999));
+                        out.push('.');
+                        out.push_str(&fraction[..*digits]);
+                    }
+                    DatePart::ElapsedHours => {
+                        out.push_str(&((serial * 24.0).floor() as i64).to_string());
+                    }
+                    DatePart::ElapsedMinutes => {
+                        out.push_str(&((serial * 1_440.0).floor() as i64).to_string());
+                    }
+                    DatePart::ElapsedSeconds => {
+                        out.push_str(&((serial * SECONDS_PER_DAY).round() as i64).to_string());
+                    }
+                    DatePart::AmPm => out.push_str(if is_pm { "PM" } else { "AM" }),
+                    DatePart::AP => out.push(if is_pm { 'P' } else { 'A' }),
+                },
+                Token::Digit(Digit::Zero) => out.push('0'),
+                Token::DecimalPoint => out.push('.'),
+                Token::Comma => out.push(','),
+                Token::Percent => out.push('%'),
+                Token::General | Token::Digit(_) | Token::Exponent { .. } | Token::Text => {}
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Integer,
+    Fraction,
+    Exponent,
+}
+
+/// Placement of the digit placeholders in a numeric section.
+struct NumberLayout {
+    tokens: Vec<Token>,
+    integer: Vec<Digit>,
+    fraction: Vec<Digit>,
+    exponent: Option<(bool, Vec<Digit>)>,
+    thousands: bool,
+    scale: usize,
+    percents: usize,
+}
+
+impl NumberLayout {
+    fn new(tokens: &[Token]) -> Self {
+        let mut tokens = tokens.to_vec();
+
+        // a format without integer placeholders (eg, `.00`) still shows the
+        // integer digits
+        let decimal = tokens.iter().position(|t| *t == Token::DecimalPoint);
+        let first_digit = tokens.iter().position(|t| matches!(t, Token::Digit(_)));
+        if let Some(decimal) = decimal {
+            if first_digit.map_or(true, |first| first > decimal) {
+                tokens.insert(decimal, Token::Digit(Digit::Hash));
+            }
+        }
+
+        let mut integer = vec![];
+        let mut fraction = vec![];
+        let mut exponent: Option<(bool, Vec<Digit>)> = None;
+        let mut thousands = false;
+        let mut scale = 0;
+        let mut percents = 0;
+        let mut phase = Phase::Integer;
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Digit(digit) => match phase {
+                    Phase::Integer => integer.push(*digit),
+                    Phase::Fraction => fraction.push(*digit),
+                    Phase::Exponent => {
+                        if let Some((_, digits)) = exponent.as_mut() {
+                            digits.push(*digit);
+                        }
+                    }
+                },
+                Token::DecimalPoint => {
+                    if phase == Phase::Integer {
+                        phase = Phase::Fraction;
+                    }
+                }
+                Token::Exponent { plus } => {
+                    if exponent.is_none() {
+                        exponent = Some((*plus, vec![]));
+                        phase = Phase::Exponent;
+                    }
+                }
+                Token::Comma => {
+                    let preceded_by_digit = tokens[..index]
+                        .iter()
+                        .rev()
+                        .find(|t| **t != Token::Comma)
+                        .is_some_and(|t| matches!(t, Token::Digit(_)));
+                    let followed_by_digit = tokens[index + 1..]
+                        .iter()
+                        .take_while(|t| !matches!(t, Token::DecimalPoint | Token::Exponent { .. }))
+                        .any(|t| matches!(t, Token::Digit(_)));
+                    if phase == Phase::Integer && preceded_by_digit && followed_by_digit {
+                        thousands = true;
+                    } else if preceded_by_digit && phase != Phase::Exponent {
+                        scale += 1;
+                    }
+                }
+                Token::Percent => percents += 1,
+                _ => {}
+            }
+        }
+
+        Self {
+            tokens,
+            integer,
+            fraction,
+            exponent,
+            thousands,
+            scale,
+            percents,
+        }
+    }
+
+    /// Splits a non-negative value into mantissa and exponent.
+    fn split_exponent(&self, value: &BigDecimal) -> (BigDecimal, i64) {
+        let float = value.to_f64().unwrap_or_default();
+        if float == 0.0 || !float.is_finite() {
+            return (BigDecimal::zero(), 0);
+        }
+        let integer_places = self.integer.len().max(1) as i64;
+        let magnitude = float.log10().floor() as i64;
+        let engineering = integer_places > 1 && self.integer.contains(&Digit::Hash);
+        let mut exponent = if engineering {
+            magnitude.div_euclid(integer_places) * integer_places
+        } else {
+            magnitude - (integer_places - 1)
+        };
+
+        let shift = |exponent: i64| value * power_of_ten(-exponent);
+        let mut mantissa = shift(exponent);
+
+        // rounding may carry into another integer digit (eg, 9.99 -> 10.0)
+        let limit = power_of_ten(integer_places);
+        let rounded = mantissa.with_scale_round(self.fraction.len() as i64, RoundingMode::HalfUp);
+        if rounded >= limit {
+            exponent += if engineering { integer_places } else { 1 };
+            mantissa = shift(exponent);
+        }
+        (mantissa, exponent)
+    }
+
+    /// Renders the integer digits into one string per integer placeholder.
+    /// Extra digits are added to the leftmost placeholder.
+    fn render_integer(&self, digits: &str) -> Vec<String> {
+        let digits: Vec<char> = digits.chars().collect();
+        let count = self.integer.len();
+        let mut output = vec![String::new(); count];
+        for (from_right, digit) in self.integer.iter().rev().enumerate() {
+            let placeholder = count - 1 - from_right;
+            if from_right < digits.len() {
+                output[placeholder].push(digits[digits.len() - 1 - from_right]);
+            } else {
+                output[placeholder].push_str(digit.pad());
+            }
+        }
+        if digits.len() > count && count > 0 {
+            let extra: String = digits[..digits.len() - count].iter().collect();
+            output[0].insert_str(0, &extra);
+        }
+
+        if self.thousands {
+            let total = output
+                .iter()
+                .flat_map(|s| s.chars())
+                .filter(|c| c.is_ascii_digit())
+                .count();
+            let mut seen = 0;
+            for placeholder in output.iter_mut() {
+                let mut grouped = String::new();
+                for c in placeholder.chars() {
+                    grouped.push(c);
+                    if c.is_ascii_digit() {
+                        seen += 1;
+                        let remaining = total - seen;
+                        if remaining > 0 && remaining % 3 == 0 {
+                            grouped.push(',');
+                        }
+                    }
+                }
+                *placeholder = grouped;
+            }
+        }
+        output
+    }
+
+    /// Renders the fraction digits into one string per fraction placeholder.
+    fn render_fraction(&self, digits: &str) -> Vec<String> {
+        let mut digits: Vec<char> = digits.chars().collect();
+        digits.resize(self.fraction.len(), '0');
+        let mut output = vec![String::new(); self.fraction.len()];
+        let mut trailing = true;
+        for (index, placeholder) in self.fraction.iter().enumerate().rev() {
+            let digit = digits[index];
+            if trailing && digit == '0' && *placeholder != Digit::Zero {
+                output[index] = placeholder.pad().to_string();
+            } else {
+                trailing = false;
+                output[index] = digit.to_string();
+            }
+        }
+        output
+    }
+
+    fn render_exponent(&self, exponent: i64) -> String {
+        let Some((plus, digits)) = self.exponent.as_ref() else {
+            return String::new();
+        };
+        let sign = if exponent < 0 {
+            "-"
+        } else if *plus {
+            "+"
+        } else {
+            ""
+        };
+        let width = digits.iter().filter(|d| **d == Digit::Zero).count();
+        format!("E{sign}{:0width$}", exponent.abs(), width = width)
+    }
+}
+
+/// Returns 10^exponent.
+fn power_of_ten(exponent: i64) -> BigDecimal {
+    BigDecimal::new(BigInt::from(1), -exponent)
+}
+
+/// Splits a non-negative decimal into its integer and fraction digits. This
+/// avoids `BigDecimal`'s `Display`, which switches to exponential notation for
+/// very small and very large values.
+fn split_digits(value: &BigDecimal) -> (String, String) {
+    let (int, scale) = value.as_bigint_and_exponent();
+    let mut digits = int.magnitude().to_string();
+    if scale <= 0 {
+        digits.push_str(&"0".repeat(scale.unsigned_abs() as usize));
+        return (digits, String::new());
+    }
+    let scale = scale as usize;
+    if digits.len() <= scale {
+        digits.insert_str(0, &"0".repeat(scale - digits.len() + 1));
+    }
+    let (int, fraction) = digits.split_at(digits.len() - scale);
+    (int.to_string(), fraction.to_string())
+}
+
+/// Converts an Excel serial date into a date and time.
+pub fn serial_to_datetime(serial: f64, keep_sub_second: bool) -> Option<NaiveDateTime> {
+    if !serial.is_finite() {
+        return None;
+    }
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    let millis = if keep_sub_second {
+        (serial * SECONDS_PER_DAY * 1000.0).round() as i64
+    } else {
+        (serial * SECONDS_PER_DAY).round() as i64 * 1000
+    };
+    epoch.checked_add_signed(ChronoDuration::milliseconds(millis))
+}
+
+/// Displays a number the way Excel's `General` format would.
+fn general(n: &BigDecimal) -> String {
+    n.normalized().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn format(code: &str, value: &str) -> String {
+        FormatCode::parse(code)
+            .unwrap()
+            .format_number(&BigDecimal::from_str(value).unwrap())
+            .text
+    }
+
+    #[test]
+    fn number_placeholders() {
+        assert_eq!(format("0", "12.5"), "13");
+        assert_eq!(format("0.00", "3.14159"), "3.14");
+        assert_eq!(format("0.0#", "1.5"), "1.5");
+        assert_eq!(format("0.0#", "1.257"), "1.26");
+        assert_eq!(format("#.00", "0.5"), ".50");
+        assert_eq!(format("000", "7"), "007");
+        assert_eq!(format("??.??", "1.5"), " 1.5 ");
+        assert_eq!(format(".00", "12.5"), "12.50");
+        assert_eq!(format("0", "-3"), "-3");
+    }
+
+    #[test]
+    fn thousands_and_scaling() {
+        assert_eq!(format("#,##0", "1234567"), "1,234,567");
+        assert_eq!(format("#,##0.00", "1234.5"), "1,234.50");
+        assert_eq!(format("#,##0", "123"), "123");
+        assert_eq!(format("0.0,,", "12345678"), "12.3");
+        assert_eq!(format("#,##0,\"K\"", "12345"), "12K");
+    }
+
+    #[test]
+    fn percent_and_exponent() {
+        assert_eq!(format("0.0%", "0.1234"), "12.3%");
+        assert_eq!(format("0%", "1"), "100%");
+        assert_eq!(format("0.00E+00", "12345"), "1.23E+04");
+        assert_eq!(format("0.00E+00", "0.00012"), "1.20E-04");
+        assert_eq!(format("0.00E+00", "9.999"), "1.00E+01");
+        assert_eq!(format("##0.0E+0", "12345"), "12.3E+3");
+    }
+
+    #[test]
+    fn sections_and_colors() {
+        let code = FormatCode::parse("#,##0.00;[Red](#,##0.00)").unwrap();
+        let positive = code.format_number(&BigDecimal::from(1234));
+        assert_eq!(positive.text, "1,234.00");
+        assert_eq!(positive.color, None);
+        let negative = code.format_number(&BigDecimal::from(-1234));
+        assert_eq!(negative.text, "(1,234.00)");
+        assert_eq!(negative.color, Some("rgb(255, 0, 0)".into()));
+
+        let code = FormatCode::parse("0;-0;\"zero\";\"text: \"@").unwrap();
+        assert_eq!(code.format_number(&BigDecimal::zero()).text, "zero");
+        assert_eq!(code.format_text("hi").unwrap().text, "text: hi");
+
+        assert_eq!(format("$#,##0.00", "-5"), "-$5.00");
+        assert_eq!(format("[$€-407]#,##0.00", "5"), "€5.00");
+        assert_eq!(format("0.00_);(0.00)", "5"), "5.00 ");
+    }
+
+    #[test]
+    fn conditions() {
+        let code = FormatCode::parse("[Blue][>=100]0;[Red][<0]0;0.00").unwrap();
+        let big = code.format_number(&BigDecimal::from(150));
+        assert_eq!(big.text, "150");
+        assert_eq!(big.color, Some("rgb(0, 0, 255)".into()));
+        let negative = code.format_number(&BigDecimal::from(-5));
+        assert_eq!(negative.text, "-5");
+        assert_eq!(negative.color, Some("rgb(255, 0, 0)".into()));
+        assert_eq!(code.format_number(&BigDecimal::from(5)).text, "5.00");
+    }
+
+    #[test]
+    fn text_sections() {
+        let code = FormatCode::parse("@").unwrap();
+        assert!(code.is_text());
+        assert_eq!(code.format_text("hello").unwrap().text, "hello");
+        assert_eq!(
+            FormatCode::parse("0.00").unwrap().format_text("hello"),
+            None
+        );
+    }
+
+    #[test]
+    fn dates_and_times() {
+        // 2024-03-05 14:07:09
+        let serial = "45356.588298611";
+        assert_eq!(format("yyyy-mm-dd hh:mm", serial), "2024-03-05 14:07");
+        assert_eq!(format("m/d/yy", serial), "3/5/24");
+        assert_eq!(format("mmm d, yyyy", serial), "Mar 5, 2024");
+        assert_eq!(format("dddd, mmmm d", serial), "Tuesday, March 5");
+        assert_eq!(format("h:mm:ss AM/PM", serial), "2:07:09 PM");
+        assert_eq!(format("hh:mm:ss", serial), "14:07:09");
+        assert_eq!(format("[h]:mm", "1.5"), "36:00");
+        assert!(FormatCode::parse("yyyy-mm-dd").unwrap().is_date_time());
+        assert!(!FormatCode::parse("0.00").unwrap().is_date_time());
+    }
+
+    #[test]
+    fn general_format() {
+        assert_eq!(format("General", "1.50"), "1.5");
+        assert_eq!(format("\"Total: \"General", "12"), "Total: 12");
+    }
+
+    #[test]
+    fn invalid_codes() {
+        assert!(FormatCode::parse("").is_err());
+        assert!(FormatCode::parse("\"unterminated").is_err());
+        assert!(FormatCode::parse("[Red").is_err());
+        assert!(FormatCode::parse("0;0;0;0;0").is_err());
+        assert!(FormatCode::parse("[>abc]0").is_err());
+    }
+}
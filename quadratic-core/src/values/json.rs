@@ -0,0 +1,349 @@
+//! Conversion between JSON records and rows of cell values.
+//!
+//! Imported files are either a JSON array of records, a single record, or
+//! newline-delimited JSON (one record per line). Nested objects are
+//! flattened into dotted column names (`{"a": {"b": 1}}` becomes column
+//! `a.b`), and exports nest dotted column names again.
+
+use std::{collections::HashSet, fmt};
+
+use anyhow::{anyhow, Result};
+use indexmap::{IndexMap, IndexSet};
+use serde::{
+    de::{Error, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::{CellValue, DateLocale, Instant};
+
+/// Column name of records that are not objects.
+const VALUE_COLUMN: &str = "value";
+
+/// A JSON value that keeps the order of object keys, so that columns are
+/// imported and exported in the order they appear.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(IndexMap<String, JsonValue>),
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
+
+struct JsonValueVisitor;
+
+impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        JsonValue::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: Error>(self, value: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Bool(value))
+    }
+
+    fn visit_i64<E: Error>(self, value: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(value.into()))
+    }
+
+    fn visit_u64<E: Error>(self, value: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(value.into()))
+    }
+
+    fn visit_f64<E: Error>(self, value: f64) -> Result<JsonValue, E> {
+        Ok(serde_json::Number::from_f64(value).map_or(JsonValue::Null, JsonValue::Number))
+    }
+
+    fn visit_str<E: Error>(self, value: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value.to_string()))
+    }
+
+    fn visit_string<E: Error>(self, value: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut values = vec![];
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(JsonValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut values = IndexMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            values.insert(key, value);
+        }
+        Ok(JsonValue::Object(values))
+    }
+}
+
+/// Parses a JSON or NDJSON file into column names and rows of values.
+pub fn json_to_records(file: &[u8]) -> Result<(Vec<String>, Vec<Vec<CellValue>>)> {
+    let text = std::str::from_utf8(file).map_err(|e| anyhow!("invalid UTF-8: {e}"))?;
+    let text = text.trim_start_matches('\u{feff}');
+
+    let records = match serde_json::from_str::<JsonValue>(text) {
+        Ok(JsonValue::Array(records)) => records,
+        Ok(record) => vec![record],
+
+        // an array is a single JSON value, so only other files can be NDJSON
+        Err(e) if text.trim_start().starts_with('[') => return Err(e.into()),
+        Err(_) => text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str::<JsonValue>(line).map_err(|e| anyhow!("line {}: {e}", i + 1))
+            })
+            .collect::<Result<_>>()?,
+    };
+
+    let mut columns = IndexSet::new();
+    let rows = records
+        .into_iter()
+        .map(|record| {
+            let mut fields = vec![];
+            match record {
+                JsonValue::Object(_) => flatten(String::new(), record, &mut fields),
+                record => fields.push((VALUE_COLUMN.to_string(), record)),
+            }
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let (x, _) = columns.insert_full(name);
+                    (x, json_to_cell_value(value))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let width = columns.len();
+    let rows = rows
+        .into_iter()
+        .map(|fields| {
+            let mut row = vec![CellValue::Blank; width];
+            for (x, value) in fields {
+                row[x] = value;
+            }
+            row
+        })
+        .collect();
+
+    Ok((columns.into_iter().collect(), rows))
+}
+
+/// Adds the fields of a value to `fields`, naming nested fields by their
+/// dotted path.
+fn flatten(name: String, value: JsonValue, fields: &mut Vec<(String, JsonValue)>) {
+    match value {
+        JsonValue::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let name = match name.is_empty() {
+                    true => key,
+                    false => format!("{name}.{key}"),
+                };
+                flatten(name, value, fields);
+            }
+        }
+        value => fields.push((name, value)),
+    }
+}
+
+fn json_to_cell_value(value: JsonValue) -> CellValue {
+    match value {
+        JsonValue::Null => CellValue::Blank,
+        JsonValue::Bool(value) => CellValue::Logical(value),
+        JsonValue::Number(n) => CellValue::unpack_str_float(&n.to_string(), CellValue::Blank),
+        JsonValue::String(s) => match is_iso_date(&s) {
+            true => Instant::parse(&s, DateLocale::Iso).map_or(CellValue::Text(s), Into::into),
+            false => CellValue::Text(s),
+        },
+        // arrays and empty objects are kept as JSON text
+        value => CellValue::Text(serde_json::to_string(&value).unwrap_or_default()),
+    }
+}
+
+/// Returns whether a string starts like an ISO 8601 date (`2024-01-15`), so
+/// that other strings are never read as dates.
+fn is_iso_date(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    bytes.len() >= 10
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+}
+
+/// Writes columns of values as JSON records keyed by `headers`, either as a
+/// JSON array or as NDJSON (one record per line).
+pub fn records_to_json(
+    headers: &[String],
+    columns: &[Vec<CellValue>],
+    ndjson: bool,
+) -> Result<String> {
+    // a dotted header is nested unless one of its prefixes is also a header,
+    // so that no value is overwritten
+    let names = headers.iter().map(String::as_str).collect::<HashSet<_>>();
+    let paths = headers
+        .iter()
+        .map(|header| {
+            let path = header.split('.').collect::<Vec<_>>();
+            let conflicts = path.iter().any(|part| part.is_empty())
+                || (1..path.len()).any(|i| names.contains(path[..i].join(".").as_str()));
+            match conflicts {
+                true => vec![header.as_str()],
+                false => path,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let height = columns.first().map_or(0, Vec::len);
+    let records = (0..height).map(|y| {
+        let mut record = IndexMap::new();
+        for (path, column) in paths.iter().zip(columns) {
+            insert_path(&mut record, path, cell_value_to_json(&column[y]));
+        }
+        JsonValue::Object(record)
+    });
+
+    if ndjson {
+        let mut output = String::new();
+        for record in records {
+            output.push_str(&serde_json::to_string(&record)?);
+            output.push('\n');
+        }
+        Ok(output)
+    } else {
+        Ok(serde_json::to_string_pretty(&records.collect::<Vec<_>>())?)
+    }
+}
+
+fn insert_path(record: &mut IndexMap<String, JsonValue>, path: &[&str], value: JsonValue) {
+    match path {
+        [] => {}
+        [name] => {
+            record.insert(name.to_string(), value);
+        }
+        [name, rest @ ..] => {
+            let nested = record
+                .entry(name.to_string())
+                .or_insert_with(|| JsonValue::Object(IndexMap::new()));
+            if let JsonValue::Object(nested) = nested {
+                insert_path(nested, rest, value);
+            }
+        }
+    }
+}
+
+fn cell_value_to_json(value: &CellValue) -> JsonValue {
+    match value {
+        CellValue::Blank => JsonValue::Null,
+        CellValue::Text(s) => JsonValue::String(s.clone()),
+        CellValue::Number(n) => serde_json::from_str(&n.to_string())
+            .map_or_else(|_| JsonValue::String(n.to_string()), JsonValue::Number),
+        CellValue::Logical(value) => JsonValue::Bool(*value),
+        value => JsonValue::String(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_json_records() {
+        let file = br#"[
+            {"name": "a", "price": 1.5, "in_stock": true, "meta": {"id": 1, "tags": ["x"]}},
+            {"price": null, "name": "b", "added": "2024-01-15", "meta": {"id": 2}}
+        ]"#;
+        let (headers, rows) = json_to_records(file).unwrap();
+        assert_eq!(
+            headers,
+            vec!["name", "price", "in_stock", "meta.id", "meta.tags", "added"]
+        );
+        assert_eq!(
+            rows[0],
+            vec![
+                CellValue::Text("a".into()),
+                CellValue::unpack_str_float("1.5", CellValue::Blank),
+                CellValue::Logical(true),
+                CellValue::unpack_str_float("1", CellValue::Blank),
+                CellValue::Text(r#"["x"]"#.into()),
+                CellValue::Blank,
+            ]
+        );
+        assert_eq!(rows[1][0], CellValue::Text("b".into()));
+        assert_eq!(rows[1][1], CellValue::Blank);
+        assert!(matches!(rows[1][5], CellValue::Instant(_)));
+    }
+
+    #[test]
+    fn reads_ndjson_records() {
+        let file = b"{\"a\": 1}\n\n{\"b\": \"x\"}\n";
+        let (headers, rows) = json_to_records(file).unwrap();
+        assert_eq!(headers, vec!["a", "b"]);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1], vec![CellValue::Blank, CellValue::Text("x".into())]);
+
+        let error = json_to_records(b"{\"a\": 1}\n{\"a\": }").unwrap_err();
+        assert!(error.to_string().starts_with("line 2:"));
+
+        let (headers, rows) = json_to_records(b"[1, \"2\"]").unwrap();
+        assert_eq!(headers, vec![VALUE_COLUMN]);
+        assert_eq!(rows[1], vec![CellValue::Text("2".into())]);
+    }
+
+    #[test]
+    fn writes_json_records() {
+        let headers = vec![
+            "name".to_string(),
+            "meta.id".to_string(),
+            "a".to_string(),
+            "a.b".to_string(),
+        ];
+        let columns = vec![
+            vec![CellValue::Text("x".into()), CellValue::Blank],
+            vec![
+                CellValue::unpack_str_float("1", CellValue::Blank),
+                CellValue::unpack_str_float("2.5", CellValue::Blank),
+            ],
+            vec![CellValue::Logical(true), CellValue::Logical(false)],
+            vec![CellValue::Text("y".into()), CellValue::Blank],
+        ];
+
+        let ndjson = records_to_json(&headers, &columns, true).unwrap();
+        assert_eq!(
+            ndjson,
+            "{\"name\":\"x\",\"meta\":{\"id\":1},\"a\":true,\"a.b\":\"y\"}\n\
+             {\"name\":null,\"meta\":{\"id\":2.5},\"a\":false,\"a.b\":null}\n"
+        );
+
+        // exported records are imported with the same columns
+        let json = records_to_json(&headers, &columns, false).unwrap();
+        let (imported_headers, rows) = json_to_records(json.as_bytes()).unwrap();
+        assert_eq!(imported_headers, headers);
+        assert_eq!(rows[1][1], columns[1][1]);
+    }
+}
@@ -9,7 +9,9 @@ pub mod arrow;
 pub mod cell_values;
 pub mod cellvalue;
 mod convert;
+pub mod format_code;
 mod isblank;
+pub mod json;
 pub mod parquet;
 mod time;
 
@@ -18,8 +20,9 @@ pub use array_size::{ArraySize, Axis};
 pub use cellvalue::CellValue;
 pub use cellvalue::CodeCellValue;
 pub use convert::CoerceInto;
+pub use format_code::{FormatCode, FormattedValue};
 pub use isblank::IsBlank;
-pub use time::{Duration, Instant};
+pub use time::{DateLocale, Duration, Instant};
 
 use crate::{CodeResult, CodeResultExt, RunErrorMsg, SpannableIterExt, Spanned};
 
@@ -1,8 +1,16 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use arrow_array::RecordBatch;
 use bytes::Bytes;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::{Compression, GzipLevel},
+    file::properties::WriterProperties,
+};
 
-use crate::{arrow::arrow_col_to_cell_value_vec, CellValue};
+use crate::{
+    arrow::{arrow_col_to_cell_value_vec, ExportCompression},
+    CellValue,
+};
 
 pub fn parquet_to_vec(file: Vec<u8>) -> Result<Vec<Vec<CellValue>>> {
     if file.is_empty() {
@@ -43,6 +51,27 @@ pub fn parquet_to_vec(file: Vec<u8>) -> Result<Vec<Vec<CellValue>>> {
 
     Ok(output)
 }
+
+/// Writes a record batch to the bytes of a Parquet file.
+pub fn record_batch_to_parquet(
+    batch: &RecordBatch,
+    compression: ExportCompression,
+) -> Result<Vec<u8>> {
+    let compression = match compression {
+        ExportCompression::None => Compression::UNCOMPRESSED,
+        ExportCompression::Snappy => Compression::SNAPPY,
+        ExportCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+        other => bail!("{other:?} compression is not supported for Parquet files"),
+    };
+    let properties = WriterProperties::builder()
+        .set_compression(compression)
+        .build();
+
+    let mut writer = ArrowWriter::try_new(vec![], batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    Ok(writer.into_inner()?)
+}
+
 #[cfg(test)]
 mod test {
     use std::fs::File;
@@ -62,4 +91,50 @@ mod test {
         let _results = parquet_to_vec(buffer);
         // println!("{:?}", results);
     }
+
+    #[test]
+    fn test_record_batch_to_parquet() {
+        let batch = crate::arrow::cell_values_to_record_batch(
+            vec!["name".into(), "count".into(), "ratio".into(), "ok".into()],
+            vec![
+                vec!["a".into(), "b".into()],
+                vec![CellValue::Number(1.into()), CellValue::Number(2.into())],
+                vec![
+                    CellValue::Number(1.into()),
+                    CellValue::Number("0.5".parse().unwrap()),
+                ],
+                vec![CellValue::Logical(true), CellValue::Logical(false)],
+            ],
+        )
+        .unwrap();
+
+        for compression in [
+            ExportCompression::None,
+            ExportCompression::Snappy,
+            ExportCompression::Gzip,
+        ] {
+            let file = record_batch_to_parquet(&batch, compression).unwrap();
+            let values = parquet_to_vec(file).unwrap();
+            assert_eq!(
+                values,
+                vec![
+                    vec!["name".into(), "count".into(), "ratio".into(), "ok".into()],
+                    vec![
+                        "a".into(),
+                        CellValue::Number(1.into()),
+                        CellValue::Number(1.into()),
+                        CellValue::Logical(true)
+                    ],
+                    vec![
+                        "b".into(),
+                        CellValue::Number(2.into()),
+                        CellValue::Number("0.5".parse().unwrap()),
+                        CellValue::Logical(false)
+                    ],
+                ]
+            );
+        }
+
+        assert!(record_batch_to_parquet(&batch, ExportCompression::Lz4).is_err());
+    }
 }
@@ -1,10 +1,80 @@
-use std::fmt::{self, Display};
+use std::fmt;
 
-use anyhow::{bail, Result};
-use chrono::{DateTime, MappedLocalTime, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
-use crate::CellValue;
+/// Seconds in a day, used to convert serial dates.
+pub const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Serial number of 1970-01-01. Serial dates count days since 1899-12-30, the
+/// same as Excel.
+pub const UNIX_EPOCH_SERIAL: f64 = 25_569.0;
+
+/// Date formats that never depend on the locale.
+const ISO_DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d"];
+
+/// Numeric date formats with the month before the day.
+const MONTH_FIRST_DATE_FORMATS: &[&str] = &["%m/%d/%y", "%m/%d/%Y", "%m-%d-%y", "%m-%d-%Y"];
+
+/// Numeric date formats with the day before the month.
+const DAY_FIRST_DATE_FORMATS: &[&str] = &[
+    "%d/%m/%y", "%d/%m/%Y", "%d.%m.%y", "%d.%m.%Y", "%d-%m-%y", "%d-%m-%Y",
+];
+
+/// Date formats with month names, which are never ambiguous.
+const NAMED_MONTH_DATE_FORMATS: &[&str] =
+    &["%B %d, %Y", "%B %d %Y", "%d %B %Y", "%d-%b-%y", "%d-%b-%Y"];
+
+/// Time formats that may follow a date.
+const TIME_FORMATS: &[&str] = &[
+    "%H:%M",
+    "%H:%M:%S",
+    "%H:%M:%S%.f",
+    "%I:%M %p",
+    "%I:%M:%S %p",
+    "%I:%M%p",
+];
+
+/// Longer strings are never dates. The longest formats, with a month name
+/// and nanoseconds, are under 40 characters.
+const MAX_DATE_LENGTH: usize = 64;
+
+/// A format to parse dates with, which may include a time.
+struct DateFormat {
+    format: String,
+    has_time: bool,
+}
+
+/// Combines the date formats with the time formats, in the order that they
+/// are tried.
+fn date_formats(numeric_formats: &[&str]) -> Vec<DateFormat> {
+    let mut formats = vec![];
+    for date_format in ISO_DATE_FORMATS
+        .iter()
+        .chain(numeric_formats)
+        .chain(NAMED_MONTH_DATE_FORMATS)
+    {
+        formats.push(DateFormat {
+            format: date_format.to_string(),
+            has_time: false,
+        });
+        for time_format in TIME_FORMATS {
+            for separator in [" ", "T"] {
+                formats.push(DateFormat {
+                    format: format!("{date_format}{separator}{time_format}"),
+                    has_time: true,
+                });
+            }
+        }
+    }
+    formats
+}
+
+lazy_static! {
+    static ref MONTH_FIRST_FORMATS: Vec<DateFormat> = date_formats(MONTH_FIRST_DATE_FORMATS);
+    static ref DAY_FIRST_FORMATS: Vec<DateFormat> = date_formats(DAY_FIRST_DATE_FORMATS);
+}
 
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
@@ -18,24 +88,97 @@ impl Instant {
     pub fn new(seconds: f64) -> Self {
         Self { seconds }
     }
+
+    /// Creates an instant from a serial date (days since 1899-12-30).
+    /// Rounds to the nearest millisecond, since serial dates from other
+    /// spreadsheets rarely land exactly on a second.
+    pub fn from_serial(serial: f64) -> Self {
+        let millis = ((serial - UNIX_EPOCH_SERIAL) * SECONDS_PER_DAY * 1000.0).round();
+        Self::new(millis / 1000.0)
+    }
+
+    /// Returns the serial date (days since 1899-12-30). This is the value
+    /// used when an instant is treated as a number.
+    pub fn to_serial(&self) -> f64 {
+        self.seconds / SECONDS_PER_DAY + UNIX_EPOCH_SERIAL
+    }
+
+    /// Returns the date and time, or `None` if it is out of range.
+    pub fn to_naive_datetime(&self) -> Option<NaiveDateTime> {
+        if !self.seconds.is_finite() {
+            return None;
+        }
+        DateTime::from_timestamp_millis((self.seconds * 1000.0).round() as i64)
+            .map(|datetime| datetime.naive_utc())
+    }
+
+    /// Returns true if the instant has no time of day.
+    pub fn is_date(&self) -> bool {
+        self.to_naive_datetime()
+            .is_some_and(|datetime| datetime.time() == NaiveTime::MIN)
+    }
+
+    /// Parses a date or date and time, using `locale` to decide between
+    /// month-first and day-first dates (eg, `1/2/2024`).
+    pub fn parse(value: &str, locale: DateLocale) -> Option<Self> {
+        let value = value.trim();
+
+        // every format has a day or a year, so most text is rejected here
+        // without trying them
+        if value.len() > MAX_DATE_LENGTH || !value.bytes().any(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+            return Some(datetime.naive_utc().into());
+        }
+
+        let formats: &[DateFormat] = if locale.day_first() {
+            &DAY_FIRST_FORMATS
+        } else {
+            &MONTH_FIRST_FORMATS
+        };
+
+        formats.iter().find_map(|DateFormat { format, has_time }| {
+            if *has_time {
+                NaiveDateTime::parse_from_str(value, format)
+                    .ok()
+                    .map(Into::into)
+            } else {
+                NaiveDate::parse_from_str(value, format)
+                    .ok()
+                    .map(Into::into)
+            }
+        })
+    }
 }
 
 impl From<NaiveDateTime> for Instant {
     fn from(datetime: NaiveDateTime) -> Self {
-        Self {
-            seconds: datetime.and_utc().timestamp() as f64,
-        }
+        let seconds = datetime.and_utc().timestamp() as f64;
+        let nanos = datetime.nanosecond() as f64 / 1_000_000_000.0;
+        Self::new(seconds + nanos)
+    }
+}
+
+impl From<NaiveDate> for Instant {
+    fn from(date: NaiveDate) -> Self {
+        date.and_time(NaiveTime::MIN).into()
     }
 }
 
 impl fmt::Display for Instant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            CellValue::unpack_unix_timestamp(self.seconds as i64).unwrap_or_default()
-        )
-        // write!(f, "{s} seconds", s = self.seconds)
+        match self.to_naive_datetime() {
+            Some(datetime) if datetime.time() == NaiveTime::MIN => {
+                write!(f, "{}", datetime.format("%Y-%m-%d"))
+            }
+            Some(datetime) if datetime.nanosecond() != 0 => {
+                write!(f, "{}", datetime.format("%Y-%m-%d %H:%M:%S%.3f"))
+            }
+            Some(datetime) => write!(f, "{}", datetime.format("%Y-%m-%d %H:%M:%S")),
+            None => write!(f, "{} seconds", self.seconds),
+        }
     }
 }
 
@@ -66,15 +209,152 @@ pub struct Duration {
     pub seconds: f64,
 }
 
+impl Duration {
+    /// Creates a duration that is a fixed number of seconds.
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self {
+            years: 0,
+            months: 0,
+            seconds,
+        }
+    }
+
+    /// Creates a duration from a number of days, which is how spreadsheets
+    /// store times and durations.
+    pub fn from_days(days: f64) -> Self {
+        Self::from_seconds((days * SECONDS_PER_DAY * 1000.0).round() / 1000.0)
+    }
+
+    /// Returns the length in days, or `None` if the duration includes years
+    /// or months, which have no fixed length.
+    pub fn to_days(&self) -> Option<f64> {
+        (self.years == 0 && self.months == 0).then(|| self.seconds / SECONDS_PER_DAY)
+    }
+
+    /// Parses a time (`13:45`, `1:45:30 PM`), an elapsed time (`36:00:00`),
+    /// or an ISO 8601 duration (`P1Y2M3DT4H5M6S`).
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.starts_with('P') || value.starts_with("-P") {
+            return Self::parse_iso(value);
+        }
+
+        let (negative, value) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+
+        let upper = value.to_ascii_uppercase();
+        let (value, pm) = if let Some(rest) = upper.strip_suffix("PM") {
+            (rest.trim_end(), Some(true))
+        } else if let Some(rest) = upper.strip_suffix("AM") {
+            (rest.trim_end(), Some(false))
+        } else {
+            (upper.as_str(), None)
+        };
+
+        let parts = value.split(':').collect::<Vec<_>>();
+        if !(2..=3).contains(&parts.len())
+            || parts.iter().any(|part| {
+                part.is_empty() || !part.chars().all(|c| c.is_ascii_digit() || c == '.')
+            })
+        {
+            return None;
+        }
+
+        let mut hours = parts[0].parse::<u32>().ok()?;
+        let minutes = parts[1].parse::<u32>().ok()?;
+        let seconds = match parts.get(2) {
+            Some(seconds) => seconds.parse::<f64>().ok()?,
+            None => 0.0,
+        };
+        if minutes >= 60 || seconds >= 60.0 {
+            return None;
+        }
+        if let Some(pm) = pm {
+            if !(1..=12).contains(&hours) || negative {
+                return None;
+            }
+            hours = match (hours, pm) {
+                (12, false) => 0,
+                (12, true) => 12,
+                (hours, true) => hours + 12,
+                (hours, false) => hours,
+            };
+        }
+
+        let total = hours as f64 * 3600.0 + minutes as f64 * 60.0 + seconds;
+        Some(Self::from_seconds(if negative { -total } else { total }))
+    }
+
+    /// Parses an ISO 8601 duration (eg, `P1Y2M3DT4H5M6.5S` or `PT10H`).
+    fn parse_iso(value: &str) -> Option<Self> {
+        let (negative, value) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let value = value.strip_prefix('P')?;
+        if value.is_empty() {
+            return None;
+        }
+
+        let mut duration = Self::from_seconds(0.0);
+        let mut in_time = false;
+        let mut number = String::new();
+        for c in value.chars() {
+            match c {
+                'T' if !in_time && number.is_empty() => in_time = true,
+                '0'..='9' | '.' => number.push(c),
+                _ => {
+                    let n = std::mem::take(&mut number).parse::<f64>().ok()?;
+                    match (c, in_time) {
+                        ('Y', false) => duration.years += n as i32,
+                        ('M', false) => duration.months += n as i32,
+                        ('W', false) => duration.seconds += n * 7.0 * SECONDS_PER_DAY,
+                        ('D', false) => duration.seconds += n * SECONDS_PER_DAY,
+                        ('H', true) => duration.seconds += n * 3600.0,
+                        ('M', true) => duration.seconds += n * 60.0,
+                        ('S', true) => duration.seconds += n,
+                        _ => return None,
+                    }
+                }
+            }
+        }
+        if !number.is_empty() {
+            return None;
+        }
+
+        if negative {
+            duration.years = -duration.years;
+            duration.months = -duration.months;
+            duration.seconds = -duration.seconds;
+        }
+        Some(duration)
+    }
+}
+
 impl fmt::Display for Duration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{y} years, {m} months, {s} seconds",
-            y = self.years,
-            m = self.months,
-            s = self.seconds,
-        )
+        if self.years != 0 || self.months != 0 {
+            return write!(
+                f,
+                "{y} years, {m} months, {s} seconds",
+                y = self.years,
+                m = self.months,
+                s = self.seconds,
+            );
+        }
+
+        // elapsed time, eg: 36:00:00
+        let sign = if self.seconds < 0.0 { "-" } else { "" };
+        let millis = (self.seconds.abs() * 1000.0).round() as u64;
+        let (hours, minutes) = (millis / 3_600_000, millis / 60_000 % 60);
+        let (seconds, millis) = (millis / 1000 % 60, millis % 1000);
+        write!(f, "{sign}{hours}:{minutes:02}:{seconds:02}")?;
+        if millis != 0 {
+            write!(f, ".{millis:03}")?;
+        }
+        Ok(())
     }
 }
 
@@ -106,11 +386,266 @@ impl Ord for Duration {
     }
 }
 
-pub fn map_local_result<T: chrono::TimeZone + Display>(
-    value: MappedLocalTime<DateTime<T>>,
-) -> Result<DateTime<T>> {
-    match value {
-        chrono::LocalResult::Single(timestamp) => Ok(timestamp),
-        _ => bail!("Could not parse timestamp: {:?}", value),
+/// Locale conventions for entering and displaying dates and times.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DateLocale {
+    /// United States: 1/15/2024, 1:45:00 PM
+    #[default]
+    EnUs,
+    /// United Kingdom: 15/01/2024, 13:45:00
+    EnGb,
+    /// Germany: 15.01.2024, 13:45:00
+    De,
+    /// France: 15/01/2024, 13:45:00
+    Fr,
+    /// Japan: 2024/01/15, 13:45:00
+    Ja,
+    /// ISO 8601: 2024-01-15, 13:45:00
+    Iso,
+}
+
+impl DateLocale {
+    /// Returns the locale for a BCP 47 language tag (eg, `en-GB` or `de`).
+    /// Unknown regions fall back to the language's default.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_ascii_lowercase().replace('_', "-");
+        let locale = match tag.as_str() {
+            "iso" => DateLocale::Iso,
+            "en-us" | "en" => DateLocale::EnUs,
+            "en-gb" | "en-au" | "en-nz" | "en-ie" | "en-in" => DateLocale::EnGb,
+            _ => match tag.split('-').next()? {
+                "en" => DateLocale::EnUs,
+                "de" => DateLocale::De,
+                "fr" => DateLocale::Fr,
+                "ja" => DateLocale::Ja,
+                _ => return None,
+            },
+        };
+        Some(locale)
+    }
+
+    /// Returns the language tag of the locale.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            DateLocale::EnUs => "en-US",
+            DateLocale::EnGb => "en-GB",
+            DateLocale::De => "de-DE",
+            DateLocale::Fr => "fr-FR",
+            DateLocale::Ja => "ja-JP",
+            DateLocale::Iso => "iso",
+        }
+    }
+
+    /// Returns true if numeric dates are written day first (eg, 15/01/2024).
+    pub fn day_first(&self) -> bool {
+        matches!(self, DateLocale::EnGb | DateLocale::De | DateLocale::Fr)
+    }
+
+    /// Format code for dates.
+    pub fn date_pattern(&self) -> &'static str {
+        match self {
+            DateLocale::EnUs => "m/d/yyyy",
+            DateLocale::EnGb | DateLocale::Fr => "dd/mm/yyyy",
+            DateLocale::De => "dd.mm.yyyy",
+            DateLocale::Ja => "yyyy/mm/dd",
+            DateLocale::Iso => "yyyy-mm-dd",
+        }
+    }
+
+    /// Format code for times.
+    pub fn time_pattern(&self) -> &'static str {
+        match self {
+            DateLocale::EnUs => "h:mm:ss AM/PM",
+            _ => "hh:mm:ss",
+        }
+    }
+
+    /// Format code for dates with times.
+    pub fn date_time_pattern(&self) -> String {
+        format!("{} {}", self.date_pattern(), self.time_pattern())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> Instant {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().into()
+    }
+
+    fn datetime(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> Instant {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_instant_serial() {
+        let instant = date(2024, 1, 1);
+        assert_eq!(instant.to_serial(), 45292.0);
+        assert_eq!(Instant::from_serial(45292.0), instant);
+
+        // 13:00 isn't exactly representable as a serial date
+        assert_eq!(
+            Instant::from_serial(45292.541666666664),
+            datetime(2024, 1, 1, 13, 0, 0)
+        );
+        assert_eq!(Instant::from_serial(UNIX_EPOCH_SERIAL).seconds, 0.0);
+    }
+
+    #[test]
+    fn test_instant_display() {
+        assert_eq!(date(2016, 10, 20).to_string(), "2016-10-20");
+        assert_eq!(
+            datetime(2024, 1, 1, 13, 0, 0).to_string(),
+            "2024-01-01 13:00:00"
+        );
+        assert_eq!(Instant::new(0.5).to_string(), "1970-01-01 00:00:00.500");
+        assert!(date(2016, 10, 20).is_date());
+        assert!(!datetime(2016, 10, 20, 0, 0, 1).is_date());
+    }
+
+    #[test]
+    fn test_instant_parse() {
+        let parse = |s| Instant::parse(s, DateLocale::EnUs);
+        assert_eq!(parse("2024-01-15"), Some(date(2024, 1, 15)));
+        assert_eq!(parse("2024/1/15"), Some(date(2024, 1, 15)));
+        assert_eq!(parse("1/15/2024"), Some(date(2024, 1, 15)));
+        assert_eq!(parse("01/15/24"), Some(date(2024, 1, 15)));
+        assert_eq!(parse("January 15, 2024"), Some(date(2024, 1, 15)));
+        assert_eq!(parse("Jan 15, 2024"), Some(date(2024, 1, 15)));
+        assert_eq!(parse("15 Jan 2024"), Some(date(2024, 1, 15)));
+        assert_eq!(parse("15-Jan-24"), Some(date(2024, 1, 15)));
+        assert_eq!(
+            parse("2024-01-15 13:45"),
+            Some(datetime(2024, 1, 15, 13, 45, 0))
+        );
+        assert_eq!(
+            parse("2024-01-15T13:45:30"),
+            Some(datetime(2024, 1, 15, 13, 45, 30))
+        );
+        assert_eq!(
+            parse("2024-01-15T13:45:30+02:00"),
+            Some(datetime(2024, 1, 15, 11, 45, 30))
+        );
+        assert_eq!(
+            parse("1/15/2024 1:45 PM"),
+            Some(datetime(2024, 1, 15, 13, 45, 0))
+        );
+
+        // day first
+        assert_eq!(parse("15/01/2024"), None);
+        assert_eq!(
+            Instant::parse("15/01/2024", DateLocale::EnGb),
+            Some(date(2024, 1, 15))
+        );
+        assert_eq!(
+            Instant::parse("15.01.2024", DateLocale::De),
+            Some(date(2024, 1, 15))
+        );
+
+        // not dates
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("2024"), None);
+        assert_eq!(parse("1.5"), None);
+        assert_eq!(parse("hello"), None);
+        assert_eq!(parse("2024-13-01"), None);
+
+        // surrounding whitespace doesn't count toward the length limit
+        let padded = format!("2024-01-15{}", " ".repeat(100));
+        assert_eq!(
+            Instant::parse(&padded, DateLocale::EnUs),
+            Some(date(2024, 1, 15))
+        );
+        let long = format!("January 15, 2024{}", "!".repeat(100));
+        assert_eq!(Instant::parse(&long, DateLocale::EnUs), None);
+    }
+
+    #[test]
+    fn test_duration_parse() {
+        assert_eq!(
+            Duration::parse("13:45"),
+            Some(Duration::from_seconds(49500.0))
+        );
+        assert_eq!(
+            Duration::parse("1:45:30 PM"),
+            Some(Duration::from_seconds(49530.0))
+        );
+        assert_eq!(
+            Duration::parse("12:00 am"),
+            Some(Duration::from_seconds(0.0))
+        );
+        assert_eq!(
+            Duration::parse("36:00:00"),
+            Some(Duration::from_seconds(129600.0))
+        );
+        assert_eq!(
+            Duration::parse("-0:30"),
+            Some(Duration::from_seconds(-1800.0))
+        );
+        assert_eq!(
+            Duration::parse("0:00:01.5"),
+            Some(Duration::from_seconds(1.5))
+        );
+        assert_eq!(
+            Duration::parse("PT10H10M10S"),
+            Some(Duration::from_seconds(36610.0))
+        );
+        assert_eq!(
+            Duration::parse("P1Y2M3D"),
+            Some(Duration {
+                years: 1,
+                months: 2,
+                seconds: 3.0 * SECONDS_PER_DAY
+            })
+        );
+
+        assert_eq!(Duration::parse("13:60"), None);
+        assert_eq!(Duration::parse("13:45 PM"), None);
+        assert_eq!(Duration::parse("1:2:3:4"), None);
+        assert_eq!(Duration::parse("P"), None);
+        assert_eq!(Duration::parse("PT1X"), None);
+        assert_eq!(Duration::parse("hello"), None);
+    }
+
+    #[test]
+    fn test_duration_display() {
+        assert_eq!(Duration::from_seconds(49530.0).to_string(), "13:45:30");
+        assert_eq!(Duration::from_seconds(129600.0).to_string(), "36:00:00");
+        assert_eq!(Duration::from_seconds(-1800.0).to_string(), "-0:30:00");
+        assert_eq!(Duration::from_seconds(1.5).to_string(), "0:00:01.500");
+        assert_eq!(Duration::from_days(0.5).to_days(), Some(0.5));
+        assert_eq!(
+            Duration {
+                years: 1,
+                months: 2,
+                seconds: 3.0
+            }
+            .to_string(),
+            "1 years, 2 months, 3 seconds"
+        );
+    }
+
+    #[test]
+    fn test_date_locale() {
+        assert_eq!(DateLocale::from_tag("en-US"), Some(DateLocale::EnUs));
+        assert_eq!(DateLocale::from_tag("en_GB"), Some(DateLocale::EnGb));
+        assert_eq!(DateLocale::from_tag("en-CA"), Some(DateLocale::EnUs));
+        assert_eq!(DateLocale::from_tag("de-AT"), Some(DateLocale::De));
+        assert_eq!(DateLocale::from_tag("xx"), None);
+        assert_eq!(DateLocale::De.date_time_pattern(), "dd.mm.yyyy hh:mm:ss");
+        for locale in [
+            DateLocale::EnUs,
+            DateLocale::EnGb,
+            DateLocale::De,
+            DateLocale::Fr,
+            DateLocale::Ja,
+            DateLocale::Iso,
+        ] {
+            assert_eq!(DateLocale::from_tag(locale.tag()), Some(locale));
+        }
     }
 }
@@ -12,8 +12,9 @@ use crate::{
 impl GridController {
     /// Returns the clipboard [`JsClipboard`]
     #[wasm_bindgen(js_name = "copyToClipboard")]
-    pub fn js_copy_to_clipboard(&self, selection: String) -> Result<JsValue, JsValue> {
+    pub fn js_copy_to_clipboard(&mut self, selection: String) -> Result<JsValue, JsValue> {
         let selection = Selection::from_str(&selection).map_err(|_| "Invalid selection")?;
+        self.load_sheet_on_access(selection.sheet_id);
         let sheet = self.try_sheet(selection.sheet_id).ok_or("No Sheet found")?;
         let (plain_text, html) = sheet.copy_to_clipboard(&selection)?;
         let output = JsClipboard { plain_text, html };
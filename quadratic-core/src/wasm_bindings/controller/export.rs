@@ -1,17 +1,74 @@
 use std::str::FromStr;
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
-use crate::{controller::GridController, selection::Selection};
+use crate::{
+    controller::{export::ColumnarExportOptions, GridController},
+    selection::Selection,
+};
 
 #[wasm_bindgen]
 impl GridController {
     /// Returns [`TransactionSummary`]
     #[wasm_bindgen(js_name = "exportCsvSelection")]
-    pub fn js_export_csv_selection(&self, selection: String) -> Result<String, JsValue> {
+    pub fn js_export_csv_selection(&mut self, selection: String) -> Result<String, JsValue> {
         let selection = Selection::from_str(&selection).map_err(|e| e.to_string())?;
         let output = self
             .export_csv_selection(selection)
             .map_err(|e| e.to_string())?;
         Ok(output)
     }
+
+    /// Returns the bytes of a Parquet file of the selection.
+    #[wasm_bindgen(js_name = "exportParquetSelection")]
+    pub fn js_export_parquet_selection(
+        &mut self,
+        selection: String,
+        options: String,
+    ) -> Result<Vec<u8>, JsValue> {
+        let selection = Selection::from_str(&selection).map_err(|e| e.to_string())?;
+        let options: ColumnarExportOptions =
+            serde_json::from_str(&options).map_err(|e| e.to_string())?;
+        let output = self
+            .export_parquet_selection(selection, options)
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+
+    /// Returns the bytes of an Arrow IPC file of the selection.
+    #[wasm_bindgen(js_name = "exportArrowSelection")]
+    pub fn js_export_arrow_selection(
+        &mut self,
+        selection: String,
+        options: String,
+    ) -> Result<Vec<u8>, JsValue> {
+        let selection = Selection::from_str(&selection).map_err(|e| e.to_string())?;
+        let options: ColumnarExportOptions =
+            serde_json::from_str(&options).map_err(|e| e.to_string())?;
+        let output = self
+            .export_arrow_selection(selection, options)
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+
+    /// Returns JSON records (or NDJSON lines) of the selection, keyed by its
+    /// first row.
+    #[wasm_bindgen(js_name = "exportJsonSelection")]
+    pub fn js_export_json_selection(
+        &mut self,
+        selection: String,
+        ndjson: bool,
+    ) -> Result<String, JsValue> {
+        let selection = Selection::from_str(&selection).map_err(|e| e.to_string())?;
+        let output = self
+            .export_json_selection(selection, ndjson)
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+
+    /// Returns the bytes of an XLSX file of the whole grid.
+    #[wasm_bindgen(js_name = "exportExcel")]
+    pub fn js_export_excel(&self) -> Result<Vec<u8>, JsValue> {
+        let output = self.export_excel().map_err(|e| e.to_string())?;
+        Ok(output)
+    }
 }
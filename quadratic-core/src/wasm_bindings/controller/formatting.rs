@@ -97,6 +97,65 @@ impl GridController {
         Ok(())
     }
 
+    /// Sets cells numeric_format to a custom Excel-style format code
+    #[wasm_bindgen(js_name = "setCellCustomFormat")]
+    pub fn js_set_custom_format(
+        &mut self,
+        selection: String,
+        format_code: String,
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let selection = Selection::from_str(&selection).map_err(|_| "Invalid selection")?;
+        FormatCode::parse(&format_code).map_err(|e| e.to_string())?;
+        self.set_numeric_format_selection(
+            selection,
+            NumericFormatKind::Custom,
+            Some(format_code),
+            cursor,
+        )?;
+        Ok(())
+    }
+
+    /// Sets cells numeric_format to a date. The locale is a language tag (eg,
+    /// `en-GB`) that picks the date pattern; it defaults to `en-US`.
+    #[wasm_bindgen(js_name = "setCellDate")]
+    pub fn js_set_date(
+        &mut self,
+        selection: String,
+        locale: Option<String>,
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let selection = Selection::from_str(&selection).map_err(|_| "Invalid selection")?;
+        self.set_numeric_format_selection(selection, NumericFormatKind::Date, locale, cursor)?;
+        Ok(())
+    }
+
+    /// Sets cells numeric_format to a time of day.
+    #[wasm_bindgen(js_name = "setCellTime")]
+    pub fn js_set_time(
+        &mut self,
+        selection: String,
+        locale: Option<String>,
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let selection = Selection::from_str(&selection).map_err(|_| "Invalid selection")?;
+        self.set_numeric_format_selection(selection, NumericFormatKind::Time, locale, cursor)?;
+        Ok(())
+    }
+
+    /// Sets cells numeric_format to a date and time.
+    #[wasm_bindgen(js_name = "setCellDateTime")]
+    pub fn js_set_date_time(
+        &mut self,
+        selection: String,
+        locale: Option<String>,
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let selection = Selection::from_str(&selection).map_err(|_| "Invalid selection")?;
+        self.set_numeric_format_selection(selection, NumericFormatKind::DateTime, locale, cursor)?;
+        Ok(())
+    }
+
     /// Sets cells numeric_commas
     #[wasm_bindgen(js_name = "setCellCommas")]
     pub fn js_set_commas(
@@ -2,13 +2,19 @@ use std::str::FromStr;
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
 use crate::{
-    controller::GridController,
+    controller::{
+        operations::import::{CsvImportOptions, ImportJob, ImportLimits},
+        GridController,
+    },
     grid::{Grid, SheetId},
     Pos,
 };
 
 #[wasm_bindgen]
 impl GridController {
+    /// Starts importing a CSV file. `options` is a JSON [`CsvImportOptions`];
+    /// any missing fields use their defaults. The import's batches are
+    /// applied by calling `continueImport`.
     #[wasm_bindgen(js_name = "importCsv")]
     pub fn js_import_csv(
         &mut self,
@@ -16,26 +22,61 @@ impl GridController {
         file: &[u8],
         file_name: &str,
         insert_at: &str,
+        options: Option<String>,
         cursor: Option<String>,
-    ) -> Result<(), JsValue> {
+    ) -> Result<ImportJob, JsValue> {
         let insert_at = serde_json::from_str::<Pos>(insert_at).map_err(|e| e.to_string())?;
         let sheet_id = SheetId::from_str(sheet_id).map_err(|e| e.to_string())?;
-        self.import_csv(sheet_id, file, file_name, insert_at, cursor)
-            .map_err(|e| e.to_string())?;
+        let options = match options {
+            Some(options) => {
+                serde_json::from_str::<CsvImportOptions>(&options).map_err(|e| e.to_string())?
+            }
+            None => CsvImportOptions::default(),
+        };
+        self.begin_import_csv(sheet_id, file, file_name, insert_at, &options, cursor)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Applies the next batch of an import. Returns whether batches remain,
+    /// so that other messages, like `cancelImport`, can be handled between
+    /// batches. The import is rolled back if it fails or was cancelled.
+    #[wasm_bindgen(js_name = "continueImport")]
+    pub fn js_continue_import(&mut self, import: &mut ImportJob) -> Result<bool, JsValue> {
+        self.continue_import(import)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Cancels the import in progress before its next batch.
+    #[wasm_bindgen(js_name = "cancelImport")]
+    pub fn js_cancel_import(&self) {
+        self.import_cancellation().cancel();
+    }
 
+    /// Sets the limits on the size of imported files. `limits` is a JSON
+    /// [`ImportLimits`]; any missing fields use their defaults.
+    #[wasm_bindgen(js_name = "setImportLimits")]
+    pub fn js_set_import_limits(&mut self, limits: &str) -> Result<(), JsValue> {
+        let limits = serde_json::from_str::<ImportLimits>(limits).map_err(|e| e.to_string())?;
+        self.set_import_limits(limits);
         Ok(())
     }
 }
 
 #[wasm_bindgen]
 impl GridController {
+    /// Creates a grid from a spreadsheet file (xlsx, xlsb, xls, or ods).
     #[wasm_bindgen(js_name = "importExcel")]
     pub fn js_import_excel(file: Vec<u8>, file_name: &str) -> Result<GridController, JsValue> {
         let grid = Grid::new_blank();
         let mut grid_controller = GridController::from_grid(grid, 0);
-        grid_controller
+        let warnings = grid_controller
             .import_excel(file, file_name)
             .map_err(|e| e.to_string())?;
+        if !warnings.is_empty() {
+            if let Ok(warnings) = serde_json::to_string(&warnings) {
+                crate::wasm_bindings::js::jsImportWarnings(file_name, warnings);
+            }
+        }
 
         Ok(grid_controller)
     }
@@ -43,6 +84,28 @@ impl GridController {
 
 #[wasm_bindgen]
 impl GridController {
+    /// Starts importing a JSON or NDJSON file of records. The import's
+    /// batches are applied by calling `continueImport`.
+    #[wasm_bindgen(js_name = "importJson")]
+    pub fn js_import_json(
+        &mut self,
+        sheet_id: &str,
+        file: &[u8],
+        file_name: &str,
+        insert_at: &str,
+        cursor: Option<String>,
+    ) -> Result<ImportJob, JsValue> {
+        let insert_at = serde_json::from_str::<Pos>(insert_at).map_err(|e| e.to_string())?;
+        let sheet_id = SheetId::from_str(sheet_id).map_err(|e| e.to_string())?;
+        self.begin_import_json(sheet_id, file, file_name, insert_at, cursor)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[wasm_bindgen]
+impl GridController {
+    /// Starts importing a Parquet file. The import's batches are applied by
+    /// calling `continueImport`.
     #[wasm_bindgen(js_name = "importParquet")]
     pub fn js_import_parquet(
         &mut self,
@@ -51,12 +114,10 @@ impl GridController {
         file_name: &str,
         insert_at: &str,
         cursor: Option<String>,
-    ) -> Result<(), JsValue> {
+    ) -> Result<ImportJob, JsValue> {
         let insert_at = serde_json::from_str::<Pos>(insert_at).map_err(|e| e.to_string())?;
         let sheet_id = SheetId::from_str(sheet_id).map_err(|e| e.to_string())?;
-        self.import_parquet(sheet_id, file, file_name, insert_at, cursor)
-            .map_err(|e| e.to_string())?;
-
-        Ok(())
+        self.begin_import_parquet(sheet_id, file, file_name, insert_at, cursor)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
     }
 }
@@ -12,6 +12,7 @@ pub mod code;
 pub mod export;
 pub mod formatting;
 pub mod import;
+pub mod protections;
 pub mod render;
 pub mod search;
 pub mod sheet_info;
@@ -23,14 +24,18 @@ pub mod worker;
 
 #[wasm_bindgen]
 impl GridController {
-    /// Imports a [`GridController`] from a JSON string.
+    /// Imports a [`GridController`] from the bytes of a binary or JSON file.
+    /// Only the client's active sheet (given by name, or the first sheet) of
+    /// a binary file is loaded; the rest are loaded when accessed or by
+    /// calling `loadNextSheet`.
     #[wasm_bindgen(js_name = "newFromFile")]
     pub fn js_new_from_file(
-        file: &str,
+        file: &[u8],
         last_sequence_num: u32,
         initialize: bool,
+        active_sheet: Option<String>,
     ) -> Result<GridController, JsValue> {
-        match file::import(file) {
+        match file::import_lazy_by_name(file, active_sheet.as_deref()) {
             Ok(file) => {
                 let grid = GridController::from_grid(file, last_sequence_num as u64);
 
@@ -92,6 +97,14 @@ impl GridController {
         }
     }
 
+    /// Loads the next sheet that was not loaded with the file. Returns whether
+    /// any sheets remain to be loaded.
+    #[wasm_bindgen(js_name = "loadNextSheet")]
+    pub fn js_load_next_sheet(&mut self) -> Result<bool, JsValue> {
+        self.load_next_sheet()
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     #[wasm_bindgen(js_name = "test")]
     pub fn js_test() -> GridController {
         GridController::test()
@@ -0,0 +1,64 @@
+use uuid::Uuid;
+
+use super::*;
+
+#[wasm_bindgen]
+impl GridController {
+    /// Sets the user making changes, for checking protections.
+    #[wasm_bindgen(js_name = "setUserId")]
+    pub fn js_set_user_id(&mut self, user_id: Option<String>) {
+        self.set_user_id(user_id);
+    }
+
+    /// Protects a range of cells (a Rect), or the whole sheet if `rect` is
+    /// undefined.  Returns the id of the protection.
+    #[wasm_bindgen(js_name = "addProtection")]
+    pub fn js_add_protection(
+        &mut self,
+        sheet_id: String,
+        rect: Option<String>,
+        editors: String, /* Vec<String> */
+        cursor: Option<String>,
+    ) -> Result<String, JsValue> {
+        let sheet_id = SheetId::from_str(&sheet_id).map_err(|e| e.to_string())?;
+        let editors: Vec<String> = serde_json::from_str(&editors).map_err(|e| e.to_string())?;
+        let rect = match rect {
+            Some(rect) => Some(serde_json::from_str::<Rect>(&rect).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        let protection_id = self
+            .add_protection(sheet_id, rect, editors, cursor)
+            .map_err(|e| e.to_string())?;
+        Ok(protection_id.to_string())
+    }
+
+    #[wasm_bindgen(js_name = "setProtectionEditors")]
+    pub fn js_set_protection_editors(
+        &mut self,
+        sheet_id: String,
+        protection_id: String,
+        editors: String, /* Vec<String> */
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let sheet_id = SheetId::from_str(&sheet_id).map_err(|e| e.to_string())?;
+        let protection_id = Uuid::parse_str(&protection_id).map_err(|e| e.to_string())?;
+        let editors: Vec<String> = serde_json::from_str(&editors).map_err(|e| e.to_string())?;
+        self.set_protection_editors(sheet_id, protection_id, editors, cursor)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = "removeProtection")]
+    pub fn js_remove_protection(
+        &mut self,
+        sheet_id: String,
+        protection_id: String,
+        cursor: Option<String>,
+    ) -> Result<(), JsValue> {
+        let sheet_id = SheetId::from_str(&sheet_id).map_err(|e| e.to_string())?;
+        let protection_id = Uuid::parse_str(&protection_id).map_err(|e| e.to_string())?;
+        self.remove_protection(sheet_id, protection_id, cursor)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
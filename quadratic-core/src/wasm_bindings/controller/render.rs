@@ -7,8 +7,9 @@ impl GridController {
     ///
     /// Returns a string containing a JSON array of [`JsRenderCell`].
     #[wasm_bindgen(js_name = "getRenderCells")]
-    pub fn get_render_cells(&self, sheet_id: String, rect: String) -> Result<String, JsValue> {
+    pub fn get_render_cells(&mut self, sheet_id: String, rect: String) -> Result<String, JsValue> {
         let rect = serde_json::from_str::<Rect>(&rect).map_err(|e| e.to_string())?;
+        self.load_sheet_from_string_id(&sheet_id);
         let Some(sheet) = self.try_sheet_from_string_id(sheet_id) else {
             return Result::Err("Sheet not found".into());
         };
@@ -18,8 +19,9 @@ impl GridController {
 
     /// Returns whether there is any cells to render in this region
     #[wasm_bindgen(js_name = "hasRenderCells")]
-    pub fn has_render_cells(&self, sheet_id: String, rect: String) -> bool {
+    pub fn has_render_cells(&mut self, sheet_id: String, rect: String) -> bool {
         if let Ok(rect) = serde_json::from_str::<Rect>(&rect) {
+            self.load_sheet_from_string_id(&sheet_id);
             let Some(sheet) = self.try_sheet_from_string_id(sheet_id) else {
                 return false;
             };
@@ -29,3 +31,12 @@ impl GridController {
         }
     }
 }
+
+impl GridController {
+    /// Loads a sheet that was not loaded with the file before it is rendered.
+    fn load_sheet_from_string_id(&mut self, sheet_id: &str) {
+        if let Ok(sheet_id) = SheetId::from_str(sheet_id) {
+            self.load_sheet_on_access(sheet_id);
+        }
+    }
+}
@@ -1,9 +1,20 @@
 use super::*;
+use crate::grid::sheet::search::SearchOptions;
 
 #[wasm_bindgen]
 impl GridController {
-    pub fn search(&self, query: String, options: JsValue) -> Result<JsValue, JsValue> {
-        let options = serde_wasm_bindgen::from_value(options)?;
+    pub fn search(&mut self, query: String, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: SearchOptions = serde_wasm_bindgen::from_value(options)?;
+
+        // sheets that were not loaded with the file are searched too
+        let sheet_ids = match &options.sheet_id {
+            Some(sheet_id) => vec![SheetId::from_str(sheet_id).unwrap_or_default()],
+            None => self.sheet_ids(),
+        };
+        for sheet_id in sheet_ids {
+            self.load_sheet_on_access(sheet_id);
+        }
+
         let search = self.grid().search(&query, options);
         Ok(serde_wasm_bindgen::to_value(&search)?)
     }
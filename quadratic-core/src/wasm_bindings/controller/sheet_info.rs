@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::grid::{GridBounds, Sheet};
+use crate::grid::{js_types::JsProtection, GridBounds, Sheet};
 
 #[derive(Serialize, Deserialize)]
 #[cfg_attr(feature = "js", derive(ts_rs::TS))]
@@ -12,10 +12,13 @@ pub struct SheetInfo {
     pub offsets: String,
     pub bounds: GridBounds,
     pub bounds_without_formatting: GridBounds,
+    pub protections: Vec<JsProtection>,
 }
 
-impl From<&Sheet> for SheetInfo {
-    fn from(sheet: &Sheet) -> Self {
+impl SheetInfo {
+    /// The sheet's info for a user (None if unknown), whose protections are
+    /// locked unless the user can edit them.
+    pub fn new(sheet: &Sheet, user_id: Option<&str>) -> Self {
         let offsets = serde_json::to_string(&sheet.offsets).unwrap_or("".to_string());
         Self {
             sheet_id: sheet.id.to_string(),
@@ -25,10 +28,27 @@ impl From<&Sheet> for SheetInfo {
             offsets,
             bounds: sheet.bounds(false),
             bounds_without_formatting: sheet.bounds(true),
+            protections: sheet
+                .protections
+                .iter()
+                .map(|protection| JsProtection {
+                    id: protection.id.to_string(),
+                    rect: protection.rect,
+                    owner: protection.owner.clone(),
+                    editors: protection.editors.clone(),
+                    can_edit: protection.can_edit(user_id),
+                })
+                .collect(),
         }
     }
 }
 
+impl From<&Sheet> for SheetInfo {
+    fn from(sheet: &Sheet) -> Self {
+        Self::new(sheet, None)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[cfg_attr(feature = "js", derive(ts_rs::TS))]
 pub struct SheetBounds {
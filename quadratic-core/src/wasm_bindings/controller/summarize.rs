@@ -22,6 +22,7 @@ impl GridController {
             dbgjs!("Unable to parse selection in core.summarizeSelection");
             return None;
         };
+        self.load_sheet_on_access(selection.sheet_id);
         let sheet = self.try_sheet(selection.sheet_id)?;
         let summary = sheet.summarize_selection(selection, max_decimals);
         match serde_json::to_string(&summary) {
@@ -1,6 +1,7 @@
 use super::*;
 use crate::controller::{
-    active_transactions::unsaved_transactions::UnsavedTransaction, transaction::TransactionServer,
+    active_transactions::unsaved_transactions::UnsavedTransaction,
+    transaction::{decompress_and_deserialize, TransactionServer},
 };
 use uuid::Uuid;
 
@@ -28,6 +29,35 @@ impl GridController {
         ))?)
     }
 
+    /// Receives a transaction whose operations are in the binary multiplayer
+    /// protocol.
+    #[wasm_bindgen(js_name = "multiplayerTransactionBinary")]
+    pub fn js_multiplayer_transaction_binary(
+        &mut self,
+        transaction_id: String,
+        sequence_num: u32,
+        operations: Vec<u8>,
+    ) -> Result<JsValue, JsValue> {
+        let transaction_id = Uuid::parse_str(&transaction_id)
+            .map_err(|e| JsValue::from_str(&format!("Invalid transaction id: {}", e)))?;
+        let operations = decompress_and_deserialize(&operations)
+            .map_err(|e| JsValue::from_str(&format!("Invalid operations: {}", e)))?;
+        Ok(serde_wasm_bindgen::to_value(&self.received_transaction(
+            transaction_id,
+            sequence_num as u64,
+            operations,
+        ))?)
+    }
+
+    /// Rolls back a transaction that the multiplayer server rejected.
+    #[wasm_bindgen(js_name = "rejectedTransaction")]
+    pub fn js_rejected_transaction(&mut self, transaction_id: String) -> Result<(), JsValue> {
+        let transaction_id = Uuid::parse_str(&transaction_id)
+            .map_err(|e| JsValue::from_str(&format!("Invalid transaction id: {}", e)))?;
+        self.rejected_transaction(transaction_id);
+        Ok(())
+    }
+
     /// Used to set the sequence_num for multiplayer. This should only be called when receiving the sequence_num
     /// directly from the file. Use receiveSequenceNum for all other cases.
     #[wasm_bindgen(js_name = "setMultiplayerSequenceNum")]
@@ -61,6 +91,34 @@ impl GridController {
         }
     }
 
+    #[wasm_bindgen(js_name = "receiveMultiplayerTransactionsBinary")]
+    pub fn js_receive_multiplayer_transactions_binary(
+        &mut self,
+        transactions: Vec<u8>,
+    ) -> Result<JsValue, JsValue> {
+        let transactions = decompress_and_deserialize::<Vec<TransactionServer>>(&transactions)
+            .map_err(|e| {
+                JsValue::from_str(&format!(
+                    "Invalid transactions received in receiveMultiplayerTransactionsBinary: {e}"
+                ))
+            })?;
+        Ok(serde_wasm_bindgen::to_value(
+            &self.received_transactions(&transactions[..]),
+        )?)
+    }
+
+    /// Replaces the grid with a checkpoint of the file at `sequence_num`,
+    /// which multiplayer sends when the client cannot catch up with
+    /// transactions alone.  Only the checkpoint's first sheet is loaded; the
+    /// rest are loaded by calling `loadNextSheet`.
+    #[wasm_bindgen(js_name = "loadCheckpoint")]
+    pub fn js_load_checkpoint(&mut self, file: &[u8], sequence_num: u32) -> Result<(), JsValue> {
+        let grid = file::import_lazy(file, None)
+            .map_err(|e| JsValue::from_str(&format!("Invalid checkpoint: {e}")))?;
+        self.load_checkpoint(grid, sequence_num as u64);
+        Ok(())
+    }
+
     #[wasm_bindgen(js_name = "applyOfflineUnsavedTransaction")]
     pub fn js_apply_offline_unsaved_transaction(
         &mut self,
@@ -84,6 +84,7 @@ extern "C" {
         w: u32,
         h: u32,
     );
+    pub fn jsImportWarnings(file_name: &str, warnings: String /* Vec<ImportWarning> */);
     pub fn jsTransactionStart(transaction_id: String, name: String);
     pub fn addUnsentTransaction(transaction_id: String, transaction: String, operations: u32);
     pub fn jsSendTransaction(transaction_id: String, transaction: String);
@@ -92,6 +93,8 @@ extern "C" {
 
     pub fn jsUndoRedo(undo: bool, redo: bool);
 
+    pub fn jsProtectionError(error: String);
+
     pub fn jsConnection(
         transactionId: String,
         x: i32,
@@ -438,6 +441,15 @@ pub fn jsImportProgress(file_name: &str, current: u32, total: u32, x: i64, y: i6
     ));
 }
 
+#[cfg(test)]
+#[allow(non_snake_case)]
+pub fn jsImportWarnings(file_name: &str, warnings: String) {
+    TEST_ARRAY.lock().unwrap().push(TestFunction::new(
+        "jsImportWarnings",
+        format!("{},{}", file_name, warnings),
+    ));
+}
+
 #[cfg(test)]
 #[allow(non_snake_case)]
 pub fn jsTransactionStart(transaction_id: String, name: String) {
@@ -485,6 +497,15 @@ pub fn jsUndoRedo(undo: bool, redo: bool) {
     ));
 }
 
+#[cfg(test)]
+#[allow(non_snake_case)]
+pub fn jsProtectionError(error: String) {
+    TEST_ARRAY
+        .lock()
+        .unwrap()
+        .push(TestFunction::new("jsProtectionError", error));
+}
+
 #[cfg(test)]
 #[allow(non_snake_case)]
 pub fn jsConnection(
@@ -0,0 +1,279 @@
+use anyhow::Result;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use super::reader::attribute;
+use crate::color::Rgba;
+
+/// The legacy indexed color palette (ECMA-376 Part 1, 18.8.27). Indexes 0-7
+/// repeat 8-15.
+const INDEXED_COLORS: [u32; 56] = [
+    0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00, 0x0000FF, 0xFFFF00, 0xFF00FF, 0x00FFFF, 0x800000,
+    0x008000, 0x000080, 0x808000, 0x800080, 0x008080, 0xC0C0C0, 0x808080, 0x9999FF, 0x993366,
+    0xFFFFCC, 0xCCFFFF, 0x660066, 0xFF8080, 0x0066CC, 0xCCCCFF, 0x000080, 0xFF00FF, 0xFFFF00,
+    0x00FFFF, 0x800080, 0x800000, 0x008080, 0x0000FF, 0x00CCFF, 0xCCFFFF, 0xCCFFCC, 0xFFFF99,
+    0x99CCFF, 0xFF99CC, 0xCC99FF, 0xFFCC99, 0x3366FF, 0x33CCCC, 0x99CC00, 0xFFCC00, 0xFF9900,
+    0xFF6600, 0x666699, 0x969696, 0x003366, 0x339966, 0x003300, 0x333300, 0x993300, 0x993366,
+    0x333399, 0x333333,
+];
+
+/// Indexed colors used for the system foreground and background.
+const SYSTEM_FOREGROUND: u32 = 64;
+const SYSTEM_BACKGROUND: u32 = 65;
+
+/// A color as written in styles.xml or a worksheet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XlsxColor {
+    Rgb(Rgba),
+
+    /// Index into the theme's color scheme, lightened or darkened by `tint`
+    /// (-1.0 to 1.0).
+    Theme {
+        index: u32,
+        tint: f64,
+    },
+
+    /// Index into the legacy palette.
+    Indexed(u32),
+
+    /// The application's default color (usually black text).
+    Auto,
+}
+
+impl XlsxColor {
+    /// Parses a color element (`<color>`, `<fgColor>`, `<tabColor>`, etc).
+    pub fn parse(e: &BytesStart<'_>) -> Option<Self> {
+        let tint = attribute(e, "tint")
+            .and_then(|tint| tint.parse().ok())
+            .unwrap_or(0.0);
+        if let Some(rgb) = attribute(e, "rgb") {
+            // ARGB, though some files omit the alpha
+            let rgb = u32::from_str_radix(&rgb[rgb.len().saturating_sub(6)..], 16).ok()?;
+            Some(XlsxColor::Rgb(rgba(rgb)))
+        } else if let Some(index) = attribute(e, "theme") {
+            Some(XlsxColor::Theme {
+                index: index.parse().ok()?,
+                tint,
+            })
+        } else if let Some(index) = attribute(e, "indexed") {
+            Some(XlsxColor::Indexed(index.parse().ok()?))
+        } else if attribute(e, "auto").is_some_and(|auto| auto == "1" || auto == "true") {
+            Some(XlsxColor::Auto)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the color, or `None` if it depends on the application (auto
+    /// and system colors) or is missing from the theme.
+    pub fn resolve(&self, theme_colors: &[Rgba]) -> Option<Rgba> {
+        match *self {
+            XlsxColor::Rgb(rgba) => Some(rgba),
+            XlsxColor::Theme { index, tint } => {
+                // the first two pairs of dark/light colors are swapped
+                let index = match index {
+                    0 => 1,
+                    1 => 0,
+                    2 => 3,
+                    3 => 2,
+                    index => index,
+                };
+                let color = theme_colors.get(index as usize)?;
+                Some(apply_tint(*color, tint))
+            }
+            XlsxColor::Indexed(index) if index < 8 => Some(rgba(INDEXED_COLORS[index as usize])),
+            XlsxColor::Indexed(index) => {
+                INDEXED_COLORS.get(index as usize - 8).map(|&rgb| rgba(rgb))
+            }
+            XlsxColor::Auto => None,
+        }
+    }
+
+    /// Returns true for colors that mean "the default" rather than a specific
+    /// color.
+    pub fn is_default(&self) -> bool {
+        matches!(
+            self,
+            XlsxColor::Auto | XlsxColor::Indexed(SYSTEM_FOREGROUND | SYSTEM_BACKGROUND)
+        )
+    }
+}
+
+/// Returns a color in the CSS format used for Quadratic text, fill, and sheet
+/// colors.
+pub fn css_color(color: Rgba) -> String {
+    format!("rgb({}, {}, {})", color.red, color.green, color.blue)
+}
+
+fn rgba(rgb: u32) -> Rgba {
+    Rgba {
+        red: (rgb >> 16) as u8,
+        green: (rgb >> 8) as u8,
+        blue: rgb as u8,
+        alpha: 255,
+    }
+}
+
+/// Lightens (positive tint) or darkens (negative tint) a color by changing
+/// its luminance, as Excel does.
+fn apply_tint(color: Rgba, tint: f64) -> Rgba {
+    if tint == 0.0 {
+        return color;
+    }
+    let (h, s, l) = rgb_to_hsl(color);
+    let l = if tint < 0.0 {
+        l * (1.0 + tint)
+    } else {
+        l * (1.0 - tint) + tint
+    };
+    hsl_to_rgb(h, s, l.clamp(0.0, 1.0))
+}
+
+fn rgb_to_hsl(color: Rgba) -> (f64, f64, f64) {
+    let r = color.red as f64 / 255.0;
+    let g = color.green as f64 / 255.0;
+    let b = color.blue as f64 / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    if max == min {
+        return (0.0, 0.0, l);
+    }
+
+    let d = max - min;
+    let s = if l > 0.5 {
+        d / (2.0 - max - min)
+    } else {
+        d / (max + min)
+    };
+    let h = if max == r {
+        (g - b) / d + if g < b { 6.0 } else { 0.0 }
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h / 6.0, s, l)
+}
+
+fn hsl_to_rgb(h: f64, s: f64, l: f64) -> Rgba {
+    let channel = |v: f64| (v * 255.0).round() as u8;
+    if s == 0.0 {
+        let v = channel(l);
+        return Rgba {
+            red: v,
+            green: v,
+            blue: v,
+            alpha: 255,
+        };
+    }
+
+    let q = if l < 0.5 {
+        l * (1.0 + s)
+    } else {
+        l + s - l * s
+    };
+    let p = 2.0 * l - q;
+    let hue = |t: f64| {
+        let t = t.rem_euclid(1.0);
+        if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        }
+    };
+    Rgba {
+        red: channel(hue(h + 1.0 / 3.0)),
+        green: channel(hue(h)),
+        blue: channel(hue(h - 1.0 / 3.0)),
+        alpha: 255,
+    }
+}
+
+/// Returns the colors of a theme's color scheme (xl/theme/theme1.xml), in
+/// order: dk1, lt1, dk2, lt2, accent1-6, hlink, folHlink.
+pub fn parse_theme_colors(xml: &str) -> Result<Vec<Rgba>> {
+    let mut reader = Reader::from_str(xml);
+    let mut colors = vec![];
+    let mut in_scheme = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == b"clrScheme" => in_scheme = true,
+            Event::End(e) if e.local_name().as_ref() == b"clrScheme" => break,
+            Event::Start(e) | Event::Empty(e) if in_scheme => {
+                let rgb = match e.local_name().as_ref() {
+                    b"srgbClr" => attribute(&e, "val"),
+                    b"sysClr" => attribute(&e, "lastClr"),
+                    _ => continue,
+                };
+                if let Some(rgb) = rgb.and_then(|rgb| u32::from_str_radix(&rgb, 16).ok()) {
+                    colors.push(rgba(rgb));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_colors() {
+        let theme = vec![
+            rgba(0x000000),
+            rgba(0xFFFFFF),
+            rgba(0x44546A),
+            rgba(0xE7E6E6),
+        ];
+
+        assert_eq!(
+            XlsxColor::Rgb(rgba(0x112233)).resolve(&theme),
+            Some(rgba(0x112233))
+        );
+        assert_eq!(
+            XlsxColor::Theme {
+                index: 1,
+                tint: 0.0
+            }
+            .resolve(&theme),
+            Some(rgba(0x000000))
+        );
+        assert_eq!(
+            XlsxColor::Theme {
+                index: 0,
+                tint: -0.5
+            }
+            .resolve(&theme),
+            Some(rgba(0x808080))
+        );
+        assert_eq!(XlsxColor::Indexed(10).resolve(&theme), Some(rgba(0xFF0000)));
+        assert_eq!(XlsxColor::Indexed(2).resolve(&theme), Some(rgba(0xFF0000)));
+        assert_eq!(XlsxColor::Indexed(SYSTEM_FOREGROUND).resolve(&theme), None);
+        assert_eq!(XlsxColor::Auto.resolve(&theme), None);
+        assert_eq!(css_color(rgba(0x0080FF)), "rgb(0, 128, 255)");
+    }
+
+    #[test]
+    fn parses_theme_colors() {
+        let xml = r#"<a:theme xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main">
+            <a:themeElements><a:clrScheme name="Office">
+                <a:dk1><a:sysClr val="windowText" lastClr="000000"/></a:dk1>
+                <a:lt1><a:sysClr val="window" lastClr="FFFFFF"/></a:lt1>
+                <a:dk2><a:srgbClr val="44546A"/></a:dk2>
+            </a:clrScheme></a:themeElements></a:theme>"#;
+        assert_eq!(
+            parse_theme_colors(xml).unwrap(),
+            vec![rgba(0x000000), rgba(0xFFFFFF), rgba(0x44546A)]
+        );
+    }
+}
@@ -0,0 +1,1176 @@
+//! Translation of Excel formulas into Quadratic formulas.
+//!
+//! Excel formulas are parsed into a Quadratic [`AstNode`], which is then
+//! written back out as Quadratic formula source. This resolves the syntax
+//! that Quadratic does not share with Excel (structured references, `_xlfn.`
+//! prefixes, `""` string escapes, quoted sheet names, implicit intersection)
+//! and reports what cannot be translated.
+//!
+//! Cell references are kept in A1 notation. The Excel importer places Excel
+//! row `n` at Quadratic row `n`, so A1 references mean the same thing in
+//! both.
+
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::reader::XlsxTable;
+use crate::{
+    formulas::{
+        ast::{AstNode, AstNodeContents},
+        functions::{excel::is_valid_excel_function, lookup_function},
+        CellRef, CellRefCoord,
+    },
+    Pos, Span, Spanned,
+};
+
+/// Excel functions that Quadratic implements under another name.
+const FUNCTION_ALIASES: &[(&str, &str)] = &[("CONCATENATE", "CONCAT")];
+
+/// Prefixes that Excel adds to functions introduced after the file format.
+const FUNCTION_PREFIXES: &[&str] = &["_xlfn.", "_xlws."];
+
+/// Prefix of user-defined (VBA and add-in) functions.
+const USER_DEFINED_FUNCTION_PREFIX: &str = "_xludf.";
+
+/// Binary operators by precedence, from lowest to highest. Excel evaluates
+/// all of them left to right, including `^`.
+const BINARY_OPERATORS: &[&[&str]] = &[
+    &["<=", ">=", "<>", "=", "<", ">"],
+    &["&"],
+    &["+", "-"],
+    &["*", "/"],
+    &["^"],
+];
+
+lazy_static! {
+    static ref NUMBER_REGEX: Regex = Regex::new(r"^(\d+(\.\d*)?|\.\d+)([eE][+-]?\d+)?").unwrap();
+    static ref CELL_REF_REGEX: Regex = Regex::new(r"^\$?[A-Za-z]{1,3}\$?[1-9]\d*$").unwrap();
+    static ref COLUMN_RANGE_REGEX: Regex =
+        Regex::new(r"^\$?[A-Za-z]{1,3}:\$?[A-Za-z]{1,3}($|[^A-Za-z0-9_])").unwrap();
+    static ref ROW_RANGE_REGEX: Regex = Regex::new(r"^\$?\d+:\$?\d+").unwrap();
+    static ref EXTERNAL_REF_REGEX: Regex = Regex::new(r"^\[[^\[\]]*\][A-Za-z0-9_\.]*!").unwrap();
+    static ref UNQUOTED_SHEET_NAME_REGEX: Regex =
+        Regex::new(r"^[A-Za-z_][A-Za-z0-9_\.]*$").unwrap();
+}
+
+/// Something in an Excel formula that Quadratic does not support.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FormulaIssue {
+    /// An Excel function that Quadratic does not implement. The formula is
+    /// translated but returns an error when run.
+    UnsupportedFunction(String),
+
+    /// A function that is not part of Excel, such as a VBA or add-in
+    /// function. The formula is translated but returns an error when run.
+    UnknownFunction(String),
+
+    /// Syntax with no Quadratic equivalent, such as a full-column reference.
+    /// The formula is not translated.
+    UnsupportedSyntax(&'static str),
+
+    /// The formula could not be parsed. The formula is not translated.
+    InvalidFormula,
+}
+
+impl fmt::Display for FormulaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormulaIssue::UnsupportedFunction(name) => write!(f, "unsupported function {name}"),
+            FormulaIssue::UnknownFunction(name) => write!(f, "unknown function {name}"),
+            FormulaIssue::UnsupportedSyntax(syntax) => write!(f, "unsupported {syntax}"),
+            FormulaIssue::InvalidFormula => write!(f, "invalid formula"),
+        }
+    }
+}
+
+/// Where an Excel formula is being translated.
+#[derive(Debug, Clone, Copy)]
+pub struct FormulaContext<'a> {
+    pub sheet_name: &'a str,
+
+    /// Position of the formula cell in Quadratic.
+    pub pos: Pos,
+
+    /// The workbook's tables, for structured references.
+    pub tables: &'a [XlsxTable],
+}
+
+/// A translated formula, with the functions that will fail when it is run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslatedFormula {
+    pub code: String,
+    pub issues: Vec<FormulaIssue>,
+}
+
+/// Translates an Excel formula (with or without the leading `=`) into a
+/// Quadratic formula. Returns an error if the formula uses syntax that cannot
+/// be translated.
+pub fn translate_excel_formula(
+    formula: &str,
+    context: &FormulaContext<'_>,
+) -> Result<TranslatedFormula, FormulaIssue> {
+    let ast = parse_excel_formula(formula, context)?;
+    let mut issues = vec![];
+    check_functions(&ast, &mut issues);
+    Ok(TranslatedFormula {
+        code: to_source(&ast, context.pos),
+        issues,
+    })
+}
+
+/// Parses an Excel formula into a Quadratic AST. Spans refer to `formula`.
+pub fn parse_excel_formula(
+    formula: &str,
+    context: &FormulaContext<'_>,
+) -> Result<AstNode, FormulaIssue> {
+    let mut p = ExcelParser {
+        source: formula,
+        offset: 0,
+        context,
+    };
+    p.eat("=");
+    let ast = p.expression()?;
+    p.skip_whitespace();
+    if p.offset < p.source.len() {
+        return Err(FormulaIssue::InvalidFormula);
+    }
+    Ok(ast)
+}
+
+/// Adds an issue for each function that Quadratic does not implement.
+fn check_functions(node: &AstNode, issues: &mut Vec<FormulaIssue>) {
+    match &node.inner {
+        AstNodeContents::FunctionCall { func, args } => {
+            let name = &func.inner;
+            let is_function = name.starts_with(|c: char| c.is_alphabetic() || c == '_');
+            if is_function && lookup_function(name).is_none() {
+                let issue = if is_valid_excel_function(name) {
+                    FormulaIssue::UnsupportedFunction(name.clone())
+                } else {
+                    FormulaIssue::UnknownFunction(name.clone())
+                };
+                if !issues.contains(&issue) {
+                    issues.push(issue);
+                }
+            }
+            args.iter().for_each(|arg| check_functions(arg, issues));
+        }
+        AstNodeContents::Paren(inner) => check_functions(inner, issues),
+        AstNodeContents::Array(rows) => rows
+            .iter()
+            .flatten()
+            .for_each(|arg| check_functions(arg, issues)),
+        _ => {}
+    }
+}
+
+type ParseResult<T = AstNode> = Result<T, FormulaIssue>;
+
+/// Recursive descent parser for Excel formulas (en-US syntax, as stored in
+/// files).
+struct ExcelParser<'a> {
+    source: &'a str,
+    offset: usize,
+    context: &'a FormulaContext<'a>,
+}
+
+impl<'a> ExcelParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Skips whitespace, returning whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let trimmed = rest.trim_start();
+        self.offset += rest.len() - trimmed.len();
+        rest.len() != trimmed.len()
+    }
+
+    /// Consumes `s` (after any whitespace) if it is next.
+    fn eat(&mut self, s: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(s) {
+            self.offset += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, s: &str) -> ParseResult<()> {
+        match self.eat(s) {
+            true => Ok(()),
+            false => Err(FormulaIssue::InvalidFormula),
+        }
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        Span {
+            start: start as u32,
+            end: self.offset as u32,
+        }
+    }
+
+    fn node(&self, start: usize, inner: AstNodeContents) -> AstNode {
+        Spanned {
+            span: self.span_from(start),
+            inner,
+        }
+    }
+
+    fn operator(op: Spanned<String>, args: Vec<AstNode>) -> AstNode {
+        let span = args
+            .iter()
+            .fold(op.span, |span, arg| Span::merge(span, arg.span));
+        Spanned {
+            span,
+            inner: AstNodeContents::FunctionCall { func: op, args },
+        }
+    }
+
+    fn expression(&mut self) -> ParseResult {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> ParseResult {
+        let Some(operators) = BINARY_OPERATORS.get(level) else {
+            return self.percent();
+        };
+        let mut lhs = self.binary(level + 1)?;
+        loop {
+            self.skip_whitespace();
+            let start = self.offset;
+            let Some(op) = operators.iter().find(|op| self.rest().starts_with(**op)) else {
+                return Ok(lhs);
+            };
+            self.offset += op.len();
+            let op = Spanned {
+                span: self.span_from(start),
+                inner: op.to_string(),
+            };
+            let rhs = self.binary(level + 1)?;
+            lhs = Self::operator(op, vec![lhs, rhs]);
+        }
+    }
+
+    fn percent(&mut self) -> ParseResult {
+        let mut value = self.unary()?;
+        while self.eat("%") {
+            let op = Spanned {
+                span: self.span_from(self.offset - 1),
+                inner: "%".to_string(),
+            };
+            value = Self::operator(op, vec![value]);
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> ParseResult {
+        self.skip_whitespace();
+        let start = self.offset;
+        match self.peek() {
+            Some(c @ ('+' | '-')) => {
+                self.offset += 1;
+                let op = Spanned {
+                    span: self.span_from(start),
+                    inner: c.to_string(),
+                };
+                let value = self.unary()?;
+                Ok(Self::operator(op, vec![value]))
+            }
+            // implicit intersection, which Quadratic does not need
+            Some('@') => {
+                self.offset += 1;
+                self.unary()
+            }
+            _ => self.intersection(),
+        }
+    }
+
+    /// Parses a range, and rejects the intersection operator (a space between
+    /// two references).
+    fn intersection(&mut self) -> ParseResult {
+        let range = self.range()?;
+        let start = self.offset;
+        if self.skip_whitespace()
+            && is_reference(&range)
+            && self
+                .peek()
+                .is_some_and(|c| c.is_alphabetic() || matches!(c, '$' | '\'' | '(' | '_'))
+        {
+            return Err(FormulaIssue::UnsupportedSyntax("intersection operator"));
+        }
+        self.offset = start;
+        Ok(range)
+    }
+
+    fn range(&mut self) -> ParseResult {
+        let mut range = self.postfix()?;
+        while self.eat(":") {
+            let start = Span::empty(self.offset as u32 - 1);
+            let end = self.postfix()?;
+            let (AstNodeContents::CellRef(first), AstNodeContents::CellRef(last)) =
+                (&range.inner, &end.inner)
+            else {
+                return Err(FormulaIssue::UnsupportedSyntax(
+                    "range operator on expressions",
+                ));
+            };
+            if last.sheet.is_some() && last.sheet != first.sheet {
+                return Err(FormulaIssue::UnsupportedSyntax("range across sheets"));
+            }
+            let op = Spanned {
+                span: start,
+                inner: ":".to_string(),
+            };
+            range = Self::operator(op, vec![range, end]);
+        }
+        Ok(range)
+    }
+
+    fn postfix(&mut self) -> ParseResult {
+        let value = self.primary()?;
+        if self.rest().starts_with('#') {
+            return Err(FormulaIssue::UnsupportedSyntax("spilled range reference"));
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> ParseResult {
+        self.skip_whitespace();
+        let start = self.offset;
+        let rest = self.rest();
+        match self.peek().ok_or(FormulaIssue::InvalidFormula)? {
+            '"' => self.string(),
+            '{' => self.array(),
+            '(' => {
+                self.offset += 1;
+                let inner = self.expression()?;
+                if self.eat(",") {
+                    return Err(FormulaIssue::UnsupportedSyntax("union operator"));
+                }
+                self.expect(")")?;
+                Ok(self.node(start, AstNodeContents::Paren(Box::new(inner))))
+            }
+            '#' => Err(FormulaIssue::UnsupportedSyntax("error value")),
+            '[' if EXTERNAL_REF_REGEX.is_match(rest) => {
+                Err(FormulaIssue::UnsupportedSyntax("external reference"))
+            }
+            '[' => self.structured_reference(start, None),
+            '\'' => {
+                let sheet = self.quoted_sheet_name()?;
+                self.sheet_reference(start, sheet)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                if ROW_RANGE_REGEX.is_match(rest) {
+                    return Err(FormulaIssue::UnsupportedSyntax("full row reference"));
+                }
+                let number = NUMBER_REGEX
+                    .find(rest)
+                    .ok_or(FormulaIssue::InvalidFormula)?;
+                self.offset += number.end();
+                let n = number
+                    .as_str()
+                    .parse()
+                    .map_err(|_| FormulaIssue::InvalidFormula)?;
+                Ok(self.node(start, AstNodeContents::Number(n)))
+            }
+            c if c.is_alphabetic() || matches!(c, '_' | '\\' | '$') => self.name(start),
+            _ => Err(FormulaIssue::InvalidFormula),
+        }
+    }
+
+    /// Parses anything that starts with a name: function calls, cell
+    /// references, sheet references, structured references, and booleans.
+    fn name(&mut self, start: usize) -> ParseResult {
+        let rest = self.rest();
+        if COLUMN_RANGE_REGEX.is_match(rest) {
+            return Err(FormulaIssue::UnsupportedSyntax("full column reference"));
+        }
+        let name = self.scan_name();
+        match self.peek() {
+            Some('(') => {
+                self.offset += 1;
+                self.function_call(start, name)
+            }
+            Some('!') => {
+                self.offset += 1;
+                self.sheet_reference(start, name.to_string())
+            }
+            Some(':') if self.is_3d_reference() => {
+                Err(FormulaIssue::UnsupportedSyntax("3D reference"))
+            }
+            Some('[') => self.structured_reference(start, Some(name)),
+            _ if name.eq_ignore_ascii_case("true") => {
+                Ok(self.node(start, AstNodeContents::Bool(true)))
+            }
+            _ if name.eq_ignore_ascii_case("false") => {
+                Ok(self.node(start, AstNodeContents::Bool(false)))
+            }
+            _ if CELL_REF_REGEX.is_match(name) => self.cell_ref(start, None, name),
+            // a table name on its own refers to the table's data
+            _ if self.table(Some(name)).is_some() => {
+                self.resolve_structured_reference(start, Some(name), &[], None)
+            }
+            _ => Err(FormulaIssue::UnsupportedSyntax("defined name")),
+        }
+    }
+
+    fn scan_name(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '\\' | '$' | '?')))
+            .unwrap_or(rest.len());
+        self.offset += len;
+        &rest[..len]
+    }
+
+    /// Returns whether the cursor is at the `:` of `Sheet1:Sheet3!A1`.
+    fn is_3d_reference(&self) -> bool {
+        let rest = &self.rest()[1..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.')))
+            .unwrap_or(rest.len());
+        len > 0 && rest[len..].starts_with('!')
+    }
+
+    /// Parses a sheet name in single quotes, where `''` is an escaped quote.
+    fn quoted_sheet_name(&mut self) -> ParseResult<String> {
+        let mut name = String::new();
+        let mut chars = self.rest().char_indices().skip(1);
+        while let Some((i, c)) = chars.next() {
+            if c != '\'' {
+                name.push(c);
+            } else if self.rest()[i + 1..].starts_with('\'') {
+                name.push('\'');
+                chars.next();
+            } else {
+                self.offset += i + 1;
+                if name.starts_with('[') {
+                    return Err(FormulaIssue::UnsupportedSyntax("external reference"));
+                }
+                if self.rest().starts_with(':') {
+                    return Err(FormulaIssue::UnsupportedSyntax("3D reference"));
+                }
+                self.expect("!")?;
+                return Ok(name);
+            }
+        }
+        Err(FormulaIssue::InvalidFormula)
+    }
+
+    /// Parses the reference after `Sheet!`.
+    fn sheet_reference(&mut self, start: usize, sheet: String) -> ParseResult {
+        let rest = self.rest();
+        if rest.starts_with('#') {
+            return Err(FormulaIssue::UnsupportedSyntax("error value"));
+        }
+        if COLUMN_RANGE_REGEX.is_match(rest) {
+            return Err(FormulaIssue::UnsupportedSyntax("full column reference"));
+        }
+        if ROW_RANGE_REGEX.is_match(rest) {
+            return Err(FormulaIssue::UnsupportedSyntax("full row reference"));
+        }
+        let name = self.scan_name();
+        if CELL_REF_REGEX.is_match(name) {
+            self.cell_ref(start, Some(sheet), name)
+        } else {
+            Err(FormulaIssue::UnsupportedSyntax("defined name"))
+        }
+    }
+
+    fn cell_ref(&mut self, start: usize, sheet: Option<String>, name: &str) -> ParseResult {
+        let mut cell_ref = CellRef::parse_a1(&name.to_ascii_uppercase(), self.context.pos)
+            .ok_or(FormulaIssue::InvalidFormula)?;
+        cell_ref.sheet = sheet;
+        Ok(self.node(start, AstNodeContents::CellRef(cell_ref)))
+    }
+
+    fn function_call(&mut self, start: usize, name: &str) -> ParseResult {
+        let func_span = Span {
+            start: start as u32,
+            end: (start + name.len()) as u32,
+        };
+
+        let mut args = vec![];
+        if !self.eat(")") {
+            loop {
+                self.skip_whitespace();
+                let arg_start = self.offset;
+                let arg = if matches!(self.peek(), Some(',' | ')')) {
+                    self.node(arg_start, AstNodeContents::Empty)
+                } else {
+                    self.expression()?
+                };
+                args.push(arg);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        let mut name = name;
+        while let Some(prefix) = FUNCTION_PREFIXES.iter().find(|prefix| {
+            name.len() > prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix)
+        }) {
+            name = &name[prefix.len()..];
+        }
+        let name = name.to_ascii_uppercase();
+        let name = FUNCTION_ALIASES
+            .iter()
+            .find(|(excel, _)| *excel == name)
+            .map_or(name.clone(), |(_, quadratic)| quadratic.to_string());
+
+        match name.as_str() {
+            // implicit intersection, which Quadratic does not need
+            "SINGLE" if args.len() == 1 => return Ok(args.remove(0)),
+            "ANCHORARRAY" => {
+                return Err(FormulaIssue::UnsupportedSyntax("spilled range reference"))
+            }
+            _ => {}
+        }
+
+        // user-defined functions keep their prefix so that they are reported
+        // as unknown rather than matched to a built-in function
+        let prefix_len = USER_DEFINED_FUNCTION_PREFIX.len();
+        let is_user_defined = name
+            .get(..prefix_len)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(USER_DEFINED_FUNCTION_PREFIX));
+        let name = match is_user_defined {
+            true => format!("{USER_DEFINED_FUNCTION_PREFIX}{}", &name[prefix_len..]),
+            false => name,
+        };
+
+        Ok(self.node(
+            start,
+            AstNodeContents::FunctionCall {
+                func: Spanned {
+                    span: func_span,
+                    inner: name,
+                },
+                args,
+            },
+        ))
+    }
+
+    /// Parses a string, where `""` is an escaped quote.
+    fn string(&mut self) -> ParseResult {
+        let start = self.offset;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices().skip(1);
+        while let Some((i, c)) = chars.next() {
+            if c != '"' {
+                value.push(c);
+            } else if self.rest()[i + 1..].starts_with('"') {
+                value.push('"');
+                chars.next();
+            } else {
+                self.offset += i + 1;
+                return Ok(self.node(start, AstNodeContents::String(value)));
+            }
+        }
+        Err(FormulaIssue::InvalidFormula)
+    }
+
+    /// Parses an array constant, eg, `{1,2;3,4}`.
+    fn array(&mut self) -> ParseResult {
+        let start = self.offset;
+        self.offset += 1;
+        let mut rows = vec![vec![]];
+        loop {
+            let value = self.expression()?;
+            rows.last_mut().unwrap().push(value);
+            if self.eat(",") {
+                continue;
+            } else if self.eat(";") {
+                rows.push(vec![]);
+            } else {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(self.node(start, AstNodeContents::Array(rows)))
+    }
+
+    fn table(&self, name: Option<&str>) -> Option<&'a XlsxTable> {
+        let tables = self.context.tables;
+        match name {
+            Some(name) => tables
+                .iter()
+                .find(|table| table.name.eq_ignore_ascii_case(name)),
+            // a reference without a table name is inside its table
+            None => {
+                let pos = self.context.pos;
+                tables.iter().find(|table| {
+                    table.sheet_name == self.context.sheet_name
+                        && (table.first.x..=table.last.x).contains(&pos.x)
+                        && (table.first.y + 1..=table.last.y + 1).contains(&pos.y)
+                })
+            }
+        }
+    }
+
+    /// Parses the brackets of a structured reference, eg, `Sales[Amount]`,
+    /// `Sales[[#Totals],[Amount]]`, `Sales[@Amount]`, or `[@[Unit Price]]`.
+    fn structured_reference(&mut self, start: usize, table: Option<&str>) -> ParseResult {
+        self.offset += 1;
+        let mut items = vec![];
+        let mut columns = None;
+
+        // `[@Column]` and `[@[Column]]`
+        if self.eat("@") {
+            items.push(TableItem::ThisRow);
+            if !self.rest().starts_with(']') {
+                let column = match self.rest().starts_with('[') {
+                    true => self.bracketed_name()?,
+                    false => self.column_name(),
+                };
+                columns = Some((column, None));
+            }
+            self.expect("]")?;
+        } else if self.rest().starts_with('[') {
+            loop {
+                self.skip_whitespace();
+                let name = self.bracketed_name()?;
+                match TableItem::parse(&name) {
+                    Some(item) => items.push(item),
+                    None if self.eat(":") => {
+                        self.skip_whitespace();
+                        columns = Some((name, Some(self.bracketed_name()?)));
+                    }
+                    None => columns = Some((name, None)),
+                }
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect("]")?;
+        } else {
+            let name = self.column_name();
+            self.expect("]")?;
+            match TableItem::parse(&name) {
+                Some(item) => items.push(item),
+                None if name.is_empty() => {}
+                None => columns = Some((name, None)),
+            }
+        }
+
+        self.resolve_structured_reference(start, table, &items, columns)
+    }
+
+    /// Parses a name in brackets, eg, `[Unit Price]` or `[#Totals]`.
+    fn bracketed_name(&mut self) -> ParseResult<String> {
+        self.expect("[")?;
+        let name = self.column_name();
+        self.expect("]")?;
+        Ok(name)
+    }
+
+    /// Reads a column name up to a closing bracket. Special characters are
+    /// escaped with `'`.
+    fn column_name(&mut self) -> String {
+        let mut name = String::new();
+        let mut chars = self.rest().char_indices();
+        let mut end = self.rest().len();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\'' => name.extend(chars.next().map(|(_, c)| c)),
+                ']' | '[' => {
+                    end = i;
+                    break;
+                }
+                c => name.push(c),
+            }
+        }
+        self.offset += end;
+        name
+    }
+
+    /// Converts a structured reference into a cell or cell range reference.
+    fn resolve_structured_reference(
+        &self,
+        start: usize,
+        table: Option<&str>,
+        items: &[TableItem],
+        columns: Option<(String, Option<String>)>,
+    ) -> ParseResult {
+        let unresolved = FormulaIssue::UnsupportedSyntax("structured reference");
+        let table = self.table(table).ok_or(unresolved.clone())?;
+
+        // rows are zero-based in the table and one-based in Quadratic
+        let first_row = table.first.y + 1;
+        let last_row = table.last.y + 1;
+        let data_first = first_row + table.header_rows;
+        let data_last = last_row - table.totals_rows;
+        let mut rows: Option<(i64, i64)> = None;
+        let mut this_row = false;
+        for item in items {
+            let (first, last) = match item {
+                TableItem::All => (first_row, last_row),
+                TableItem::Data => (data_first, data_last),
+                TableItem::Headers => (first_row, data_first - 1),
+                TableItem::Totals => (data_last + 1, last_row),
+                TableItem::ThisRow => {
+                    this_row = true;
+                    (self.context.pos.y, self.context.pos.y)
+                }
+            };
+            if first > last {
+                return Err(unresolved);
+            }
+            rows = Some(match rows {
+                Some((min, max)) => (min.min(first), max.max(last)),
+                None => (first, last),
+            });
+        }
+        let (first_y, last_y) = rows.unwrap_or((data_first, data_last));
+        if this_row && !(data_first..=data_last).contains(&first_y) {
+            return Err(unresolved);
+        }
+
+        let column = |name: &str| {
+            table
+                .columns
+                .iter()
+                .position(|column| column.eq_ignore_ascii_case(name.trim()))
+                .map(|index| table.first.x + index as i64)
+        };
+        let (first_x, last_x) = match columns {
+            Some((first, last)) => {
+                let first_x = column(&first).ok_or(unresolved.clone())?;
+                let last_x = match last {
+                    Some(last) => column(&last).ok_or(unresolved.clone())?,
+                    None => first_x,
+                };
+                (first_x.min(last_x), first_x.max(last_x))
+            }
+            None => (table.first.x, table.last.x),
+        };
+
+        let sheet = (table.sheet_name != self.context.sheet_name).then(|| table.sheet_name.clone());
+        let cell_ref = |x: i64, y: i64, sheet: Option<String>| {
+            let y = match this_row {
+                true => CellRefCoord::Relative(y - self.context.pos.y),
+                false => CellRefCoord::Absolute(y),
+            };
+            Spanned {
+                span: self.span_from(start),
+                inner: AstNodeContents::CellRef(CellRef {
+                    sheet,
+                    x: CellRefCoord::Absolute(x),
+                    y,
+                }),
+            }
+        };
+
+        let first = cell_ref(first_x, first_y, sheet);
+        if first_x == last_x && first_y == last_y {
+            return Ok(first);
+        }
+        let last = cell_ref(last_x, last_y, None);
+        let op = Spanned {
+            span: self.span_from(start),
+            inner: ":".to_string(),
+        };
+        Ok(Self::operator(op, vec![first, last]))
+    }
+}
+
+/// Row specifiers of a structured reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableItem {
+    All,
+    Data,
+    Headers,
+    Totals,
+    ThisRow,
+}
+
+impl TableItem {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "#all" => Some(Self::All),
+            "#data" => Some(Self::Data),
+            "#headers" => Some(Self::Headers),
+            "#totals" => Some(Self::Totals),
+            "#this row" => Some(Self::ThisRow),
+            _ => None,
+        }
+    }
+}
+
+fn is_reference(node: &AstNode) -> bool {
+    match &node.inner {
+        AstNodeContents::CellRef(_) => true,
+        AstNodeContents::FunctionCall { func, .. } => func.inner == ":",
+        _ => false,
+    }
+}
+
+/// Returns the Quadratic precedence of an AST node (see `OpPrecedence` in
+/// the formula parser), from 0 (comparison) to 9 (atom).
+fn precedence(node: &AstNode) -> u8 {
+    let AstNodeContents::FunctionCall { func, args } = &node.inner else {
+        return 9;
+    };
+    match (func.inner.as_str(), args.len()) {
+        ("=" | "<>" | "<" | ">" | "<=" | ">=", 2) => 0,
+        ("&", 2) => 1,
+        ("+" | "-", 2) => 2,
+        ("*" | "/", 2) => 3,
+        ("^", 2) => 4,
+        (":", 2) => 6,
+        ("+" | "-", 1) => 7,
+        ("%", 1) => 8,
+        _ => 9,
+    }
+}
+
+/// Writes an AST as Quadratic formula source, adding parentheses where
+/// Quadratic's precedence differs from the tree.
+pub fn to_source(node: &AstNode, pos: Pos) -> String {
+    let mut source = String::new();
+    write_source(&mut source, node, pos, 0);
+    source
+}
+
+fn write_source(out: &mut String, node: &AstNode, pos: Pos, min_precedence: u8) {
+    let precedence = precedence(node);
+    if precedence < min_precedence {
+        out.push('(');
+        write_source(out, node, pos, 0);
+        out.push(')');
+        return;
+    }
+
+    match &node.inner {
+        AstNodeContents::Empty => {}
+        AstNodeContents::FunctionCall { func, args } => match (precedence, args.as_slice()) {
+            (6, [start, end]) => {
+                write_source(out, start, pos, 9);
+                out.push(':');
+                match &end.inner {
+                    AstNodeContents::CellRef(cell_ref) => {
+                        let cell_ref = CellRef {
+                            sheet: None,
+                            ..cell_ref.clone()
+                        };
+                        out.push_str(&cell_ref.a1_string(pos));
+                    }
+                    _ => write_source(out, end, pos, 9),
+                }
+            }
+            (0..=5, [lhs, rhs]) => {
+                // `^` is right-associative in Quadratic
+                let (lhs_precedence, rhs_precedence) = match precedence {
+                    4 => (precedence + 1, precedence),
+                    _ => (precedence, precedence + 1),
+                };
+                write_source(out, lhs, pos, lhs_precedence);
+                out.push(' ');
+                out.push_str(&func.inner);
+                out.push(' ');
+                write_source(out, rhs, pos, rhs_precedence);
+            }
+            (7, [value]) => {
+                out.push_str(&func.inner);
+                write_source(out, value, pos, 7);
+            }
+            (8, [value]) => {
+                write_source(out, value, pos, 8);
+                out.push_str(&func.inner);
+            }
+            _ => {
+                out.push_str(&func.inner);
+                out.push('(');
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    write_source(out, arg, pos, 0);
+                }
+                out.push(')');
+            }
+        },
+        AstNodeContents::Paren(inner) => {
+            out.push('(');
+            write_source(out, inner, pos, 0);
+            out.push(')');
+        }
+        AstNodeContents::Array(rows) => {
+            out.push('{');
+            for (i, row) in rows.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("; ");
+                }
+                for (j, value) in row.iter().enumerate() {
+                    if j > 0 {
+                        out.push_str(", ");
+                    }
+                    write_source(out, value, pos, 0);
+                }
+            }
+            out.push('}');
+        }
+        AstNodeContents::CellRef(cell_ref) => {
+            if let Some(sheet) = &cell_ref.sheet {
+                match UNQUOTED_SHEET_NAME_REGEX.is_match(sheet) {
+                    true => out.push_str(sheet),
+                    false => out.push_str(&quote(sheet)),
+                }
+                out.push('!');
+            }
+            let cell_ref = CellRef {
+                sheet: None,
+                ..cell_ref.clone()
+            };
+            out.push_str(&cell_ref.a1_string(pos));
+        }
+        AstNodeContents::String(s) => out.push_str(&quote(s)),
+        AstNodeContents::Number(n) => out.push_str(&n.to_string()),
+        AstNodeContents::Bool(true) => out.push_str("TRUE"),
+        AstNodeContents::Bool(false) => out.push_str("FALSE"),
+    }
+}
+
+/// Converts an OpenDocument formula (OpenFormula, as stored in `.ods` files)
+/// into Excel syntax, so that it can be translated with
+/// [`translate_excel_formula`]. For example, `of:=SUM([$Data.A1:.B2];1)`
+/// becomes `=SUM('Data'!A1:B2,1)`.
+pub fn openformula_to_excel(formula: &str) -> String {
+    let formula = formula
+        .strip_prefix("of:")
+        .or_else(|| formula.strip_prefix("oooc:"))
+        .unwrap_or(formula);
+
+    let mut excel = String::with_capacity(formula.len());
+    let mut array_depth = 0;
+    let mut chars = formula.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // `""` escapes are the same in both, and are copied as two strings
+            '"' => {
+                excel.push(c);
+                for c in chars.by_ref() {
+                    excel.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                let reference: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                excel.push_str(&openformula_reference(&reference));
+            }
+            '{' => {
+                array_depth += 1;
+                excel.push(c);
+            }
+            '}' => {
+                array_depth -= 1;
+                excel.push(c);
+            }
+            // separates both arguments and array columns
+            ';' => excel.push(','),
+            '|' if array_depth > 0 => excel.push(';'),
+            c => excel.push(c),
+        }
+    }
+    excel
+}
+
+/// Converts the inside of an OpenFormula reference, eg, `$'Sheet 1'.$A$1:.B2`,
+/// into an Excel reference.
+fn openformula_reference(reference: &str) -> String {
+    let mut excel = String::new();
+    let mut first_sheet = None;
+    for (i, part) in reference.split(':').enumerate() {
+        // sheet names may contain `.`, but cell addresses cannot
+        let (sheet, cell) = part.rsplit_once('.').unwrap_or(("", part));
+        let sheet = sheet.trim_start_matches('$');
+        let sheet = match sheet.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+            Some(quoted) => quoted.replace("''", "'"),
+            None => sheet.to_string(),
+        };
+
+        if i > 0 {
+            excel.push(':');
+        }
+        if !sheet.is_empty() && (i == 0 || first_sheet.as_ref() != Some(&sheet)) {
+            excel.push_str(&format!("'{}'!", sheet.replace('\'', "''")));
+        }
+        if i == 0 {
+            first_sheet = Some(sheet);
+        }
+        excel.push_str(cell);
+    }
+    excel
+}
+
+/// Quotes a string for a Quadratic formula, which uses backslash escapes.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formulas::parse_formula;
+
+    fn tables() -> Vec<XlsxTable> {
+        vec![XlsxTable {
+            name: "Sales".to_string(),
+            sheet_name: "Data".to_string(),
+            first: Pos { x: 1, y: 1 },
+            last: Pos { x: 3, y: 5 },
+            header_rows: 1,
+            totals_rows: 1,
+            columns: vec!["Region".into(), "Unit Price".into(), "Amount".into()],
+        }]
+    }
+
+    fn translate(formula: &str, sheet_name: &str, pos: Pos) -> Result<String, FormulaIssue> {
+        let tables = tables();
+        let context = FormulaContext {
+            sheet_name,
+            pos,
+            tables: &tables,
+        };
+        let translated = translate_excel_formula(formula, &context)?;
+
+        // the translation must be a valid Quadratic formula
+        assert!(
+            parse_formula(&translated.code, pos).is_ok(),
+            "{} does not parse",
+            translated.code
+        );
+        Ok(translated.code)
+    }
+
+    #[test]
+    fn translates_formulas() {
+        let pos = Pos { x: 5, y: 10 };
+        let t = |formula| translate(formula, "Sheet1", pos).unwrap();
+
+        assert_eq!(t("=SUM(A1:B2)*2"), "SUM(A1:B2) * 2");
+        assert_eq!(t("$A$1+A$2-$B3"), "$A$1 + A$2 - $B3");
+        assert_eq!(t("'Sheet 1'!A1:B2"), "\"Sheet 1\"!A1:B2");
+        assert_eq!(t("'It''s'!A1"), "\"It's\"!A1");
+        assert_eq!(t("Sheet2!C3"), "Sheet2!C3");
+        assert_eq!(t("\"say \"\"hi\"\"\""), r#""say \"hi\"""#);
+        assert_eq!(
+            t("_xlfn.XLOOKUP(A1,B1:B5,C1:C5)"),
+            "XLOOKUP(A1, B1:B5, C1:C5)"
+        );
+        assert_eq!(t("CONCATENATE(\"a\",\"b\")"), "CONCAT(\"a\", \"b\")");
+        assert_eq!(t("_xlfn.SINGLE(A1:A5)+@B1"), "A1:A5 + B1");
+        assert_eq!(t("{1,2;-3,4.5}"), "{1, 2; -3, 4.5}");
+        assert_eq!(t("IF(A1>=1,TRUE,)"), "IF(A1 >= 1, TRUE, )");
+
+        // Excel evaluates `^` left to right
+        assert_eq!(t("2^3^2"), "(2 ^ 3) ^ 2");
+        assert_eq!(t("-2^2"), "-2 ^ 2");
+        assert_eq!(t("(1+2)*3%"), "(1 + 2) * 3%");
+    }
+
+    #[test]
+    fn translates_structured_references() {
+        let t = |formula, sheet_name, pos| translate(formula, sheet_name, pos).unwrap();
+        let inside = Pos { x: 2, y: 4 };
+        let outside = Pos { x: 0, y: 0 };
+
+        assert_eq!(t("SUM(Sales[Amount])", "Data", outside), "SUM($D$3:$D$5)");
+        assert_eq!(
+            t("SUM(Sales[Amount])", "Other", outside),
+            "SUM(Data!$D$3:$D$5)"
+        );
+        assert_eq!(t("Sales[[#Totals],[Amount]]", "Data", outside), "$D$6");
+        assert_eq!(t("Sales[#Headers]", "Data", outside), "$B$2:$D$2");
+        assert_eq!(t("COUNTA(Sales)", "Data", outside), "COUNTA($B$3:$D$5)");
+        assert_eq!(
+            t("COUNTA(Sales[#All])", "Data", outside),
+            "COUNTA($B$2:$D$6)"
+        );
+        assert_eq!(
+            t("Sales[[Unit Price]:[Amount]]", "Data", outside),
+            "$C$3:$D$5"
+        );
+        assert_eq!(t("[@[Unit Price]]*[@Amount]", "Data", inside), "$C4 * $D4");
+        assert_eq!(t("Sales[[#This Row],[Region]]", "Data", inside), "$B4");
+
+        assert_eq!(
+            translate("Missing[Amount]", "Data", outside),
+            Err(FormulaIssue::UnsupportedSyntax("structured reference"))
+        );
+        assert_eq!(
+            translate("[@Amount]", "Data", outside),
+            Err(FormulaIssue::UnsupportedSyntax("structured reference"))
+        );
+    }
+
+    #[test]
+    fn reports_unsupported_formulas() {
+        let tables = tables();
+        let context = FormulaContext {
+            sheet_name: "Sheet1",
+            pos: Pos { x: 0, y: 1 },
+            tables: &tables,
+        };
+        let issues = |formula| translate_excel_formula(formula, &context).map(|t| t.issues);
+
+        assert_eq!(issues("SUM(A1:A3)"), Ok(vec![]));
+        assert_eq!(
+            issues("IFERROR(_xlfn.STDEV.S(A1:A3), 0) + _xludf.MYFUNC(1)"),
+            Ok(vec![
+                FormulaIssue::UnsupportedFunction("IFERROR".into()),
+                FormulaIssue::UnsupportedFunction("STDEV.S".into()),
+                FormulaIssue::UnknownFunction("_xludf.MYFUNC".into()),
+            ])
+        );
+
+        let unsupported = |syntax| -> Result<Vec<FormulaIssue>, FormulaIssue> {
+            Err(FormulaIssue::UnsupportedSyntax(syntax))
+        };
+        assert_eq!(issues("SUM(A:A)"), unsupported("full column reference"));
+        assert_eq!(issues("SUM(Sheet2!1:3)"), unsupported("full row reference"));
+        assert_eq!(
+            issues("[Book2.xlsx]Sheet1!A1"),
+            unsupported("external reference")
+        );
+        assert_eq!(issues("SUM(Sheet1:Sheet3!A1)"), unsupported("3D reference"));
+        assert_eq!(issues("TaxRate*2"), unsupported("defined name"));
+        assert_eq!(
+            issues("SUM(A1:A3 A2:B2)"),
+            unsupported("intersection operator")
+        );
+        assert_eq!(issues("SUM((A1,B1))"), unsupported("union operator"));
+        assert_eq!(issues("IF(A1,#N/A,1)"), unsupported("error value"));
+        assert_eq!(issues("SUM(A1#)"), unsupported("spilled range reference"));
+        assert_eq!(issues("SUM(A1"), Err(FormulaIssue::InvalidFormula));
+    }
+
+    #[test]
+    fn converts_openformula() {
+        assert_eq!(
+            openformula_to_excel("of:=SUM([.A1:.B2];2)"),
+            "=SUM(A1:B2,2)"
+        );
+        assert_eq!(
+            openformula_to_excel("of:=[$'It''s'.$A$1]&\"a;\"\"b\"\"\""),
+            "='It''s'!$A$1&\"a;\"\"b\"\"\""
+        );
+        assert_eq!(
+            openformula_to_excel("of:=SUM([$Data.A1:$Data.B2])"),
+            "=SUM('Data'!A1:B2)"
+        );
+        assert_eq!(openformula_to_excel("of:={1;2|3;4}"), "={1,2;3,4}");
+    }
+}
@@ -0,0 +1,16 @@
+//! Direct access to the parts of an XLSX package that calamine does not
+//! expose (styles, number formats, etc.), and a writer for exporting a grid.
+//!
+//! An XLSX file is a zip archive of XML parts. Cell values and formulas are
+//! read through calamine; this module only reads the remaining metadata.
+//! Writing is done entirely here so that it works in WASM.
+
+pub mod color;
+pub mod formula;
+pub mod reader;
+pub mod styles;
+pub mod writer;
+
+pub use reader::{XlsxSheet, XlsxTable, XlsxWorkbook};
+pub use styles::XlsxStyles;
+pub use writer::write_grid;
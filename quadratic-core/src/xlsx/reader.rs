@@ -0,0 +1,490 @@
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read},
+};
+
+use anyhow::{anyhow, Context, Result};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use zip::ZipArchive;
+
+use super::{
+    color::{parse_theme_colors, XlsxColor},
+    styles::XlsxStyles,
+};
+use crate::{color::Rgba, util::column_from_name, Pos};
+
+/// Returns the unescaped value of an attribute.
+pub(crate) fn attribute(e: &BytesStart<'_>, name: &str) -> Option<String> {
+    e.try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// Parses an A1 cell reference (eg, "B2") into a zero-based (column, row)
+/// position, as used by calamine.
+pub(crate) fn parse_cell_ref(cell_ref: &str) -> Option<Pos> {
+    let split = cell_ref.find(|c: char| c.is_ascii_digit())?;
+    let (column, row) = cell_ref.split_at(split);
+    let x = column_from_name(&column.to_ascii_uppercase())?;
+    let y = row.parse::<i64>().ok()?.checked_sub(1)?;
+    Some(Pos { x, y })
+}
+
+/// A `<col>` record, which applies to a range of columns.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxColumns {
+    /// Zero-based first and last column (inclusive).
+    pub first: i64,
+    pub last: i64,
+
+    /// Width in characters of the default font, if customized.
+    pub width: Option<f64>,
+    pub hidden: bool,
+}
+
+/// A worksheet's metadata.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxSheet {
+    pub name: String,
+
+    /// Style index (into [`XlsxStyles::cell_xfs`]) of every styled cell,
+    /// keyed by zero-based (column, row) position.
+    pub cell_styles: HashMap<Pos, u32>,
+
+    pub columns: Vec<XlsxColumns>,
+
+    /// Custom row heights in points, keyed by zero-based row.
+    pub row_heights: HashMap<i64, f64>,
+
+    pub tab_color: Option<XlsxColor>,
+
+    // Counts of features that are not imported.
+    pub merged_cells: u32,
+    pub conditional_formats: u32,
+    pub data_validations: u32,
+    pub drawings: u32,
+    pub hidden_rows: u32,
+}
+
+/// A table in a worksheet, used to resolve structured references (eg,
+/// `Sales[Amount]`) in formulas.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxTable {
+    /// Name used in formulas (`displayName`).
+    pub name: String,
+    pub sheet_name: String,
+
+    /// Zero-based (column, row) positions of the first and last cells,
+    /// including header and totals rows.
+    pub first: Pos,
+    pub last: Pos,
+
+    pub header_rows: i64,
+    pub totals_rows: i64,
+
+    /// Column names, in order.
+    pub columns: Vec<String>,
+}
+
+/// Metadata of an XLSX workbook that calamine does not expose.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxWorkbook {
+    pub sheets: Vec<XlsxSheet>,
+    pub styles: XlsxStyles,
+    pub tables: Vec<XlsxTable>,
+
+    /// Colors of the workbook theme, referenced by [`XlsxColor::Theme`].
+    pub theme_colors: Vec<Rgba>,
+}
+
+impl XlsxWorkbook {
+    /// Reads the workbook metadata from the bytes of an XLSX file.
+    pub fn read(file: &[u8]) -> Result<Self> {
+        let mut archive = ZipArchive::new(Cursor::new(file))?;
+
+        let relationships =
+            read_relationships(&read_part(&mut archive, "xl/_rels/workbook.xml.rels")?)?;
+
+        let styles = match read_part(&mut archive, "xl/styles.xml") {
+            Ok(xml) => XlsxStyles::parse(&xml)?,
+            Err(_) => XlsxStyles::default(),
+        };
+        let theme_colors = match read_part(&mut archive, "xl/theme/theme1.xml") {
+            Ok(xml) => parse_theme_colors(&xml)?,
+            Err(_) => vec![],
+        };
+
+        let mut sheets = vec![];
+        let mut tables = vec![];
+        for (name, relationship_id) in
+            read_sheet_names(&read_part(&mut archive, "xl/workbook.xml")?)?
+        {
+            let target = relationships.get(&relationship_id).with_context(|| {
+                format!("Missing relationship {relationship_id} for sheet {name}")
+            })?;
+            let path = part_path(target);
+            let xml = read_part(&mut archive, &path)?;
+
+            // tables are related to the worksheet, eg, xl/worksheets/_rels/sheet1.xml.rels
+            let (folder, file_name) = path.rsplit_once('/').unwrap_or(("", &path));
+            if let Ok(xml) = read_part(&mut archive, &format!("{folder}/_rels/{file_name}.rels")) {
+                for target in read_relationship_targets(&xml, TABLE_RELATIONSHIP)? {
+                    let xml = read_part(&mut archive, &relative_part_path(folder, &target))?;
+                    if let Some(table) = parse_table(&xml, &name)? {
+                        tables.push(table);
+                    }
+                }
+            }
+
+            sheets.push(XlsxSheet {
+                name,
+                ..read_worksheet(&xml)?
+            });
+        }
+
+        Ok(Self {
+            sheets,
+            styles,
+            tables,
+            theme_colors,
+        })
+    }
+
+    /// Returns the metadata of a sheet by name.
+    pub fn sheet(&self, name: &str) -> Option<&XlsxSheet> {
+        self.sheets.iter().find(|sheet| sheet.name == name)
+    }
+}
+
+fn read_part(archive: &mut ZipArchive<Cursor<&[u8]>>, path: &str) -> Result<String> {
+    let mut part = archive
+        .by_name(path)
+        .map_err(|e| anyhow!("Unable to read {path}: {e}"))?;
+    let mut xml = String::new();
+    part.read_to_string(&mut xml)?;
+    Ok(xml)
+}
+
+/// Relationship targets are relative to the xl/ folder unless absolute.
+fn part_path(target: &str) -> String {
+    match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("xl/{target}"),
+    }
+}
+
+/// Resolves a relationship target relative to the folder of the part that
+/// owns the relationship.
+fn relative_part_path(folder: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut parts = folder
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    for part in target.split('/') {
+        match part {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Relationship type of a worksheet's tables.
+const TABLE_RELATIONSHIP: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/table";
+
+/// Returns the targets of relationships of one type.
+fn read_relationship_targets(xml: &str, relationship_type: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut targets = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                if attribute(&e, "Type").is_some_and(|t| t == relationship_type) {
+                    targets.extend(attribute(&e, "Target"));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(targets)
+}
+
+/// Parses a table part (eg, xl/tables/table1.xml). Returns `None` if the
+/// table has no name or range.
+fn parse_table(xml: &str, sheet_name: &str) -> Result<Option<XlsxTable>> {
+    let mut reader = Reader::from_str(xml);
+    let mut table: Option<XlsxTable> = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"table" => {
+                    let name = attribute(&e, "displayName").or_else(|| attribute(&e, "name"));
+                    let range = attribute(&e, "ref").and_then(|range| {
+                        let (first, last) = range.split_once(':').unwrap_or((&range, &range));
+                        Some((parse_cell_ref(first)?, parse_cell_ref(last)?))
+                    });
+                    let (Some(name), Some((first, last))) = (name, range) else {
+                        return Ok(None);
+                    };
+                    let count = |name, default| {
+                        attribute(&e, name)
+                            .and_then(|count| count.parse().ok())
+                            .unwrap_or(default)
+                    };
+                    table = Some(XlsxTable {
+                        name,
+                        sheet_name: sheet_name.to_string(),
+                        first,
+                        last,
+                        header_rows: count("headerRowCount", 1),
+                        totals_rows: count("totalsRowCount", 0),
+                        columns: vec![],
+                    });
+                }
+                b"tableColumn" => {
+                    if let (Some(table), Some(name)) = (table.as_mut(), attribute(&e, "name")) {
+                        table.columns.push(name);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(table)
+}
+
+/// Returns relationship targets keyed by relationship id.
+fn read_relationships(xml: &str) -> Result<HashMap<String, String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut relationships = HashMap::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                if let (Some(id), Some(target)) = (attribute(&e, "Id"), attribute(&e, "Target")) {
+                    relationships.insert(id, target);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(relationships)
+}
+
+/// Returns the (name, relationship id) of each sheet, in workbook order.
+fn read_sheet_names(xml: &str) -> Result<Vec<(String, String)>> {
+    let mut reader = Reader::from_str(xml);
+    let mut sheets = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sheet" => {
+                if let (Some(name), Some(id)) = (attribute(&e, "name"), attribute(&e, "r:id")) {
+                    sheets.push((name, id));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(sheets)
+}
+
+/// Reads the styles, layout, and unsupported features of a worksheet. The
+/// name is left empty.
+fn read_worksheet(xml: &str) -> Result<XlsxSheet> {
+    let mut reader = Reader::from_str(xml);
+    let mut sheet = XlsxSheet::default();
+    let is_set = |value: Option<String>| value.is_some_and(|v| v == "1" || v == "true");
+    loop {
+        let e = match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => e,
+            Event::Eof => break,
+            _ => continue,
+        };
+        match e.local_name().as_ref() {
+            b"c" => {
+                let pos = attribute(&e, "r").and_then(|r| parse_cell_ref(&r));
+                let style = attribute(&e, "s").and_then(|s| s.parse::<u32>().ok());
+                if let (Some(pos), Some(style)) = (pos, style) {
+                    if style != 0 {
+                        sheet.cell_styles.insert(pos, style);
+                    }
+                }
+            }
+            b"row" => {
+                let Some(row) = attribute(&e, "r").and_then(|r| r.parse::<i64>().ok()) else {
+                    continue;
+                };
+                if is_set(attribute(&e, "hidden")) {
+                    sheet.hidden_rows += 1;
+                }
+                if is_set(attribute(&e, "customHeight")) {
+                    if let Some(height) = attribute(&e, "ht").and_then(|ht| ht.parse().ok()) {
+                        sheet.row_heights.insert(row - 1, height);
+                    }
+                }
+            }
+            b"col" => {
+                let index = |name| attribute(&e, name).and_then(|i| i.parse::<i64>().ok());
+                if let (Some(min), Some(max)) = (index("min"), index("max")) {
+                    let custom = is_set(attribute(&e, "customWidth"));
+                    sheet.columns.push(XlsxColumns {
+                        first: min - 1,
+                        last: max - 1,
+                        width: attribute(&e, "width")
+                            .and_then(|width| width.parse().ok())
+                            .filter(|_| custom),
+                        hidden: is_set(attribute(&e, "hidden")),
+                    });
+                }
+            }
+            b"tabColor" => sheet.tab_color = XlsxColor::parse(&e),
+            b"mergeCell" => sheet.merged_cells += 1,
+            b"conditionalFormatting" => sheet.conditional_formats += 1,
+            b"dataValidation" => sheet.data_validations += 1,
+            b"drawing" | b"legacyDrawing" => sheet.drawings += 1,
+            _ => {}
+        }
+    }
+    Ok(sheet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cell_refs() {
+        assert_eq!(parse_cell_ref("A1"), Some(Pos { x: 0, y: 0 }));
+        assert_eq!(parse_cell_ref("b12"), Some(Pos { x: 1, y: 11 }));
+        assert_eq!(parse_cell_ref("AA3"), Some(Pos { x: 26, y: 2 }));
+        assert_eq!(parse_cell_ref("A0"), None);
+        assert_eq!(parse_cell_ref("12"), None);
+    }
+
+    #[test]
+    fn reads_worksheets() {
+        let xml = r#"<worksheet>
+            <sheetPr><tabColor rgb="FF00B050"/></sheetPr>
+            <cols>
+                <col min="1" max="1" width="20.5" customWidth="1"/>
+                <col min="3" max="5" width="9.14" hidden="1"/>
+            </cols>
+            <sheetData>
+                <row r="1"><c r="A1" s="1"><v>1</v></c><c r="B1"><v>2</v></c></row>
+                <row r="2" ht="30" customHeight="1"><c r="C2" s="0"><v>3</v></c><c r="D2" s="4"/></row>
+                <row r="3" ht="15" hidden="1"/>
+            </sheetData>
+            <mergeCells count="2"><mergeCell ref="A5:B5"/><mergeCell ref="A6:B6"/></mergeCells>
+            <conditionalFormatting sqref="A1:A3"><cfRule type="cellIs" dxfId="0"/></conditionalFormatting>
+            <dataValidations count="1"><dataValidation type="list" sqref="B1"/></dataValidations>
+            <drawing r:id="rId1"/>
+        </worksheet>"#;
+        let sheet = read_worksheet(xml).unwrap();
+
+        assert_eq!(sheet.cell_styles.len(), 2);
+        assert_eq!(sheet.cell_styles.get(&Pos { x: 0, y: 0 }), Some(&1));
+        assert_eq!(sheet.cell_styles.get(&Pos { x: 3, y: 1 }), Some(&4));
+
+        assert_eq!(
+            sheet.columns,
+            vec![
+                XlsxColumns {
+                    first: 0,
+                    last: 0,
+                    width: Some(20.5),
+                    hidden: false,
+                },
+                XlsxColumns {
+                    first: 2,
+                    last: 4,
+                    width: None,
+                    hidden: true,
+                },
+            ]
+        );
+        assert_eq!(sheet.row_heights, HashMap::from([(1, 30.0)]));
+        assert_eq!(sheet.hidden_rows, 1);
+        assert_eq!(
+            sheet.tab_color,
+            Some(XlsxColor::Rgb(Rgba {
+                red: 0x00,
+                green: 0xB0,
+                blue: 0x50,
+                alpha: 255,
+            }))
+        );
+        assert_eq!(sheet.merged_cells, 2);
+        assert_eq!(sheet.conditional_formats, 1);
+        assert_eq!(sheet.data_validations, 1);
+        assert_eq!(sheet.drawings, 1);
+    }
+
+    #[test]
+    fn reads_tables() {
+        let xml = r#"<table xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" id="1" name="Table1" displayName="Sales" ref="B2:D6" totalsRowCount="1">
+            <autoFilter ref="B2:D5"/>
+            <tableColumns count="3">
+                <tableColumn id="1" name="Region"/>
+                <tableColumn id="2" name="Units"/>
+                <tableColumn id="3" name="Amount" totalsRowFunction="sum"/>
+            </tableColumns>
+        </table>"#;
+        assert_eq!(
+            parse_table(xml, "Sheet1").unwrap(),
+            Some(XlsxTable {
+                name: "Sales".to_string(),
+                sheet_name: "Sheet1".to_string(),
+                first: Pos { x: 1, y: 1 },
+                last: Pos { x: 3, y: 5 },
+                header_rows: 1,
+                totals_rows: 1,
+                columns: vec!["Region".into(), "Units".into(), "Amount".into()],
+            })
+        );
+
+        assert_eq!(
+            relative_part_path("xl/worksheets", "../tables/table1.xml"),
+            "xl/tables/table1.xml"
+        );
+        assert_eq!(
+            relative_part_path("xl/worksheets", "/xl/tables/table2.xml"),
+            "xl/tables/table2.xml"
+        );
+    }
+
+    #[test]
+    fn reads_workbook() {
+        let file_path = "../quadratic-rust-shared/data/excel/financial_sample.xlsx";
+        let file = std::fs::read(file_path).unwrap();
+        let workbook = XlsxWorkbook::read(&file).unwrap();
+
+        assert_eq!(workbook.sheets.len(), 1);
+        let sheet = workbook.sheet("Sheet1").unwrap();
+
+        // F2 uses an accounting format
+        let style = sheet.cell_styles[&Pos { x: 5, y: 1 }];
+        assert_eq!(
+            workbook.styles.format_code(style),
+            Some(r#"_("$"* #,##0.00_);_("$"* \(#,##0.00\);_("$"* "-"??_);_(@_)"#)
+        );
+
+        // E2 has no style
+        assert!(!sheet.cell_styles.contains_key(&Pos { x: 4, y: 1 }));
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use quick_xml::{events::Event, Reader};
+
+use super::{color::XlsxColor, reader::attribute};
+use crate::{grid::NumericFormat, FormatCode};
+
+/// Returns the code of a built-in number format. These are implied by their
+/// id and never written to styles.xml (ECMA-376 Part 1, 18.8.30).
+///
+/// Fraction formats (ids 12 and 13) are not supported and are left out.
+pub fn builtin_num_fmt(id: u32) -> Option<&'static str> {
+    let code = match id {
+        0 => "General",
+        1 => "0",
+        2 => "0.00",
+        3 => "#,##0",
+        4 => "#,##0.00",
+        9 => "0%",
+        10 => "0.00%",
+        11 => "0.00E+00",
+        14 => "mm-dd-yy",
+        15 => "d-mmm-yy",
+        16 => "d-mmm",
+        17 => "mmm-yy",
+        18 => "h:mm AM/PM",
+        19 => "h:mm:ss AM/PM",
+        20 => "h:mm",
+        21 => "h:mm:ss",
+        22 => "m/d/yy h:mm",
+        37 => "#,##0 ;(#,##0)",
+        38 => "#,##0 ;[Red](#,##0)",
+        39 => "#,##0.00;(#,##0.00)",
+        40 => "#,##0.00;[Red](#,##0.00)",
+        45 => "mm:ss",
+        46 => "[h]:mm:ss",
+        47 => "mm:ss.0",
+        48 => "##0.0E+0",
+        49 => "@",
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// A font record (`<font>` in `<fonts>`).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxFont {
+    pub bold: bool,
+    pub italic: bool,
+    pub color: Option<XlsxColor>,
+}
+
+/// A fill record (`<fill>` in `<fills>`). Only pattern fills are read.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxFill {
+    /// `patternType`, eg, "solid" or "gray125".
+    pub pattern: Option<String>,
+    pub color: Option<XlsxColor>,
+
+    /// Whether the fill is a gradient, which is not read.
+    pub gradient: bool,
+}
+
+/// One side of a border record.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxBorderSide {
+    /// eg, "thin", "medium", or "dashed".
+    pub style: String,
+    pub color: Option<XlsxColor>,
+}
+
+/// A border record (`<border>` in `<borders>`).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxBorder {
+    pub left: Option<XlsxBorderSide>,
+    pub right: Option<XlsxBorderSide>,
+    pub top: Option<XlsxBorderSide>,
+    pub bottom: Option<XlsxBorderSide>,
+
+    /// Whether the border has a diagonal line, which is not supported.
+    pub diagonal: bool,
+}
+
+/// A cell format record (`<xf>` in `<cellXfs>`).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxCellXf {
+    pub num_fmt_id: u32,
+    pub font_id: u32,
+    pub fill_id: u32,
+    pub border_id: u32,
+
+    /// `<alignment horizontal="..">`, eg, "left" or "center".
+    pub horizontal: Option<String>,
+    pub wrap_text: bool,
+}
+
+/// The parts of styles.xml that map to Quadratic formats.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XlsxStyles {
+    /// Custom number formats, keyed by numFmtId.
+    pub num_fmts: HashMap<u32, String>,
+
+    pub fonts: Vec<XlsxFont>,
+    pub fills: Vec<XlsxFill>,
+    pub borders: Vec<XlsxBorder>,
+
+    /// Cell formats, indexed by a cell's `s` attribute.
+    pub cell_xfs: Vec<XlsxCellXf>,
+}
+
+/// The list in styles.xml being read. Records with the same element names
+/// (eg, `<font>` in `<dxfs>`) appear elsewhere and are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Fonts,
+    Fills,
+    Borders,
+    CellXfs,
+    Other,
+}
+
+/// Parses boolean elements like `<b/>` and `<b val="0"/>`.
+fn is_set(e: &quick_xml::events::BytesStart<'_>) -> bool {
+    attribute(e, "val").map_or(true, |val| val != "0" && val != "false")
+}
+
+impl XlsxStyles {
+    /// Parses the contents of xl/styles.xml.
+    pub fn parse(xml: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(xml);
+        let mut styles = Self::default();
+        let mut section = Section::Other;
+        let mut border_side: Option<&'static str> = None;
+
+        loop {
+            let (e, is_empty) = match reader.read_event()? {
+                Event::Start(e) => (e, false),
+                Event::Empty(e) => (e, true),
+                Event::End(e) => {
+                    match e.local_name().as_ref() {
+                        b"fonts" | b"fills" | b"borders" | b"cellXfs" | b"dxfs" => {
+                            section = Section::Other
+                        }
+                        b"left" | b"right" | b"top" | b"bottom" => border_side = None,
+                        _ => {}
+                    }
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+
+            match (section, e.local_name().as_ref()) {
+                (_, b"fonts") => section = Section::Fonts,
+                (_, b"fills") => section = Section::Fills,
+                (_, b"borders") => section = Section::Borders,
+                (_, b"cellXfs") => section = Section::CellXfs,
+                (_, b"dxfs") | (_, b"cellStyleXfs") => section = Section::Other,
+                (_, b"numFmt") => {
+                    let id = attribute(&e, "numFmtId").and_then(|id| id.parse().ok());
+                    if let (Some(id), Some(code)) = (id, attribute(&e, "formatCode")) {
+                        styles.num_fmts.insert(id, code);
+                    }
+                }
+
+                (Section::Fonts, b"font") => styles.fonts.push(XlsxFont::default()),
+                (Section::Fonts, name) => {
+                    if let Some(font) = styles.fonts.last_mut() {
+                        match name {
+                            b"b" => font.bold = is_set(&e),
+                            b"i" => font.italic = is_set(&e),
+                            b"color" => font.color = XlsxColor::parse(&e),
+                            _ => {}
+                        }
+                    }
+                }
+
+                (Section::Fills, b"fill") => styles.fills.push(XlsxFill::default()),
+                (Section::Fills, name) => {
+                    if let Some(fill) = styles.fills.last_mut() {
+                        match name {
+                            b"patternFill" => fill.pattern = attribute(&e, "patternType"),
+                            b"fgColor" => fill.color = XlsxColor::parse(&e),
+                            b"gradientFill" => fill.gradient = true,
+                            _ => {}
+                        }
+                    }
+                }
+
+                (Section::Borders, b"border") => styles.borders.push(XlsxBorder::default()),
+                (Section::Borders, name) => {
+                    let Some(border) = styles.borders.last_mut() else {
+                        continue;
+                    };
+                    match name {
+                        b"left" | b"right" | b"top" | b"bottom" => {
+                            let side = match name {
+                                b"left" => "left",
+                                b"right" => "right",
+                                b"top" => "top",
+                                _ => "bottom",
+                            };
+                            let value = attribute(&e, "style")
+                                .filter(|style| style != "none")
+                                .map(|style| XlsxBorderSide { style, color: None });
+                            match side {
+                                "left" => border.left = value,
+                                "right" => border.right = value,
+                                "top" => border.top = value,
+                                _ => border.bottom = value,
+                            }
+                            border_side = (!is_empty).then_some(side);
+                        }
+                        b"diagonal" => {
+                            border.diagonal |=
+                                attribute(&e, "style").is_some_and(|style| style != "none");
+                        }
+                        b"color" => {
+                            let side = match border_side {
+                                Some("left") => border.left.as_mut(),
+                                Some("right") => border.right.as_mut(),
+                                Some("top") => border.top.as_mut(),
+                                Some("bottom") => border.bottom.as_mut(),
+                                _ => None,
+                            };
+                            if let Some(side) = side {
+                                side.color = XlsxColor::parse(&e);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                (Section::CellXfs, b"xf") => {
+                    let id = |name| {
+                        attribute(&e, name)
+                            .and_then(|id| id.parse().ok())
+                            .unwrap_or_default()
+                    };
+                    styles.cell_xfs.push(XlsxCellXf {
+                        num_fmt_id: id("numFmtId"),
+                        font_id: id("fontId"),
+                        fill_id: id("fillId"),
+                        border_id: id("borderId"),
+                        ..Default::default()
+                    });
+                }
+                (Section::CellXfs, b"alignment") => {
+                    if let Some(xf) = styles.cell_xfs.last_mut() {
+                        xf.horizontal = attribute(&e, "horizontal");
+                        xf.wrap_text = attribute(&e, "wrapText")
+                            .is_some_and(|wrap| wrap == "1" || wrap == "true");
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(styles)
+    }
+
+    /// Returns the cell format record for a cell style index.
+    pub fn cell_xf(&self, style: u32) -> Option<&XlsxCellXf> {
+        self.cell_xfs.get(style as usize)
+    }
+
+    /// Returns the number format code for a cell style index, or `None` for
+    /// General and unsupported codes.
+    pub fn format_code(&self, style: u32) -> Option<&str> {
+        let id = self.cell_xfs.get(style as usize)?.num_fmt_id;
+        let code = self
+            .num_fmts
+            .get(&id)
+            .map(String::as_str)
+            .or_else(|| builtin_num_fmt(id))?;
+
+        if code.eq_ignore_ascii_case("general") || FormatCode::parse(code).is_err() {
+            return None;
+        }
+        Some(code)
+    }
+
+    /// Returns true if a cell style has a number format that is not General
+    /// and cannot be imported.
+    pub fn has_unsupported_format(&self, style: u32) -> bool {
+        let Some(xf) = self.cell_xfs.get(style as usize) else {
+            return false;
+        };
+        match self
+            .num_fmts
+            .get(&xf.num_fmt_id)
+            .map(String::as_str)
+            .or_else(|| builtin_num_fmt(xf.num_fmt_id))
+        {
+            Some(code) => !code.eq_ignore_ascii_case("general") && FormatCode::parse(code).is_err(),
+            None => xf.num_fmt_id != 0,
+        }
+    }
+
+    /// Returns the [`NumericFormat`] for a cell style index.
+    pub fn numeric_format(&self, style: u32) -> Option<NumericFormat> {
+        self.format_code(style).map(NumericFormat::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STYLES: &str = r##"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+    <numFmts count="2">
+        <numFmt numFmtId="164" formatCode="#,##0.00;[Red]\(#,##0.00\)"/>
+        <numFmt numFmtId="165" formatCode="yyyy\-mm\-dd\ hh:mm"/>
+    </numFmts>
+    <cellStyleXfs count="1">
+        <xf numFmtId="3" fontId="0" fillId="0" borderId="0"/>
+    </cellStyleXfs>
+    <cellXfs count="5">
+        <xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/>
+        <xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/>
+        <xf numFmtId="165" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/>
+        <xf numFmtId="10" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/>
+        <xf numFmtId="13" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/>
+    </cellXfs>
+</styleSheet>"##;
+
+    #[test]
+    fn parses_styles() {
+        let styles = XlsxStyles::parse(STYLES).unwrap();
+        assert_eq!(styles.num_fmts.len(), 2);
+        assert_eq!(styles.cell_xfs.len(), 5);
+
+        assert_eq!(styles.format_code(0), None);
+        assert_eq!(styles.format_code(1), Some(r"#,##0.00;[Red]\(#,##0.00\)"));
+        assert_eq!(styles.format_code(2), Some(r"yyyy\-mm\-dd\ hh:mm"));
+        assert_eq!(styles.format_code(3), Some("0.00%"));
+
+        // unsupported built-in format
+        assert_eq!(styles.format_code(4), None);
+        assert!(styles.has_unsupported_format(4));
+        assert!(!styles.has_unsupported_format(0));
+        assert!(!styles.has_unsupported_format(1));
+
+        // out of range
+        assert_eq!(styles.format_code(5), None);
+
+        assert_eq!(
+            styles.numeric_format(3),
+            Some(NumericFormat::custom("0.00%"))
+        );
+    }
+
+    #[test]
+    fn parses_fonts_fills_and_borders() {
+        let xml = r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+    <fonts count="2">
+        <font><sz val="11"/><color theme="1"/><name val="Calibri"/></font>
+        <font><b/><i val="0"/><color rgb="FFFF0000"/></font>
+    </fonts>
+    <fills count="3">
+        <fill><patternFill patternType="none"/></fill>
+        <fill><patternFill patternType="gray125"/></fill>
+        <fill><patternFill patternType="solid"><fgColor indexed="13"/><bgColor indexed="64"/></patternFill></fill>
+    </fills>
+    <borders count="2">
+        <border><left/><right/><top/><bottom/><diagonal/></border>
+        <border>
+            <left style="thin"><color auto="1"/></left>
+            <right style="none"/>
+            <top/>
+            <bottom style="double"><color rgb="FF0000FF"/></bottom>
+            <diagonal style="thin"/>
+        </border>
+    </borders>
+    <cellXfs count="2">
+        <xf numFmtId="0" fontId="0" fillId="0" borderId="0"/>
+        <xf numFmtId="0" fontId="1" fillId="2" borderId="1" applyAlignment="1">
+            <alignment horizontal="center" wrapText="1"/>
+        </xf>
+    </cellXfs>
+    <dxfs count="1">
+        <dxf><font><b/></font><fill><patternFill><bgColor rgb="FFFFC7CE"/></patternFill></fill></dxf>
+    </dxfs>
+</styleSheet>"#;
+        let styles = XlsxStyles::parse(xml).unwrap();
+        assert_eq!(styles.fonts.len(), 2);
+        assert_eq!(styles.fills.len(), 3);
+        assert_eq!(styles.borders.len(), 2);
+
+        assert_eq!(
+            styles.fonts[1],
+            XlsxFont {
+                bold: true,
+                italic: false,
+                color: Some(XlsxColor::Rgb(crate::color::Rgba {
+                    red: 255,
+                    green: 0,
+                    blue: 0,
+                    alpha: 255,
+                })),
+            }
+        );
+        assert_eq!(styles.fills[2].pattern.as_deref(), Some("solid"));
+        assert_eq!(styles.fills[2].color, Some(XlsxColor::Indexed(13)));
+
+        let border = &styles.borders[1];
+        assert_eq!(border.left.as_ref().unwrap().style, "thin");
+        assert_eq!(border.left.as_ref().unwrap().color, Some(XlsxColor::Auto));
+        assert_eq!(border.right, None);
+        assert_eq!(border.top, None);
+        assert_eq!(border.bottom.as_ref().unwrap().style, "double");
+        assert!(border.diagonal);
+        assert!(!styles.borders[0].diagonal);
+
+        let xf = styles.cell_xf(1).unwrap();
+        assert_eq!((xf.font_id, xf.fill_id, xf.border_id), (1, 2, 1));
+        assert_eq!(xf.horizontal.as_deref(), Some("center"));
+        assert!(xf.wrap_text);
+    }
+}
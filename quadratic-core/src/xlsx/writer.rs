@@ -0,0 +1,852 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{Cursor, Write},
+};
+
+use anyhow::Result;
+use indexmap::IndexSet;
+use quick_xml::escape::escape;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::{reader::parse_cell_ref, styles::builtin_num_fmt};
+use crate::{
+    color::Rgba,
+    formulas::{to_excel_formula, CellRef, CellRefCoord, RangeRef},
+    grid::{
+        formats::format::Format, CellAlign, CellBorderLine, CellBorders, CellWrap,
+        CodeCellLanguage, Grid, GridBounds, NumericFormat, NumericFormatKind, Sheet,
+    },
+    util::column_name,
+    CellValue, Pos, RunErrorMsg, DEFAULT_COLUMN_WIDTH, DEFAULT_ROW_HEIGHT,
+};
+
+const MAX_COLUMNS: i64 = 16_384;
+const MAX_ROWS: i64 = 1_048_576;
+const MAX_SHEET_NAME_LENGTH: usize = 31;
+
+/// The first id available for custom number formats.
+const FIRST_CUSTOM_NUM_FMT_ID: u32 = 164;
+
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const RELATIONSHIPS_NS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_RELATIONSHIPS_NS: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships";
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+/// Where a Quadratic sheet ends up in the workbook.
+struct ExcelSheet {
+    name: String,
+
+    /// Offset added to Quadratic coordinates so that every cell lands at or
+    /// after A1.
+    offset: Pos,
+}
+
+/// Writes every sheet of a grid to the bytes of an XLSX file.
+///
+/// Quadratic coordinates may be negative and rows start at 0, so each sheet
+/// is shifted just enough for its content to start at or after A1. Formula
+/// references are translated to match, including references to other sheets.
+pub fn write_grid(grid: &Grid) -> Result<Vec<u8>> {
+    let mut sheets = grid.sheets().iter().collect::<Vec<_>>();
+    sheets.sort_by(|a, b| a.order.cmp(&b.order));
+
+    let mut excel_sheets = HashMap::new();
+    let mut names = vec![];
+    for sheet in &sheets {
+        let name = unique_sheet_name(&sheet.name, &names);
+        names.push(name.clone());
+        excel_sheets.insert(
+            sheet.name.to_lowercase(),
+            ExcelSheet {
+                name,
+                offset: sheet_offset(sheet),
+            },
+        );
+    }
+
+    let mut styles = StylesWriter::new();
+    let worksheets = sheets
+        .iter()
+        .map(|sheet| write_sheet(sheet, &excel_sheets, &mut styles))
+        .collect::<Vec<_>>();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("[Content_Types].xml", options)?;
+    zip.write_all(content_types(names.len()).as_bytes())?;
+    zip.start_file("_rels/.rels", options)?;
+    zip.write_all(root_relationships().as_bytes())?;
+    zip.start_file("xl/workbook.xml", options)?;
+    zip.write_all(workbook(&names).as_bytes())?;
+    zip.start_file("xl/_rels/workbook.xml.rels", options)?;
+    zip.write_all(workbook_relationships(names.len()).as_bytes())?;
+    zip.start_file("xl/styles.xml", options)?;
+    zip.write_all(styles.to_xml().as_bytes())?;
+    for (i, worksheet) in worksheets.iter().enumerate() {
+        zip.start_file(format!("xl/worksheets/sheet{}.xml", i + 1), options)?;
+        zip.write_all(worksheet.as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Excel sheet names are at most 31 characters, may not contain `[]:*?/\`,
+/// and must be unique (ignoring case).
+fn unique_sheet_name(name: &str, existing: &[String]) -> String {
+    let base = name
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .take(MAX_SHEET_NAME_LENGTH)
+        .collect::<String>();
+    let base = match base.trim_matches('\'') {
+        "" => "Sheet".to_string(),
+        trimmed => trimmed.to_string(),
+    };
+
+    let is_taken = |name: &str| existing.iter().any(|e| e.eq_ignore_ascii_case(name));
+    let mut unique = base.clone();
+    let mut i = 2;
+    while is_taken(&unique) {
+        let suffix = format!(" ({i})");
+        let truncated = base
+            .chars()
+            .take(MAX_SHEET_NAME_LENGTH - suffix.len())
+            .collect::<String>();
+        unique = format!("{truncated}{suffix}");
+        i += 1;
+    }
+    unique
+}
+
+fn sheet_offset(sheet: &Sheet) -> Pos {
+    match sheet.bounds(false) {
+        GridBounds::NonEmpty(rect) => Pos {
+            x: (-rect.min.x).max(0),
+            y: (1 - rect.min.y).max(0),
+        },
+        GridBounds::Empty => Pos { x: 0, y: 1 },
+    }
+}
+
+/// Returns the A1 reference of a (shifted) position, or `None` if it is
+/// outside of what Excel supports.
+fn excel_cell_name(x: i64, y: i64) -> Option<String> {
+    ((0..MAX_COLUMNS).contains(&x) && (1..=MAX_ROWS).contains(&y))
+        .then(|| format!("{}{y}", column_name(x)))
+}
+
+/// Returns a sheet name as used in an Excel formula, quoting it if needed.
+fn excel_sheet_prefix(name: &str) -> String {
+    let is_plain = name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && parse_cell_ref(name).is_none();
+    if is_plain {
+        format!("{name}!")
+    } else {
+        format!("'{}'!", name.replace('\'', "''"))
+    }
+}
+
+/// Converts a Quadratic reference in a formula at `base` (on `sheet_name`)
+/// to an Excel reference.
+fn excel_reference(
+    range_ref: RangeRef,
+    base: Pos,
+    sheet_name: &str,
+    sheets: &HashMap<String, ExcelSheet>,
+) -> String {
+    let sheet_of =
+        |sheet: &Option<String>| sheets.get(&sheet.as_deref().unwrap_or(sheet_name).to_lowercase());
+    let prefix = |sheet: &Option<String>| match (sheet, sheet_of(sheet)) {
+        (Some(_), Some(excel_sheet)) => Some(excel_sheet_prefix(&excel_sheet.name)),
+        (Some(_), None) => None,
+        (None, _) => Some(String::new()),
+    };
+    let dollar = |coord: CellRefCoord| match coord {
+        CellRefCoord::Absolute(_) => "$",
+        CellRefCoord::Relative(_) => "",
+    };
+    let column = |coord: CellRefCoord, offset: i64| {
+        let x = coord.resolve_from(base.x) + offset;
+        (0..MAX_COLUMNS)
+            .contains(&x)
+            .then(|| format!("{}{}", dollar(coord), column_name(x)))
+    };
+    let row = |coord: CellRefCoord, offset: i64| {
+        let y = coord.resolve_from(base.y) + offset;
+        (1..=MAX_ROWS)
+            .contains(&y)
+            .then(|| format!("{}{y}", dollar(coord)))
+    };
+    let cell = |cell_ref: &CellRef, offset: Pos| {
+        Some(format!(
+            "{}{}",
+            column(cell_ref.x, offset.x)?,
+            row(cell_ref.y, offset.y)?
+        ))
+    };
+
+    let reference = match &range_ref {
+        RangeRef::Cell { pos } => sheet_of(&pos.sheet)
+            .and_then(|s| Some(format!("{}{}", prefix(&pos.sheet)?, cell(pos, s.offset)?))),
+        RangeRef::CellRange { start, end } => sheet_of(&start.sheet).and_then(|s| {
+            Some(format!(
+                "{}{}:{}",
+                prefix(&start.sheet)?,
+                cell(start, s.offset)?,
+                cell(end, s.offset)?
+            ))
+        }),
+        RangeRef::ColRange { start, end, sheet } => sheet_of(sheet).and_then(|s| {
+            Some(format!(
+                "{}{}:{}",
+                prefix(sheet)?,
+                column(*start, s.offset.x)?,
+                column(*end, s.offset.x)?
+            ))
+        }),
+        RangeRef::RowRange { start, end, sheet } => sheet_of(sheet).and_then(|s| {
+            Some(format!(
+                "{}{}:{}",
+                prefix(sheet)?,
+                row(*start, s.offset.y)?,
+                row(*end, s.offset.y)?
+            ))
+        }),
+    };
+    reference.unwrap_or_else(|| "#REF!".to_string())
+}
+
+fn write_sheet(
+    sheet: &Sheet,
+    excel_sheets: &HashMap<String, ExcelSheet>,
+    styles: &mut StylesWriter,
+) -> String {
+    let offset = sheet_offset(sheet);
+    let (column_widths, row_heights) = sheet.offsets.export();
+
+    let mut rows: HashMap<i64, String> = HashMap::new();
+    let mut custom_row_heights = HashMap::new();
+    for (y, height) in row_heights {
+        let y = y + offset.y;
+        if (1..=MAX_ROWS).contains(&y) && height != DEFAULT_ROW_HEIGHT {
+            custom_row_heights.insert(y, height);
+        }
+    }
+
+    if let GridBounds::NonEmpty(rect) = sheet.bounds(false) {
+        let mut borders = sheet.borders().per_cell.clone();
+        for y in rect.y_range() {
+            for x in rect.x_range() {
+                let pos = Pos { x, y };
+                let Some(reference) = excel_cell_name(x + offset.x, y + offset.y) else {
+                    continue;
+                };
+
+                let value = sheet.display_value(pos);
+                let formula = match sheet.cell_value(pos) {
+                    Some(CellValue::Code(code)) if code.language == CodeCellLanguage::Formula => {
+                        let replace_fn =
+                            |range_ref| excel_reference(range_ref, pos, &sheet.name, excel_sheets);
+                        Some(to_excel_formula(&code.code, pos, &replace_fn))
+                    }
+                    _ => None,
+                };
+                let array_ref = formula.as_ref().and_then(|_| {
+                    let code_run = sheet.code_runs.get(&pos)?;
+                    let output = code_run.output_rect(pos, false);
+                    (!code_run.spill_error && output.len() > 1).then(|| {
+                        let min = output.min;
+                        let max = output.max;
+                        let start = excel_cell_name(min.x + offset.x, min.y + offset.y)?;
+                        let end = excel_cell_name(max.x + offset.x, max.y + offset.y)?;
+                        Some(format!("{start}:{end}"))
+                    })?
+                });
+
+                let format = sheet.format_cell(x, y, true);
+                let cell_borders = borders.get_cell_border(pos);
+                let style = styles.cell_style(&format, cell_borders.as_ref(), value.as_ref());
+
+                let mut cell = String::new();
+                write_cell_contents(&mut cell, value.as_ref(), formula.as_deref(), array_ref);
+                if cell.is_empty() && style == 0 {
+                    continue;
+                }
+
+                let row = rows.entry(y + offset.y).or_default();
+                let _ = write!(row, r#"<c r="{reference}""#);
+                if style != 0 {
+                    let _ = write!(row, r#" s="{style}""#);
+                }
+                row.push_str(&cell);
+            }
+        }
+    }
+
+    let mut xml = String::from(XML_DECLARATION);
+    let _ = write!(
+        xml,
+        r#"<worksheet xmlns="{MAIN_NS}" xmlns:r="{RELATIONSHIPS_NS}">"#
+    );
+    if let Some(color) = sheet.color.as_deref().and_then(excel_color) {
+        let _ = write!(xml, r#"<sheetPr><tabColor rgb="{color}"/></sheetPr>"#);
+    }
+    let _ = write!(
+        xml,
+        r#"<sheetFormatPr defaultColWidth="{}" defaultRowHeight="{}" customHeight="1"/>"#,
+        excel_column_width(DEFAULT_COLUMN_WIDTH),
+        excel_row_height(DEFAULT_ROW_HEIGHT)
+    );
+
+    let mut columns = column_widths
+        .into_iter()
+        .map(|(x, width)| (x + offset.x, width))
+        .filter(|(x, width)| (0..MAX_COLUMNS).contains(x) && *width != DEFAULT_COLUMN_WIDTH)
+        .collect::<Vec<_>>();
+    columns.sort_by_key(|(x, _)| *x);
+    if !columns.is_empty() {
+        xml.push_str("<cols>");
+        for (x, width) in columns {
+            let _ = write!(
+                xml,
+                r#"<col min="{0}" max="{0}" width="{1}" customWidth="1"/>"#,
+                x + 1,
+                excel_column_width(width)
+            );
+        }
+        xml.push_str("</cols>");
+    }
+
+    let mut row_numbers = rows
+        .keys()
+        .chain(custom_row_heights.keys())
+        .copied()
+        .collect::<Vec<_>>();
+    row_numbers.sort_unstable();
+    row_numbers.dedup();
+
+    xml.push_str("<sheetData>");
+    for y in row_numbers {
+        let _ = write!(xml, r#"<row r="{y}""#);
+        if let Some(height) = custom_row_heights.get(&y) {
+            let _ = write!(
+                xml,
+                r#" ht="{}" customHeight="1""#,
+                excel_row_height(*height)
+            );
+        }
+        match rows.get(&y) {
+            Some(cells) => {
+                let _ = write!(xml, ">{cells}</row>");
+            }
+            None => xml.push_str("/>"),
+        }
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Writes the type, formula, and value of a cell after its `<c r=".." s=".."`
+/// attributes. Writes nothing for a blank cell without a formula.
+fn write_cell_contents(
+    xml: &mut String,
+    value: Option<&CellValue>,
+    formula: Option<&str>,
+    array_ref: Option<String>,
+) {
+    let (cell_type, cached) = match value {
+        Some(CellValue::Text(text)) if formula.is_some() => (Some("str"), Some(escape(text))),
+        Some(CellValue::Text(text)) => {
+            let _ = write!(
+                xml,
+                r#" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                escape(text)
+            );
+            return;
+        }
+        Some(CellValue::Logical(b)) => (Some("b"), Some(u8::from(*b).to_string().into())),
+        Some(CellValue::Error(error)) => (Some("e"), Some(excel_error(&error.msg).into())),
+        Some(value) => (
+            None,
+            value
+                .as_serial()
+                .filter(|n| n.is_finite())
+                .map(|n| n.to_string().into()),
+        ),
+        None => (None, None),
+    };
+
+    if formula.is_none() && cached.is_none() {
+        return;
+    }
+    if let Some(cell_type) = cell_type {
+        let _ = write!(xml, r#" t="{cell_type}""#);
+    }
+    xml.push('>');
+    if let Some(formula) = formula {
+        match array_ref {
+            Some(array_ref) => {
+                let _ = write!(xml, r#"<f t="array" ref="{array_ref}">"#);
+            }
+            None => xml.push_str("<f>"),
+        }
+        let _ = write!(xml, "{}</f>", escape(formula));
+    }
+    if let Some(cached) = cached {
+        let _ = write!(xml, "<v>{cached}</v>");
+    }
+    xml.push_str("</c>");
+}
+
+fn excel_error(msg: &RunErrorMsg) -> &'static str {
+    match msg {
+        RunErrorMsg::DivideByZero => "#DIV/0!",
+        RunErrorMsg::NoMatch => "#N/A",
+        RunErrorMsg::BadFunctionName => "#NAME?",
+        RunErrorMsg::BadCellReference => "#REF!",
+        RunErrorMsg::Overflow | RunErrorMsg::NotANumber | RunErrorMsg::Infinity => "#NUM!",
+        RunErrorMsg::Spill => "#SPILL!",
+        _ => "#VALUE!",
+    }
+}
+
+/// Converts a width in pixels to Excel's width in characters (of the default
+/// font, which is 7px wide with 5px of padding).
+fn excel_column_width(px: f64) -> f64 {
+    (((px - 5.0) / 7.0).max(0.0) * 100.0).round() / 100.0
+}
+
+/// Converts a height in pixels to points.
+fn excel_row_height(px: f64) -> f64 {
+    px * 0.75
+}
+
+/// Converts a CSS color (`#rrggbb` or `rgb(r, g, b)`) to Excel's ARGB hex.
+fn excel_color(color: &str) -> Option<String> {
+    let rgba = if color.starts_with('#') {
+        Rgba::color_from_str(color).ok()?
+    } else {
+        Rgba::from_css_str(color).ok()?
+    };
+    Some(format!(
+        "FF{:02X}{:02X}{:02X}",
+        rgba.red, rgba.green, rgba.blue
+    ))
+}
+
+fn excel_border_style(line: CellBorderLine) -> &'static str {
+    match line {
+        CellBorderLine::Line1 => "thin",
+        CellBorderLine::Line2 => "medium",
+        CellBorderLine::Line3 => "thick",
+        CellBorderLine::Dotted => "dotted",
+        CellBorderLine::Dashed => "dashed",
+        CellBorderLine::Double => "double",
+    }
+}
+
+/// Returns the Excel number format code for a cell's format, or `None` for
+/// General. Dates and durations without a format get a default one, since
+/// Excel stores them as plain numbers.
+fn number_format_code(format: &Format, value: Option<&CellValue>) -> Option<String> {
+    let numeric_format = format.numeric_format.as_ref();
+    if let Some(code) = numeric_format.and_then(NumericFormat::format_code_str) {
+        return Some(code);
+    }
+
+    let kind = numeric_format.map(|f| f.kind);
+    match (kind, value) {
+        (None, Some(CellValue::Instant(instant))) => {
+            let code = if instant.is_date() {
+                "yyyy-mm-dd"
+            } else {
+                "yyyy-mm-dd hh:mm:ss"
+            };
+            return Some(code.to_string());
+        }
+        (None, Some(CellValue::Duration(_))) => return Some("[h]:mm:ss".to_string()),
+        _ => {}
+    }
+
+    let commas = format
+        .numeric_commas
+        .unwrap_or(kind == Some(NumericFormatKind::Currency));
+    let integer = if commas { "#,##0" } else { "0" };
+    let decimals = |default: Option<i16>| match format.numeric_decimals.or(default) {
+        Some(decimals) if decimals > 0 => format!(".{}", "0".repeat(decimals as usize)),
+        _ => String::new(),
+    };
+
+    match kind {
+        None | Some(NumericFormatKind::Number) => {
+            if format.numeric_decimals.is_none() && !commas {
+                return None;
+            }
+            Some(format!("{integer}{}", decimals(None)))
+        }
+        Some(NumericFormatKind::Currency) => {
+            let symbol = numeric_format
+                .and_then(|f| f.symbol.as_deref())
+                .unwrap_or("$")
+                .replace('"', "");
+            Some(format!("\"{symbol}\"{integer}{}", decimals(Some(2))))
+        }
+        Some(NumericFormatKind::Percentage) => Some(format!("0{}%", decimals(None))),
+        Some(NumericFormatKind::Exponential) => Some(format!("0{}E+00", decimals(Some(2)))),
+        Some(_) => None,
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+struct FontStyle {
+    bold: bool,
+    italic: bool,
+    color: Option<String>,
+}
+
+/// Border style and ARGB color of each side, in Excel's order: left, right,
+/// top, bottom.
+type BorderSides = [Option<(&'static str, String)>; 4];
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+struct CellXf {
+    num_fmt_id: u32,
+    font_id: usize,
+    fill_id: usize,
+    border_id: usize,
+    horizontal: Option<&'static str>,
+    wrap: bool,
+}
+
+/// Collects the deduplicated styles used by cells and writes styles.xml.
+struct StylesWriter {
+    /// Custom number format codes; the id of each is its index plus
+    /// [`FIRST_CUSTOM_NUM_FMT_ID`].
+    num_fmts: IndexSet<String>,
+    fonts: IndexSet<FontStyle>,
+
+    /// Solid fill colors. The first two fills are reserved by Excel.
+    fills: IndexSet<Option<String>>,
+    borders: IndexSet<BorderSides>,
+    cell_xfs: IndexSet<CellXf>,
+}
+
+impl StylesWriter {
+    fn new() -> Self {
+        let mut fills = IndexSet::new();
+        fills.insert(None);
+        fills.insert(Some(String::new()));
+        Self {
+            num_fmts: IndexSet::new(),
+            fonts: IndexSet::from([FontStyle::default()]),
+            fills,
+            borders: IndexSet::from([BorderSides::default()]),
+            cell_xfs: IndexSet::from([CellXf::default()]),
+        }
+    }
+
+    /// Returns the style index (`s` attribute) of a cell.
+    fn cell_style(
+        &mut self,
+        format: &Format,
+        borders: Option<&CellBorders>,
+        value: Option<&CellValue>,
+    ) -> usize {
+        let num_fmt_id = match number_format_code(format, value) {
+            None => 0,
+            Some(code) => (0..FIRST_CUSTOM_NUM_FMT_ID)
+                .find(|&id| builtin_num_fmt(id) == Some(code.as_str()))
+                .unwrap_or_else(|| {
+                    FIRST_CUSTOM_NUM_FMT_ID + self.num_fmts.insert_full(code).0 as u32
+                }),
+        };
+
+        let font = FontStyle {
+            bold: format.bold.unwrap_or(false),
+            italic: format.italic.unwrap_or(false),
+            color: format.text_color.as_deref().and_then(excel_color),
+        };
+        let fill = format.fill_color.as_deref().and_then(excel_color);
+
+        let mut sides = BorderSides::default();
+        if let Some(borders) = borders {
+            // Quadratic's order is left, top, right, bottom
+            for (excel_side, side) in [0, 2, 1, 3].into_iter().enumerate() {
+                sides[excel_side] = borders.borders[side].map(|style| {
+                    let color = format!(
+                        "FF{:02X}{:02X}{:02X}",
+                        style.color.red, style.color.green, style.color.blue
+                    );
+                    (excel_border_style(style.line), color)
+                });
+            }
+        }
+
+        let xf = CellXf {
+            num_fmt_id,
+            font_id: self.fonts.insert_full(font).0,
+            fill_id: self.fills.insert_full(fill).0,
+            border_id: self.borders.insert_full(sides).0,
+            horizontal: format.align.map(|align| match align {
+                CellAlign::Left => "left",
+                CellAlign::Center => "center",
+                CellAlign::Right => "right",
+            }),
+            wrap: format.wrap == Some(CellWrap::Wrap),
+        };
+        self.cell_xfs.insert_full(xf).0
+    }
+
+    fn to_xml(&self) -> String {
+        let mut xml = String::from(XML_DECLARATION);
+        let _ = write!(xml, r#"<styleSheet xmlns="{MAIN_NS}">"#);
+
+        if !self.num_fmts.is_empty() {
+            let _ = write!(xml, r#"<numFmts count="{}">"#, self.num_fmts.len());
+            for (i, code) in self.num_fmts.iter().enumerate() {
+                let _ = write!(
+                    xml,
+                    r#"<numFmt numFmtId="{}" formatCode="{}"/>"#,
+                    FIRST_CUSTOM_NUM_FMT_ID + i as u32,
+                    escape(code)
+                );
+            }
+            xml.push_str("</numFmts>");
+        }
+
+        let _ = write!(xml, r#"<fonts count="{}">"#, self.fonts.len());
+        for font in &self.fonts {
+            xml.push_str("<font>");
+            if font.bold {
+                xml.push_str("<b/>");
+            }
+            if font.italic {
+                xml.push_str("<i/>");
+            }
+            xml.push_str(r#"<sz val="11"/>"#);
+            if let Some(color) = &font.color {
+                let _ = write!(xml, r#"<color rgb="{color}"/>"#);
+            }
+            xml.push_str(r#"<name val="Calibri"/><family val="2"/></font>"#);
+        }
+        xml.push_str("</fonts>");
+
+        let _ = write!(xml, r#"<fills count="{}">"#, self.fills.len());
+        for (i, fill) in self.fills.iter().enumerate() {
+            match (i, fill) {
+                (0, _) => xml.push_str(r#"<fill><patternFill patternType="none"/></fill>"#),
+                (1, _) => xml.push_str(r#"<fill><patternFill patternType="gray125"/></fill>"#),
+                (_, Some(color)) => {
+                    let _ = write!(
+                        xml,
+                        r#"<fill><patternFill patternType="solid"><fgColor rgb="{color}"/><bgColor indexed="64"/></patternFill></fill>"#
+                    );
+                }
+                (_, None) => xml.push_str(r#"<fill><patternFill patternType="none"/></fill>"#),
+            }
+        }
+        xml.push_str("</fills>");
+
+        let _ = write!(xml, r#"<borders count="{}">"#, self.borders.len());
+        for sides in &self.borders {
+            xml.push_str("<border>");
+            for (name, side) in ["left", "right", "top", "bottom"].iter().zip(sides) {
+                match side {
+                    Some((style, color)) => {
+                        let _ = write!(
+                            xml,
+                            r#"<{name} style="{style}"><color rgb="{color}"/></{name}>"#
+                        );
+                    }
+                    None => {
+                        let _ = write!(xml, "<{name}/>");
+                    }
+                }
+            }
+            xml.push_str("<diagonal/></border>");
+        }
+        xml.push_str("</borders>");
+
+        xml.push_str(r#"<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>"#);
+
+        let _ = write!(xml, r#"<cellXfs count="{}">"#, self.cell_xfs.len());
+        for xf in &self.cell_xfs {
+            let _ = write!(
+                xml,
+                r#"<xf numFmtId="{}" fontId="{}" fillId="{}" borderId="{}" xfId="0""#,
+                xf.num_fmt_id, xf.font_id, xf.fill_id, xf.border_id
+            );
+            for (applied, attribute) in [
+                (xf.num_fmt_id != 0, "applyNumberFormat"),
+                (xf.font_id != 0, "applyFont"),
+                (xf.fill_id != 0, "applyFill"),
+                (xf.border_id != 0, "applyBorder"),
+            ] {
+                if applied {
+                    let _ = write!(xml, r#" {attribute}="1""#);
+                }
+            }
+            if xf.horizontal.is_none() && !xf.wrap {
+                xml.push_str("/>");
+                continue;
+            }
+            xml.push_str(r#" applyAlignment="1"><alignment"#);
+            if let Some(horizontal) = xf.horizontal {
+                let _ = write!(xml, r#" horizontal="{horizontal}""#);
+            }
+            if xf.wrap {
+                xml.push_str(r#" wrapText="1""#);
+            }
+            xml.push_str("/></xf>");
+        }
+        xml.push_str("</cellXfs>");
+
+        xml.push_str(r#"<cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles>"#);
+        xml.push_str("</styleSheet>");
+        xml
+    }
+}
+
+fn content_types(sheet_count: usize) -> String {
+    let mut xml = String::from(XML_DECLARATION);
+    xml.push_str(r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#);
+    xml.push_str(r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#);
+    xml.push_str(r#"<Default Extension="xml" ContentType="application/xml"/>"#);
+    xml.push_str(r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#);
+    xml.push_str(r#"<Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#);
+    for i in 1..=sheet_count {
+        let _ = write!(
+            xml,
+            r#"<Override PartName="/xl/worksheets/sheet{i}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+        );
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+fn root_relationships() -> String {
+    format!(
+        r#"{XML_DECLARATION}<Relationships xmlns="{PACKAGE_RELATIONSHIPS_NS}"><Relationship Id="rId1" Type="{RELATIONSHIPS_NS}/officeDocument" Target="xl/workbook.xml"/></Relationships>"#
+    )
+}
+
+fn workbook(sheet_names: &[String]) -> String {
+    let mut xml = String::from(XML_DECLARATION);
+    let _ = write!(
+        xml,
+        r#"<workbook xmlns="{MAIN_NS}" xmlns:r="{RELATIONSHIPS_NS}"><sheets>"#
+    );
+    for (i, name) in sheet_names.iter().enumerate() {
+        let _ = write!(
+            xml,
+            r#"<sheet name="{}" sheetId="{id}" r:id="rId{id}"/>"#,
+            escape(name),
+            id = i + 1
+        );
+    }
+    xml.push_str("</sheets></workbook>");
+    xml
+}
+
+/// Sheets are relationships rId1..rIdN, followed by styles.
+fn workbook_relationships(sheet_count: usize) -> String {
+    let mut xml = String::from(XML_DECLARATION);
+    let _ = write!(xml, r#"<Relationships xmlns="{PACKAGE_RELATIONSHIPS_NS}">"#);
+    for i in 1..=sheet_count {
+        let _ = write!(
+            xml,
+            r#"<Relationship Id="rId{i}" Type="{RELATIONSHIPS_NS}/worksheet" Target="worksheets/sheet{i}.xml"/>"#
+        );
+    }
+    let _ = write!(
+        xml,
+        r#"<Relationship Id="rId{}" Type="{RELATIONSHIPS_NS}/styles" Target="styles.xml"/>"#,
+        sheet_count + 1
+    );
+    xml.push_str("</Relationships>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_sheet_names() {
+        let existing = vec!["Sheet 1".to_string()];
+        assert_eq!(unique_sheet_name("Data [2024]", &existing), "Data _2024_");
+        assert_eq!(unique_sheet_name("sheet 1", &existing), "sheet 1 (2)");
+        assert_eq!(unique_sheet_name("''", &existing), "Sheet");
+        assert_eq!(unique_sheet_name(&"a".repeat(40), &existing).len(), 31);
+    }
+
+    #[test]
+    fn quotes_sheet_prefixes() {
+        assert_eq!(excel_sheet_prefix("Data"), "Data!");
+        assert_eq!(excel_sheet_prefix("Sheet 1"), "'Sheet 1'!");
+        assert_eq!(excel_sheet_prefix("A1"), "'A1'!");
+        assert_eq!(excel_sheet_prefix("it's"), "'it''s'!");
+    }
+
+    #[test]
+    fn converts_number_formats() {
+        let format = |kind, symbol: Option<&str>, decimals, commas| Format {
+            numeric_format: Some(NumericFormat {
+                kind,
+                symbol: symbol.map(String::from),
+            }),
+            numeric_decimals: decimals,
+            numeric_commas: commas,
+            ..Default::default()
+        };
+        assert_eq!(
+            number_format_code(
+                &format(NumericFormatKind::Currency, Some("€"), None, None),
+                None
+            ),
+            Some("\"€\"#,##0.00".to_string())
+        );
+        assert_eq!(
+            number_format_code(
+                &format(NumericFormatKind::Percentage, None, Some(1), None),
+                None
+            ),
+            Some("0.0%".to_string())
+        );
+        assert_eq!(
+            number_format_code(
+                &format(NumericFormatKind::Number, None, Some(2), Some(true)),
+                None
+            ),
+            Some("#,##0.00".to_string())
+        );
+        assert_eq!(
+            number_format_code(
+                &format(NumericFormatKind::Custom, Some("0.0;(0.0)"), None, None),
+                None
+            ),
+            Some("0.0;(0.0)".to_string())
+        );
+        assert_eq!(number_format_code(&Format::default(), None), None);
+    }
+
+    #[test]
+    fn converts_sizes_and_colors() {
+        assert_eq!(excel_column_width(100.0), 13.57);
+        assert_eq!(excel_row_height(20.0), 15.0);
+        assert_eq!(excel_color("#ff0000"), Some("FFFF0000".to_string()));
+        assert_eq!(
+            excel_color("rgb(0, 128, 255)"),
+            Some("FF0080FF".to_string())
+        );
+        assert_eq!(excel_color("red"), None);
+    }
+}
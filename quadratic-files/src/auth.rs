@@ -0,0 +1,123 @@
+//! Authentication
+//!
+//! Requests to the file endpoints carry the user's Auth0 JWT as a bearer
+//! token.  The token is validated against the JWKS, and is passed on to the
+//! Quadratic API to get the user's permissions for a file.
+
+use std::sync::Arc;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts, Extension};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use quadratic_rust_shared::{
+    auth::jwt::authorize,
+    quadratic_api::{can_edit, can_view, get_file_perms, FilePermRole},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    error::{FilesError, Result},
+    state::State,
+};
+
+/// The claims from the Quadratic/Auth0 JWT token.  The token's expiration is
+/// checked when it is authorized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Claims {
+    pub(crate) sub: String,
+}
+
+/// An authenticated user and the token they authenticated with.
+#[derive(Debug, Clone)]
+pub(crate) struct User {
+    pub(crate) claims: Claims,
+    pub(crate) jwt: String,
+}
+
+impl User {
+    pub(crate) fn user_id(&self) -> &str {
+        &self.claims.sub
+    }
+
+    /// Get the user's permissions for a file from the Quadratic API.
+    async fn permissions(&self, state: &State, file_id: Uuid) -> Result<Vec<FilePermRole>> {
+        if cfg!(test) {
+            return Ok(vec![FilePermRole::FileView, FilePermRole::FileEdit]);
+        }
+
+        let (permissions, _) = get_file_perms(
+            &state.settings.quadratic_api_uri,
+            self.jwt.to_owned(),
+            file_id,
+        )
+        .await?;
+
+        Ok(permissions)
+    }
+
+    /// Returns an error if the user cannot view the file.
+    pub(crate) async fn check_can_view(&self, state: &State, file_id: Uuid) -> Result<()> {
+        if !can_view(&self.permissions(state, file_id).await?) {
+            return Err(FilesError::FilePermissions(format!(
+                "user {} cannot view file {file_id}",
+                self.user_id()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if the user cannot edit the file.
+    pub(crate) async fn check_can_edit(&self, state: &State, file_id: Uuid) -> Result<()> {
+        if !can_edit(&self.permissions(state, file_id).await?) {
+            return Err(FilesError::FilePermissions(format!(
+                "user {} cannot edit file {file_id}",
+                self.user_id()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Extract the user from the request.  Anytime a user parameter is added to
+/// a handler, the request's JWT is validated.
+#[async_trait]
+impl<S> FromRequestParts<S> for User
+where
+    S: Send + Sync,
+{
+    type Rejection = FilesError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        // Extract the token from the authorization header
+        let TypedHeader(Authorization(bearer)) =
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
+                .await
+                .map_err(|e| FilesError::Authentication(e.to_string()))?;
+        let jwt = bearer.token().to_owned();
+
+        if cfg!(test) {
+            let claims = Claims {
+                sub: "test-user".into(),
+            };
+            return Ok(User { claims, jwt });
+        }
+
+        let Extension(state) = Extension::<Arc<State>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| FilesError::InternalServer(e.to_string()))?;
+        let jwks = state
+            .settings
+            .jwks
+            .as_ref()
+            .ok_or(FilesError::InternalServer("JWKS not found in state".into()))?;
+
+        let claims = authorize::<Claims>(jwks, &jwt, false, true)?.claims;
+
+        Ok(User { claims, jwt })
+    }
+}
@@ -8,6 +8,8 @@
 use crate::error::{FilesError, Result};
 use dotenv::dotenv;
 use quadratic_rust_shared::environment::Environment;
+use quadratic_rust_shared::pubsub::PubSubType;
+use quadratic_rust_shared::storage::StorageType;
 use serde::Deserialize;
 
 #[allow(dead_code)]
@@ -21,18 +23,38 @@ pub(crate) struct Config {
     pub(crate) truncate_transaction_age_days: i64,
     pub(crate) environment: Environment,
 
+    // where transactions are queued: redis (default), or memory in tests
+    #[serde(default)]
+    pub(crate) pubsub_type: PubSubType,
+
+    #[serde(default)]
     pub(crate) pubsub_host: String,
+    #[serde(default)]
     pub(crate) pubsub_port: String,
+    #[serde(default)]
     pub(crate) pubsub_password: String,
     pub(crate) pubsub_active_channels: String,
     pub(crate) pubsub_processed_transactions_channel: String,
 
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
+    pub(crate) auth0_jwks_uri: String,
+
+    // where files are stored: s3 (default), file-system, or memory
+    #[serde(default)]
+    pub(crate) storage_type: StorageType,
 
+    // the directory for file-system storage
+    #[serde(default)]
+    pub(crate) storage_dir: String,
+
+    #[serde(default)]
     pub(crate) aws_s3_region: String,
+    #[serde(default)]
     pub(crate) aws_s3_bucket_name: String,
+    #[serde(default)]
     pub(crate) aws_s3_access_key_id: String,
+    #[serde(default)]
     pub(crate) aws_s3_secret_access_key: String,
 }
 
@@ -45,6 +67,11 @@ pub(crate) fn config() -> Result<Config> {
     dotenv().ok();
 
     let config = envy::from_env::<Config>().map_err(|e| FilesError::Config(e.to_string()))?;
+    config
+        .pubsub_type
+        .check_environment(&config.environment)
+        .map_err(|e| FilesError::Config(e.to_string()))?;
+
     Ok(config)
 }
 
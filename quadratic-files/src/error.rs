@@ -5,6 +5,8 @@
 //! Convert third party crate errors to application errors.
 //! Convert errors to responses.
 
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use quadratic_rust_shared::{Aws, SharedError};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -16,6 +18,9 @@ pub(crate) enum FilesError {
     #[error("Authentication error: {0}")]
     Authentication(String),
 
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
     #[error("Background service error: {0}")]
     BackgroundService(String),
 
@@ -28,6 +33,9 @@ pub(crate) enum FilesError {
     #[error("Unable to export file {0}: {1}")]
     ExportFile(String, String),
 
+    #[error("File permissions error: {0}")]
+    FilePermissions(String),
+
     #[error("Unable to import file {0}: {1}")]
     ImportFile(String, String),
 
@@ -37,6 +45,9 @@ pub(crate) enum FilesError {
     #[error("Unable to load file {0} from bucket {1}: {2}")]
     LoadFile(String, String, String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("PubSub error: {0}")]
     PubSub(String),
 
@@ -49,6 +60,9 @@ pub(crate) enum FilesError {
     #[error("Error serializing or deserializing: {0}")]
     Serialization(String),
 
+    #[error("Error in storage: {0}")]
+    Storage(String),
+
     #[error("Transaction queue error: {0}")]
     TransactionQueue(String),
 
@@ -59,13 +73,32 @@ pub(crate) enum FilesError {
     Unknown(String),
 }
 
+impl IntoResponse for FilesError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            FilesError::Authentication(_) => StatusCode::UNAUTHORIZED,
+            FilesError::FilePermissions(_) => StatusCode::FORBIDDEN,
+            FilesError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            FilesError::NotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        tracing::warn!("{} {:?}", status, self);
+
+        (status, self.to_string()).into_response()
+    }
+}
+
 impl From<SharedError> for FilesError {
     fn from(error: SharedError) -> Self {
         match error {
             SharedError::Aws(aws) => match aws {
                 Aws::S3(error) => FilesError::S3(error),
             },
+            SharedError::Auth(error) => FilesError::Authentication(error.to_string()),
             SharedError::PubSub(error) => FilesError::PubSub(error),
+            SharedError::QuadraticApi(error) => FilesError::FilePermissions(error),
+            SharedError::Storage(error) => FilesError::Storage(error.to_string()),
             _ => FilesError::Unknown(format!("Unknown Quadratic API error: {error}")),
         }
     }
@@ -7,17 +7,14 @@ use quadratic_core::{
         operations::operation::Operation, transaction::TransactionServer, GridController,
     },
     grid::{
-        file::{export_vec, import, CURRENT_VERSION},
+        file::{export_binary, import_bytes, CURRENT_BINARY_VERSION},
         Grid,
     },
 };
 use quadratic_rust_shared::{
-    aws::{
-        s3::{download_object, upload_object},
-        Client,
-    },
     pubsub::PubSub as PubSubTrait,
     quadratic_api::{get_file_checkpoint, set_file_checkpoint},
+    storage::{Storage, StorageContainer},
 };
 
 use crate::{
@@ -28,14 +25,14 @@ use crate::{
 
 pub static GROUP_NAME: &str = "quadratic-file-service-1";
 
-/// Load a .grid file
-pub(crate) fn load_file(key: &str, file: &str) -> Result<Grid> {
-    import(file).map_err(|e| FilesError::ImportFile(key.into(), e.to_string()))
+/// Load a .grid file (binary or JSON)
+pub(crate) fn load_file(key: &str, file: &[u8]) -> Result<Grid> {
+    import_bytes(file).map_err(|e| FilesError::ImportFile(key.into(), e.to_string()))
 }
 
-/// Exports a .grid file
+/// Exports a .grid file in the binary format
 pub(crate) fn export_file(key: &str, grid: &mut Grid) -> Result<Vec<u8>> {
-    export_vec(grid).map_err(|e| FilesError::ExportFile(key.into(), e.to_string()))
+    export_binary(grid).map_err(|e| FilesError::ExportFile(key.into(), e.to_string()))
 }
 
 /// Apply a vec of operations to the grid
@@ -45,21 +42,14 @@ pub(crate) fn apply_transaction(grid: &mut GridController, operations: Vec<Opera
 
 /// Exports a .grid file
 pub(crate) async fn get_and_load_object(
-    client: &Client,
-    bucket: &str,
+    storage: &StorageContainer,
     key: &str,
     sequence_num: u64,
 ) -> Result<GridController> {
-    let file = download_object(client, bucket, key).await?;
-    let body = file
-        .body
-        .collect()
-        .await
-        .map_err(|e| FilesError::LoadFile(key.into(), bucket.to_string(), e.to_string()))?
-        .into_bytes();
-    let body = std::str::from_utf8(&body)
-        .map_err(|e| FilesError::LoadFile(key.into(), bucket.to_string(), e.to_string()))?;
-    let grid = load_file(key, body)?;
+    let body = storage.read(key).await.map_err(|e| {
+        FilesError::LoadFile(key.into(), storage.location().to_string(), e.to_string())
+    })?;
+    let grid = load_file(key, &body)?;
 
     Ok(GridController::from_grid(grid, sequence_num))
 }
@@ -68,18 +58,16 @@ pub(crate) fn key(file_id: Uuid, sequence: u64) -> String {
     format!("{file_id}-{sequence}.grid")
 }
 
-/// Load a file from S3, add it to memory, process transactions and upload it back to S3
+/// Load a file from storage, add it to memory, process transactions and write it back to storage
 pub(crate) async fn process_transactions(
-    client: &Client,
-    bucket: &str,
+    storage: &StorageContainer,
     file_id: Uuid,
     checkpoint_sequence_num: u64,
     final_sequence_num: u64,
     operations: Vec<Operation>,
 ) -> Result<u64> {
     let mut grid = get_and_load_object(
-        client,
-        bucket,
+        storage,
         &key(file_id, checkpoint_sequence_num),
         checkpoint_sequence_num,
     )
@@ -90,7 +78,7 @@ pub(crate) async fn process_transactions(
     apply_transaction(&mut grid, operations);
     let body = export_file(&key, grid.grid_mut())?;
 
-    upload_object(client, bucket, &key, &body).await?;
+    storage.write(&key, &body).await?;
 
     Ok(final_sequence_num)
 }
@@ -105,8 +93,7 @@ pub(crate) async fn process_queue_for_room(
     let channel = &file_id.to_string();
 
     let Settings {
-        aws_client,
-        aws_s3_bucket_name,
+        storage,
         quadratic_api_uri,
         m2m_auth_token,
         ..
@@ -118,7 +105,7 @@ pub(crate) async fn process_queue_for_room(
             Err(_) => 0,
         };
 
-    // this is an expensive lock since we're waiting for the file to write to S3 before unlocking
+    // this is an expensive lock since we're waiting for the file to write to storage before unlocking
     let mut pubsub = state.pubsub.lock().await;
 
     // subscribe to the channel
@@ -171,10 +158,9 @@ pub(crate) async fn process_queue_for_room(
         })
         .collect::<Vec<Operation>>();
 
-    // process the transactions and save the file to S3
+    // process the transactions and save the file to storage
     let last_sequence_num = process_transactions(
-        aws_client,
-        aws_s3_bucket_name,
+        storage,
         *file_id,
         checkpoint_sequence_num,
         last_sequence_num,
@@ -204,9 +190,9 @@ pub(crate) async fn process_queue_for_room(
         m2m_auth_token,
         file_id,
         last_sequence_num,
-        CURRENT_VERSION.into(),
+        CURRENT_BINARY_VERSION.into(),
         key.to_owned(),
-        aws_s3_bucket_name.to_owned(),
+        storage.location().to_owned(),
     )
     .await?;
 
@@ -272,7 +258,7 @@ mod tests {
         // load the file
         let mut file = load_file(
             key,
-            include_str!("../../quadratic-rust-shared/data/grid/v1_4_simple.grid"),
+            include_bytes!("../../quadratic-rust-shared/data/grid/v1_4_simple.grid"),
         )
         .unwrap();
 
@@ -305,8 +291,8 @@ mod tests {
             Some(CellValue::Text("hello".to_string()))
         );
 
-        let grid = export_file(key, &mut file);
-        assert!(grid.is_ok());
+        let grid = export_file(key, &mut file).unwrap();
+        assert!(load_file(key, &grid).is_ok());
     }
 
     #[tokio::test]
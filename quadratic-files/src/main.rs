@@ -3,6 +3,7 @@
 //! A file servic for that consumes transactions from a queue, applies them to
 //! a grid and writes them to S3.
 
+mod auth;
 mod config;
 mod error;
 mod file;
@@ -11,6 +12,7 @@ mod state;
 #[cfg(test)]
 mod test_util;
 mod truncate;
+mod version;
 
 use error::Result;
 
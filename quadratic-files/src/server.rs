@@ -5,7 +5,11 @@
 
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::{routing::get, Extension, Router};
+use axum::{
+    routing::{get, post},
+    Extension, Router,
+};
+use quadratic_rust_shared::auth::jwt::get_jwks;
 use std::time::Duration;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::time;
@@ -13,6 +17,10 @@ use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::truncate::truncate_processed_transactions;
+use crate::version::{
+    get_checkpoints_handler, get_file_at_handler, get_versions_handler, restore_version_handler,
+    tag_version_handler,
+};
 use crate::{
     config::config,
     error::{FilesError, Result},
@@ -28,6 +36,19 @@ pub(crate) fn app(state: Arc<State>) -> Router {
     Router::new()
         // routes
         .route("/health", get(healthcheck))
+        .route("/files/:file_id/checkpoints", get(get_checkpoints_handler))
+        .route(
+            "/files/:file_id/sequence/:sequence_num",
+            get(get_file_at_handler),
+        )
+        .route(
+            "/files/:file_id/versions",
+            get(get_versions_handler).post(tag_version_handler),
+        )
+        .route(
+            "/files/:file_id/versions/:name/restore",
+            post(restore_version_handler),
+        )
         // state
         .layer(Extension(state))
         // logger
@@ -49,7 +70,8 @@ pub(crate) async fn serve() -> Result<()> {
         .init();
 
     let config = config()?;
-    let state = Arc::new(State::new(&config).await?);
+    let jwks = get_jwks(&config.auth0_jwks_uri).await?;
+    let state = Arc::new(State::new(&config, Some(jwks)).await?);
     let app = app(Arc::clone(&state));
 
     let listener = tokio::net::TcpListener::bind(format!("{}:{}", config.host, config.port))
@@ -175,4 +197,44 @@ pub(crate) mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn requires_a_token_for_versions() {
+        let state = new_arc_state().await;
+        let app = app(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri(format!("/files/{}/versions", uuid::Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_version_without_a_name() {
+        let state = new_arc_state().await;
+        let app = app(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(format!("/files/{}/versions", uuid::Uuid::new_v4()))
+                    .header(http::header::AUTHORIZATION, "Bearer token")
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"name":"","sequence_num":1}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -7,8 +7,10 @@ pub mod pubsub;
 pub mod settings;
 pub mod stats;
 
+use jsonwebtoken::jwk::JwkSet;
+use quadratic_rust_shared::pubsub::memory::MemoryConfig;
 use quadratic_rust_shared::pubsub::redis_streams::RedisStreamsConfig;
-use quadratic_rust_shared::pubsub::Config as PubSubConfig;
+use quadratic_rust_shared::pubsub::{Config as PubSubConfig, PubSubType};
 use tokio::sync::Mutex;
 
 use crate::config::Config;
@@ -26,17 +28,22 @@ pub(crate) struct State {
 }
 
 impl State {
-    pub(crate) async fn new(config: &Config) -> Result<Self> {
-        let pubsub_config = PubSubConfig::RedisStreams(RedisStreamsConfig {
-            host: config.pubsub_host.to_owned(),
-            port: config.pubsub_port.to_owned(),
-            password: config.pubsub_password.to_owned(),
-            active_channels: config.pubsub_active_channels.to_owned(),
-        });
+    pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Result<Self> {
+        let pubsub_config = match config.pubsub_type {
+            PubSubType::Redis => PubSubConfig::RedisStreams(RedisStreamsConfig {
+                host: config.pubsub_host.to_owned(),
+                port: config.pubsub_port.to_owned(),
+                password: config.pubsub_password.to_owned(),
+                active_channels: config.pubsub_active_channels.to_owned(),
+            }),
+            PubSubType::Memory => PubSubConfig::Memory(MemoryConfig {
+                active_channels: config.pubsub_active_channels.to_owned(),
+            }),
+        };
 
         Ok(State {
             pubsub: Mutex::new(PubSub::new(pubsub_config).await?),
-            settings: Settings::new(config).await,
+            settings: Settings::new(config, jwks).await,
             stats: Mutex::new(Stats::new()),
         })
     }
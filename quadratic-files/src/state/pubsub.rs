@@ -1,13 +1,11 @@
-use quadratic_rust_shared::pubsub::{
-    redis_streams::RedisConnection, Config as PubSubConfig, PubSub as PubSubTrait,
-};
+use quadratic_rust_shared::pubsub::{Config as PubSubConfig, Connection, PubSub as PubSubTrait};
 
 use crate::error::Result;
 
 #[derive(Debug)]
 pub(crate) struct PubSub {
     pub(crate) config: PubSubConfig,
-    pub(crate) connection: RedisConnection,
+    pub(crate) connection: Connection,
 }
 
 impl PubSub {
@@ -18,8 +16,8 @@ impl PubSub {
     }
 
     /// Connect to the PubSub server
-    pub(crate) async fn connect(config: &PubSubConfig) -> Result<RedisConnection> {
-        let connection = RedisConnection::new(config.to_owned()).await?;
+    pub(crate) async fn connect(config: &PubSubConfig) -> Result<Connection> {
+        let connection = Connection::new(config.to_owned()).await?;
         Ok(connection)
     }
 
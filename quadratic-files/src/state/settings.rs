@@ -1,5 +1,9 @@
-use quadratic_rust_shared::aws::{client, Client};
+use jsonwebtoken::jwk::JwkSet;
 use quadratic_rust_shared::environment::Environment;
+use quadratic_rust_shared::storage::{
+    file_system::FileSystemConfig, s3::S3Config, Config as StorageConfig, StorageContainer,
+    StorageType,
+};
 
 use crate::config::Config;
 
@@ -7,27 +11,36 @@ use crate::config::Config;
 pub(crate) struct Settings {
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
-    pub(crate) aws_client: Client,
-    pub(crate) aws_s3_bucket_name: String,
+    pub(crate) jwks: Option<JwkSet>,
+    pub(crate) storage: StorageContainer,
     pub(crate) pubsub_processed_transactions_channel: String,
 }
 
 impl Settings {
-    pub(crate) async fn new(config: &Config) -> Self {
+    pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Self {
         let is_local =
             config.environment == Environment::Docker || config.environment == Environment::Local;
+
+        let storage_config = match config.storage_type {
+            StorageType::S3 => StorageConfig::S3(S3Config {
+                region: config.aws_s3_region.to_owned(),
+                access_key_id: config.aws_s3_access_key_id.to_owned(),
+                secret_access_key: config.aws_s3_secret_access_key.to_owned(),
+                bucket: config.aws_s3_bucket_name.to_owned(),
+                provider_name: "Quadratic File Service",
+                is_local,
+            }),
+            StorageType::FileSystem => StorageConfig::FileSystem(FileSystemConfig {
+                path: config.storage_dir.to_owned(),
+            }),
+            StorageType::Memory => StorageConfig::Memory,
+        };
+
         Settings {
             quadratic_api_uri: config.quadratic_api_uri.to_owned(),
             m2m_auth_token: config.m2m_auth_token.to_owned(),
-            aws_client: client(
-                &config.aws_s3_access_key_id,
-                &config.aws_s3_secret_access_key,
-                &config.aws_s3_region,
-                "Quadratic File Service",
-                is_local,
-            )
-            .await,
-            aws_s3_bucket_name: config.aws_s3_bucket_name.to_owned(),
+            jwks,
+            storage: StorageContainer::new(storage_config).await,
             pubsub_processed_transactions_channel: config
                 .pubsub_processed_transactions_channel
                 .to_owned(),
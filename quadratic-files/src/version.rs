@@ -0,0 +1,809 @@
+//! Version History
+//!
+//! Each time the queue is processed, a checkpoint of the file is saved to
+//! storage as `{file_id}-{sequence_num}.grid`.  A file is rebuilt at any
+//! sequence number by loading the checkpoint at or before it and replaying
+//! the transactions in between from the transaction queue.  Transactions are
+//! truncated from the queue after a few days, so only checkpoints remain
+//! available after that.
+//!
+//! Named versions point at a sequence number and are stored in PubSub.  A
+//! checkpoint is saved for each named version, so it outlives truncation.
+//!
+//! Restoring a version diffs the current file against it and publishes the
+//! operations as a new transaction, if the user can make those changes to
+//! the current file's protected ranges and sheets.  Multiplayer is told about
+//! the transaction, so users that have the file open receive it.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::header,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use quadratic_core::controller::{
+    edit_history::EditRecord,
+    operations::{diff::diff_operations, protection::check_protections},
+    transaction::{TransactionAuthor, TransactionServer},
+    GridController,
+};
+use quadratic_rust_shared::{
+    pubsub::{
+        broadcast::{Broadcast, BroadcastMessage, BROADCAST_CHANNEL},
+        edit_history,
+        memory::MemoryConnection,
+        redis::{RedisConfig, RedisConnection},
+        Config as PubSubConfig, PubSub as PubSubTrait,
+    },
+    storage::Storage,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    auth::User,
+    error::{FilesError, Result},
+    file::{apply_transaction, export_file, get_and_load_object, key},
+    state::State,
+};
+
+/// Versions are named by users, so keep their names short.
+pub(crate) const MAX_VERSION_NAME_LENGTH: usize = 100;
+
+/// How many times a restore is retried when other transactions are published
+/// while it is being made.
+const MAX_RESTORE_ATTEMPTS: usize = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Checkpoint {
+    pub(crate) sequence_num: u64,
+    pub(crate) key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Version {
+    pub(crate) name: String,
+    pub(crate) sequence_num: u64,
+    pub(crate) user_id: String,
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct TagVersion {
+    pub(crate) name: String,
+    pub(crate) sequence_num: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Restored {
+    // None if the file already matched the version
+    pub(crate) sequence_num: Option<u64>,
+}
+
+fn versions_key(file_id: &Uuid) -> String {
+    format!("{file_id}:versions")
+}
+
+/// Parse the sequence number from the key of one of the file's checkpoints.
+pub(crate) fn checkpoint_sequence_num(file_id: &Uuid, key: &str) -> Option<u64> {
+    key.strip_prefix(&format!("{file_id}-"))?
+        .strip_suffix(".grid")?
+        .parse::<u64>()
+        .ok()
+}
+
+/// Returns an error if a version's name is empty or too long.
+pub(crate) fn validate_version_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(FilesError::BadRequest("version name is empty".into()));
+    }
+
+    if name.chars().count() > MAX_VERSION_NAME_LENGTH {
+        return Err(FilesError::BadRequest(format!(
+            "version name is longer than {MAX_VERSION_NAME_LENGTH} characters"
+        )));
+    }
+
+    Ok(())
+}
+
+/// List the file's checkpoints in storage, ordered by sequence number.
+pub(crate) async fn list_checkpoints(state: &State, file_id: &Uuid) -> Result<Vec<Checkpoint>> {
+    let keys = state.settings.storage.list(&format!("{file_id}-")).await?;
+
+    let mut checkpoints = keys
+        .into_iter()
+        .flat_map(|key| {
+            checkpoint_sequence_num(file_id, &key)
+                .map(|sequence_num| Checkpoint { sequence_num, key })
+        })
+        .collect::<Vec<_>>();
+    checkpoints.sort_by_key(|checkpoint| checkpoint.sequence_num);
+
+    Ok(checkpoints)
+}
+
+/// Apply the transactions after `from_sequence_num`, up to and including
+/// `to_sequence_num`, to the grid.  Every transaction in between must still
+/// be in the queue.
+async fn replay_transactions(
+    state: &State,
+    file_id: &Uuid,
+    grid: &mut GridController,
+    from_sequence_num: u64,
+    to_sequence_num: u64,
+) -> Result<()> {
+    if from_sequence_num >= to_sequence_num {
+        return Ok(());
+    }
+
+    let transactions = state
+        .pubsub
+        .lock()
+        .await
+        .connection
+        .get_messages_range(
+            &file_id.to_string(),
+            &(from_sequence_num + 1).to_string(),
+            &to_sequence_num.to_string(),
+            false,
+        )
+        .await?
+        .iter()
+        .flat_map(|(_, message)| serde_json::from_str::<TransactionServer>(message))
+        .collect::<Vec<_>>();
+
+    let complete = transactions.len() as u64 == to_sequence_num - from_sequence_num
+        && transactions
+            .iter()
+            .zip(from_sequence_num + 1..)
+            .all(|(transaction, sequence_num)| transaction.sequence_num == sequence_num);
+
+    if !complete {
+        return Err(FilesError::NotFound(format!(
+            "transactions {} - {to_sequence_num} of file {file_id} are no longer available",
+            from_sequence_num + 1
+        )));
+    }
+
+    let operations = transactions
+        .into_iter()
+        .flat_map(|transaction| transaction.operations)
+        .collect();
+    apply_transaction(grid, operations);
+
+    Ok(())
+}
+
+/// Load the file as it was at a sequence number.
+pub(crate) async fn load_file_at(
+    state: &State,
+    file_id: &Uuid,
+    sequence_num: u64,
+) -> Result<GridController> {
+    let checkpoint = list_checkpoints(state, file_id)
+        .await?
+        .into_iter()
+        .rev()
+        .find(|checkpoint| checkpoint.sequence_num <= sequence_num)
+        .ok_or_else(|| {
+            FilesError::NotFound(format!(
+                "no checkpoint of file {file_id} at or before sequence number {sequence_num}"
+            ))
+        })?;
+
+    let mut grid = get_and_load_object(
+        &state.settings.storage,
+        &checkpoint.key,
+        checkpoint.sequence_num,
+    )
+    .await?;
+
+    replay_transactions(
+        state,
+        file_id,
+        &mut grid,
+        checkpoint.sequence_num,
+        sequence_num,
+    )
+    .await?;
+
+    Ok(grid)
+}
+
+/// The file's last sequence number, from the queue or its last checkpoint.
+pub(crate) async fn current_sequence_num(state: &State, file_id: &Uuid) -> Result<u64> {
+    let checkpoint_sequence_num = list_checkpoints(state, file_id)
+        .await?
+        .last()
+        .map_or(0, |checkpoint| checkpoint.sequence_num);
+
+    // the queue is empty if all of its transactions were truncated
+    let queue_sequence_num = state
+        .pubsub
+        .lock()
+        .await
+        .connection
+        .last_message(&file_id.to_string(), false)
+        .await
+        .ok()
+        .and_then(|(sequence_num, _)| sequence_num.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    Ok(checkpoint_sequence_num.max(queue_sequence_num))
+}
+
+/// Get the file's named versions, ordered by sequence number.  Versions that
+/// cannot be read are skipped.
+pub(crate) async fn get_versions(state: &State, file_id: &Uuid) -> Result<Vec<Version>> {
+    let mut versions = state
+        .pubsub
+        .lock()
+        .await
+        .connection
+        .members(&versions_key(file_id))
+        .await?
+        .iter()
+        .flat_map(|(_, version)| serde_json::from_str::<Version>(version))
+        .collect::<Vec<_>>();
+    versions.sort_by_key(|version| version.sequence_num);
+
+    Ok(versions)
+}
+
+/// Name the file at a sequence number.  A checkpoint is saved at the sequence
+/// number if there isn't one, so the version is available after the queue is
+/// truncated.
+pub(crate) async fn tag_version(
+    state: &State,
+    file_id: &Uuid,
+    name: String,
+    sequence_num: u64,
+    user_id: &str,
+) -> Result<Version> {
+    validate_version_name(&name)?;
+
+    if get_versions(state, file_id)
+        .await?
+        .iter()
+        .any(|version| version.name == name)
+    {
+        return Err(FilesError::BadRequest(format!(
+            "version {name} already exists"
+        )));
+    }
+
+    let current_sequence_num = current_sequence_num(state, file_id).await?;
+    if sequence_num > current_sequence_num {
+        return Err(FilesError::BadRequest(format!(
+            "sequence number {sequence_num} is after the file's last sequence number {current_sequence_num}"
+        )));
+    }
+
+    let is_checkpoint = list_checkpoints(state, file_id)
+        .await?
+        .iter()
+        .any(|checkpoint| checkpoint.sequence_num == sequence_num);
+
+    if !is_checkpoint {
+        let mut grid = load_file_at(state, file_id, sequence_num).await?;
+        let key = key(*file_id, sequence_num);
+        let body = export_file(&key, grid.grid_mut())?;
+
+        state.settings.storage.write(&key, &body).await?;
+    }
+
+    let version = Version {
+        name,
+        sequence_num,
+        user_id: user_id.to_owned(),
+        created_at: Utc::now(),
+    };
+
+    state
+        .pubsub
+        .lock()
+        .await
+        .connection
+        .upsert_member(
+            &versions_key(file_id),
+            &version.name,
+            &serde_json::to_string(&version)?,
+        )
+        .await?;
+
+    Ok(version)
+}
+
+/// Record a published transaction in the file's edit history, in the same
+/// capped sets that multiplayer writes when it sequences transactions.
+async fn add_edit_record(
+    state: &State,
+    file_id: &Uuid,
+    transaction: &TransactionServer,
+) -> Result<()> {
+    if let Some(record) = EditRecord::from_transaction(transaction) {
+        let value = serde_json::to_string(&record)?;
+        let mut pubsub = state.pubsub.lock().await;
+
+        edit_history::add_edit_record(
+            &mut pubsub.connection,
+            file_id,
+            record.sequence_num,
+            &record.user_id,
+            record.timestamp.timestamp_millis(),
+            &value,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Tell multiplayer about a transaction that was published outside of it, so
+/// it reaches the users that have the file open.
+async fn broadcast_transaction(state: &State, transaction: &TransactionServer) -> Result<()> {
+    let config = match &state.pubsub.lock().await.config {
+        PubSubConfig::RedisStreams(config) => PubSubConfig::Redis(RedisConfig {
+            host: config.host.to_owned(),
+            port: config.port.to_owned(),
+            password: config.password.to_owned(),
+            active_channels: config.active_channels.to_owned(),
+        }),
+        config => config.to_owned(),
+    };
+
+    // the file service is not a multiplayer instance, so every instance
+    // sends the broadcast to its users
+    let broadcast = Broadcast {
+        instance_id: Uuid::new_v4(),
+        file_id: transaction.file_id,
+        exclude: vec![],
+        message: BroadcastMessage::Transaction {
+            id: transaction.id,
+            file_id: transaction.file_id,
+            sequence_num: transaction.sequence_num,
+            operations: serde_json::to_string(&transaction.operations)?,
+        },
+    };
+
+    let broadcast = serde_json::to_string(&broadcast)?;
+
+    // the in-memory PubSub only reaches multiplayer in the same process
+    if let PubSubConfig::Memory(_) = config {
+        MemoryConnection::new(config)
+            .await?
+            .broadcast(BROADCAST_CHANNEL, &broadcast)?;
+    } else {
+        RedisConnection::new(config)
+            .await?
+            .publish(BROADCAST_CHANNEL, "", &broadcast, None)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Bring the file back to a named version by publishing the operations that
+/// turn the current file into it.
+///
+/// Returns the restore's transaction, None if the file already matches the
+/// version, or FilePermissions if the user cannot change what the restore
+/// changes.
+pub(crate) async fn restore_version(
+    state: &State,
+    file_id: &Uuid,
+    name: &str,
+    user_id: &str,
+) -> Result<Option<TransactionServer>> {
+    let version = get_versions(state, file_id)
+        .await?
+        .into_iter()
+        .find(|version| version.name == name)
+        .ok_or_else(|| FilesError::NotFound(format!("version {name} of file {file_id}")))?;
+
+    let target = load_file_at(state, file_id, version.sequence_num).await?;
+    let sequence_num = current_sequence_num(state, file_id).await?;
+    let current = load_file_at(state, file_id, sequence_num).await?;
+
+    publish_restore(
+        state,
+        file_id,
+        name,
+        user_id,
+        &target,
+        current,
+        sequence_num,
+    )
+    .await
+}
+
+/// Publish the operations that turn `current`, the file at `sequence_num`,
+/// into `target`.  If other transactions are published after `sequence_num`
+/// in the meantime, `current` catches up with them and the diff is retried.
+async fn publish_restore(
+    state: &State,
+    file_id: &Uuid,
+    name: &str,
+    user_id: &str,
+    target: &GridController,
+    mut current: GridController,
+    mut sequence_num: u64,
+) -> Result<Option<TransactionServer>> {
+    let channel = file_id.to_string();
+    let active_channels = state
+        .pubsub
+        .lock()
+        .await
+        .config
+        .active_channels()
+        .to_owned();
+
+    // restores are not made from a multiplayer session
+    let author = TransactionAuthor {
+        user_id: user_id.to_owned(),
+        session_id: Uuid::nil(),
+        timestamp: Utc::now(),
+    };
+
+    for _ in 0..MAX_RESTORE_ATTEMPTS {
+        let operations = diff_operations(current.grid(), target.grid());
+        if operations.is_empty() {
+            return Ok(None);
+        }
+
+        let mut protections = current
+            .grid()
+            .sheets()
+            .iter()
+            .map(|sheet| (sheet.id, sheet.protections.to_owned()))
+            .collect();
+        check_protections(
+            &operations,
+            Some(user_id),
+            &mut protections,
+            &mut current.code_outputs(),
+        )
+        .map_err(|error| FilesError::FilePermissions(error.to_string()))?;
+
+        let transaction = TransactionServer {
+            id: Uuid::new_v4(),
+            file_id: *file_id,
+            operations,
+            sequence_num: sequence_num + 1,
+            author: Some(author.to_owned()),
+        };
+
+        let next_sequence_num = state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .publish_if_next(
+                &channel,
+                transaction.sequence_num,
+                sequence_num,
+                &serde_json::to_string(&transaction)?,
+                Some(&active_channels),
+            )
+            .await?;
+
+        if next_sequence_num == transaction.sequence_num {
+            // the restore is already published, so failing to record it
+            // in the edit history doesn't fail the request
+            if let Err(error) = add_edit_record(state, file_id, &transaction).await {
+                tracing::warn!("Error recording the restore of file {file_id}: {error}");
+            }
+
+            // the transaction is in the queue, so users that miss the
+            // broadcast still receive it when they next catch up
+            if let Err(error) = broadcast_transaction(state, &transaction).await {
+                tracing::warn!("Error broadcasting the restore of file {file_id}: {error}");
+            }
+
+            return Ok(Some(transaction));
+        }
+
+        // the file changed after it was loaded, so catch up and diff again
+        replay_transactions(
+            state,
+            file_id,
+            &mut current,
+            sequence_num,
+            next_sequence_num - 1,
+        )
+        .await?;
+        sequence_num = next_sequence_num - 1;
+    }
+
+    Err(FilesError::TransactionQueue(format!(
+        "file {file_id} changed too often to restore version {name}"
+    )))
+}
+
+/// List the file's checkpoints.
+pub(crate) async fn get_checkpoints_handler(
+    user: User,
+    Path(file_id): Path<Uuid>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<Checkpoint>>> {
+    user.check_can_view(&state, file_id).await?;
+
+    Ok(Json(list_checkpoints(&state, &file_id).await?))
+}
+
+/// Download the file as it was at a sequence number.
+pub(crate) async fn get_file_at_handler(
+    user: User,
+    Path((file_id, sequence_num)): Path<(Uuid, u64)>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Response> {
+    user.check_can_view(&state, file_id).await?;
+
+    let mut grid = load_file_at(&state, &file_id, sequence_num).await?;
+    let body = export_file(&key(file_id, sequence_num), grid.grid_mut())?;
+
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], body).into_response())
+}
+
+/// List the file's named versions.
+pub(crate) async fn get_versions_handler(
+    user: User,
+    Path(file_id): Path<Uuid>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Vec<Version>>> {
+    user.check_can_view(&state, file_id).await?;
+
+    Ok(Json(get_versions(&state, &file_id).await?))
+}
+
+/// Name the file at a sequence number.
+pub(crate) async fn tag_version_handler(
+    user: User,
+    Path(file_id): Path<Uuid>,
+    Extension(state): Extension<Arc<State>>,
+    Json(TagVersion { name, sequence_num }): Json<TagVersion>,
+) -> Result<Json<Version>> {
+    user.check_can_edit(&state, file_id).await?;
+
+    let version = tag_version(&state, &file_id, name, sequence_num, user.user_id()).await?;
+
+    Ok(Json(version))
+}
+
+/// Bring the file back to a named version.
+pub(crate) async fn restore_version_handler(
+    user: User,
+    Path((file_id, name)): Path<(Uuid, String)>,
+    Extension(state): Extension<Arc<State>>,
+) -> Result<Json<Restored>> {
+    user.check_can_edit(&state, file_id).await?;
+
+    let transaction = restore_version(&state, &file_id, &name, user.user_id()).await?;
+
+    Ok(Json(Restored {
+        sequence_num: transaction.map(|transaction| transaction.sequence_num),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::{CellValue, Pos, SheetPos};
+    use quadratic_rust_shared::pubsub::edit_history::edit_history_key;
+
+    use super::*;
+    use crate::test_util::new_arc_state;
+
+    // saves a checkpoint of a file with 1 in A1 at sequence number 0, then
+    // publishes each of `values` in A1 as the next transaction
+    async fn setup(values: &[&str]) -> (Arc<State>, Uuid, GridController) {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let mut grid = GridController::test();
+        set_a1(&mut grid, "1");
+
+        let key = key(file_id, 0);
+        let body = export_file(&key, grid.grid_mut()).unwrap();
+        state.settings.storage.write(&key, &body).await.unwrap();
+
+        for value in values {
+            publish(&state, &file_id, &mut grid, value).await;
+        }
+
+        (state, file_id, grid)
+    }
+
+    fn set_a1(grid: &mut GridController, value: &str) {
+        let sheet_id = grid.sheet_ids()[0];
+        grid.set_cell_value(SheetPos::new(sheet_id, 0, 0), value.into(), None);
+    }
+
+    fn a1(grid: &GridController) -> Option<CellValue> {
+        let sheet_id = grid.sheet_ids()[0];
+        grid.try_sheet(sheet_id)
+            .unwrap()
+            .display_value(Pos { x: 0, y: 0 })
+    }
+
+    // sets A1 and publishes the change as the file's next transaction
+    async fn publish(state: &State, file_id: &Uuid, grid: &mut GridController, value: &str) {
+        set_a1(grid, value);
+        let sequence_num = current_sequence_num(state, file_id).await.unwrap() + 1;
+        let transaction = TransactionServer {
+            id: Uuid::new_v4(),
+            file_id: *file_id,
+            operations: grid.last_transaction().unwrap().operations.clone(),
+            sequence_num,
+            author: None,
+        };
+
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .publish_if_next(
+                &file_id.to_string(),
+                sequence_num,
+                sequence_num - 1,
+                &serde_json::to_string(&transaction).unwrap(),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    fn number(value: i64) -> Option<CellValue> {
+        Some(CellValue::Number(value.into()))
+    }
+
+    #[tokio::test]
+    async fn loads_files_from_a_checkpoint_and_the_queue() {
+        let (state, file_id, _) = setup(&["2", "3"]).await;
+
+        assert_eq!(current_sequence_num(&state, &file_id).await.unwrap(), 2);
+        for (sequence_num, value) in [(0, 1), (1, 2), (2, 3)] {
+            let grid = load_file_at(&state, &file_id, sequence_num).await.unwrap();
+            assert_eq!(a1(&grid), number(value));
+        }
+
+        // the queue doesn't have the transactions of a file without checkpoints
+        let result = load_file_at(&state, &Uuid::new_v4(), 1).await;
+        assert!(matches!(result, Err(FilesError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn tags_versions_with_a_checkpoint() {
+        let (state, file_id, _) = setup(&["2", "3"]).await;
+
+        let version = tag_version(&state, &file_id, "two".into(), 1, "user")
+            .await
+            .unwrap();
+        assert_eq!(version.sequence_num, 1);
+        assert_eq!(get_versions(&state, &file_id).await.unwrap(), vec![version]);
+
+        // the checkpoint outlives the queue
+        let checkpoints = list_checkpoints(&state, &file_id).await.unwrap();
+        assert_eq!(
+            checkpoints,
+            vec![
+                Checkpoint {
+                    sequence_num: 0,
+                    key: key(file_id, 0),
+                },
+                Checkpoint {
+                    sequence_num: 1,
+                    key: key(file_id, 1),
+                },
+            ]
+        );
+        let grid = get_and_load_object(&state.settings.storage, &key(file_id, 1), 1)
+            .await
+            .unwrap();
+        assert_eq!(a1(&grid), number(2));
+
+        // names are unique, and versions can't be ahead of the file
+        let result = tag_version(&state, &file_id, "two".into(), 2, "user").await;
+        assert!(matches!(result, Err(FilesError::BadRequest(_))));
+        let result = tag_version(&state, &file_id, "four".into(), 3, "user").await;
+        assert!(matches!(result, Err(FilesError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn restores_versions() {
+        let (state, file_id, _) = setup(&["2", "3"]).await;
+        tag_version(&state, &file_id, "one".into(), 0, "user")
+            .await
+            .unwrap();
+
+        let memory = MemoryConnection::new(state.pubsub.lock().await.config.to_owned())
+            .await
+            .unwrap();
+        let mut broadcasts = memory.listen(BROADCAST_CHANNEL).unwrap();
+
+        let transaction = restore_version(&state, &file_id, "one", "user")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction.sequence_num, 3);
+
+        // the diff is published
+        let grid = load_file_at(&state, &file_id, 3).await.unwrap();
+        assert_eq!(a1(&grid), number(1));
+
+        // and broadcast to multiplayer, and recorded in the edit history
+        loop {
+            let broadcast = broadcasts.recv().await.unwrap();
+            let broadcast: Broadcast<BroadcastMessage> = serde_json::from_str(&broadcast).unwrap();
+            if broadcast.file_id == file_id {
+                let BroadcastMessage::Transaction { id, .. } = broadcast.message;
+                assert_eq!(id, transaction.id);
+                break;
+            }
+        }
+        let records = state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .capped_members(&edit_history_key(&file_id), 0, i64::MAX, false, 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, "3");
+
+        // the file already matches the version
+        let restored = restore_version(&state, &file_id, "one", "user").await;
+        assert_eq!(restored.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn retries_restores_when_the_file_changes() {
+        let (state, file_id, mut grid) = setup(&["2"]).await;
+        tag_version(&state, &file_id, "one".into(), 0, "user")
+            .await
+            .unwrap();
+        let target = load_file_at(&state, &file_id, 0).await.unwrap();
+        let current = load_file_at(&state, &file_id, 1).await.unwrap();
+
+        // another transaction is published after the file is loaded
+        publish(&state, &file_id, &mut grid, "3").await;
+
+        let transaction = publish_restore(&state, &file_id, "one", "user", &target, current, 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction.sequence_num, 3);
+
+        let grid = load_file_at(&state, &file_id, 3).await.unwrap();
+        assert_eq!(a1(&grid), number(1));
+    }
+
+    #[test]
+    fn parses_checkpoint_keys() {
+        let file_id = Uuid::new_v4();
+
+        assert_eq!(
+            checkpoint_sequence_num(&file_id, &key(file_id, 12)),
+            Some(12)
+        );
+        assert_eq!(
+            checkpoint_sequence_num(&file_id, &key(Uuid::new_v4(), 12)),
+            None
+        );
+        assert_eq!(
+            checkpoint_sequence_num(&file_id, &format!("{file_id}-thumbnail.png")),
+            None
+        );
+    }
+
+    #[test]
+    fn validates_version_names() {
+        assert!(validate_version_name("Before the import").is_ok());
+        assert!(validate_version_name(" ").is_err());
+        assert!(validate_version_name(&"a".repeat(MAX_VERSION_NAME_LENGTH + 1)).is_err());
+    }
+}
@@ -5,8 +5,7 @@ use uuid::Uuid;
 
 use crate::{
     error::Result,
-    get_room,
-    message::{broadcast, response::MessageResponse},
+    message::{broadcast, broadcast_local, response::MessageResponse},
     state::State,
 };
 
@@ -26,8 +25,14 @@ pub(crate) fn start(
         let mut interval = time::interval(Duration::from_millis(heartbeat_check_s as u64 * 1000));
 
         loop {
-            // reconnect if pubsub connection is unhealthy
+            // reconnect if pubsub connections are unhealthy
             state.pubsub.lock().await.reconnect_if_unhealthy().await;
+            state
+                .broadcaster
+                .lock()
+                .await
+                .reconnect_if_unhealthy()
+                .await;
 
             // get all room ids
             let rooms = state
@@ -75,11 +80,12 @@ pub(crate) fn start(
     })
 }
 
-// broadcast sequence number to all users in the room
+// broadcast sequence number to all local users in the room, since each
+// instance broadcasts to its own users
 async fn broadcast_sequence_num(state: Arc<State>, file_id: &Uuid) -> Result<JoinHandle<()>> {
     let sequence_num = state.get_sequence_num(file_id).await?;
 
-    Ok(broadcast(
+    Ok(broadcast_local(
         vec![],
         file_id.to_owned(),
         Arc::clone(&state),
@@ -104,12 +110,14 @@ async fn remove_stale_users_in_room(
         return Ok(None);
     }
 
-    if num_remaining == 0 {
+    // users on other instances may remain in the room
+    let users = state.get_members(file_id).await?;
+
+    if users.is_empty() {
         tracing::trace!("No users remaining in room {file_id}",);
         return Ok(None);
     }
 
-    let users = get_room!(state, file_id)?.users.to_owned();
     let message = MessageResponse::from((users, &state.settings.min_version));
 
     Ok(Some(broadcast(
@@ -141,7 +149,13 @@ mod tests {
             .pubsub
             .lock()
             .await
-            .push(transaction_id_1, file_id, vec![operations_1.clone()], 1)
+            .push(
+                transaction_id_1,
+                file_id,
+                vec![operations_1.clone()],
+                0,
+                None,
+            )
             .await
             .unwrap();
 
@@ -0,0 +1,124 @@
+use futures::stream::StreamExt;
+use quadratic_rust_shared::pubsub::redis::RedisConnection;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio::{task::JoinHandle, time};
+
+use crate::{
+    error::{MpError, Result},
+    message::{broadcast_local, response::MessageResponse},
+    state::{
+        broadcaster::{Broadcast, Broadcaster, Subscription},
+        State,
+    },
+};
+
+/// How long to wait before resubscribing after the subscription is lost.
+const RECONNECT_DELAY_MS: u64 = 1000;
+
+/// Subscribe to broadcasts from other instances, then in a separate thread:
+///   * Send them to the local users in the room
+///   * Keep the room's sequence number up to date
+///
+/// Returns after the subscription is made, so no broadcasts are missed.
+#[tracing::instrument(level = "trace")]
+pub(crate) async fn start(state: Arc<State>) -> Result<JoinHandle<()>> {
+    let config = state.broadcaster.lock().await.config.to_owned();
+    let mut subscription = Broadcaster::subscribe(&config).await?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            match &mut subscription {
+                Subscription::Redis(connection) => listen(Arc::clone(&state), connection).await,
+                Subscription::Memory(receiver) => listen_memory(Arc::clone(&state), receiver).await,
+            }
+
+            tracing::error!("Lost the subscription to broadcasts, resubscribing");
+
+            // resubscribe until it succeeds
+            loop {
+                time::sleep(Duration::from_millis(RECONNECT_DELAY_MS)).await;
+
+                match Broadcaster::subscribe(&config).await {
+                    Ok(new_subscription) => {
+                        subscription = new_subscription;
+                        break;
+                    }
+                    Err(error) => {
+                        tracing::error!("Error resubscribing to broadcasts: {error}");
+                    }
+                }
+            }
+        }
+    }))
+}
+
+// receive broadcasts until the subscription is lost
+async fn listen(state: Arc<State>, connection: &mut RedisConnection) {
+    let mut messages = connection.pubsub.on_message();
+
+    while let Some(message) = messages.next().await {
+        let payload = message
+            .get_payload::<String>()
+            .map_err(|e| MpError::PubSub(e.to_string()));
+
+        receive_payload(Arc::clone(&state), payload).await;
+    }
+}
+
+// receive broadcasts from instances in this process until the channel closes
+async fn listen_memory(state: Arc<State>, receiver: &mut Receiver<String>) {
+    loop {
+        match receiver.recv().await {
+            Ok(payload) => receive_payload(Arc::clone(&state), Ok(payload)).await,
+            Err(RecvError::Lagged(count)) => {
+                tracing::warn!("Missed {count} broadcasts");
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+async fn receive_payload(state: Arc<State>, payload: Result<String>) {
+    let result = match payload.and_then(|payload| Ok(serde_json::from_str::<Broadcast>(&payload)?))
+    {
+        Ok(broadcast) => receive(state, broadcast).await,
+        Err(error) => Err(error),
+    };
+
+    if let Err(error) = result {
+        tracing::warn!("Error receiving a broadcast: {:?}", error);
+    }
+}
+
+// send a broadcast from another instance to the local users in the room
+async fn receive(state: Arc<State>, broadcast: Broadcast) -> Result<()> {
+    let Broadcast {
+        instance_id,
+        file_id,
+        exclude,
+        message,
+    } = broadcast;
+
+    // this instance already sent the broadcast to its users
+    if instance_id == state.instance_id {
+        return Ok(());
+    }
+
+    // rooms without local users are not tracked by this instance
+    {
+        let rooms = state.rooms.lock().await;
+        let Some(mut room) = rooms.get_mut(&file_id) else {
+            return Ok(());
+        };
+
+        // the transaction was validated and sequenced by the other instance
+        if let MessageResponse::Transaction { sequence_num, .. } = &message {
+            room.sequence_num = room.sequence_num.max(*sequence_num);
+        }
+    }
+
+    broadcast_local(exclude, file_id, state, message);
+
+    Ok(())
+}
@@ -8,6 +8,8 @@
 use crate::error::{MpError, Result};
 use dotenv::dotenv;
 use quadratic_rust_shared::environment::Environment;
+use quadratic_rust_shared::pubsub::PubSubType;
+use quadratic_rust_shared::storage::StorageType;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -19,14 +21,54 @@ pub(crate) struct Config {
     pub(crate) heartbeat_timeout_s: i64,
     pub(crate) environment: Environment,
 
+    // where transactions are queued and broadcast: redis (default), or memory
+    // in tests
+    #[serde(default)]
+    pub(crate) pubsub_type: PubSubType,
+
+    #[serde(default)]
     pub(crate) pubsub_host: String,
+    #[serde(default)]
     pub(crate) pubsub_port: String,
+    #[serde(default)]
     pub(crate) pubsub_password: String,
     pub(crate) pubsub_active_channels: String,
 
     pub(crate) auth0_jwks_uri: String,
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
+
+    // where checkpoints are read from: s3 (default), file-system, or memory
+    #[serde(default)]
+    pub(crate) storage_type: StorageType,
+
+    // the directory for file-system storage
+    #[serde(default)]
+    pub(crate) storage_dir: String,
+
+    #[serde(default)]
+    pub(crate) aws_s3_region: String,
+    #[serde(default)]
+    pub(crate) aws_s3_bucket_name: String,
+    #[serde(default)]
+    pub(crate) aws_s3_access_key_id: String,
+    #[serde(default)]
+    pub(crate) aws_s3_secret_access_key: String,
+
+    // limits on incoming transactions, see TransactionLimits for defaults
+    pub(crate) max_transaction_bytes: Option<usize>,
+    pub(crate) max_transaction_operations: Option<usize>,
+    pub(crate) max_transaction_cells: Option<u64>,
+
+    // limits on catching up clients, see CatchUpLimits for defaults
+    pub(crate) catch_up_page_size: Option<u64>,
+    pub(crate) catch_up_checkpoint_gap: Option<u64>,
+
+    // limits on chat messages, see ChatLimits for defaults
+    pub(crate) max_chat_message_length: Option<usize>,
+    pub(crate) max_chat_mentions: Option<usize>,
+    pub(crate) chat_messages_per_minute: Option<usize>,
+    pub(crate) chat_history_size: Option<usize>,
 }
 
 /// Load the global configuration from the environment into Config.
@@ -38,6 +80,11 @@ pub(crate) fn config() -> Result<Config> {
     dotenv().ok();
 
     let config = envy::from_env::<Config>().map_err(|e| MpError::Config(e.to_string()))?;
+    config
+        .pubsub_type
+        .check_environment(&config.environment)
+        .map_err(|e| MpError::Config(e.to_string()))?;
+
     Ok(config)
 }
 
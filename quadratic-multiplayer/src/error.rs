@@ -60,6 +60,12 @@ pub(crate) enum MpError {
     #[error("Internal server error: {0}")]
     InternalServer(String),
 
+    #[error("Chat message {0} was rejected: {1}")]
+    InvalidChatMessage(Uuid, String),
+
+    #[error("Transaction {0} was rejected: {1}")]
+    InvalidTransaction(Uuid, String),
+
     #[error("Error reading MinVersion file: {0}")]
     MinVersion(String),
 
@@ -4,6 +4,7 @@
 //! tracking for a shared file.
 
 mod background_worker;
+mod broadcast_listener;
 mod config;
 mod error;
 mod message;
@@ -0,0 +1,388 @@
+//! Catching Up
+//!
+//! Clients that fall behind request the transactions after the last one they
+//! applied.  Long histories are sent in pages, and the client requests the
+//! next page after applying each one.
+//!
+//! Once quadratic-files checkpoints a file, it truncates the file's
+//! transactions in PubSub.  Clients that need truncated transactions, or that
+//! are too far behind to replay the history, are pointed at the latest
+//! checkpoint and the transactions after it instead.  The client loads the
+//! checkpoint from its url, or reloads the file if storage doesn't serve it.
+//! Clients that are ahead of the latest checkpoint replay the history however
+//! long it is, since the checkpoint is of no use to them.
+
+use quadratic_rust_shared::quadratic_api::get_file_checkpoint;
+use quadratic_rust_shared::storage::Storage;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{ErrorLevel, MpError, Result};
+use crate::message::response::MessageResponse;
+use crate::state::State;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Checkpoint {
+    pub(crate) sequence_num: u64,
+    pub(crate) version: String,
+
+    /// The checkpoint's key in storage.
+    pub(crate) key: String,
+
+    /// A signed url that the client can download the checkpoint from.
+    pub(crate) url: Option<String>,
+}
+
+/// A page of transactions and the sequence number of the next page, if any.
+type Page = (String, Option<u64>);
+
+impl State {
+    /// Retrieves the file's latest checkpoint.
+    pub(crate) async fn get_checkpoint(&self, file_id: &Uuid) -> Result<Checkpoint> {
+        if cfg!(test) {
+            let sequence_num = self.get_room(file_id).await?.checkpoint_sequence_num;
+            return Ok(Checkpoint {
+                sequence_num,
+                version: "test".into(),
+                key: format!("{file_id}-{sequence_num}.grid"),
+                url: None,
+            });
+        }
+
+        let checkpoint = get_file_checkpoint(
+            &self.settings.quadratic_api_uri,
+            &self.settings.m2m_auth_token,
+            file_id,
+        )
+        .await?;
+
+        if let Some(mut room) = self.rooms.lock().await.get_mut(file_id) {
+            room.checkpoint_sequence_num = checkpoint.sequence_number;
+        }
+
+        // clients reload the file if they can't download the checkpoint
+        let url = self
+            .settings
+            .storage
+            .download_url(&checkpoint.s3_key)
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!(
+                    "Unable to sign the url of checkpoint {}: {error}",
+                    checkpoint.s3_key
+                );
+                None
+            });
+
+        Ok(Checkpoint {
+            sequence_num: checkpoint.sequence_number,
+            version: checkpoint.version,
+            key: checkpoint.s3_key,
+            url,
+        })
+    }
+}
+
+/// Reads the page of transactions that starts at `min_sequence_num`.
+/// Returns None if any of them are no longer in PubSub.
+async fn page(
+    state: &State,
+    file_id: Uuid,
+    min_sequence_num: u64,
+    sequence_num: u64,
+) -> Result<Option<Page>> {
+    if min_sequence_num > sequence_num {
+        return Ok(Some(("[]".into(), None)));
+    }
+
+    let page_size = state.settings.catch_up_limits.page_size;
+    let max_sequence_num = sequence_num.min(min_sequence_num + page_size - 1);
+    let transactions = state
+        .get_messages_range_pubsub(&file_id, min_sequence_num, max_sequence_num)
+        .await?;
+
+    if transactions.len() as u64 != max_sequence_num - min_sequence_num + 1 {
+        return Ok(None);
+    }
+
+    let next_sequence_num = (max_sequence_num < sequence_num).then_some(max_sequence_num + 1);
+
+    Ok(Some((
+        serde_json::to_string(&transactions)?,
+        next_sequence_num,
+    )))
+}
+
+/// Sends a page of the history without a checkpoint.  Short, complete
+/// histories are sent as Transactions, and everything else as CatchUp.
+fn replay(file_id: Uuid, (transactions, next_sequence_num): Page) -> MessageResponse {
+    match next_sequence_num {
+        None => MessageResponse::Transactions { transactions },
+        Some(_) => MessageResponse::CatchUp {
+            file_id,
+            checkpoint: None,
+            transactions,
+            next_sequence_num,
+        },
+    }
+}
+
+/// The response to GetTransactions for clients that can catch up.
+pub(crate) async fn catch_up_transactions(
+    state: &State,
+    file_id: Uuid,
+    min_sequence_num: u64,
+) -> Result<MessageResponse> {
+    let sequence_num = state.get_sequence_num(&file_id).await?;
+    let gap = (sequence_num + 1).saturating_sub(min_sequence_num);
+    let close_enough = gap <= state.settings.catch_up_limits.checkpoint_gap;
+
+    // replay the history if it's short enough and hasn't been truncated
+    if close_enough {
+        if let Some(page) = page(state, file_id, min_sequence_num, sequence_num).await? {
+            return Ok(replay(file_id, page));
+        }
+    }
+
+    // otherwise start from the latest checkpoint, as long as it's newer than
+    // what the client has
+    let checkpoint = state.get_checkpoint(&file_id).await?;
+    if checkpoint.sequence_num >= min_sequence_num {
+        let tail = page(state, file_id, checkpoint.sequence_num + 1, sequence_num).await?;
+
+        if let Some((transactions, next_sequence_num)) = tail {
+            return Ok(MessageResponse::CatchUp {
+                file_id,
+                checkpoint: Some(checkpoint),
+                transactions,
+                next_sequence_num,
+            });
+        }
+    } else if !close_enough {
+        // the checkpoint is of no use to the client, so replay the long
+        // history if it's still there
+        if let Some(page) = page(state, file_id, min_sequence_num, sequence_num).await? {
+            return Ok(replay(file_id, page));
+        }
+    }
+
+    Ok(MessageResponse::Error {
+        error: MpError::MissingTransactions(gap.to_string(), "0".into()),
+        error_level: ErrorLevel::Error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use quadratic_core::controller::transaction::TransactionServer;
+    use quadratic_core::controller::GridController;
+    use quadratic_rust_shared::pubsub::PubSub as PubSubTrait;
+
+    use super::*;
+    use crate::get_mut_room;
+    use crate::state::settings::CatchUpLimits;
+    use crate::test_util::{add_new_user_to_room, new_state, operation};
+
+    // creates a room with `count` transactions
+    async fn setup(limits: CatchUpLimits, count: u64) -> (Arc<State>, Uuid) {
+        let mut state = new_state().await;
+        state.settings.catch_up_limits = limits;
+        let state = Arc::new(state);
+        let file_id = Uuid::new_v4();
+        add_new_user_to_room(file_id, state.clone()).await;
+
+        let mut grid = GridController::test();
+        for index in 0..count {
+            let operations = vec![operation(&mut grid, index as i64, 0, "1")];
+            let sequence_num = state
+                .push_pubsub(Uuid::new_v4(), file_id, operations, index, None)
+                .await
+                .unwrap();
+            state
+                .update_sequence_num(&file_id, sequence_num)
+                .await
+                .unwrap();
+        }
+
+        (state, file_id)
+    }
+
+    fn sequence_nums(transactions: &str) -> Vec<u64> {
+        serde_json::from_str::<Vec<TransactionServer>>(transactions)
+            .unwrap()
+            .iter()
+            .map(|transaction| transaction.sequence_num)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pages_through_transactions() {
+        let limits = CatchUpLimits {
+            page_size: 2,
+            checkpoint_gap: 100,
+        };
+        let (state, file_id) = setup(limits, 5).await;
+
+        let response = catch_up_transactions(&state, file_id, 1).await.unwrap();
+        let MessageResponse::CatchUp {
+            checkpoint,
+            transactions,
+            next_sequence_num,
+            ..
+        } = response
+        else {
+            panic!("expected CatchUp");
+        };
+        assert_eq!(checkpoint, None);
+        assert_eq!(sequence_nums(&transactions), vec![1, 2]);
+        assert_eq!(next_sequence_num, Some(3));
+
+        // the last page is sent as Transactions
+        let response = catch_up_transactions(&state, file_id, 4).await.unwrap();
+        let MessageResponse::Transactions { transactions } = response else {
+            panic!("expected Transactions");
+        };
+        assert_eq!(sequence_nums(&transactions), vec![4, 5]);
+
+        // clients that are up to date receive no transactions
+        let response = catch_up_transactions(&state, file_id, 6).await.unwrap();
+        assert_eq!(
+            response,
+            MessageResponse::Transactions {
+                transactions: "[]".into()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn catches_up_from_a_checkpoint() {
+        let limits = CatchUpLimits {
+            page_size: 10,
+            checkpoint_gap: 100,
+        };
+        let (state, file_id) = setup(limits, 5).await;
+
+        // the file was checkpointed at 3 and transactions up to 3 were truncated
+        get_mut_room!(state, file_id)
+            .unwrap()
+            .checkpoint_sequence_num = 3;
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .trim(&file_id.to_string(), "4")
+            .await
+            .unwrap();
+
+        let response = catch_up_transactions(&state, file_id, 2).await.unwrap();
+        let MessageResponse::CatchUp {
+            checkpoint,
+            transactions,
+            next_sequence_num,
+            ..
+        } = response
+        else {
+            panic!("expected CatchUp");
+        };
+        assert_eq!(
+            checkpoint,
+            Some(Checkpoint {
+                sequence_num: 3,
+                version: "test".into(),
+                key: format!("{file_id}-3.grid"),
+                url: None,
+            })
+        );
+        assert_eq!(sequence_nums(&transactions), vec![4, 5]);
+        assert_eq!(next_sequence_num, None);
+
+        // the transactions are missing if the checkpoint is older than the client
+        get_mut_room!(state, file_id)
+            .unwrap()
+            .checkpoint_sequence_num = 1;
+        let response = catch_up_transactions(&state, file_id, 2).await.unwrap();
+        assert_eq!(
+            response,
+            MessageResponse::Error {
+                error: MpError::MissingTransactions("4".into(), "0".into()),
+                error_level: ErrorLevel::Error,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn catches_up_from_a_checkpoint_when_far_behind() {
+        let limits = CatchUpLimits {
+            page_size: 10,
+            checkpoint_gap: 2,
+        };
+        let (state, file_id) = setup(limits, 5).await;
+        get_mut_room!(state, file_id)
+            .unwrap()
+            .checkpoint_sequence_num = 3;
+
+        let response = catch_up_transactions(&state, file_id, 1).await.unwrap();
+        let MessageResponse::CatchUp {
+            checkpoint,
+            transactions,
+            ..
+        } = response
+        else {
+            panic!("expected CatchUp");
+        };
+        assert_eq!(checkpoint.unwrap().sequence_num, 3);
+        assert_eq!(sequence_nums(&transactions), vec![4, 5]);
+
+        // clients that are close enough replay the history
+        let response = catch_up_transactions(&state, file_id, 4).await.unwrap();
+        assert!(matches!(response, MessageResponse::Transactions { .. }));
+    }
+
+    #[tokio::test]
+    async fn catches_up_when_far_behind_and_the_checkpoint_is_older_than_the_client() {
+        let limits = CatchUpLimits {
+            page_size: 2,
+            checkpoint_gap: 2,
+        };
+        let (state, file_id) = setup(limits, 5).await;
+        get_mut_room!(state, file_id)
+            .unwrap()
+            .checkpoint_sequence_num = 1;
+
+        // the history is paged through from what the client has
+        let response = catch_up_transactions(&state, file_id, 2).await.unwrap();
+        let MessageResponse::CatchUp {
+            checkpoint,
+            transactions,
+            next_sequence_num,
+            ..
+        } = response
+        else {
+            panic!("expected CatchUp");
+        };
+        assert_eq!(checkpoint, None);
+        assert_eq!(sequence_nums(&transactions), vec![2, 3]);
+        assert_eq!(next_sequence_num, Some(4));
+
+        // the transactions are missing if the history was truncated
+        state
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .trim(&file_id.to_string(), "3")
+            .await
+            .unwrap();
+        let response = catch_up_transactions(&state, file_id, 2).await.unwrap();
+        assert_eq!(
+            response,
+            MessageResponse::Error {
+                error: MpError::MissingTransactions("4".into(), "0".into()),
+                error_level: ErrorLevel::Error,
+            }
+        );
+    }
+}
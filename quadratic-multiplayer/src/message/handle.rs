@@ -7,22 +7,30 @@
 
 use axum::extract::ws::{Message, WebSocket};
 use futures_util::stream::SplitSink;
-use quadratic_core::controller::operations::operation::Operation;
+use quadratic_core::controller::{
+    operations::operation::Operation, transaction::TransactionAuthor,
+};
 use quadratic_rust_shared::quadratic_api::{get_file_perms, FilePermRole};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::error::{ErrorLevel, MpError, Result};
-use crate::get_mut_room;
 use crate::message::{
-    broadcast, request::MessageRequest, response::MessageResponse, send_user_message,
+    broadcast,
+    catch_up::catch_up_transactions,
+    protocol::Protocol,
+    request::MessageRequest,
+    response::MessageResponse,
+    send_user_message,
+    validate::{validate_operations, validate_operations_size},
 };
 use crate::permissions::{
     validate_can_edit_or_view_file, validate_user_can_edit_file,
     validate_user_can_edit_or_view_file,
 };
 use crate::state::{
+    chat::{validate_chat_message, ChatMessage},
     connection::PreConnection,
     pubsub::GROUP_NAME,
     user::{User, UserState},
@@ -54,6 +62,7 @@ pub(crate) async fn handle_message(
             cell_edit,
             viewport,
             follow,
+            protocol_version,
         } => {
             // validate that the user has permission to access the file
             let base_url = &state.settings.quadratic_api_uri;
@@ -96,6 +105,16 @@ pub(crate) async fn handle_message(
                 follow,
             };
 
+            // protections are only checked against the subject of a
+            // validated jwt, never the user id that the client sends (tests
+            // connect without a jwt)
+            let authenticated_user_id = if cfg!(test) {
+                Some(user_id.to_owned())
+            } else {
+                pre_connection.user_id.to_owned()
+            };
+            let user_id = authenticated_user_id.to_owned().unwrap_or(user_id);
+
             let mut user = User {
                 user_id,
                 session_id,
@@ -108,6 +127,8 @@ pub(crate) async fn handle_message(
                 state: user_state,
                 socket: Some(Arc::clone(&sender)),
                 last_heartbeat: chrono::Utc::now(),
+                protocol: Protocol::negotiate(protocol_version, &state.settings.min_version),
+                authenticated_user_id,
 
                 // this will be properly set in the enter_room function
                 index: 0,
@@ -122,6 +143,12 @@ pub(crate) async fn handle_message(
                 .enter_room(file_id, &mut user, pre_connection, sequence_num)
                 .await?;
 
+            // seed the room's protections from the file's checkpoint, which
+            // is retried on the next transaction if it fails
+            if let Err(error) = state.get_protections(&file_id).await {
+                tracing::warn!("Error loading protections for room {file_id}: {error}");
+            }
+
             // direct response to user w/sequence_num after logging in
             send_user_message(
                 session_id,
@@ -130,6 +157,7 @@ pub(crate) async fn handle_message(
                 MessageResponse::EnterRoom {
                     file_id,
                     sequence_num,
+                    protocol_version: user.protocol.version(),
                 },
             )
             .await
@@ -137,8 +165,7 @@ pub(crate) async fn handle_message(
 
             // only broadcast if the user is new to the room
             if is_new {
-                let room = state.get_room(&file_id).await?;
-                let response = MessageResponse::from((room.users, &state.settings.min_version));
+                let response = state.users_in_room(&file_id).await?;
 
                 broadcast(vec![], file_id, Arc::clone(&state), response);
             }
@@ -153,13 +180,12 @@ pub(crate) async fn handle_message(
         } => {
             validate_user_can_edit_or_view_file(Arc::clone(&state), file_id, session_id).await?;
 
-            let is_not_empty = state.leave_room(file_id, &session_id).await?;
-            let room = state.get_room(&file_id).await?;
+            state.leave_room(file_id, &session_id).await?;
 
-            if is_not_empty {
-                let response = MessageResponse::from((room.users, &state.settings.min_version));
-                broadcast(vec![session_id], file_id, Arc::clone(&state), response);
-            }
+            // users on other instances are still notified if the room has no
+            // local users left
+            let response = state.users_in_room(&file_id).await?;
+            broadcast(vec![session_id], file_id, Arc::clone(&state), response);
 
             Ok(None)
         }
@@ -182,27 +208,41 @@ pub(crate) async fn handle_message(
                 session_id
             );
 
+            // reject transactions that are too large before parsing them
+            let limits = &state.settings.transaction_limits;
+            if let Err(reason) = validate_operations_size(&operations, limits) {
+                return Ok(Some(invalid_transaction(id, reason)));
+            }
+
             // unpack the operations or return an error
             let operations_unpacked: Vec<Operation> = serde_json::from_str(&operations)?;
 
-            // get and increment the room's sequence_num
-            let room_sequence_num = get_mut_room!(state, file_id)?.increment_sequence_num();
+            let sent = SentOperations::Json(operations);
+            sequence_transaction(state, id, session_id, file_id, operations_unpacked, sent).await
+        }
 
-            // add the transaction to the transaction queue
-            let sequence_num = state
-                .push_pubsub(id, file_id, operations_unpacked, room_sequence_num)
-                .await?;
+        // User sends transactions as binary messages, whose size was checked
+        // when they were decompressed
+        MessageRequest::BinaryTransaction {
+            id,
+            session_id,
+            file_id,
+            operations,
+            compressed,
+        } => {
+            validate_user_can_edit_file(Arc::clone(&state), file_id, session_id).await?;
 
-            // broadcast the transaction to all users in the room
-            let response = MessageResponse::Transaction {
-                id,
+            // update the heartbeat
+            state.update_user_heartbeat(file_id, &session_id).await?;
+
+            tracing::trace!(
+                "Binary transaction received for room {} from user {}",
                 file_id,
-                operations,
-                sequence_num,
-            };
-            broadcast(vec![], file_id, Arc::clone(&state), response);
+                session_id
+            );
 
-            Ok(None)
+            let sent = SentOperations::Binary(compressed);
+            sequence_transaction(state, id, session_id, file_id, operations, sent).await
         }
 
         // User sends transactions
@@ -210,12 +250,19 @@ pub(crate) async fn handle_message(
             file_id,
             session_id,
             min_sequence_num,
+            catch_up,
         } => {
             validate_user_can_edit_or_view_file(Arc::clone(&state), file_id, session_id).await?;
 
             // update the heartbeat
             state.update_user_heartbeat(file_id, &session_id).await?;
 
+            if catch_up {
+                return Ok(Some(
+                    catch_up_transactions(&state, file_id, min_sequence_num).await?,
+                ));
+            }
+
             let sequence_num = state.get_sequence_num(&file_id).await?;
 
             // calculate the expected number of transactions to get from redis
@@ -291,13 +338,199 @@ pub(crate) async fn handle_message(
             state.update_user_heartbeat(file_id, &session_id).await?;
             Ok(None)
         }
+
+        // User asks who changed a cell or what a user changed
+        MessageRequest::GetEditHistory {
+            session_id,
+            file_id,
+            query,
+        } => {
+            validate_user_can_edit_or_view_file(Arc::clone(&state), file_id, session_id).await?;
+
+            // update the heartbeat
+            state.update_user_heartbeat(file_id, &session_id).await?;
+
+            let records = state.query_edit_history(&file_id, &query).await?;
+
+            Ok(Some(MessageResponse::EditHistory { file_id, records }))
+        }
+
+        // User sends a chat message to the room
+        MessageRequest::ChatMessage {
+            id,
+            session_id,
+            file_id,
+            text,
+            mentions,
+            anchor,
+        } => {
+            validate_user_can_edit_or_view_file(Arc::clone(&state), file_id, session_id).await?;
+
+            // update the heartbeat
+            state.update_user_heartbeat(file_id, &session_id).await?;
+
+            let limits = &state.settings.chat_limits;
+            let (text, mentions) = match validate_chat_message(&text, mentions, limits) {
+                Ok(validated) => validated,
+                Err(reason) => return Ok(Some(invalid_chat_message(id, reason))),
+            };
+
+            if !state.check_chat_rate(&file_id, &session_id, limits).await? {
+                let reason = format!(
+                    "users can send at most {} messages a minute",
+                    limits.messages_per_minute
+                );
+                return Ok(Some(invalid_chat_message(id, reason)));
+            }
+
+            if let Some(anchor) = anchor {
+                if let Some(reason) = state.validate_chat_anchor(&file_id, anchor).await? {
+                    return Ok(Some(invalid_chat_message(id, reason)));
+                }
+            }
+
+            let user = state.get_room(&file_id).await?.get_user(&session_id)?;
+            let message = ChatMessage {
+                id,
+                user_id: user.user_id,
+                session_id,
+                first_name: user.first_name,
+                last_name: user.last_name,
+                text,
+                mentions,
+                anchor,
+                timestamp: chrono::Utc::now(),
+            };
+
+            if !state
+                .add_chat_message(&file_id, &message, limits.history_size)
+                .await?
+            {
+                return Ok(Some(invalid_chat_message(id, "the id is taken".into())));
+            }
+
+            // the sender also receives the message, with its timestamp
+            let response = MessageResponse::ChatMessage { file_id, message };
+            broadcast(vec![], file_id, Arc::clone(&state), response);
+
+            Ok(None)
+        }
+
+        // User asks for the room's recent chat messages
+        MessageRequest::GetChatMessages {
+            session_id,
+            file_id,
+        } => {
+            validate_user_can_edit_or_view_file(Arc::clone(&state), file_id, session_id).await?;
+
+            // update the heartbeat
+            state.update_user_heartbeat(file_id, &session_id).await?;
+
+            let history_size = state.settings.chat_limits.history_size;
+            let messages = state.get_chat_messages(&file_id, history_size).await?;
+
+            Ok(Some(MessageResponse::ChatMessages { file_id, messages }))
+        }
+    }
+}
+
+/// The operations of a transaction as the user sent them, which are reused
+/// to broadcast it.
+enum SentOperations {
+    Json(String),
+    Binary(Vec<u8>),
+}
+
+/// Validates a transaction, adds it to the transaction queue and broadcasts
+/// it to the room.
+async fn sequence_transaction(
+    state: Arc<State>,
+    id: Uuid,
+    session_id: Uuid,
+    file_id: Uuid,
+    operations: Vec<Operation>,
+    sent: SentOperations,
+) -> Result<Option<MessageResponse>> {
+    let limits = &state.settings.transaction_limits;
+    let user = state.get_room(&file_id).await?.get_user(&session_id)?;
+
+    if let Err(reason) = validate_operations(&operations, limits) {
+        tracing::warn!("Transaction {id} in room {file_id} was rejected: {reason}");
+        return Ok(Some(invalid_transaction(id, reason)));
+    }
+
+    // JSON clients still receive the operations as JSON, which binary
+    // transactions are only converted to once they are valid
+    let (json, compressed) = match sent {
+        SentOperations::Json(json) => (json, None),
+        SentOperations::Binary(compressed) => {
+            (serde_json::to_string(&operations)?, Some(compressed))
+        }
+    };
+
+    // check the sheets and protections that the transaction changes and add
+    // it to the transaction queue, which allocates its sequence_num
+    let author = TransactionAuthor {
+        user_id: user.user_id,
+        session_id,
+        timestamp: chrono::Utc::now(),
+    };
+    let pushed = state
+        .push_protected(
+            id,
+            file_id,
+            operations,
+            author,
+            user.authenticated_user_id.as_deref(),
+        )
+        .await;
+    let sequence_num = match pushed {
+        Err(MpError::InvalidTransaction(_, reason)) => {
+            tracing::warn!("Transaction {id} in room {file_id} was rejected: {reason}");
+            return Ok(Some(invalid_transaction(id, reason)));
+        }
+        pushed => pushed?,
+    };
+    state.update_sequence_num(&file_id, sequence_num).await?;
+
+    // broadcast the transaction to all users in the room
+    let response = MessageResponse::Transaction {
+        id,
+        file_id,
+        operations: json,
+        sequence_num,
+        compressed,
+    };
+    broadcast(vec![], file_id, Arc::clone(&state), response);
+
+    Ok(None)
+}
+
+/// Response to a transaction that failed validation. The transaction is not
+/// sequenced, and the sender rolls it back.
+fn invalid_transaction(id: Uuid, reason: String) -> MessageResponse {
+    MessageResponse::Error {
+        error: MpError::InvalidTransaction(id, reason),
+        error_level: ErrorLevel::Warning,
+    }
+}
+
+/// Response to a chat message that was rejected. The message is not sent to
+/// the room.
+fn invalid_chat_message(id: Uuid, reason: String) -> MessageResponse {
+    MessageResponse::Error {
+        error: MpError::InvalidChatMessage(id, reason),
+        error_level: ErrorLevel::Warning,
     }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use quadratic_core::controller::edit_history::EditHistoryQuery;
     use quadratic_core::controller::operations::operation::Operation;
+    use quadratic_core::grid::Protection;
     use quadratic_core::grid::SheetId;
+    use quadratic_core::SheetPos;
     use tokio::net::TcpStream;
     use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
     use uuid::Uuid;
@@ -305,7 +538,9 @@ pub(crate) mod tests {
     use super::*;
     use crate::state::settings::MinVersion;
     use crate::state::user::{CellEdit, UserStateUpdate};
-    use crate::test_util::{integration_test_receive, new_user, setup};
+    use crate::test_util::{
+        add_user_to_room, integration_test_receive, new_user, setup, sheet_operation,
+    };
 
     async fn test_handle(
         socket: Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
@@ -393,11 +628,13 @@ pub(crate) mod tests {
             cell_edit: CellEdit::default(),
             viewport: "viewport".into(),
             follow: Some(Uuid::new_v4().to_string()),
+            protocol_version: None,
         };
 
         let response = MessageResponse::EnterRoom {
             file_id,
             sequence_num: 0,
+            protocol_version: 1,
         };
 
         let users_in_room = state.get_room(&file_id).await.unwrap().users;
@@ -457,7 +694,7 @@ pub(crate) mod tests {
         let id = Uuid::new_v4();
         let session_id = user_1.session_id;
         let operations = serde_json::to_string(&vec![Operation::SetSheetColor {
-            sheet_id: SheetId::new(),
+            sheet_id: SheetId::test(),
             color: Some("red".to_string()),
         }])
         .unwrap();
@@ -474,6 +711,7 @@ pub(crate) mod tests {
             file_id,
             operations: operations.clone(),
             sequence_num: 1,
+            compressed: None,
         };
 
         test_handle(
@@ -492,6 +730,7 @@ pub(crate) mod tests {
             file_id,
             session_id,
             min_sequence_num: 1,
+            catch_up: false,
         };
 
         let string_operations = operations.to_string();
@@ -512,6 +751,318 @@ pub(crate) mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn handle_invalid_transaction() {
+        let (socket, state, _, file_id, user_1, _) = setup().await;
+        let session_id = user_1.session_id;
+        let sheet_id = SheetId::test();
+
+        // delete a sheet
+        let request = MessageRequest::Transaction {
+            id: Uuid::new_v4(),
+            file_id,
+            session_id,
+            operations: serde_json::to_string(&vec![Operation::DeleteSheet { sheet_id }]).unwrap(),
+        };
+        let stream = state
+            ._get_user_in_room(&file_id, &session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+        let handled = handle_message(request, state.clone(), stream, PreConnection::new(None))
+            .await
+            .unwrap();
+        assert_eq!(handled, None);
+
+        // changes to the deleted sheet are rejected and not sequenced
+        let id = Uuid::new_v4();
+        let request = MessageRequest::Transaction {
+            id,
+            file_id,
+            session_id,
+            operations: serde_json::to_string(&vec![Operation::SetSheetName {
+                sheet_id,
+                name: "Deleted".into(),
+            }])
+            .unwrap(),
+        };
+        let response = MessageResponse::Error {
+            error: MpError::InvalidTransaction(
+                id,
+                format!("operation 0: sheet {sheet_id} does not exist"),
+            ),
+            error_level: ErrorLevel::Warning,
+        };
+        test_handle(
+            socket,
+            state.clone(),
+            file_id,
+            user_1,
+            request,
+            Some(response),
+            None,
+        )
+        .await;
+
+        assert_eq!(state.get_sequence_num(&file_id).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn handle_protected_transaction() {
+        let (socket, state, _, file_id, user_1, user_2) = setup().await;
+        let sheet_id = SheetId::test();
+
+        // user_2 protects the sheet
+        let protection = Protection::new(None, user_2.user_id.clone(), vec![]);
+        let request = MessageRequest::Transaction {
+            id: Uuid::new_v4(),
+            file_id,
+            session_id: user_2.session_id,
+            operations: serde_json::to_string(&vec![Operation::SetProtection {
+                sheet_id,
+                protection_id: protection.id,
+                protection: Some(protection.clone()),
+            }])
+            .unwrap(),
+        };
+        let stream = state
+            ._get_user_in_room(&file_id, &user_2.session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+        let handled = handle_message(request, state.clone(), stream, PreConnection::new(None))
+            .await
+            .unwrap();
+        assert_eq!(handled, None);
+        assert_eq!(
+            state.get_protections(&file_id).await.unwrap().sheets[&sheet_id],
+            vec![protection]
+        );
+
+        // user_1 cannot change it
+        let id = Uuid::new_v4();
+        let request = MessageRequest::Transaction {
+            id,
+            file_id,
+            session_id: user_1.session_id,
+            operations: serde_json::to_string(&vec![Operation::SetSheetName {
+                sheet_id,
+                name: "Mine".into(),
+            }])
+            .unwrap(),
+        };
+        let response = MessageResponse::Error {
+            error: MpError::InvalidTransaction(
+                id,
+                format!(
+                    "Protected: sheet {sheet_id}: the sheet can only be changed by the protection's owner and editors"
+                ),
+            ),
+            error_level: ErrorLevel::Warning,
+        };
+        test_handle(
+            socket,
+            state.clone(),
+            file_id,
+            user_1,
+            request,
+            Some(response),
+            None,
+        )
+        .await;
+
+        assert_eq!(state.get_sequence_num(&file_id).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn handle_unauthenticated_transaction() {
+        let (_, state, _, file_id, _, user_2) = setup().await;
+        let sheet_id = SheetId::test();
+        let stream = state
+            ._get_user_in_room(&file_id, &user_2.session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+
+        // user_2 protects the sheet
+        let protection = Protection::new(None, user_2.user_id.clone(), vec![]);
+        let request = MessageRequest::Transaction {
+            id: Uuid::new_v4(),
+            file_id,
+            session_id: user_2.session_id,
+            operations: serde_json::to_string(&vec![Operation::SetProtection {
+                sheet_id,
+                protection_id: protection.id,
+                protection: Some(protection.clone()),
+            }])
+            .unwrap(),
+        };
+        handle_message(
+            request,
+            state.clone(),
+            stream.clone(),
+            PreConnection::new(None),
+        )
+        .await
+        .unwrap();
+
+        // a user without a jwt cannot act as the owner by claiming their id
+        let impostor = User {
+            user_id: user_2.user_id.clone(),
+            authenticated_user_id: None,
+            ..new_user()
+        };
+        let impostor = add_user_to_room(file_id, impostor, state.clone()).await;
+        let id = Uuid::new_v4();
+        let request = MessageRequest::Transaction {
+            id,
+            file_id,
+            session_id: impostor.session_id,
+            operations: serde_json::to_string(&vec![Operation::SetProtection {
+                sheet_id,
+                protection_id: protection.id,
+                protection: None,
+            }])
+            .unwrap(),
+        };
+        let response = handle_message(request, state.clone(), stream, PreConnection::new(None))
+            .await
+            .unwrap();
+        assert!(matches!(
+            response,
+            Some(MessageResponse::Error {
+                error: MpError::InvalidTransaction(rejected, _),
+                ..
+            }) if rejected == id
+        ));
+        assert_eq!(
+            state.get_protections(&file_id).await.unwrap().sheets[&sheet_id],
+            vec![protection]
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_get_edit_history() {
+        let (_, state, _, file_id, user_1, _) = setup().await;
+        let session_id = user_1.session_id;
+        let sheet_id = SheetId::test();
+        let stream = state
+            ._get_user_in_room(&file_id, &session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+
+        let id = Uuid::new_v4();
+        let request = MessageRequest::Transaction {
+            id,
+            file_id,
+            session_id,
+            operations: serde_json::to_string(&vec![sheet_operation(sheet_id, 1, 6, "1")]).unwrap(),
+        };
+        handle_message(
+            request,
+            state.clone(),
+            stream.clone(),
+            PreConnection::new(None),
+        )
+        .await
+        .unwrap();
+
+        let request = MessageRequest::GetEditHistory {
+            session_id,
+            file_id,
+            query: EditHistoryQuery::Cell {
+                sheet_pos: SheetPos::new(sheet_id, 1, 6),
+                limit: 5,
+            },
+        };
+        let response = handle_message(request, state.clone(), stream, PreConnection::new(None))
+            .await
+            .unwrap();
+        let Some(MessageResponse::EditHistory { records, .. }) = response else {
+            panic!("expected an EditHistory response, got {response:?}");
+        };
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].transaction_id, id);
+        assert_eq!(records[0].user_id, user_1.user_id);
+        assert_eq!(records[0].session_id, session_id);
+    }
+
+    #[tokio::test]
+    async fn handle_chat_messages() {
+        let (socket, state, _, file_id, user_1, user_2) = setup().await;
+        let session_id = user_1.session_id;
+        let anchor = SheetPos::new(SheetId::test(), 1, 2);
+
+        let id = Uuid::new_v4();
+        let request = MessageRequest::ChatMessage {
+            id,
+            session_id,
+            file_id,
+            text: " look at this ".into(),
+            mentions: vec![user_2.user_id.clone(), user_2.user_id.clone()],
+            anchor: Some(anchor),
+        };
+        let stream = state
+            ._get_user_in_room(&file_id, &session_id)
+            .await
+            .unwrap()
+            .socket
+            .unwrap();
+        let handled = handle_message(
+            request.clone(),
+            state.clone(),
+            stream.clone(),
+            PreConnection::new(None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(handled, None);
+
+        // late joiners get the message from the history
+        let request_history = MessageRequest::GetChatMessages {
+            session_id,
+            file_id,
+        };
+        let response = handle_message(
+            request_history,
+            state.clone(),
+            stream.clone(),
+            PreConnection::new(None),
+        )
+        .await
+        .unwrap();
+        let Some(MessageResponse::ChatMessages { messages, .. }) = response else {
+            panic!("expected a ChatMessages response, got {response:?}");
+        };
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, id);
+        assert_eq!(messages[0].user_id, user_1.user_id);
+        assert_eq!(messages[0].text, "look at this");
+        assert_eq!(messages[0].mentions, vec![user_2.user_id.clone()]);
+        assert_eq!(messages[0].anchor, Some(anchor));
+
+        // ids cannot be reused
+        let response = MessageResponse::Error {
+            error: MpError::InvalidChatMessage(id, "the id is taken".into()),
+            error_level: ErrorLevel::Warning,
+        };
+        test_handle(
+            socket,
+            state,
+            file_id,
+            user_1,
+            request,
+            Some(response),
+            None,
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn handle_missing_transactions() {
         let (socket, state, _, file_id, user_1, _) = setup().await;
@@ -521,12 +1072,11 @@ pub(crate) mod tests {
             file_id,
             session_id,
             min_sequence_num: 1,
+            catch_up: false,
         };
 
         // increment the sequence_num
-        get_mut_room!(state, file_id)
-            .unwrap()
-            .increment_sequence_num();
+        state.update_sequence_num(&file_id, 1).await.unwrap();
 
         let response = MessageResponse::Error {
             error: MpError::MissingTransactions("1".into(), "0".into()), // requested 1, got 0
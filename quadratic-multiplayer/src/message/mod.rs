@@ -1,17 +1,21 @@
 use axum::extract::ws::Message;
 use futures_util::SinkExt;
 use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, HashMap};
 use std::sync::Arc;
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 use crate::error::MpError;
-use crate::message::response::MessageResponse;
+use crate::message::{protocol::Protocol, response::MessageResponse};
 use crate::state::State;
 
+pub mod catch_up;
 pub mod handle;
+pub mod protocol;
 pub mod request;
 pub mod response;
+pub mod validate;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub(crate) struct CellEdit {
@@ -48,7 +52,8 @@ pub(crate) struct UserStateUpdate {
     pub viewport: Option<String>,
 }
 
-/// Broadcast a message to all users in a room except the sender.
+/// Broadcast a message to all users in a room except the sender, including
+/// users on other instances.
 /// All messages are sent in a separate thread.
 #[tracing::instrument(level = "trace")]
 pub(crate) fn broadcast(
@@ -64,19 +69,53 @@ pub(crate) fn broadcast(
         message
     );
 
+    tokio::spawn(async move {
+        let published = state
+            .publish_broadcast(exclude.to_owned(), file_id, message.to_owned())
+            .await;
+
+        if let Err(e) = published {
+            tracing::warn!("Error publishing broadcast: {:?}", e.to_string());
+        }
+
+        if let Err(e) = broadcast_local(exclude, file_id, state, message).await {
+            tracing::warn!("Error broadcasting message: {:?}", e.to_string());
+        }
+    })
+}
+
+/// Broadcast a message to the users of a room on this instance, except the
+/// sender.
+/// All messages are sent in a separate thread.
+#[tracing::instrument(level = "trace")]
+pub(crate) fn broadcast_local(
+    exclude: Vec<Uuid>,
+    file_id: Uuid,
+    state: Arc<State>,
+    message: MessageResponse,
+) -> JoinHandle<()> {
     tokio::spawn(async move {
         if let Ok(room) = state.get_room(&file_id).await {
             let result = async {
+                // encode the message once for each protocol in the room
+                let mut encoded: HashMap<Protocol, Message> = HashMap::new();
+
                 for user in room
                     .users
                     .iter()
                     .filter(|user| !exclude.contains(&user.session_id))
                 {
                     if let Some(sender) = &user.socket {
+                        let encoded_message = match encoded.entry(user.protocol) {
+                            Entry::Occupied(entry) => entry.get().to_owned(),
+                            Entry::Vacant(entry) => {
+                                entry.insert(message.to_message(user.protocol)?).to_owned()
+                            }
+                        };
                         let sent = sender
                             .lock()
                             .await
-                            .send(Message::Text(serde_json::to_string(&message)?))
+                            .send(encoded_message)
                             .await
                             .map_err(|e| MpError::SendingMessage(e.to_string()));
 
@@ -120,7 +159,7 @@ pub(crate) fn send_user_message(
                     sender
                         .lock()
                         .await
-                        .send(Message::Text(serde_json::to_string(&message)?))
+                        .send(message.to_message(user.protocol)?)
                         .await
                         .map_err(|e| MpError::SendingMessage(e.to_string()))?;
                 }
@@ -0,0 +1,312 @@
+//! Websocket Protocol
+//!
+//! Clients that support it negotiate a binary protocol when entering a room.
+//! In the binary protocol, transactions are sent as binary messages with
+//! compressed operations, and all other messages are still sent as JSON.
+//!
+//! A binary message is the length of its header as a big-endian u32, the JSON
+//! header, and the compressed operations or transactions (see
+//! `quadratic_core::controller::transaction::serialize_and_compress`).
+
+use axum::extract::ws::Message;
+use quadratic_core::controller::operations::operation::Operation;
+use quadratic_core::controller::transaction::{
+    decompress_and_deserialize_with_limit, serialize_and_compress, TransactionServer,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{MpError, Result};
+use crate::message::{request::MessageRequest, response::MessageResponse};
+use crate::state::settings::MinVersion;
+
+/// JSON messages only.
+pub(crate) const JSON_PROTOCOL_VERSION: u32 = 1;
+
+/// Transactions are sent as binary messages.
+pub(crate) const BINARY_PROTOCOL_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum Protocol {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl Protocol {
+    /// Chooses the highest protocol that both the client and the server
+    /// support. Clients that don't send a version only support JSON.
+    pub(crate) fn negotiate(client_version: Option<u32>, min_version: &MinVersion) -> Self {
+        let version = client_version
+            .unwrap_or(JSON_PROTOCOL_VERSION)
+            .min(min_version.protocol_version);
+
+        if version >= BINARY_PROTOCOL_VERSION {
+            Protocol::Binary
+        } else {
+            Protocol::Json
+        }
+    }
+
+    pub(crate) fn version(&self) -> u32 {
+        match self {
+            Protocol::Json => JSON_PROTOCOL_VERSION,
+            Protocol::Binary => BINARY_PROTOCOL_VERSION,
+        }
+    }
+}
+
+/// Header of a binary request. The compressed operations follow it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub(crate) enum BinaryRequestHeader {
+    Transaction {
+        id: Uuid,
+        session_id: Uuid,
+        file_id: Uuid,
+    },
+}
+
+/// Header of a binary response. The compressed operations or transactions
+/// follow it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub(crate) enum BinaryResponseHeader {
+    Transaction {
+        id: Uuid,
+        file_id: Uuid,
+        sequence_num: u64,
+    },
+    Transactions,
+}
+
+fn binary_error(error: impl ToString) -> MpError {
+    MpError::Serialization(error.to_string())
+}
+
+/// Writes a binary message from its header and compressed body.
+pub(crate) fn encode_binary<H: Serialize>(header: &H, body: &[u8]) -> Result<Vec<u8>> {
+    let header = serde_json::to_vec(header)?;
+    let header_len = u32::try_from(header.len()).map_err(binary_error)?;
+
+    let mut bytes = Vec::with_capacity(4 + header.len() + body.len());
+    bytes.extend_from_slice(&header_len.to_be_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(body);
+
+    Ok(bytes)
+}
+
+/// Reads the header and compressed body of a binary message.
+pub(crate) fn decode_binary<H: DeserializeOwned>(bytes: &[u8]) -> Result<(H, &[u8])> {
+    let (header_len, rest) = bytes
+        .split_first_chunk::<4>()
+        .ok_or_else(|| binary_error("Binary message is missing its header"))?;
+    let header_len = u32::from_be_bytes(*header_len) as usize;
+
+    if rest.len() < header_len {
+        return Err(binary_error("Binary message header is truncated"));
+    }
+
+    let (header, body) = rest.split_at(header_len);
+
+    Ok((serde_json::from_slice(header)?, body))
+}
+
+impl MessageRequest {
+    /// Reads a binary request. The compressed operations are kept with the
+    /// decoded ones, so they can be broadcast to binary clients without
+    /// compressing them again. Operations that decompress to more than
+    /// `max_bytes` are rejected.
+    pub(crate) fn from_binary(bytes: &[u8], max_bytes: usize) -> Result<Self> {
+        let (header, body) = decode_binary::<BinaryRequestHeader>(bytes)?;
+        let operations: Vec<Operation> =
+            decompress_and_deserialize_with_limit(body, max_bytes).map_err(binary_error)?;
+
+        match header {
+            BinaryRequestHeader::Transaction {
+                id,
+                session_id,
+                file_id,
+            } => Ok(MessageRequest::BinaryTransaction {
+                id,
+                session_id,
+                file_id,
+                operations,
+                compressed: body.to_vec(),
+            }),
+        }
+    }
+}
+
+impl MessageResponse {
+    /// Encodes the response for a protocol. Only transactions are sent as
+    /// binary messages, reusing the compressed operations that the sender
+    /// sent if there are any.
+    pub(crate) fn to_message(&self, protocol: Protocol) -> Result<Message> {
+        let binary = match (protocol, self) {
+            (
+                Protocol::Binary,
+                MessageResponse::Transaction {
+                    id,
+                    file_id,
+                    sequence_num,
+                    operations,
+                    compressed,
+                },
+            ) => {
+                let header = BinaryResponseHeader::Transaction {
+                    id: *id,
+                    file_id: *file_id,
+                    sequence_num: *sequence_num,
+                };
+                let body = match compressed {
+                    Some(compressed) => compressed.to_owned(),
+                    None => {
+                        let operations = serde_json::from_str::<Vec<Operation>>(operations)?;
+                        serialize_and_compress(&operations).map_err(binary_error)?
+                    }
+                };
+                Some((header, body))
+            }
+            (Protocol::Binary, MessageResponse::Transactions { transactions }) => {
+                let transactions = serde_json::from_str::<Vec<TransactionServer>>(transactions)?;
+                let body = serialize_and_compress(&transactions).map_err(binary_error)?;
+                Some((BinaryResponseHeader::Transactions, body))
+            }
+            _ => None,
+        };
+
+        match binary {
+            Some((header, body)) => Ok(Message::Binary(encode_binary(&header, &body)?)),
+            None => Ok(Message::Text(serde_json::to_string(self)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::controller::{transaction::decompress_and_deserialize, GridController};
+
+    use super::*;
+    use crate::state::settings::TransactionLimits;
+    use crate::test_util::operation;
+
+    fn min_version(protocol_version: u32) -> MinVersion {
+        MinVersion {
+            protocol_version,
+            ..MinVersion::new().unwrap()
+        }
+    }
+
+    #[test]
+    fn negotiates_the_protocol() {
+        let binary = min_version(BINARY_PROTOCOL_VERSION);
+        assert_eq!(Protocol::negotiate(None, &binary), Protocol::Json);
+        assert_eq!(Protocol::negotiate(Some(1), &binary), Protocol::Json);
+        assert_eq!(Protocol::negotiate(Some(2), &binary), Protocol::Binary);
+        assert_eq!(Protocol::negotiate(Some(3), &binary), Protocol::Binary);
+
+        // the server can turn off the binary protocol
+        let json = min_version(JSON_PROTOCOL_VERSION);
+        assert_eq!(Protocol::negotiate(Some(2), &json), Protocol::Json);
+    }
+
+    #[test]
+    fn encodes_binary_transactions() {
+        let mut grid = GridController::test();
+        let operations = vec![
+            operation(&mut grid, 0, 0, "1"),
+            operation(&mut grid, 1, 0, "2"),
+        ];
+        let id = Uuid::new_v4();
+        let file_id = Uuid::new_v4();
+        let response = MessageResponse::Transaction {
+            id,
+            file_id,
+            sequence_num: 3,
+            operations: serde_json::to_string(&operations).unwrap(),
+            compressed: None,
+        };
+
+        // JSON clients receive text
+        let Message::Text(text) = response.to_message(Protocol::Json).unwrap() else {
+            panic!("expected a text message");
+        };
+        assert_eq!(
+            serde_json::from_str::<MessageResponse>(&text).unwrap(),
+            response
+        );
+
+        // binary clients receive compressed operations
+        let Message::Binary(bytes) = response.to_message(Protocol::Binary).unwrap() else {
+            panic!("expected a binary message");
+        };
+        let (header, body) = decode_binary::<BinaryResponseHeader>(&bytes).unwrap();
+        assert_eq!(
+            header,
+            BinaryResponseHeader::Transaction {
+                id,
+                file_id,
+                sequence_num: 3
+            }
+        );
+        let decoded: Vec<Operation> = decompress_and_deserialize(body).unwrap();
+        assert_eq!(decoded, operations);
+
+        // compressed operations from a binary client are sent as they are
+        let compressed = serialize_and_compress(&operations[..1].to_vec()).unwrap();
+        let response = MessageResponse::Transaction {
+            id,
+            file_id,
+            sequence_num: 3,
+            operations: serde_json::to_string(&operations).unwrap(),
+            compressed: Some(compressed.to_owned()),
+        };
+        let Message::Binary(bytes) = response.to_message(Protocol::Binary).unwrap() else {
+            panic!("expected a binary message");
+        };
+        let (_, body) = decode_binary::<BinaryResponseHeader>(&bytes).unwrap();
+        assert_eq!(body, compressed);
+
+        // other messages are always sent as JSON
+        let response = MessageResponse::CurrentTransaction { sequence_num: 3 };
+        assert!(matches!(
+            response.to_message(Protocol::Binary).unwrap(),
+            Message::Text(_)
+        ));
+    }
+
+    #[test]
+    fn decodes_binary_requests() {
+        let mut grid = GridController::test();
+        let operations = vec![operation(&mut grid, 0, 0, "1")];
+        let id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+        let file_id = Uuid::new_v4();
+        let header = BinaryRequestHeader::Transaction {
+            id,
+            session_id,
+            file_id,
+        };
+        let compressed = serialize_and_compress(&operations).unwrap();
+        let bytes = encode_binary(&header, &compressed).unwrap();
+
+        let max_bytes = TransactionLimits::default().max_bytes;
+        assert_eq!(
+            MessageRequest::from_binary(&bytes, max_bytes).unwrap(),
+            MessageRequest::BinaryTransaction {
+                id,
+                session_id,
+                file_id,
+                operations,
+                compressed,
+            }
+        );
+
+        // truncated and oversized messages are rejected
+        assert!(MessageRequest::from_binary(&bytes[..2], max_bytes).is_err());
+        assert!(MessageRequest::from_binary(&bytes[..10], max_bytes).is_err());
+        assert!(MessageRequest::from_binary(&bytes, 10).is_err());
+    }
+}
@@ -2,6 +2,9 @@
 //!
 //! A central place for websocket messages requests.
 
+use quadratic_core::controller::edit_history::EditHistoryQuery;
+use quadratic_core::controller::operations::operation::Operation;
+use quadratic_core::SheetPos;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -24,6 +27,10 @@ pub(crate) enum MessageRequest {
         cell_edit: CellEdit,
         viewport: String,
         follow: Option<String>,
+
+        // highest protocol version that the client supports
+        #[serde(default)]
+        protocol_version: Option<u32>,
     },
     LeaveRoom {
         session_id: Uuid,
@@ -40,13 +47,51 @@ pub(crate) enum MessageRequest {
         file_id: Uuid,
         operations: String,
     },
+
+    // a transaction that was sent as a binary message (see `protocol`), with
+    // its decoded operations and the compressed operations as they were sent
+    #[serde(skip)]
+    BinaryTransaction {
+        id: Uuid,
+        session_id: Uuid,
+        file_id: Uuid,
+        operations: Vec<Operation>,
+        compressed: Vec<u8>,
+    },
     GetTransactions {
         file_id: Uuid,
         session_id: Uuid,
         min_sequence_num: u64,
+
+        // the client can load a checkpoint and page through transactions
+        #[serde(default)]
+        catch_up: bool,
     },
     Heartbeat {
         session_id: Uuid,
         file_id: Uuid,
     },
+    GetEditHistory {
+        session_id: Uuid,
+        file_id: Uuid,
+        query: EditHistoryQuery,
+    },
+    ChatMessage {
+        id: Uuid,
+        session_id: Uuid,
+        file_id: Uuid,
+        text: String,
+
+        // user ids of the mentioned users
+        #[serde(default)]
+        mentions: Vec<String>,
+
+        // the cell that the message is about
+        #[serde(default)]
+        anchor: Option<SheetPos>,
+    },
+    GetChatMessages {
+        session_id: Uuid,
+        file_id: Uuid,
+    },
 }
@@ -3,9 +3,11 @@
 //! A central place for websocket messages responses.
 
 use crate::error::{ErrorLevel, MpError};
+use crate::message::catch_up::Checkpoint;
+use crate::state::chat::ChatMessage;
 use crate::state::settings::MinVersion;
 use crate::state::user::{User, UserStateUpdate};
-use dashmap::DashMap;
+use quadratic_core::controller::edit_history::EditRecord;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -27,27 +29,51 @@ pub(crate) enum MessageResponse {
         file_id: Uuid,
         sequence_num: u64,
         operations: String,
+
+        // the compressed operations, if the transaction was sent as a binary
+        // message, so they are sent to binary clients as they are
+        #[serde(skip)]
+        compressed: Option<Vec<u8>>,
     },
     Transactions {
         transactions: String,
     },
+    CatchUp {
+        file_id: Uuid,
+        checkpoint: Option<Checkpoint>,
+        transactions: String,
+        next_sequence_num: Option<u64>,
+    },
     EnterRoom {
         file_id: Uuid,
         sequence_num: u64,
+        protocol_version: u32,
     },
     CurrentTransaction {
         sequence_num: u64,
     },
+    EditHistory {
+        file_id: Uuid,
+        records: Vec<EditRecord>,
+    },
+    ChatMessage {
+        file_id: Uuid,
+        message: ChatMessage,
+    },
+    ChatMessages {
+        file_id: Uuid,
+        messages: Vec<ChatMessage>,
+    },
     Error {
         error: MpError,
         error_level: ErrorLevel,
     },
 }
 
-impl From<(DashMap<Uuid, User>, &MinVersion)> for MessageResponse {
-    fn from((users, min_version): (DashMap<Uuid, User>, &MinVersion)) -> Self {
+impl From<(Vec<User>, &MinVersion)> for MessageResponse {
+    fn from((users, min_version): (Vec<User>, &MinVersion)) -> Self {
         MessageResponse::UsersInRoom {
-            users: users.into_iter().map(|user| (user.1)).collect(),
+            users,
             min_version: min_version.to_owned(),
         }
     }
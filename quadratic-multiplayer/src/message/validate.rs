@@ -0,0 +1,626 @@
+//! Transaction Validation
+//!
+//! The server does not hold a copy of the grid, so transactions are checked
+//! structurally before they are sequenced and broadcast: sizes and positions
+//! must be sane, payloads must be within the configured limits, and only
+//! sheets that exist in the file can be changed.
+
+use std::collections::HashSet;
+
+use quadratic_core::cell_values::CellValues;
+use quadratic_core::controller::operations::operation::Operation;
+use quadratic_core::grid::{CodeRun, Protection, Sheet, SheetId};
+use quadratic_core::selection::Selection;
+use quadratic_core::{CellValue, Pos, Rect, SheetRect};
+
+use crate::state::settings::TransactionLimits;
+
+/// Positions beyond this are rejected.
+pub(crate) const MAX_COORDINATE: i64 = i32::MAX as i64;
+
+/// Column widths and row heights beyond this are rejected.
+pub(crate) const MAX_SIZE: f64 = 100_000.0;
+
+/// Sheet names, colors, and order keys longer than this are rejected.
+pub(crate) const MAX_SHEET_STRING_LENGTH: usize = 1_024;
+
+/// Protections with more editors than this are rejected.
+pub(crate) const MAX_PROTECTION_EDITORS: usize = 1_000;
+
+/// Code, and the output that code runs print, longer than this (in bytes) is
+/// rejected.
+pub(crate) const MAX_CODE_LENGTH: usize = 1_000_000;
+
+type ValidationResult = std::result::Result<(), String>;
+
+pub(crate) fn validate_pos(pos: Pos) -> ValidationResult {
+    if pos.x.abs() > MAX_COORDINATE || pos.y.abs() > MAX_COORDINATE {
+        return Err(format!("position ({}, {}) is out of bounds", pos.x, pos.y));
+    }
+    Ok(())
+}
+
+/// Validates a rect and returns the number of cells in it.
+fn validate_rect(min: Pos, max: Pos) -> std::result::Result<u64, String> {
+    validate_pos(min)?;
+    validate_pos(max)?;
+    if min.x > max.x || min.y > max.y {
+        return Err("rect has a negative size".into());
+    }
+    Ok((max.x - min.x + 1) as u64 * (max.y - min.y + 1) as u64)
+}
+
+fn validate_cell_count(count: u64, limits: &TransactionLimits) -> ValidationResult {
+    if count > limits.max_cells {
+        return Err(format!(
+            "operation changes {count} cells, more than the limit of {}",
+            limits.max_cells
+        ));
+    }
+    Ok(())
+}
+
+fn validate_sheet_rect(sheet_rect: &SheetRect, limits: &TransactionLimits) -> ValidationResult {
+    validate_cell_count(validate_rect(sheet_rect.min, sheet_rect.max)?, limits)
+}
+
+fn validate_code(name: &str, code: &str) -> ValidationResult {
+    if code.len() > MAX_CODE_LENGTH {
+        return Err(format!(
+            "{name} is {} bytes, more than the limit of {MAX_CODE_LENGTH}",
+            code.len()
+        ));
+    }
+    Ok(())
+}
+
+fn validate_cell_value(value: &CellValue) -> ValidationResult {
+    match value {
+        CellValue::Code(code) => validate_code("code", &code.code),
+        _ => Ok(()),
+    }
+}
+
+fn validate_cell_values(values: &CellValues, limits: &TransactionLimits) -> ValidationResult {
+    validate_cell_count(values.w as u64 * values.h as u64, limits)?;
+    if values.columns.len() != values.w as usize {
+        return Err("cell values have the wrong number of columns".into());
+    }
+    if values
+        .columns
+        .iter()
+        .any(|column| column.keys().any(|y| *y >= values.h as u64))
+    {
+        return Err("cell values are outside of their size".into());
+    }
+    values
+        .columns
+        .iter()
+        .flat_map(|column| column.values())
+        .try_for_each(validate_cell_value)
+}
+
+/// Validates the output of a code cell at `pos` and returns the number of
+/// cells in it.
+fn validate_code_run(
+    pos: Pos,
+    code_run: &CodeRun,
+    limits: &TransactionLimits,
+) -> std::result::Result<u64, String> {
+    let size = code_run.output_size();
+    let cells = size.len() as u64;
+    validate_cell_count(cells, limits)?;
+    validate_pos(Pos {
+        x: pos.x.saturating_add(size.w.get() as i64),
+        y: pos.y.saturating_add(size.h.get() as i64),
+    })?;
+
+    let texts = [
+        ("formatted code", &code_run.formatted_code_string),
+        ("standard output", &code_run.std_out),
+        ("standard error", &code_run.std_err),
+    ];
+    for (name, text) in texts {
+        validate_code(name, text.as_deref().unwrap_or_default())?;
+    }
+    Ok(cells)
+}
+
+/// Validates a selection and returns the number of cells in its largest rect.
+fn validate_selection(selection: &Selection) -> std::result::Result<u64, String> {
+    let mut cells = 0;
+    for Rect { min, max } in selection.rects.iter().flatten() {
+        cells = cells.max(validate_rect(*min, *max)?);
+    }
+    let lines = selection.rows.iter().chain(selection.columns.iter());
+    for line in lines.flatten() {
+        validate_pos(Pos { x: *line, y: 0 })?;
+    }
+    Ok(cells)
+}
+
+fn validate_size(size: f64) -> ValidationResult {
+    if !size.is_finite() || !(0.0..=MAX_SIZE).contains(&size) {
+        return Err(format!("size {size} is out of bounds"));
+    }
+    Ok(())
+}
+
+fn validate_sheet_string(name: &str, value: &str) -> ValidationResult {
+    if value.len() > MAX_SHEET_STRING_LENGTH {
+        return Err(format!("sheet {name} is too long"));
+    }
+    Ok(())
+}
+
+fn validate_sheet_name(name: &str) -> ValidationResult {
+    if name.trim().is_empty() {
+        return Err("sheet name is empty".into());
+    }
+    validate_sheet_string("name", name)
+}
+
+fn validate_protection(protection: &Protection) -> ValidationResult {
+    if let Some(Rect { min, max }) = protection.rect {
+        validate_rect(min, max)?;
+    }
+    if protection.editors.len() > MAX_PROTECTION_EDITORS {
+        return Err(format!(
+            "protection has {} editors, more than the limit of {MAX_PROTECTION_EDITORS}",
+            protection.editors.len()
+        ));
+    }
+    let users = std::iter::once(&protection.owner).chain(protection.editors.iter());
+    for user in users {
+        validate_sheet_string("protection user", user)?;
+    }
+    Ok(())
+}
+
+/// Validates a sheet that is added. Its cells and code outputs count towards
+/// the operation's cell limit.
+fn validate_sheet(sheet: &Sheet, limits: &TransactionLimits) -> ValidationResult {
+    validate_sheet_name(&sheet.name)?;
+    validate_sheet_string("color", sheet.color.as_deref().unwrap_or_default())?;
+    validate_sheet_string("order", &sheet.order)?;
+
+    let mut cells = 0;
+    for (x, column) in sheet.columns.iter() {
+        for (y, value) in column.values.iter() {
+            validate_pos(Pos { x: *x, y: *y })?;
+            validate_cell_value(value)?;
+        }
+        cells += column.values.len() as u64;
+    }
+    for (pos, code_run) in sheet.code_runs.iter() {
+        validate_pos(*pos)?;
+        cells += validate_code_run(*pos, code_run, limits)?;
+    }
+    validate_cell_count(cells, limits)?;
+
+    sheet.protections.iter().try_for_each(validate_protection)
+}
+
+fn validate_operation(operation: &Operation, limits: &TransactionLimits) -> ValidationResult {
+    match operation {
+        Operation::SetCellValues { sheet_pos, values } => {
+            validate_pos((*sheet_pos).into())?;
+            validate_pos(Pos {
+                x: sheet_pos.x.saturating_add(values.w as i64),
+                y: sheet_pos.y.saturating_add(values.h as i64),
+            })?;
+            validate_cell_values(values, limits)
+        }
+        Operation::SetCodeRun {
+            sheet_pos,
+            code_run,
+            ..
+        } => {
+            validate_pos((*sheet_pos).into())?;
+            match code_run {
+                Some(code_run) => {
+                    validate_code_run((*sheet_pos).into(), code_run, limits).map(|_| ())
+                }
+                None => Ok(()),
+            }
+        }
+        Operation::ComputeCode { sheet_pos } => validate_pos((*sheet_pos).into()),
+        Operation::SetCellFormats { sheet_rect, .. } => validate_sheet_rect(sheet_rect, limits),
+        Operation::SetCellFormatsSelection { selection, formats } => {
+            validate_cell_count(validate_selection(selection)?, limits)?;
+            validate_cell_count(formats.formats.size() as u64, limits)
+        }
+        Operation::SetBorders { sheet_rect, .. } => validate_sheet_rect(sheet_rect, limits),
+        Operation::MoveCells { source, dest } => {
+            validate_sheet_rect(source, limits)?;
+            validate_pos((*dest).into())
+        }
+        Operation::AddSheet { sheet } => validate_sheet(sheet, limits),
+        Operation::AddSheetSchema { schema } => {
+            let sheet = schema
+                .into_latest()
+                .map_err(|error| format!("sheet cannot be read: {error}"))?;
+            validate_sheet(&sheet, limits)
+        }
+        Operation::DuplicateSheet {
+            sheet_id,
+            new_sheet_id,
+        } => {
+            if sheet_id == new_sheet_id {
+                return Err("a sheet cannot be duplicated onto itself".into());
+            }
+            Ok(())
+        }
+        Operation::SetSheetName { name, .. } => validate_sheet_name(name),
+        Operation::SetSheetColor { color, .. } => {
+            validate_sheet_string("color", color.as_deref().unwrap_or_default())
+        }
+        Operation::ReorderSheet { order, .. } => validate_sheet_string("order", order),
+        Operation::ResizeColumn {
+            column: line,
+            new_size,
+            ..
+        }
+        | Operation::ResizeRow {
+            row: line,
+            new_size,
+            ..
+        } => {
+            validate_pos(Pos { x: *line, y: 0 })?;
+            validate_size(*new_size)
+        }
+        // cursors can select whole sheets, so only their positions are checked
+        Operation::SetCursor { sheet_rect } => {
+            validate_rect(sheet_rect.min, sheet_rect.max).map(|_| ())
+        }
+        Operation::SetCursorSelection { selection } => validate_selection(selection).map(|_| ()),
+        Operation::SetProtection { protection, .. } => {
+            protection.as_ref().map_or(Ok(()), validate_protection)
+        }
+        // the sheet must exist, which is checked by `validate_sheet_ids`
+        Operation::DeleteSheet { .. } => Ok(()),
+    }
+}
+
+/// Validates the operations of a transaction, apart from the sheets that
+/// they change, which are checked by `validate_sheet_ids`.
+///
+/// Returns the reason that the transaction is invalid.
+pub(crate) fn validate_operations(
+    operations: &[Operation],
+    limits: &TransactionLimits,
+) -> ValidationResult {
+    if operations.len() > limits.max_operations {
+        return Err(format!(
+            "transaction has {} operations, more than the limit of {}",
+            operations.len(),
+            limits.max_operations
+        ));
+    }
+
+    for (index, operation) in operations.iter().enumerate() {
+        validate_operation(operation, limits)
+            .map_err(|error| format!("operation {index}: {error}"))?;
+    }
+
+    Ok(())
+}
+
+/// Returns the sheets that must exist for an operation to apply, including
+/// sheets whose metadata it changes. Cursor operations are not included.
+fn referenced_sheets(operation: &Operation) -> Vec<SheetId> {
+    match operation {
+        Operation::SetSheetName { sheet_id, .. } | Operation::SetSheetColor { sheet_id, .. } => {
+            vec![*sheet_id]
+        }
+        Operation::ReorderSheet { target, .. } => vec![*target],
+        operation => operation.sheet_ids(),
+    }
+}
+
+/// Returns the sheet that an operation adds.
+fn added_sheet(operation: &Operation) -> Option<SheetId> {
+    match operation {
+        Operation::AddSheet { sheet } => Some(sheet.id),
+        Operation::AddSheetSchema { schema } => Some(schema.sheet_id()),
+        Operation::DuplicateSheet { new_sheet_id, .. } => Some(*new_sheet_id),
+        _ => None,
+    }
+}
+
+/// Checks that the operations of a transaction only change sheets that exist
+/// and only add sheets that don't. `known_sheets` are the sheets of the file
+/// before the transaction, and is updated with the sheets that it adds and
+/// deletes.
+///
+/// Returns the reason that the transaction is invalid.
+pub(crate) fn validate_sheet_ids(
+    operations: &[Operation],
+    known_sheets: &mut HashSet<SheetId>,
+) -> ValidationResult {
+    for (index, operation) in operations.iter().enumerate() {
+        if let Some(sheet_id) = referenced_sheets(operation)
+            .iter()
+            .find(|sheet_id| !known_sheets.contains(sheet_id))
+        {
+            return Err(format!(
+                "operation {index}: sheet {sheet_id} does not exist"
+            ));
+        }
+
+        if let Some(sheet_id) = added_sheet(operation) {
+            if !known_sheets.insert(sheet_id) {
+                return Err(format!(
+                    "operation {index}: sheet {sheet_id} already exists"
+                ));
+            }
+        }
+        if let Operation::DeleteSheet { sheet_id } = operation {
+            known_sheets.remove(sheet_id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates the size of serialized operations before they are parsed.
+pub(crate) fn validate_operations_size(
+    operations: &str,
+    limits: &TransactionLimits,
+) -> ValidationResult {
+    if operations.len() > limits.max_bytes {
+        return Err(format!(
+            "transaction is {} bytes, more than the limit of {}",
+            operations.len(),
+            limits.max_bytes
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_core::grid::file::sheet_schema::export_sheet;
+    use quadratic_core::grid::{CodeCellLanguage, CodeRunResult};
+    use quadratic_core::{CodeCellValue, SheetPos, Value};
+
+    use super::*;
+
+    fn validate(operations: Vec<Operation>) -> ValidationResult {
+        validate_operations(&operations, &TransactionLimits::default())
+    }
+
+    #[test]
+    fn accepts_valid_operations() {
+        let sheet_id = SheetId::new();
+        let operations = vec![
+            Operation::SetCellValues {
+                sheet_pos: SheetPos::new(sheet_id, 1, 2),
+                values: CellValues::from(CellValue::Text("hello".into())),
+            },
+            Operation::ResizeColumn {
+                sheet_id,
+                column: 1,
+                new_size: 120.0,
+                client_resized: false,
+            },
+            Operation::SetSheetName {
+                sheet_id,
+                name: "Data".into(),
+            },
+        ];
+        assert_eq!(validate(operations), Ok(()));
+    }
+
+    #[test]
+    fn rejects_invalid_operations() {
+        let sheet_id = SheetId::new();
+        let invalid = |operation: Operation| validate(vec![operation]).unwrap_err();
+
+        assert_eq!(
+            invalid(Operation::ResizeRow {
+                sheet_id,
+                row: 0,
+                new_size: f64::NAN,
+                client_resized: false,
+            }),
+            "operation 0: size NaN is out of bounds"
+        );
+        assert_eq!(
+            invalid(Operation::ComputeCode {
+                sheet_pos: SheetPos::new(sheet_id, i64::MAX, 0),
+            }),
+            format!("operation 0: position ({}, 0) is out of bounds", i64::MAX)
+        );
+        assert_eq!(
+            invalid(Operation::SetBorders {
+                sheet_rect: SheetRect::new_pos_span(
+                    Pos { x: 0, y: 0 },
+                    Pos {
+                        x: 100_000,
+                        y: 100_000
+                    },
+                    sheet_id
+                ),
+                borders: Default::default(),
+            }),
+            "operation 0: operation changes 10000200001 cells, more than the limit of 10000000"
+        );
+        assert_eq!(
+            invalid(Operation::SetCellValues {
+                sheet_pos: SheetPos::new(sheet_id, 0, 0),
+                values: CellValues {
+                    columns: vec![],
+                    w: 1,
+                    h: 1,
+                },
+            }),
+            "operation 0: cell values have the wrong number of columns"
+        );
+        assert_eq!(
+            invalid(Operation::SetSheetName {
+                sheet_id,
+                name: " ".into(),
+            }),
+            "operation 0: sheet name is empty"
+        );
+        assert_eq!(
+            invalid(Operation::SetProtection {
+                sheet_id,
+                protection_id: Default::default(),
+                protection: Some(Protection::new(
+                    Some(Rect::new(2, 2, 1, 1)),
+                    "owner".into(),
+                    vec![],
+                )),
+            }),
+            "operation 0: rect has a negative size"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_sheets() {
+        let sheet = Sheet::test();
+        let sheet_id = sheet.id;
+        let mut known_sheets = HashSet::from([sheet_id]);
+        let delete = vec![Operation::DeleteSheet { sheet_id }];
+        let change = vec![Operation::SetSheetColor {
+            sheet_id,
+            color: Some("red".into()),
+        }];
+
+        validate_sheet_ids(&delete, &mut known_sheets).unwrap();
+        assert_eq!(
+            validate_sheet_ids(&change, &mut known_sheets),
+            Err(format!("operation 0: sheet {sheet_id} does not exist"))
+        );
+
+        // undoing the delete adds the sheet back, after which it can be changed
+        let undo_and_change = [
+            vec![Operation::AddSheetSchema {
+                schema: export_sheet(&sheet),
+            }],
+            change.clone(),
+        ]
+        .concat();
+        validate_sheet_ids(&undo_and_change, &mut known_sheets).unwrap();
+        assert!(known_sheets.contains(&sheet_id));
+
+        // sheets cannot be added twice
+        assert_eq!(
+            validate_sheet_ids(
+                &[Operation::DuplicateSheet {
+                    sheet_id,
+                    new_sheet_id: sheet_id,
+                }],
+                &mut known_sheets
+            ),
+            Err(format!("operation 0: sheet {sheet_id} already exists"))
+        );
+
+        // sheets that the file never had cannot be changed
+        let unknown = SheetId::new();
+        assert_eq!(
+            validate_sheet_ids(
+                &[Operation::ResizeColumn {
+                    sheet_id: unknown,
+                    column: 0,
+                    new_size: 10.0,
+                    client_resized: false,
+                }],
+                &mut known_sheets
+            ),
+            Err(format!("operation 0: sheet {unknown} does not exist"))
+        );
+    }
+
+    #[test]
+    fn rejects_large_sheets_and_code() {
+        let limits = TransactionLimits {
+            max_cells: 2,
+            ..TransactionLimits::default()
+        };
+        let mut sheet = Sheet::test();
+        for y in 0..3 {
+            sheet.set_cell_value(Pos { x: 0, y }, CellValue::Text(y.to_string()));
+        }
+        assert_eq!(
+            validate_operations(
+                &[Operation::AddSheetSchema {
+                    schema: export_sheet(&sheet),
+                }],
+                &limits
+            ),
+            Err("operation 0: operation changes 3 cells, more than the limit of 2".into())
+        );
+
+        sheet.name = " ".into();
+        assert_eq!(
+            validate(vec![Operation::AddSheet {
+                sheet: Box::new(sheet),
+            }]),
+            Err("operation 0: sheet name is empty".into())
+        );
+
+        let code = CellValue::Code(CodeCellValue {
+            language: CodeCellLanguage::Python,
+            code: "#".repeat(MAX_CODE_LENGTH + 1),
+        });
+        assert_eq!(
+            validate(vec![Operation::SetCellValues {
+                sheet_pos: SheetPos::new(SheetId::new(), 0, 0),
+                values: CellValues::from(code),
+            }]),
+            Err(format!(
+                "operation 0: code is {} bytes, more than the limit of {MAX_CODE_LENGTH}",
+                MAX_CODE_LENGTH + 1
+            ))
+        );
+
+        let code_run = CodeRun {
+            formatted_code_string: None,
+            std_out: Some("#".repeat(MAX_CODE_LENGTH + 1)),
+            std_err: None,
+            cells_accessed: Default::default(),
+            result: CodeRunResult::Ok(Value::Single(CellValue::Blank)),
+            return_type: None,
+            spill_error: false,
+            line_number: None,
+            output_type: None,
+            last_modified: chrono::Utc::now(),
+        };
+        assert_eq!(
+            validate(vec![Operation::SetCodeRun {
+                sheet_pos: SheetPos::new(SheetId::new(), 0, 0),
+                code_run: Some(code_run),
+                index: 0,
+            }]),
+            Err(format!(
+                "operation 0: standard output is {} bytes, more than the limit of {MAX_CODE_LENGTH}",
+                MAX_CODE_LENGTH + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_large_transactions() {
+        let limits = TransactionLimits {
+            max_bytes: 10,
+            max_operations: 1,
+            max_cells: 1,
+        };
+        assert_eq!(
+            validate_operations_size("[1234567890]", &limits),
+            Err("transaction is 12 bytes, more than the limit of 10".into())
+        );
+
+        let sheet_id = SheetId::new();
+        let operation = Operation::ComputeCode {
+            sheet_pos: SheetPos::new(sheet_id, 0, 0),
+        };
+        assert_eq!(
+            validate_operations(&[operation.clone(), operation], &limits),
+            Err("transaction has 2 operations, more than the limit of 1".into())
+        );
+    }
+}
@@ -17,16 +17,16 @@ use axum_extra::TypedHeader;
 use futures::stream::StreamExt;
 use futures_util::stream::SplitSink;
 use futures_util::SinkExt;
+use quadratic_rust_shared::auth::jwt::{authorize, get_jwks};
 use serde::{Deserialize, Serialize};
 use std::ops::ControlFlow;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::sync::Mutex;
-use quadratic_rust_shared::auth::jwt::{authorize, get_jwks};
 use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
-    background_worker,
+    background_worker, broadcast_listener,
     config::config,
     error::{ErrorLevel, MpError, Result},
     message::{
@@ -101,6 +101,9 @@ pub(crate) async fn serve() -> Result<()> {
         config.heartbeat_timeout_s,
     );
 
+    // receive broadcasts from other instances
+    broadcast_listener::start(Arc::clone(&state)).await?;
+
     axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
@@ -130,6 +133,7 @@ async fn ws_handler(
 
     #[allow(unused)]
     let mut jwt = None;
+    let mut user_id = None;
 
     #[cfg(test)]
     {
@@ -149,9 +153,10 @@ async fn ws_handler(
                     .clone()
                     .ok_or_else(|| auth_error("No JWKS found"))?;
 
-                authorize::<Claims>(&jwks, token, false, true)?;
+                let claims = authorize::<Claims>(&jwks, token, false, true)?.claims;
 
                 jwt = Some(token.to_owned());
+                user_id = Some(claims.sub);
 
                 Ok::<_, MpError>(())
             } else {
@@ -167,7 +172,8 @@ async fn ws_handler(
         }
     }
 
-    let pre_connection = PreConnection::new(jwt);
+    let mut pre_connection = PreConnection::new(jwt);
+    pre_connection.user_id = user_id;
 
     tracing::info!(
         "New connection {}, `{user_agent}` at {addr}",
@@ -243,11 +249,9 @@ async fn handle_socket(
                     connection.session_id
                 );
 
-                if let Ok(room) = state.get_room(&file_id).await {
+                if let Ok(message) = state.users_in_room(&file_id).await {
                     tracing::info!("Broadcasting room {file_id} after connection close");
 
-                    let message = MessageResponse::from((room.users, &state.settings.min_version));
-
                     if let Err(error) = broadcast(
                         vec![connection.session_id],
                         file_id,
@@ -282,28 +286,10 @@ async fn process_message(
     state: Arc<State>,
     pre_connection: PreConnection,
 ) -> Result<ControlFlow<Option<MessageResponse>, ()>> {
-    match msg {
-        Message::Text(text) => {
-            let messsage_request = serde_json::from_str::<MessageRequest>(&text)?;
-            let message_response =
-                handle_message(messsage_request, state, Arc::clone(&sender), pre_connection)
-                    .await?;
-
-            if let Some(message_response) = message_response {
-                let response = Message::Text(serde_json::to_string(&message_response)?);
-
-                (*sender.lock().await)
-                    .send(response)
-                    .await
-                    .map_err(|e| MpError::SendingMessage(e.to_string()))?;
-            }
-        }
-        Message::Binary(d) => {
-            tracing::info!(
-                "Binary messages are not yet supported.  {} bytes: {:?}",
-                d.len(),
-                d
-            );
+    let messsage_request = match msg {
+        Message::Text(text) => serde_json::from_str::<MessageRequest>(&text)?,
+        Message::Binary(bytes) => {
+            MessageRequest::from_binary(&bytes, state.settings.transaction_limits.max_bytes)?
         }
         Message::Close(c) => {
             if let Some(cf) = c {
@@ -315,7 +301,27 @@ async fn process_message(
         }
         _ => {
             tracing::info!("Unhandled message type");
+            return Ok(ControlFlow::Continue(()));
         }
+    };
+
+    let connection_id = pre_connection.id;
+    let message_response = handle_message(
+        messsage_request,
+        Arc::clone(&state),
+        Arc::clone(&sender),
+        pre_connection,
+    )
+    .await?;
+
+    if let Some(message_response) = message_response {
+        let protocol = state.get_protocol(connection_id).await;
+        let response = message_response.to_message(protocol)?;
+
+        (*sender.lock().await)
+            .send(response)
+            .await
+            .map_err(|e| MpError::SendingMessage(e.to_string()))?;
     }
 
     Ok(ControlFlow::Continue(()))
@@ -329,17 +335,28 @@ pub(crate) async fn healthcheck() -> impl IntoResponse {
 pub(crate) mod tests {
 
     use super::*;
+    use crate::message::protocol::{
+        decode_binary, encode_binary, BinaryRequestHeader, BinaryResponseHeader,
+        BINARY_PROTOCOL_VERSION,
+    };
     use crate::state::settings::MinVersion;
     use crate::state::user::{User, UserStateUpdate};
     use crate::test_util::{
-        add_user_via_ws, integration_test_send_and_receive, new_arc_state, setup,
+        add_user_via_ws, enter_room_request, integration_test_receive,
+        integration_test_receive_until, integration_test_send, integration_test_send_and_receive,
+        integration_test_setup, new_arc_state, new_arc_state_with_pubsub, new_user, setup,
     };
     use axum::{
         body::Body,
         http::{self, Request},
     };
     use quadratic_core::controller::operations::operation::Operation;
+    use quadratic_core::controller::transaction::{
+        decompress_and_deserialize, serialize_and_compress,
+    };
     use quadratic_core::grid::SheetId;
+    use quadratic_rust_shared::pubsub::PubSubType;
+    use tokio_tungstenite::tungstenite;
 
     use tower::ServiceExt;
     use uuid::Uuid;
@@ -529,7 +546,7 @@ pub(crate) mod tests {
         let (socket, _, _, file_id, user, _) = setup().await;
         let session_id = user.session_id;
         let operations = vec![Operation::SetSheetName {
-            sheet_id: SheetId::new(),
+            sheet_id: SheetId::test(),
             name: "test".to_string(),
         }];
         let id = Uuid::new_v4();
@@ -545,10 +562,148 @@ pub(crate) mod tests {
             file_id,
             operations,
             sequence_num: 1,
+            compressed: None,
         };
 
         let response = integration_test_send_and_receive(&socket, request, true, 2).await;
 
         assert_eq!(response, Some(expected));
     }
+
+    #[tokio::test]
+    async fn user_shares_binary_operations() {
+        let (_, state, _, file_id, _, _) = setup().await;
+
+        // a user that supports the binary protocol enters on a new connection
+        let socket = Arc::new(Mutex::new(integration_test_setup(state.clone()).await));
+        let user = new_user();
+        let session_id = user.session_id;
+        let request = enter_room_request(file_id, &user, Some(BINARY_PROTOCOL_VERSION));
+        let expected = MessageResponse::EnterRoom {
+            file_id,
+            sequence_num: 0,
+            protocol_version: BINARY_PROTOCOL_VERSION,
+        };
+
+        let response = integration_test_send_and_receive(&socket, request, true, 1).await;
+        assert_eq!(response, Some(expected));
+
+        // UsersInRoom is still sent as JSON
+        integration_test_receive(&socket, 1).await;
+
+        let operations = vec![Operation::SetSheetName {
+            sheet_id: SheetId::test(),
+            name: "test".to_string(),
+        }];
+        let id = Uuid::new_v4();
+        let header = BinaryRequestHeader::Transaction {
+            id,
+            session_id,
+            file_id,
+        };
+        let request = encode_binary(&header, &serialize_and_compress(&operations).unwrap());
+        socket
+            .lock()
+            .await
+            .send(tungstenite::Message::binary(request.unwrap()))
+            .await
+            .unwrap();
+
+        let Some(Ok(tungstenite::Message::Binary(response))) = socket.lock().await.next().await
+        else {
+            panic!("expected a binary message");
+        };
+        let (header, body) = decode_binary::<BinaryResponseHeader>(&response).unwrap();
+        assert_eq!(
+            header,
+            BinaryResponseHeader::Transaction {
+                id,
+                file_id,
+                sequence_num: 1
+            }
+        );
+        assert_eq!(
+            decompress_and_deserialize::<Vec<Operation>>(body).unwrap(),
+            operations
+        );
+    }
+
+    #[tokio::test]
+    async fn users_share_a_room_across_instances() {
+        share_a_room(PubSubType::Memory).await;
+    }
+
+    // run with a Redis server at PUBSUB_HOST:PUBSUB_PORT, using --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn users_share_a_room_across_instances_with_redis() {
+        share_a_room(PubSubType::Redis).await;
+    }
+
+    async fn share_a_room(pubsub_type: PubSubType) {
+        let state_1 = new_arc_state_with_pubsub(pubsub_type).await;
+        let state_2 = new_arc_state_with_pubsub(pubsub_type).await;
+        let listener_1 = broadcast_listener::start(state_1.clone()).await.unwrap();
+        let listener_2 = broadcast_listener::start(state_2.clone()).await.unwrap();
+        let socket_1 = Arc::new(Mutex::new(integration_test_setup(state_1.clone()).await));
+        let socket_2 = Arc::new(Mutex::new(integration_test_setup(state_2.clone()).await));
+        let file_id = Uuid::new_v4();
+        let user_1 = new_user();
+        let user_2 = new_user();
+
+        // user_1 enters the room on the first instance
+        integration_test_send(&socket_1, enter_room_request(file_id, &user_1, None)).await;
+        integration_test_receive_until(&socket_1, |response| {
+            matches!(response, MessageResponse::UsersInRoom { users, .. } if users.len() == 1)
+        })
+        .await;
+
+        // user_2 enters the room on the second instance, and both users see
+        // each other
+        integration_test_send(&socket_2, enter_room_request(file_id, &user_2, None)).await;
+        for socket in [&socket_1, &socket_2] {
+            integration_test_receive_until(socket, |response| {
+                matches!(response, MessageResponse::UsersInRoom { users, .. }
+                    if users.len() == 2 && users.contains(&user_1) && users.contains(&user_2))
+            })
+            .await;
+        }
+
+        // transactions from either instance are sequenced and sent to all users
+        for (sequence_num, (socket, user)) in [(&socket_1, &user_1), (&socket_2, &user_2)]
+            .into_iter()
+            .enumerate()
+        {
+            let id = Uuid::new_v4();
+            let operations = serde_json::to_string(&vec![Operation::SetSheetName {
+                sheet_id: SheetId::test(),
+                name: "test".to_string(),
+            }])
+            .unwrap();
+            let request = MessageRequest::Transaction {
+                id,
+                session_id: user.session_id,
+                file_id,
+                operations: operations.clone(),
+            };
+            let expected = MessageResponse::Transaction {
+                id,
+                file_id,
+                operations,
+                sequence_num: sequence_num as u64 + 1,
+                compressed: None,
+            };
+
+            integration_test_send(socket, request).await;
+            for socket in [&socket_1, &socket_2] {
+                integration_test_receive_until(socket, |response| response == &expected).await;
+            }
+        }
+
+        assert_eq!(state_1.get_sequence_num(&file_id).await.unwrap(), 2);
+        assert_eq!(state_2.get_sequence_num(&file_id).await.unwrap(), 2);
+
+        listener_1.abort();
+        listener_2.abort();
+    }
 }
@@ -0,0 +1,169 @@
+use quadratic_rust_shared::pubsub::{
+    broadcast::{self, BROADCAST_CHANNEL},
+    memory::MemoryConnection,
+    redis::RedisConnection,
+    Config as PubSubConfig, PubSub as PubSubTrait,
+};
+use tokio::sync::broadcast::Receiver;
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::message::response::MessageResponse;
+
+use super::State;
+
+/// A message for the users of a room on other instances.
+pub(crate) type Broadcast = broadcast::Broadcast<MessageResponse>;
+
+/// Broadcasts go through Redis' publish/subscribe, or through the in-memory
+/// PubSub when every instance is in the same process.
+#[derive(Debug)]
+pub(crate) enum BroadcastConnection {
+    Redis(RedisConnection),
+    Memory(MemoryConnection),
+}
+
+/// A subscription to broadcasts from all instances
+pub(crate) enum Subscription {
+    Redis(RedisConnection),
+    Memory(Receiver<String>),
+}
+
+#[derive(Debug)]
+pub(crate) struct Broadcaster {
+    pub(crate) config: PubSubConfig,
+    pub(crate) connection: BroadcastConnection,
+}
+
+impl Broadcaster {
+    /// Create a new connection to the PubSub server
+    pub(crate) async fn new(config: PubSubConfig) -> Result<Self> {
+        let connection = Self::connect(&config).await?;
+
+        Ok(Broadcaster { config, connection })
+    }
+
+    /// Connect to the PubSub server
+    pub(crate) async fn connect(config: &PubSubConfig) -> Result<BroadcastConnection> {
+        let connection = match config {
+            PubSubConfig::Memory(_) => {
+                BroadcastConnection::Memory(MemoryConnection::new(config.to_owned()).await?)
+            }
+            _ => BroadcastConnection::Redis(RedisConnection::new(config.to_owned()).await?),
+        };
+
+        Ok(connection)
+    }
+
+    /// Connect to the PubSub server and subscribe to broadcasts from all
+    /// instances
+    pub(crate) async fn subscribe(config: &PubSubConfig) -> Result<Subscription> {
+        let subscription = match Self::connect(config).await? {
+            BroadcastConnection::Redis(mut connection) => {
+                connection.subscribe(BROADCAST_CHANNEL, "").await?;
+                Subscription::Redis(connection)
+            }
+            BroadcastConnection::Memory(connection) => {
+                Subscription::Memory(connection.listen(BROADCAST_CHANNEL)?)
+            }
+        };
+
+        Ok(subscription)
+    }
+
+    /// Publish a broadcast to all instances
+    pub(crate) async fn publish(&mut self, broadcast: &Broadcast) -> Result<()> {
+        let broadcast = serde_json::to_string(broadcast)?;
+
+        match &mut self.connection {
+            BroadcastConnection::Redis(connection) => {
+                connection
+                    .publish(BROADCAST_CHANNEL, "", &broadcast, None)
+                    .await?
+            }
+            BroadcastConnection::Memory(connection) => {
+                connection.broadcast(BROADCAST_CHANNEL, &broadcast)?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the connection is healthy and attempt to reconnect if not
+    pub(crate) async fn reconnect_if_unhealthy(&mut self) {
+        let is_healthy = match &mut self.connection {
+            BroadcastConnection::Redis(connection) => connection.is_healthy().await,
+            BroadcastConnection::Memory(connection) => connection.is_healthy().await,
+        };
+
+        if !is_healthy {
+            tracing::error!("Broadcaster connection is unhealthy");
+
+            match Self::connect(&self.config).await {
+                Ok(connection) => {
+                    self.connection = connection;
+                    tracing::info!("Broadcaster connection is now healthy");
+                }
+                Err(error) => {
+                    tracing::error!("Error reconnecting the broadcaster {error}");
+                }
+            }
+        }
+    }
+}
+
+impl State {
+    /// Send a message to the users of a room on other instances
+    pub(crate) async fn publish_broadcast(
+        &self,
+        exclude: Vec<Uuid>,
+        file_id: Uuid,
+        message: MessageResponse,
+    ) -> Result<()> {
+        let broadcast = Broadcast {
+            instance_id: self.instance_id,
+            file_id,
+            exclude,
+            message,
+        };
+
+        self.broadcaster.lock().await.publish(&broadcast).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_rust_shared::pubsub::broadcast::BroadcastMessage;
+
+    use super::*;
+
+    #[test]
+    fn reads_broadcasts_from_other_services() {
+        let id = Uuid::new_v4();
+        let file_id = Uuid::new_v4();
+        let broadcast = broadcast::Broadcast {
+            instance_id: Uuid::new_v4(),
+            file_id,
+            exclude: vec![],
+            message: BroadcastMessage::Transaction {
+                id,
+                file_id,
+                sequence_num: 1,
+                operations: "[]".into(),
+            },
+        };
+
+        let received: Broadcast =
+            serde_json::from_str(&serde_json::to_string(&broadcast).unwrap()).unwrap();
+        assert_eq!(
+            received.message,
+            MessageResponse::Transaction {
+                id,
+                file_id,
+                sequence_num: 1,
+                operations: "[]".into(),
+                compressed: None,
+            }
+        );
+    }
+}
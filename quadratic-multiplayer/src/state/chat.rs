@@ -0,0 +1,363 @@
+//! Chat
+//!
+//! Users in a room can send chat messages to each other.  Messages can mention
+//! users and be anchored to a cell.  The room's recent messages are stored in
+//! a capped set in PubSub, keyed by message id and ordered by time, so users
+//! that enter the room later, on any instance, can read them.  The rate limit
+//! is kept in PubSub too, so it applies across instances.
+
+use chrono::{DateTime, Duration, Utc};
+use quadratic_core::SheetPos;
+use quadratic_rust_shared::pubsub::PubSub as PubSubTrait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::message::validate::validate_pos;
+use crate::state::settings::ChatLimits;
+use crate::state::State;
+
+/// Maximum length of a mentioned user id, in characters.
+const MAX_MENTION_LENGTH: usize = 255;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ChatMessage {
+    pub(crate) id: Uuid,
+    pub(crate) user_id: String,
+    pub(crate) session_id: Uuid,
+    pub(crate) first_name: String,
+    pub(crate) last_name: String,
+    pub(crate) text: String,
+
+    /// The user ids of the mentioned users.
+    pub(crate) mentions: Vec<String>,
+
+    /// The cell that the message is about.
+    pub(crate) anchor: Option<SheetPos>,
+    pub(crate) timestamp: DateTime<Utc>,
+}
+
+fn chat_key(file_id: &Uuid) -> String {
+    format!("{file_id}:chat")
+}
+
+fn chat_sent_key(file_id: &Uuid, sender: &str) -> String {
+    format!("{file_id}:chat:sent:{sender}")
+}
+
+// readable messages, oldest first
+fn sort_messages(members: Vec<(String, String)>) -> Vec<ChatMessage> {
+    let mut messages = members
+        .iter()
+        .flat_map(|(_, message)| serde_json::from_str::<ChatMessage>(message))
+        .collect::<Vec<_>>();
+    messages.sort_by_key(|message| (message.timestamp, message.id));
+
+    messages
+}
+
+/// Validates the text and mentions of a chat message.  Returns the trimmed
+/// text and the mentions without duplicates, or the reason that the message
+/// is rejected.
+pub(crate) fn validate_chat_message(
+    text: &str,
+    mentions: Vec<String>,
+    limits: &ChatLimits,
+) -> std::result::Result<(String, Vec<String>), String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("the message is empty".into());
+    }
+
+    let length = text.chars().count();
+    if length > limits.max_length {
+        return Err(format!(
+            "the message has {length} characters, more than the limit of {}",
+            limits.max_length
+        ));
+    }
+
+    let mut unique_mentions: Vec<String> = vec![];
+    for mention in mentions {
+        if mention.is_empty() || mention.chars().count() > MAX_MENTION_LENGTH {
+            return Err(format!("{mention:?} is not a user id"));
+        }
+        if !unique_mentions.contains(&mention) {
+            unique_mentions.push(mention);
+        }
+    }
+
+    if unique_mentions.len() > limits.max_mentions {
+        return Err(format!(
+            "the message mentions {} users, more than the limit of {}",
+            unique_mentions.len(),
+            limits.max_mentions
+        ));
+    }
+
+    Ok((text.to_owned(), unique_mentions))
+}
+
+impl State {
+    /// Applies the chat rate limit to a user in a room.  The limit is per
+    /// authenticated user, across their sessions and instances, so it can't be
+    /// avoided by reconnecting.  Unauthenticated users are limited per session.
+    /// Returns false if the user sent too many messages in the last minute.
+    pub(crate) async fn check_chat_rate(
+        &self,
+        file_id: &Uuid,
+        session_id: &Uuid,
+        limits: &ChatLimits,
+    ) -> Result<bool> {
+        let sender = self
+            .get_room(file_id)
+            .await?
+            .get_user(session_id)?
+            .authenticated_user_id
+            .unwrap_or_else(|| format!("session:{session_id}"));
+
+        self.record_chat_sent(file_id, &sender, Utc::now(), limits)
+            .await
+    }
+
+    /// Records that a user tried to send a chat message at `now`, in a capped
+    /// set that only keeps their latest attempts.  Attempts over the limit
+    /// count too, so the limit lifts once the user stops sending for a
+    /// minute.  Returns false if they are over the limit.
+    async fn record_chat_sent(
+        &self,
+        file_id: &Uuid,
+        sender: &str,
+        now: DateTime<Utc>,
+        limits: &ChatLimits,
+    ) -> Result<bool> {
+        let key = chat_sent_key(file_id, sender);
+        let max_sent = limits.messages_per_minute + 1;
+        let window_start = (now - Duration::minutes(1)).timestamp_millis();
+        let mut pubsub = self.pubsub.lock().await;
+
+        pubsub
+            .connection
+            .add_capped_member(
+                &key,
+                &Uuid::new_v4().to_string(),
+                now.timestamp_millis(),
+                "",
+                max_sent,
+            )
+            .await?;
+        let sent = pubsub
+            .connection
+            .capped_members(&key, window_start + 1, i64::MAX, false, 0, max_sent)
+            .await?;
+
+        Ok(sent.len() <= limits.messages_per_minute)
+    }
+
+    /// Validates the cell that a chat message is anchored to: it must be
+    /// within the grid, in a sheet that exists in the file.  Returns the
+    /// reason that the anchor is rejected, if any.
+    pub(crate) async fn validate_chat_anchor(
+        &self,
+        file_id: &Uuid,
+        anchor: SheetPos,
+    ) -> Result<Option<String>> {
+        if let Err(reason) = validate_pos(anchor.into()) {
+            return Ok(Some(reason));
+        }
+
+        if !self.has_sheet(file_id, &anchor.sheet_id).await? {
+            return Ok(Some(format!("sheet {} does not exist", anchor.sheet_id)));
+        }
+
+        Ok(None)
+    }
+
+    /// Adds a message to the room's chat history and drops the oldest
+    /// messages beyond `history_size`, atomically.  Returns false if the room
+    /// already has a message with the same id.
+    pub(crate) async fn add_chat_message(
+        &self,
+        file_id: &Uuid,
+        message: &ChatMessage,
+        history_size: usize,
+    ) -> Result<bool> {
+        let value = serde_json::to_string(message)?;
+        let added = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .add_capped_member(
+                &chat_key(file_id),
+                &message.id.to_string(),
+                message.timestamp.timestamp_millis(),
+                &value,
+                history_size,
+            )
+            .await?;
+
+        Ok(added)
+    }
+
+    /// Retrieves the room's last `history_size` chat messages, oldest first.
+    /// Messages that cannot be read are skipped.
+    pub(crate) async fn get_chat_messages(
+        &self,
+        file_id: &Uuid,
+        history_size: usize,
+    ) -> Result<Vec<ChatMessage>> {
+        let members = self
+            .pubsub
+            .lock()
+            .await
+            .connection
+            .capped_members(
+                &chat_key(file_id),
+                i64::MIN,
+                i64::MAX,
+                true,
+                0,
+                history_size,
+            )
+            .await?;
+
+        Ok(sort_messages(members))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::user::User;
+    use crate::test_util::{add_user_to_room, new_arc_state, new_user};
+    use quadratic_core::grid::SheetId;
+
+    fn chat_message(text: &str, seconds: i64) -> ChatMessage {
+        ChatMessage {
+            id: Uuid::new_v4(),
+            user_id: "user".into(),
+            session_id: Uuid::new_v4(),
+            first_name: "first".into(),
+            last_name: "last".into(),
+            text: text.into(),
+            mentions: vec![],
+            anchor: None,
+            timestamp: Utc::now() + Duration::seconds(seconds),
+        }
+    }
+
+    #[test]
+    fn validates_chat_messages() {
+        let limits = ChatLimits {
+            max_length: 5,
+            max_mentions: 1,
+            ..ChatLimits::default()
+        };
+        let mention = |id: &str| vec![id.to_string()];
+
+        assert_eq!(
+            validate_chat_message(" hi ", [mention("a"), mention("a")].concat(), &limits),
+            Ok(("hi".into(), mention("a")))
+        );
+        assert!(validate_chat_message("  ", vec![], &limits).is_err());
+        assert!(validate_chat_message("hello!", vec![], &limits).is_err());
+        assert!(validate_chat_message("hi", mention(""), &limits).is_err());
+        assert!(
+            validate_chat_message("hi", [mention("a"), mention("b")].concat(), &limits).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn limits_the_chat_rate() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let limits = ChatLimits {
+            messages_per_minute: 2,
+            ..ChatLimits::default()
+        };
+        let now = Utc::now();
+        let sent = |now| state.record_chat_sent(&file_id, "user", now, &limits);
+
+        assert!(sent(now).await.unwrap());
+        assert!(sent(now).await.unwrap());
+        assert!(!sent(now).await.unwrap());
+
+        // messages older than a minute no longer count
+        assert!(sent(now + Duration::seconds(61)).await.unwrap());
+        assert!(!sent(now + Duration::seconds(61)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn limits_the_chat_rate_per_user() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let limits = ChatLimits {
+            messages_per_minute: 1,
+            ..ChatLimits::default()
+        };
+
+        // a second session of the same user shares their limit
+        let user = add_user_to_room(file_id, new_user(), state.clone()).await;
+        let other_session = User {
+            session_id: Uuid::new_v4(),
+            ..user.clone()
+        };
+        let other_session = add_user_to_room(file_id, other_session, state.clone()).await;
+        let other_user = add_user_to_room(file_id, new_user(), state.clone()).await;
+
+        for (session_id, allowed) in [
+            (user.session_id, true),
+            (other_session.session_id, false),
+            (other_user.session_id, true),
+        ] {
+            let result = state.check_chat_rate(&file_id, &session_id, &limits).await;
+            assert_eq!(result.unwrap(), allowed);
+        }
+    }
+
+    #[tokio::test]
+    async fn validates_chat_anchors() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        add_user_to_room(file_id, new_user(), state.clone()).await;
+
+        let validate = |anchor| state.validate_chat_anchor(&file_id, anchor);
+        let sheet_id = SheetId::test();
+
+        assert_eq!(validate(SheetPos::new(sheet_id, 1, 2)).await.unwrap(), None);
+        assert!(validate(SheetPos::new(sheet_id, i64::MAX, 2))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(validate(SheetPos::new(SheetId::new(), 1, 2))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn keeps_a_bounded_chat_history() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let messages = (0..3)
+            .map(|i| chat_message(&i.to_string(), i))
+            .collect::<Vec<_>>();
+
+        for message in messages.iter() {
+            assert!(state.add_chat_message(&file_id, message, 2).await.unwrap());
+        }
+
+        // message ids are unique
+        assert!(!state
+            .add_chat_message(&file_id, &messages[2], 2)
+            .await
+            .unwrap());
+
+        // the oldest message was dropped
+        assert_eq!(
+            state.get_chat_messages(&file_id, 2).await.unwrap(),
+            messages[1..].to_vec()
+        );
+    }
+}
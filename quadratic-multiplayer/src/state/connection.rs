@@ -1,12 +1,17 @@
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
+use crate::message::protocol::Protocol;
 use crate::state::State;
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct PreConnection {
     pub(crate) id: Uuid,
     pub(crate) jwt: Option<String>,
+
+    // the subject of a validated jwt, which takes precedence over the user id
+    // that the client sends
+    pub(crate) user_id: Option<String>,
 }
 
 impl PreConnection {
@@ -14,6 +19,7 @@ impl PreConnection {
         Self {
             id: Uuid::new_v4(),
             jwt,
+            user_id: None,
         }
     }
 }
@@ -53,6 +59,19 @@ impl State {
         Ok(connection)
     }
 
+    /// Retrieve the protocol that the connection's user negotiated.  Defaults
+    /// to JSON if the user has not entered a room.
+    pub(crate) async fn get_protocol(&self, connection_id: Uuid) -> Protocol {
+        let Ok(connection) = self.get_connection(connection_id).await else {
+            return Protocol::default();
+        };
+
+        self.get_room(&connection.file_id)
+            .await
+            .and_then(|room| room.get_user(&connection.session_id))
+            .map_or(Protocol::default(), |user| user.protocol)
+    }
+
     /// Removes a connection from the state.  If the connection is in a room, leave the room.
     #[tracing::instrument(level = "trace")]
     pub(crate) async fn remove_connection(&self, connection: &Connection) -> Result<Option<Uuid>> {
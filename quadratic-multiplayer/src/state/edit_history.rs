@@ -0,0 +1,218 @@
+//! Edit History
+//!
+//! Each transaction is recorded with its author and the areas that it changed
+//! when it is sequenced.  Records are stored in PubSub so they outlive the
+//! transaction queue, which is truncated once transactions are saved to a
+//! checkpoint.  They are kept in two capped sets: the file's records by
+//! sequence number, and each user's records by time, which are shared with
+//! the file service's restores.
+
+use quadratic_core::controller::edit_history::{EditHistoryQuery, EditRecord};
+use quadratic_rust_shared::pubsub::edit_history::{
+    add_edit_record, edit_history_key, user_edit_history_key,
+};
+use quadratic_rust_shared::pubsub::PubSub as PubSubTrait;
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::state::State;
+
+/// Queries return at most this many records.
+pub(crate) const MAX_EDIT_HISTORY_RECORDS: usize = 1_000;
+
+/// Cell queries read the file's records, newest first, this many at a time.
+const EDIT_HISTORY_PAGE_SIZE: usize = 500;
+
+// records that cannot be read are skipped
+fn read_records(members: &[(String, String)]) -> impl Iterator<Item = EditRecord> + '_ {
+    members
+        .iter()
+        .flat_map(|(_, record)| serde_json::from_str::<EditRecord>(record))
+}
+
+impl State {
+    /// Records a sequenced transaction in the file's edit history.
+    pub(crate) async fn add_edit_record(&self, file_id: &Uuid, record: &EditRecord) -> Result<()> {
+        let value = serde_json::to_string(record)?;
+        let mut pubsub = self.pubsub.lock().await;
+
+        add_edit_record(
+            &mut pubsub.connection,
+            file_id,
+            record.sequence_num,
+            &record.user_id,
+            record.timestamp.timestamp_millis(),
+            &value,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Answers a query about the file's edit history with at most
+    /// `MAX_EDIT_HISTORY_RECORDS` records.
+    pub(crate) async fn query_edit_history(
+        &self,
+        file_id: &Uuid,
+        query: &EditHistoryQuery,
+    ) -> Result<Vec<EditRecord>> {
+        match query {
+            EditHistoryQuery::Cell { sheet_pos, limit } => {
+                let key = edit_history_key(file_id);
+                let limit = (*limit).min(MAX_EDIT_HISTORY_RECORDS);
+                let mut records = vec![];
+                let mut max_score = i64::MAX;
+
+                // page by sequence number rather than by offset, so records
+                // that are added in the meantime aren't read twice
+                while records.len() < limit {
+                    let page = self
+                        .pubsub
+                        .lock()
+                        .await
+                        .connection
+                        .capped_members(&key, i64::MIN, max_score, true, 0, EDIT_HISTORY_PAGE_SIZE)
+                        .await?;
+
+                    records.extend(read_records(&page).filter(|record| record.changed(*sheet_pos)));
+
+                    match page
+                        .last()
+                        .and_then(|(member, _)| member.parse::<i64>().ok())
+                    {
+                        Some(last) if page.len() == EDIT_HISTORY_PAGE_SIZE => max_score = last - 1,
+                        _ => break,
+                    }
+                }
+
+                records.truncate(limit);
+                Ok(records)
+            }
+            EditHistoryQuery::User { user_id, from, to } => {
+                let page = self
+                    .pubsub
+                    .lock()
+                    .await
+                    .connection
+                    .capped_members(
+                        &user_edit_history_key(file_id, user_id),
+                        from.timestamp_millis(),
+                        to.timestamp_millis(),
+                        false,
+                        0,
+                        MAX_EDIT_HISTORY_RECORDS,
+                    )
+                    .await?;
+
+                // records with the same timestamp are ordered by member,
+                // which is the sequence number as a string
+                let mut records: Vec<_> = read_records(&page).collect();
+                records.sort_by_key(|record| record.sequence_num);
+
+                Ok(records)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use quadratic_core::controller::transaction::TransactionAuthor;
+    use quadratic_core::controller::GridController;
+    use quadratic_core::grid::SheetId;
+    use quadratic_core::{Pos, Rect, SheetPos};
+
+    use super::*;
+    use crate::test_util::{new_arc_state, operation};
+
+    #[tokio::test]
+    async fn records_authors_of_transactions() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let author = TransactionAuthor {
+            user_id: "user".into(),
+            session_id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+        };
+
+        let id = Uuid::new_v4();
+        let operations = vec![operation(&mut grid, 1, 6, "1")];
+        state
+            .push_pubsub(id, file_id, operations.clone(), 0, Some(author.clone()))
+            .await
+            .unwrap();
+
+        // transactions without an author are not recorded
+        state
+            .push_pubsub(Uuid::new_v4(), file_id, operations, 0, None)
+            .await
+            .unwrap();
+
+        let query = EditHistoryQuery::Cell {
+            sheet_pos: SheetPos::new(sheet_id, 1, 6),
+            limit: 10,
+        };
+        let changes = state.query_edit_history(&file_id, &query).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].transaction_id, id);
+        assert_eq!(changes[0].sequence_num, 1);
+        assert_eq!(changes[0].user_id, author.user_id);
+    }
+
+    #[tokio::test]
+    async fn queries_records_by_range() {
+        let state = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let sheet_id = SheetId::test();
+        let start = Utc::now();
+
+        // only the first record changes A1, so cell queries read every page
+        let count = EDIT_HISTORY_PAGE_SIZE + 2;
+        for sequence_num in 1..=count as u64 {
+            let pos = Pos {
+                x: 0,
+                y: (sequence_num != 1).into(),
+            };
+            let record = EditRecord {
+                transaction_id: Uuid::new_v4(),
+                sequence_num,
+                user_id: format!("user {}", sequence_num % 2),
+                session_id: Uuid::new_v4(),
+                timestamp: start + Duration::seconds(sequence_num as i64),
+                areas: vec![(sheet_id, Some(Rect::single_pos(pos)))],
+            };
+            state.add_edit_record(&file_id, &record).await.unwrap();
+        }
+
+        let query = EditHistoryQuery::Cell {
+            sheet_pos: SheetPos::new(sheet_id, 0, 0),
+            limit: 10,
+        };
+        let changes = state.query_edit_history(&file_id, &query).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].sequence_num, 1);
+
+        let query = EditHistoryQuery::Cell {
+            sheet_pos: SheetPos::new(sheet_id, 0, 1),
+            limit: 3,
+        };
+        let changes = state.query_edit_history(&file_id, &query).await.unwrap();
+        let sequence_nums: Vec<_> = changes.iter().map(|record| record.sequence_num).collect();
+        assert_eq!(
+            sequence_nums,
+            vec![count as u64, count as u64 - 1, count as u64 - 2]
+        );
+
+        let query = EditHistoryQuery::User {
+            user_id: "user 1".into(),
+            from: start + Duration::seconds(2),
+            to: start + Duration::seconds(7),
+        };
+        let changes = state.query_edit_history(&file_id, &query).await.unwrap();
+        let sequence_nums: Vec<_> = changes.iter().map(|record| record.sequence_num).collect();
+        assert_eq!(sequence_nums, vec![3, 5, 7]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Room Membership
+//!
+//! The users of a room across all instances are stored in PubSub, keyed by
+//! session id.  Each instance keeps the members for its own users up to
+//! date.  Members that stop sending heartbeats (e.g. their instance was
+//! stopped) are removed when the room is read.
+
+use chrono::{DateTime, Utc};
+use quadratic_rust_shared::pubsub::PubSub as PubSubTrait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::message::response::MessageResponse;
+use crate::state::{user::User, State};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct RoomMember {
+    pub(crate) instance_id: Uuid,
+    pub(crate) last_heartbeat: DateTime<Utc>,
+    pub(crate) user: User,
+}
+
+fn members_key(file_id: &Uuid) -> String {
+    format!("{file_id}:users")
+}
+
+impl State {
+    /// Stores a copy of a local user in the room's members.
+    pub(crate) async fn upsert_member(&self, file_id: &Uuid, user: &User) -> Result<()> {
+        let member = serde_json::to_string(&RoomMember {
+            instance_id: self.instance_id,
+            last_heartbeat: user.last_heartbeat,
+            user: user.to_owned(),
+        })?;
+
+        self.pubsub
+            .lock()
+            .await
+            .connection
+            .upsert_member(&members_key(file_id), &user.session_id.to_string(), &member)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stores the latest copy of a local user in the room's members.
+    pub(crate) async fn sync_member(&self, file_id: &Uuid, session_id: &Uuid) -> Result<()> {
+        let user = self.get_room(file_id).await?.get_user(session_id)?;
+
+        self.upsert_member(file_id, &user).await
+    }
+
+    /// Removes a user from the room's members.
+    pub(crate) async fn remove_member(&self, file_id: &Uuid, session_id: &Uuid) -> Result<()> {
+        self.pubsub
+            .lock()
+            .await
+            .connection
+            .remove_member(&members_key(file_id), &session_id.to_string())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the users in a room across all instances, ordered by their
+    /// index.  Stale members are removed.
+    pub(crate) async fn get_members(&self, file_id: &Uuid) -> Result<Vec<User>> {
+        let key = members_key(file_id);
+        let members = self.pubsub.lock().await.connection.members(&key).await?;
+        let oldest_heartbeat = Utc::now().timestamp() - self.settings.heartbeat_timeout_s;
+        let mut users = vec![];
+
+        for (session_id, member) in members {
+            match serde_json::from_str::<RoomMember>(&member) {
+                Ok(member) if member.last_heartbeat.timestamp() >= oldest_heartbeat => {
+                    users.push(member.user);
+                }
+                _ => {
+                    tracing::info!("Removing stale member {session_id} from room {file_id}");
+
+                    self.pubsub
+                        .lock()
+                        .await
+                        .connection
+                        .remove_member(&key, &session_id)
+                        .await?;
+                }
+            }
+        }
+
+        users.sort_by_key(|user| user.index);
+
+        Ok(users)
+    }
+
+    /// The UsersInRoom message for a room.
+    pub(crate) async fn users_in_room(&self, file_id: &Uuid) -> Result<MessageResponse> {
+        let users = self.get_members(file_id).await?;
+
+        Ok(MessageResponse::from((users, &self.settings.min_version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quadratic_rust_shared::pubsub::PubSubType;
+
+    use crate::test_util::{add_user_to_room, new_arc_state_with_pubsub, new_user};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn shares_members_across_instances() {
+        share_members(PubSubType::Memory).await;
+    }
+
+    // run with a Redis server at PUBSUB_HOST:PUBSUB_PORT, using --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn shares_members_across_instances_with_redis() {
+        share_members(PubSubType::Redis).await;
+    }
+
+    async fn share_members(pubsub_type: PubSubType) {
+        let state_1 = new_arc_state_with_pubsub(pubsub_type).await;
+        let state_2 = new_arc_state_with_pubsub(pubsub_type).await;
+        let file_id = Uuid::new_v4();
+        let user_1 = add_user_to_room(file_id, new_user(), state_1.clone()).await;
+        let user_2 = add_user_to_room(file_id, new_user(), state_2.clone()).await;
+
+        // both instances see both users
+        for state in [&state_1, &state_2] {
+            let users = state.get_members(&file_id).await.unwrap();
+            assert_eq!(users.len(), 2);
+            assert!(users.contains(&user_1));
+            assert!(users.contains(&user_2));
+        }
+
+        state_2
+            .leave_room(file_id, &user_2.session_id)
+            .await
+            .unwrap();
+        let users = state_1.get_members(&file_id).await.unwrap();
+        assert_eq!(users, vec![user_1.clone()]);
+
+        // members without a recent heartbeat are removed
+        let stale_user = User {
+            last_heartbeat: Utc::now() - chrono::Duration::hours(1),
+            ..new_user()
+        };
+        state_2.upsert_member(&file_id, &stale_user).await.unwrap();
+        let users = state_1.get_members(&file_id).await.unwrap();
+        assert_eq!(users, vec![user_1]);
+    }
+}
@@ -3,7 +3,12 @@
 //! Store information about the state of the application in a send + sync
 //! struct.  All access and mutations to state should be performed here.
 
+pub mod broadcaster;
+pub mod chat;
 pub mod connection;
+pub mod edit_history;
+pub mod member;
+pub mod protection;
 pub mod pubsub;
 pub mod room;
 pub mod settings;
@@ -11,8 +16,10 @@ pub mod user;
 
 use dashmap::DashMap;
 use jsonwebtoken::jwk::JwkSet;
+use quadratic_rust_shared::pubsub::memory::MemoryConfig;
+use quadratic_rust_shared::pubsub::redis::RedisConfig;
 use quadratic_rust_shared::pubsub::redis_streams::RedisStreamsConfig;
-use quadratic_rust_shared::pubsub::Config as PubSubConfig;
+use quadratic_rust_shared::pubsub::{Config as PubSubConfig, PubSubType};
 use std::collections::HashMap;
 use tokio::sync::Mutex;
 use uuid::Uuid;
@@ -22,30 +29,54 @@ use crate::error::Result;
 use crate::state::room::Room;
 use crate::state::settings::Settings;
 
+use self::broadcaster::Broadcaster;
 use self::connection::Connection;
 use self::pubsub::PubSub;
 
+/// Several instances can serve the same room.  Each instance only holds the
+/// sockets of its own users: room membership and sequence numbers are kept
+/// in PubSub, and broadcasts are fanned out to the other instances.
 #[derive(Debug)]
 pub(crate) struct State {
+    pub(crate) instance_id: Uuid,
     pub(crate) rooms: Mutex<DashMap<Uuid, Room>>,
     pub(crate) connections: Mutex<HashMap<Uuid, Connection>>,
     pub(crate) pubsub: Mutex<PubSub>,
+    pub(crate) broadcaster: Mutex<Broadcaster>,
     pub(crate) settings: Settings,
 }
 
 impl State {
     pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Result<Self> {
-        let pubsub_config = PubSubConfig::RedisStreams(RedisStreamsConfig {
-            host: config.pubsub_host.to_owned(),
-            port: config.pubsub_port.to_owned(),
-            password: config.pubsub_password.to_owned(),
-            active_channels: config.pubsub_active_channels.to_owned(),
-        });
+        let (pubsub_config, broadcaster_config) = match config.pubsub_type {
+            PubSubType::Redis => (
+                PubSubConfig::RedisStreams(RedisStreamsConfig {
+                    host: config.pubsub_host.to_owned(),
+                    port: config.pubsub_port.to_owned(),
+                    password: config.pubsub_password.to_owned(),
+                    active_channels: config.pubsub_active_channels.to_owned(),
+                }),
+                PubSubConfig::Redis(RedisConfig {
+                    host: config.pubsub_host.to_owned(),
+                    port: config.pubsub_port.to_owned(),
+                    password: config.pubsub_password.to_owned(),
+                    active_channels: config.pubsub_active_channels.to_owned(),
+                }),
+            ),
+            PubSubType::Memory => {
+                let memory_config = PubSubConfig::Memory(MemoryConfig {
+                    active_channels: config.pubsub_active_channels.to_owned(),
+                });
+                (memory_config.to_owned(), memory_config)
+            }
+        };
 
         Ok(State {
+            instance_id: Uuid::new_v4(),
             rooms: Mutex::new(DashMap::new()),
             connections: Mutex::new(HashMap::new()),
             pubsub: Mutex::new(PubSub::new(pubsub_config).await?),
+            broadcaster: Mutex::new(Broadcaster::new(broadcaster_config).await?),
             settings: Settings::new(config, jwks).await,
         })
     }
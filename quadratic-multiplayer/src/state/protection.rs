@@ -0,0 +1,560 @@
+//! Protections
+//!
+//! Transactions that change protected ranges or sheets are rejected before
+//! they are sequenced.  Each room holds the protections of the file's sheets
+//! as of a sequence number: they are seeded from the file's latest checkpoint,
+//! and caught up with the transactions after it, which any instance can
+//! publish.  A transaction is checked against the protections as of the
+//! sequence number before its own, and is only published if no other
+//! transaction took that sequence number first, so it is never checked
+//! against stale protections.  Every sheet of the file has an entry, even
+//! without protections, so transactions that change sheets that don't exist
+//! are rejected too.  The room also tracks the outputs of the file's code
+//! runs, since replacing a code run changes the cells of its old output.
+
+use quadratic_core::controller::edit_history::EditRecord;
+use quadratic_core::controller::operations::operation::Operation;
+use quadratic_core::controller::operations::protection::{
+    apply_code_outputs, apply_protections, check_protections, sheet_code_outputs, CodeOutputs,
+    SheetProtections,
+};
+use quadratic_core::controller::transaction::{TransactionAuthor, TransactionServer};
+use quadratic_core::grid::file::import_bytes;
+use quadratic_core::grid::SheetId;
+use quadratic_rust_shared::quadratic_api::get_file_checkpoint;
+use quadratic_rust_shared::storage::Storage;
+use uuid::Uuid;
+
+use crate::error::{MpError, Result};
+use crate::message::validate::validate_sheet_ids;
+use crate::state::State;
+
+/// The protections of a file's sheets as of a sequence number.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct RoomProtections {
+    pub(crate) sequence_num: u64,
+
+    /// The protections of every sheet in the file.
+    pub(crate) sheets: SheetProtections,
+
+    /// The outputs of the code runs in every sheet of the file.
+    pub(crate) code_outputs: CodeOutputs,
+}
+
+impl RoomProtections {
+    /// Catches up with transactions that were already checked.  Returns false
+    /// if they don't directly follow `sequence_num`.
+    fn catch_up(&mut self, transactions: &[TransactionServer]) -> Result<bool> {
+        for transaction in transactions {
+            if transaction.sequence_num != self.sequence_num + 1 {
+                return Ok(false);
+            }
+
+            apply_protections(&transaction.operations, &mut self.sheets)
+                .and_then(|_| apply_code_outputs(&transaction.operations, &mut self.code_outputs))
+                .map_err(|e| MpError::Serialization(e.to_string()))?;
+            self.sequence_num = transaction.sequence_num;
+        }
+
+        Ok(true)
+    }
+}
+
+impl State {
+    /// Retrieves the room's protections, which are loaded from the file's
+    /// latest checkpoint if the room doesn't have them yet.
+    pub(crate) async fn get_protections(&self, file_id: &Uuid) -> Result<RoomProtections> {
+        let protections = {
+            let rooms = self.rooms.lock().await;
+            let room = rooms
+                .get(file_id)
+                .ok_or(MpError::RoomNotFound(file_id.to_string()))?;
+            room.protections.to_owned()
+        };
+
+        match protections {
+            Some(protections) => Ok(protections),
+            None => {
+                let protections = self.load_protections(file_id).await?;
+                self.update_protections(file_id, protections.to_owned())
+                    .await?;
+                Ok(protections)
+            }
+        }
+    }
+
+    /// Replaces the room's protections if they are newer.
+    async fn update_protections(&self, file_id: &Uuid, protections: RoomProtections) -> Result<()> {
+        let rooms = self.rooms.lock().await;
+        let mut room = rooms
+            .get_mut(file_id)
+            .ok_or(MpError::RoomNotFound(file_id.to_string()))?;
+
+        let is_newer = match &room.protections {
+            Some(current) => protections.sequence_num > current.sequence_num,
+            None => true,
+        };
+        if is_newer {
+            room.protections = Some(protections);
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether a sheet exists in the file as of its latest transaction.
+    /// The room's protections are caught up first, since other instances can
+    /// publish transactions that add or delete sheets.
+    pub(crate) async fn has_sheet(&self, file_id: &Uuid, sheet_id: &SheetId) -> Result<bool> {
+        let mut protections = self.get_protections(file_id).await?;
+        let mut reloaded = false;
+
+        loop {
+            let transactions = self
+                .pubsub
+                .lock()
+                .await
+                .get_transactions_from(file_id, protections.sequence_num + 1)
+                .await?;
+
+            if protections.catch_up(&transactions)? {
+                break;
+            }
+
+            // transactions before the latest checkpoint can be truncated, so
+            // start over from the checkpoint once
+            if reloaded {
+                return Err(MpError::TransactionQueue(format!(
+                    "Transactions after {} are missing for file {file_id}",
+                    protections.sequence_num
+                )));
+            }
+            protections = self.load_protections(file_id).await?;
+            reloaded = true;
+        }
+
+        let has_sheet = protections.sheets.contains_key(sheet_id);
+        self.update_protections(file_id, protections).await?;
+
+        Ok(has_sheet)
+    }
+
+    /// Loads the protections of a file's sheets from its latest checkpoint.
+    async fn load_protections(&self, file_id: &Uuid) -> Result<RoomProtections> {
+        // tests don't have checkpoints, so their files have a single sheet
+        if cfg!(test) {
+            return Ok(RoomProtections {
+                sequence_num: self.get_room(file_id).await?.checkpoint_sequence_num,
+                sheets: SheetProtections::from([(SheetId::test(), vec![])]),
+                code_outputs: CodeOutputs::new(),
+            });
+        }
+
+        let checkpoint = get_file_checkpoint(
+            &self.settings.quadratic_api_uri,
+            &self.settings.m2m_auth_token,
+            file_id,
+        )
+        .await?;
+
+        self.read_protections(&checkpoint.s3_key, checkpoint.sequence_number)
+            .await
+    }
+
+    /// Reads the protections of a file's sheets, and the outputs of their code
+    /// runs, from a checkpoint in storage.
+    pub(crate) async fn read_protections(
+        &self,
+        key: &str,
+        sequence_num: u64,
+    ) -> Result<RoomProtections> {
+        let storage = &self.settings.storage;
+        let file = storage.read(key).await.map_err(|e| {
+            MpError::FileService(format!(
+                "Unable to read checkpoint {key} from {}: {e}",
+                storage.location()
+            ))
+        })?;
+        let grid = import_bytes(&file)
+            .map_err(|e| MpError::FileService(format!("Unable to load checkpoint {key}: {e}")))?;
+
+        Ok(RoomProtections {
+            sequence_num,
+            sheets: grid
+                .sheets()
+                .iter()
+                .map(|sheet| (sheet.id, sheet.protections.to_owned()))
+                .collect(),
+            code_outputs: grid
+                .sheets()
+                .iter()
+                .map(|sheet| (sheet.id, sheet_code_outputs(sheet)))
+                .collect(),
+        })
+    }
+
+    /// Checks that a transaction only changes sheets that exist, and checks it
+    /// against the protections of the room's sheets, then publishes it with
+    /// the file's next sequence number and records it in the file's edit
+    /// history.  `user_id` is the authenticated user that sent it, if any.
+    ///
+    /// Returns the transaction's sequence number, or InvalidTransaction if
+    /// it changes sheets that don't exist or the user cannot change what it
+    /// changes.
+    pub(crate) async fn push_protected(
+        &self,
+        id: Uuid,
+        file_id: Uuid,
+        operations: Vec<Operation>,
+        author: TransactionAuthor,
+        user_id: Option<&str>,
+    ) -> Result<u64> {
+        let mut transaction = TransactionServer {
+            id,
+            file_id,
+            operations,
+            sequence_num: 0,
+            author: Some(author),
+        };
+        let mut protections = self.get_protections(&file_id).await?;
+
+        // the last sequence number that is known to be taken
+        let mut last_sequence_num = 0;
+
+        loop {
+            let mut pubsub = self.pubsub.lock().await;
+            let transactions = pubsub
+                .get_transactions_from(&file_id, protections.sequence_num + 1)
+                .await?;
+
+            // transactions before the latest checkpoint can be truncated, so
+            // start over from the checkpoint
+            if !protections.catch_up(&transactions)? || protections.sequence_num < last_sequence_num
+            {
+                drop(pubsub);
+                let checkpoint = self.load_protections(&file_id).await?;
+                if checkpoint.sequence_num <= protections.sequence_num {
+                    return Err(MpError::TransactionQueue(format!(
+                        "Transactions after {} are missing for file {file_id}",
+                        protections.sequence_num
+                    )));
+                }
+                protections = checkpoint;
+                continue;
+            }
+
+            let mut known_sheets = protections.sheets.keys().copied().collect();
+            let mut checked = protections.sheets.to_owned();
+            let mut code_outputs = protections.code_outputs.to_owned();
+            let checked_result = validate_sheet_ids(&transaction.operations, &mut known_sheets)
+                .and_then(|_| {
+                    check_protections(
+                        &transaction.operations,
+                        user_id,
+                        &mut checked,
+                        &mut code_outputs,
+                    )
+                    .map_err(|error| error.to_string())
+                });
+            if let Err(reason) = checked_result {
+                drop(pubsub);
+                self.update_protections(&file_id, protections).await?;
+                return Err(MpError::InvalidTransaction(id, reason));
+            }
+
+            transaction.sequence_num = protections.sequence_num + 1;
+            let next_sequence_num = pubsub
+                .publish_if_next(&transaction, protections.sequence_num)
+                .await?;
+            drop(pubsub);
+
+            // another transaction took the sequence number, so check this one
+            // again after it
+            if next_sequence_num != transaction.sequence_num {
+                last_sequence_num = next_sequence_num - 1;
+                continue;
+            }
+
+            let published = RoomProtections {
+                sequence_num: transaction.sequence_num,
+                sheets: checked,
+                code_outputs,
+            };
+            self.update_protections(&file_id, published).await?;
+
+            // the transaction is already published, so failing to record it
+            // doesn't fail the request
+            if let Some(record) = EditRecord::from_transaction(&transaction) {
+                if let Err(error) = self.add_edit_record(&file_id, &record).await {
+                    tracing::warn!(
+                        "Error recording transaction {id} in the edit history of file {file_id}: {error}"
+                    );
+                }
+            }
+
+            return Ok(transaction.sequence_num);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use quadratic_core::controller::GridController;
+    use quadratic_core::grid::{file::export_binary, CodeRun, CodeRunResult, Protection, Sheet};
+    use quadratic_core::{Array, Rect, SheetPos, Value};
+
+    use quadratic_rust_shared::pubsub::PubSubType;
+
+    use crate::test_util::{
+        add_new_user_to_room, new_arc_state, new_arc_state_with_pubsub, sheet_operation,
+    };
+
+    use super::*;
+
+    fn author() -> TransactionAuthor {
+        TransactionAuthor {
+            user_id: "user".into(),
+            session_id: Uuid::new_v4(),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    fn protect(sheet_id: SheetId, owner: &str) -> Vec<Operation> {
+        let protection = Protection::new(None, owner.into(), vec![]);
+        vec![Operation::SetProtection {
+            sheet_id,
+            protection_id: protection.id,
+            protection: Some(protection),
+        }]
+    }
+
+    #[tokio::test]
+    async fn reads_protections_from_checkpoints() {
+        let state = new_arc_state().await;
+        let mut grid = GridController::test();
+        let sheet_id = grid.sheet_ids()[0];
+        let protection = Protection::new(None, "owner".into(), vec![]);
+        grid.sheet_mut(sheet_id).protections = vec![protection.clone()];
+
+        let file = export_binary(grid.grid_mut()).unwrap();
+        state
+            .settings
+            .storage
+            .write("file.grid", &file)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            state.read_protections("file.grid", 7).await.unwrap(),
+            RoomProtections {
+                sequence_num: 7,
+                sheets: SheetProtections::from([(sheet_id, vec![protection])]),
+                code_outputs: CodeOutputs::from([(sheet_id, Default::default())]),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn checks_protections_published_by_other_instances() {
+        check_protections_published_by_another_instance(PubSubType::Memory).await;
+    }
+
+    // run with a Redis server at PUBSUB_HOST:PUBSUB_PORT, using --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn checks_protections_published_by_other_instances_with_redis() {
+        check_protections_published_by_another_instance(PubSubType::Redis).await;
+    }
+
+    async fn check_protections_published_by_another_instance(pubsub_type: PubSubType) {
+        let state_1 = new_arc_state_with_pubsub(pubsub_type).await;
+        let state_2 = new_arc_state_with_pubsub(pubsub_type).await;
+        let file_id = Uuid::new_v4();
+        let sheet_id = SheetId::test();
+        add_new_user_to_room(file_id, state_1.clone()).await;
+        add_new_user_to_room(file_id, state_2.clone()).await;
+
+        // both instances know the file before it is protected
+        state_2.get_protections(&file_id).await.unwrap();
+
+        let sequence_num = state_1
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                protect(sheet_id, "owner"),
+                author(),
+                Some("owner"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 1);
+
+        // the other instance catches up before checking
+        let result = state_2
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                vec![sheet_operation(sheet_id, 0, 0, "1")],
+                author(),
+                Some("other"),
+            )
+            .await;
+        assert!(matches!(result, Err(MpError::InvalidTransaction(..))));
+        assert_eq!(
+            state_2
+                .get_protections(&file_id)
+                .await
+                .unwrap()
+                .sequence_num,
+            1
+        );
+
+        // other sheets can still be changed
+        let other = Sheet::new(SheetId::new(), "Other".into(), "a1".into());
+        let other_id = other.id;
+        let sequence_num = state_2
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                vec![
+                    Operation::AddSheet {
+                        sheet: Box::new(other),
+                    },
+                    sheet_operation(other_id, 0, 0, "1"),
+                ],
+                author(),
+                Some("other"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(sequence_num, 2);
+    }
+
+    // a code run at (0, 0) with an output `width` cells wide, or no run
+    fn set_code_run(sheet_id: SheetId, width: Option<usize>) -> Vec<Operation> {
+        let code_run = width.map(|width| CodeRun {
+            formatted_code_string: None,
+            std_out: None,
+            std_err: None,
+            cells_accessed: HashSet::new(),
+            result: CodeRunResult::Ok(Value::Array(Array::from(vec![vec!["1"; width]]))),
+            return_type: None,
+            spill_error: false,
+            line_number: None,
+            output_type: None,
+            last_modified: chrono::Utc::now(),
+        });
+        vec![Operation::SetCodeRun {
+            sheet_pos: SheetPos::new(sheet_id, 0, 0),
+            code_run,
+            index: 0,
+        }]
+    }
+
+    #[tokio::test]
+    async fn rejects_code_runs_into_protected_ranges() {
+        let state_1 = new_arc_state().await;
+        let state_2 = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        let sheet_id = SheetId::test();
+        add_new_user_to_room(file_id, state_1.clone()).await;
+        add_new_user_to_room(file_id, state_2.clone()).await;
+        state_2.get_protections(&file_id).await.unwrap();
+
+        let range = Protection::new(Some(Rect::new(2, 0, 3, 3)), "owner".into(), vec![]);
+        let protect_range = vec![Operation::SetProtection {
+            sheet_id,
+            protection_id: range.id,
+            protection: Some(range),
+        }];
+        state_1
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                protect_range,
+                author(),
+                Some("owner"),
+            )
+            .await
+            .unwrap();
+
+        // a run built by another user can't spill into the range
+        let spill = set_code_run(sheet_id, Some(3));
+        let result = state_1
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                spill.clone(),
+                author(),
+                Some("other"),
+            )
+            .await;
+        assert!(matches!(result, Err(MpError::InvalidTransaction(..))));
+        state_1
+            .push_protected(Uuid::new_v4(), file_id, spill, author(), Some("owner"))
+            .await
+            .unwrap();
+
+        // or clear the owner's output, which the other instance caught up with
+        let result = state_2
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                set_code_run(sheet_id, None),
+                author(),
+                Some("other"),
+            )
+            .await;
+        assert!(matches!(result, Err(MpError::InvalidTransaction(..))));
+    }
+
+    #[tokio::test]
+    async fn rejects_changes_to_sheets_that_do_not_exist() {
+        let state_1 = new_arc_state().await;
+        let state_2 = new_arc_state().await;
+        let file_id = Uuid::new_v4();
+        add_new_user_to_room(file_id, state_1.clone()).await;
+        add_new_user_to_room(file_id, state_2.clone()).await;
+        state_2.get_protections(&file_id).await.unwrap();
+
+        let unknown = SheetId::new();
+        let result = state_1
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                vec![sheet_operation(unknown, 0, 0, "1")],
+                author(),
+                None,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MpError::InvalidTransaction(_, reason))
+                if reason == format!("operation 0: sheet {unknown} does not exist")
+        ));
+
+        // sheets deleted on another instance cannot be changed
+        let sheet_id = SheetId::test();
+        state_1
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                vec![Operation::DeleteSheet { sheet_id }],
+                author(),
+                None,
+            )
+            .await
+            .unwrap();
+        let result = state_2
+            .push_protected(
+                Uuid::new_v4(),
+                file_id,
+                vec![sheet_operation(sheet_id, 0, 0, "1")],
+                author(),
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(MpError::InvalidTransaction(..))));
+    }
+}
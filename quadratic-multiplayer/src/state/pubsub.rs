@@ -1,9 +1,9 @@
+use quadratic_core::controller::transaction::TransactionServer;
+#[cfg(test)]
 use quadratic_core::controller::{
-    operations::operation::Operation, transaction::TransactionServer,
-};
-use quadratic_rust_shared::pubsub::{
-    redis_streams::RedisConnection, Config as PubSubConfig, PubSub as PubSubTrait,
+    edit_history::EditRecord, operations::operation::Operation, transaction::TransactionAuthor,
 };
+use quadratic_rust_shared::pubsub::{Config as PubSubConfig, Connection, PubSub as PubSubTrait};
 use uuid::Uuid;
 
 use crate::error::Result;
@@ -15,7 +15,7 @@ pub static GROUP_NAME: &str = "quadratic-multiplayer-1";
 #[derive(Debug)]
 pub(crate) struct PubSub {
     pub(crate) config: PubSubConfig,
-    pub(crate) connection: RedisConnection,
+    pub(crate) connection: Connection,
 }
 
 impl PubSub {
@@ -27,42 +27,85 @@ impl PubSub {
     }
 
     /// Connect to the PubSub server
-    pub(crate) async fn connect(config: &PubSubConfig) -> Result<RedisConnection> {
-        let connection = RedisConnection::new(config.to_owned()).await?;
+    pub(crate) async fn connect(config: &PubSubConfig) -> Result<Connection> {
+        let connection = Connection::new(config.to_owned()).await?;
 
         Ok(connection)
     }
 
+    /// Push a transaction with the file's next sequence number, which is
+    /// after `min_sequence_num`.  Sequence numbers are allocated by PubSub, so
+    /// instances that serve the same file never reuse one.  Users'
+    /// transactions are pushed with `State::push_protected` instead, which
+    /// checks them first.
+    ///
+    /// Returns the published transaction.
+    #[cfg(test)]
     pub(crate) async fn push(
         &mut self,
         id: Uuid,
         file_id: Uuid,
         operations: Vec<Operation>,
-        sequence_num: u64,
-    ) -> Result<u64> {
-        let transaction = TransactionServer {
+        min_sequence_num: u64,
+        author: Option<TransactionAuthor>,
+    ) -> Result<TransactionServer> {
+        let mut transaction = TransactionServer {
             id,
             file_id,
             operations,
-            sequence_num,
+            sequence_num: min_sequence_num + 1,
+            author,
         };
 
-        let transaction = serde_json::to_string(&transaction)?;
-        let active_channels = match self.config {
-            PubSubConfig::RedisStreams(ref config) => config.active_channels.as_str(),
-            _ => "active_channels",
-        };
+        // the sequence number is part of the message, so retry until the
+        // message is published with the next sequence number
+        loop {
+            let next_sequence_num = self.publish_if_next(&transaction, min_sequence_num).await?;
 
-        self.connection
-            .publish(
-                &file_id.to_string(),
-                &sequence_num.to_string(),
-                &transaction,
+            if next_sequence_num == transaction.sequence_num {
+                return Ok(transaction);
+            }
+
+            transaction.sequence_num = next_sequence_num;
+        }
+    }
+
+    /// Publish a transaction if its sequence number is the file's next one,
+    /// which is after `min_sequence_num`.  Returns the file's next sequence
+    /// number.
+    pub(crate) async fn publish_if_next(
+        &mut self,
+        transaction: &TransactionServer,
+        min_sequence_num: u64,
+    ) -> Result<u64> {
+        let active_channels = self.config.active_channels();
+        let next_sequence_num = self
+            .connection
+            .publish_if_next(
+                &transaction.file_id.to_string(),
+                transaction.sequence_num,
+                min_sequence_num,
+                &serde_json::to_string(transaction)?,
                 Some(active_channels),
             )
             .await?;
 
-        Ok(sequence_num)
+        Ok(next_sequence_num)
+    }
+
+    /// Get the transactions from a sequence number on, inclusive
+    pub(crate) async fn get_transactions_from(
+        &mut self,
+        file_id: &Uuid,
+        min_sequence_num: u64,
+    ) -> Result<Vec<TransactionServer>> {
+        Ok(self
+            .connection
+            .get_messages_from(&file_id.to_string(), &min_sequence_num.to_string(), false)
+            .await?
+            .iter()
+            .flat_map(|(_, message)| serde_json::from_str::<TransactionServer>(message))
+            .collect::<Vec<TransactionServer>>())
     }
 
     /// Check if the connection is healthy and attempt to reconnect if not
@@ -98,32 +141,62 @@ impl State {
         Ok(())
     }
 
-    /// Push a transaction to the transaction queue
+    /// Push a transaction to the transaction queue without checking it, and
+    /// record it in the file's edit history.  Returns the transaction's
+    /// sequence number, which is after `min_sequence_num`.
+    #[cfg(test)]
     pub(crate) async fn push_pubsub(
         &self,
         id: Uuid,
         file_id: Uuid,
         operations: Vec<Operation>,
-        sequence_num: u64,
+        min_sequence_num: u64,
+        author: Option<TransactionAuthor>,
     ) -> Result<u64> {
+        let transaction = self
+            .pubsub
+            .lock()
+            .await
+            .push(id, file_id, operations, min_sequence_num, author)
+            .await?;
+
+        if let Some(record) = EditRecord::from_transaction(&transaction) {
+            self.add_edit_record(&file_id, &record).await?;
+        }
+
+        Ok(transaction.sequence_num)
+    }
+
+    pub(crate) async fn get_messages_from_pubsub(
+        &self,
+        file_id: &Uuid,
+        min_sequence_num: u64,
+    ) -> Result<Vec<TransactionServer>> {
         self.pubsub
             .lock()
             .await
-            .push(id, file_id, operations, sequence_num)
+            .get_transactions_from(file_id, min_sequence_num)
             .await
     }
 
-    pub(crate) async fn get_messages_from_pubsub(
+    /// Get the transactions between two sequence numbers, inclusive
+    pub(crate) async fn get_messages_range_pubsub(
         &self,
         file_id: &Uuid,
         min_sequence_num: u64,
+        max_sequence_num: u64,
     ) -> Result<Vec<TransactionServer>> {
         Ok(self
             .pubsub
             .lock()
             .await
             .connection
-            .get_messages_from(&file_id.to_string(), &min_sequence_num.to_string(), false)
+            .get_messages_range(
+                &file_id.to_string(),
+                &min_sequence_num.to_string(),
+                &max_sequence_num.to_string(),
+                false,
+            )
             .await?
             .iter()
             .flat_map(|(_, message)| serde_json::from_str::<TransactionServer>(message))
@@ -147,7 +220,9 @@ impl State {
 mod tests {
     use quadratic_core::controller::GridController;
 
-    use crate::test_util::{operation, setup};
+    use quadratic_rust_shared::pubsub::PubSubType;
+
+    use crate::test_util::{new_arc_state_with_pubsub, operation, setup};
 
     use super::*;
     #[tokio::test]
@@ -161,29 +236,34 @@ mod tests {
         let operations_2 = operation(&mut grid, 1, 0, "2");
         let transaction_2 = vec![operations_2.clone()];
 
-        state
-            .push_pubsub(transaction_id_1, file_id, transaction_1.clone(), 1)
+        let sequence_num = state
+            .push_pubsub(transaction_id_1, file_id, transaction_1.clone(), 0, None)
             .await
             .unwrap();
+        assert_eq!(sequence_num, 1);
         let transaction = state.get_messages_from_pubsub(&file_id, 0).await.unwrap();
         let expected_transaction_1 = TransactionServer {
             id: transaction_id_1,
             file_id,
             operations: transaction_1,
             sequence_num: 1,
+            author: None,
         };
         assert_eq!(transaction[0], expected_transaction_1);
 
-        state
-            .push_pubsub(transaction_id_2, file_id, transaction_2.clone(), 2)
+        // the sequence number is allocated even if the minimum is stale
+        let sequence_num = state
+            .push_pubsub(transaction_id_2, file_id, transaction_2.clone(), 0, None)
             .await
             .unwrap();
+        assert_eq!(sequence_num, 2);
         let transaction = state.get_messages_from_pubsub(&file_id, 0).await.unwrap();
         let expected_transaction_2 = TransactionServer {
             id: transaction_id_2,
             file_id,
             operations: transaction_2,
             sequence_num: 2,
+            author: None,
         };
         assert_eq!(
             transaction,
@@ -197,4 +277,52 @@ mod tests {
             serde_json::to_string(&expected_transaction_2).unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn allocates_sequence_nums_across_instances() {
+        allocate_sequence_nums(PubSubType::Memory).await;
+    }
+
+    // run with a Redis server at PUBSUB_HOST:PUBSUB_PORT, using --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn allocates_sequence_nums_across_instances_with_redis() {
+        allocate_sequence_nums(PubSubType::Redis).await;
+    }
+
+    async fn allocate_sequence_nums(pubsub_type: PubSubType) {
+        let state_1 = new_arc_state_with_pubsub(pubsub_type).await;
+        let state_2 = new_arc_state_with_pubsub(pubsub_type).await;
+        let file_id = Uuid::new_v4();
+        let mut grid = GridController::test();
+        let operations = vec![operation(&mut grid, 0, 0, "1")];
+
+        // both instances think that the file is at sequence_num 5
+        let handles = (0..10).map(|index| {
+            let state = if index % 2 == 0 { &state_1 } else { &state_2 }.clone();
+            let operations = operations.clone();
+            tokio::spawn(async move {
+                state
+                    .push_pubsub(Uuid::new_v4(), file_id, operations, 5, None)
+                    .await
+                    .unwrap()
+            })
+        });
+
+        let mut sequence_nums = futures::future::join_all(handles)
+            .await
+            .into_iter()
+            .map(|sequence_num| sequence_num.unwrap())
+            .collect::<Vec<_>>();
+        sequence_nums.sort();
+        assert_eq!(sequence_nums, (6..16).collect::<Vec<_>>());
+
+        // each message contains its sequence number
+        let transactions = state_1.get_messages_from_pubsub(&file_id, 0).await.unwrap();
+        let transaction_sequence_nums = transactions
+            .iter()
+            .map(|transaction| transaction.sequence_num)
+            .collect::<Vec<_>>();
+        assert_eq!(transaction_sequence_nums, sequence_nums);
+    }
 }
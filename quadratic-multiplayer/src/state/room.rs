@@ -3,7 +3,7 @@ use serde::Serialize;
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
-use crate::state::{user::User, State};
+use crate::state::{protection::RoomProtections, user::User, State};
 use crate::{get_mut_room, get_or_create_room, get_room};
 
 use super::connection::{Connection, PreConnection};
@@ -15,6 +15,11 @@ pub(crate) struct Room {
     pub(crate) sequence_num: u64,
     pub(crate) checkpoint_sequence_num: u64,
     pub(crate) user_index: usize,
+
+    /// The protections of the file's sheets, once they are loaded from its
+    /// latest checkpoint.
+    #[serde(skip)]
+    pub(crate) protections: Option<RoomProtections>,
 }
 
 #[cfg(test)]
@@ -34,14 +39,10 @@ impl Room {
             sequence_num,
             checkpoint_sequence_num: sequence_num,
             user_index: 0,
+            protections: None,
         }
     }
 
-    pub fn increment_sequence_num(&mut self) -> u64 {
-        self.sequence_num += 1;
-        self.sequence_num
-    }
-
     pub fn get_user(&self, session_id: &Uuid) -> Result<User> {
         let user = self
             .users
@@ -68,8 +69,8 @@ impl State {
     }
 
     /// Add a user to a room.  If the room doesn't exist, it is created.  Users
-    /// are only added to a room once (DashMap).  Returns true if the user was
-    /// newly added.
+    /// are only added to a room once (DashMap).  The user is also added to
+    /// the room's members.  Returns true if the user was newly added.
     #[tracing::instrument(level = "trace")]
     pub(crate) async fn enter_room(
         &self,
@@ -84,6 +85,8 @@ impl State {
             .insert(user.session_id.to_owned(), user.to_owned())
             .is_none();
 
+        self.upsert_member(&file_id, user).await?;
+
         let connection = Connection::new(
             pre_connection.id,
             user.session_id,
@@ -99,13 +102,16 @@ impl State {
         Ok(is_new)
     }
 
-    /// Removes a user from a room. If the room is empty, it deletes the room.
-    /// Returns true if the room still exists after the user leaves.
+    /// Removes a user from a room and its members. If the room has no local
+    /// users, it deletes the room.  Returns true if the room still exists
+    /// after the user leaves.
     #[tracing::instrument(level = "trace")]
     pub(crate) async fn leave_room(&self, file_id: Uuid, session_id: &Uuid) -> Result<bool> {
         get_mut_room!(self, file_id)?.users.remove(session_id);
         let num_in_room = get_room!(self, file_id)?.users.len();
 
+        self.remove_member(&file_id, session_id).await?;
+
         tracing::info!(
             "User {:?} is leaving room {}, {} user(s) left",
             session_id,
@@ -132,6 +138,22 @@ impl State {
     pub(crate) async fn get_sequence_num(&self, file_id: &Uuid) -> Result<u64> {
         Ok(get_room!(self, file_id)?.sequence_num)
     }
+
+    /// Records a sequence number that was allocated for the room by any
+    /// instance.  Returns the room's current sequence number.
+    pub(crate) async fn update_sequence_num(
+        &self,
+        file_id: &Uuid,
+        sequence_num: u64,
+    ) -> Result<u64> {
+        let rooms = self.rooms.lock().await;
+        let mut room = rooms
+            .get_mut(file_id)
+            .ok_or(MpError::RoomNotFound(file_id.to_string()))?;
+        room.sequence_num = room.sequence_num.max(sequence_num);
+
+        Ok(room.sequence_num)
+    }
 }
 
 #[macro_export]
@@ -227,9 +249,7 @@ mod tests {
         let sequence_num = state.get_sequence_num(&file_id).await.unwrap();
         assert_eq!(sequence_num, 0);
 
-        get_mut_room!(state, file_id)
-            .unwrap()
-            .increment_sequence_num();
+        state.update_sequence_num(&file_id, 1).await.unwrap();
         let sequence_num = state.get_sequence_num(&file_id).await.unwrap();
         assert_eq!(sequence_num, 1);
 
@@ -1,14 +1,25 @@
 use jsonwebtoken::jwk::JwkSet;
+use quadratic_rust_shared::environment::Environment;
+use quadratic_rust_shared::storage::{
+    file_system::FileSystemConfig, s3::S3Config, Config as StorageConfig, StorageContainer,
+    StorageType,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
 use crate::error::{MpError, Result};
+use crate::message::protocol::JSON_PROTOCOL_VERSION;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct MinVersion {
     pub required_version: u32,
     pub recommended_version: u32,
+
+    /// Highest websocket protocol version that the server negotiates with
+    /// clients, see `Protocol`.
+    #[serde(default = "MinVersion::default_protocol_version")]
+    pub protocol_version: u32,
 }
 
 impl MinVersion {
@@ -16,25 +27,171 @@ impl MinVersion {
         let file = include_str!("../../updateAlertVersion.json");
         serde_json::from_str(file).map_err(|e| MpError::MinVersion(e.to_string()))
     }
+
+    fn default_protocol_version() -> u32 {
+        JSON_PROTOCOL_VERSION
+    }
+}
+
+/// Limits on the transactions that users send. Transactions that exceed them
+/// are rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TransactionLimits {
+    /// Maximum size of the serialized operations, in bytes.
+    pub(crate) max_bytes: usize,
+
+    /// Maximum number of operations in a transaction.
+    pub(crate) max_operations: usize,
+
+    /// Maximum number of cells that a single operation can change.
+    pub(crate) max_cells: u64,
+}
+
+impl Default for TransactionLimits {
+    fn default() -> Self {
+        TransactionLimits {
+            max_bytes: 50 * 1024 * 1024,
+            max_operations: 100_000,
+            max_cells: 10_000_000,
+        }
+    }
+}
+
+impl TransactionLimits {
+    pub(crate) fn new(config: &Config) -> Self {
+        let default = TransactionLimits::default();
+        TransactionLimits {
+            max_bytes: config.max_transaction_bytes.unwrap_or(default.max_bytes),
+            max_operations: config
+                .max_transaction_operations
+                .unwrap_or(default.max_operations),
+            max_cells: config.max_transaction_cells.unwrap_or(default.max_cells),
+        }
+    }
+}
+
+/// Limits on the transactions sent to clients that are catching up.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CatchUpLimits {
+    /// Maximum number of transactions in a response.
+    pub(crate) page_size: u64,
+
+    /// Clients that are more transactions behind than this start from the
+    /// latest checkpoint instead.
+    pub(crate) checkpoint_gap: u64,
+}
+
+impl Default for CatchUpLimits {
+    fn default() -> Self {
+        CatchUpLimits {
+            page_size: 500,
+            checkpoint_gap: 5_000,
+        }
+    }
+}
+
+impl CatchUpLimits {
+    pub(crate) fn new(config: &Config) -> Self {
+        let default = CatchUpLimits::default();
+        CatchUpLimits {
+            page_size: config
+                .catch_up_page_size
+                .unwrap_or(default.page_size)
+                .max(1),
+            checkpoint_gap: config
+                .catch_up_checkpoint_gap
+                .unwrap_or(default.checkpoint_gap),
+        }
+    }
+}
+
+/// Limits on the chat messages that users send. Messages that exceed them are
+/// rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ChatLimits {
+    /// Maximum length of a message's text, in characters.
+    pub(crate) max_length: usize,
+
+    /// Maximum number of users that a message can mention.
+    pub(crate) max_mentions: usize,
+
+    /// Maximum number of messages that a user can send in a minute.
+    pub(crate) messages_per_minute: usize,
+
+    /// Number of recent messages that are kept for each room.
+    pub(crate) history_size: usize,
+}
+
+impl Default for ChatLimits {
+    fn default() -> Self {
+        ChatLimits {
+            max_length: 4_000,
+            max_mentions: 20,
+            messages_per_minute: 30,
+            history_size: 100,
+        }
+    }
+}
+
+impl ChatLimits {
+    pub(crate) fn new(config: &Config) -> Self {
+        let default = ChatLimits::default();
+        ChatLimits {
+            max_length: config.max_chat_message_length.unwrap_or(default.max_length),
+            max_mentions: config.max_chat_mentions.unwrap_or(default.max_mentions),
+            messages_per_minute: config
+                .chat_messages_per_minute
+                .unwrap_or(default.messages_per_minute),
+            history_size: config.chat_history_size.unwrap_or(default.history_size),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct Settings {
     pub(crate) jwks: Option<JwkSet>,
     pub(crate) authenticate_jwt: bool,
+    pub(crate) heartbeat_timeout_s: i64,
     pub(crate) quadratic_api_uri: String,
     pub(crate) m2m_auth_token: String,
     pub(crate) min_version: MinVersion,
+    pub(crate) storage: StorageContainer,
+    pub(crate) transaction_limits: TransactionLimits,
+    pub(crate) catch_up_limits: CatchUpLimits,
+    pub(crate) chat_limits: ChatLimits,
 }
 
 impl Settings {
     pub(crate) async fn new(config: &Config, jwks: Option<JwkSet>) -> Self {
+        let is_local =
+            config.environment == Environment::Docker || config.environment == Environment::Local;
+
+        let storage_config = match config.storage_type {
+            StorageType::S3 => StorageConfig::S3(S3Config {
+                region: config.aws_s3_region.to_owned(),
+                access_key_id: config.aws_s3_access_key_id.to_owned(),
+                secret_access_key: config.aws_s3_secret_access_key.to_owned(),
+                bucket: config.aws_s3_bucket_name.to_owned(),
+                provider_name: "Quadratic Multiplayer",
+                is_local,
+            }),
+            StorageType::FileSystem => StorageConfig::FileSystem(FileSystemConfig {
+                path: config.storage_dir.to_owned(),
+            }),
+            StorageType::Memory => StorageConfig::Memory,
+        };
+
         Settings {
             jwks,
             authenticate_jwt: config.authenticate_jwt,
+            heartbeat_timeout_s: config.heartbeat_timeout_s,
             quadratic_api_uri: config.quadratic_api_uri.to_owned(),
             m2m_auth_token: config.m2m_auth_token.to_owned(),
             min_version: MinVersion::new().expect("Unable to load min version file"),
+            storage: StorageContainer::new(storage_config).await,
+            transaction_limits: TransactionLimits::new(config),
+            catch_up_limits: CatchUpLimits::new(config),
+            chat_limits: ChatLimits::new(config),
         }
     }
 }
@@ -7,6 +7,7 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::error::{MpError, Result};
+use crate::message::protocol::Protocol;
 use crate::state::State;
 use crate::{get_mut_room, get_room};
 use quadratic_rust_shared::quadratic_api::FilePermRole;
@@ -30,6 +31,13 @@ pub(crate) struct User {
     pub socket: Option<UserSocket>,
     #[serde(skip)]
     pub last_heartbeat: DateTime<Utc>,
+    #[serde(skip)]
+    pub protocol: Protocol,
+
+    /// The subject of the user's validated jwt.  Protections are only checked
+    /// against it, so unauthenticated users are never an owner or editor.
+    #[serde(skip)]
+    pub authenticated_user_id: Option<String>,
 }
 
 impl PartialEq for User {
@@ -152,7 +160,7 @@ impl State {
                 tracing::trace!("Updating heartbeat for {session_id}");
             });
 
-        Ok(())
+        self.sync_member(&file_id, session_id).await
     }
 
     /// Updates a user's permissions in a room
@@ -168,7 +176,7 @@ impl State {
             .entry(session_id.to_owned())
             .and_modify(|user| user.permissions = permissions);
 
-        Ok(())
+        self.sync_member(&file_id, session_id).await
     }
 
     /// updates a user's state in a room
@@ -218,7 +226,7 @@ impl State {
                 user.last_heartbeat = Utc::now();
             });
 
-        Ok(())
+        self.sync_member(file_id, session_id).await
     }
 }
 
@@ -7,7 +7,9 @@ use futures_util::SinkExt;
 use quadratic_core::cell_values::CellValues;
 use quadratic_core::controller::operations::operation::Operation;
 use quadratic_core::controller::GridController;
+use quadratic_core::grid::SheetId;
 use quadratic_core::{CellValue, SheetPos};
+use quadratic_rust_shared::pubsub::PubSubType;
 use quadratic_rust_shared::quadratic_api::FilePermRole;
 use std::sync::Arc;
 use std::{
@@ -19,7 +21,8 @@ use tokio::sync::Mutex;
 use tokio_tungstenite::{tungstenite, MaybeTlsStream, WebSocketStream};
 use uuid::Uuid;
 
-use crate::config::config;
+use crate::config::{config, Config};
+use crate::message::protocol::Protocol;
 use crate::message::request::MessageRequest;
 use crate::message::response::MessageResponse;
 use crate::state::connection::PreConnection;
@@ -87,11 +90,23 @@ pub(crate) async fn new_arc_state() -> Arc<State> {
     Arc::new(new_state().await)
 }
 
+/// Create new global state wrapped in an Arc, with the given type of PubSub
+/// rather than the configured one
+pub(crate) async fn new_arc_state_with_pubsub(pubsub_type: PubSubType) -> Arc<State> {
+    let config = Config {
+        pubsub_type,
+        ..config().unwrap()
+    };
+    Arc::new(State::new(&config, None).await.unwrap())
+}
+
 /// Create a new user with fake values
 pub(crate) fn new_user() -> User {
+    let user_id = Uuid::new_v4().to_string();
+
     User {
         session_id: Uuid::new_v4(),
-        user_id: Uuid::new_v4().to_string(),
+        user_id: user_id.clone(),
         connection_id: Uuid::new_v4(),
         first_name: FirstName().fake(),
         last_name: LastName().fake(),
@@ -111,6 +126,8 @@ pub(crate) fn new_user() -> User {
         permissions: vec![FilePermRole::FileView, FilePermRole::FileEdit],
         socket: None,
         last_heartbeat: chrono::Utc::now(),
+        protocol: Protocol::Json,
+        authenticated_user_id: Some(user_id),
         index: 0,
     }
 }
@@ -122,9 +139,23 @@ pub(crate) async fn add_user_via_ws(
     socket: Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
     user: User,
 ) -> User {
-    let session_id = user.session_id;
-    let request = MessageRequest::EnterRoom {
-        session_id,
+    let request = enter_room_request(file_id, &user, None);
+
+    // UsersInRoom and EnterRoom are sent to the client when they enter a room
+    integration_test_send_and_receive(&socket, request, true, 1).await;
+    integration_test_receive(&socket, 1).await;
+
+    user
+}
+
+/// Create an EnterRoom request for a user.
+pub(crate) fn enter_room_request(
+    file_id: Uuid,
+    user: &User,
+    protocol_version: Option<u32>,
+) -> MessageRequest {
+    MessageRequest::EnterRoom {
+        session_id: user.session_id,
         user_id: user.user_id.clone(),
         file_id,
         sheet_id: user.state.sheet_id,
@@ -136,13 +167,8 @@ pub(crate) async fn add_user_via_ws(
         cell_edit: CellEdit::default(),
         viewport: "initial viewport".to_string(),
         follow: None,
-    };
-
-    // UsersInRoom and EnterRoom are sent to the client when they enter a room
-    integration_test_send_and_receive(&socket, request, true, 1).await;
-    integration_test_receive(&socket, 1).await;
-
-    user
+        protocol_version,
+    }
 }
 
 /// Add a new user to a room via global state directly.
@@ -183,6 +209,12 @@ pub(crate) async fn new_connection(
 /// Create a new operation for testing
 pub(crate) fn operation(grid: &mut GridController, x: i64, y: i64, value: &str) -> Operation {
     let sheet_id = grid.sheet_ids().first().unwrap().to_owned();
+    sheet_operation(sheet_id, x, y, value)
+}
+
+/// Create a new operation for testing that sets a cell in a sheet.  Files
+/// in tests have a single sheet, `SheetId::test()`.
+pub(crate) fn sheet_operation(sheet_id: SheetId, x: i64, y: i64, value: &str) -> Operation {
     let sheet_pos = SheetPos { x, y, sheet_id };
     let value = CellValue::Text(value.into());
     let values = CellValues::from(value);
@@ -275,3 +307,18 @@ pub(crate) async fn integration_test_receive(
 
     last_response
 }
+
+/// Using the WebSocket created in integration_test_setup(), receive responses
+/// until one matches.  Returns the matching response.
+pub(crate) async fn integration_test_receive_until(
+    socket: &Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
+    matches: impl Fn(&MessageResponse) -> bool,
+) -> MessageResponse {
+    loop {
+        if let Some(response) = integration_test_receive(socket, 1).await {
+            if matches(&response) {
+                return response;
+            }
+        }
+    }
+}
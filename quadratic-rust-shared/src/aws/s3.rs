@@ -1,5 +1,8 @@
+use std::time::Duration;
+
 use aws_sdk_s3::{
     operation::{get_object::GetObjectOutput, put_object::PutObjectOutput},
+    presigning::PresigningConfig,
     primitives::{ByteStream, SdkBody},
     Client,
 };
@@ -21,6 +24,31 @@ pub async fn download_object(client: &Client, bucket: &str, key: &str) -> Result
         })
 }
 
+/// Create a URL that downloads an object without credentials until it
+/// expires.
+pub async fn presigned_download_url(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    expires_in: Duration,
+) -> Result<String> {
+    let error = |error: String| {
+        SharedError::Aws(Aws::S3(format!(
+            "Error signing the url of file {key} in bucket {bucket}: {error}."
+        )))
+    };
+    let config = PresigningConfig::expires_in(expires_in).map_err(|e| error(e.to_string()))?;
+    let request = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .presigned(config)
+        .await
+        .map_err(|e| error(format!("{:?}", e)))?;
+
+    Ok(request.uri().to_string())
+}
+
 pub async fn upload_object(
     client: &Client,
     bucket: &str,
@@ -44,5 +72,40 @@ pub async fn upload_object(
         })
 }
 
+/// List the keys of the objects in a bucket that start with `prefix`.
+pub async fn list_objects(client: &Client, bucket: &str, prefix: &str) -> Result<Vec<String>> {
+    let mut keys = vec![];
+    let mut continuation_token = None;
+
+    // results are paged, so keep requesting until the listing is complete
+    loop {
+        let output = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|error| {
+                SharedError::Aws(Aws::S3(format!(
+                    "Error listing files {prefix} in bucket {bucket}: {:?}.",
+                    error
+                )))
+            })?;
+
+        keys.extend(
+            output
+                .contents()
+                .iter()
+                .flat_map(|object| object.key().map(ToOwned::to_owned)),
+        );
+
+        match output.next_continuation_token() {
+            Some(token) => continuation_token = Some(token.to_owned()),
+            None => return Ok(keys),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {}
@@ -43,6 +43,21 @@ pub enum Sql {
     Schema(String),
 }
 
+#[derive(Error, Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum Storage {
+    #[error("Invalid key {0}")]
+    InvalidKey(String),
+
+    #[error("Error listing {0}: {1}")]
+    List(String, String),
+
+    #[error("Error reading {0}: {1}")]
+    Read(String, String),
+
+    #[error("Error writing {0}: {1}")]
+    Write(String, String),
+}
+
 #[derive(Error, Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum SharedError {
     #[error("Error with Arrow: {0}")]
@@ -69,6 +84,9 @@ pub enum SharedError {
     #[error("Error with SQL connector: {0}")]
     Sql(Sql),
 
+    #[error("Error with storage: {0}")]
+    Storage(Storage),
+
     #[error("Error with Uuid: {0}")]
     Uuid(String),
 }
@@ -6,6 +6,7 @@ pub mod error;
 pub mod pubsub;
 pub mod quadratic_api;
 pub mod sql;
+pub mod storage;
 
 // pub use aws::*;
 pub use error::*;
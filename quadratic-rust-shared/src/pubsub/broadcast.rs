@@ -0,0 +1,35 @@
+//! Broadcasts
+//!
+//! Multiplayer instances send messages for the users of a room on other
+//! instances over the broadcast channel.  Services that publish transactions
+//! outside of multiplayer broadcast them on the same channel, so the users
+//! that have the file open receive them.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The channel that multiplayer instances share broadcasts on.
+pub static BROADCAST_CHANNEL: &str = "quadratic-multiplayer-broadcasts";
+
+/// A message for the users of a room on other instances.  Multiplayer sends
+/// its own responses as the message, and other services send a
+/// `BroadcastMessage`, which multiplayer reads as the matching response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Broadcast<T> {
+    pub instance_id: Uuid,
+    pub file_id: Uuid,
+    pub exclude: Vec<Uuid>,
+    pub message: T,
+}
+
+/// The messages that services other than multiplayer broadcast.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum BroadcastMessage {
+    Transaction {
+        id: Uuid,
+        file_id: Uuid,
+        sequence_num: u64,
+        operations: String,
+    },
+}
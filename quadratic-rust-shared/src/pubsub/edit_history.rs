@@ -0,0 +1,120 @@
+//! Edit History
+//!
+//! The keys of a file's edit history and the writer of its records, shared
+//! by the services that sequence transactions.  Records are kept in two
+//! capped sets: the file's records by sequence number, and each user's
+//! records by time.  Only the most recent `EDIT_HISTORY_SIZE` records of
+//! each are kept.
+
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::pubsub::PubSub;
+
+/// The number of records kept for a file, and for each of its users.
+pub const EDIT_HISTORY_SIZE: usize = 10_000;
+
+/// The key of the file's records, scored by sequence number.
+pub fn edit_history_key(file_id: &Uuid) -> String {
+    format!("{file_id}:history")
+}
+
+/// The key of a user's records of the file, scored by time in milliseconds.
+pub fn user_edit_history_key(file_id: &Uuid, user_id: &str) -> String {
+    format!("{file_id}:history:{user_id}")
+}
+
+/// Adds a serialized record of a sequenced transaction to the file's edit
+/// history and to its author's.  Records are keyed by sequence number, so
+/// adding a record twice keeps the first.
+pub async fn add_edit_record(
+    connection: &mut impl PubSub,
+    file_id: &Uuid,
+    sequence_num: u64,
+    user_id: &str,
+    timestamp_millis: i64,
+    record: &str,
+) -> Result<()> {
+    let member = sequence_num.to_string();
+
+    connection
+        .add_capped_member(
+            &edit_history_key(file_id),
+            &member,
+            sequence_num as i64,
+            record,
+            EDIT_HISTORY_SIZE,
+        )
+        .await?;
+    connection
+        .add_capped_member(
+            &user_edit_history_key(file_id, user_id),
+            &member,
+            timestamp_millis,
+            record,
+            EDIT_HISTORY_SIZE,
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubsub::memory::{MemoryConfig, MemoryConnection};
+    use crate::pubsub::Config;
+
+    #[tokio::test]
+    async fn adds_records_to_file_and_user_sets() {
+        let config = Config::Memory(MemoryConfig {
+            active_channels: Uuid::new_v4().to_string(),
+        });
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+        let file_id = Uuid::new_v4();
+
+        add_edit_record(&mut connection, &file_id, 1, "user 1", 20, "record 1")
+            .await
+            .unwrap();
+        add_edit_record(&mut connection, &file_id, 2, "user 2", 10, "record 2")
+            .await
+            .unwrap();
+
+        // a second record with the same sequence number is ignored
+        add_edit_record(&mut connection, &file_id, 2, "user 2", 30, "record 3")
+            .await
+            .unwrap();
+
+        let records = connection
+            .capped_members(
+                &edit_history_key(&file_id),
+                i64::MIN,
+                i64::MAX,
+                false,
+                0,
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                ("1".to_string(), "record 1".to_string()),
+                ("2".to_string(), "record 2".to_string()),
+            ]
+        );
+
+        let records = connection
+            .capped_members(
+                &user_edit_history_key(&file_id, "user 2"),
+                0,
+                20,
+                false,
+                0,
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(records, vec![("2".to_string(), "record 2".to_string())]);
+    }
+}
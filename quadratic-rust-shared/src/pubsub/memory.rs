@@ -0,0 +1,901 @@
+//! In-memory PubSub
+//!
+//! Implements the Redis Streams semantics in process: streams with explicit
+//! or generated ids, consumer groups with pending messages and acks, sorted
+//! sets of active channels, hashes of members, capped sets, and range reads
+//! and trims.
+//! There is also a simple publish/subscribe for broadcasts.
+//!
+//! All connections in a process share the same data, like connections to the
+//! same Redis server.  Nothing is shared between processes, so every service
+//! that uses the data must run in the same process.
+
+use chrono::Utc;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use tokio::sync::broadcast;
+
+use crate::pubsub::Config;
+use crate::{error::Result, SharedError};
+
+/// How many broadcasts a subscriber can fall behind before it misses some.
+const BROADCAST_CAPACITY: usize = 1_000;
+
+#[derive(Debug, Clone)]
+pub struct MemoryConfig {
+    pub active_channels: String,
+}
+
+type Message = (String, String);
+
+/// A stream id: milliseconds and a sequence number, as in Redis.
+type Id = (u64, u64);
+
+#[derive(Debug, Default)]
+struct Group {
+    last_delivered: Id,
+
+    // delivered messages that are not acknowledged, and their consumer
+    pending: BTreeMap<Id, String>,
+}
+
+#[derive(Debug, Default)]
+struct Stream {
+    entries: BTreeMap<Id, String>,
+    groups: HashMap<String, Group>,
+    last_id: Id,
+}
+
+/// Members ordered by score, and their values.
+#[derive(Debug, Default)]
+struct CappedSet {
+    scores: BTreeSet<(i64, String)>,
+    values: HashMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    streams: HashMap<String, Stream>,
+    sorted_sets: HashMap<String, HashMap<String, i64>>,
+    hashes: HashMap<String, HashMap<String, String>>,
+    capped_sets: HashMap<String, CappedSet>,
+    values: HashMap<String, u64>,
+    broadcasts: HashMap<String, broadcast::Sender<String>>,
+}
+
+fn store() -> &'static Mutex<Store> {
+    static STORE: OnceLock<Mutex<Store>> = OnceLock::new();
+    STORE.get_or_init(Mutex::default)
+}
+
+fn sequence_num_key(channel: &str) -> String {
+    format!("{channel}:sequence_num")
+}
+
+fn to_key(id: Id, preserve_sequence: bool) -> String {
+    if preserve_sequence {
+        format!("{}-{}", id.0, id.1)
+    } else {
+        id.0.to_string()
+    }
+}
+
+/// Parse an id.  An id without a sequence number is the first (or, for the
+/// end of a range, the last) id with those milliseconds.
+fn parse_id(id: &str, is_end: bool) -> Result<Id> {
+    let invalid = || SharedError::PubSub(format!("Invalid stream id {id}"));
+
+    match id {
+        "-" => Ok((0, 0)),
+        "+" => Ok((u64::MAX, u64::MAX)),
+        _ => match id.split_once('-') {
+            Some((millis, sequence)) => Ok((
+                millis.parse().map_err(|_| invalid())?,
+                sequence.parse().map_err(|_| invalid())?,
+            )),
+            None => Ok((
+                id.parse().map_err(|_| invalid())?,
+                if is_end { u64::MAX } else { 0 },
+            )),
+        },
+    }
+}
+
+impl Stream {
+    /// Add a message, like XADD.  Ids must increase; "*" generates one from
+    /// the current time.
+    fn add(&mut self, id: &str, value: &str) -> Result<Id> {
+        let id = match id {
+            "*" => {
+                let millis = Utc::now().timestamp_millis().max(0) as u64;
+                if millis > self.last_id.0 {
+                    (millis, 0)
+                } else {
+                    (self.last_id.0, self.last_id.1 + 1)
+                }
+            }
+            _ => parse_id(id, false)?,
+        };
+
+        if id == (0, 0) || id <= self.last_id {
+            return Err(SharedError::PubSub(format!(
+                "The stream id {}-{} is equal or smaller than the last id",
+                id.0, id.1
+            )));
+        }
+
+        self.entries.insert(id, value.into());
+        self.last_id = id;
+
+        Ok(id)
+    }
+
+    fn range(&self, from: Id, to: Id, preserve_sequence: bool) -> Vec<Message> {
+        if from > to {
+            return vec![];
+        }
+
+        self.entries
+            .range(from..=to)
+            .map(|(id, value)| (to_key(*id, preserve_sequence), value.to_owned()))
+            .collect()
+    }
+}
+
+impl Store {
+    fn range(&self, channel: &str, from: &str, to: &str, preserve: bool) -> Result<Vec<Message>> {
+        let (from, to) = (parse_id(from, false)?, parse_id(to, true)?);
+
+        Ok(self
+            .streams
+            .get(channel)
+            .map(|stream| stream.range(from, to, preserve))
+            .unwrap_or_default())
+    }
+
+    fn upsert_active_channel(&mut self, set_key: &str, channel: &str) {
+        self.sorted_sets
+            .entry(set_key.into())
+            .or_default()
+            .insert(channel.into(), Utc::now().timestamp_millis());
+    }
+}
+
+/// A connection to the process' in-memory PubSub.
+#[derive(Debug, Clone)]
+pub struct MemoryConnection {
+    store: &'static Mutex<Store>,
+}
+
+impl MemoryConnection {
+    fn lock(&self) -> Result<MutexGuard<'_, Store>> {
+        self.store
+            .lock()
+            .map_err(|e| SharedError::PubSub(format!("Error locking the in-memory PubSub: {e}")))
+    }
+
+    /// Send a message to the current subscribers of a channel, like Redis'
+    /// PUBLISH.  Messages without subscribers are dropped.
+    pub fn broadcast(&self, channel: &str, message: &str) -> Result<()> {
+        if let Some(sender) = self.lock()?.broadcasts.get(channel) {
+            // an error means that there are no subscribers
+            let _ = sender.send(message.into());
+        }
+
+        Ok(())
+    }
+
+    /// Receive the messages broadcast to a channel from now on, like Redis'
+    /// SUBSCRIBE.
+    pub fn listen(&self, channel: &str) -> Result<broadcast::Receiver<String>> {
+        let receiver = self
+            .lock()?
+            .broadcasts
+            .entry(channel.into())
+            .or_insert_with(|| broadcast::channel(BROADCAST_CAPACITY).0)
+            .subscribe();
+
+        Ok(receiver)
+    }
+}
+
+impl super::PubSub for MemoryConnection {
+    type Connection = MemoryConnection;
+
+    /// Create a new connection to the in-memory PubSub.
+    async fn new(config: Config) -> Result<MemoryConnection> {
+        Self::connect(config).await
+    }
+
+    /// Connect to the in-memory PubSub.
+    async fn connect(config: Config) -> Result<MemoryConnection> {
+        match config {
+            Config::Memory(_) => Ok(MemoryConnection { store: store() }),
+            _ => Err(SharedError::PubSub(
+                "Config type must be MemoryConfig".into(),
+            )),
+        }
+    }
+
+    /// The in-memory PubSub is always available
+    async fn is_healthy(&mut self) -> bool {
+        true
+    }
+
+    /// Get a list of channels (all keys, as in Redis)
+    async fn channels(&mut self) -> Result<Vec<String>> {
+        let store = self.lock()?;
+        let channels = store
+            .streams
+            .keys()
+            .chain(store.sorted_sets.keys())
+            .chain(store.hashes.keys())
+            .chain(store.capped_sets.keys())
+            .chain(store.values.keys())
+            .cloned()
+            .collect();
+
+        Ok(channels)
+    }
+
+    /// Get a list of active channels, least recently active first
+    async fn active_channels(&mut self, set_key: &str) -> Result<Vec<String>> {
+        let store = self.lock()?;
+        let mut channels = store
+            .sorted_sets
+            .get(set_key)
+            .map(|set| set.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        channels.sort_by(|(a, a_score), (b, b_score)| a_score.cmp(b_score).then(a.cmp(b)));
+
+        Ok(channels
+            .into_iter()
+            .map(|(channel, _)| channel.to_owned())
+            .collect())
+    }
+
+    /// Insert or update a key within an active channel
+    async fn upsert_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        self.lock()?.upsert_active_channel(set_key, channel);
+        Ok(())
+    }
+
+    /// Remove an a key within an active channel
+    async fn remove_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        if let Some(set) = self.lock()?.sorted_sets.get_mut(set_key) {
+            set.remove(channel);
+        }
+
+        Ok(())
+    }
+
+    /// Create a group and a key (if it doesn't already exist).  The group
+    /// receives messages that are published after it is created.
+    async fn subscribe(&mut self, channel: &str, group: &str) -> Result<()> {
+        let mut store = self.lock()?;
+        let stream = store.streams.entry(channel.into()).or_default();
+        let last_id = stream.last_id;
+
+        stream.groups.entry(group.into()).or_insert_with(|| Group {
+            last_delivered: last_id,
+            ..Default::default()
+        });
+
+        Ok(())
+    }
+
+    /// Publish a message to a channel.
+    async fn publish(
+        &mut self,
+        channel: &str,
+        key: &str,
+        value: &str,
+        active_channel: Option<&str>,
+    ) -> Result<()> {
+        let mut store = self.lock()?;
+        store
+            .streams
+            .entry(channel.into())
+            .or_default()
+            .add(key, value)?;
+
+        // add the channel to the active channels set
+        if let Some(active_channel) = active_channel {
+            store.upsert_active_channel(active_channel, channel);
+        }
+
+        Ok(())
+    }
+
+    /// Publish a message to a channel if `sequence_num` is the channel's next
+    /// sequence number, which is at least `min_sequence_num + 1`.
+    ///
+    /// Returns the next sequence number.  The message was published if it is
+    /// `sequence_num`; otherwise the caller should retry with the returned
+    /// sequence number.
+    async fn publish_if_next(
+        &mut self,
+        channel: &str,
+        sequence_num: u64,
+        min_sequence_num: u64,
+        value: &str,
+        active_channel: Option<&str>,
+    ) -> Result<u64> {
+        let mut store = self.lock()?;
+        let key = sequence_num_key(channel);
+
+        // start from the stream's last message if the key is missing
+        let last = match store.values.get(&key) {
+            Some(last) => *last,
+            None => store
+                .streams
+                .get(channel)
+                .and_then(|stream| stream.entries.keys().next_back())
+                .map_or(0, |id| id.0),
+        };
+        let next = last.max(min_sequence_num) + 1;
+
+        if sequence_num == next {
+            store
+                .streams
+                .entry(channel.into())
+                .or_default()
+                .add(&next.to_string(), value)?;
+            store.values.insert(key, next);
+
+            // add the channel to the active channels set
+            if let Some(active_channel) = active_channel {
+                store.upsert_active_channel(active_channel, channel);
+            }
+        }
+
+        Ok(next)
+    }
+
+    /// Insert or update a member of a set
+    async fn upsert_member(&mut self, set_key: &str, member: &str, value: &str) -> Result<()> {
+        self.lock()?
+            .hashes
+            .entry(set_key.into())
+            .or_default()
+            .insert(member.into(), value.into());
+
+        Ok(())
+    }
+
+    /// Remove a member from a set
+    async fn remove_member(&mut self, set_key: &str, member: &str) -> Result<()> {
+        if let Some(hash) = self.lock()?.hashes.get_mut(set_key) {
+            hash.remove(member);
+        }
+
+        Ok(())
+    }
+
+    /// Get all members of a set and their values
+    async fn members(&mut self, set_key: &str) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .lock()?
+            .hashes
+            .get(set_key)
+            .map(|hash| {
+                hash.iter()
+                    .map(|(member, value)| (member.to_owned(), value.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Add a member to a capped set, removing the lowest scored members
+    /// beyond `max_members`.  Returns false if the member already exists.
+    async fn add_capped_member(
+        &mut self,
+        set_key: &str,
+        member: &str,
+        score: i64,
+        value: &str,
+        max_members: usize,
+    ) -> Result<bool> {
+        let mut store = self.lock()?;
+        let set = store.capped_sets.entry(set_key.into()).or_default();
+
+        if set.values.contains_key(member) {
+            return Ok(false);
+        }
+
+        set.scores.insert((score, member.into()));
+        set.values.insert(member.into(), value.into());
+
+        while set.scores.len() > max_members {
+            if let Some((_, dropped)) = set.scores.pop_first() {
+                set.values.remove(&dropped);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Get up to `count` members of a capped set with scores between
+    /// `min_score` and `max_score`, after skipping `offset` of them.  Members
+    /// are in order of score, highest first if `reverse`.
+    async fn capped_members(
+        &mut self,
+        set_key: &str,
+        min_score: i64,
+        max_score: i64,
+        reverse: bool,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<(String, String)>> {
+        let store = self.lock()?;
+        let Some(set) = store.capped_sets.get(set_key) else {
+            return Ok(vec![]);
+        };
+
+        let in_range = set
+            .scores
+            .iter()
+            .filter(|(score, _)| (min_score..=max_score).contains(score));
+        let members: Vec<_> = if reverse {
+            in_range.rev().skip(offset).take(count).collect()
+        } else {
+            in_range.skip(offset).take(count).collect()
+        };
+
+        Ok(members
+            .into_iter()
+            .map(|(_, member)| (member.to_owned(), set.values[member].to_owned()))
+            .collect())
+    }
+
+    /// Acknowledge that a message was processed
+    async fn ack(
+        &mut self,
+        channel: &str,
+        group: &str,
+        keys: Vec<&str>,
+        active_channel: Option<&str>,
+        _preserve_sequence: bool,
+    ) -> Result<()> {
+        if keys.is_empty() {
+            return Err(SharedError::PubSub(
+                "Error acking messages for channel {channel}: no keys provided".into(),
+            ));
+        }
+
+        let ids = keys
+            .iter()
+            .map(|key| parse_id(key, false))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut store = self.lock()?;
+        if let Some(group) = store
+            .streams
+            .get_mut(channel)
+            .and_then(|stream| stream.groups.get_mut(group))
+        {
+            for id in ids {
+                group.pending.remove(&id);
+            }
+        }
+
+        // remove the channel from the active channels set
+        if let Some(set) = active_channel.and_then(|key| store.sorted_sets.get_mut(key)) {
+            set.remove(channel);
+        }
+
+        Ok(())
+    }
+
+    /// Trim messages before `key` from a channel.  Returns the number of
+    /// messages that were trimmed.
+    async fn trim(&mut self, channel: &str, key: &str) -> Result<i64> {
+        let min_id = parse_id(key, false)?;
+        let mut store = self.lock()?;
+        let Some(stream) = store.streams.get_mut(channel) else {
+            return Ok(0);
+        };
+
+        let kept = stream.entries.split_off(&min_id);
+        let trimmed = std::mem::replace(&mut stream.entries, kept);
+
+        Ok(trimmed.len() as i64)
+    }
+
+    /// Get unread messages from a channel.  Specify an id to get the pending
+    /// messages after it, or None to get all new messages.
+    ///
+    /// Once messages are processed, they must be acknowledged with `ack` to
+    /// remove them from the pending messages.
+    async fn messages(
+        &mut self,
+        channel: &str,
+        group: &str,
+        consumer: &str,
+        maybe_id: Option<&str>,
+        max_messages: usize,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        let after = maybe_id.map(|id| parse_id(id, false)).transpose()?;
+        let mut store = self.lock()?;
+        let no_group = || {
+            SharedError::PubSub(format!(
+                "Error reading messages for channel {channel}: no group {group}"
+            ))
+        };
+        let stream = store.streams.get_mut(channel).ok_or_else(no_group)?;
+        let Stream {
+            entries, groups, ..
+        } = stream;
+        let group = groups.get_mut(group).ok_or_else(no_group)?;
+
+        let messages = match after {
+            // pending messages of this consumer
+            Some(after) => group
+                .pending
+                .range((after.0, after.1.saturating_add(1))..)
+                .filter(|(_, pending_consumer)| pending_consumer.as_str() == consumer)
+                .flat_map(|(id, _)| entries.get_key_value(id))
+                .take(max_messages)
+                .map(|(id, value)| (to_key(*id, preserve_sequence), value.to_owned()))
+                .collect(),
+
+            // new messages, which become pending
+            None => {
+                let delivered = entries
+                    .range(
+                        (
+                            group.last_delivered.0,
+                            group.last_delivered.1.saturating_add(1),
+                        )..,
+                    )
+                    .take(max_messages)
+                    .map(|(id, value)| (*id, value.to_owned()))
+                    .collect::<Vec<_>>();
+
+                for (id, _) in delivered.iter() {
+                    group.pending.insert(*id, consumer.into());
+                    group.last_delivered = *id;
+                }
+
+                delivered
+                    .into_iter()
+                    .map(|(id, value)| (to_key(id, preserve_sequence), value))
+                    .collect()
+            }
+        };
+
+        Ok(messages)
+    }
+
+    /// Get messages from the beginning of a channel ending at a specific id
+    async fn get_messages_before(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        self.lock()?.range(channel, "-", id, preserve_sequence)
+    }
+
+    /// Get messages from a channel starting from a specific id
+    async fn get_messages_from(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        self.lock()?.range(channel, id, "+", preserve_sequence)
+    }
+
+    /// Get messages from a channel between two ids, inclusive
+    async fn get_messages_range(
+        &mut self,
+        channel: &str,
+        from_id: &str,
+        to_id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        self.lock()?
+            .range(channel, from_id, to_id, preserve_sequence)
+    }
+
+    /// Get the last message in a channel
+    async fn last_message(&mut self, channel: &str, preserve_sequence: bool) -> Result<Message> {
+        self.lock()?
+            .streams
+            .get(channel)
+            .and_then(|stream| stream.entries.last_key_value())
+            .map(|(id, value)| (to_key(*id, preserve_sequence), value.to_owned()))
+            .ok_or_else(|| {
+                SharedError::PubSub("Error getting last message: no messages found".into())
+            })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::pubsub::PubSub;
+
+    fn setup() -> (Config, String) {
+        let channel = Uuid::new_v4().to_string();
+        let config = Config::Memory(MemoryConfig {
+            active_channels: Uuid::new_v4().to_string(),
+        });
+
+        (config, channel)
+    }
+
+    #[tokio::test]
+    async fn memory_subscribe_publish_get_messages_and_ack() {
+        let (config, channel) = setup();
+        let messages = ["test 1", "test 2", "test 3"];
+        let group = "group 1";
+        let consumer = "consumer 1";
+
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+        connection.subscribe(&channel, group).await.unwrap();
+
+        for (key, value) in messages.iter().enumerate() {
+            connection
+                .publish(&channel, &(key + 1).to_string(), value, None)
+                .await
+                .unwrap();
+        }
+
+        // ids must increase
+        assert!(connection
+            .publish(&channel, "2", "test 4", None)
+            .await
+            .is_err());
+
+        // new messages are delivered once
+        let results = connection
+            .messages(&channel, group, consumer, None, 2, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![("1".into(), "test 1".into()), ("2".into(), "test 2".into())]
+        );
+        let results = connection
+            .messages(&channel, group, consumer, None, 10, false)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![("3".into(), "test 3".into())]);
+
+        // delivered messages are pending until they are acknowledged
+        let results = connection
+            .messages(&channel, group, consumer, Some("1"), 10, false)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        connection
+            .ack(&channel, group, vec!["1", "2", "3"], None, false)
+            .await
+            .unwrap();
+        let results = connection
+            .messages(&channel, group, consumer, Some("0"), 10, false)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        // reading from a group that doesn't exist is an error
+        assert!(connection
+            .messages(&channel, "group 2", consumer, None, 10, false)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn memory_ranges_and_trims() {
+        let (config, channel) = setup();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+
+        for key in 1..=5 {
+            connection
+                .publish(&channel, &key.to_string(), &format!("test {key}"), None)
+                .await
+                .unwrap();
+        }
+
+        let ids = |messages: Vec<Message>| {
+            messages
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<String>>()
+        };
+
+        let results = connection
+            .get_messages_from(&channel, "4", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(results), vec!["4", "5"]);
+
+        let results = connection
+            .get_messages_before(&channel, "2", true)
+            .await
+            .unwrap();
+        assert_eq!(ids(results), vec!["1-0", "2-0"]);
+
+        let results = connection
+            .get_messages_range(&channel, "2", "3", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(results), vec!["2", "3"]);
+
+        // trimming keeps the message at the key
+        assert_eq!(connection.trim(&channel, "3-0").await.unwrap(), 2);
+        let results = connection
+            .get_messages_before(&channel, "+", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(results), vec!["3", "4", "5"]);
+
+        assert_eq!(
+            connection.last_message(&channel, false).await.unwrap(),
+            ("5".into(), "test 5".into())
+        );
+        assert!(connection.last_message("missing", false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn memory_publish_if_next() {
+        let (config, channel) = setup();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+
+        // the first sequence number is after the minimum
+        let next = connection
+            .publish_if_next(&channel, 1, 5, "test 1", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 6);
+
+        let next = connection
+            .publish_if_next(&channel, 6, 5, "test 1", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 6);
+
+        // a sequence number can only be used once
+        let next = connection
+            .publish_if_next(&channel, 6, 5, "test 2", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 7);
+
+        let results = connection
+            .get_messages_from(&channel, "0", false)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![("6".into(), "test 1".into())]);
+    }
+
+    #[tokio::test]
+    async fn memory_publish_if_next_after_existing_messages() {
+        let (config, channel) = setup();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+
+        // messages that were published without a sequence number key
+        for key in ["1", "2", "3"] {
+            connection
+                .publish(&channel, key, "test", None)
+                .await
+                .unwrap();
+        }
+
+        let next = connection
+            .publish_if_next(&channel, 1, 0, "test 4", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 4);
+    }
+
+    #[tokio::test]
+    async fn memory_active_channels_and_members() {
+        let (config, _) = setup();
+        let active_channels = Uuid::new_v4().to_string();
+        let set_key = Uuid::new_v4().to_string();
+        let channels = [Uuid::new_v4().to_string(), Uuid::new_v4().to_string()];
+
+        // connections share data
+        let mut connection = MemoryConnection::new(config.clone()).await.unwrap();
+        let mut other = MemoryConnection::new(config).await.unwrap();
+
+        for channel in channels.iter() {
+            connection
+                .publish(channel, "*", "test", Some(&active_channels))
+                .await
+                .unwrap();
+        }
+
+        let mut results = other.active_channels(&active_channels).await.unwrap();
+        results.sort();
+        let mut expected = channels.to_vec();
+        expected.sort();
+        assert_eq!(results, expected);
+
+        other
+            .ack(
+                &channels[0],
+                "group",
+                vec!["1"],
+                Some(&active_channels),
+                false,
+            )
+            .await
+            .unwrap();
+        let results = connection.active_channels(&active_channels).await.unwrap();
+        assert_eq!(results, vec![channels[1].clone()]);
+
+        connection
+            .upsert_member(&set_key, "user 1", "value 1")
+            .await
+            .unwrap();
+        connection
+            .upsert_member(&set_key, "user 1", "value 2")
+            .await
+            .unwrap();
+        assert_eq!(
+            other.members(&set_key).await.unwrap(),
+            vec![("user 1".into(), "value 2".into())]
+        );
+
+        other.remove_member(&set_key, "user 1").await.unwrap();
+        assert!(connection.members(&set_key).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_capped_members() {
+        let (config, _) = setup();
+        let set_key = Uuid::new_v4().to_string();
+        let mut connection = MemoryConnection::new(config).await.unwrap();
+
+        for score in 1..=4 {
+            let added = connection
+                .add_capped_member(&set_key, &score.to_string(), score, "value", 3)
+                .await
+                .unwrap();
+            assert!(added);
+        }
+
+        // members are unique
+        let added = connection
+            .add_capped_member(&set_key, "4", 5, "other", 3)
+            .await
+            .unwrap();
+        assert!(!added);
+
+        // the lowest scored member was removed
+        let results = connection
+            .capped_members(&set_key, i64::MIN, i64::MAX, false, 0, 10)
+            .await
+            .unwrap();
+        let members = results.iter().map(|(member, _)| member.as_str());
+        assert_eq!(members.collect::<Vec<_>>(), vec!["2", "3", "4"]);
+        assert_eq!(results[2].1, "value");
+
+        let results = connection
+            .capped_members(&set_key, 2, 4, true, 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![("3".into(), "value".into())]);
+    }
+
+    #[tokio::test]
+    async fn memory_broadcasts() {
+        let (config, channel) = setup();
+        let connection = MemoryConnection::new(config).await.unwrap();
+
+        // messages without subscribers are dropped
+        connection.broadcast(&channel, "test 1").unwrap();
+
+        let mut receiver = connection.listen(&channel).unwrap();
+        connection.broadcast(&channel, "test 2").unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), "test 2");
+    }
+}
@@ -1,16 +1,59 @@
+pub mod broadcast;
+pub mod edit_history;
+pub mod memory;
 pub mod redis;
 pub mod redis_streams;
 
 use futures_util::Future;
+use serde::Deserialize;
+use strum_macros::Display;
 
-use crate::error::Result;
+use crate::environment::Environment;
+use crate::error::{Result, SharedError};
+use crate::pubsub::memory::{MemoryConfig, MemoryConnection};
 use crate::pubsub::redis::RedisConfig;
-use crate::pubsub::redis_streams::RedisStreamsConfig;
+use crate::pubsub::redis_streams::{RedisConnection as RedisStreamsConnection, RedisStreamsConfig};
+
+/// The backends that are selectable in a service's environment.
+#[derive(Display, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum PubSubType {
+    #[default]
+    Redis,
+    Memory,
+}
+
+impl PubSubType {
+    /// The in-memory backend is only shared by the services and instances in
+    /// one process, which is only the case in tests.
+    pub fn check_environment(self, environment: &Environment) -> Result<()> {
+        if self == PubSubType::Memory && *environment != Environment::Test {
+            return Err(SharedError::PubSub(format!(
+                "PUBSUB_TYPE={self} is only supported when ENVIRONMENT=test, not {environment}"
+            )));
+        }
+
+        Ok(())
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum Config {
     Redis(RedisConfig),
     RedisStreams(RedisStreamsConfig),
+    Memory(MemoryConfig),
+}
+
+impl Config {
+    /// The key of the set of active channels
+    pub fn active_channels(&self) -> &str {
+        match self {
+            Config::Redis(config) => &config.active_channels,
+            Config::RedisStreams(config) => &config.active_channels,
+            Config::Memory(config) => &config.active_channels,
+        }
+    }
 }
 
 pub trait PubSub {
@@ -51,6 +94,52 @@ pub trait PubSub {
         active_channel: Option<&str>,
     ) -> impl Future<Output = Result<()>> + Send;
 
+    fn publish_if_next(
+        &mut self,
+        channel: &str,
+        sequence_num: u64,
+        min_sequence_num: u64,
+        value: &str,
+        active_channel: Option<&str>,
+    ) -> impl Future<Output = Result<u64>> + Send;
+
+    fn upsert_member(
+        &mut self,
+        set_key: &str,
+        member: &str,
+        value: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn remove_member(
+        &mut self,
+        set_key: &str,
+        member: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn members(
+        &mut self,
+        set_key: &str,
+    ) -> impl Future<Output = Result<Vec<(String, String)>>> + Send;
+
+    fn add_capped_member(
+        &mut self,
+        set_key: &str,
+        member: &str,
+        score: i64,
+        value: &str,
+        max_members: usize,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    fn capped_members(
+        &mut self,
+        set_key: &str,
+        min_score: i64,
+        max_score: i64,
+        reverse: bool,
+        offset: usize,
+        count: usize,
+    ) -> impl Future<Output = Result<Vec<(String, String)>>> + Send;
+
     fn ack(
         &mut self,
         channel: &str,
@@ -86,9 +175,282 @@ pub trait PubSub {
         preserve_sequence: bool,
     ) -> impl Future<Output = Result<Vec<(String, String)>>> + Send;
 
+    fn get_messages_range(
+        &mut self,
+        channel: &str,
+        from_id: &str,
+        to_id: &str,
+        preserve_sequence: bool,
+    ) -> impl Future<Output = Result<Vec<(String, String)>>> + Send;
+
     fn last_message(
         &mut self,
         channel: &str,
         preserve_sequence: bool,
     ) -> impl Future<Output = Result<(String, String)>> + Send;
 }
+
+/// A connection to the transaction queue, which is Redis Streams or the
+/// in-memory PubSub depending on the Config.
+#[derive(Debug)]
+pub enum Connection {
+    RedisStreams(RedisStreamsConnection),
+    Memory(MemoryConnection),
+}
+
+macro_rules! dispatch {
+    ( $self:ident, $method:ident ( $( $arg:expr ),* ) ) => {
+        match $self {
+            Connection::RedisStreams(connection) => connection.$method($( $arg ),*).await,
+            Connection::Memory(connection) => connection.$method($( $arg ),*).await,
+        }
+    };
+}
+
+impl PubSub for Connection {
+    type Connection = Connection;
+
+    async fn new(config: Config) -> Result<Connection> {
+        Self::connect(config).await
+    }
+
+    async fn connect(config: Config) -> Result<Connection> {
+        match config {
+            Config::Memory(_) => Ok(Connection::Memory(MemoryConnection::new(config).await?)),
+            _ => Ok(Connection::RedisStreams(
+                RedisStreamsConnection::new(config).await?,
+            )),
+        }
+    }
+
+    async fn is_healthy(&mut self) -> bool {
+        dispatch!(self, is_healthy())
+    }
+
+    async fn channels(&mut self) -> Result<Vec<String>> {
+        dispatch!(self, channels())
+    }
+
+    async fn active_channels(&mut self, channel: &str) -> Result<Vec<String>> {
+        dispatch!(self, active_channels(channel))
+    }
+
+    async fn upsert_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        dispatch!(self, upsert_active_channel(set_key, channel))
+    }
+
+    async fn remove_active_channel(&mut self, set_key: &str, channel: &str) -> Result<()> {
+        dispatch!(self, remove_active_channel(set_key, channel))
+    }
+
+    async fn subscribe(&mut self, channel: &str, group: &str) -> Result<()> {
+        dispatch!(self, subscribe(channel, group))
+    }
+
+    async fn publish(
+        &mut self,
+        channel: &str,
+        key: &str,
+        value: &str,
+        active_channel: Option<&str>,
+    ) -> Result<()> {
+        dispatch!(self, publish(channel, key, value, active_channel))
+    }
+
+    async fn publish_if_next(
+        &mut self,
+        channel: &str,
+        sequence_num: u64,
+        min_sequence_num: u64,
+        value: &str,
+        active_channel: Option<&str>,
+    ) -> Result<u64> {
+        dispatch!(
+            self,
+            publish_if_next(
+                channel,
+                sequence_num,
+                min_sequence_num,
+                value,
+                active_channel
+            )
+        )
+    }
+
+    async fn upsert_member(&mut self, set_key: &str, member: &str, value: &str) -> Result<()> {
+        dispatch!(self, upsert_member(set_key, member, value))
+    }
+
+    async fn remove_member(&mut self, set_key: &str, member: &str) -> Result<()> {
+        dispatch!(self, remove_member(set_key, member))
+    }
+
+    async fn members(&mut self, set_key: &str) -> Result<Vec<(String, String)>> {
+        dispatch!(self, members(set_key))
+    }
+
+    async fn add_capped_member(
+        &mut self,
+        set_key: &str,
+        member: &str,
+        score: i64,
+        value: &str,
+        max_members: usize,
+    ) -> Result<bool> {
+        dispatch!(
+            self,
+            add_capped_member(set_key, member, score, value, max_members)
+        )
+    }
+
+    async fn capped_members(
+        &mut self,
+        set_key: &str,
+        min_score: i64,
+        max_score: i64,
+        reverse: bool,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<(String, String)>> {
+        dispatch!(
+            self,
+            capped_members(set_key, min_score, max_score, reverse, offset, count)
+        )
+    }
+
+    async fn ack(
+        &mut self,
+        channel: &str,
+        group: &str,
+        keys: Vec<&str>,
+        active_channel: Option<&str>,
+        preserve_sequence: bool,
+    ) -> Result<()> {
+        dispatch!(
+            self,
+            ack(channel, group, keys, active_channel, preserve_sequence)
+        )
+    }
+
+    async fn trim(&mut self, channel: &str, key: &str) -> Result<i64> {
+        dispatch!(self, trim(channel, key))
+    }
+
+    async fn messages(
+        &mut self,
+        channel: &str,
+        group: &str,
+        consumer: &str,
+        keys: Option<&str>,
+        max_messages: usize,
+        preserve_sequence: bool,
+    ) -> Result<Vec<(String, String)>> {
+        dispatch!(
+            self,
+            messages(
+                channel,
+                group,
+                consumer,
+                keys,
+                max_messages,
+                preserve_sequence
+            )
+        )
+    }
+
+    async fn get_messages_before(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<(String, String)>> {
+        dispatch!(self, get_messages_before(channel, id, preserve_sequence))
+    }
+
+    async fn get_messages_from(
+        &mut self,
+        channel: &str,
+        id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<(String, String)>> {
+        dispatch!(self, get_messages_from(channel, id, preserve_sequence))
+    }
+
+    async fn get_messages_range(
+        &mut self,
+        channel: &str,
+        from_id: &str,
+        to_id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<(String, String)>> {
+        dispatch!(
+            self,
+            get_messages_range(channel, from_id, to_id, preserve_sequence)
+        )
+    }
+
+    async fn last_message(
+        &mut self,
+        channel: &str,
+        preserve_sequence: bool,
+    ) -> Result<(String, String)> {
+        dispatch!(self, last_message(channel, preserve_sequence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pubsub_types() {
+        let parse = |value: &str| serde_json::from_str::<PubSubType>(&format!("\"{value}\""));
+
+        assert_eq!(parse("redis").unwrap(), PubSubType::Redis);
+        assert_eq!(parse("memory").unwrap(), PubSubType::Memory);
+        assert!(parse("kafka").is_err());
+        assert_eq!(PubSubType::Memory.to_string(), "memory");
+    }
+
+    #[test]
+    fn allows_memory_pubsub_only_in_tests() {
+        assert!(PubSubType::Memory
+            .check_environment(&Environment::Test)
+            .is_ok());
+        assert!(PubSubType::Memory
+            .check_environment(&Environment::Production)
+            .is_err());
+        assert!(PubSubType::Memory
+            .check_environment(&Environment::Docker)
+            .is_err());
+        assert!(PubSubType::Redis
+            .check_environment(&Environment::Production)
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn connects_to_the_configured_pubsub() {
+        let config = Config::Memory(MemoryConfig {
+            active_channels: "active_channels".into(),
+        });
+        let mut connection = Connection::new(config.to_owned()).await.unwrap();
+        let channel = uuid::Uuid::new_v4().to_string();
+
+        assert!(matches!(connection, Connection::Memory(_)));
+        assert_eq!(config.active_channels(), "active_channels");
+
+        connection
+            .publish(&channel, "1", "one", Some(config.active_channels()))
+            .await
+            .unwrap();
+        assert_eq!(
+            connection.last_message(&channel, false).await.unwrap(),
+            ("1".into(), "one".into())
+        );
+        assert!(connection
+            .active_channels(config.active_channels())
+            .await
+            .unwrap()
+            .contains(&channel));
+    }
+}
@@ -2,6 +2,7 @@ use redis::{
     aio::{AsyncStream, MultiplexedConnection, PubSub},
     cmd, AsyncCommands, Client,
 };
+use std::fmt::{self, Debug};
 use std::pin::Pin;
 
 use crate::pubsub::Config;
@@ -22,6 +23,12 @@ pub struct RedisConnection {
     multiplex: MultiplexedConnection,
 }
 
+impl Debug for RedisConnection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.multiplex)
+    }
+}
+
 fn client(config: Config) -> Result<Client> {
     if let Config::Redis(RedisConfig {
         host,
@@ -101,6 +108,57 @@ impl super::PubSub for RedisConnection {
         Ok(())
     }
 
+    async fn publish_if_next(
+        &mut self,
+        _channel: &str,
+        _sequence_num: u64,
+        _min_sequence_num: u64,
+        _value: &str,
+        _active_channel: Option<&str>,
+    ) -> Result<u64> {
+        unimplemented!()
+    }
+
+    /// Insert or update a member of a set
+    async fn upsert_member(&mut self, set_key: &str, member: &str, value: &str) -> Result<()> {
+        self.multiplex.hset(set_key, member, value).await?;
+        Ok(())
+    }
+
+    /// Remove a member from a set
+    async fn remove_member(&mut self, set_key: &str, member: &str) -> Result<()> {
+        self.multiplex.hdel(set_key, member).await?;
+        Ok(())
+    }
+
+    /// Get all members of a set and their values
+    async fn members(&mut self, set_key: &str) -> Result<Vec<(String, String)>> {
+        Ok(self.multiplex.hgetall(set_key).await?)
+    }
+
+    async fn add_capped_member(
+        &mut self,
+        _set_key: &str,
+        _member: &str,
+        _score: i64,
+        _value: &str,
+        _max_members: usize,
+    ) -> Result<bool> {
+        unimplemented!()
+    }
+
+    async fn capped_members(
+        &mut self,
+        _set_key: &str,
+        _min_score: i64,
+        _max_score: i64,
+        _reverse: bool,
+        _offset: usize,
+        _count: usize,
+    ) -> Result<Vec<(String, String)>> {
+        unimplemented!()
+    }
+
     /// Acknowledge that a message was processed
     async fn ack(
         &mut self,
@@ -147,6 +205,16 @@ impl super::PubSub for RedisConnection {
         unimplemented!()
     }
 
+    async fn get_messages_range(
+        &mut self,
+        _channel: &str,
+        _from_id: &str,
+        _to_id: &str,
+        _preserve_sequence: bool,
+    ) -> Result<Vec<(String, String)>> {
+        unimplemented!()
+    }
+
     async fn last_message(
         &mut self,
         _channel: &str,
@@ -4,7 +4,7 @@ use redis::{
     aio::{AsyncStream, Monitor, MultiplexedConnection, PubSub},
     cmd,
     streams::{StreamId, StreamKey, StreamRangeReply, StreamReadOptions, StreamReadReply},
-    AsyncCommands, Client, Value,
+    AsyncCommands, Client, Script, Value,
 };
 use std::fmt::{self, Debug};
 use std::pin::Pin;
@@ -35,6 +35,100 @@ impl Debug for RedisConnection {
 
 type Message = (String, String);
 
+/// Publishes a message if its sequence number is the next one for the
+/// channel.  The last sequence number is kept in a separate key so that it
+/// survives trimming the stream.  If that key is missing, e.g. for channels
+/// that were published to before it existed, it starts from the id of the
+/// stream's last message.
+///
+/// KEYS: the channel and its sequence number key, which are in the same hash
+/// slot so that the script can run on Redis Cluster
+/// ARGV: the sequence number, the minimum sequence number, and the message
+///
+/// Returns the next sequence number, which is the requested one if the
+/// message was published.
+const PUBLISH_IF_NEXT: &str = r"
+local stored = redis.call('GET', KEYS[2])
+if not stored then
+    stored = '0'
+    local entries = redis.call('XREVRANGE', KEYS[1], '+', '-', 'COUNT', 1)
+    if entries[1] then
+        stored = string.match(entries[1][1], '^(%d+)')
+    end
+end
+local last = math.max(tonumber(stored), tonumber(ARGV[2]))
+local next = last + 1
+if tonumber(ARGV[1]) == next then
+    redis.call('SET', KEYS[2], next)
+    redis.call('XADD', KEYS[1], next .. '-0', next, ARGV[3])
+end
+return next
+";
+
+/// Adds a member to a capped set, a sorted set of members and a hash of
+/// their values, and removes the lowest scored members beyond the cap.
+///
+/// KEYS: the set's scores and values keys, which are in the same hash slot
+/// ARGV: the member, its score, its value, and the maximum number of members
+///
+/// Returns 1 if the member was added, or 0 if it already exists.
+const ADD_CAPPED_MEMBER: &str = r"
+if redis.call('HEXISTS', KEYS[2], ARGV[1]) == 1 then
+    return 0
+end
+redis.call('HSET', KEYS[2], ARGV[1], ARGV[3])
+redis.call('ZADD', KEYS[1], ARGV[2], ARGV[1])
+local overflow = redis.call('ZCARD', KEYS[1]) - tonumber(ARGV[4])
+if overflow > 0 then
+    local dropped = redis.call('ZRANGE', KEYS[1], 0, overflow - 1)
+    redis.call('ZREMRANGEBYRANK', KEYS[1], 0, overflow - 1)
+    redis.call('HDEL', KEYS[2], unpack(dropped))
+end
+return 1
+";
+
+/// Reads a page of a capped set's members within a range of scores.
+///
+/// KEYS: the set's scores and values keys
+/// ARGV: the minimum and maximum scores, 1 to read the highest scores first,
+/// the offset and the count
+///
+/// Returns the members and their values, flattened.
+const CAPPED_MEMBERS: &str = r"
+local members
+if ARGV[3] == '1' then
+    members = redis.call('ZREVRANGEBYSCORE', KEYS[1], ARGV[2], ARGV[1], 'LIMIT', ARGV[4], ARGV[5])
+else
+    members = redis.call('ZRANGEBYSCORE', KEYS[1], ARGV[1], ARGV[2], 'LIMIT', ARGV[4], ARGV[5])
+end
+local result = {}
+if #members == 0 then
+    return result
+end
+local values = redis.call('HMGET', KEYS[2], unpack(members))
+for i, member in ipairs(members) do
+    if values[i] then
+        result[#result + 1] = member
+        result[#result + 1] = values[i]
+    end
+end
+return result
+";
+
+// the set key is the hash tag of both of a capped set's keys
+fn capped_set_keys(set_key: &str) -> (String, String) {
+    (
+        format!("{{{set_key}}}:scores"),
+        format!("{{{set_key}}}:values"),
+    )
+}
+
+// the channel is the key's hash tag, so both keys are in the channel's hash
+// slot
+fn sequence_num_key(channel: &str) -> String {
+    format!("{{{channel}}}:sequence_num")
+}
+
 fn client(config: Config) -> Result<Client> {
     if let Config::RedisStreams(RedisStreamsConfig {
         host,
@@ -198,6 +292,111 @@ impl super::PubSub for RedisConnection {
         Ok(())
     }
 
+    /// Publish a message to a channel if `sequence_num` is the channel's next
+    /// sequence number, which is at least `min_sequence_num + 1`.  This lets
+    /// several publishers share a channel without reusing sequence numbers.
+    ///
+    /// Returns the next sequence number.  The message was published if it is
+    /// `sequence_num`; otherwise the caller should retry with the returned
+    /// sequence number.
+    async fn publish_if_next(
+        &mut self,
+        channel: &str,
+        sequence_num: u64,
+        min_sequence_num: u64,
+        value: &str,
+        active_channel: Option<&str>,
+    ) -> Result<u64> {
+        let next = Script::new(PUBLISH_IF_NEXT)
+            .key(channel)
+            .key(sequence_num_key(channel))
+            .arg(sequence_num)
+            .arg(min_sequence_num)
+            .arg(value)
+            .invoke_async::<_, u64>(&mut self.multiplex)
+            .await?;
+
+        // add the channel to the active channels set
+        if next == sequence_num {
+            if let Some(active_channel) = active_channel {
+                self.upsert_active_channel(active_channel, channel).await?
+            }
+        }
+
+        Ok(next)
+    }
+
+    /// Insert or update a member of a set
+    async fn upsert_member(&mut self, set_key: &str, member: &str, value: &str) -> Result<()> {
+        self.multiplex.hset(set_key, member, value).await?;
+        Ok(())
+    }
+
+    /// Remove a member from a set
+    async fn remove_member(&mut self, set_key: &str, member: &str) -> Result<()> {
+        self.multiplex.hdel(set_key, member).await?;
+        Ok(())
+    }
+
+    /// Get all members of a set and their values
+    async fn members(&mut self, set_key: &str) -> Result<Vec<(String, String)>> {
+        Ok(self.multiplex.hgetall(set_key).await?)
+    }
+
+    /// Add a member to a capped set, removing the lowest scored members
+    /// beyond `max_members`.  Returns false if the member already exists.
+    async fn add_capped_member(
+        &mut self,
+        set_key: &str,
+        member: &str,
+        score: i64,
+        value: &str,
+        max_members: usize,
+    ) -> Result<bool> {
+        let (scores, values) = capped_set_keys(set_key);
+        let added = Script::new(ADD_CAPPED_MEMBER)
+            .key(scores)
+            .key(values)
+            .arg(member)
+            .arg(score)
+            .arg(value)
+            .arg(max_members)
+            .invoke_async::<_, bool>(&mut self.multiplex)
+            .await?;
+
+        Ok(added)
+    }
+
+    /// Get up to `count` members of a capped set with scores between
+    /// `min_score` and `max_score`, after skipping `offset` of them.  Members
+    /// are in order of score, highest first if `reverse`.
+    async fn capped_members(
+        &mut self,
+        set_key: &str,
+        min_score: i64,
+        max_score: i64,
+        reverse: bool,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<(String, String)>> {
+        let (scores, values) = capped_set_keys(set_key);
+        let flattened = Script::new(CAPPED_MEMBERS)
+            .key(scores)
+            .key(values)
+            .arg(min_score)
+            .arg(max_score)
+            .arg(u8::from(reverse))
+            .arg(offset)
+            .arg(count)
+            .invoke_async::<_, Vec<String>>(&mut self.multiplex)
+            .await?;
+
+        Ok(flattened
+            .chunks_exact(2)
+            .map(|pair| (pair[0].to_owned(), pair[1].to_owned()))
+            .collect())
+    }
+
     /// Acknowledge that a message was processed
     async fn ack(
         &mut self,
@@ -306,6 +505,19 @@ impl super::PubSub for RedisConnection {
         Ok(stream_ids_to_messages(messages.ids, preserve_sequence))
     }
 
+    /// Get messages from a channel between two ids, inclusive
+    async fn get_messages_range(
+        &mut self,
+        channel: &str,
+        from_id: &str,
+        to_id: &str,
+        preserve_sequence: bool,
+    ) -> Result<Vec<Message>> {
+        let messages: StreamRangeReply = self.multiplex.xrange(channel, from_id, to_id).await?;
+
+        Ok(stream_ids_to_messages(messages.ids, preserve_sequence))
+    }
+
     /// Get the last message in a channel
     async fn last_message(&mut self, channel: &str, preserve_sequence: bool) -> Result<Message> {
         let message: StreamRangeReply =
@@ -524,4 +736,152 @@ pub mod tests {
         let results = connection.active_channels(&active_channels).await.unwrap();
         assert_eq!(results, vec![channels[1].clone()]);
     }
+
+    #[tokio::test]
+    async fn stream_publish_if_next() {
+        let (config, channel) = setup();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        // the first sequence number is after the minimum
+        let next = connection
+            .publish_if_next(&channel, 1, 5, "test 1", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 6);
+
+        let next = connection
+            .publish_if_next(&channel, 6, 5, "test 1", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 6);
+
+        // a sequence number can only be used once
+        let next = connection
+            .publish_if_next(&channel, 6, 5, "test 2", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 7);
+
+        let next = connection
+            .publish_if_next(&channel, 7, 5, "test 2", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 7);
+
+        let results = connection
+            .get_messages_from(&channel, "0", false)
+            .await
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![("6".into(), "test 1".into()), ("7".into(), "test 2".into())]
+        );
+
+        let results = connection
+            .get_messages_range(&channel, "7", "8", false)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![("7".into(), "test 2".into())]);
+    }
+
+    #[tokio::test]
+    async fn stream_publish_if_next_after_existing_messages() {
+        let (config, channel) = setup();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+        assert_eq!(
+            sequence_num_key(&channel),
+            format!("{{{channel}}}:sequence_num")
+        );
+
+        // messages that were published without a sequence number key
+        for key in ["1", "2", "3"] {
+            connection
+                .publish(&channel, key, "test", None)
+                .await
+                .unwrap();
+        }
+
+        let next = connection
+            .publish_if_next(&channel, 1, 0, "test 4", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 4);
+
+        let next = connection
+            .publish_if_next(&channel, 4, 0, "test 4", None)
+            .await
+            .unwrap();
+        assert_eq!(next, 4);
+    }
+
+    #[tokio::test]
+    async fn stream_members() {
+        let (config, _) = setup();
+        let set_key = Uuid::new_v4().to_string();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        connection
+            .upsert_member(&set_key, "user 1", "value 1")
+            .await
+            .unwrap();
+        connection
+            .upsert_member(&set_key, "user 2", "value 2")
+            .await
+            .unwrap();
+        connection
+            .upsert_member(&set_key, "user 1", "value 3")
+            .await
+            .unwrap();
+
+        let mut results = connection.members(&set_key).await.unwrap();
+        results.sort();
+        assert_eq!(
+            results,
+            vec![
+                ("user 1".into(), "value 3".into()),
+                ("user 2".into(), "value 2".into())
+            ]
+        );
+
+        connection.remove_member(&set_key, "user 1").await.unwrap();
+        let results = connection.members(&set_key).await.unwrap();
+        assert_eq!(results, vec![("user 2".into(), "value 2".into())]);
+    }
+
+    #[tokio::test]
+    async fn stream_capped_members() {
+        let (config, _) = setup();
+        let set_key = Uuid::new_v4().to_string();
+        let mut connection = RedisConnection::new(config).await.unwrap();
+
+        for score in 1..=4 {
+            let added = connection
+                .add_capped_member(&set_key, &score.to_string(), score, "value", 3)
+                .await
+                .unwrap();
+            assert!(added);
+        }
+
+        // members are unique
+        let added = connection
+            .add_capped_member(&set_key, "4", 5, "other", 3)
+            .await
+            .unwrap();
+        assert!(!added);
+
+        // the lowest scored member was removed
+        let results = connection
+            .capped_members(&set_key, i64::MIN, i64::MAX, false, 0, 10)
+            .await
+            .unwrap();
+        let members = results.iter().map(|(member, _)| member.as_str());
+        assert_eq!(members.collect::<Vec<_>>(), vec!["2", "3", "4"]);
+        assert_eq!(results[2].1, "value");
+
+        let results = connection
+            .capped_members(&set_key, 2, 4, true, 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![("3".into(), "value".into())]);
+    }
 }
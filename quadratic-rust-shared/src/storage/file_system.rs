@@ -0,0 +1,144 @@
+use std::path::{Component, Path, PathBuf};
+
+use bytes::Bytes;
+use tokio::fs;
+
+use crate::error::{Result, SharedError, Storage};
+
+#[derive(Debug, Clone)]
+pub struct FileSystemConfig {
+    pub path: String,
+}
+
+/// Files stored in a directory on the local file system.  Keys are paths
+/// relative to the directory.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    pub path: String,
+}
+
+impl FileSystem {
+    pub fn new(config: FileSystemConfig) -> Self {
+        FileSystem { path: config.path }
+    }
+
+    /// The path of a key.  Keys cannot leave the directory.
+    fn file_path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        let is_contained = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+        if key.is_empty() || !is_contained {
+            return Err(SharedError::Storage(Storage::InvalidKey(key.into())));
+        }
+
+        Ok(Path::new(&self.path).join(relative))
+    }
+}
+
+impl super::Storage for FileSystem {
+    async fn read(&self, key: &str) -> Result<Bytes> {
+        let path = self.file_path(key)?;
+        let file = fs::read(path)
+            .await
+            .map_err(|e| SharedError::Storage(Storage::Read(key.into(), e.to_string())))?;
+
+        Ok(Bytes::from(file))
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = self.file_path(key)?;
+        let write =
+            |e: std::io::Error| SharedError::Storage(Storage::Write(key.into(), e.to_string()));
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.map_err(write)?;
+        }
+
+        fs::write(path, data).await.map_err(write)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let list =
+            |e: std::io::Error| SharedError::Storage(Storage::List(prefix.into(), e.to_string()));
+
+        // only the directory that the prefix ends in is searched
+        let (directory, file_prefix) = match prefix.rsplit_once('/') {
+            Some((directory, file_prefix)) => (format!("{directory}/"), file_prefix),
+            None => (String::new(), prefix),
+        };
+        let path = if directory.is_empty() {
+            PathBuf::from(&self.path)
+        } else {
+            self.file_path(&directory)?
+        };
+
+        let mut entries = match fs::read_dir(path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(list(e)),
+        };
+
+        let mut keys = vec![];
+        while let Some(entry) = entries.next_entry().await.map_err(list)? {
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if name.starts_with(file_prefix) && entry.file_type().await.map_err(list)?.is_file() {
+                keys.push(format!("{directory}{name}"));
+            }
+        }
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    // files are only readable by services on the same machine
+    async fn download_url(&self, _key: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn location(&self) -> &str {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage as StorageTrait;
+
+    #[tokio::test]
+    async fn reads_writes_and_lists_files() {
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let storage = FileSystem::new(FileSystemConfig {
+            path: path.to_string_lossy().to_string(),
+        });
+
+        // the directory is created on the first write
+        assert_eq!(storage.list("a-").await.unwrap(), Vec::<String>::new());
+
+        storage.write("a-1.grid", b"one").await.unwrap();
+        storage.write("a-2.grid", b"two").await.unwrap();
+        storage.write("b-1.grid", b"three").await.unwrap();
+        storage.write("nested/a-1.grid", b"four").await.unwrap();
+
+        assert_eq!(storage.read("a-2.grid").await.unwrap(), "two");
+        assert_eq!(storage.read("nested/a-1.grid").await.unwrap(), "four");
+        assert!(storage.read("c-1.grid").await.is_err());
+        assert_eq!(
+            storage.list("a-").await.unwrap(),
+            vec!["a-1.grid".to_string(), "a-2.grid".to_string()]
+        );
+        assert_eq!(
+            storage.list("nested/a").await.unwrap(),
+            vec!["nested/a-1.grid".to_string()]
+        );
+
+        // keys cannot leave the directory
+        assert!(storage.write("../a-1.grid", b"five").await.is_err());
+        assert!(storage.read("/etc/passwd").await.is_err());
+
+        fs::remove_dir_all(path).await.unwrap();
+    }
+}
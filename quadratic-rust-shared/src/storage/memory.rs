@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use bytes::Bytes;
+
+use crate::error::{Result, SharedError, Storage};
+
+/// Files stored in memory, which are lost when the process exits.  Clones
+/// share the same files.
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    files: Arc<RwLock<BTreeMap<String, Bytes>>>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl super::Storage for Memory {
+    async fn read(&self, key: &str) -> Result<Bytes> {
+        self.files
+            .read()
+            .map_err(|e| SharedError::Storage(Storage::Read(key.into(), e.to_string())))?
+            .get(key)
+            .cloned()
+            .ok_or_else(|| SharedError::Storage(Storage::Read(key.into(), "not found".into())))
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.files
+            .write()
+            .map_err(|e| SharedError::Storage(Storage::Write(key.into(), e.to_string())))?
+            .insert(key.into(), Bytes::copy_from_slice(data));
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let files = self
+            .files
+            .read()
+            .map_err(|e| SharedError::Storage(Storage::List(prefix.into(), e.to_string())))?;
+        let keys = files
+            .range(prefix.to_owned()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.to_owned())
+            .collect();
+
+        Ok(keys)
+    }
+
+    // files are only readable in process
+    async fn download_url(&self, _key: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn location(&self) -> &str {
+        "memory"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage as StorageTrait;
+
+    #[tokio::test]
+    async fn reads_writes_and_lists_files() {
+        let storage = Memory::new();
+        storage.write("a-1.grid", b"one").await.unwrap();
+        storage.write("a-2.grid", b"two").await.unwrap();
+        storage.write("b-1.grid", b"three").await.unwrap();
+
+        // clones share files
+        let clone = storage.clone();
+        clone.write("a-2.grid", b"four").await.unwrap();
+
+        assert_eq!(storage.read("a-2.grid").await.unwrap(), "four");
+        assert!(storage.read("c-1.grid").await.is_err());
+        assert_eq!(
+            storage.list("a-").await.unwrap(),
+            vec!["a-1.grid".to_string(), "a-2.grid".to_string()]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Storage
+//!
+//! A place to read and write files by key.  Files are stored in S3, in a
+//! directory on the local file system, or in memory (for tests).  The backend
+//! is selected with a Config.
+
+pub mod file_system;
+pub mod memory;
+pub mod s3;
+
+use bytes::Bytes;
+use futures_util::Future;
+use serde::Deserialize;
+use strum_macros::Display;
+
+use self::file_system::{FileSystem, FileSystemConfig};
+use self::memory::Memory;
+use self::s3::{S3Config, S3};
+use crate::error::Result;
+
+/// The backends that are selectable in a service's environment.
+#[derive(Display, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum StorageType {
+    #[default]
+    S3,
+    FileSystem,
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub enum Config {
+    S3(S3Config),
+    FileSystem(FileSystemConfig),
+    Memory,
+}
+
+pub trait Storage {
+    /// Read the file at a key.
+    fn read(&self, key: &str) -> impl Future<Output = Result<Bytes>> + Send;
+
+    /// Write a file to a key, replacing the file if it exists.
+    fn write(&self, key: &str, data: &[u8]) -> impl Future<Output = Result<()>> + Send;
+
+    /// List the keys that start with `prefix`.
+    fn list(&self, prefix: &str) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// A url that clients can download the file at a key from, if the
+    /// backend serves files directly.
+    fn download_url(&self, key: &str) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Where files are stored: the bucket, directory, or "memory".
+    fn location(&self) -> &str;
+}
+
+#[derive(Debug, Clone)]
+pub enum StorageContainer {
+    S3(S3),
+    FileSystem(FileSystem),
+    Memory(Memory),
+}
+
+impl StorageContainer {
+    pub async fn new(config: Config) -> Self {
+        match config {
+            Config::S3(config) => StorageContainer::S3(S3::new(config).await),
+            Config::FileSystem(config) => StorageContainer::FileSystem(FileSystem::new(config)),
+            Config::Memory => StorageContainer::Memory(Memory::new()),
+        }
+    }
+}
+
+impl Storage for StorageContainer {
+    async fn read(&self, key: &str) -> Result<Bytes> {
+        match self {
+            StorageContainer::S3(storage) => storage.read(key).await,
+            StorageContainer::FileSystem(storage) => storage.read(key).await,
+            StorageContainer::Memory(storage) => storage.read(key).await,
+        }
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        match self {
+            StorageContainer::S3(storage) => storage.write(key, data).await,
+            StorageContainer::FileSystem(storage) => storage.write(key, data).await,
+            StorageContainer::Memory(storage) => storage.write(key, data).await,
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        match self {
+            StorageContainer::S3(storage) => storage.list(prefix).await,
+            StorageContainer::FileSystem(storage) => storage.list(prefix).await,
+            StorageContainer::Memory(storage) => storage.list(prefix).await,
+        }
+    }
+
+    async fn download_url(&self, key: &str) -> Result<Option<String>> {
+        match self {
+            StorageContainer::S3(storage) => storage.download_url(key).await,
+            StorageContainer::FileSystem(storage) => storage.download_url(key).await,
+            StorageContainer::Memory(storage) => storage.download_url(key).await,
+        }
+    }
+
+    fn location(&self) -> &str {
+        match self {
+            StorageContainer::S3(storage) => storage.location(),
+            StorageContainer::FileSystem(storage) => storage.location(),
+            StorageContainer::Memory(storage) => storage.location(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_storage_types() {
+        let parse = |value: &str| serde_json::from_str::<StorageType>(&format!("\"{value}\""));
+
+        assert_eq!(parse("s3").unwrap(), StorageType::S3);
+        assert_eq!(parse("file-system").unwrap(), StorageType::FileSystem);
+        assert_eq!(parse("memory").unwrap(), StorageType::Memory);
+        assert!(parse("ftp").is_err());
+        assert_eq!(StorageType::FileSystem.to_string(), "file-system");
+    }
+}
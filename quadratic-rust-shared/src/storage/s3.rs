@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::aws::{
+    client,
+    s3::{download_object, list_objects, presigned_download_url, upload_object},
+    Client,
+};
+use crate::error::{Aws, Result, SharedError};
+
+/// How long download urls are valid for.
+const DOWNLOAD_URL_EXPIRATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub bucket: String,
+    pub provider_name: &'static str,
+
+    // use the local S3 emulator
+    pub is_local: bool,
+}
+
+/// Files stored in an S3 bucket.
+#[derive(Debug, Clone)]
+pub struct S3 {
+    pub client: Client,
+    pub bucket: String,
+}
+
+impl S3 {
+    pub async fn new(config: S3Config) -> Self {
+        let S3Config {
+            region,
+            access_key_id,
+            secret_access_key,
+            bucket,
+            provider_name,
+            is_local,
+        } = config;
+
+        S3 {
+            client: client(
+                &access_key_id,
+                &secret_access_key,
+                &region,
+                provider_name,
+                is_local,
+            )
+            .await,
+            bucket,
+        }
+    }
+}
+
+impl super::Storage for S3 {
+    async fn read(&self, key: &str) -> Result<Bytes> {
+        let file = download_object(&self.client, &self.bucket, key).await?;
+        let body = file.body.collect().await.map_err(|error| {
+            SharedError::Aws(Aws::S3(format!(
+                "Error reading file {key} from bucket {}: {:?}.",
+                self.bucket, error
+            )))
+        })?;
+
+        Ok(body.into_bytes())
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        upload_object(&self.client, &self.bucket, key, data).await?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        list_objects(&self.client, &self.bucket, prefix).await
+    }
+
+    async fn download_url(&self, key: &str) -> Result<Option<String>> {
+        let url = presigned_download_url(&self.client, &self.bucket, key, DOWNLOAD_URL_EXPIRATION)
+            .await?;
+
+        Ok(Some(url))
+    }
+
+    fn location(&self) -> &str {
+        &self.bucket
+    }
+}
@@ -124,6 +124,8 @@ export const ApiSchemas = {
   '/v0/files.POST.request': z.object({
     name: FileSchema.shape.name,
     contents: z.string(),
+    // binary files, like checkpoints, are sent as base64
+    contentsEncoding: z.enum(['utf8', 'base64']).optional(),
     version: z.string(),
     teamUuid: TeamSchema.shape.uuid,
     isPrivate: z.boolean().optional(),